@@ -0,0 +1,371 @@
+//! Agent definition files
+//!
+//! An agent can be shared as a YAML or JSON file holding one
+//! [`AgentDefinition`]. Files are imported through a command or by dropping
+//! them into the `agents/` directory next to the database, which is scanned
+//! at startup; any agent can be exported back to a file. Definitions are
+//! checked strictly: unknown fields, a missing `version`, or values an agent
+//! can't have are rejected with the reason.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+use crate::agent_lint::{self, Diagnostic, SaveError};
+use crate::agents::{self, Agent, AgentUpdate, NewAgent};
+use crate::tool_policy::ToolPermission;
+
+/// Definition format written by this version of the app
+pub const DEFINITION_VERSION: u32 = 1;
+
+/// An agent as stored in a definition file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentDefinition {
+    /// Format version; must be 1
+    pub version: u32,
+    /// Lowercase letters, digits, and dashes; derived from `name` when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Local tools the agent may use; reading and writing project files when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Vec<ToolPermission>>,
+    pub system_prompt: String,
+}
+
+/// Outcome of importing one file from the agents directory
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentImport {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<Agent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Problems found in the definition; warnings only if it was imported
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionFormat {
+    Json,
+    Yaml,
+}
+
+impl DefinitionFormat {
+    /// Format for a file name: `.json`, `.yaml`, or `.yml`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(DefinitionFormat::Json),
+            "yaml" | "yml" => Some(DefinitionFormat::Yaml),
+            _ => None,
+        }
+    }
+}
+
+impl AgentDefinition {
+    /// Parse and check a definition
+    pub fn parse(contents: &str, format: DefinitionFormat) -> Result<Self, String> {
+        let definition: AgentDefinition = match format {
+            DefinitionFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string())?,
+            DefinitionFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| e.to_string())?,
+        };
+        definition.check()?;
+        Ok(definition)
+    }
+
+    /// Serialize for a definition file
+    pub fn render(&self, format: DefinitionFormat) -> Result<String, String> {
+        match format {
+            DefinitionFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+            DefinitionFormat::Yaml => serde_yaml::to_string(self).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.version != DEFINITION_VERSION {
+            return Err(format!("Unsupported definition version {} (expected {})", self.version, DEFINITION_VERSION));
+        }
+        if let Some(id) = &self.id {
+            if !valid_id(id) {
+                return Err(format!("Invalid agent id {:?}: use lowercase letters, digits, and dashes", id));
+            }
+        }
+        if slug(&self.name).is_empty() {
+            return Err("Agent name is required".to_string());
+        }
+        Ok(())
+    }
+
+    /// ID the agent is imported under
+    pub fn agent_id(&self) -> String {
+        self.id.clone().unwrap_or_else(|| slug(&self.name))
+    }
+}
+
+impl From<&Agent> for AgentDefinition {
+    fn from(agent: &Agent) -> Self {
+        Self {
+            version: DEFINITION_VERSION,
+            id: valid_id(&agent.id).then(|| agent.id.clone()),
+            name: agent.name.clone(),
+            description: agent.description.clone(),
+            category: Some(agent.category.clone()),
+            capabilities: agent.capabilities.clone(),
+            model: Some(agent.model.clone()),
+            icon: Some(agent.icon.clone()),
+            temperature: Some(agent.temperature),
+            permissions: Some(agent.permissions.clone()),
+            system_prompt: agent.system_prompt.clone(),
+        }
+    }
+}
+
+fn valid_id(id: &str) -> bool {
+    (1..=64).contains(&id.len()) && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// `Frontend Architect!` → `frontend-architect`
+pub(crate) fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(64);
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Save a definition as a custom agent, replacing an earlier import of it
+///
+/// Fails if its ID belongs to a built-in agent.
+pub async fn import(pool: &SqlitePool, definition: AgentDefinition) -> Result<Agent, SaveError> {
+    let id = definition.agent_id();
+    let existing = agents::get(pool, &id)
+        .await
+        .map_err(|e| SaveError::Failed(format!("Database error: {}", e)))?;
+
+    match existing {
+        Some(agent) if agent.builtin => Err(SaveError::Invalid(vec![agent_lint::duplicate_id(&id, true)])),
+        Some(_) => {
+            let update = AgentUpdate {
+                name: Some(definition.name),
+                description: Some(definition.description),
+                category: definition.category,
+                capabilities: Some(definition.capabilities),
+                model: definition.model,
+                icon: definition.icon,
+                system_prompt: Some(definition.system_prompt),
+                temperature: definition.temperature,
+                permissions: definition.permissions,
+            };
+            agents::update(pool, &id, update)
+                .await?
+                .ok_or_else(|| SaveError::Failed(format!("Agent not found: {}", id)))
+        }
+        None => {
+            let agent = NewAgent {
+                name: definition.name,
+                description: definition.description,
+                category: definition.category,
+                capabilities: definition.capabilities,
+                model: definition.model,
+                icon: definition.icon,
+                system_prompt: definition.system_prompt,
+                temperature: definition.temperature,
+                permissions: definition.permissions,
+            };
+            agents::create_with_id(pool, id, agent).await
+        }
+    }
+}
+
+/// Read and import one definition file
+pub async fn import_file(pool: &SqlitePool, path: &Path) -> Result<Agent, SaveError> {
+    let definition = read_file(path).await?;
+    import(pool, definition).await
+}
+
+async fn read_file(path: &Path) -> Result<AgentDefinition, SaveError> {
+    let format = DefinitionFormat::from_path(path)
+        .ok_or_else(|| SaveError::Failed("Agent files must be .json, .yaml, or .yml".to_string()))?;
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| SaveError::Failed(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    AgentDefinition::parse(&contents, format)
+        .map_err(|e| SaveError::Invalid(vec![Diagnostic::error("definition_invalid", "definition", e)]))
+}
+
+/// Write an agent to a definition file, in the format its extension names
+pub async fn export_file(pool: &SqlitePool, agent_id: &str, path: &Path) -> Result<(), String> {
+    let format = DefinitionFormat::from_path(path)
+        .ok_or_else(|| "Agent files must be .json, .yaml, or .yml".to_string())?;
+    let agent = agents::get(pool, agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Agent not found: {}", agent_id))?;
+
+    let contents = AgentDefinition::from(&agent).render(format)?;
+    tokio::fs::write(path, contents)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// `agents/` next to the database, where dropped-in files are picked up
+pub fn agents_dir() -> PathBuf {
+    let db_path = crate::database::get_db_path();
+    db_path.parent().map(|dir| dir.join("agents")).unwrap_or_else(|| PathBuf::from("agents"))
+}
+
+/// Import every definition file in `dir`, in name order
+///
+/// A missing directory imports nothing; one bad file doesn't stop the rest.
+/// A file whose ID an earlier file in the directory already used is
+/// skipped rather than replacing that agent.
+pub async fn import_dir(pool: &SqlitePool, dir: &Path) -> Vec<AgentImport> {
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return Vec::new();
+    };
+
+    let mut paths = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if DefinitionFormat::from_path(&path).is_some() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut imports = Vec::with_capacity(paths.len());
+    let mut seen: Vec<(String, PathBuf)> = Vec::new();
+    for path in paths {
+        let result = match read_file(&path).await {
+            Ok(definition) => {
+                let id = definition.agent_id();
+                match seen.iter().find(|(seen_id, _)| *seen_id == id) {
+                    Some((_, first)) => {
+                        let message = format!("{} also defines agent {}", first.display(), id);
+                        Err(SaveError::Invalid(vec![Diagnostic::error("duplicate_id", "id", message)]))
+                    }
+                    None => {
+                        seen.push((id, path.clone()));
+                        import(pool, definition).await
+                    }
+                }
+            }
+            Err(e) => Err(e),
+        };
+
+        let import = match result {
+            Ok(agent) => AgentImport {
+                path: path.display().to_string(),
+                diagnostics: agent_lint::lint(&agent),
+                agent: Some(agent),
+                error: None,
+            },
+            Err(e) => AgentImport {
+                path: path.display().to_string(),
+                agent: None,
+                error: Some(e.to_string()),
+                diagnostics: match e {
+                    SaveError::Invalid(diagnostics) => diagnostics,
+                    SaveError::Failed(_) => Vec::new(),
+                },
+            },
+        };
+        imports.push(import);
+    }
+    imports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{NamedTempFile, TempDir};
+
+    const COPYWRITER: &str = r#"
+version: 1
+name: Copywriter
+category: Marketing
+capabilities: [Landing pages, Headlines]
+temperature: 0.9
+system_prompt: |
+  You write punchy marketing copy.
+"#;
+
+    #[test]
+    fn test_definitions_are_checked() {
+        let definition = AgentDefinition::parse(COPYWRITER, DefinitionFormat::Yaml).unwrap();
+        assert_eq!(definition.agent_id(), "copywriter");
+        assert_eq!(definition.capabilities, vec!["Landing pages", "Headlines"]);
+
+        let unknown = COPYWRITER.replace("category:", "colour: red\ncategory:");
+        assert!(AgentDefinition::parse(&unknown, DefinitionFormat::Yaml).unwrap_err().contains("colour"));
+        let future = COPYWRITER.replace("version: 1", "version: 2");
+        assert!(AgentDefinition::parse(&future, DefinitionFormat::Yaml).is_err());
+        let bad_id = COPYWRITER.replace("name:", "id: ../etc\nname:");
+        assert!(AgentDefinition::parse(&bad_id, DefinitionFormat::Yaml).is_err());
+
+        // Exports parse back to the same definition in either format
+        for format in [DefinitionFormat::Json, DefinitionFormat::Yaml] {
+            let exported = definition.render(format).unwrap();
+            assert_eq!(AgentDefinition::parse(&exported, format).unwrap(), definition);
+        }
+        assert_eq!(slug(" Frontend Architect! "), "frontend-architect");
+    }
+
+    #[tokio::test]
+    async fn test_import_dir() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("copywriter.yaml"), COPYWRITER).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not an agent").unwrap();
+        let builtin = r#"{"version": 1, "id": "ui-designer", "name": "Mine", "system_prompt": "Hi"}"#;
+        std::fs::write(dir.path().join("ui.json"), builtin).unwrap();
+
+        let again = COPYWRITER.replace("0.9", "0.1");
+        std::fs::write(dir.path().join("copywriter2.yaml"), again).unwrap();
+
+        let imports = import_dir(&pool, dir.path()).await;
+        assert_eq!(imports.len(), 3);
+        assert_eq!(imports[0].agent.as_ref().unwrap().id, "copywriter");
+        assert!(imports[0].diagnostics.is_empty());
+        assert_eq!(imports[1].diagnostics[0].code, "duplicate_id");
+        assert!(imports[2].error.as_ref().unwrap().contains("built-in"));
+        std::fs::remove_file(dir.path().join("copywriter2.yaml")).unwrap();
+
+        // Importing again replaces the earlier import
+        let edited = COPYWRITER.replace("0.9", "0.2");
+        std::fs::write(dir.path().join("copywriter.yaml"), edited).unwrap();
+        import_dir(&pool, dir.path()).await;
+        let agent = agents::get(&pool, "copywriter").await.unwrap().unwrap();
+        assert!((agent.temperature - 0.2).abs() < 1e-6);
+
+        let exported = dir.path().join("export.json");
+        export_file(&pool, "copywriter", &exported).await.unwrap();
+        let definition = AgentDefinition::parse(&std::fs::read_to_string(&exported).unwrap(), DefinitionFormat::Json).unwrap();
+        assert_eq!(definition.agent_id(), "copywriter");
+        assert_eq!(definition.category.as_deref(), Some("Marketing"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Per-project agent execution history
+//!
+//! Generations that name an agent record it on the assistant message they
+//! save. The history groups a project's replies by the agent that wrote
+//! them, each with the prompt it answered, so code in a project can be
+//! traced back to the agent responsible.
+
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use utoipa::ToSchema;
+
+/// One reply and the prompt it answered
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AgentReply {
+    pub message_id: String,
+    pub prompt: Option<String>,
+    pub content: String,
+    pub created_at: String,
+}
+
+/// Everything one agent contributed to a project
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AgentExecutions {
+    /// `None` for replies generated without an agent
+    pub agent_id: Option<String>,
+    /// The agent's current name; `None` if it has since been deleted
+    pub agent_name: Option<String>,
+    /// Oldest first
+    pub replies: Vec<AgentReply>,
+}
+
+/// A project's replies grouped by agent, in order of each agent's first reply
+pub async fn history(pool: &SqlitePool, project_id: &str) -> Result<Vec<AgentExecutions>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT m.id, m.agent_id, m.content, m.created_at, a.name AS agent_name,
+               (SELECT p.content FROM messages p
+                WHERE p.project_id = m.project_id AND p.role = 'user' AND p.rowid < m.rowid
+                ORDER BY p.rowid DESC LIMIT 1) AS prompt
+        FROM messages m
+        LEFT JOIN agents a ON a.id = m.agent_id
+        WHERE m.project_id = ? AND m.role = 'assistant'
+        ORDER BY m.rowid
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut groups: Vec<AgentExecutions> = Vec::new();
+    for row in rows {
+        let agent_id: Option<String> = row.get("agent_id");
+        let reply = AgentReply {
+            message_id: row.get("id"),
+            prompt: row.get("prompt"),
+            content: row.get("content"),
+            created_at: row.get("created_at"),
+        };
+
+        match groups.iter_mut().find(|group| group.agent_id == agent_id) {
+            Some(group) => group.replies.push(reply),
+            None => groups.push(AgentExecutions {
+                agent_id,
+                agent_name: row.get("agent_name"),
+                replies: vec![reply],
+            }),
+        }
+    }
+
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_history_groups_by_agent() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Site', 'website', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let messages = [
+            ("m1", "user", "Lay out the page", None),
+            ("m2", "assistant", "<main></main>", Some("ui-designer")),
+            ("m3", "user", "Add an API", None),
+            ("m4", "assistant", "GET /items", Some("backend-architect")),
+            ("m5", "user", "Style the header", None),
+            ("m6", "assistant", "header { }", Some("ui-designer")),
+            ("m7", "assistant", "Done", None),
+        ];
+        for (id, role, content, agent_id) in messages {
+            sqlx::query("INSERT INTO messages (id, role, content, project_id, agent_id) VALUES (?, ?, ?, 'p1', ?)")
+                .bind(id)
+                .bind(role)
+                .bind(content)
+                .bind(agent_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let groups = history(&pool, "p1").await.unwrap();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].agent_id.as_deref(), Some("ui-designer"));
+        assert_eq!(groups[0].agent_name.as_deref(), Some("UI/UX Designer"));
+        let prompts: Vec<_> = groups[0].replies.iter().map(|reply| reply.prompt.as_deref()).collect();
+        assert_eq!(prompts, vec![Some("Lay out the page"), Some("Style the header")]);
+        assert_eq!(groups[1].replies[0].content, "GET /items");
+        assert!(groups[2].agent_id.is_none());
+    }
+}
@@ -0,0 +1,257 @@
+//! Agent icon assets
+//!
+//! An agent's `icon` is an emoji, which doesn't render everywhere. Agents can
+//! also have an SVG or PNG icon, stored in `agent_icons`; agents without one
+//! get an avatar generated from the initials of their name, so there is
+//! always an image to show.
+
+use base64::Engine;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use utoipa::ToSchema;
+
+/// Largest icon accepted, after decoding
+pub const MAX_ICON_SIZE: usize = 256 * 1024;
+
+const SVG: &str = "image/svg+xml";
+const PNG: &str = "image/png";
+
+/// Background colors of generated avatars, picked by agent ID
+const AVATAR_COLORS: &[&str] = &[
+    "#4f46e5", "#0891b2", "#059669", "#ca8a04", "#ea580c", "#dc2626", "#db2777", "#7c3aed",
+];
+
+/// An icon ready to serve
+#[derive(Debug, Clone, PartialEq)]
+pub struct Icon {
+    pub media_type: String,
+    pub bytes: Vec<u8>,
+    /// Strong ETag derived from the content hash
+    pub etag: String,
+    /// Generated from the agent's initials rather than uploaded
+    pub generated: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewIcon {
+    /// Base64 SVG or PNG data, or a `data:` URL
+    pub data: String,
+}
+
+/// Decode and check base64 icon data (optionally a `data:` URL)
+///
+/// Returns the media type, read from the bytes, and the bytes.
+pub fn decode_icon(data: &str) -> Result<(&'static str, Vec<u8>), String> {
+    let data = data.trim();
+    let data = match data.strip_prefix("data:") {
+        Some(url) => url.split_once(',').ok_or("Malformed data URL")?.1,
+        None => data,
+    };
+
+    if data.len() / 4 * 3 > MAX_ICON_SIZE + 3 {
+        return Err(format!("Icons must be at most {} KB", MAX_ICON_SIZE / 1024));
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid base64 icon data: {}", e))?;
+    if bytes.len() > MAX_ICON_SIZE {
+        return Err(format!("Icons must be at most {} KB", MAX_ICON_SIZE / 1024));
+    }
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Ok((PNG, bytes));
+    }
+    if is_svg(&bytes) {
+        return Ok((SVG, bytes));
+    }
+    Err("Unsupported icon type; use SVG or PNG".to_string())
+}
+
+fn is_svg(bytes: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return false;
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    (text.starts_with("<svg") || text.starts_with("<?xml")) && text.contains("<svg")
+}
+
+/// Store an uploaded icon for an agent, replacing any earlier one
+pub async fn set(pool: &SqlitePool, agent_id: &str, icon: NewIcon) -> Result<Icon, String> {
+    if crate::agents::get(pool, agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .is_none()
+    {
+        return Err(format!("Agent not found: {}", agent_id));
+    }
+    let (media_type, bytes) = decode_icon(&icon.data)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO agent_icons (agent_id, media_type, data, updated_at)
+        VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(agent_id) DO UPDATE SET
+            media_type = excluded.media_type,
+            data = excluded.data,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(agent_id)
+    .bind(media_type)
+    .bind(&bytes)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save icon: {}", e))?;
+
+    Ok(Icon {
+        media_type: media_type.to_string(),
+        etag: etag(&bytes),
+        bytes,
+        generated: false,
+    })
+}
+
+/// An agent's icon: the uploaded one, or else its initials avatar
+///
+/// `None` if there's no such agent.
+pub async fn load(pool: &SqlitePool, agent_id: &str) -> Result<Option<Icon>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT a.name, i.media_type, i.data
+        FROM agents a
+        LEFT JOIN agent_icons i ON i.agent_id = a.id
+        WHERE a.id = ?
+        "#,
+    )
+    .bind(agent_id)
+    .fetch_optional(pool)
+    .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let icon = match row.get::<Option<String>, _>("media_type") {
+        Some(media_type) => {
+            let bytes: Vec<u8> = row.get("data");
+            Icon {
+                media_type,
+                etag: etag(&bytes),
+                bytes,
+                generated: false,
+            }
+        }
+        None => {
+            let name: String = row.get("name");
+            let bytes = avatar_svg(agent_id, &name).into_bytes();
+            Icon {
+                media_type: SVG.to_string(),
+                etag: etag(&bytes),
+                bytes,
+                generated: true,
+            }
+        }
+    };
+    Ok(Some(icon))
+}
+
+/// Remove an agent's uploaded icon; false if it had none
+pub async fn delete(pool: &SqlitePool, agent_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM agent_icons WHERE agent_id = ?")
+        .bind(agent_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Up to two initials, from the first letters of the name's first two words
+pub fn initials(name: &str) -> String {
+    let initials: String = name
+        .split_whitespace()
+        .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()))
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect();
+
+    if initials.is_empty() {
+        "?".to_string()
+    } else {
+        initials
+    }
+}
+
+/// A rounded square with the name's initials, colored by agent ID
+fn avatar_svg(agent_id: &str, name: &str) -> String {
+    let digest = Sha256::digest(agent_id.as_bytes());
+    let color = AVATAR_COLORS[digest[0] as usize % AVATAR_COLORS.len()];
+
+    format!(
+        concat!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64" viewBox="0 0 64 64">"##,
+            r##"<rect width="64" height="64" rx="12" fill="{}"/>"##,
+            r##"<text x="32" y="32" dy=".35em" text-anchor="middle" font-family="system-ui, sans-serif" "##,
+            r##"font-size="26" font-weight="600" fill="#ffffff">{}</text></svg>"##,
+        ),
+        color,
+        initials(name)
+    )
+}
+
+fn etag(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let hash: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    /// A 1x1 transparent PNG
+    const PIXEL: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+    #[test]
+    fn test_decode_icon() {
+        assert_eq!(decode_icon(PIXEL).unwrap().0, "image/png");
+
+        let svg = base64::engine::general_purpose::STANDARD.encode("<?xml version=\"1.0\"?>\n<svg></svg>");
+        assert_eq!(decode_icon(&format!("data:image/svg+xml;base64,{}", svg)).unwrap().0, "image/svg+xml");
+
+        let gif = base64::engine::general_purpose::STANDARD.encode("GIF89a");
+        assert!(decode_icon(&gif).unwrap_err().starts_with("Unsupported icon type"));
+        assert!(decode_icon("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_initials() {
+        assert_eq!(initials("Backend Architect"), "BA");
+        assert_eq!(initials("UI/UX Designer"), "UD");
+        assert_eq!(initials("copywriter"), "C");
+        assert_eq!(initials("  "), "?");
+    }
+
+    #[tokio::test]
+    async fn test_uploaded_icon_replaces_avatar() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let avatar = load(&pool, "backend-architect").await.unwrap().unwrap();
+        assert!(avatar.generated);
+        assert_eq!(avatar.media_type, "image/svg+xml");
+        assert!(String::from_utf8(avatar.bytes.clone()).unwrap().contains(">BA</text>"));
+        assert!(load(&pool, "missing").await.unwrap().is_none());
+
+        let upload = || NewIcon { data: PIXEL.to_string() };
+        assert!(set(&pool, "missing", upload()).await.is_err());
+        let icon = set(&pool, "backend-architect", upload()).await.unwrap();
+        assert_eq!(load(&pool, "backend-architect").await.unwrap().unwrap(), icon);
+        assert_ne!(icon.etag, avatar.etag);
+
+        assert!(delete(&pool, "backend-architect").await.unwrap());
+        assert!(!delete(&pool, "backend-architect").await.unwrap());
+        assert_eq!(load(&pool, "backend-architect").await.unwrap().unwrap(), avatar);
+    }
+}
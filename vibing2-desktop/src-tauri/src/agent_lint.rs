@@ -0,0 +1,229 @@
+//! Agent definition checks
+//!
+//! Every agent is linted before it's created, edited, or imported. Errors
+//! stop the save and come back as structured diagnostics, one per problem,
+//! rather than the first failure; warnings flag agents that will work but
+//! probably not as intended. The same pass can be run on a draft without
+//! saving it.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+use crate::agents::Agent;
+
+/// A system prompt using more than this share of the model's context
+/// window leaves little room for the project's history
+const PROMPT_WINDOW_SHARE: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The agent can't be saved
+    Error,
+    /// The agent can be saved, but probably isn't what was meant
+    Warning,
+}
+
+/// One problem with an agent
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier, e.g. `system_prompt_too_long`
+    pub code: &'static str,
+    /// Field the problem is in
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, field: &'static str, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, code, field, message: message.into() }
+    }
+
+    pub fn warning(code: &'static str, field: &'static str, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, code, field, message: message.into() }
+    }
+}
+
+/// Why an agent wasn't saved
+#[derive(Debug)]
+pub enum SaveError {
+    /// The agent has errors; carries all its diagnostics, warnings included
+    Invalid(Vec<Diagnostic>),
+    /// Reading or writing failed
+    Failed(String),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Invalid(diagnostics) => {
+                let errors: Vec<&str> = diagnostics
+                    .iter()
+                    .filter(|d| d.severity == Severity::Error)
+                    .map(|d| d.message.as_str())
+                    .collect();
+                write!(f, "{}", errors.join("; "))
+            }
+            SaveError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<SaveError> for String {
+    fn from(error: SaveError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Whether any diagnostic is an error
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+/// Lint an agent, returning `SaveError::Invalid` if it has errors
+pub fn require_valid(agent: &Agent) -> Result<Vec<Diagnostic>, SaveError> {
+    let diagnostics = lint(agent);
+    if has_errors(&diagnostics) {
+        return Err(SaveError::Invalid(diagnostics));
+    }
+    Ok(diagnostics)
+}
+
+/// Every problem with `agent`, errors first
+pub fn lint(agent: &Agent) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if agent.name.trim().is_empty() {
+        diagnostics.push(Diagnostic::error("name_required", "name", "Agent name is required"));
+    }
+    if !(0.0..=2.0).contains(&agent.temperature) {
+        diagnostics.push(Diagnostic::error(
+            "temperature_range",
+            "temperature",
+            "Agent temperature must be between 0 and 2",
+        ));
+    }
+
+    if agent.system_prompt.trim().is_empty() {
+        diagnostics.push(Diagnostic::error("system_prompt_required", "system_prompt", "Agent system prompt is required"));
+    } else {
+        let tokens = crate::usage::estimate_tokens(&agent.system_prompt);
+        let window = crate::llm::context_window(&agent.model);
+        if tokens >= window {
+            diagnostics.push(Diagnostic::error(
+                "system_prompt_too_long",
+                "system_prompt",
+                format!("System prompt is about {} tokens, more than {}'s {}-token context", tokens, agent.model, window),
+            ));
+        } else if tokens > window / PROMPT_WINDOW_SHARE {
+            diagnostics.push(Diagnostic::warning(
+                "system_prompt_long",
+                "system_prompt",
+                format!("System prompt is about {} tokens, over a quarter of {}'s context", tokens, agent.model),
+            ));
+        }
+    }
+
+    // Agent prompts aren't rendered the way templates are
+    for name in crate::templates::variables(&agent.system_prompt) {
+        diagnostics.push(Diagnostic::error(
+            "placeholder",
+            "system_prompt",
+            format!("System prompt contains the placeholder {{{{{}}}}}, which would be sent unfilled", name),
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    for capability in &agent.capabilities {
+        let capability = capability.trim();
+        if capability.is_empty() {
+            diagnostics.push(Diagnostic::error("capability_empty", "capabilities", "Capabilities can't be blank"));
+        } else if !seen.insert(capability.to_lowercase()) {
+            diagnostics.push(Diagnostic::error(
+                "capability_duplicate",
+                "capabilities",
+                format!("Capability {:?} is listed twice", capability),
+            ));
+        }
+    }
+    if agent.capabilities.is_empty() {
+        diagnostics.push(Diagnostic::warning(
+            "capabilities_missing",
+            "capabilities",
+            "Agent lists no capabilities, so capability filters never find it",
+        ));
+    }
+
+    if crate::llm::ProviderKind::infer(&agent.model).is_none() {
+        diagnostics.push(Diagnostic::warning(
+            "model_unknown",
+            "model",
+            format!("{} isn't a model of a known provider unless a local Ollama has it", agent.model),
+        ));
+    }
+
+    diagnostics.sort_by_key(|d| d.severity != Severity::Error);
+    diagnostics
+}
+
+/// The ID is already taken by another agent
+pub fn duplicate_id(id: &str, builtin: bool) -> Diagnostic {
+    let message = if builtin {
+        format!("{} is a built-in agent", id)
+    } else {
+        format!("An agent with id {} already exists", id)
+    };
+    Diagnostic::error("duplicate_id", "id", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent() -> Agent {
+        Agent {
+            id: "copywriter".to_string(),
+            name: "Copywriter".to_string(),
+            description: String::new(),
+            category: "Custom".to_string(),
+            capabilities: vec!["Headlines".to_string()],
+            model: "claude-sonnet-4-5".to_string(),
+            icon: "🤖".to_string(),
+            system_prompt: "You write punchy marketing copy.".to_string(),
+            temperature: 0.5,
+            permissions: crate::tool_policy::default_permissions(),
+            builtin: false,
+            favorite: false,
+        }
+    }
+
+    #[test]
+    fn test_lint() {
+        assert!(lint(&agent()).is_empty());
+
+        let mut broken = agent();
+        broken.name = " ".to_string();
+        broken.capabilities = vec!["Headlines".to_string(), "headlines".to_string(), String::new()];
+        broken.system_prompt = "Write for {{audience}} about {{ topic }} in a {{a-b}} tone, {{audience}}.".to_string();
+        let codes: Vec<_> = lint(&broken).iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            vec!["name_required", "placeholder", "placeholder", "capability_duplicate", "capability_empty"]
+        );
+
+        let mut long = agent();
+        long.model = "mystery".to_string();
+        long.capabilities.clear();
+        long.system_prompt = "x".repeat(4 * 3_000);
+        let diagnostics = lint(&long);
+        assert!(!has_errors(&diagnostics));
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec!["system_prompt_long", "capabilities_missing", "model_unknown"]);
+
+        long.system_prompt = "x".repeat(4 * 9_000);
+        assert_eq!(lint(&long)[0].code, "system_prompt_too_long");
+        assert!(matches!(require_valid(&long), Err(SaveError::Invalid(_))));
+    }
+}
@@ -0,0 +1,330 @@
+//! Bundled agent manifest
+//!
+//! The built-in agents ship as `agents/manifest.yaml`, compiled into the
+//! binary. Every startup syncs it into the `agents` table: new agents are
+//! added, changed ones updated, and ones dropped from the manifest removed.
+//! Built-ins the user has edited are left as they are, and a built-in the
+//! manifest drops after it was edited stays on as a custom agent.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::OnceLock;
+
+use crate::agents::{Agent, AgentTranslation};
+use crate::tool_policy::ToolPermission;
+
+/// Manifest format this version of the app reads
+pub const MANIFEST_VERSION: u32 = 1;
+
+const BUNDLED: &str = include_str!("../agents/manifest.yaml");
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub version: u32,
+    pub agents: Vec<ManifestAgent>,
+}
+
+/// A built-in agent as listed in the manifest
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestAgent {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    pub model: String,
+    pub icon: String,
+    pub temperature: f32,
+    /// Local tools the agent may use; reading and writing project files when omitted
+    #[serde(default = "crate::tool_policy::default_permissions")]
+    pub permissions: Vec<ToolPermission>,
+    pub system_prompt: String,
+    /// Name and description by language tag, shown when the UI is in that
+    /// language
+    #[serde(default)]
+    pub translations: BTreeMap<String, AgentTranslation>,
+}
+
+/// What a sync changed in the agents table, by agent ID
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ManifestSync {
+    pub manifest_version: u32,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+    /// Built-ins the user has edited; the manifest's version isn't applied
+    pub kept_edits: Vec<String>,
+    /// Dropped from the manifest after being edited; now custom agents
+    pub detached: Vec<String>,
+    /// IDs already taken by a custom agent, which is kept
+    pub conflicts: Vec<String>,
+}
+
+impl ManifestSync {
+    /// Whether the sync changed any row
+    pub fn changed(&self) -> bool {
+        !(self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty() && self.detached.is_empty())
+    }
+}
+
+impl Manifest {
+    /// Parse and check a manifest
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut manifest: Manifest = serde_yaml::from_str(contents).map_err(|e| e.to_string())?;
+        if manifest.version != MANIFEST_VERSION {
+            return Err(format!(
+                "Unsupported manifest version {} (expected {})",
+                manifest.version, MANIFEST_VERSION
+            ));
+        }
+
+        let mut ids = HashSet::new();
+        for agent in manifest.agents.iter_mut() {
+            if !ids.insert(agent.id.clone()) {
+                return Err(format!("Agent {} is listed twice", agent.id));
+            }
+            if agent.id.is_empty() || agent.name.trim().is_empty() || agent.system_prompt.trim().is_empty() {
+                return Err(format!("Agent {:?} needs an id, a name, and a system prompt", agent.id));
+            }
+            if !(0.0..=2.0).contains(&agent.temperature) {
+                return Err(format!("Agent {} temperature must be between 0 and 2", agent.id));
+            }
+
+            let mut translations = BTreeMap::new();
+            for (tag, translation) in std::mem::take(&mut agent.translations) {
+                let Some(locale) = crate::locale::normalize(&tag) else {
+                    return Err(format!("Agent {} has a translation for unknown locale {:?}", agent.id, tag));
+                };
+                translations.insert(locale, translation);
+            }
+            agent.translations = translations;
+        }
+        Ok(manifest)
+    }
+}
+
+impl From<&ManifestAgent> for Agent {
+    fn from(agent: &ManifestAgent) -> Self {
+        Agent {
+            id: agent.id.clone(),
+            name: agent.name.clone(),
+            description: agent.description.clone(),
+            category: agent.category.clone(),
+            capabilities: agent.capabilities.clone(),
+            model: agent.model.clone(),
+            icon: agent.icon.clone(),
+            system_prompt: agent.system_prompt.trim_end().to_string(),
+            temperature: agent.temperature,
+            permissions: agent.permissions.clone(),
+            builtin: true,
+            favorite: false,
+        }
+    }
+}
+
+/// The manifest compiled into this build
+pub fn bundled() -> &'static Manifest {
+    static MANIFEST: OnceLock<Manifest> = OnceLock::new();
+    MANIFEST.get_or_init(|| Manifest::parse(BUNDLED).expect("bundled agent manifest is valid"))
+}
+
+/// Bring the table's built-in agents in line with `manifest`
+pub async fn sync(pool: &SqlitePool, manifest: &Manifest) -> Result<ManifestSync, sqlx::Error> {
+    let mut report = ManifestSync {
+        manifest_version: manifest.version,
+        ..Default::default()
+    };
+
+    let mut tx = pool.begin().await?;
+    for entry in &manifest.agents {
+        let agent = Agent::from(entry);
+        let capabilities = serde_json::to_string(&agent.capabilities).unwrap_or_default();
+        let permissions = serde_json::to_string(&agent.permissions).unwrap_or_default();
+        let translations = serde_json::to_string(&entry.translations).unwrap_or_default();
+
+        let existing = sqlx::query("SELECT builtin, customized FROM agents WHERE id = ?")
+            .bind(&agent.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(existing) = existing else {
+            sqlx::query(
+                r#"
+                INSERT INTO agents
+                    (id, name, description, category, capabilities, model, icon, system_prompt, temperature,
+                     tool_permissions, translations, builtin)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 1)
+                "#,
+            )
+            .bind(&agent.id)
+            .bind(&agent.name)
+            .bind(&agent.description)
+            .bind(&agent.category)
+            .bind(&capabilities)
+            .bind(&agent.model)
+            .bind(&agent.icon)
+            .bind(&agent.system_prompt)
+            .bind(agent.temperature)
+            .bind(&permissions)
+            .bind(&translations)
+            .execute(&mut *tx)
+            .await?;
+            report.added.push(agent.id);
+            continue;
+        };
+
+        if !existing.get::<bool, _>("builtin") {
+            report.conflicts.push(agent.id);
+            continue;
+        }
+        if existing.get::<bool, _>("customized") {
+            // Translations still apply while the name and description are the manifest's
+            sqlx::query("UPDATE agents SET translations = ? WHERE id = ? AND name = ? AND description = ?")
+                .bind(&translations)
+                .bind(&agent.id)
+                .bind(&agent.name)
+                .bind(&agent.description)
+                .execute(&mut *tx)
+                .await?;
+            report.kept_edits.push(agent.id);
+            continue;
+        }
+
+        let result = sqlx::query(
+            r#"
+            UPDATE agents
+            SET name = ?, description = ?, category = ?, capabilities = ?, model = ?, icon = ?,
+                system_prompt = ?, temperature = ?, tool_permissions = ?, translations = ?,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+              AND NOT (name = ? AND description = ? AND category = ? AND capabilities = ? AND model = ?
+                       AND icon = ? AND system_prompt = ? AND temperature = ? AND tool_permissions = ?
+                       AND translations = ?)
+            "#,
+        )
+        .bind(&agent.name)
+        .bind(&agent.description)
+        .bind(&agent.category)
+        .bind(&capabilities)
+        .bind(&agent.model)
+        .bind(&agent.icon)
+        .bind(&agent.system_prompt)
+        .bind(agent.temperature)
+        .bind(&permissions)
+        .bind(&translations)
+        .bind(&agent.id)
+        .bind(&agent.name)
+        .bind(&agent.description)
+        .bind(&agent.category)
+        .bind(&capabilities)
+        .bind(&agent.model)
+        .bind(&agent.icon)
+        .bind(&agent.system_prompt)
+        .bind(agent.temperature)
+        .bind(&permissions)
+        .bind(&translations)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            report.updated.push(agent.id);
+        } else {
+            report.unchanged += 1;
+        }
+    }
+
+    // Built-ins the manifest no longer lists
+    let listed: HashSet<&str> = manifest.agents.iter().map(|agent| agent.id.as_str()).collect();
+    let rows = sqlx::query("SELECT id, customized FROM agents WHERE builtin = 1")
+        .fetch_all(&mut *tx)
+        .await?;
+    for row in rows {
+        let id: String = row.get("id");
+        if listed.contains(id.as_str()) {
+            continue;
+        }
+        if row.get::<bool, _>("customized") {
+            sqlx::query("UPDATE agents SET builtin = 0, customized = 0 WHERE id = ?")
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+            report.detached.push(id);
+        } else {
+            sqlx::query("DELETE FROM agents WHERE id = ?")
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+            report.removed.push(id);
+        }
+    }
+    tx.commit().await?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{self, AgentUpdate};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_bundled_manifest_is_valid() {
+        let manifest = Manifest::parse(BUNDLED).unwrap();
+        assert!(manifest.agents.len() >= 5);
+        for agent in &manifest.agents {
+            assert!(
+                crate::llm::ProviderKind::infer(&agent.model).is_some(),
+                "{} prefers unknown model {}",
+                agent.id,
+                agent.model
+            );
+        }
+
+        let twice = "version: 1\nagents:\n  - {id: a, name: A, description: '', category: X, model: m, icon: i, temperature: 0.1, system_prompt: Hi}\n";
+        let twice = format!("{}{}", twice, twice.trim_start_matches("version: 1\nagents:\n"));
+        assert!(Manifest::parse(&twice).unwrap_err().contains("twice"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_keeps_edits() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        // The migrations already synced the bundled manifest
+        let report = sync(&pool, bundled()).await.unwrap();
+        assert!(!report.changed());
+        assert_eq!(report.unchanged, bundled().agents.len());
+
+        let edit = AgentUpdate { temperature: Some(0.9), ..Default::default() };
+        agents::update(&pool, "backend-architect", edit.clone()).await.unwrap();
+        agents::update(&pool, "devops-engineer", edit).await.unwrap();
+
+        // A newer manifest changes one agent and drops two
+        let mut manifest = bundled().clone();
+        manifest.agents.retain(|agent| agent.id != "devops-engineer" && agent.id != "ui-designer");
+        for agent in manifest.agents.iter_mut() {
+            if agent.id == "frontend-architect" || agent.id == "backend-architect" {
+                agent.description = "Updated".to_string();
+            }
+        }
+
+        let report = sync(&pool, &manifest).await.unwrap();
+        assert_eq!(report.updated, vec!["frontend-architect"]);
+        assert_eq!(report.kept_edits, vec!["backend-architect"]);
+        assert_eq!(report.removed, vec!["ui-designer"]);
+        assert_eq!(report.detached, vec!["devops-engineer"]);
+
+        let backend = agents::get(&pool, "backend-architect").await.unwrap().unwrap();
+        assert!((backend.temperature - 0.9).abs() < 1e-6);
+        assert_ne!(backend.description, "Updated");
+        assert!(agents::get(&pool, "ui-designer").await.unwrap().is_none());
+        assert!(!agents::get(&pool, "devops-engineer").await.unwrap().unwrap().builtin);
+    }
+}
@@ -0,0 +1,405 @@
+//! Remote agent registry
+//!
+//! When a registry URL and its Ed25519 public key are set, the app fetches
+//! the registry's agent index every few hours. The index is a signed
+//! envelope: `payload` is the base64 of a JSON [`RegistryIndex`] and
+//! `signature` the base64 signature over those bytes. Nothing from an index
+//! that fails verification is used.
+//!
+//! New and changed definitions are staged for review rather than installed;
+//! approving one imports it like a definition file. A failed fetch, e.g.
+//! while offline, is recorded and retried on the next check, and staged
+//! definitions stay reviewable in the meantime.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use std::time::Duration;
+
+use crate::agent_files::{self, AgentDefinition};
+use crate::agents::Agent;
+
+/// Setting holding the registry index URL; the registry is off when empty
+pub const URL_SETTING: &str = "agent_registry_url";
+/// Setting holding the registry's base64 Ed25519 public key
+pub const KEY_SETTING: &str = "agent_registry_key";
+
+const CHECKED_AT_SETTING: &str = "agent_registry_checked_at";
+const ERROR_SETTING: &str = "agent_registry_error";
+
+/// How often the registry is checked in the background
+const SYNC_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Index format this version of the app reads
+pub const INDEX_VERSION: u32 = 1;
+
+/// What the registry serves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedIndex {
+    /// Base64 of the JSON index
+    pub payload: String,
+    /// Base64 Ed25519 signature over the decoded payload
+    pub signature: String,
+}
+
+/// The agents a registry publishes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    pub version: u32,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    pub agents: Vec<AgentDefinition>,
+}
+
+/// A definition from the registry and where it is in review
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistryAgent {
+    pub agent_id: String,
+    /// `pending`, `approved`, or `rejected`
+    pub status: String,
+    pub definition: AgentDefinition,
+    pub fetched_at: String,
+}
+
+/// Result of checking the registry
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RegistrySync {
+    pub published_at: Option<String>,
+    /// Definitions in the index
+    pub listed: usize,
+    /// New or changed definitions now waiting for review
+    pub staged: Vec<String>,
+    /// Definitions that couldn't be used, with the reason
+    pub invalid: Vec<String>,
+}
+
+/// Registry configuration and the definitions awaiting review
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistryStatus {
+    pub configured: bool,
+    pub url: Option<String>,
+    /// Last check that reached the registry (RFC 3339)
+    pub checked_at: Option<String>,
+    /// Why the last check failed; cleared by the next success
+    pub last_error: Option<String>,
+    pub pending: Vec<RegistryAgent>,
+}
+
+/// Verify an envelope against the registry's key and decode its index
+pub fn verify(body: &str, public_key: &str) -> Result<RegistryIndex, String> {
+    let key: [u8; 32] = STANDARD
+        .decode(public_key.trim())
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| "Registry key must be a base64 Ed25519 public key".to_string())?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|_| "Registry key is not a valid Ed25519 key".to_string())?;
+
+    let envelope: SignedIndex = serde_json::from_str(body).map_err(|e| format!("Malformed registry response: {}", e))?;
+    let payload = STANDARD
+        .decode(envelope.payload.trim())
+        .map_err(|_| "Registry payload is not base64".to_string())?;
+    let signature = STANDARD
+        .decode(envelope.signature.trim())
+        .ok()
+        .and_then(|signature| Signature::from_slice(&signature).ok())
+        .ok_or_else(|| "Registry signature is malformed".to_string())?;
+    key.verify_strict(&payload, &signature)
+        .map_err(|_| "Registry signature does not match its key".to_string())?;
+
+    let index: RegistryIndex =
+        serde_json::from_slice(&payload).map_err(|e| format!("Malformed registry index: {}", e))?;
+    if index.version != INDEX_VERSION {
+        return Err(format!(
+            "Unsupported registry index version {} (expected {})",
+            index.version, INDEX_VERSION
+        ));
+    }
+    Ok(index)
+}
+
+/// Stage the index's new and changed definitions for review
+///
+/// A definition already staged, approved, or rejected in the same form is
+/// left as it is, so rejecting one keeps it away until the registry changes it.
+pub async fn stage(pool: &SqlitePool, index: &RegistryIndex) -> Result<RegistrySync, sqlx::Error> {
+    let mut report = RegistrySync {
+        published_at: index.published_at.clone(),
+        listed: index.agents.len(),
+        ..Default::default()
+    };
+
+    for definition in &index.agents {
+        let id = definition.agent_id();
+        if definition.id.is_none() {
+            report.invalid.push(format!("{}: registry agents need an id", definition.name));
+            continue;
+        }
+        // Stored as JSON, and checked the way a definition file would be
+        let json = definition
+            .render(agent_files::DefinitionFormat::Json)
+            .and_then(|json| AgentDefinition::parse(&json, agent_files::DefinitionFormat::Json).map(|_| json));
+        let json = match json {
+            Ok(json) => json,
+            Err(e) => {
+                report.invalid.push(format!("{}: {}", id, e));
+                continue;
+            }
+        };
+        let digest = hex(&Sha256::digest(json.as_bytes()));
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO registry_agents (agent_id, definition, digest, status, fetched_at)
+            VALUES (?, ?, ?, 'pending', ?)
+            ON CONFLICT(agent_id) DO UPDATE
+                SET definition = excluded.definition, digest = excluded.digest,
+                    status = 'pending', fetched_at = excluded.fetched_at
+                WHERE registry_agents.digest != excluded.digest
+            "#,
+        )
+        .bind(&id)
+        .bind(&json)
+        .bind(&digest)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            report.staged.push(id);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Fetch, verify, and stage the configured registry's index
+///
+/// The outcome is recorded for [`status`]; `Ok(None)` when no registry is
+/// configured.
+pub async fn sync(pool: &SqlitePool) -> Result<Option<RegistrySync>, String> {
+    let (Some(url), Some(key)) = (setting(pool, URL_SETTING).await, setting(pool, KEY_SETTING).await) else {
+        return Ok(None);
+    };
+
+    let result = async {
+        let body = fetch(&url).await?;
+        let index = verify(&body, &key)?;
+        stage(pool, &index).await.map_err(|e| format!("Database error: {}", e))
+    }
+    .await;
+
+    match &result {
+        Ok(_) => {
+            save_setting(pool, CHECKED_AT_SETTING, &chrono::Utc::now().to_rfc3339()).await;
+            save_setting(pool, ERROR_SETTING, "").await;
+        }
+        Err(e) => save_setting(pool, ERROR_SETTING, e).await,
+    }
+    result.map(Some)
+}
+
+async fn fetch(url: &str) -> Result<String, String> {
+    let response = crate::proxy::client()
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Registry unreachable: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Registry returned {}", response.status()));
+    }
+    response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read registry response: {}", e))
+}
+
+/// Configuration, last check, and definitions awaiting review
+pub async fn status(pool: &SqlitePool) -> Result<RegistryStatus, sqlx::Error> {
+    let url = setting(pool, URL_SETTING).await;
+    let configured = url.is_some() && setting(pool, KEY_SETTING).await.is_some();
+
+    let rows = sqlx::query(
+        "SELECT agent_id, definition, status, fetched_at FROM registry_agents WHERE status = 'pending' ORDER BY agent_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let pending = rows
+        .iter()
+        .filter_map(|row| {
+            let definition: String = row.get("definition");
+            Some(RegistryAgent {
+                agent_id: row.get("agent_id"),
+                status: row.get("status"),
+                definition: serde_json::from_str(&definition).ok()?,
+                fetched_at: row.get("fetched_at"),
+            })
+        })
+        .collect();
+
+    Ok(RegistryStatus {
+        configured,
+        url,
+        checked_at: setting(pool, CHECKED_AT_SETTING).await,
+        last_error: setting(pool, ERROR_SETTING).await,
+        pending,
+    })
+}
+
+/// Install a staged definition as a custom agent
+pub async fn approve(pool: &SqlitePool, agent_id: &str) -> Result<Agent, String> {
+    let definition: Option<String> =
+        sqlx::query_scalar("SELECT definition FROM registry_agents WHERE agent_id = ? AND status = 'pending'")
+            .bind(agent_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    let definition = definition.ok_or_else(|| format!("No registry update pending for {}", agent_id))?;
+
+    let definition = AgentDefinition::parse(&definition, agent_files::DefinitionFormat::Json)?;
+    let agent = agent_files::import(pool, definition).await?;
+
+    sqlx::query("UPDATE registry_agents SET status = 'approved' WHERE agent_id = ?")
+        .bind(agent_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(agent)
+}
+
+/// Turn down a staged definition; `false` if none was pending
+pub async fn reject(pool: &SqlitePool, agent_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE registry_agents SET status = 'rejected' WHERE agent_id = ? AND status = 'pending'")
+        .bind(agent_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Check the registry periodically in the background
+pub fn spawn_sync_task() {
+    tauri::async_runtime::spawn(async {
+        let mut interval = tokio::time::interval(SYNC_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            crate::power::wait_active().await;
+
+            let pool = match crate::database::get_pool().await {
+                Ok(pool) => pool,
+                Err(e) => {
+                    eprintln!("Agent registry check skipped: {}", e);
+                    continue;
+                }
+            };
+
+            match sync(pool.as_ref()).await {
+                Ok(Some(report)) if !report.staged.is_empty() => {
+                    println!("📥 {} agent updates from the registry await review", report.staged.len())
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Agent registry check failed: {}", e),
+            }
+        }
+    });
+}
+
+async fn setting(pool: &SqlitePool, key: &str) -> Option<String> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+async fn save_setting(pool: &SqlitePool, key: &str, value: &str) {
+    let _ = sqlx::query(
+        r#"
+        INSERT INTO settings (id, key, value, updated_at)
+        VALUES (?, ?, ?, datetime('now'))
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(format!("setting-{}", key))
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await;
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use tempfile::NamedTempFile;
+
+    fn signed(key: &SigningKey, index: &str) -> String {
+        serde_json::to_string(&SignedIndex {
+            payload: STANDARD.encode(index),
+            signature: STANDARD.encode(key.sign(index.as_bytes()).to_bytes()),
+        })
+        .unwrap()
+    }
+
+    const INDEX: &str = r#"{"version": 1, "agents": [
+        {"version": 1, "id": "copywriter", "name": "Copywriter", "system_prompt": "You write punchy copy."}
+    ]}"#;
+
+    #[test]
+    fn test_verify_rejects_tampering() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let public = STANDARD.encode(key.verifying_key().to_bytes());
+
+        let index = verify(&signed(&key, INDEX), &public).unwrap();
+        assert_eq!(index.agents[0].agent_id(), "copywriter");
+
+        let mut tampered: SignedIndex = serde_json::from_str(&signed(&key, INDEX)).unwrap();
+        tampered.payload = STANDARD.encode(INDEX.replace("punchy", "sneaky"));
+        let tampered = serde_json::to_string(&tampered).unwrap();
+        assert!(verify(&tampered, &public).unwrap_err().contains("signature"));
+
+        let other = STANDARD.encode(SigningKey::from_bytes(&[8; 32]).verifying_key().to_bytes());
+        assert!(verify(&signed(&key, INDEX), &other).is_err());
+        assert!(verify(&signed(&key, INDEX), "not a key").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_review_staged_agents() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let index: RegistryIndex = serde_json::from_str(INDEX).unwrap();
+        let report = stage(&pool, &index).await.unwrap();
+        assert_eq!(report.staged, vec!["copywriter"]);
+        assert!(crate::agents::get(&pool, "copywriter").await.unwrap().is_none());
+
+        // Unconfigured registries don't sync, but staged agents stay reviewable
+        assert!(sync(&pool).await.unwrap().is_none());
+        let status = status(&pool).await.unwrap();
+        assert!(!status.configured);
+        assert_eq!(status.pending.len(), 1);
+
+        assert!(reject(&pool, "copywriter").await.unwrap());
+        assert!(stage(&pool, &index).await.unwrap().staged.is_empty());
+
+        // A changed definition comes back for review
+        let changed: RegistryIndex = serde_json::from_str(&INDEX.replace("punchy", "clear")).unwrap();
+        assert_eq!(stage(&pool, &changed).await.unwrap().staged, vec!["copywriter"]);
+        let agent = approve(&pool, "copywriter").await.unwrap();
+        assert_eq!(agent.system_prompt, "You write clear copy.");
+        assert!(approve(&pool, "copywriter").await.is_err());
+    }
+}
@@ -0,0 +1,418 @@
+// Agent tools - project-scoped handlers the model can call during a generation
+use futures::future::BoxFuture;
+use serde_json::json;
+use sqlx::{Row, SqlitePool};
+
+use crate::llm::{Tool, ToolDefinition, ToolRegistry};
+use crate::sandbox;
+use crate::sharing::{Access, Principal};
+use crate::tool_policy::{ToolGate, ToolPermission};
+
+/// Largest file `write_file` accepts, in bytes
+const MAX_FILE_SIZE: usize = 1024 * 1024;
+
+/// Tools for one project; `write_file` is only offered with write access,
+/// and `run_command` only to the local owner with write access
+///
+/// Calls are limited to the gate's permissions.
+pub fn registry_for_project(
+    pool: &SqlitePool,
+    project_id: &str,
+    principal: Principal<'_>,
+    access: Access,
+    gate: ToolGate,
+) -> ToolRegistry {
+    let scope = || ProjectScope {
+        pool: pool.clone(),
+        project_id: project_id.to_string(),
+    };
+
+    let mut registry = ToolRegistry::new();
+    registry.register(GetProject(scope()));
+    registry.register(ListFiles(scope()));
+    registry.register(ReadFile(scope()));
+    if access >= Access::Write {
+        registry.register(WriteFile(scope()));
+        if principal.is_local_owner() {
+            registry.register(RunCommand(scope()));
+        }
+    }
+    registry.set_gate(gate);
+    registry
+}
+
+struct ProjectScope {
+    pool: SqlitePool,
+    project_id: String,
+}
+
+fn db_error(e: sqlx::Error) -> String {
+    format!("Database error: {}", e)
+}
+
+fn path_arg(input: &serde_json::Value) -> Result<String, String> {
+    let path = input["path"].as_str().map(str::trim).unwrap_or_default();
+    if path.is_empty() {
+        return Err("path is required".to_string());
+    }
+    let path = path.trim_start_matches('/');
+    if path.split('/').any(|segment| segment == "..") || sandbox::relative_path(path).is_err() {
+        return Err(format!("Invalid path: {}", path));
+    }
+    Ok(path.to_string())
+}
+
+/// Language label for a file, from its extension
+pub(crate) fn language_for(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).as_deref() {
+        Some("html" | "htm") => "html",
+        Some("css") => "css",
+        Some("js" | "mjs" | "cjs") => "javascript",
+        Some("jsx") => "jsx",
+        Some("ts") => "typescript",
+        Some("tsx") => "tsx",
+        Some("json") => "json",
+        Some("md") => "markdown",
+        Some("py") => "python",
+        Some("rs") => "rust",
+        Some("svg") => "svg",
+        _ => "plaintext",
+    }
+}
+
+/// Create or overwrite a project file, touching the project
+pub async fn save_file(pool: &SqlitePool, project_id: &str, path: &str, content: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO project_files (id, project_id, path, content, language)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(project_id, path) DO UPDATE SET
+            content = excluded.content,
+            language = excluded.language,
+            updated_at = datetime('now')
+        "#
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(project_id)
+    .bind(path)
+    .bind(content)
+    .bind(language_for(path))
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE projects SET updated_at = datetime('now') WHERE id = ?")
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+struct GetProject(ProjectScope);
+
+impl Tool for GetProject {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "get_project".to_string(),
+            description: "Get the current project's name, type, description, and file count".to_string(),
+            input_schema: json!({"type": "object", "properties": {}}),
+        }
+    }
+
+    fn call(&self, _input: serde_json::Value) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(async move {
+            let row = sqlx::query(
+                "SELECT name, description, project_type,
+                        (SELECT COUNT(*) FROM project_files WHERE project_id = projects.id) AS file_count
+                 FROM projects WHERE id = ?"
+            )
+            .bind(&self.0.project_id)
+            .fetch_optional(&self.0.pool)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(|| "Project not found".to_string())?;
+
+            Ok(json!({
+                "name": row.get::<String, _>("name"),
+                "description": row.get::<Option<String>, _>("description"),
+                "project_type": row.get::<String, _>("project_type"),
+                "file_count": row.get::<i64, _>("file_count"),
+            })
+            .to_string())
+        })
+    }
+
+    fn permission(&self) -> Option<ToolPermission> {
+        Some(ToolPermission::ReadFiles)
+    }
+}
+
+struct ListFiles(ProjectScope);
+
+impl Tool for ListFiles {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "list_files".to_string(),
+            description: "List the paths of all files in the current project".to_string(),
+            input_schema: json!({"type": "object", "properties": {}}),
+        }
+    }
+
+    fn call(&self, _input: serde_json::Value) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(async move {
+            let paths: Vec<String> = sqlx::query_scalar("SELECT path FROM project_files WHERE project_id = ? ORDER BY path")
+                .bind(&self.0.project_id)
+                .fetch_all(&self.0.pool)
+                .await
+                .map_err(db_error)?;
+
+            if paths.is_empty() {
+                return Ok("The project has no files".to_string());
+            }
+            Ok(paths.join("\n"))
+        })
+    }
+
+    fn permission(&self) -> Option<ToolPermission> {
+        Some(ToolPermission::ReadFiles)
+    }
+}
+
+struct ReadFile(ProjectScope);
+
+impl Tool for ReadFile {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file from the current project".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {"path": {"type": "string", "description": "File path within the project"}},
+                "required": ["path"]
+            }),
+        }
+    }
+
+    fn call(&self, input: serde_json::Value) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(async move {
+            let path = path_arg(&input)?;
+            sqlx::query_scalar("SELECT content FROM project_files WHERE project_id = ? AND path = ?")
+                .bind(&self.0.project_id)
+                .bind(&path)
+                .fetch_optional(&self.0.pool)
+                .await
+                .map_err(db_error)?
+                .ok_or_else(|| format!("File not found: {}", path))
+        })
+    }
+
+    fn permission(&self) -> Option<ToolPermission> {
+        Some(ToolPermission::ReadFiles)
+    }
+}
+
+struct WriteFile(ProjectScope);
+
+impl Tool for WriteFile {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "write_file".to_string(),
+            description: "Create or overwrite a file in the current project".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "File path within the project"},
+                    "content": {"type": "string", "description": "The complete new file content"}
+                },
+                "required": ["path", "content"]
+            }),
+        }
+    }
+
+    fn call(&self, input: serde_json::Value) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(async move {
+            let path = path_arg(&input)?;
+            let content = input["content"]
+                .as_str()
+                .ok_or_else(|| "content is required".to_string())?;
+            if content.len() > MAX_FILE_SIZE {
+                return Err(format!("File is too large ({} bytes, limit {})", content.len(), MAX_FILE_SIZE));
+            }
+
+            save_file(&self.0.pool, &self.0.project_id, &path, content)
+                .await
+                .map_err(db_error)?;
+
+            Ok(format!("Wrote {} ({} bytes)", path, content.len()))
+        })
+    }
+
+    fn permission(&self) -> Option<ToolPermission> {
+        Some(ToolPermission::WriteFiles)
+    }
+}
+
+struct RunCommand(ProjectScope);
+
+impl Tool for RunCommand {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "run_command".to_string(),
+            description: "Run a build or test command, such as `npm test`, on a copy of the project's files. \
+                          One program and its arguments; no shell, pipes, or redirects. \
+                          The user approves each command before it runs."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "command": {"type": "string", "description": "Program and arguments"},
+                    "cwd": {"type": "string", "description": "Folder within the project to run in"},
+                    "timeout_secs": {"type": "integer", "description": "Seconds before the command is killed (default 60, max 600)"}
+                },
+                "required": ["command"]
+            }),
+        }
+    }
+
+    fn call(&self, input: serde_json::Value) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(async move {
+            let request = sandbox::CommandRequest {
+                command: input["command"].as_str().unwrap_or_default().to_string(),
+                cwd: input["cwd"].as_str().map(str::to_string),
+                timeout_secs: input["timeout_secs"].as_u64(),
+            };
+            let root = sandbox::materialize(&self.0.pool, &self.0.project_id).await?;
+            let output = sandbox::run(&root, &request).await?;
+
+            if output.success() {
+                Ok(output.summary())
+            } else {
+                Err(output.summary())
+            }
+        })
+    }
+
+    fn permission(&self) -> Option<ToolPermission> {
+        Some(ToolPermission::RunShell)
+    }
+
+    fn describe_call(&self, input: &serde_json::Value) -> Option<String> {
+        let command = input["command"].as_str().unwrap_or_default().trim();
+        match input["cwd"].as_str().map(str::trim).filter(|cwd| !cwd.is_empty()) {
+            Some(cwd) => Some(format!("{} (in {})", command, cwd)),
+            None => Some(command.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ToolCall;
+    use tempfile::NamedTempFile;
+
+    fn call(name: &str, input: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: format!("toolu_{}", name),
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_project_file_tools() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Demo', 'web', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let tools = registry_for_project(&pool, "p1", Principal::desktop(), Access::Write, ToolGate::without_agent());
+
+        let written = tools
+            .call(&call("write_file", json!({"path": "index.html", "content": "<h1>Hi</h1>"})))
+            .await;
+        assert!(!written.is_error, "{}", written.content);
+
+        let read = tools.call(&call("read_file", json!({"path": "index.html"}))).await;
+        assert_eq!(read.content, "<h1>Hi</h1>");
+
+        let listed = tools.call(&call("list_files", json!({}))).await;
+        assert_eq!(listed.content, "index.html");
+
+        let escaped = tools.call(&call("read_file", json!({"path": "../other/index.html"}))).await;
+        assert!(escaped.is_error);
+
+        let hook = tools
+            .call(&call("write_file", json!({"path": ".git/hooks/pre-commit", "content": "#!/bin/sh"})))
+            .await;
+        assert!(hook.is_error);
+
+        let language: String = sqlx::query_scalar("SELECT language FROM project_files WHERE path = 'index.html'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(language, "html");
+    }
+
+    #[tokio::test]
+    async fn test_read_access_has_no_write_tool() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let tools = registry_for_project(&pool, "p1", Principal::desktop(), Access::Read, ToolGate::without_agent());
+        assert!(tools
+            .definitions()
+            .iter()
+            .all(|tool| tool.name != "write_file" && tool.name != "run_command"));
+
+        let result = tools.call(&call("write_file", json!({"path": "a.txt", "content": "x"}))).await;
+        assert!(result.is_error);
+
+        // Paired devices and other accounts can write but never run commands
+        let device = Principal {
+            user_id: "local-user",
+            device_id: Some("d1"),
+            is_owner: false,
+        };
+        let tools = registry_for_project(&pool, "p1", device, Access::Write, ToolGate::without_agent());
+        let names: Vec<String> = tools.definitions().into_iter().map(|tool| tool.name).collect();
+        assert!(names.contains(&"write_file".to_string()));
+        assert!(!names.contains(&"run_command".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_gate_blocks_undeclared_tools() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Demo', 'web', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut agent = crate::agents::get(&pool, "backend-architect").await.unwrap().unwrap();
+        agent.permissions = vec![ToolPermission::ReadFiles];
+        let tools = registry_for_project(&pool, "p1", Principal::desktop(), Access::Write, ToolGate::for_agent(&agent));
+
+        let listed = tools.call(&call("list_files", json!({}))).await;
+        assert!(!listed.is_error, "{}", listed.content);
+        let written = tools
+            .call(&call("write_file", json!({"path": "index.html", "content": "<h1>Hi</h1>"})))
+            .await;
+        assert!(written.is_error);
+        assert!(written.content.contains("write project files"));
+
+        let ran = tools.call(&call("run_command", json!({"command": "npm test"}))).await;
+        assert!(ran.is_error);
+        assert!(ran.content.contains("run shell commands"));
+    }
+}
@@ -0,0 +1,545 @@
+//! Agent registry
+//!
+//! Each agent carries the system prompt, preferred model, and temperature
+//! applied when a generation names it in `agent_id`. Agents live in the
+//! `agents` table; the built-in set comes from the bundled manifest (see
+//! [`crate::agent_manifest`]), and editing a built-in marks it customized so
+//! later manifest updates leave it alone.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::agent_lint::{self, SaveError};
+use crate::tool_policy::{self, ToolPermission};
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Agent {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub capabilities: Vec<String>,
+    /// Preferred model, used when the request doesn't name one
+    pub model: String,
+    pub icon: String,
+    pub system_prompt: String,
+    pub temperature: f32,
+    /// Kinds of local tool the agent may use without asking
+    #[serde(default = "tool_policy::default_permissions")]
+    pub permissions: Vec<ToolPermission>,
+    /// Ships with the app
+    #[serde(default)]
+    pub builtin: bool,
+    /// In the listing user's favorites
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+/// An agent's name and description in another language
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentTranslation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A user-defined agent; omitted fields take the built-ins' defaults
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NewAgent {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// `Custom` when omitted
+    pub category: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Preferred model; the built-ins' model when omitted
+    pub model: Option<String>,
+    /// Emoji shown next to the name
+    pub icon: Option<String>,
+    pub system_prompt: String,
+    pub temperature: Option<f32>,
+    /// Reading and writing project files when omitted
+    pub permissions: Option<Vec<ToolPermission>>,
+}
+
+/// Changes to an agent; omitted fields are left as they are
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct AgentUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub capabilities: Option<Vec<String>>,
+    pub model: Option<String>,
+    pub icon: Option<String>,
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub permissions: Option<Vec<ToolPermission>>,
+}
+
+/// Which agents to list; every agent when empty
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AgentFilter {
+    /// Case-insensitive match on name or description
+    pub search: Option<String>,
+    /// Only agents in this category (case-insensitive)
+    pub category: Option<String>,
+    /// Only agents with this capability (case-insensitive)
+    pub capability: Option<String>,
+    /// Only agents preferring this model
+    pub model: Option<String>,
+    /// Only the user's favorite agents
+    pub favorites_only: Option<bool>,
+    /// Language to show names and descriptions in; the `locale` setting when
+    /// omitted
+    pub locale: Option<String>,
+}
+
+/// Model a custom agent prefers when it doesn't pick one
+const AGENT_MODEL: &str = "claude-sonnet-4-5";
+
+/// Icon of a custom agent that doesn't pick one
+const DEFAULT_ICON: &str = "🤖";
+
+/// Temperature of a custom agent that doesn't pick one
+const DEFAULT_TEMPERATURE: f32 = 0.5;
+
+/// Agents matching `filter`, built-ins first
+///
+/// `favorite` is set for `user_id`'s favorites; without a user none are, and
+/// `favorites_only` matches nothing. Names and descriptions are translated to
+/// the filter's locale where the agent has a translation, and searches match
+/// the translated text too.
+pub async fn list(pool: &SqlitePool, user_id: Option<&str>, filter: &AgentFilter) -> Result<Vec<Agent>, sqlx::Error> {
+    let locale = match filter.locale.as_deref().and_then(crate::locale::normalize) {
+        Some(locale) => locale,
+        None => crate::locale::current(pool).await,
+    };
+
+    let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT id, name, description, category, capabilities, model, icon, system_prompt, temperature, \
+         tool_permissions, builtin, translations, \
+         EXISTS (SELECT 1 FROM agent_favorites f WHERE f.agent_id = agents.id AND f.user_id = ",
+    );
+    builder.push_bind(user_id.map(str::to_string)).push(") AS favorite FROM agents WHERE 1 = 1");
+
+    if let Some(search) = non_empty(filter.search.clone()) {
+        let pattern = format!(
+            "%{}%",
+            search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        builder
+            .push(" AND (name LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR description LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\'");
+        for key in crate::locale::fallbacks(&locale) {
+            for field in ["name", "description"] {
+                builder
+                    .push(" OR json_extract(translations, ")
+                    .push_bind(format!("$.\"{}\".{}", key, field))
+                    .push(") LIKE ")
+                    .push_bind(pattern.clone())
+                    .push(" ESCAPE '\\'");
+            }
+        }
+        builder.push(")");
+    }
+    if let Some(category) = non_empty(filter.category.clone()) {
+        builder.push(" AND category = ").push_bind(category).push(" COLLATE NOCASE");
+    }
+    if let Some(capability) = non_empty(filter.capability.clone()) {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM json_each(agents.capabilities) WHERE value = ")
+            .push_bind(capability)
+            .push(" COLLATE NOCASE)");
+    }
+    if let Some(model) = non_empty(filter.model.clone()) {
+        builder.push(" AND model = ").push_bind(model);
+    }
+    if filter.favorites_only.unwrap_or(false) {
+        builder.push(" AND favorite");
+    }
+    builder.push(" ORDER BY builtin DESC, rowid");
+
+    let rows = builder.build().fetch_all(pool).await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let mut agent = agent_from_row(row);
+            let translations: BTreeMap<String, AgentTranslation> =
+                serde_json::from_str(row.get::<&str, _>("translations")).unwrap_or_default();
+            if let Some(translation) = crate::locale::pick(&translations, &locale) {
+                if let Some(name) = &translation.name {
+                    agent.name = name.clone();
+                }
+                if let Some(description) = &translation.description {
+                    agent.description = description.clone();
+                }
+            }
+            agent
+        })
+        .collect())
+}
+
+/// An agent by ID
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Agent>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, name, description, category, capabilities, model, icon, system_prompt, temperature,
+               tool_permissions, builtin, 0 AS favorite
+        FROM agents
+        WHERE id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(agent_from_row))
+}
+
+/// Save a user-defined agent
+pub async fn create(pool: &SqlitePool, agent: NewAgent) -> Result<Agent, SaveError> {
+    create_with_id(pool, uuid::Uuid::new_v4().to_string(), agent).await
+}
+
+/// The agent `agent` would be saved as, with defaults filled in
+pub fn draft(id: String, agent: NewAgent) -> Agent {
+    Agent {
+        id,
+        name: agent.name.trim().to_string(),
+        description: agent.description.trim().to_string(),
+        category: non_empty(agent.category).unwrap_or_else(|| "Custom".to_string()),
+        capabilities: agent.capabilities,
+        model: non_empty(agent.model).unwrap_or_else(|| AGENT_MODEL.to_string()),
+        icon: non_empty(agent.icon).unwrap_or_else(|| DEFAULT_ICON.to_string()),
+        system_prompt: agent.system_prompt,
+        temperature: agent.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+        permissions: agent.permissions.unwrap_or_else(tool_policy::default_permissions),
+        builtin: false,
+        favorite: false,
+    }
+}
+
+/// Save a user-defined agent under a chosen ID, e.g. one from an imported file
+pub async fn create_with_id(pool: &SqlitePool, id: String, agent: NewAgent) -> Result<Agent, SaveError> {
+    let agent = draft(id, agent);
+    let mut diagnostics = agent_lint::lint(&agent);
+    let existing = get(pool, &agent.id)
+        .await
+        .map_err(|e| SaveError::Failed(format!("Database error: {}", e)))?;
+    if let Some(existing) = existing {
+        diagnostics.insert(0, agent_lint::duplicate_id(&agent.id, existing.builtin));
+    }
+    if agent_lint::has_errors(&diagnostics) {
+        return Err(SaveError::Invalid(diagnostics));
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO agents
+            (id, name, description, category, capabilities, model, icon, system_prompt, temperature,
+             tool_permissions, builtin)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0)
+        "#,
+    )
+    .bind(&agent.id)
+    .bind(&agent.name)
+    .bind(&agent.description)
+    .bind(&agent.category)
+    .bind(serde_json::to_string(&agent.capabilities).unwrap_or_default())
+    .bind(&agent.model)
+    .bind(&agent.icon)
+    .bind(&agent.system_prompt)
+    .bind(agent.temperature)
+    .bind(serde_json::to_string(&agent.permissions).unwrap_or_default())
+    .execute(pool)
+    .await
+    .map_err(|e| SaveError::Failed(format!("Failed to save agent: {}", e)))?;
+
+    Ok(agent)
+}
+
+/// Change an agent, built-in or not; `None` if there's no such agent
+pub async fn update(pool: &SqlitePool, id: &str, update: AgentUpdate) -> Result<Option<Agent>, SaveError> {
+    let Some(mut agent) = get(pool, id)
+        .await
+        .map_err(|e| SaveError::Failed(format!("Database error: {}", e)))?
+    else {
+        return Ok(None);
+    };
+
+    if let Some(name) = update.name {
+        agent.name = name.trim().to_string();
+    }
+    if let Some(description) = update.description {
+        agent.description = description.trim().to_string();
+    }
+    if let Some(category) = non_empty(update.category) {
+        agent.category = category;
+    }
+    if let Some(capabilities) = update.capabilities {
+        agent.capabilities = capabilities;
+    }
+    if let Some(model) = non_empty(update.model) {
+        agent.model = model;
+    }
+    if let Some(icon) = non_empty(update.icon) {
+        agent.icon = icon;
+    }
+    if let Some(system_prompt) = update.system_prompt {
+        agent.system_prompt = system_prompt;
+    }
+    if let Some(temperature) = update.temperature {
+        agent.temperature = temperature;
+    }
+    if let Some(permissions) = update.permissions {
+        agent.permissions = permissions;
+    }
+    agent_lint::require_valid(&agent)?;
+
+    sqlx::query(
+        r#"
+        UPDATE agents
+        SET name = ?, description = ?, category = ?, capabilities = ?, model = ?, icon = ?,
+            system_prompt = ?, temperature = ?, tool_permissions = ?, customized = builtin,
+            translations = CASE WHEN name = ? AND description = ? THEN translations ELSE '{}' END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+    )
+    .bind(&agent.name)
+    .bind(&agent.description)
+    .bind(&agent.category)
+    .bind(serde_json::to_string(&agent.capabilities).unwrap_or_default())
+    .bind(&agent.model)
+    .bind(&agent.icon)
+    .bind(&agent.system_prompt)
+    .bind(agent.temperature)
+    .bind(serde_json::to_string(&agent.permissions).unwrap_or_default())
+    // Translations of the old name and description no longer fit
+    .bind(&agent.name)
+    .bind(&agent.description)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| SaveError::Failed(format!("Failed to save agent: {}", e)))?;
+
+    Ok(Some(agent))
+}
+
+/// Delete a user-defined agent; built-ins can't be deleted
+pub async fn delete(pool: &SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM agents WHERE id = ? AND builtin = 0")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Favorite or unfavorite an agent for `user_id`; returns whether it's now a favorite
+pub async fn toggle_favorite(pool: &SqlitePool, user_id: &str, agent_id: &str) -> Result<bool, String> {
+    if get(pool, agent_id).await.map_err(|e| format!("Database error: {}", e))?.is_none() {
+        return Err(format!("Agent not found: {}", agent_id));
+    }
+
+    let removed = sqlx::query("DELETE FROM agent_favorites WHERE user_id = ? AND agent_id = ?")
+        .bind(user_id)
+        .bind(agent_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save favorite: {}", e))?;
+    if removed.rows_affected() > 0 {
+        return Ok(false);
+    }
+
+    sqlx::query("INSERT INTO agent_favorites (user_id, agent_id) VALUES (?, ?)")
+        .bind(user_id)
+        .bind(agent_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save favorite: {}", e))?;
+    Ok(true)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+fn agent_from_row(row: &sqlx::sqlite::SqliteRow) -> Agent {
+    let capabilities: String = row.get("capabilities");
+    let permissions: String = row.get("tool_permissions");
+    Agent {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        category: row.get("category"),
+        capabilities: serde_json::from_str(&capabilities).unwrap_or_default(),
+        model: row.get("model"),
+        icon: row.get("icon"),
+        system_prompt: row.get("system_prompt"),
+        temperature: row.get("temperature"),
+        permissions: serde_json::from_str(&permissions).unwrap_or_else(|_| tool_policy::default_permissions()),
+        builtin: row.get("builtin"),
+        favorite: row.get("favorite"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_builtins_are_seeded() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let agent = get(&pool, "backend-architect").await.unwrap().unwrap();
+        assert_eq!(agent.name, "Backend Architect");
+        assert_eq!(agent.capabilities.len(), 3);
+        assert!(agent.builtin);
+        assert!(!agent.system_prompt.is_empty());
+        assert_eq!(crate::llm::ProviderKind::infer(&agent.model), Some(crate::llm::ProviderKind::Anthropic));
+        assert!(get(&pool, "missing").await.unwrap().is_none());
+        assert_eq!(list(&pool, None, &AgentFilter::default()).await.unwrap().len(), crate::agent_manifest::bundled().agents.len());
+    }
+
+    #[tokio::test]
+    async fn test_filter_agents() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let ids = |agents: Vec<Agent>| agents.into_iter().map(|agent| agent.id).collect::<Vec<_>>();
+        let search = AgentFilter { search: Some("ARCHITECT".to_string()), ..Default::default() };
+        assert_eq!(
+            ids(list(&pool, None, &search).await.unwrap()),
+            vec!["frontend-architect", "backend-architect", "database-architect", "frontend-developer", "mobile-developer"]
+        );
+
+        let category = AgentFilter { category: Some("devops".to_string()), ..Default::default() };
+        assert_eq!(ids(list(&pool, None, &category).await.unwrap()), vec!["devops-engineer"]);
+
+        let capability = AgentFilter { capability: Some("schema design".to_string()), ..Default::default() };
+        assert_eq!(ids(list(&pool, None, &capability).await.unwrap()), vec!["database-architect"]);
+
+        // LIKE wildcards in the search are matched literally
+        let wildcard = AgentFilter { search: Some("%".to_string()), ..Default::default() };
+        assert!(list(&pool, None, &wildcard).await.unwrap().is_empty());
+
+        let model = AgentFilter { model: Some("gpt-4o".to_string()), ..Default::default() };
+        assert!(list(&pool, None, &model).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_localized_agents() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let name = |agents: Vec<Agent>, id: &str| agents.into_iter().find(|agent| agent.id == id).unwrap().name;
+        let all = AgentFilter::default();
+        assert_eq!(name(list(&pool, None, &all).await.unwrap(), "ui-designer"), "UI/UX Designer");
+
+        // The setting applies unless the filter picks a locale; regions fall back to the language
+        sqlx::query("INSERT INTO settings (id, key, value) VALUES ('setting-locale', 'locale', 'es-MX')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(name(list(&pool, None, &all).await.unwrap(), "ui-designer"), "Diseñador UI/UX");
+        let french = AgentFilter { locale: Some("fr_CA".to_string()), ..Default::default() };
+        assert_eq!(name(list(&pool, None, &french).await.unwrap(), "ui-designer"), "Designer UI/UX");
+
+        let search = AgentFilter { search: Some("diseñador".to_string()), ..Default::default() };
+        let found = list(&pool, None, &search).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "ui-designer");
+
+        // Renaming drops translations of the old name; other edits keep them
+        let edit = AgentUpdate { temperature: Some(0.5), ..Default::default() };
+        update(&pool, "ui-designer", edit).await.unwrap();
+        assert_eq!(name(list(&pool, None, &all).await.unwrap(), "ui-designer"), "Diseñador UI/UX");
+        let rename = AgentUpdate { name: Some("Designer".to_string()), ..Default::default() };
+        update(&pool, "ui-designer", rename).await.unwrap();
+        assert_eq!(name(list(&pool, None, &all).await.unwrap(), "ui-designer"), "Designer");
+        assert_eq!(get(&pool, "ui-designer").await.unwrap().unwrap().name, "Designer");
+    }
+
+    #[tokio::test]
+    async fn test_custom_agents() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let new_agent = |system_prompt: &str| NewAgent {
+            name: " Copywriter ".to_string(),
+            description: String::new(),
+            category: None,
+            capabilities: vec!["Landing pages".to_string()],
+            model: None,
+            icon: None,
+            system_prompt: system_prompt.to_string(),
+            temperature: Some(0.9),
+            permissions: None,
+        };
+        assert!(create(&pool, new_agent("  ")).await.is_err());
+
+        let agent = create(&pool, new_agent("You write punchy marketing copy.")).await.unwrap();
+        assert_eq!(agent.name, "Copywriter");
+        assert_eq!(agent.category, "Custom");
+        assert!(!agent.builtin);
+        let all = AgentFilter::default();
+        assert_eq!(list(&pool, None, &all).await.unwrap().last().unwrap().id, agent.id);
+
+        let update_temperature = |temperature| AgentUpdate { temperature: Some(temperature), ..Default::default() };
+        assert!(update(&pool, &agent.id, update_temperature(3.0)).await.is_err());
+        let updated = update(&pool, &agent.id, update_temperature(0.1)).await.unwrap().unwrap();
+        assert_eq!(updated.name, "Copywriter");
+        assert!((get(&pool, &agent.id).await.unwrap().unwrap().temperature - 0.1).abs() < 1e-6);
+        assert!(update(&pool, "missing", AgentUpdate::default()).await.unwrap().is_none());
+
+        // Built-ins can be edited but not deleted
+        assert!(!delete(&pool, "backend-architect").await.unwrap());
+        assert!(delete(&pool, &agent.id).await.unwrap());
+        assert!(get(&pool, &agent.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_favorites() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        assert!(toggle_favorite(&pool, "local-user", "devops-engineer").await.unwrap());
+        assert!(toggle_favorite(&pool, "local-user", "ui-designer").await.unwrap());
+        assert!(toggle_favorite(&pool, "local-user", "missing").await.is_err());
+
+        let favorites = AgentFilter { favorites_only: Some(true), ..Default::default() };
+        let listed = list(&pool, Some("local-user"), &favorites).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().all(|agent| agent.favorite));
+        assert!(list(&pool, None, &favorites).await.unwrap().is_empty());
+        let all = list(&pool, Some("local-user"), &AgentFilter::default()).await.unwrap();
+        assert_eq!(all.iter().filter(|agent| agent.favorite).count(), 2);
+
+        // Toggling again removes it
+        assert!(!toggle_favorite(&pool, "local-user", "ui-designer").await.unwrap());
+        let listed = list(&pool, Some("local-user"), &favorites).await.unwrap();
+        assert_eq!(listed.iter().map(|agent| agent.id.as_str()).collect::<Vec<_>>(), vec!["devops-engineer"]);
+    }
+}
@@ -0,0 +1,189 @@
+//! Image attachments
+//!
+//! Images are uploaded once and then referenced from stream requests by ID,
+//! so a screenshot isn't re-sent with every prompt. Only the formats vision
+//! models accept are stored, and each is checked against its actual bytes.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use utoipa::ToSchema;
+
+/// Largest image accepted, after decoding (the Anthropic API's limit)
+pub const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+
+/// An uploaded image
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Attachment {
+    pub id: String,
+    pub media_type: String,
+    /// Size in bytes
+    pub size: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewAttachment {
+    /// Base64 image data, or a `data:` URL
+    pub data: String,
+    /// `image/png`, `image/jpeg`, `image/gif`, or `image/webp`; detected
+    /// from the data when omitted
+    pub media_type: Option<String>,
+}
+
+/// An image ready to send: its media type and raw bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub media_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl Image {
+    pub fn base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(&self.bytes)
+    }
+}
+
+/// Decode and check base64 image data (optionally a `data:` URL)
+///
+/// The format is read from the bytes; a declared `media_type` that doesn't
+/// match them is an error.
+pub fn decode_image(data: &str, media_type: Option<&str>) -> Result<Image, String> {
+    let (declared, data) = match data.trim().strip_prefix("data:") {
+        Some(url) => {
+            let (header, data) = url.split_once(',').ok_or("Malformed data URL")?;
+            let declared = header.strip_suffix(";base64").ok_or("Data URLs must be base64-encoded")?;
+            (media_type.or(Some(declared)), data)
+        }
+        None => (media_type, data.trim()),
+    };
+
+    // Rough pre-check so an oversized upload isn't decoded first
+    if data.len() / 4 * 3 > MAX_IMAGE_SIZE + 3 {
+        return Err(format!("Images must be at most {} MB", MAX_IMAGE_SIZE / (1024 * 1024)));
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid base64 image data: {}", e))?;
+    if bytes.len() > MAX_IMAGE_SIZE {
+        return Err(format!("Images must be at most {} MB", MAX_IMAGE_SIZE / (1024 * 1024)));
+    }
+
+    let detected = sniff(&bytes).ok_or("Unsupported image type; use PNG, JPEG, GIF, or WebP")?;
+    if let Some(declared) = declared.map(str::trim).filter(|t| !t.is_empty()) {
+        if !declared.eq_ignore_ascii_case(detected) {
+            return Err(format!("Image data is {}, not {}", detected, declared));
+        }
+    }
+
+    Ok(Image {
+        media_type: detected.to_string(),
+        bytes,
+    })
+}
+
+/// Media type from an image's magic bytes
+fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Store an image for `user_id`
+pub async fn create(pool: &SqlitePool, user_id: &str, attachment: NewAttachment) -> Result<Attachment, String> {
+    let image = decode_image(&attachment.data, attachment.media_type.as_deref())?;
+    let attachment = Attachment {
+        id: uuid::Uuid::new_v4().to_string(),
+        media_type: image.media_type,
+        size: image.bytes.len() as i64,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    sqlx::query("INSERT INTO attachments (id, user_id, media_type, data, size, created_at) VALUES (?, ?, ?, ?, ?, ?)")
+        .bind(&attachment.id)
+        .bind(user_id)
+        .bind(&attachment.media_type)
+        .bind(&image.bytes)
+        .bind(attachment.size)
+        .bind(&attachment.created_at)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save attachment: {}", e))?;
+
+    Ok(attachment)
+}
+
+/// Load one of `user_id`'s images
+pub async fn load(pool: &SqlitePool, user_id: &str, id: &str) -> Result<Option<Image>, sqlx::Error> {
+    let row = sqlx::query("SELECT media_type, data FROM attachments WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.map(|row| Image {
+        media_type: row.get("media_type"),
+        bytes: row.get("data"),
+    }))
+}
+
+/// Delete one of `user_id`'s images; false if there was none
+pub async fn delete(pool: &SqlitePool, user_id: &str, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM attachments WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    /// A 1x1 transparent PNG
+    const PIXEL: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+    #[test]
+    fn test_decode_image() {
+        let image = decode_image(PIXEL, None).unwrap();
+        assert_eq!(image.media_type, "image/png");
+        assert_eq!(image.base64(), PIXEL);
+
+        let url = format!("data:image/png;base64,{}", PIXEL);
+        assert_eq!(decode_image(&url, None).unwrap(), image);
+
+        assert!(decode_image(PIXEL, Some("image/jpeg")).unwrap_err().contains("not image/jpeg"));
+        assert!(decode_image("PHN2Zz48L3N2Zz4=", None).unwrap_err().starts_with("Unsupported image type"));
+        assert!(decode_image("not base64!", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_attachments_belong_to_their_user() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let upload = NewAttachment {
+            data: PIXEL.to_string(),
+            media_type: None,
+        };
+        let attachment = create(&pool, "local-user", upload).await.unwrap();
+        assert_eq!(attachment.size, 68);
+
+        assert!(load(&pool, "local-user", &attachment.id).await.unwrap().is_some());
+        assert!(load(&pool, "someone-else", &attachment.id).await.unwrap().is_none());
+        assert!(!delete(&pool, "someone-else", &attachment.id).await.unwrap());
+        assert!(delete(&pool, "local-user", &attachment.id).await.unwrap());
+    }
+}
@@ -1,6 +1,17 @@
 use keyring::Entry;
 use serde::{Deserialize, Serialize};
 use sqlx::{SqlitePool, Row};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often stored keys are revalidated in the background
+const REVALIDATION_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How long a cached auth status is served before a background refresh
+const AUTH_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ClaudeCredentials {
@@ -9,47 +20,182 @@ pub struct ClaudeCredentials {
     pub subscription_tier: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AuthStatus {
     pub authenticated: bool,
-    pub source: String, // "keychain", "manual", or "none"
+    pub source: String, // "keychain", "credential_manager", "secret_service", "claude_config", "oauth", "database", "locked", or "none"
     pub email: Option<String>,
 }
 
-/// Try to read Claude Code credentials from macOS Keychain
+/// LLM providers that credentials can be stored for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Anthropic,
+    OpenAI,
+    Custom,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 3] = [
+        Provider::Anthropic,
+        Provider::OpenAI,
+        Provider::Custom,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Anthropic => "anthropic",
+            Provider::OpenAI => "openai",
+            Provider::Custom => "custom",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Provider::Anthropic => "Anthropic",
+            Provider::OpenAI => "OpenAI",
+            Provider::Custom => "Custom Endpoint",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Provider, String> {
+        match value.to_lowercase().as_str() {
+            "anthropic" | "claude" => Ok(Provider::Anthropic),
+            "openai" => Ok(Provider::OpenAI),
+            "custom" => Ok(Provider::Custom),
+            other => Err(format!("Unknown provider: {}", other)),
+        }
+    }
+}
+
+/// Stored credentials for a single provider
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProviderCredentials {
+    pub provider: Provider,
+    pub api_key: String,
+    pub base_url: Option<String>,
+    /// `api_key` is an OAuth access token, sent as a bearer token
+    #[serde(default)]
+    pub oauth: bool,
+}
+
+/// Provider summary returned to the frontend (never includes the key itself)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProviderInfo {
+    pub provider: Provider,
+    pub display_name: String,
+    pub configured: bool,
+    pub base_url: Option<String>,
+    pub last_validated: Option<String>,
+}
+
+/// On-disk credentials file written by Claude Code (`~/.claude/.credentials.json`)
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ClaudeCodeCredentialsFile {
+    claude_ai_oauth: Option<ClaudeCodeOAuth>,
+    primary_api_key: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ClaudeCodeOAuth {
+    access_token: String,
+    subscription_type: Option<String>,
+}
+
+/// Name of the OS credential store probed on this platform, reported as the auth source
+pub fn keychain_source() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "keychain"
+    } else if cfg!(target_os = "windows") {
+        "credential_manager"
+    } else {
+        "secret_service"
+    }
+}
+
+/// Parse a secret found in a credential store or file into credentials
+///
+/// Accepts our own `ClaudeCredentials` JSON, Claude Code's `claudeAiOauth` JSON,
+/// or a bare `sk-ant-` key.
+fn parse_claude_code_secret(secret: &str) -> Option<ClaudeCredentials> {
+    let secret = secret.trim();
+
+    if let Ok(creds) = serde_json::from_str::<ClaudeCredentials>(secret) {
+        return Some(creds);
+    }
+
+    if let Ok(file) = serde_json::from_str::<ClaudeCodeCredentialsFile>(secret) {
+        if let Some(oauth) = file.claude_ai_oauth {
+            return Some(ClaudeCredentials {
+                api_key: oauth.access_token,
+                email: None,
+                subscription_tier: oauth.subscription_type,
+            });
+        }
+        if let Some(api_key) = file.primary_api_key {
+            return Some(ClaudeCredentials {
+                api_key,
+                email: None,
+                subscription_tier: None,
+            });
+        }
+    }
+
+    if secret.starts_with("sk-ant-") {
+        return Some(ClaudeCredentials {
+            api_key: secret.to_string(),
+            email: None,
+            subscription_tier: None,
+        });
+    }
+
+    None
+}
+
+/// Try to read Claude Code credentials from the OS credential store
+///
+/// Uses the macOS Keychain, Windows Credential Manager, or the Linux Secret
+/// Service depending on the platform (all via `keyring`).
 pub fn read_claude_code_keychain() -> Result<ClaudeCredentials, String> {
     // Try multiple possible keychain entries that Claude Code might use
     let possible_services = vec![
+        "Claude Code-credentials",
         "com.anthropic.claude-code",
         "claude-code",
         "anthropic-claude",
     ];
 
-    let possible_accounts = vec![
-        "default",
-        "api_key",
-        "credentials",
+    let mut possible_accounts = vec![
+        "default".to_string(),
+        "api_key".to_string(),
+        "credentials".to_string(),
     ];
 
+    // Claude Code keys its entries by the OS user name
+    for var in ["USER", "USERNAME"] {
+        if let Ok(user) = std::env::var(var) {
+            if !possible_accounts.contains(&user) {
+                possible_accounts.insert(0, user);
+            }
+        }
+    }
+
     for service in &possible_services {
         for account in &possible_accounts {
             if let Ok(entry) = Entry::new(service, account) {
                 if let Ok(password) = entry.get_password() {
-                    // Try to parse as JSON first
-                    if let Ok(creds) = serde_json::from_str::<ClaudeCredentials>(&password) {
-                        println!("✅ Found Claude Code credentials in keychain: {} / {}", service, account);
+                    if let Some(creds) = parse_claude_code_secret(&password) {
+                        println!(
+                            "✅ Found Claude Code credentials in {}: {} / {}",
+                            keychain_source(),
+                            service,
+                            account
+                        );
                         return Ok(creds);
                     }
-
-                    // If not JSON, treat the whole thing as an API key
-                    if password.starts_with("sk-ant-") {
-                        println!("✅ Found Claude API key in keychain: {} / {}", service, account);
-                        return Ok(ClaudeCredentials {
-                            api_key: password,
-                            email: None,
-                            subscription_tier: None,
-                        });
-                    }
                 }
             }
         }
@@ -58,14 +204,68 @@ pub fn read_claude_code_keychain() -> Result<ClaudeCredentials, String> {
     Err("No Claude Code credentials found in keychain".to_string())
 }
 
-/// Validate API key with Anthropic API
-pub async fn validate_api_key(api_key: &str) -> Result<bool, String> {
-    let client = reqwest::Client::new();
+/// Path to Claude Code's on-disk credentials file
+pub fn claude_code_credentials_path() -> Option<std::path::PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude").join(".credentials.json"))
+}
 
-    // Use Anthropic's messages API to validate the key
-    let response = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("x-api-key", api_key)
+/// Read Claude Code credentials from `~/.claude/.credentials.json`
+pub fn read_claude_code_config_file() -> Result<ClaudeCredentials, String> {
+    let path = claude_code_credentials_path()
+        .ok_or_else(|| "Could not determine home directory".to_string())?;
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let creds = parse_claude_code_secret(&contents)
+        .ok_or_else(|| format!("No usable credentials in {}", path.display()))?;
+
+    println!("✅ Found Claude Code credentials in {}", path.display());
+    Ok(creds)
+}
+
+/// Discover Claude Code credentials from any known location
+///
+/// Returns the credentials along with the source they were found in
+/// (see [`keychain_source`], or `"claude_config"` for the on-disk file).
+pub fn discover_claude_code_credentials() -> Result<(ClaudeCredentials, String), String> {
+    if let Ok(creds) = read_claude_code_keychain() {
+        return Ok((creds, keychain_source().to_string()));
+    }
+
+    if let Ok(creds) = read_claude_code_config_file() {
+        return Ok((creds, "claude_config".to_string()));
+    }
+
+    Err("No Claude Code credentials found".to_string())
+}
+
+/// Whether a stored Anthropic credential is a Claude Code subscription
+/// token (an OAuth bearer token) rather than a console key
+///
+/// Keys don't record where they came from, so this goes by the token prefix.
+pub fn is_oauth_token(api_key: &str) -> bool {
+    api_key.starts_with("sk-ant-oat")
+}
+
+/// Send a minimal one-token request to Anthropic's messages API
+///
+/// Used both to validate keys and to read the rate-limit headers.
+async fn anthropic_probe(api_key: &str) -> Result<reqwest::Response, String> {
+    let client = crate::proxy::client();
+
+    let request = if is_oauth_token(api_key) {
+        client
+            .post("https://api.anthropic.com/v1/messages")
+            .bearer_auth(api_key)
+            .header("anthropic-beta", "oauth-2025-04-20")
+    } else {
+        client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", api_key)
+    };
+
+    request
         .header("anthropic-version", "2023-06-01")
         .header("content-type", "application/json")
         .json(&serde_json::json!({
@@ -75,7 +275,13 @@ pub async fn validate_api_key(api_key: &str) -> Result<bool, String> {
         }))
         .send()
         .await
-        .map_err(|e| format!("API request failed: {}", e))?;
+        .map_err(|e| format!("API request failed: {}", e))
+}
+
+/// Validate API key with Anthropic API
+pub async fn validate_api_key(api_key: &str) -> Result<bool, String> {
+    // Use Anthropic's messages API to validate the key
+    let response = anthropic_probe(api_key).await?;
 
     let status = response.status();
 
@@ -89,6 +295,285 @@ pub async fn validate_api_key(api_key: &str) -> Result<bool, String> {
     }
 }
 
+/// Rate-limit budget reported by Anthropic's `anthropic-ratelimit-*` headers
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RateLimits {
+    pub requests_limit: Option<u64>,
+    pub requests_remaining: Option<u64>,
+    pub requests_reset: Option<String>,
+    pub input_tokens_limit: Option<u64>,
+    pub input_tokens_remaining: Option<u64>,
+    pub output_tokens_limit: Option<u64>,
+    pub output_tokens_remaining: Option<u64>,
+    pub tokens_reset: Option<String>,
+}
+
+impl RateLimits {
+    /// Parse the rate-limit headers from an Anthropic response
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        let text = |name: &str| {
+            headers
+                .get(format!("anthropic-ratelimit-{}", name))
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        let number = |name: &str| text(name).and_then(|value| value.parse().ok());
+
+        Self {
+            requests_limit: number("requests-limit"),
+            requests_remaining: number("requests-remaining"),
+            requests_reset: text("requests-reset"),
+            input_tokens_limit: number("input-tokens-limit"),
+            input_tokens_remaining: number("input-tokens-remaining"),
+            output_tokens_limit: number("output-tokens-limit"),
+            output_tokens_remaining: number("output-tokens-remaining"),
+            tokens_reset: text("tokens-reset"),
+        }
+    }
+}
+
+/// Account details shown in the UI
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountInfo {
+    pub email: Option<String>,
+    pub subscription_tier: Option<String>,
+    pub rate_limits: RateLimits,
+    pub checked_at: String,
+}
+
+/// Infer the API usage tier from the requests-per-minute limit
+///
+/// Anthropic's published tiers: 1 = 50 RPM, 2 = 1,000, 3 = 2,000, 4 = 4,000.
+/// Anything above is a custom (scale) plan.
+pub fn infer_tier(limits: &RateLimits) -> Option<String> {
+    let rpm = limits.requests_limit?;
+
+    let tier = match rpm {
+        0..=50 => "tier-1",
+        51..=1000 => "tier-2",
+        1001..=2000 => "tier-3",
+        2001..=4000 => "tier-4",
+        _ => "custom",
+    };
+
+    Some(tier.to_string())
+}
+
+/// Query Anthropic for the current key's tier and rate-limit budget
+///
+/// Subscription tiers reported by Claude Code (pro, max, ...) take precedence
+/// over the tier inferred from rate limits. The result is persisted to
+/// `auth_credentials.subscription_tier`.
+pub async fn fetch_account_info(pool: &SqlitePool) -> Result<AccountInfo, String> {
+    let creds = match discover_claude_code_credentials() {
+        Ok((creds, _)) => creds,
+        Err(_) => load_credentials_from_db(pool).await?,
+    };
+
+    let response = anthropic_probe(&creds.api_key).await?;
+
+    if response.status().as_u16() == 401 {
+        return Err("API key is invalid".to_string());
+    }
+
+    let rate_limits = RateLimits::from_headers(response.headers());
+    let subscription_tier = creds
+        .subscription_tier
+        .clone()
+        .or_else(|| infer_tier(&rate_limits));
+
+    sqlx::query(
+        "UPDATE auth_credentials SET subscription_tier = ?, last_validated = datetime('now'), updated_at = datetime('now') WHERE id = 1"
+    )
+    .bind(&subscription_tier)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(AccountInfo {
+        email: creds.email,
+        subscription_tier,
+        rate_limits,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Validate a key against the given provider's API
+///
+/// Custom endpoints are expected to be OpenAI-compatible and must supply a base URL.
+pub async fn validate_provider_key(
+    provider: Provider,
+    api_key: &str,
+    base_url: Option<&str>,
+) -> Result<bool, String> {
+    let client = crate::proxy::client();
+
+    let request = match provider {
+        Provider::Anthropic => return validate_api_key(api_key).await,
+        Provider::OpenAI => client
+            .get("https://api.openai.com/v1/models")
+            .bearer_auth(api_key),
+        Provider::Custom => {
+            let base_url = base_url
+                .filter(|url| !url.is_empty())
+                .ok_or_else(|| "Custom provider requires a base URL".to_string())?;
+            client
+                .get(format!("{}/models", base_url.trim_end_matches('/')))
+                .bearer_auth(api_key)
+        }
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("API request failed: {}", e))?;
+
+    let status = response.status();
+
+    if status.is_success() {
+        Ok(true)
+    } else if status.as_u16() == 401 || status.as_u16() == 403 || status.as_u16() == 400 {
+        Ok(false)
+    } else {
+        Err(format!("Unexpected API response: {}", status))
+    }
+}
+
+/// Store a provider key in the local database
+pub async fn store_provider_key(
+    pool: &SqlitePool,
+    provider: Provider,
+    api_key: &str,
+    base_url: Option<&str>,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO provider_credentials (provider, api_key, base_url, last_validated, updated_at)
+         VALUES (?1, ?2, ?3, datetime('now'), datetime('now'))
+         ON CONFLICT(provider) DO UPDATE SET
+            api_key = excluded.api_key,
+            base_url = excluded.base_url,
+            last_validated = excluded.last_validated,
+            updated_at = excluded.updated_at"
+    )
+    .bind(provider.as_str())
+    .bind(crate::vault::seal(pool, api_key).await?)
+    .bind(base_url)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    // Keep the legacy single-credential table in sync for the Anthropic path
+    if provider == Provider::Anthropic {
+        store_credentials_in_db(pool, api_key, None, None).await?;
+    }
+    crate::llm::invalidate_models();
+
+    Ok(())
+}
+
+/// Load the stored key for a provider
+///
+/// Anthropic falls back to the legacy `auth_credentials` row so keys saved
+/// before multi-provider support keep working, and then to the OAuth sign
+/// in, refreshing its access token if needed.
+pub async fn load_provider_key(
+    pool: &SqlitePool,
+    provider: Provider,
+) -> Result<ProviderCredentials, String> {
+    let result = sqlx::query("SELECT api_key, base_url FROM provider_credentials WHERE provider = ?")
+        .bind(provider.as_str())
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if let Some(row) = result {
+        let api_key = crate::vault::open(row.get("api_key"))?;
+        return Ok(ProviderCredentials {
+            provider,
+            oauth: provider == Provider::Anthropic && is_oauth_token(&api_key),
+            api_key,
+            base_url: row.get("base_url"),
+        });
+    }
+
+    if provider == Provider::Anthropic {
+        let legacy = match load_credentials_from_db(pool).await {
+            Ok(creds) => {
+                return Ok(ProviderCredentials {
+                    provider,
+                    oauth: is_oauth_token(&creds.api_key),
+                    api_key: creds.api_key,
+                    base_url: None,
+                })
+            }
+            Err(e) => e,
+        };
+
+        return match crate::oauth::get_valid_access_token(pool).await {
+            Ok(access_token) => Ok(ProviderCredentials {
+                provider,
+                api_key: access_token,
+                base_url: None,
+                oauth: true,
+            }),
+            Err(_) => Err(legacy),
+        };
+    }
+
+    Err(format!("No credentials stored for {}", provider.display_name()))
+}
+
+/// Remove the stored key for a provider
+pub async fn delete_provider_key(pool: &SqlitePool, provider: Provider) -> Result<(), String> {
+    sqlx::query("DELETE FROM provider_credentials WHERE provider = ?")
+        .bind(provider.as_str())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if provider == Provider::Anthropic {
+        sqlx::query("DELETE FROM auth_credentials WHERE id = 1")
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+    crate::llm::invalidate_models();
+
+    Ok(())
+}
+
+/// List every supported provider along with whether a key is configured
+pub async fn list_provider_info(pool: &SqlitePool) -> Result<Vec<ProviderInfo>, String> {
+    let rows = sqlx::query("SELECT provider, base_url, last_validated FROM provider_credentials")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut providers = Vec::with_capacity(Provider::ALL.len());
+
+    for provider in Provider::ALL {
+        let row = rows
+            .iter()
+            .find(|row| row.get::<String, _>("provider") == provider.as_str());
+
+        let mut info = ProviderInfo {
+            provider,
+            display_name: provider.display_name().to_string(),
+            configured: row.is_some(),
+            base_url: row.and_then(|r| r.get("base_url")),
+            last_validated: row.and_then(|r| r.get("last_validated")),
+        };
+
+        if provider == Provider::Anthropic && !info.configured {
+            info.configured = load_credentials_from_db(pool).await.is_ok();
+        }
+
+        providers.push(info);
+    }
+
+    Ok(providers)
+}
+
 /// Load credentials from local database
 pub async fn load_credentials_from_db(pool: &SqlitePool) -> Result<ClaudeCredentials, String> {
     let result = sqlx::query("SELECT api_key, email, subscription_tier FROM auth_credentials WHERE id = 1")
@@ -98,7 +583,7 @@ pub async fn load_credentials_from_db(pool: &SqlitePool) -> Result<ClaudeCredent
 
     match result {
         Some(row) => Ok(ClaudeCredentials {
-            api_key: row.get("api_key"),
+            api_key: crate::vault::open(row.get("api_key"))?,
             email: row.get("email"),
             subscription_tier: row.get("subscription_tier"),
         }),
@@ -117,7 +602,7 @@ pub async fn store_credentials_in_db(
         "INSERT OR REPLACE INTO auth_credentials (id, api_key, email, subscription_tier, last_validated, updated_at)
          VALUES (1, ?1, ?2, ?3, datetime('now'), datetime('now'))"
     )
-    .bind(api_key)
+    .bind(crate::vault::seal(pool, api_key).await?)
     .bind(email)
     .bind(subscription_tier)
     .execute(pool)
@@ -129,8 +614,8 @@ pub async fn store_credentials_in_db(
 
 /// Check authentication status - tries keychain first, then database
 pub async fn check_auth_status(pool: &SqlitePool) -> Result<AuthStatus, String> {
-    // Try keychain first
-    if let Ok(creds) = read_claude_code_keychain() {
+    // Try keychain / Claude Code config first
+    if let Ok((creds, source)) = discover_claude_code_credentials() {
         // Validate and store in database for future use
         if let Ok(true) = validate_api_key(&creds.api_key).await {
             let _ = store_credentials_in_db(
@@ -143,12 +628,30 @@ pub async fn check_auth_status(pool: &SqlitePool) -> Result<AuthStatus, String>
 
             return Ok(AuthStatus {
                 authenticated: true,
-                source: "keychain".to_string(),
+                source,
                 email: creds.email,
             });
         }
     }
 
+    // Try OAuth sign-in (refreshes the access token if needed)
+    if crate::oauth::get_valid_access_token(pool).await.is_ok() {
+        return Ok(AuthStatus {
+            authenticated: true,
+            source: "oauth".to_string(),
+            email: None,
+        });
+    }
+
+    // Stored keys are unreadable while the app lock is engaged
+    if crate::vault::is_enabled(pool).await? && !crate::vault::is_unlocked() {
+        return Ok(AuthStatus {
+            authenticated: false,
+            source: "locked".to_string(),
+            email: None,
+        });
+    }
+
     // Try database
     if let Ok(creds) = load_credentials_from_db(pool).await {
         return Ok(AuthStatus {
@@ -165,6 +668,238 @@ pub async fn check_auth_status(pool: &SqlitePool) -> Result<AuthStatus, String>
     })
 }
 
+struct CachedStatus {
+    status: AuthStatus,
+    checked_at: Instant,
+}
+
+/// Cached result of [`check_auth_status`], managed as app state
+///
+/// Serves the last status immediately and refreshes it in the background once
+/// it is older than the TTL, so the frontend can poll cheaply without hitting
+/// the Anthropic API on every call. Every invalidation bumps a generation
+/// counter, and a background refresh started before it is discarded.
+#[derive(Default)]
+pub struct AuthCache {
+    entry: tokio::sync::Mutex<Option<CachedStatus>>,
+    refreshing: AtomicBool,
+    generation: AtomicU64,
+}
+
+impl AuthCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the auth status, using the cache unless `force` is set
+    pub async fn get(self: &Arc<Self>, pool: Arc<SqlitePool>, force: bool) -> Result<AuthStatus, String> {
+        let mut entry = self.entry.lock().await;
+
+        if !force {
+            if let Some(cached) = entry.as_ref() {
+                if cached.checked_at.elapsed() >= AUTH_CACHE_TTL {
+                    self.spawn_refresh(pool);
+                }
+                return Ok(cached.status.clone());
+            }
+        }
+
+        let status = check_auth_status(pool.as_ref()).await?;
+        *entry = Some(CachedStatus {
+            status: status.clone(),
+            checked_at: Instant::now(),
+        });
+
+        Ok(status)
+    }
+
+    /// Drop the cached status so the next call checks again
+    pub async fn invalidate(&self) {
+        let mut entry = self.entry.lock().await;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *entry = None;
+    }
+
+    /// Store a status checked during `generation`, unless it was invalidated since
+    async fn store_if_current(&self, generation: u64, status: AuthStatus) -> bool {
+        let mut entry = self.entry.lock().await;
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return false;
+        }
+
+        *entry = Some(CachedStatus {
+            status,
+            checked_at: Instant::now(),
+        });
+        true
+    }
+
+    fn spawn_refresh(self: &Arc<Self>, pool: Arc<SqlitePool>) {
+        // Only one refresh in flight at a time
+        if self.refreshing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let cache = Arc::clone(self);
+        let generation = self.generation.load(Ordering::SeqCst);
+        tauri::async_runtime::spawn(async move {
+            match check_auth_status(pool.as_ref()).await {
+                Ok(status) => {
+                    cache.store_if_current(generation, status).await;
+                }
+                Err(e) => eprintln!("Background auth refresh failed: {}", e),
+            }
+            cache.refreshing.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Invalidate the managed [`AuthCache`], if one is registered
+pub async fn invalidate_auth_cache(app: &AppHandle) {
+    if let Some(cache) = app.try_state::<Arc<AuthCache>>() {
+        cache.invalidate().await;
+    }
+}
+
+/// Credential state changes broadcast to every window and the tray
+///
+/// Each variant is emitted under its own event name (see [`AuthEvent::name`])
+/// with the variant's fields as payload, so listeners don't have to poll
+/// `check_claude_auth`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum AuthEvent {
+    /// `auth:signed-in` - new credentials were stored or an OAuth sign-in completed
+    SignedIn {
+        source: String,
+        email: Option<String>,
+    },
+    /// `auth:signed-out` - the Anthropic credentials were removed
+    SignedOut {},
+    /// `auth:key-invalid` - a stored key was rejected by its provider
+    KeyInvalid {
+        provider: Provider,
+        message: String,
+    },
+    /// `auth:locked` - the credential vault was locked
+    Locked {},
+    /// `auth:unlocked` - the credential vault was unlocked
+    Unlocked {},
+}
+
+impl AuthEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuthEvent::SignedIn { .. } => "auth:signed-in",
+            AuthEvent::SignedOut {} => "auth:signed-out",
+            AuthEvent::KeyInvalid { .. } => "auth:key-invalid",
+            AuthEvent::Locked {} => "auth:locked",
+            AuthEvent::Unlocked {} => "auth:unlocked",
+        }
+    }
+}
+
+/// Emit an auth state change to all windows
+pub fn emit_auth_event(app: &AppHandle, event: AuthEvent) {
+    if let Err(e) = app.emit(event.name(), &event) {
+        eprintln!("Failed to emit {}: {}", event.name(), e);
+    }
+}
+
+/// Record a successful validation for a provider key
+async fn mark_validated(pool: &SqlitePool, provider: Provider) -> Result<(), String> {
+    sqlx::query("UPDATE provider_credentials SET last_validated = datetime('now') WHERE provider = ?")
+        .bind(provider.as_str())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if provider == Provider::Anthropic {
+        sqlx::query("UPDATE auth_credentials SET last_validated = datetime('now') WHERE id = 1")
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Revalidate every stored provider key once
+///
+/// Returns the providers whose keys were rejected. Network errors are not
+/// treated as expiry so a flaky connection doesn't sign the user out.
+pub async fn revalidate_stored_keys(pool: &SqlitePool) -> Result<Vec<Provider>, String> {
+    let mut rejected = Vec::new();
+
+    for provider in Provider::ALL {
+        let Ok(creds) = load_provider_key(pool, provider).await else {
+            continue;
+        };
+        // OAuth tokens are kept fresh by refreshing, not revalidation
+        if creds.oauth {
+            continue;
+        }
+
+        match validate_provider_key(provider, &creds.api_key, creds.base_url.as_deref()).await {
+            Ok(true) => mark_validated(pool, provider).await?,
+            Ok(false) => rejected.push(provider),
+            Err(e) => eprintln!("Skipping {} revalidation: {}", provider.display_name(), e),
+        }
+    }
+
+    Ok(rejected)
+}
+
+/// Spawn a background task that periodically revalidates stored keys
+///
+/// Emits `auth:key-invalid` the first time a provider's key starts failing, and
+/// again only after it has been replaced and fails anew.
+pub fn spawn_revalidation_task(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(REVALIDATION_INTERVAL);
+        let mut expired: HashSet<Provider> = HashSet::new();
+
+        loop {
+            interval.tick().await;
+
+            let pool = match crate::database::get_pool().await {
+                Ok(pool) => pool,
+                Err(e) => {
+                    eprintln!("Key revalidation skipped: {}", e);
+                    continue;
+                }
+            };
+
+            let rejected = match revalidate_stored_keys(pool.as_ref()).await {
+                Ok(rejected) => rejected,
+                Err(e) => {
+                    eprintln!("Key revalidation failed: {}", e);
+                    continue;
+                }
+            };
+
+            if !rejected.is_empty() {
+                invalidate_auth_cache(&app).await;
+            }
+
+            for provider in &rejected {
+                if expired.insert(*provider) {
+                    eprintln!("⚠️  {} API key is no longer valid", provider.display_name());
+                    emit_auth_event(&app, AuthEvent::KeyInvalid {
+                        provider: *provider,
+                        message: format!(
+                            "Your {} API key was rejected. Please sign in again.",
+                            provider.display_name()
+                        ),
+                    });
+                }
+            }
+
+            expired.retain(|provider| rejected.contains(provider));
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +913,109 @@ mod tests {
         assert_eq!(parsed.email, Some("user@example.com".to_string()));
     }
 
+    #[test]
+    fn test_parse_claude_code_credentials_file() {
+        let file = r#"{"claudeAiOauth":{"accessToken":"sk-ant-oat01-abc","refreshToken":"r","expiresAt":1,"subscriptionType":"max"}}"#;
+        let parsed = parse_claude_code_secret(file).unwrap();
+        assert_eq!(parsed.api_key, "sk-ant-oat01-abc");
+        assert_eq!(parsed.subscription_tier, Some("max".to_string()));
+
+        let bare = parse_claude_code_secret("  sk-ant-api03-xyz\n").unwrap();
+        assert_eq!(bare.api_key, "sk-ant-api03-xyz");
+
+        assert!(parse_claude_code_secret("not a key").is_none());
+    }
+
+    #[test]
+    fn test_rate_limit_headers_and_tier() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("anthropic-ratelimit-requests-limit", "1000".parse().unwrap());
+        headers.insert("anthropic-ratelimit-requests-remaining", "999".parse().unwrap());
+        headers.insert("anthropic-ratelimit-tokens-reset", "2025-01-01T00:00:00Z".parse().unwrap());
+
+        let limits = RateLimits::from_headers(&headers);
+        assert_eq!(limits.requests_limit, Some(1000));
+        assert_eq!(limits.requests_remaining, Some(999));
+        assert_eq!(limits.tokens_reset.as_deref(), Some("2025-01-01T00:00:00Z"));
+        assert_eq!(limits.input_tokens_limit, None);
+        assert_eq!(infer_tier(&limits), Some("tier-2".to_string()));
+
+        assert_eq!(infer_tier(&RateLimits::default()), None);
+    }
+
+    #[test]
+    fn test_auth_event_names_and_payloads() {
+        let event = AuthEvent::KeyInvalid {
+            provider: Provider::Anthropic,
+            message: "rejected".to_string(),
+        };
+        assert_eq!(event.name(), "auth:key-invalid");
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"provider": "anthropic", "message": "rejected"})
+        );
+        assert_eq!(AuthEvent::Locked {}.name(), "auth:locked");
+    }
+
+    #[test]
+    fn test_provider_parsing() {
+        assert_eq!(Provider::parse("anthropic").unwrap(), Provider::Anthropic);
+        assert_eq!(Provider::parse("OpenAI").unwrap(), Provider::OpenAI);
+        assert!(Provider::parse("gemini").is_err());
+        assert!(Provider::parse("unknown").is_err());
+
+        for provider in Provider::ALL {
+            assert_eq!(Provider::parse(provider.as_str()).unwrap(), provider);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_key_falls_back_to_oauth_sign_in() {
+        let temp_db = tempfile::NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        assert!(load_provider_key(&pool, Provider::Anthropic).await.is_err());
+
+        let tokens = crate::oauth::OAuthTokens {
+            access_token: "access-123".to_string(),
+            refresh_token: Some("refresh-123".to_string()),
+            expires_at: Some(chrono::Utc::now().timestamp() + 3600),
+            scope: None,
+        };
+        crate::oauth::store_tokens(&pool, &tokens).await.unwrap();
+
+        let creds = load_provider_key(&pool, Provider::Anthropic).await.unwrap();
+        assert_eq!(creds.api_key, "access-123");
+        assert!(creds.oauth);
+
+        // A stored console key wins over the sign in
+        store_credentials_in_db(&pool, "sk-ant-api03-key", None, None).await.unwrap();
+        let creds = load_provider_key(&pool, Provider::Anthropic).await.unwrap();
+        assert_eq!(creds.api_key, "sk-ant-api03-key");
+        assert!(!creds.oauth);
+    }
+
+    #[tokio::test]
+    async fn test_stale_refresh_does_not_overwrite_invalidation() {
+        let cache = AuthCache::new();
+        let status = AuthStatus {
+            authenticated: true,
+            source: "database".to_string(),
+            email: None,
+        };
+
+        // A refresh that started before the invalidation is dropped
+        let generation = cache.generation.load(Ordering::SeqCst);
+        cache.invalidate().await;
+        assert!(!cache.store_if_current(generation, status.clone()).await);
+        assert!(cache.entry.lock().await.is_none());
+
+        let generation = cache.generation.load(Ordering::SeqCst);
+        assert!(cache.store_if_current(generation, status).await);
+        assert!(cache.entry.lock().await.is_some());
+    }
+
     #[tokio::test]
     async fn test_invalid_api_key() {
         let result = validate_api_key("invalid-key").await;
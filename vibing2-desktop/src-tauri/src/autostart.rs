@@ -0,0 +1,108 @@
+//! Launch at login
+//!
+//! The login item is registered with the OS by the autostart plugin (a
+//! launch agent on macOS, the Run registry key on Windows, an XDG autostart
+//! entry on Linux) and launches the app with [`LAUNCH_ARG`]. With "start
+//! minimized" on, those launches hide the main window so the app starts in
+//! the tray.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::Manager;
+use tauri_plugin_autostart::ManagerExt;
+
+/// Argument the login item launches the app with
+pub const LAUNCH_ARG: &str = "--autostart";
+
+/// Setting for starting in the tray when launched at login
+pub const MINIMIZED_SETTING: &str = "start_minimized";
+
+/// Launch-at-login state
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    pub start_minimized: bool,
+}
+
+/// Whether the app is registered to launch at login
+pub fn is_enabled(app: &tauri::AppHandle) -> bool {
+    app.autolaunch().is_enabled().unwrap_or(false)
+}
+
+/// Register or remove the login item
+pub fn set_enabled(app: &tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let autolaunch = app.autolaunch();
+    let result = if enabled { autolaunch.enable() } else { autolaunch.disable() };
+    result.map_err(|e| format!("Failed to update launch at login: {}", e))
+}
+
+/// Whether launches at login start in the tray
+pub async fn start_minimized(pool: &SqlitePool) -> bool {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(MINIMIZED_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.as_deref() == Some("true")
+}
+
+pub async fn set_start_minimized(pool: &SqlitePool, minimized: bool) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        INSERT INTO settings (id, key, value, updated_at)
+        VALUES (?, ?, ?, datetime('now'))
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(format!("setting-{}", MINIMIZED_SETTING))
+    .bind(MINIMIZED_SETTING)
+    .bind(minimized.to_string())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save setting {}: {}", MINIMIZED_SETTING, e))?;
+    Ok(())
+}
+
+pub async fn status(app: &tauri::AppHandle, pool: &SqlitePool) -> AutostartStatus {
+    AutostartStatus {
+        enabled: is_enabled(app),
+        start_minimized: start_minimized(pool).await,
+    }
+}
+
+/// Whether this process was started by the login item
+pub fn launched_at_login() -> bool {
+    std::env::args().any(|arg| arg == LAUNCH_ARG)
+}
+
+/// Hide the main window when launched at login with "start minimized" on
+pub async fn apply_on_launch(app: &tauri::AppHandle, pool: &SqlitePool) {
+    if !launched_at_login() || !start_minimized(pool).await {
+        return;
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.hide();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_start_minimized_setting() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        assert!(!start_minimized(&pool).await);
+
+        set_start_minimized(&pool, true).await.unwrap();
+        assert!(start_minimized(&pool).await);
+
+        set_start_minimized(&pool, false).await.unwrap();
+        assert!(!start_minimized(&pool).await);
+    }
+}
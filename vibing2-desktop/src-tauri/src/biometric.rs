@@ -0,0 +1,125 @@
+//! OS user-presence verification
+//!
+//! Gates sensitive actions (revealing a stored API key) behind Touch ID /
+//! the login password on macOS and Windows Hello on Windows. Platforms
+//! without a native prompt fall back to the credential vault: an unlocked
+//! vault means the user already entered their passphrase.
+
+/// Ask the OS to verify the user is present
+///
+/// Blocks until the user completes or cancels the system prompt, so call it
+/// from `spawn_blocking` in async code.
+pub fn verify_user_presence(reason: &str) -> Result<(), String> {
+    platform::verify(reason)
+}
+
+/// Async wrapper around [`verify_user_presence`]
+pub async fn require_user_presence(reason: &str) -> Result<(), String> {
+    let reason = reason.to_string();
+    tokio::task::spawn_blocking(move || verify_user_presence(&reason))
+        .await
+        .map_err(|e| format!("Verification task failed: {}", e))?
+}
+
+/// Partially mask a secret for display, keeping its prefix and last 4 chars
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 14 {
+        return "•".repeat(chars.len());
+    }
+
+    let prefix: String = chars[..10].iter().collect();
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", prefix, suffix)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2_foundation::{NSError, NSString};
+    use objc2_local_authentication::{LAContext, LAPolicy};
+    use std::sync::mpsc;
+
+    pub fn verify(reason: &str) -> Result<(), String> {
+        let (tx, rx) = mpsc::channel();
+
+        let reply = RcBlock::new(move |success: Bool, error: *mut NSError| {
+            let result = if success.as_bool() {
+                Ok(())
+            } else if error.is_null() {
+                Err("Verification failed".to_string())
+            } else {
+                Err(unsafe { (*error).localizedDescription() }.to_string())
+            };
+            let _ = tx.send(result);
+        });
+
+        // The prompt is cancelled if its context is released, so the context
+        // lives until the reply arrives
+        let context = unsafe { LAContext::new() };
+
+        // DeviceOwnerAuthentication allows Touch ID with a password fallback
+        unsafe {
+            context.evaluatePolicy_localizedReason_reply(
+                LAPolicy::DeviceOwnerAuthentication,
+                &NSString::from_str(reason),
+                &reply,
+            );
+        }
+
+        let result = rx.recv().map_err(|_| "Verification was interrupted".to_string());
+        drop(context);
+        result?
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+
+    pub fn verify(reason: &str) -> Result<(), String> {
+        let availability = UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|op| op.get())
+            .map_err(|e| format!("Windows Hello unavailable: {}", e))?;
+
+        if availability != UserConsentVerifierAvailability::Available {
+            return Err("Windows Hello is not set up on this device".to_string());
+        }
+
+        let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))
+            .and_then(|op| op.get())
+            .map_err(|e| format!("Windows Hello failed: {}", e))?;
+
+        if result == UserConsentVerificationResult::Verified {
+            Ok(())
+        } else {
+            Err("Verification was cancelled".to_string())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+    pub fn verify(_reason: &str) -> Result<(), String> {
+        if crate::vault::is_unlocked() {
+            Ok(())
+        } else {
+            Err("Enable and unlock the credential lock to reveal keys on this platform".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_secret() {
+        assert_eq!(mask_secret("sk-ant-REDACTED"), "sk-ant-api...mnop");
+        assert_eq!(mask_secret("short"), "•••••");
+    }
+}
@@ -0,0 +1,366 @@
+//! Daily token and cost budgets for agents and projects
+//!
+//! An agent or project can cap the tokens and the USD cost its generations
+//! use per UTC day. Consumption is summed from the usage ledger, counting
+//! from the later of midnight and the last reset. [`check`] refuses a
+//! generation once a budget is spent, and the [`Remaining`] it returns lets
+//! the generation be cut off when it runs out midway.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+use crate::llm::LlmError;
+use crate::usage::{self, ModelPrice};
+
+/// What a budget applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetScope {
+    Agent,
+    Project,
+}
+
+impl BudgetScope {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "agent" => Ok(BudgetScope::Agent),
+            "project" => Ok(BudgetScope::Project),
+            other => Err(format!("Unknown budget scope: {}", other)),
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            BudgetScope::Agent => "agents",
+            BudgetScope::Project => "projects",
+        }
+    }
+
+    fn usage_column(self) -> &'static str {
+        match self {
+            BudgetScope::Agent => "agent_id",
+            BudgetScope::Project => "project_id",
+        }
+    }
+}
+
+/// Daily limits; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    pub max_tokens_per_day: Option<i64>,
+    pub max_cost_per_day: Option<f64>,
+}
+
+impl Budget {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tokens_per_day.is_none() && self.max_cost_per_day.is_none()
+    }
+}
+
+/// Tokens and cost used since `since`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Consumption {
+    pub tokens: i64,
+    /// Cost of the priced models used; unpriced models count as free
+    pub cost_usd: f64,
+    /// Midnight UTC, or the last reset if later
+    pub since: String,
+}
+
+/// A budget and how much of it is used
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetStatus {
+    pub scope: BudgetScope,
+    pub id: String,
+    pub budget: Budget,
+    pub consumption: Consumption,
+    pub exceeded: bool,
+}
+
+impl BudgetStatus {
+    fn remaining(&self) -> Remaining {
+        Remaining {
+            tokens: self
+                .budget
+                .max_tokens_per_day
+                .map(|max| (max - self.consumption.tokens).max(0)),
+            cost_usd: self
+                .budget
+                .max_cost_per_day
+                .map(|max| (max - self.consumption.cost_usd).max(0.0)),
+        }
+    }
+}
+
+/// What a generation may still use before a budget runs out
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Remaining {
+    pub tokens: Option<i64>,
+    pub cost_usd: Option<f64>,
+}
+
+impl Remaining {
+    fn min(self, other: Remaining) -> Remaining {
+        fn least<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+                (a, b) => a.or(b),
+            }
+        }
+        Remaining {
+            tokens: least(self.tokens, other.tokens),
+            cost_usd: least(self.cost_usd, other.cost_usd),
+        }
+    }
+
+    /// Whether a generation that has used `input` and `output` tokens of a
+    /// model priced at `price` is over
+    pub fn exceeded_by(&self, input: u32, output: u32, price: Option<ModelPrice>) -> bool {
+        let over_tokens = self
+            .tokens
+            .is_some_and(|tokens| i64::from(input) + i64::from(output) > tokens);
+        let over_cost = match (self.cost_usd, price) {
+            (Some(cost_usd), Some(price)) => price.cost(input, output) > cost_usd,
+            _ => false,
+        };
+        over_tokens || over_cost
+    }
+}
+
+/// The budget of an agent or project; `None` if there's no such one
+pub async fn get(pool: &SqlitePool, scope: BudgetScope, id: &str) -> Result<Option<Budget>, sqlx::Error> {
+    let row = sqlx::query(&format!(
+        "SELECT daily_token_budget, daily_cost_budget FROM {} WHERE id = ?",
+        scope.table()
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| Budget {
+        max_tokens_per_day: row.get("daily_token_budget"),
+        max_cost_per_day: row.get("daily_cost_budget"),
+    }))
+}
+
+/// Set the budget of an agent or project
+pub async fn set(pool: &SqlitePool, scope: BudgetScope, id: &str, budget: Budget) -> Result<(), String> {
+    if budget.max_tokens_per_day.is_some_and(|tokens| tokens < 0) || budget.max_cost_per_day.is_some_and(|cost| cost < 0.0) {
+        return Err("Budgets can't be negative".to_string());
+    }
+
+    let result = sqlx::query(&format!(
+        "UPDATE {} SET daily_token_budget = ?, daily_cost_budget = ? WHERE id = ?",
+        scope.table()
+    ))
+    .bind(budget.max_tokens_per_day)
+    .bind(budget.max_cost_per_day)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("{} not found: {}", scope_name(scope), id));
+    }
+    Ok(())
+}
+
+/// Start counting consumption afresh from now
+pub async fn reset(pool: &SqlitePool, scope: BudgetScope, id: &str) -> Result<(), String> {
+    let result = sqlx::query(&format!("UPDATE {} SET budget_reset_at = ? WHERE id = ?", scope.table()))
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("{} not found: {}", scope_name(scope), id));
+    }
+    Ok(())
+}
+
+/// An agent's or project's budget and today's consumption
+pub async fn status(pool: &SqlitePool, scope: BudgetScope, id: &str) -> Result<BudgetStatus, String> {
+    let row = sqlx::query(&format!(
+        "SELECT daily_token_budget, daily_cost_budget, budget_reset_at FROM {} WHERE id = ?",
+        scope.table()
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("{} not found: {}", scope_name(scope), id))?;
+
+    let budget = Budget {
+        max_tokens_per_day: row.get("daily_token_budget"),
+        max_cost_per_day: row.get("daily_cost_budget"),
+    };
+    let midnight = chrono::Utc::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+        .to_rfc3339();
+    let since = match row.get::<Option<String>, _>("budget_reset_at") {
+        Some(reset_at) if reset_at > midnight => reset_at,
+        _ => midnight,
+    };
+
+    let consumption = consumption(pool, scope, id, since)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let exceeded = budget
+        .max_tokens_per_day
+        .is_some_and(|max| consumption.tokens >= max)
+        || budget.max_cost_per_day.is_some_and(|max| consumption.cost_usd >= max);
+
+    Ok(BudgetStatus {
+        scope,
+        id: id.to_string(),
+        budget,
+        consumption,
+        exceeded,
+    })
+}
+
+async fn consumption(pool: &SqlitePool, scope: BudgetScope, id: &str, since: String) -> Result<Consumption, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT model, SUM(input_tokens) AS input_tokens, SUM(output_tokens) AS output_tokens
+        FROM usage_events
+        WHERE {} = ? AND created_at >= ?
+        GROUP BY model
+        "#,
+        scope.usage_column()
+    ))
+    .bind(id)
+    .bind(&since)
+    .fetch_all(pool)
+    .await?;
+
+    let mut consumption = Consumption {
+        since,
+        ..Consumption::default()
+    };
+    for row in rows {
+        let model: String = row.get("model");
+        let input: i64 = row.get("input_tokens");
+        let output: i64 = row.get("output_tokens");
+        consumption.tokens += input + output;
+        if let Some(price) = usage::price(&model) {
+            consumption.cost_usd += price.cost(input as u32, output as u32);
+        }
+    }
+    Ok(consumption)
+}
+
+/// Refuse a generation for an agent or project whose budget is spent
+///
+/// Returns what the generation may use before one of the budgets runs out.
+pub async fn check(pool: &SqlitePool, project_id: Option<&str>, agent_id: Option<&str>) -> Result<Remaining, LlmError> {
+    let scopes = [(BudgetScope::Project, project_id), (BudgetScope::Agent, agent_id)];
+
+    let mut remaining = Remaining::default();
+    for (scope, id) in scopes {
+        let Some(id) = id else {
+            continue;
+        };
+        let budget = get(pool, scope, id)
+            .await
+            .map_err(|e| LlmError::Config(format!("Database error: {}", e)))?;
+        if budget.is_none_or(|budget| budget.is_unlimited()) {
+            continue;
+        }
+
+        let status = status(pool, scope, id).await.map_err(LlmError::Config)?;
+        if status.exceeded {
+            return Err(LlmError::BudgetExceeded(exceeded_message(&status)));
+        }
+        remaining = remaining.min(status.remaining());
+    }
+    Ok(remaining)
+}
+
+/// Why a generation was refused or cut off
+pub fn exceeded_message(status: &BudgetStatus) -> String {
+    let limit = match (status.budget.max_tokens_per_day, status.budget.max_cost_per_day) {
+        (Some(tokens), Some(cost)) => format!("{} tokens or ${:.2}", tokens, cost),
+        (Some(tokens), None) => format!("{} tokens", tokens),
+        (None, Some(cost)) => format!("${:.2}", cost),
+        (None, None) => "nothing".to_string(),
+    };
+    format!(
+        "The {} {}'s daily budget of {} is used up; it renews at midnight UTC or when reset",
+        scope_name(status.scope).to_lowercase(),
+        status.id,
+        limit
+    )
+}
+
+fn scope_name(scope: BudgetScope) -> &'static str {
+    match scope {
+        BudgetScope::Agent => "Agent",
+        BudgetScope::Project => "Project",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::Usage;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_budget_is_enforced_until_reset() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Site', 'website', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(check(&pool, Some("p1"), Some("ui-designer")).await.unwrap(), Remaining::default());
+
+        let budget = Budget {
+            max_tokens_per_day: Some(1_000),
+            max_cost_per_day: None,
+        };
+        set(&pool, BudgetScope::Project, "p1", budget).await.unwrap();
+        assert!(set(&pool, BudgetScope::Agent, "missing", budget).await.is_err());
+
+        let usage = Usage {
+            input_tokens: 400,
+            output_tokens: 200,
+            model: "claude-sonnet-4-5".to_string(),
+            stop_reason: "end_turn".to_string(),
+        };
+        usage::record(&pool, "local-user", Some("p1"), Some("ui-designer"), &usage).await.unwrap();
+        let remaining = check(&pool, Some("p1"), None).await.unwrap();
+        assert_eq!(remaining.tokens, Some(400));
+        assert!(remaining.exceeded_by(300, 101, None));
+
+        usage::record(&pool, "local-user", Some("p1"), None, &usage).await.unwrap();
+        let error = check(&pool, Some("p1"), Some("ui-designer")).await.unwrap_err();
+        assert!(matches!(error, LlmError::BudgetExceeded(_)));
+
+        // The agent's budget counts only its own generations
+        let agent_budget = Budget {
+            max_tokens_per_day: None,
+            max_cost_per_day: Some(1.0),
+        };
+        set(&pool, BudgetScope::Agent, "ui-designer", agent_budget).await.unwrap();
+        let agent = status(&pool, BudgetScope::Agent, "ui-designer").await.unwrap();
+        assert_eq!(agent.consumption.tokens, 600);
+        assert!((agent.consumption.cost_usd - 0.0042).abs() < 1e-9);
+        assert!(!agent.exceeded);
+
+        reset(&pool, BudgetScope::Project, "p1").await.unwrap();
+        let project = status(&pool, BudgetScope::Project, "p1").await.unwrap();
+        assert_eq!(project.consumption.tokens, 0);
+        assert!(check(&pool, Some("p1"), Some("ui-designer")).await.is_ok());
+    }
+}
@@ -9,7 +9,11 @@ pub struct Project {
     pub name: String,
     pub description: Option<String>,
     pub project_type: String,
-    pub active_agents: String,
+    /// IDs of the project's agents, in order
+    pub active_agents: Vec<String>,
+    /// Team last applied to the project
+    #[serde(default)]
+    pub team_id: Option<String>,
     pub current_code: Option<String>,
     pub visibility: String,
     pub user_id: String,
@@ -23,7 +27,11 @@ pub struct ProjectWithMessages {
     pub name: String,
     pub description: Option<String>,
     pub project_type: String,
-    pub active_agents: String,
+    /// IDs of the project's agents, in order
+    pub active_agents: Vec<String>,
+    /// Team last applied to the project
+    #[serde(default)]
+    pub team_id: Option<String>,
     pub current_code: Option<String>,
     pub visibility: String,
     pub user_id: String,
@@ -37,7 +45,9 @@ pub struct SaveProjectRequest {
     pub project_id: Option<String>,
     pub name: String,
     pub project_type: String,
-    pub active_agents: String,
+    /// Replaces the project's agents when given
+    #[serde(default)]
+    pub active_agents: Option<Vec<String>>,
     pub messages: Vec<Message>,
     pub current_code: Option<String>,
 }
@@ -47,6 +57,15 @@ pub struct Message {
     pub id: String,
     pub role: String,
     pub content: String,
+    /// Agent that produced an assistant message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    /// Prompt a reply from an agent comparison answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// `pending` until a comparison reply is picked, then `rejected` for the others
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_status: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -55,6 +74,116 @@ pub struct Settings {
     pub theme: String,
     pub auto_save: bool,
     pub default_project_path: String,
+    /// Require TOTP codes when signing in to the embedded server
+    #[serde(default)]
+    pub server_two_factor: bool,
+    /// Start the embedded server when the app launches
+    #[serde(default)]
+    pub server_autostart: bool,
+    /// Let other machines on the network reach the embedded server
+    #[serde(default)]
+    pub server_lan_mode: bool,
+    /// Address to bind in LAN mode; all interfaces when unset
+    #[serde(default)]
+    pub server_bind_address: Option<String>,
+    /// `tcp`, or `local` to serve over a Unix socket / named pipe only
+    #[serde(default)]
+    pub server_transport: Option<String>,
+    /// Port the embedded server tries first; updated to the port it last used
+    #[serde(default)]
+    pub server_port: Option<u16>,
+    /// Extra origins allowed to call the embedded server
+    #[serde(default)]
+    pub server_cors_origins: Vec<String>,
+    /// Export server and command spans over OTLP
+    #[serde(default)]
+    pub telemetry_enabled: bool,
+    /// OTLP/gRPC collector endpoint
+    #[serde(default)]
+    pub telemetry_endpoint: Option<String>,
+    /// Fraction of traces to export (0.0 - 1.0)
+    #[serde(default)]
+    pub telemetry_sample_ratio: Option<f64>,
+    /// Allow credentials on cross-origin requests to the embedded server
+    #[serde(default)]
+    pub server_cors_credentials: bool,
+    /// Attempts per provider before a generation fails or fails over
+    #[serde(default)]
+    pub llm_max_attempts: Option<u32>,
+    /// Provider to fail over to when the primary keeps failing
+    #[serde(default)]
+    pub llm_failover_provider: Option<String>,
+    /// Model for the failover provider (its default when unset)
+    #[serde(default)]
+    pub llm_failover_model: Option<String>,
+    /// Ollama daemon URL or `host:port`; `OLLAMA_HOST` or the local default
+    /// when unset
+    #[serde(default)]
+    pub ollama_base_url: Option<String>,
+    /// Write raw provider streams to disk so sessions can be replayed
+    #[serde(default)]
+    pub llm_record_streams: bool,
+    /// Signed agent registry to check for new and updated agents
+    #[serde(default)]
+    pub agent_registry_url: Option<String>,
+    /// The registry's base64 Ed25519 public key
+    #[serde(default)]
+    pub agent_registry_key: Option<String>,
+    /// UI language tag (e.g. `es`, `pt-BR`); agent names follow it
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// What the system tray menu shows
+    #[serde(default)]
+    pub tray_layout: crate::tray::TrayLayout,
+    /// Proxy for outbound requests (`http://`, `https://`, `socks5://`)
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    #[serde(default)]
+    pub proxy_username: Option<String>,
+    /// Comma-separated hosts to reach without the proxy
+    #[serde(default)]
+    pub proxy_no_proxy: Option<String>,
+    /// When background tasks pause to save power
+    #[serde(default)]
+    pub low_power_mode: crate::power::LowPowerMode,
+}
+
+/// Changes to settings; omitted fields are left as they are, and an empty
+/// string clears a text setting
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SettingsUpdate {
+    pub anthropic_api_key: Option<String>,
+    pub theme: Option<String>,
+    pub auto_save: Option<bool>,
+    pub default_project_path: Option<String>,
+    pub server_two_factor: Option<bool>,
+    pub server_autostart: Option<bool>,
+    pub server_lan_mode: Option<bool>,
+    pub server_bind_address: Option<String>,
+    pub server_transport: Option<String>,
+    /// `0` clears the saved port
+    pub server_port: Option<u16>,
+    pub server_cors_origins: Option<Vec<String>>,
+    pub server_cors_credentials: Option<bool>,
+    pub telemetry_enabled: Option<bool>,
+    pub telemetry_endpoint: Option<String>,
+    pub telemetry_sample_ratio: Option<f64>,
+    pub llm_max_attempts: Option<u32>,
+    pub llm_failover_provider: Option<String>,
+    pub llm_failover_model: Option<String>,
+    pub ollama_base_url: Option<String>,
+    pub llm_record_streams: Option<bool>,
+    pub agent_registry_url: Option<String>,
+    pub agent_registry_key: Option<String>,
+    pub locale: Option<String>,
+    pub tray_layout: Option<crate::tray::TrayLayout>,
+    pub proxy_url: Option<String>,
+    pub proxy_username: Option<String>,
+    /// New proxy password; never loaded back
+    pub proxy_password: Option<String>,
+    pub proxy_no_proxy: Option<String>,
+    pub low_power_mode: Option<crate::power::LowPowerMode>,
 }
 
 /// Generate a CUID-like ID using timestamp
@@ -72,12 +201,14 @@ fn generate_id(prefix: &str) -> String {
 
 /// Simple greeting command for testing
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub fn greet(name: &str) -> String {
     format!("Hello, {}! Welcome to Vibing2 Desktop.", name)
 }
 
 /// Save a project to the local database
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn save_project(request: SaveProjectRequest) -> Result<String, String> {
     let pool = crate::database::get_pool()
         .await
@@ -102,14 +233,23 @@ pub async fn save_project(request: SaveProjectRequest) -> Result<String, String>
     .await
     .map_err(|e| format!("Failed to check existing project: {}", e))?;
 
+    let mut message_agents = std::collections::HashMap::new();
+    let mut message_branches = std::collections::HashMap::new();
     if existing.is_some() {
+        crate::sharing::require_access(
+            pool.as_ref(),
+            &project_id,
+            crate::sharing::Principal::desktop(),
+            crate::sharing::Access::Write,
+        )
+        .await?;
+
         // Update existing project
         sqlx::query(
             r#"
             UPDATE projects
             SET name = ?,
                 project_type = ?,
-                active_agents = ?,
                 current_code = ?,
                 updated_at = ?
             WHERE id = ?
@@ -117,7 +257,6 @@ pub async fn save_project(request: SaveProjectRequest) -> Result<String, String>
         )
         .bind(&request.name)
         .bind(&request.project_type)
-        .bind(&request.active_agents)
         .bind(&request.current_code)
         .bind(&now)
         .bind(&project_id)
@@ -125,6 +264,26 @@ pub async fn save_project(request: SaveProjectRequest) -> Result<String, String>
         .await
         .map_err(|e| format!("Failed to update project: {}", e))?;
 
+        // Keep agent attribution and comparison branches for messages saved without them
+        let rows = sqlx::query(
+            "SELECT id, agent_id, parent_id, branch_status FROM messages \
+             WHERE project_id = ? AND (agent_id IS NOT NULL OR parent_id IS NOT NULL OR branch_status IS NOT NULL)",
+        )
+        .bind(&project_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to load message agents: {}", e))?;
+        for row in rows {
+            let id: String = row.get("id");
+            if let Some(agent_id) = row.get::<Option<String>, _>("agent_id") {
+                message_agents.insert(id.clone(), agent_id);
+            }
+            message_branches.insert(
+                id,
+                (row.get::<Option<String>, _>("parent_id"), row.get::<Option<String>, _>("branch_status")),
+            );
+        }
+
         // Delete existing messages for this project
         sqlx::query("DELETE FROM messages WHERE project_id = ?")
             .bind(&project_id)
@@ -137,14 +296,13 @@ pub async fn save_project(request: SaveProjectRequest) -> Result<String, String>
         // Insert new project
         sqlx::query(
             r#"
-            INSERT INTO projects (id, name, project_type, active_agents, current_code, user_id, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, 'local-user', ?, ?)
+            INSERT INTO projects (id, name, project_type, current_code, user_id, created_at, updated_at)
+            VALUES (?, ?, ?, ?, 'local-user', ?, ?)
             "#
         )
         .bind(&project_id)
         .bind(&request.name)
         .bind(&request.project_type)
-        .bind(&request.active_agents)
         .bind(&request.current_code)
         .bind(&now)
         .bind(&now)
@@ -155,18 +313,29 @@ pub async fn save_project(request: SaveProjectRequest) -> Result<String, String>
         println!("📝 Created new project: {}", project_id);
     }
 
+    if let Some(agent_ids) = &request.active_agents {
+        crate::project_agents::set(&mut *tx, &project_id, agent_ids).await?;
+    }
+
     // Insert messages
     for message in &request.messages {
+        let (parent_id, branch_status) = match (&message.parent_id, &message.branch_status) {
+            (None, None) => message_branches.get(&message.id).cloned().unwrap_or_default(),
+            (parent_id, branch_status) => (parent_id.clone(), branch_status.clone()),
+        };
         sqlx::query(
             r#"
-            INSERT INTO messages (id, role, content, project_id, created_at)
-            VALUES (?, ?, ?, ?, ?)
+            INSERT INTO messages (id, role, content, project_id, agent_id, parent_id, branch_status, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(&message.id)
         .bind(&message.role)
         .bind(&message.content)
         .bind(&project_id)
+        .bind(message.agent_id.as_ref().or(message_agents.get(&message.id)))
+        .bind(parent_id)
+        .bind(branch_status)
         .bind(&now)
         .execute(&mut *tx)
         .await
@@ -178,21 +347,35 @@ pub async fn save_project(request: SaveProjectRequest) -> Result<String, String>
         .await
         .map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
+    // Keep the revision for diffs, unless nothing changed
+    if let Err(e) = crate::snapshots::record(pool.as_ref(), &project_id, crate::snapshots::SnapshotKind::Save).await {
+        eprintln!("Failed to snapshot project {}: {}", project_id, e);
+    }
+
     println!("✅ Project saved successfully: {}", project_id);
     Ok(project_id)
 }
 
 /// Load a project from the local database
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn load_project(project_id: String) -> Result<ProjectWithMessages, String> {
     let pool = crate::database::get_pool()
         .await
         .map_err(|e| format!("Failed to get database pool: {}", e))?;
 
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Read,
+    )
+    .await?;
+
     // Fetch project
     let row = sqlx::query(
         r#"
-        SELECT id, name, description, project_type, active_agents, current_code,
+        SELECT id, name, description, project_type, team_id, current_code,
                visibility, user_id, created_at, updated_at
         FROM projects
         WHERE id = ?
@@ -204,12 +387,17 @@ pub async fn load_project(project_id: String) -> Result<ProjectWithMessages, Str
     .map_err(|e| format!("Failed to fetch project: {}", e))?
     .ok_or_else(|| format!("Project not found: {}", project_id))?;
 
+    let active_agents = crate::project_agents::list(pool.as_ref(), &project_id)
+        .await
+        .map_err(|e| format!("Failed to fetch project agents: {}", e))?;
+
     let project = Project {
         id: row.get("id"),
         name: row.get("name"),
         description: row.get("description"),
         project_type: row.get("project_type"),
-        active_agents: row.get("active_agents"),
+        active_agents,
+        team_id: row.get("team_id"),
         current_code: row.get("current_code"),
         visibility: row.get("visibility"),
         user_id: row.get("user_id"),
@@ -220,7 +408,7 @@ pub async fn load_project(project_id: String) -> Result<ProjectWithMessages, Str
     // Fetch messages
     let message_rows = sqlx::query(
         r#"
-        SELECT id, role, content
+        SELECT id, role, content, agent_id, parent_id, branch_status
         FROM messages
         WHERE project_id = ?
         ORDER BY created_at ASC
@@ -237,6 +425,9 @@ pub async fn load_project(project_id: String) -> Result<ProjectWithMessages, Str
             id: row.get("id"),
             role: row.get("role"),
             content: row.get("content"),
+            agent_id: row.get("agent_id"),
+            parent_id: row.get("parent_id"),
+            branch_status: row.get("branch_status"),
         })
         .collect();
 
@@ -248,6 +439,7 @@ pub async fn load_project(project_id: String) -> Result<ProjectWithMessages, Str
         description: project.description,
         project_type: project.project_type,
         active_agents: project.active_agents,
+        team_id: project.team_id,
         current_code: project.current_code,
         visibility: project.visibility,
         user_id: project.user_id,
@@ -257,8 +449,9 @@ pub async fn load_project(project_id: String) -> Result<ProjectWithMessages, Str
     })
 }
 
-/// List all projects for the local user
+/// List all projects owned by or shared with the local user
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn list_projects() -> Result<Vec<Project>, String> {
     let pool = crate::database::get_pool()
         .await
@@ -266,10 +459,14 @@ pub async fn list_projects() -> Result<Vec<Project>, String> {
 
     let rows = sqlx::query(
         r#"
-        SELECT id, name, description, project_type, active_agents, current_code,
+        SELECT id, name, description, project_type, team_id, current_code,
                visibility, user_id, created_at, updated_at
         FROM projects
         WHERE user_id = 'local-user'
+           OR id IN (
+               SELECT project_id FROM project_shares
+               WHERE grantee_type = 'user' AND grantee_id = 'local-user'
+           )
         ORDER BY updated_at DESC
         "#
     )
@@ -277,21 +474,27 @@ pub async fn list_projects() -> Result<Vec<Project>, String> {
     .await
     .map_err(|e| format!("Failed to fetch projects: {}", e))?;
 
-    let projects: Vec<Project> = rows
-        .iter()
-        .map(|row| Project {
-            id: row.get("id"),
+    let mut projects: Vec<Project> = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let id: String = row.get("id");
+        let active_agents = crate::project_agents::list(pool.as_ref(), &id)
+            .await
+            .map_err(|e| format!("Failed to fetch project agents: {}", e))?;
+
+        projects.push(Project {
+            id,
             name: row.get("name"),
             description: row.get("description"),
             project_type: row.get("project_type"),
-            active_agents: row.get("active_agents"),
+            active_agents,
+            team_id: row.get("team_id"),
             current_code: row.get("current_code"),
             visibility: row.get("visibility"),
             user_id: row.get("user_id"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
-        })
-        .collect();
+        });
+    }
 
     println!("📋 Listed {} projects", projects.len());
     Ok(projects)
@@ -299,11 +502,20 @@ pub async fn list_projects() -> Result<Vec<Project>, String> {
 
 /// Delete a project from the local database
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn delete_project(project_id: String) -> Result<(), String> {
     let pool = crate::database::get_pool()
         .await
         .map_err(|e| format!("Failed to get database pool: {}", e))?;
 
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
     // SQLite CASCADE will automatically delete messages and files
     let result = sqlx::query("DELETE FROM projects WHERE id = ?")
         .bind(&project_id)
@@ -315,174 +527,2497 @@ pub async fn delete_project(project_id: String) -> Result<(), String> {
         return Err(format!("Project not found: {}", project_id));
     }
 
+    crate::file_sync::unwatch(&project_id);
     println!("🗑️  Deleted project: {}", project_id);
     Ok(())
 }
 
-/// Save settings to local storage
+// ============================================================================
+// Project Sharing Commands
+// ============================================================================
+
+/// Share a project with another local user or a paired device
+/// `grantee_type` is "user" or "device"; `access` is "read" or "write"
 #[tauri::command]
-pub async fn save_settings(settings: Settings) -> Result<(), String> {
+#[tracing::instrument(skip_all)]
+pub async fn share_project(
+    project_id: String,
+    grantee_type: String,
+    grantee_id: String,
+    access: String,
+) -> Result<crate::sharing::ProjectShare, String> {
+    let grantee_type = crate::sharing::Grantee::parse(&grantee_type)?;
+    let access = crate::sharing::Access::parse(&access)?;
+
     let pool = crate::database::get_pool()
         .await
-        .map_err(|e| format!("Failed to get database pool: {}", e))?;
+        .map_err(|e| format!("Database error: {}", e))?;
 
-    let now = Utc::now().to_rfc3339();
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
 
-    // Upsert each setting
-    let settings_map = vec![
-        (
-            "anthropic_api_key",
-            settings.anthropic_api_key.unwrap_or_default(),
-        ),
-        ("theme", settings.theme),
-        ("auto_save", settings.auto_save.to_string()),
-        ("default_project_path", settings.default_project_path),
-    ];
+    let share = crate::sharing::share_project(pool.as_ref(), &project_id, grantee_type, &grantee_id, access).await?;
 
-    for (key, value) in settings_map {
-        sqlx::query(
-            r#"
-            INSERT INTO settings (id, key, value, updated_at)
-            VALUES (?, ?, ?, ?)
-            ON CONFLICT(key) DO UPDATE SET value = ?, updated_at = ?
-            "#
-        )
-        .bind(generate_id("setting"))
-        .bind(key)
-        .bind(&value)
-        .bind(&now)
-        .bind(&value)
-        .bind(&now)
-        .execute(pool.as_ref())
+    println!("🤝 Shared project {} with {} {} ({})", project_id, grantee_type.as_str(), grantee_id, access.as_str());
+    Ok(share)
+}
+
+/// Remove a project share
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn unshare_project(project_id: String, share_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
         .await
-        .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let removed = crate::sharing::unshare_project(pool.as_ref(), &project_id, &share_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if !removed {
+        return Err(format!("Share not found: {}", share_id));
     }
 
-    println!("⚙️  Settings saved successfully");
     Ok(())
 }
 
-/// Load settings from local storage
+/// List who a project is shared with
 #[tauri::command]
-pub async fn load_settings() -> Result<Settings, String> {
+#[tracing::instrument(skip_all)]
+pub async fn list_project_shares(project_id: String) -> Result<Vec<crate::sharing::ProjectShare>, String> {
     let pool = crate::database::get_pool()
         .await
-        .map_err(|e| format!("Failed to get database pool: {}", e))?;
+        .map_err(|e| format!("Database error: {}", e))?;
 
-    let rows = sqlx::query("SELECT key, value FROM settings")
-        .fetch_all(pool.as_ref())
+    crate::sharing::list_shares(pool.as_ref(), &project_id)
         .await
-        .map_err(|e| format!("Failed to fetch settings: {}", e))?;
+        .map_err(|e| format!("Database error: {}", e))
+}
 
-    let mut anthropic_api_key: Option<String> = None;
-    let mut theme = String::from("dark");
-    let mut auto_save = true;
-    let mut default_project_path = String::from("~/Documents/Vibing2Projects");
+// ============================================================================
+// Project Workspace Commands
+// ============================================================================
 
-    for row in rows {
-        let key: String = row.get("key");
-        let value: String = row.get("value");
+/// Run a build or test command on a project's files in its workspace
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn run_project_command(
+    project_id: String,
+    command: String,
+    cwd: Option<String>,
+    timeout_secs: Option<u64>,
+) -> Result<crate::sandbox::CommandOutput, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
 
-        match key.as_str() {
-            "anthropic_api_key" => {
-                if !value.is_empty() {
-                    anthropic_api_key = Some(value);
-                }
-            }
-            "theme" => theme = value,
-            "auto_save" => auto_save = value.parse().unwrap_or(true),
-            "default_project_path" => default_project_path = value,
-            _ => {}
-        }
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    let root = crate::sandbox::materialize(pool.as_ref(), &project_id).await?;
+    let request = crate::sandbox::CommandRequest { command, cwd, timeout_secs };
+    let output = crate::sandbox::run(&root, &request).await?;
+
+    println!("🛠️  Ran `{}` in project {} ({} ms)", output.command, project_id, output.duration_ms);
+    Ok(output)
+}
+
+/// Write a project's files to a folder on disk, by default under `default_project_path`
+/// Later writes to the same folder only touch what changed, and edits made there sync back
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn materialize_project(
+    app: tauri::AppHandle,
+    project_id: String,
+    target_dir: Option<String>,
+    on_conflict: Option<crate::scaffold::ConflictPolicy>,
+) -> Result<crate::scaffold::MaterializeReport, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    let root = match target_dir.as_deref().map(str::trim).filter(|dir| !dir.is_empty()) {
+        Some(dir) => crate::scaffold::expand_home(dir),
+        None => crate::scaffold::default_target(pool.as_ref(), &project_id).await?,
+    };
+    let report = crate::scaffold::materialize(pool.as_ref(), &project_id, &root, on_conflict.unwrap_or_default()).await?;
+
+    println!(
+        "📁 Wrote project {} to {} ({} written, {} deleted, {} conflicts)",
+        project_id,
+        report.root,
+        report.written.len(),
+        report.deleted.len(),
+        report.conflicts.len()
+    );
+
+    if let Err(e) = crate::file_sync::watch(&app, &project_id, &root) {
+        eprintln!("{}", e);
     }
+    Ok(report)
+}
 
-    println!("⚙️  Settings loaded successfully");
+/// List a project's snapshots, newest first
+/// Taken before each agent run and on saves that changed the project's files
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_project_snapshots(project_id: String) -> Result<Vec<crate::snapshots::Snapshot>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
 
-    Ok(Settings {
-        anthropic_api_key,
-        theme,
-        auto_save,
-        default_project_path,
-    })
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Read,
+    )
+    .await?;
+
+    crate::snapshots::list(pool.as_ref(), &project_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Get unified diffs per file between two versions of a project
+/// Versions are snapshot IDs, "current", or "last_run"; by default what the last agent run changed
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn diff_project_versions(
+    project_id: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<crate::snapshots::ProjectDiff, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Read,
+    )
+    .await?;
+
+    let from = from.as_deref().filter(|v| !v.is_empty()).unwrap_or(crate::snapshots::LAST_RUN);
+    let to = to.as_deref().filter(|v| !v.is_empty()).unwrap_or(crate::snapshots::CURRENT);
+    crate::snapshots::diff(pool.as_ref(), &project_id, from, to).await
+}
+
+/// Search project names and descriptions, messages, and code, for the command palette
+/// Results are grouped by kind, with snippets and the offsets of matched terms
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn search(
+    query: String,
+    scope: Option<crate::search::SearchScope>,
+) -> Result<crate::search::SearchResults, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::search::search(pool.as_ref(), &query, scope.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Search failed: {}", e))
 }
 
 // ============================================================================
-// Authentication Commands
+// Git Commands
 // ============================================================================
 
-/// Check Claude Code authentication status
-/// Tries keychain first, then database, returns authentication state
+/// Make a project's folder a git repository, writing the project to disk first if needed
+/// Once it is one, each generation in the project is committed
 #[tauri::command]
-pub async fn check_claude_auth() -> Result<crate::auth::AuthStatus, String> {
+#[tracing::instrument(skip_all)]
+pub async fn git_init_project(project_id: String) -> Result<String, String> {
     let pool = crate::database::get_pool()
         .await
         .map_err(|e| format!("Database error: {}", e))?;
 
-    crate::auth::check_auth_status(pool.as_ref()).await
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    let root = crate::git::init_project(pool.as_ref(), &project_id).await?;
+    println!("🌱 Initialized git repository for project {} in {}", project_id, root.display());
+    Ok(root.to_string_lossy().to_string())
 }
 
-/// Save API key manually after validation
-/// Validates with Anthropic API before storing
+/// Commit a project's files; `null` if nothing changed since the last commit
 #[tauri::command]
-pub async fn save_api_key(api_key: String, email: Option<String>) -> Result<(), String> {
-    println!("🔐 Validating API key...");
+#[tracing::instrument(skip_all)]
+pub async fn git_commit_project(project_id: String, message: String) -> Result<Option<crate::git::GitCommit>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
 
-    // Validate API key with Anthropic
-    let is_valid = crate::auth::validate_api_key(&api_key)
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    crate::git::commit_project(pool.as_ref(), &project_id, &message).await
+}
+
+/// Get the branch and changed files of a project's repository
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn git_status(project_id: String) -> Result<crate::git::GitStatus, String> {
+    let pool = crate::database::get_pool()
         .await
-        .map_err(|e| format!("Validation failed: {}", e))?;
+        .map_err(|e| format!("Database error: {}", e))?;
 
-    if !is_valid {
-        return Err("Invalid API key - authentication failed with Anthropic API".to_string());
-    }
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Read,
+    )
+    .await?;
 
-    println!("✅ API key validated successfully");
+    let root = crate::git::project_root(pool.as_ref(), &project_id).await?;
+    crate::git::status(&root).await
+}
 
-    // Store in database
+/// Get a project's changes since its last commit, optionally for one file
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn git_diff(project_id: String, path: Option<String>) -> Result<crate::git::GitDiff, String> {
     let pool = crate::database::get_pool()
         .await
         .map_err(|e| format!("Database error: {}", e))?;
 
-    crate::auth::store_credentials_in_db(
+    crate::sharing::require_access(
         pool.as_ref(),
-        &api_key,
-        email.as_deref(),
-        None, // subscription_tier - can be added later
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Read,
     )
     .await?;
 
-    println!("✅ API key saved to database");
+    let root = crate::git::project_root(pool.as_ref(), &project_id).await?;
+    crate::git::diff(&root, path.as_deref()).await
+}
+
+// ============================================================================
+// Context Window Commands
+// ============================================================================
+
+/// Get how a project's history is fit into the model's context window
+/// One of "drop", "summarize", or "sliding_window"
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_context_strategy(project_id: String) -> Result<crate::llm::ContextStrategy, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::llm::project_strategy(pool.as_ref(), &project_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Set how a project's history is fit into the model's context window
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_context_strategy(project_id: String, strategy: String) -> Result<(), String> {
+    let strategy = crate::llm::ContextStrategy::parse(&strategy)?;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let result = sqlx::query("UPDATE projects SET context_strategy = ?, updated_at = ? WHERE id = ?")
+        .bind(strategy.as_str())
+        .bind(Utc::now().to_rfc3339())
+        .bind(&project_id)
+        .execute(pool.as_ref())
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Project not found: {}", project_id));
+    }
 
     Ok(())
 }
 
-/// Get current credentials from database
-/// Returns stored Claude credentials if available
+/// Get the post-processors run on a project's replies before they're saved
 #[tauri::command]
-pub async fn get_credentials() -> Result<crate::auth::ClaudeCredentials, String> {
+#[tracing::instrument(skip_all)]
+pub async fn get_post_processors(project_id: String) -> Result<Vec<crate::post_process::PostProcessor>, String> {
     let pool = crate::database::get_pool()
         .await
         .map_err(|e| format!("Database error: {}", e))?;
 
-    crate::auth::load_credentials_from_db(pool.as_ref()).await
+    crate::post_process::enabled(pool.as_ref(), &project_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
 }
 
-// ============================================================================
-// System Tray Commands
-// ============================================================================
+/// Set the post-processors run on a project's replies
+/// Each is one of "extract_code", "format", or "strip_markdown"
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_post_processors(project_id: String, processors: Vec<String>) -> Result<(), String> {
+    let processors = processors
+        .iter()
+        .map(|processor| crate::post_process::PostProcessor::parse(processor))
+        .collect::<Result<Vec<_>, _>>()?;
 
-/// Update the system tray menu with current recent projects
-/// Call this after creating, updating, or deleting projects
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    crate::post_process::set(pool.as_ref(), &project_id, &processors).await
+}
+
+/// Get an agent's or project's daily budget and today's consumption
+/// `scope` is "agent" or "project"
 #[tauri::command]
-pub async fn update_tray_menu(app: tauri::AppHandle) -> Result<(), String> {
-    crate::tray::update_tray_menu(&app)
-        .map_err(|e| format!("Failed to update tray menu: {}", e))
+#[tracing::instrument(skip_all)]
+pub async fn get_budget_status(scope: String, id: String) -> Result<crate::budgets::BudgetStatus, String> {
+    let scope = crate::budgets::BudgetScope::parse(&scope)?;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::budgets::status(pool.as_ref(), scope, &id).await
 }
 
-/// Set a badge on the system tray icon (macOS only)
-/// Pass None or empty string to remove badge
+/// Set an agent's or project's daily token and cost limits; omitted limits are removed
 #[tauri::command]
-pub async fn set_tray_badge(app: tauri::AppHandle, badge: Option<String>) -> Result<(), String> {
-    crate::tray::set_tray_badge(&app, badge.as_deref())
-        .map_err(|e| format!("Failed to set tray badge: {}", e))
+#[tracing::instrument(skip_all)]
+pub async fn set_budget(
+    scope: String,
+    id: String,
+    budget: crate::budgets::Budget,
+) -> Result<crate::budgets::BudgetStatus, String> {
+    let scope = crate::budgets::BudgetScope::parse(&scope)?;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::budgets::set(pool.as_ref(), scope, &id, budget).await?;
+    crate::budgets::status(pool.as_ref(), scope, &id).await
+}
+
+/// Reset an agent's or project's consumption so its budget applies afresh
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn reset_budget(scope: String, id: String) -> Result<crate::budgets::BudgetStatus, String> {
+    let scope = crate::budgets::BudgetScope::parse(&scope)?;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::budgets::reset(pool.as_ref(), scope, &id).await?;
+    crate::budgets::status(pool.as_ref(), scope, &id).await
+}
+
+/// Estimate what a prompt will cost before sending it
+/// Input tokens are estimated from the prompt length; the maximum assumes
+/// the reply uses the whole default output cap
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn estimate_cost(prompt: String, model: String) -> Result<crate::usage::CostEstimate, String> {
+    if model.trim().is_empty() {
+        return Err("Model is required".to_string());
+    }
+    Ok(crate::usage::estimate_cost(&prompt, model.trim(), crate::llm::DEFAULT_MAX_TOKENS))
+}
+
+/// List the models each configured provider offers
+/// Lists are cached for a few minutes unless `refresh` is set
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_models(refresh: Option<bool>) -> Result<crate::llm::ModelCatalog, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(crate::llm::list_models(pool.as_ref(), refresh.unwrap_or(false)).await)
+}
+
+/// Check whether Ollama is running at the configured URL
+/// Its models are added to the catalog, so they can be used offline
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn detect_ollama() -> Result<crate::llm::OllamaStatus, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(crate::llm::detect_ollama(pool.as_ref()).await)
+}
+
+/// List agents, built-ins first
+/// With `filter`, only agents matching its search text, category, capability, and model,
+/// or only favorites
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_agents(filter: Option<crate::agents::AgentFilter>) -> Result<Vec<crate::agents::Agent>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agents::list(pool.as_ref(), Some("local-user"), &filter.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Favorite or unfavorite an agent; returns whether it's now a favorite
+/// Rebuilds the tray menu so its favorites submenu stays current
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn toggle_agent_favorite(app: tauri::AppHandle, agent_id: String) -> Result<bool, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let favorite = crate::agents::toggle_favorite(pool.as_ref(), "local-user", &agent_id).await?;
+    if let Err(e) = crate::tray::update_tray_menu(&app) {
+        eprintln!("Failed to update tray menu: {}", e);
+    }
+    Ok(favorite)
+}
+
+/// Get an agent by ID
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_agent(agent_id: String) -> Result<crate::agents::Agent, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agents::get(pool.as_ref(), &agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Agent not found: {}", agent_id))
+}
+
+/// Define a custom agent
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_agent(agent: crate::agents::NewAgent) -> Result<crate::agents::Agent, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let agent = crate::agents::create(pool.as_ref(), agent).await?;
+
+    println!("🤖 Saved agent: {}", agent.name);
+    Ok(agent)
+}
+
+/// Check a draft agent without saving it; every problem found, errors first
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn validate_agent(agent: crate::agents::NewAgent) -> Result<Vec<crate::agent_lint::Diagnostic>, String> {
+    Ok(crate::agent_lint::lint(&crate::agents::draft(String::new(), agent)))
+}
+
+/// Edit an agent, built-in or custom; omitted fields are left as they are
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn update_agent(
+    agent_id: String,
+    update: crate::agents::AgentUpdate,
+) -> Result<crate::agents::Agent, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agents::update(pool.as_ref(), &agent_id, update)
+        .await?
+        .ok_or_else(|| format!("Agent not found: {}", agent_id))
+}
+
+/// Delete a custom agent (built-in agents can't be deleted)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn delete_agent(agent_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let deleted = crate::agents::delete(pool.as_ref(), &agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if !deleted {
+        return Err(format!("Agent not found: {}", agent_id));
+    }
+    Ok(())
+}
+
+/// Upload an SVG or PNG icon for an agent, replacing any earlier one
+/// Served at `/api/agents/:id/icon`
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_agent_icon(agent_id: String, icon: crate::agent_icons::NewIcon) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agent_icons::set(pool.as_ref(), &agent_id, icon).await?;
+    Ok(())
+}
+
+/// Remove an agent's uploaded icon, going back to its initials avatar
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn remove_agent_icon(agent_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let removed = crate::agent_icons::delete(pool.as_ref(), &agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if !removed {
+        return Err(format!("Agent has no uploaded icon: {}", agent_id));
+    }
+    Ok(())
+}
+
+/// Import an agent from a YAML or JSON definition file
+/// Re-importing a file replaces the agent it created earlier.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn import_agent(path: String) -> Result<crate::agents::Agent, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let agent = crate::agent_files::import_file(pool.as_ref(), std::path::Path::new(&path)).await?;
+
+    println!("🤖 Imported agent: {}", agent.name);
+    Ok(agent)
+}
+
+/// Import every definition file in the app's `agents/` directory again
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn import_agent_dir() -> Result<Vec<crate::agent_files::AgentImport>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(crate::agent_files::import_dir(pool.as_ref(), &crate::agent_files::agents_dir()).await)
+}
+
+/// Export an agent to a definition file; `.json`, `.yaml`, or `.yml` picks the format
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn export_agent(agent_id: String, path: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agent_files::export_file(pool.as_ref(), &agent_id, std::path::Path::new(&path)).await
+}
+
+/// Sync the built-in agents with the bundled manifest and report what changed
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn sync_agent_manifest() -> Result<crate::agent_manifest::ManifestSync, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agent_manifest::sync(pool.as_ref(), crate::agent_manifest::bundled())
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Agent registry configuration and the updates awaiting review
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_agent_registry() -> Result<crate::agent_registry::RegistryStatus, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agent_registry::status(pool.as_ref())
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Check the agent registry now; `None` when no registry is configured
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn check_agent_registry() -> Result<Option<crate::agent_registry::RegistrySync>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agent_registry::sync(pool.as_ref()).await
+}
+
+/// Install an agent update from the registry
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn approve_registry_agent(agent_id: String) -> Result<crate::agents::Agent, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::agent_registry::approve(pool.as_ref(), &agent_id).await
+}
+
+/// Turn down an agent update from the registry until it changes again
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn reject_registry_agent(agent_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let rejected = crate::agent_registry::reject(pool.as_ref(), &agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if !rejected {
+        return Err(format!("No registry update pending for {}", agent_id));
+    }
+    Ok(())
+}
+
+/// Attach an agent to a project, after its other agents
+/// Returns the project's agent IDs in order.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn attach_project_agent(project_id: String, agent_id: String) -> Result<Vec<String>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    crate::project_agents::attach(pool.as_ref(), &project_id, &agent_id).await
+}
+
+/// Detach an agent from a project
+/// Returns the project's remaining agent IDs in order.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn detach_project_agent(project_id: String, agent_id: String) -> Result<Vec<String>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    crate::project_agents::detach(pool.as_ref(), &project_id, &agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    crate::project_agents::list(pool.as_ref(), &project_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// List the local user's agent teams
+/// With `project_type`, only teams for that type (and untyped ones)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_agent_teams(project_type: Option<String>) -> Result<Vec<crate::teams::Team>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let project_type = project_type.as_deref().filter(|t| !t.is_empty());
+    crate::teams::list(pool.as_ref(), "local-user", project_type)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Save an ordered group of agents as a team
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_agent_team(team: crate::teams::NewTeam) -> Result<crate::teams::Team, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::teams::create(pool.as_ref(), "local-user", team).await
+}
+
+/// Delete an agent team
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn delete_agent_team(team_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let deleted = crate::teams::delete(pool.as_ref(), "local-user", &team_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if !deleted {
+        return Err(format!("Team not found: {}", team_id));
+    }
+    Ok(())
+}
+
+/// Make a team's agents a project's active agents
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn apply_agent_team(team_id: String, project_id: String) -> Result<crate::teams::Team, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    crate::teams::apply(pool.as_ref(), "local-user", &team_id, &project_id).await
+}
+
+/// A project's assistant replies grouped by the agent that wrote them
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_agent_history(project_id: String) -> Result<Vec<crate::agent_history::AgentExecutions>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Read,
+    )
+    .await?;
+
+    crate::agent_history::history(pool.as_ref(), &project_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Run an agent on a project on a cron schedule (e.g. `0 2 * * *`, nightly)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_agent_schedule(schedule: crate::schedules::NewSchedule) -> Result<crate::schedules::Schedule, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &schedule.project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    crate::schedules::create(pool.as_ref(), "local-user", schedule).await
+}
+
+/// List scheduled agent tasks, optionally for one project
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_agent_schedules(project_id: Option<String>) -> Result<Vec<crate::schedules::Schedule>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::schedules::list(pool.as_ref(), "local-user", project_id.as_deref())
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Cancel a scheduled agent task
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn cancel_agent_schedule(schedule_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    match crate::schedules::cancel(pool.as_ref(), "local-user", &schedule_id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("Schedule not found: {}", schedule_id)),
+        Err(e) => Err(format!("Database error: {}", e)),
+    }
+}
+
+/// List built-in and saved prompt templates
+/// With `project_type`, only templates for that type (and untyped ones)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_prompt_templates(
+    project_type: Option<String>,
+) -> Result<Vec<crate::templates::PromptTemplate>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let project_type = project_type.as_deref().filter(|t| !t.is_empty());
+    crate::templates::list(pool.as_ref(), "local-user", project_type)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Save a prompt template with `{{variable}}` placeholders
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_prompt_template(
+    template: crate::templates::NewPromptTemplate,
+) -> Result<crate::templates::PromptTemplate, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let template = crate::templates::create(pool.as_ref(), "local-user", template).await?;
+
+    println!("📝 Saved prompt template: {}", template.name);
+    Ok(template)
+}
+
+/// Store an image to attach to prompts by ID
+/// `data` is base64 or a `data:` URL; PNG, JPEG, GIF, and WebP up to 5 MB
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn upload_attachment(
+    attachment: crate::attachments::NewAttachment,
+) -> Result<crate::attachments::Attachment, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::attachments::create(pool.as_ref(), "local-user", attachment).await
+}
+
+/// Delete an uploaded image
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn delete_attachment(attachment_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let deleted = crate::attachments::delete(pool.as_ref(), "local-user", &attachment_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if !deleted {
+        return Err(format!("Attachment not found: {}", attachment_id));
+    }
+    Ok(())
+}
+
+/// Delete a saved prompt template (built-in templates can't be deleted)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn delete_prompt_template(template_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let deleted = crate::templates::delete(pool.as_ref(), "local-user", &template_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if !deleted {
+        return Err(format!("Template not found: {}", template_id));
+    }
+    Ok(())
+}
+
+/// Render a prompt template with the given variables
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn render_prompt_template(
+    template_id: String,
+    variables: std::collections::HashMap<String, String>,
+) -> Result<String, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::templates::render_template(pool.as_ref(), "local-user", &template_id, &variables).await
+}
+
+/// List built-in and saved project templates (starter files, first prompt, and agents)
+/// With `project_type`, only templates for that type
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_templates(
+    project_type: Option<String>,
+) -> Result<Vec<crate::project_templates::ProjectTemplate>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let project_type = project_type.as_deref().filter(|t| !t.is_empty());
+    crate::project_templates::list(pool.as_ref(), "local-user", project_type)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Save a project template
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_project_template(
+    template: crate::project_templates::NewProjectTemplate,
+) -> Result<crate::project_templates::ProjectTemplate, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let template = crate::project_templates::create(pool.as_ref(), "local-user", template).await?;
+
+    println!("📝 Saved project template: {}", template.name);
+    Ok(template)
+}
+
+/// Delete a saved project template (built-in templates can't be deleted)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn delete_project_template(template_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let deleted = crate::project_templates::delete(pool.as_ref(), "local-user", &template_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if !deleted {
+        return Err(format!("Template not found: {}", template_id));
+    }
+    Ok(())
+}
+
+/// Create a project from a template, seeding its files, first prompt, and agents
+/// Returns the new project's ID
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_project_from_template(template_id: String, name: String) -> Result<String, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let project_id = crate::project_templates::create_project(pool.as_ref(), "local-user", &template_id, &name).await?;
+
+    println!("📝 Created project {} from template {}", project_id, template_id);
+    Ok(project_id)
+}
+
+/// Save settings to local storage
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn save_settings(app: tauri::AppHandle, settings: SettingsUpdate) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Failed to get database pool: {}", e))?;
+
+    store_settings(pool.as_ref(), settings).await?;
+
+    // The Ollama URL may have changed
+    crate::llm::invalidate_models();
+
+    // Show the new tray layout
+    if let Err(e) = crate::tray::update_tray_menu(&app) {
+        eprintln!("Failed to update tray menu: {}", e);
+    }
+
+    // Start or stop trace export to match the new settings
+    let telemetry = crate::telemetry::load_config(pool.as_ref()).await;
+    if let Err(e) = crate::telemetry::apply(&telemetry) {
+        eprintln!("{}", e);
+    }
+
+    // Route outbound requests through the new proxy
+    if let Err(e) = crate::proxy::apply(&crate::proxy::load_config(pool.as_ref()).await) {
+        eprintln!("{}", e);
+    }
+
+    // Pause or resume background tasks for the new low-power mode
+    crate::power::refresh();
+
+    println!("⚙️  Settings saved successfully");
+    Ok(())
+}
+
+/// Validate settings and write the ones given to the database, without
+/// applying them
+pub async fn store_settings(pool: &sqlx::SqlitePool, update: SettingsUpdate) -> Result<(), String> {
+    if let Some(address) = update.server_bind_address.as_deref().filter(|a| !a.trim().is_empty()) {
+        crate::server::utils::lan::parse_bind_address(address)?;
+    }
+    if let Some(provider) = update.llm_failover_provider.as_deref().filter(|p| !p.trim().is_empty()) {
+        crate::llm::ProviderKind::parse(provider).map_err(|e| e.to_string())?;
+    }
+    let locale = match update.locale.as_deref().map(str::trim) {
+        Some("") => Some(String::new()),
+        Some(tag) => Some(crate::locale::normalize(tag).ok_or_else(|| format!("Unknown locale: {}", tag))?),
+        None => None,
+    };
+    let tray_layout = update.tray_layout.map(crate::tray::TrayLayout::validate).transpose()?;
+    if update.server_lan_mode == Some(true) {
+        crate::passwords::require_for_lan(pool).await?;
+    }
+    let proxy_url = update.proxy_url.map(|url| url.trim().to_string());
+    if let Some(url) = proxy_url.as_deref().filter(|url| !url.is_empty()) {
+        crate::proxy::validate_url(url)?;
+    }
+    // `load_settings` masks the API key; saving that mask back keeps the key
+    let mut anthropic_api_key = update.anthropic_api_key;
+    if let Some(api_key) = &anthropic_api_key {
+        if is_masked_api_key(pool, api_key).await? {
+            anthropic_api_key = None;
+        }
+    }
+    let proxy_password = match update.proxy_password {
+        Some(password) if !password.is_empty() => Some(crate::vault::seal(pool, &password).await?),
+        password => password,
+    };
+
+    let now = Utc::now().to_rfc3339();
+
+    // Upsert each setting that was given
+    let settings_map = [
+        ("anthropic_api_key", anthropic_api_key),
+        ("theme", update.theme),
+        ("auto_save", update.auto_save.map(|v| v.to_string())),
+        ("default_project_path", update.default_project_path),
+        (crate::totp::SETTING_KEY, update.server_two_factor.map(|v| v.to_string())),
+        ("server_autostart", update.server_autostart.map(|v| v.to_string())),
+        ("server_lan_mode", update.server_lan_mode.map(|v| v.to_string())),
+        ("server_bind_address", update.server_bind_address),
+        ("server_transport", update.server_transport),
+        (
+            "server_port",
+            update.server_port.map(|port| if port == 0 { String::new() } else { port.to_string() }),
+        ),
+        (
+            "server_cors_origins",
+            update.server_cors_origins.map(|origins| serde_json::to_string(&origins).unwrap_or_default()),
+        ),
+        ("server_cors_credentials", update.server_cors_credentials.map(|v| v.to_string())),
+        ("telemetry_enabled", update.telemetry_enabled.map(|v| v.to_string())),
+        ("telemetry_endpoint", update.telemetry_endpoint),
+        (
+            "telemetry_sample_ratio",
+            update.telemetry_sample_ratio.map(|ratio| ratio.clamp(0.0, 1.0).to_string()),
+        ),
+        ("llm_max_attempts", update.llm_max_attempts.map(|n| n.clamp(1, 10).to_string())),
+        ("llm_failover_provider", update.llm_failover_provider),
+        ("llm_failover_model", update.llm_failover_model),
+        (
+            crate::llm::OLLAMA_BASE_URL_SETTING,
+            update.ollama_base_url.map(|url| url.trim().to_string()),
+        ),
+        (crate::llm::RECORD_STREAMS_SETTING, update.llm_record_streams.map(|v| v.to_string())),
+        (
+            crate::agent_registry::URL_SETTING,
+            update.agent_registry_url.map(|url| url.trim().to_string()),
+        ),
+        (
+            crate::agent_registry::KEY_SETTING,
+            update.agent_registry_key.map(|key| key.trim().to_string()),
+        ),
+        (crate::locale::SETTING_KEY, locale),
+        (
+            crate::tray::LAYOUT_SETTING,
+            tray_layout.map(|layout| serde_json::to_string(&layout).unwrap_or_default()),
+        ),
+        ("proxy_url", proxy_url),
+        ("proxy_username", update.proxy_username.map(|name| name.trim().to_string())),
+        (crate::proxy::PASSWORD_SETTING, proxy_password),
+        ("proxy_no_proxy", update.proxy_no_proxy.map(|hosts| hosts.trim().to_string())),
+        (crate::power::SETTING_KEY, update.low_power_mode.map(|mode| mode.as_str().to_string())),
+    ];
+
+    for (key, value) in settings_map {
+        let Some(value) = value else {
+            continue;
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO settings (id, key, value, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET value = ?, updated_at = ?
+            "#
+        )
+        .bind(generate_id("setting"))
+        .bind(key)
+        .bind(&value)
+        .bind(&now)
+        .bind(&value)
+        .bind(&now)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+    }
+
+    Ok(())
+}
+
+/// Whether `value` is the masked form of the saved API key
+async fn is_masked_api_key(pool: &sqlx::SqlitePool, value: &str) -> Result<bool, String> {
+    let saved: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'anthropic_api_key'")
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(saved.is_some_and(|saved| !saved.is_empty() && crate::biometric::mask_secret(&saved) == value))
+}
+
+/// Load settings from local storage
+///
+/// The API key comes back masked, like `get_credentials`'.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn load_settings() -> Result<Settings, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Failed to get database pool: {}", e))?;
+
+    let rows = sqlx::query("SELECT key, value FROM settings")
+        .fetch_all(pool.as_ref())
+        .await
+        .map_err(|e| format!("Failed to fetch settings: {}", e))?;
+
+    let mut anthropic_api_key: Option<String> = None;
+    let mut theme = String::from("dark");
+    let mut auto_save = true;
+    let mut default_project_path = String::from("~/Documents/Vibing2Projects");
+    let mut server_two_factor = false;
+    let mut server_autostart = false;
+    let mut server_lan_mode = false;
+    let mut server_bind_address: Option<String> = None;
+    let mut server_transport: Option<String> = None;
+    let mut server_port: Option<u16> = None;
+    let mut server_cors_origins: Vec<String> = Vec::new();
+    let mut server_cors_credentials = false;
+    let mut telemetry_enabled = false;
+    let mut telemetry_endpoint: Option<String> = None;
+    let mut telemetry_sample_ratio: Option<f64> = None;
+    let mut llm_max_attempts: Option<u32> = None;
+    let mut llm_failover_provider: Option<String> = None;
+    let mut llm_failover_model: Option<String> = None;
+    let mut ollama_base_url: Option<String> = None;
+    let mut llm_record_streams = false;
+    let mut agent_registry_url: Option<String> = None;
+    let mut agent_registry_key: Option<String> = None;
+    let mut locale: Option<String> = None;
+    let mut tray_layout = crate::tray::TrayLayout::default();
+    let mut proxy_url: Option<String> = None;
+    let mut proxy_username: Option<String> = None;
+    let mut proxy_no_proxy: Option<String> = None;
+    let mut low_power_mode = crate::power::LowPowerMode::default();
+
+    for row in rows {
+        let key: String = row.get("key");
+        let value: String = row.get("value");
+
+        match key.as_str() {
+            "anthropic_api_key" => {
+                if !value.is_empty() {
+                    anthropic_api_key = Some(crate::biometric::mask_secret(&value));
+                }
+            }
+            "theme" => theme = value,
+            "auto_save" => auto_save = value.parse().unwrap_or(true),
+            "default_project_path" => default_project_path = value,
+            crate::totp::SETTING_KEY => server_two_factor = value == "true",
+            "server_autostart" => server_autostart = value == "true",
+            "server_lan_mode" => server_lan_mode = value == "true",
+            "server_bind_address" => {
+                if !value.is_empty() {
+                    server_bind_address = Some(value);
+                }
+            }
+            "server_transport" => {
+                if !value.is_empty() {
+                    server_transport = Some(value);
+                }
+            }
+            "server_port" => server_port = value.parse().ok(),
+            "server_cors_origins" => server_cors_origins = serde_json::from_str(&value).unwrap_or_default(),
+            "server_cors_credentials" => server_cors_credentials = value == "true",
+            "telemetry_enabled" => telemetry_enabled = value == "true",
+            "telemetry_endpoint" => {
+                if !value.is_empty() {
+                    telemetry_endpoint = Some(value);
+                }
+            }
+            "telemetry_sample_ratio" => telemetry_sample_ratio = value.parse().ok(),
+            "llm_max_attempts" => llm_max_attempts = value.parse().ok(),
+            "llm_failover_provider" => {
+                if !value.is_empty() {
+                    llm_failover_provider = Some(value);
+                }
+            }
+            "llm_failover_model" => {
+                if !value.is_empty() {
+                    llm_failover_model = Some(value);
+                }
+            }
+            crate::llm::OLLAMA_BASE_URL_SETTING => {
+                if !value.is_empty() {
+                    ollama_base_url = Some(value);
+                }
+            }
+            crate::llm::RECORD_STREAMS_SETTING => llm_record_streams = value == "true",
+            crate::agent_registry::URL_SETTING => {
+                if !value.is_empty() {
+                    agent_registry_url = Some(value);
+                }
+            }
+            crate::agent_registry::KEY_SETTING => {
+                if !value.is_empty() {
+                    agent_registry_key = Some(value);
+                }
+            }
+            crate::locale::SETTING_KEY => {
+                if !value.is_empty() {
+                    locale = Some(value);
+                }
+            }
+            crate::tray::LAYOUT_SETTING => tray_layout = serde_json::from_str(&value).unwrap_or_default(),
+            "proxy_url" => {
+                if !value.is_empty() {
+                    proxy_url = Some(value);
+                }
+            }
+            "proxy_username" => {
+                if !value.is_empty() {
+                    proxy_username = Some(value);
+                }
+            }
+            "proxy_no_proxy" => {
+                if !value.is_empty() {
+                    proxy_no_proxy = Some(value);
+                }
+            }
+            crate::power::SETTING_KEY => low_power_mode = crate::power::LowPowerMode::parse(&value).unwrap_or_default(),
+            _ => {}
+        }
+    }
+
+    println!("⚙️  Settings loaded successfully");
+
+    Ok(Settings {
+        anthropic_api_key,
+        theme,
+        auto_save,
+        default_project_path,
+        server_two_factor,
+        server_autostart,
+        server_lan_mode,
+        server_bind_address,
+        server_transport,
+        server_port,
+        server_cors_origins,
+        server_cors_credentials,
+        telemetry_enabled,
+        telemetry_endpoint,
+        telemetry_sample_ratio,
+        llm_max_attempts,
+        llm_failover_provider,
+        llm_failover_model,
+        ollama_base_url,
+        llm_record_streams,
+        agent_registry_url,
+        agent_registry_key,
+        locale,
+        tray_layout,
+        proxy_url,
+        proxy_username,
+        proxy_no_proxy,
+        low_power_mode,
+    })
+}
+
+// ============================================================================
+// Authentication Commands
+// ============================================================================
+
+/// Check Claude Code authentication status
+/// Tries the OS credential store and Claude Code config first, then OAuth and the database.
+/// Returns a cached status (refreshed in the background) unless `force` is set.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn check_claude_auth(
+    force: Option<bool>,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<crate::auth::AuthStatus, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    cache.get(pool, force.unwrap_or(false)).await
+}
+
+/// Save API key manually after validation
+/// Validates with Anthropic API before storing
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn save_api_key(
+    app: tauri::AppHandle,
+    api_key: String,
+    email: Option<String>,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<(), String> {
+    println!("🔐 Validating API key...");
+
+    // Validate API key with Anthropic
+    let is_valid = crate::auth::validate_api_key(&api_key)
+        .await
+        .map_err(|e| format!("Validation failed: {}", e))?;
+
+    if !is_valid {
+        return Err("Invalid API key - authentication failed with Anthropic API".to_string());
+    }
+
+    println!("✅ API key validated successfully");
+
+    // Store in database
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::auth::store_credentials_in_db(
+        pool.as_ref(),
+        &api_key,
+        email.as_deref(),
+        None, // subscription_tier - can be added later
+    )
+    .await?;
+
+    cache.invalidate().await;
+    crate::auth::emit_auth_event(&app, crate::auth::AuthEvent::SignedIn {
+        source: "database".to_string(),
+        email,
+    });
+    println!("✅ API key saved to database");
+
+    Ok(())
+}
+
+/// Get account tier and rate-limit budget for the current Anthropic key
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_account_info() -> Result<crate::auth::AccountInfo, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::auth::fetch_account_info(pool.as_ref()).await
+}
+
+/// Get current credentials from database
+/// Returns stored Claude credentials with the API key masked;
+/// use `reveal_api_key` to get the full key
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_credentials() -> Result<crate::auth::ClaudeCredentials, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut creds = crate::auth::load_credentials_from_db(pool.as_ref()).await?;
+    creds.api_key = crate::biometric::mask_secret(&creds.api_key);
+
+    Ok(creds)
+}
+
+/// Reveal the stored API key
+/// Requires OS user-presence verification (Touch ID / password on macOS,
+/// Windows Hello on Windows)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn reveal_api_key() -> Result<String, String> {
+    crate::biometric::require_user_presence("reveal your Claude API key").await?;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let creds = crate::auth::load_credentials_from_db(pool.as_ref()).await?;
+
+    println!("🔓 API key revealed after user verification");
+
+    Ok(creds.api_key)
+}
+
+/// Set the password for signing in to the embedded server as the local account
+/// Requires OS user-presence verification; needed before LAN mode can be enabled
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_local_password(password: String) -> Result<(), String> {
+    crate::biometric::require_user_presence("set the local account password").await?;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::passwords::set(pool.as_ref(), crate::passwords::LOCAL_USER_ID, &password).await?;
+
+    println!("🔐 Local account password updated");
+    Ok(())
+}
+
+/// Save an API key for a specific LLM provider
+/// Validates the key against the provider before storing
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn save_provider_key(
+    provider: String,
+    api_key: String,
+    base_url: Option<String>,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<(), String> {
+    let provider = crate::auth::Provider::parse(&provider)?;
+
+    println!("🔐 Validating {} API key...", provider.display_name());
+
+    let is_valid = crate::auth::validate_provider_key(provider, &api_key, base_url.as_deref())
+        .await
+        .map_err(|e| format!("Validation failed: {}", e))?;
+
+    if !is_valid {
+        return Err(format!(
+            "Invalid API key - authentication failed with {}",
+            provider.display_name()
+        ));
+    }
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::auth::store_provider_key(pool.as_ref(), provider, &api_key, base_url.as_deref()).await?;
+    cache.invalidate().await;
+
+    println!("✅ {} API key saved to database", provider.display_name());
+
+    Ok(())
+}
+
+/// Remove the stored API key for a provider
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn remove_provider_key(
+    app: tauri::AppHandle,
+    provider: String,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<(), String> {
+    let provider = crate::auth::Provider::parse(&provider)?;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::auth::delete_provider_key(pool.as_ref(), provider).await?;
+    cache.invalidate().await;
+
+    if provider == crate::auth::Provider::Anthropic {
+        crate::auth::emit_auth_event(&app, crate::auth::AuthEvent::SignedOut {});
+    }
+
+    Ok(())
+}
+
+/// List all supported providers and whether each has a key configured
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_providers() -> Result<Vec<crate::auth::ProviderInfo>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::auth::list_provider_info(pool.as_ref()).await
+}
+
+// ============================================================================
+// App Lock Commands
+// ============================================================================
+
+/// Get the app lock status (enabled, locked, idle timeout)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_lock_status() -> Result<crate::vault::LockStatus, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::vault::status(pool.as_ref()).await
+}
+
+/// Enable the app lock, encrypting stored credentials with a passphrase
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn enable_credential_lock(
+    passphrase: String,
+    idle_timeout_minutes: Option<u64>,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::vault::enable(
+        pool.as_ref(),
+        &passphrase,
+        idle_timeout_minutes.unwrap_or(crate::vault::DEFAULT_IDLE_TIMEOUT_MINUTES),
+    )
+    .await?;
+    cache.invalidate().await;
+
+    println!("🔐 App lock enabled");
+    Ok(())
+}
+
+/// Disable the app lock and store credentials unencrypted again
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn disable_credential_lock(
+    passphrase: String,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::vault::disable(pool.as_ref(), &passphrase).await?;
+    cache.invalidate().await;
+
+    println!("🔓 App lock disabled");
+    Ok(())
+}
+
+/// Unlock stored credentials with the master passphrase
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn unlock_credentials(
+    app: tauri::AppHandle,
+    passphrase: String,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::vault::unlock(pool.as_ref(), &passphrase).await?;
+    cache.invalidate().await;
+    crate::auth::emit_auth_event(&app, crate::auth::AuthEvent::Unlocked {});
+
+    Ok(())
+}
+
+/// Lock stored credentials immediately
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn lock_credentials(
+    app: tauri::AppHandle,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<(), String> {
+    crate::vault::lock();
+    cache.invalidate().await;
+    crate::auth::emit_auth_event(&app, crate::auth::AuthEvent::Locked {});
+    Ok(())
+}
+
+// ============================================================================
+// Session Commands
+// ============================================================================
+
+/// List all active embedded-server sessions across local accounts
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_sessions() -> Result<Vec<crate::sessions::SessionInfo>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sessions::list_sessions(pool.as_ref(), None, None)
+        .await
+        .map_err(|e| format!("Failed to list sessions: {}", e))
+}
+
+/// Revoke a single embedded-server session
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn revoke_session(session_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let revoked = crate::sessions::revoke_session(pool.as_ref(), &session_id, None)
+        .await
+        .map_err(|e| format!("Failed to revoke session: {}", e))?;
+
+    if !revoked {
+        return Err(format!("Session not found: {}", session_id));
+    }
+
+    Ok(())
+}
+
+/// Revoke every embedded-server session, signing out all devices
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn revoke_all_sessions() -> Result<u64, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sessions::revoke_all_sessions(pool.as_ref(), None, None)
+        .await
+        .map_err(|e| format!("Failed to revoke sessions: {}", e))
+}
+
+// ============================================================================
+// Device Pairing Commands
+// ============================================================================
+
+/// Generate a short-lived code for pairing a LAN device
+/// Scope is "viewer" (read-only) or "editor"
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_pairing_code(scope: Option<String>) -> Result<crate::pairing::PairingCode, String> {
+    crate::pairing::create_pairing_code(scope.as_deref().unwrap_or("viewer"))
+}
+
+/// List paired devices
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_devices() -> Result<Vec<crate::pairing::DeviceInfo>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::pairing::list_devices(pool.as_ref())
+        .await
+        .map_err(|e| format!("Failed to list devices: {}", e))
+}
+
+/// Revoke a paired device's access
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn revoke_device(device_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let revoked = crate::pairing::revoke_device(pool.as_ref(), &device_id)
+        .await
+        .map_err(|e| format!("Failed to revoke device: {}", e))?;
+
+    if !revoked {
+        return Err(format!("Device not found: {}", device_id));
+    }
+
+    println!("📱 Revoked device: {}", device_id);
+    Ok(())
+}
+
+// ============================================================================
+// User Commands
+// ============================================================================
+
+/// Local account summary for role management
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserAccount {
+    pub id: String,
+    pub name: Option<String>,
+    pub email: String,
+    pub role: String,
+    pub created_at: String,
+}
+
+/// List local accounts with their roles
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_users() -> Result<Vec<UserAccount>, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let rows = sqlx::query("SELECT id, name, email, role, created_at FROM users ORDER BY created_at ASC")
+        .fetch_all(pool.as_ref())
+        .await
+        .map_err(|e| format!("Failed to fetch users: {}", e))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| UserAccount {
+            id: row.get("id"),
+            name: row.get("name"),
+            email: row.get("email"),
+            role: row.get("role"),
+            created_at: row.get("created_at"),
+        })
+        .collect())
+}
+
+/// Change a local account's role (owner, editor, viewer)
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_user_role(user_id: String, role: String) -> Result<(), String> {
+    if !matches!(role.as_str(), "owner" | "editor" | "viewer") {
+        return Err(format!("Unknown role: {}", role));
+    }
+
+    if user_id == "local-user" && role != "owner" {
+        return Err("The local desktop user must remain an owner".to_string());
+    }
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let result = sqlx::query("UPDATE users SET role = ?, updated_at = datetime('now') WHERE id = ?")
+        .bind(&role)
+        .bind(&user_id)
+        .execute(pool.as_ref())
+        .await
+        .map_err(|e| format!("Failed to update role: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("User not found: {}", user_id));
+    }
+
+    println!("👤 Set role of {} to {}", user_id, role);
+    Ok(())
+}
+
+// ============================================================================
+// OAuth Commands
+// ============================================================================
+
+/// Start the browser-based Anthropic sign-in flow
+/// Opens the authorization page; the browser redirects back via the vibing2:// deep link
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn start_oauth_signin(app: tauri::AppHandle) -> Result<String, String> {
+    use tauri_plugin_shell::ShellExt;
+
+    let url = crate::oauth::begin_authorization()?;
+
+    app.shell()
+        .open(&url, None)
+        .map_err(|e| format!("Failed to open browser: {}", e))?;
+
+    Ok(url)
+}
+
+/// Complete the OAuth flow with the callback URL
+/// Normally invoked by the deep link handler, exposed for manual paste as a fallback
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn complete_oauth_signin(
+    app: tauri::AppHandle,
+    callback_url: String,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<crate::auth::AuthStatus, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::oauth::complete_authorization(pool.as_ref(), &callback_url).await?;
+    let status = cache.get(pool, true).await?;
+
+    crate::auth::emit_auth_event(&app, crate::auth::AuthEvent::SignedIn {
+        source: status.source.clone(),
+        email: status.email.clone(),
+    });
+
+    Ok(status)
+}
+
+/// Sign out of the OAuth session and forget stored tokens
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn oauth_sign_out(
+    app: tauri::AppHandle,
+    cache: tauri::State<'_, std::sync::Arc<crate::auth::AuthCache>>,
+) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::oauth::sign_out(pool.as_ref()).await?;
+    cache.invalidate().await;
+    crate::auth::emit_auth_event(&app, crate::auth::AuthEvent::SignedOut {});
+
+    Ok(())
+}
+
+// ============================================================================
+// Generation Commands
+// ============================================================================
+
+/// Start a generation and stream its events over `on_event`
+/// Same events as `/api/agent/stream`, without going through the HTTP server.
+/// Returns the session ID once the provider is resolved; events arrive until the `done` message.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn start_generation(
+    mut request: crate::generation::StreamRequest,
+    on_event: tauri::ipc::Channel<crate::generation::GenerationEvent>,
+) -> Result<String, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    request.session_id = Some(session_id.clone());
+
+    let events = crate::generation::start(pool.as_ref(), crate::sharing::Principal::desktop(), request, futures::future::pending())
+        .await
+        .map_err(|e| e.to_string())?;
+    forward_events(events, on_event);
+
+    Ok(session_id)
+}
+
+/// Run a prompt through two agents at once, streaming both over `on_event`
+/// Each event carries its `agent_id`; for a project, the replies are saved as sibling
+/// branches until one is picked with `pick_comparison_reply`. Returns the session ID.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn compare_agents(
+    mut request: crate::comparison::CompareRequest,
+    on_event: tauri::ipc::Channel<crate::comparison::ComparisonEvent>,
+) -> Result<String, String> {
+    use futures::StreamExt;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    request.request.session_id = Some(session_id.clone());
+
+    let mut events = crate::comparison::start(pool.as_ref(), crate::sharing::Principal::desktop(), request, futures::future::pending())
+        .await
+        .map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = events.next().await {
+            // Stop generating once the webview has gone away
+            if on_event.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(session_id)
+}
+
+/// Keep one reply of an agent comparison in the conversation, rejecting the others
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn pick_comparison_reply(project_id: String, message_id: String) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::sharing::require_access(
+        pool.as_ref(),
+        &project_id,
+        crate::sharing::Principal::desktop(),
+        crate::sharing::Access::Write,
+    )
+    .await?;
+
+    crate::comparison::pick(pool.as_ref(), &project_id, &message_id).await
+}
+
+/// Run a generation to the end and return the whole reply
+/// For short utility calls (titles, commit messages); takes the same request as `start_generation`.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn complete_generation(
+    request: crate::generation::StreamRequest,
+) -> Result<crate::generation::CompletionResponse, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::generation::complete(pool.as_ref(), crate::sharing::Principal::desktop(), request)
+        .await
+        .map_err(|e| e.message)
+}
+
+/// Try an agent on a short sample input with a cheap model
+/// Replies are capped and saved nowhere; `test.system_prompt` tries a draft without saving it.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn test_agent(
+    agent_id: String,
+    test: Option<crate::generation::AgentTest>,
+) -> Result<crate::generation::CompletionResponse, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let agent = crate::agents::get(pool.as_ref(), &agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Agent not found: {}", agent_id))?;
+
+    crate::generation::test_agent(
+        pool.as_ref(),
+        crate::sharing::Principal::desktop(),
+        &agent,
+        test.unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| e.message)
+}
+
+/// Replay a recorded session's provider stream over `on_event`
+/// Requires stream recording to have been on when the session ran; nothing is sent to the provider.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn replay_stream(
+    session_id: String,
+    on_event: tauri::ipc::Channel<crate::generation::GenerationEvent>,
+) -> Result<(), String> {
+    let events = crate::generation::replay(&session_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    forward_events(events, on_event);
+
+    Ok(())
+}
+
+fn forward_events(
+    mut events: futures::stream::BoxStream<'static, crate::generation::GenerationEvent>,
+    on_event: tauri::ipc::Channel<crate::generation::GenerationEvent>,
+) {
+    use futures::StreamExt;
+
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = events.next().await {
+            // Stop generating once the webview has gone away
+            if on_event.send(event).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Embedded Server Commands
+// ============================================================================
+
+/// Start the embedded HTTP server (no-op if already running)
+/// Emits `server-status` with the local URL
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn start_server(
+    app: tauri::AppHandle,
+    server: tauri::State<'_, std::sync::Arc<crate::server::ServerManager>>,
+) -> Result<crate::server::ServerInfo, String> {
+    start_embedded_server(&app, server.inner()).await
+}
+
+/// Stop the embedded HTTP server
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn stop_server(
+    app: tauri::AppHandle,
+    server: tauri::State<'_, std::sync::Arc<crate::server::ServerManager>>,
+) -> Result<crate::server::ServerInfo, String> {
+    use tauri::Emitter;
+
+    server.stop().await;
+
+    let info = crate::server::ServerInfo::stopped();
+    let _ = app.emit("server-status", &info);
+    Ok(info)
+}
+
+/// Restart the embedded HTTP server
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn restart_server(
+    app: tauri::AppHandle,
+    server: tauri::State<'_, std::sync::Arc<crate::server::ServerManager>>,
+) -> Result<crate::server::ServerInfo, String> {
+    server.stop().await;
+    start_embedded_server(&app, server.inner()).await
+}
+
+/// Get the embedded server's URL and status
+/// While stopped, reports the port the server will try next
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_server_info(
+    server: tauri::State<'_, std::sync::Arc<crate::server::ServerManager>>,
+) -> Result<crate::server::ServerInfo, String> {
+    let mut info = server.info().await;
+
+    if info.status != "running" {
+        if let Ok(pool) = crate::database::get_pool().await {
+            info.port = saved_server_port(pool.as_ref()).await.unwrap_or(0);
+        }
+    }
+
+    Ok(info)
+}
+
+/// Get the embedded server's request limits
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_server_limits() -> Result<crate::server::config::ServerLimits, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(saved_server_limits(pool.as_ref()).await)
+}
+
+/// Save the embedded server's request limits
+/// Applied to the running server immediately, without a restart
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn update_server_limits(
+    limits: crate::server::config::ServerLimits,
+    server: tauri::State<'_, std::sync::Arc<crate::server::ServerManager>>,
+) -> Result<crate::server::config::ServerLimits, String> {
+    if limits.max_body_size == 0 {
+        return Err("Maximum body size must be greater than zero".to_string());
+    }
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let value = serde_json::to_string(&limits).map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339();
+
+    sqlx::query(
+        r#"
+        INSERT INTO settings (id, key, value, updated_at)
+        VALUES (?, 'server_limits', ?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#
+    )
+    .bind(generate_id("setting"))
+    .bind(&value)
+    .bind(&now)
+    .execute(pool.as_ref())
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if server.update_limits(&limits).await {
+        println!("⚙️  Server limits updated");
+    }
+
+    Ok(limits)
+}
+
+/// Get the effective server configuration
+/// Combines app settings, `server.toml`, and environment overrides
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_server_config() -> Result<crate::server::config::ServerConfigFile, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let file = crate::server::config::ServerConfigFile::load()?;
+    let config = resolve_server_config(pool.as_ref(), file).await?;
+    Ok(crate::server::config::ServerConfigFile::from(&config))
+}
+
+/// Validate and save `server.toml`, then apply it to the running server
+/// Limit changes apply in place; anything else restarts the server.
+/// Environment overrides still take precedence over the saved file.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_server_config(
+    config: crate::server::config::ServerConfigFile,
+    app: tauri::AppHandle,
+    server: tauri::State<'_, std::sync::Arc<crate::server::ServerManager>>,
+) -> Result<crate::server::config::ServerConfigFile, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let previous = resolve_server_config(pool.as_ref(), crate::server::config::ServerConfigFile::load()?).await?;
+    let effective = resolve_server_config(pool.as_ref(), config.clone()).await?;
+    config.save()?;
+
+    if server.info().await.status == "running" {
+        if previous.clone().with_limits(&effective.limits()) == effective {
+            if server.update_limits(&effective.limits()).await {
+                println!("⚙️  Server limits updated");
+            }
+        } else {
+            server.stop().await;
+            start_embedded_server(&app, server.inner()).await?;
+        }
+    }
+
+    Ok(crate::server::config::ServerConfigFile::from(&effective))
+}
+
+/// Get the URLs other devices can use to reach the server in LAN mode
+/// Suitable for display or encoding in a pairing QR code
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_lan_urls(
+    server: tauri::State<'_, std::sync::Arc<crate::server::ServerManager>>,
+) -> Result<Vec<crate::server::utils::lan::LanUrl>, String> {
+    let info = server.info().await;
+    if info.status != "running" {
+        return Err("Server is not running".to_string());
+    }
+
+    let host = crate::server::utils::lan::parse_bind_address(&info.host)?;
+    if host.is_loopback() || info.transport == crate::server::config::Transport::Local.as_str() {
+        return Err("LAN mode is not enabled".to_string());
+    }
+
+    crate::server::utils::lan::lan_urls(host, info.port, &info.base_path)
+        .map_err(|e| format!("Failed to list network interfaces: {}", e))
+}
+
+/// Bind address for LAN mode, or `None` to stay on loopback
+async fn saved_lan_bind_address(pool: &sqlx::SqlitePool) -> Result<Option<std::net::IpAddr>, String> {
+    let rows = sqlx::query("SELECT key, value FROM settings WHERE key IN ('server_lan_mode', 'server_bind_address')")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut lan_mode = false;
+    let mut bind_address = String::new();
+    for row in rows {
+        let key: String = row.get("key");
+        let value: String = row.get("value");
+        match key.as_str() {
+            "server_lan_mode" => lan_mode = value == "true",
+            "server_bind_address" => bind_address = value,
+            _ => {}
+        }
+    }
+
+    if !lan_mode {
+        return Ok(None);
+    }
+
+    // Saved before LAN mode needed a password
+    if let Err(e) = crate::passwords::require_for_lan(pool).await {
+        eprintln!("⚠️  {}; staying on loopback", e);
+        return Ok(None);
+    }
+
+    if bind_address.trim().is_empty() {
+        return Ok(Some(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED)));
+    }
+
+    crate::server::utils::lan::parse_bind_address(&bind_address).map(Some)
+}
+
+/// Transport from settings, defaulting to TCP
+async fn saved_server_transport(pool: &sqlx::SqlitePool) -> crate::server::config::Transport {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'server_transport'")
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None);
+
+    crate::server::config::Transport::parse(value.as_deref().unwrap_or_default())
+}
+
+/// Preferred (last used) server port from settings
+async fn saved_server_port(pool: &sqlx::SqlitePool) -> Option<u16> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'server_port'")
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None);
+
+    value.and_then(|value| value.parse().ok()).filter(|port| *port != 0)
+}
+
+async fn save_server_port(pool: &sqlx::SqlitePool, port: u16) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        INSERT INTO settings (id, key, value, updated_at)
+        VALUES (?, 'server_port', ?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#
+    )
+    .bind(generate_id("setting"))
+    .bind(port.to_string())
+    .bind(Utc::now().to_rfc3339())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(())
+}
+
+/// CORS policy from settings
+async fn saved_cors_config(pool: &sqlx::SqlitePool) -> crate::server::config::CorsConfig {
+    let rows = sqlx::query("SELECT key, value FROM settings WHERE key IN ('server_cors_origins', 'server_cors_credentials')")
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default();
+
+    let mut cors = crate::server::config::CorsConfig::default();
+    for row in rows {
+        let key: String = row.get("key");
+        let value: String = row.get("value");
+        match key.as_str() {
+            "server_cors_origins" => cors.allowed_origins = serde_json::from_str(&value).unwrap_or_default(),
+            "server_cors_credentials" => cors.allow_credentials = value == "true",
+            _ => {}
+        }
+    }
+
+    cors
+}
+
+/// Saved server limits, falling back to the defaults
+async fn saved_server_limits(pool: &sqlx::SqlitePool) -> crate::server::config::ServerLimits {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'server_limits'")
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None);
+
+    value
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+/// Effective server configuration: app settings, then `file`, then
+/// `VIBING2_SERVER_*` environment variables
+async fn resolve_server_config(
+    pool: &sqlx::SqlitePool,
+    file: crate::server::config::ServerConfigFile,
+) -> Result<crate::server::config::ServerConfig, String> {
+    let limits = saved_server_limits(pool).await;
+    let mut config = crate::server::config::ServerConfig::default().with_limits(&limits);
+    if let Some(host) = saved_lan_bind_address(pool).await? {
+        config = config.with_host(host);
+    }
+    if let Some(port) = saved_server_port(pool).await {
+        config.port = port;
+    }
+    config.transport = saved_server_transport(pool).await;
+    config.cors = saved_cors_config(pool).await;
+
+    let overrides = crate::server::config::ServerConfigFile::from_env()?;
+    file.merge(overrides).apply(config)
+}
+
+/// Start the embedded server with the app database and bundled static files
+pub async fn start_embedded_server(
+    app: &tauri::AppHandle,
+    server: &crate::server::ServerManager,
+) -> Result<crate::server::ServerInfo, String> {
+    use tauri::Emitter;
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let static_dir = crate::server::utils::resolve_static_path();
+    let file = crate::server::config::ServerConfigFile::load()?;
+    let config = resolve_server_config(pool.as_ref(), file).await?;
+
+    let info = server
+        .start(static_dir, pool.as_ref().clone(), config)
+        .await
+        .map_err(|e| format!("Failed to start server: {}", e))?;
+
+    // Keep the same port across restarts so bookmarks and paired devices work
+    if info.port != 0 && saved_server_port(pool.as_ref()).await != Some(info.port) {
+        save_server_port(pool.as_ref(), info.port).await?;
+    }
+
+    let _ = app.emit("server-status", &info);
+    Ok(info)
+}
+
+/// Whether the embedded server should start with the app
+pub async fn server_autostart_enabled() -> bool {
+    let Ok(pool) = crate::database::get_pool().await else {
+        return false;
+    };
+
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'server_autostart'")
+        .fetch_optional(pool.as_ref())
+        .await
+        .unwrap_or(None);
+
+    value.as_deref() == Some("true")
+}
+
+// ============================================================================
+// System Tray Commands
+// ============================================================================
+
+/// Update the system tray menu with current recent projects
+/// Call this after creating, updating, or deleting projects
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn update_tray_menu(app: tauri::AppHandle) -> Result<(), String> {
+    crate::tray::update_tray_menu(&app)
+        .map_err(|e| format!("Failed to update tray menu: {}", e))
+}
+
+/// Set the unread count shown on the app's icon
+/// Pass None or empty string to remove badge
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_tray_badge(app: tauri::AppHandle, badge: Option<String>) -> Result<(), String> {
+    let count = match badge.as_deref().map(str::trim).filter(|badge| !badge.is_empty()) {
+        Some(badge) => badge.parse().map_err(|_| format!("Badge must be a count: {}", badge))?,
+        None => 0,
+    };
+    crate::tray::set_tray_badge(&app, count)
+        .map_err(|e| format!("Failed to set tray badge: {}", e))
+}
+
+/// Unread notifications shown on the app's badge
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_unread_count(
+    unread: tauri::State<'_, std::sync::Arc<crate::tray::UnreadCounter>>,
+) -> Result<u32, String> {
+    Ok(unread.get())
+}
+
+/// Show a status on the system tray icon: idle, generating, or attention
+///
+/// Generations set the status themselves as they start and finish; this
+/// overrides it until the next one does.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_tray_status(app: tauri::AppHandle, status: crate::tray::TrayStatus) -> Result<(), String> {
+    crate::tray::set_status(&app, status).map_err(|e| format!("Failed to set tray status: {}", e))
+}
+
+/// Open the always-on-top quick prompt window
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn open_quick_capture(app: tauri::AppHandle) -> Result<(), String> {
+    crate::quick_capture::open(&app);
+    Ok(())
+}
+
+/// Get whether the app launches at login, and whether it starts in the tray then
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_autostart(app: tauri::AppHandle) -> Result<crate::autostart::AutostartStatus, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(crate::autostart::status(&app, pool.as_ref()).await)
+}
+
+/// Turn launch at login on or off; `start_minimized` starts it in the tray
+/// Leaves "start minimized" as it was when not given, and updates the tray menu's check mark.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_autostart(
+    app: tauri::AppHandle,
+    enabled: bool,
+    start_minimized: Option<bool>,
+) -> Result<crate::autostart::AutostartStatus, String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    crate::autostart::set_enabled(&app, enabled)?;
+    if let Some(minimized) = start_minimized {
+        crate::autostart::set_start_minimized(pool.as_ref(), minimized).await?;
+    }
+    if let Err(e) = crate::tray::update_tray_menu(&app) {
+        eprintln!("Failed to update tray menu: {}", e);
+    }
+
+    Ok(crate::autostart::status(&app, pool.as_ref()).await)
+}
+
+/// Get the power source and whether background tasks are paused for sleep or low power
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_power_status() -> Result<crate::power::PowerStatus, String> {
+    Ok(crate::power::current_status())
+}
+
+/// Take the `vibing2://` link waiting for the page, if any
+/// Set when a link launched the app or reopened the main window; returns it once.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn take_deep_link() -> Result<Option<crate::deep_link::DeepLink>, String> {
+    Ok(crate::deep_link::take_pending())
+}
+
+/// Start a new project from the quick prompt window and generate for it
+/// Emits `quick-capture-event` for each generation event and opens the project in the main window.
+/// Returns the new project's ID.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn submit_quick_capture(
+    app: tauri::AppHandle,
+    prompt: String,
+    agent_id: Option<String>,
+) -> Result<String, String> {
+    crate::quick_capture::submit(&app, prompt, agent_id).await
 }
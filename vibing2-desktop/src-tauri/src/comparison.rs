@@ -0,0 +1,274 @@
+//! Side-by-side agent comparisons
+//!
+//! [`start`] runs one prompt through two agents at once and interleaves
+//! their generation events, each tagged with the agent it came from. For a
+//! project, the prompt is saved once and each reply is saved as a candidate
+//! answer to it: a sibling branch that stays out of the conversation
+//! history until the user picks one with [`pick`]. The replies not picked
+//! are kept, marked rejected.
+
+use futures::future::FutureExt;
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::future::Future;
+use utoipa::ToSchema;
+
+use crate::generation::{self, GenerationEvent, StreamRequest};
+use crate::llm::LlmError;
+use crate::sharing::Principal;
+
+/// `branch_status` of a saved prompt and its replies until one is picked
+pub const PENDING: &str = "pending";
+
+/// `branch_status` of the replies that weren't picked
+pub const REJECTED: &str = "rejected";
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CompareRequest {
+    /// The generation to run; its `agent_id` is replaced by each of
+    /// `agent_ids` in turn
+    #[serde(flatten)]
+    pub request: StreamRequest,
+    /// The two agents to compare
+    pub agent_ids: Vec<String>,
+}
+
+/// A generation event from one side of a comparison
+///
+/// Serialized as the [`GenerationEvent`] with the agent's ID alongside:
+/// `{"agent_id": ..., "event": ..., "data": ...}`.
+#[derive(Debug, Serialize)]
+pub struct ComparisonEvent {
+    pub agent_id: String,
+    #[serde(flatten)]
+    pub event: GenerationEvent,
+}
+
+impl ComparisonEvent {
+    /// SSE data payload: the event's payload under `data`, with `agent_id`
+    pub fn data(&self) -> String {
+        let data: serde_json::Value = serde_json::from_str(&self.event.data()).unwrap_or_default();
+        serde_json::json!({ "agent_id": self.agent_id, "data": data }).to_string()
+    }
+}
+
+/// Start generating with both agents
+///
+/// Fails before any event unless two different agents are named, or if
+/// either generation can't start (see [`generation::start`]). Each agent's
+/// events end with its own `done` message; the stream ends after both.
+/// When `cancel` completes, both generations stop early.
+pub async fn start(
+    pool: &SqlitePool,
+    principal: Principal<'_>,
+    compare: CompareRequest,
+    cancel: impl Future<Output = ()> + Send + 'static,
+) -> Result<BoxStream<'static, ComparisonEvent>, LlmError> {
+    let CompareRequest { mut request, agent_ids } = compare;
+    if agent_ids.len() != 2 || agent_ids[0] == agent_ids[1] {
+        return Err(LlmError::Config("Pick two different agents to compare".to_string()));
+    }
+
+    // Both agents get the same rendered prompt, which is saved once
+    generation::render_prompt(pool, principal, &mut request).await?;
+    let prompt_id = request.project_id.as_ref().map(|_| uuid::Uuid::new_v4().to_string());
+
+    let cancel = cancel.shared();
+    let mut streams = Vec::with_capacity(agent_ids.len());
+    for (index, agent_id) in agent_ids.into_iter().enumerate() {
+        let mut branch = request.clone();
+        branch.agent_id = Some(agent_id.clone());
+        branch.session_id = request.session_id.as_ref().map(|id| format!("{}-{}", id, index + 1));
+
+        let events = generation::start_branch(pool, principal, branch, cancel.clone(), prompt_id.clone()).await?;
+        streams.push(events.map(move |event| ComparisonEvent {
+            agent_id: agent_id.clone(),
+            event,
+        }));
+    }
+
+    if let (Some(project_id), Some(prompt_id)) = (&request.project_id, &prompt_id) {
+        save_prompt(pool, project_id, prompt_id, &request.prompt)
+            .await
+            .map_err(|e| LlmError::Config(format!("Database error: {}", e)))?;
+    }
+
+    Ok(Box::pin(futures::stream::select_all(streams)))
+}
+
+/// Save a compared prompt, pending until one of its replies is picked
+async fn save_prompt(pool: &SqlitePool, project_id: &str, prompt_id: &str, prompt: &str) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO messages (id, role, content, project_id, branch_status, created_at) VALUES (?, 'user', ?, ?, ?, ?)",
+    )
+    .bind(prompt_id)
+    .bind(prompt)
+    .bind(project_id)
+    .bind(PENDING)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE projects SET updated_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+/// Save one agent's reply to a compared prompt
+///
+/// A reply that finishes after another was already picked is saved as
+/// rejected.
+pub async fn save_candidate(
+    pool: &SqlitePool,
+    project_id: &str,
+    prompt_id: &str,
+    reply_id: &str,
+    reply: &str,
+    agent_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO messages (id, role, content, project_id, agent_id, parent_id, branch_status, created_at)
+        VALUES (?, 'assistant', ?, ?, ?, ?,
+                CASE WHEN EXISTS (SELECT 1 FROM messages WHERE id = ? AND branch_status IS NULL) THEN ? ELSE ? END,
+                ?)
+        "#,
+    )
+    .bind(reply_id)
+    .bind(reply)
+    .bind(project_id)
+    .bind(agent_id)
+    .bind(prompt_id)
+    .bind(prompt_id)
+    .bind(REJECTED)
+    .bind(PENDING)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Keep one reply of a comparison in the conversation, rejecting its siblings
+///
+/// Picking another reply of the same comparison later switches to it.
+pub async fn pick(pool: &SqlitePool, project_id: &str, message_id: &str) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let prompt_id: Option<String> = sqlx::query_scalar(
+        "SELECT parent_id FROM messages WHERE id = ? AND project_id = ? AND role = 'assistant' AND parent_id IS NOT NULL",
+    )
+    .bind(message_id)
+    .bind(project_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    let Some(prompt_id) = prompt_id else {
+        return Err(format!("Not a reply from a comparison: {}", message_id));
+    };
+
+    sqlx::query(
+        "UPDATE messages SET branch_status = CASE WHEN id = ? THEN NULL ELSE ? END WHERE parent_id = ? AND project_id = ?",
+    )
+    .bind(message_id)
+    .bind(REJECTED)
+    .bind(&prompt_id)
+    .bind(project_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    sqlx::query("UPDATE messages SET branch_status = NULL WHERE id = ?")
+        .bind(&prompt_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::StreamResponse;
+    use crate::llm::Priority;
+    use std::collections::HashMap;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_compare_saves_sibling_replies() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Site', 'website', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let compare = |agent_ids: &[&str]| CompareRequest {
+            request: StreamRequest {
+                prompt: "Build a landing page".to_string(),
+                agent_id: None,
+                provider: Some("demo".to_string()),
+                model: None,
+                project_id: Some("p1".to_string()),
+                files: None,
+                context: None,
+                tools: false,
+                template_id: None,
+                variables: HashMap::new(),
+                response_format: None,
+                priority: Priority::Interactive,
+                session_id: None,
+                system_prompt: None,
+                max_tokens: None,
+            },
+            agent_ids: agent_ids.iter().map(|id| id.to_string()).collect(),
+        };
+        let pending = || futures::future::pending();
+        assert!(start(&pool, Principal::desktop(), compare(&["ui-designer"]), pending()).await.is_err());
+        assert!(start(&pool, Principal::desktop(), compare(&["ui-designer", "ui-designer"]), pending()).await.is_err());
+
+        let events: Vec<ComparisonEvent> = start(&pool, Principal::desktop(), compare(&["ui-designer", "devops-engineer"]), pending())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let done: Vec<(&str, &str)> = events
+            .iter()
+            .filter_map(|event| match &event.event {
+                GenerationEvent::Message(StreamResponse { id, done: true, .. }) => Some((event.agent_id.as_str(), id.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(done.len(), 2);
+        assert!(done.iter().any(|(agent_id, _)| *agent_id == "devops-engineer"));
+
+        // Neither reply, nor the prompt, is in the conversation until one is picked
+        let history = || crate::llm::build_context(&pool, "p1", "Next", "claude-sonnet-4-5", 1024);
+        assert!(history().await.unwrap().messages.is_empty());
+
+        let (agent_id, reply_id) = done[0];
+        assert!(pick(&pool, "p1", "missing").await.is_err());
+        pick(&pool, "p1", reply_id).await.unwrap();
+        let messages = history().await.unwrap().messages;
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "Build a landing page");
+
+        let statuses: Vec<(Option<String>, Option<String>)> =
+            sqlx::query_as("SELECT agent_id, branch_status FROM messages WHERE role = 'assistant' ORDER BY agent_id = ? DESC")
+                .bind(agent_id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(statuses[0].1, None);
+        assert_eq!(statuses[1].1.as_deref(), Some(REJECTED));
+    }
+}
@@ -80,6 +80,14 @@ pub async fn init_database() -> Result<(), sqlx::Error> {
     Ok(())
 }
 
+/// Schema version written by `run_migrations`; bump when adding a migration
+pub const SCHEMA_VERSION: i64 = 25;
+
+/// Schema version recorded in the database (0 before migrations have run)
+pub async fn schema_version(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("PRAGMA user_version").fetch_one(pool).await
+}
+
 /// Run database migrations
 async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     // Create users table
@@ -111,7 +119,6 @@ async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             name TEXT NOT NULL,
             description TEXT,
             project_type TEXT NOT NULL,
-            active_agents TEXT DEFAULT '[]' NOT NULL,
             current_code TEXT,
             visibility TEXT DEFAULT 'PRIVATE' NOT NULL,
             likes INTEGER DEFAULT 0 NOT NULL,
@@ -192,6 +199,385 @@ async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await?;
 
+    // Create provider_credentials table (one row per LLM provider)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS provider_credentials (
+            provider TEXT PRIMARY KEY NOT NULL,
+            api_key TEXT NOT NULL,
+            base_url TEXT,
+            last_validated TEXT,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create oauth_tokens table (browser-based Anthropic sign-in)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS oauth_tokens (
+            id INTEGER PRIMARY KEY DEFAULT 1,
+            access_token TEXT NOT NULL,
+            refresh_token TEXT,
+            expires_at INTEGER,
+            scope TEXT,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create vault_config table (optional master-passphrase app lock)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS vault_config (
+            id INTEGER PRIMARY KEY DEFAULT 1,
+            salt TEXT NOT NULL,
+            verifier TEXT NOT NULL,
+            idle_timeout_minutes INTEGER DEFAULT 15 NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create sessions table (embedded server bearer tokens)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS sessions (
+            id TEXT PRIMARY KEY NOT NULL,
+            user_id TEXT NOT NULL,
+            token TEXT UNIQUE NOT NULL,
+            device_name TEXT NOT NULL,
+            user_agent TEXT,
+            ip_address TEXT,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            last_seen_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            expires_at TEXT NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id)")
+        .execute(pool)
+        .await?;
+
+    // Create devices table (paired LAN devices with scoped tokens)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS devices (
+            id TEXT PRIMARY KEY NOT NULL,
+            name TEXT NOT NULL,
+            token_hash TEXT UNIQUE NOT NULL,
+            scope TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            ip_address TEXT,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            last_seen_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create project_shares table (per-project ACLs for users and devices)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS project_shares (
+            id TEXT PRIMARY KEY NOT NULL,
+            project_id TEXT NOT NULL,
+            grantee_type TEXT NOT NULL,
+            grantee_id TEXT NOT NULL,
+            access TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+            UNIQUE(project_id, grantee_type, grantee_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create usage_events table (token usage ledger, one row per generation)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS usage_events (
+            id TEXT PRIMARY KEY NOT NULL,
+            user_id TEXT NOT NULL,
+            project_id TEXT,
+            model TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL,
+            stop_reason TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE SET NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_usage_events_user_created ON usage_events(user_id, created_at)")
+        .execute(pool)
+        .await?;
+
+    // Create prompt_templates table (user-defined; built-ins live in code)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS prompt_templates (
+            id TEXT PRIMARY KEY NOT NULL,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            project_type TEXT,
+            body TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create project_templates table (user-defined starters; built-ins live in code)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS project_templates (
+            id TEXT PRIMARY KEY NOT NULL,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            project_type TEXT NOT NULL,
+            files TEXT DEFAULT '[]' NOT NULL,
+            prompt TEXT,
+            agents TEXT DEFAULT '[]' NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create attachments table (images referenced from stream requests)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS attachments (
+            id TEXT PRIMARY KEY NOT NULL,
+            user_id TEXT NOT NULL,
+            media_type TEXT NOT NULL,
+            data BLOB NOT NULL,
+            size INTEGER NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create generation_queue table (generations waiting out a provider's rate limit)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS generation_queue (
+            id TEXT PRIMARY KEY NOT NULL,
+            run_id TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            priority INTEGER NOT NULL,
+            enqueued_at INTEGER NOT NULL,
+            resume_at INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_generation_queue_provider ON generation_queue(provider, priority)")
+        .execute(pool)
+        .await?;
+
+    // Create agents table (built-ins synced from the manifest below, then editable)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS agents (
+            id TEXT PRIMARY KEY NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL,
+            category TEXT NOT NULL,
+            capabilities TEXT DEFAULT '[]' NOT NULL,
+            model TEXT NOT NULL,
+            icon TEXT NOT NULL,
+            system_prompt TEXT NOT NULL,
+            temperature REAL NOT NULL,
+            builtin INTEGER DEFAULT 0 NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_agents_category ON agents(category COLLATE NOCASE)")
+        .execute(pool)
+        .await?;
+
+    // Agents attached to each project, in order
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS project_agents (
+            project_id TEXT NOT NULL,
+            agent_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            PRIMARY KEY (project_id, agent_id),
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Agent teams: named, ordered groups of agents
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS agent_teams (
+            id TEXT PRIMARY KEY NOT NULL,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            project_type TEXT,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS agent_team_members (
+            team_id TEXT NOT NULL,
+            agent_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            PRIMARY KEY (team_id, position),
+            FOREIGN KEY (team_id) REFERENCES agent_teams(id) ON DELETE CASCADE,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Agents each user has favorited
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS agent_favorites (
+            user_id TEXT NOT NULL,
+            agent_id TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            PRIMARY KEY (user_id, agent_id),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Uploaded agent icons (SVG or PNG)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS agent_icons (
+            agent_id TEXT PRIMARY KEY NOT NULL,
+            media_type TEXT NOT NULL,
+            data BLOB NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Agent definitions fetched from the remote registry, awaiting review
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS registry_agents (
+            agent_id TEXT PRIMARY KEY NOT NULL,
+            definition TEXT NOT NULL,
+            digest TEXT NOT NULL,
+            status TEXT DEFAULT 'pending' NOT NULL,
+            fetched_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Agent tasks run on a cron schedule
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS agent_schedules (
+            id TEXT PRIMARY KEY NOT NULL,
+            user_id TEXT NOT NULL,
+            project_id TEXT NOT NULL,
+            agent_id TEXT NOT NULL,
+            prompt TEXT NOT NULL,
+            cron TEXT NOT NULL,
+            next_run_at TEXT NOT NULL,
+            last_run_at TEXT,
+            last_error TEXT,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_agent_schedules_next_run ON agent_schedules(next_run_at)")
+        .execute(pool)
+        .await?;
+
+    // What a project's files looked like at a point in time (see snapshots)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS project_snapshots (
+            id TEXT PRIMARY KEY NOT NULL,
+            project_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            files TEXT NOT NULL,
+            hash TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_project_snapshots_project_created ON project_snapshots(project_id, created_at)")
+        .execute(pool)
+        .await?;
+
     // Create default user if not exists
     let user_count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
         .fetch_one(pool)
@@ -210,10 +596,197 @@ async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
         println!("✅ Created default local user");
     }
 
+    // Add role column to users (owner, editor, viewer)
+    add_column_if_missing(pool, "users", "role", "TEXT DEFAULT 'editor' NOT NULL").await?;
+
+    // The local desktop user always owns the installation
+    sqlx::query("UPDATE users SET role = 'owner' WHERE id = 'local-user'")
+        .execute(pool)
+        .await?;
+
+    // Prompt a project was generated from (set by the embedded server API)
+    add_column_if_missing(pool, "projects", "prompt", "TEXT").await?;
+
+    // TOTP two-factor columns (secret is sealed by the credential vault)
+    add_column_if_missing(pool, "users", "totp_secret", "TEXT").await?;
+    add_column_if_missing(pool, "users", "totp_enabled", "INTEGER DEFAULT 0 NOT NULL").await?;
+    add_column_if_missing(pool, "users", "totp_last_step", "INTEGER").await?;
+
+    // How a project's history is fit into the model's context window
+    add_column_if_missing(pool, "projects", "context_strategy", "TEXT DEFAULT 'drop' NOT NULL").await?;
+
+    // Agent that produced an assistant message
+    add_column_if_missing(pool, "messages", "agent_id", "TEXT").await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_agent ON messages(project_id, agent_id)")
+        .execute(pool)
+        .await?;
+
+    // Replies from an agent comparison point at their shared prompt; both stay
+    // out of the history while pending, and unpicked replies are rejected
+    add_column_if_missing(pool, "messages", "parent_id", "TEXT").await?;
+    add_column_if_missing(pool, "messages", "branch_status", "TEXT").await?;
+
+    // Team last applied to a project
+    add_column_if_missing(pool, "projects", "team_id", "TEXT REFERENCES agent_teams(id) ON DELETE SET NULL").await?;
+
+    // Post-processors applied to a project's replies (see post_process)
+    add_column_if_missing(pool, "projects", "post_processors", "TEXT DEFAULT '[]' NOT NULL").await?;
+
+    // Folder a project was last written to (see scaffold)
+    add_column_if_missing(pool, "projects", "local_path", "TEXT").await?;
+
+    // Daily budgets (see budgets); consumption counts from the later of
+    // midnight UTC and budget_reset_at
+    for table in ["agents", "projects"] {
+        add_column_if_missing(pool, table, "daily_token_budget", "INTEGER").await?;
+        add_column_if_missing(pool, table, "daily_cost_budget", "REAL").await?;
+        add_column_if_missing(pool, table, "budget_reset_at", "TEXT").await?;
+    }
+    add_column_if_missing(pool, "usage_events", "agent_id", "TEXT").await?;
+    for index in [
+        "CREATE INDEX IF NOT EXISTS idx_usage_events_agent_created ON usage_events(agent_id, created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_usage_events_project_created ON usage_events(project_id, created_at)",
+    ] {
+        sqlx::query(index).execute(pool).await?;
+    }
+
+    // Edited built-ins are skipped by manifest syncs; ones edited before
+    // that was tracked count as edited
+    let tracked: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info('agents') WHERE name = 'customized'")
+        .fetch_one(pool)
+        .await?;
+    add_column_if_missing(pool, "agents", "customized", "INTEGER DEFAULT 0 NOT NULL").await?;
+    if tracked == 0 {
+        sqlx::query("UPDATE agents SET customized = 1 WHERE builtin = 1 AND updated_at > created_at")
+            .execute(pool)
+            .await?;
+    }
+
+    // Local tools each agent may use without asking (see tool_policy)
+    add_column_if_missing(
+        pool,
+        "agents",
+        "tool_permissions",
+        r#"TEXT DEFAULT '["read_files","write_files"]' NOT NULL"#,
+    )
+    .await?;
+
+    // Built-in agents' names and descriptions by language (see locale)
+    add_column_if_missing(pool, "agents", "translations", "TEXT DEFAULT '{}' NOT NULL").await?;
+
+    let manifest = crate::agent_manifest::sync(pool, crate::agent_manifest::bundled()).await?;
+    if manifest.changed() {
+        println!(
+            "✅ Synced built-in agents: {} added, {} updated, {} removed",
+            manifest.added.len(),
+            manifest.updated.len(),
+            manifest.removed.len() + manifest.detached.len()
+        );
+    }
+
+    // Move agents from the old `active_agents` JSON column into project_agents
+    let json_agents: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info('projects') WHERE name = 'active_agents'")
+        .fetch_one(pool)
+        .await?;
+    if json_agents > 0 {
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO project_agents (project_id, agent_id, position)
+            SELECT projects.id, agent.value, agent.key
+            FROM projects, json_each(projects.active_agents) AS agent
+            WHERE json_valid(projects.active_agents) AND agent.value IN (SELECT id FROM agents)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query("ALTER TABLE projects DROP COLUMN active_agents")
+            .execute(pool)
+            .await?;
+    }
+
+    create_search_index(pool).await?;
+
+    sqlx::query(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))
+        .execute(pool)
+        .await?;
+
     println!("✅ Database migrations completed");
     Ok(())
 }
 
+/// Full-text indexes over projects, messages, and project files (see search)
+///
+/// Each is an FTS5 table over its source table's rows, kept in sync by
+/// triggers. A newly created index is filled from the rows already there.
+async fn create_search_index(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    // (index, source table, indexed columns)
+    let indexes = [
+        ("projects_fts", "projects", ["name", "description", "current_code"].as_slice()),
+        ("messages_fts", "messages", ["content"].as_slice()),
+        ("project_files_fts", "project_files", ["path", "content"].as_slice()),
+    ];
+
+    for (index, table, columns) in indexes {
+        let exists: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(index)
+            .fetch_one(pool)
+            .await?;
+
+        let list = columns.join(", ");
+        let new_values = columns.iter().map(|c| format!("new.{}", c)).collect::<Vec<_>>().join(", ");
+        let old_values = columns.iter().map(|c| format!("old.{}", c)).collect::<Vec<_>>().join(", ");
+        let remove = format!(
+            "INSERT INTO {index}({index}, rowid, {list}) VALUES ('delete', old.rowid, {old_values});"
+        );
+        let add = format!("INSERT INTO {index}(rowid, {list}) VALUES (new.rowid, {new_values});");
+
+        for statement in [
+            format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS {index} USING fts5({list}, content='{table}', content_rowid='rowid', tokenize='unicode61 remove_diacritics 2')"
+            ),
+            format!("CREATE TRIGGER IF NOT EXISTS {index}_insert AFTER INSERT ON {table} BEGIN {add} END"),
+            format!("CREATE TRIGGER IF NOT EXISTS {index}_delete AFTER DELETE ON {table} BEGIN {remove} END"),
+            format!("CREATE TRIGGER IF NOT EXISTS {index}_update AFTER UPDATE OF {list} ON {table} BEGIN {remove} {add} END"),
+        ] {
+            sqlx::query(&statement).execute(pool).await?;
+        }
+
+        if exists == 0 {
+            sqlx::query(&format!("INSERT INTO {index}({index}) VALUES ('rebuild')"))
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Add a column to an existing table if it is not already present
+///
+/// SQLite has no `ADD COLUMN IF NOT EXISTS`, so check `pragma_table_info` first.
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), sqlx::Error> {
+    let exists: i32 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?"
+    )
+    .bind(table)
+    .bind(column)
+    .fetch_one(pool)
+    .await?;
+
+    if exists == 0 {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,4 +825,53 @@ mod tests {
 
         assert_eq!(count, 1);
     }
+
+    #[tokio::test]
+    async fn test_migrations_are_idempotent() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        // Running migrations again must not fail on added columns
+        run_migrations(&pool).await.unwrap();
+
+        let role: String = sqlx::query_scalar("SELECT role FROM users WHERE id = 'local-user'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(role, "owner");
+    }
+
+    #[tokio::test]
+    async fn test_active_agents_move_to_project_agents() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        // A database from before project_agents, with an unknown agent
+        sqlx::query("ALTER TABLE projects ADD COLUMN active_agents TEXT DEFAULT '[]' NOT NULL")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"INSERT INTO projects (id, name, project_type, user_id, active_agents)
+               VALUES ('p1', 'Site', 'website', 'local-user', '["ui-designer", "gone", "backend-architect"]')"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        run_migrations(&pool).await.unwrap();
+
+        let agents = crate::project_agents::list(&pool, "p1").await.unwrap();
+        assert_eq!(agents, vec!["ui-designer", "backend-architect"]);
+        let column: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info('projects') WHERE name = 'active_agents'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(column, 0);
+    }
 }
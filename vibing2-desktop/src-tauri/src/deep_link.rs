@@ -0,0 +1,229 @@
+//! `vibing2://` links
+//!
+//! Links open the app from terminal output, docs, or a paired device:
+//!
+//! - `vibing2://project/<id>` opens a project (`load-project`)
+//! - `vibing2://new?prompt=...&agent=...` opens a new project with the
+//!   prompt filled in (`new-project`); nothing is sent until the user sends it
+//! - `vibing2://oauth/callback?...` finishes an OAuth sign-in
+//!
+//! Navigation shows the main window, recreating it if it was closed. A link
+//! that launched the app, or that recreated the window, is also kept for
+//! [`take_pending`] so the page can pick it up once it has loaded.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+/// URL scheme the app handles
+pub const SCHEME: &str = "vibing2";
+
+/// Longest prompt a link can fill in
+const MAX_PROMPT_CHARS: usize = 10_000;
+
+/// Navigation waiting for the main window's page to load
+static PENDING: Mutex<Option<DeepLink>> = Mutex::new(None);
+
+/// What a `vibing2://` link asks for
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeepLink {
+    /// Open a project
+    Project { project_id: String },
+    /// Start a new project with the prompt filled in
+    New {
+        prompt: Option<String>,
+        agent_id: Option<String>,
+    },
+    /// Finish an OAuth sign-in
+    OAuthCallback { url: String },
+}
+
+impl DeepLink {
+    /// Parse a `vibing2://` URL
+    pub fn parse(url: &str) -> Result<Self, String> {
+        if crate::oauth::is_callback_url(url) {
+            return Ok(DeepLink::OAuthCallback { url: url.to_string() });
+        }
+
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid link {}: {}", url, e))?;
+        if parsed.scheme() != SCHEME {
+            return Err(format!("Not a {}:// link: {}", SCHEME, url));
+        }
+        let query: HashMap<String, String> = parsed.query_pairs().into_owned().collect();
+        let param = |name: &str| {
+            query
+                .get(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        match parsed.host_str() {
+            Some("project") => {
+                let project_id = parsed
+                    .path_segments()
+                    .and_then(|mut segments| segments.next())
+                    .filter(|id| is_valid_id(id))
+                    .ok_or_else(|| format!("Link has no project ID: {}", url))?;
+                Ok(DeepLink::Project {
+                    project_id: project_id.to_string(),
+                })
+            }
+            Some("new") => Ok(DeepLink::New {
+                prompt: param("prompt").map(|prompt| prompt.chars().take(MAX_PROMPT_CHARS).collect()),
+                agent_id: param("agent").filter(|id| is_valid_id(id)),
+            }),
+            _ => Err(format!("Unknown link: {}", url)),
+        }
+    }
+}
+
+/// IDs are UUIDs or slugs; anything else in a link is rejected
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Act on a link opened while the app is running
+pub fn open(app: &tauri::AppHandle, url: &str) {
+    match DeepLink::parse(url) {
+        Ok(DeepLink::OAuthCallback { url }) => complete_oauth(app, url),
+        Ok(link) => navigate(app, link),
+        Err(e) => {
+            eprintln!("{}", e);
+            let _ = app.emit("deep-link-error", e);
+        }
+    }
+}
+
+/// Act on a link the app was launched with, before the page has loaded
+pub fn open_on_launch(app: &tauri::AppHandle, url: &str) {
+    match DeepLink::parse(url) {
+        Ok(DeepLink::OAuthCallback { url }) => complete_oauth(app, url),
+        Ok(link) => set_pending(link),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// The navigation waiting for the page, if any; cleared once taken
+pub fn take_pending() -> Option<DeepLink> {
+    PENDING.lock().ok().and_then(|mut pending| pending.take())
+}
+
+fn set_pending(link: DeepLink) {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = Some(link);
+    }
+}
+
+/// Show the main window and tell it where to go
+fn navigate(app: &tauri::AppHandle, link: DeepLink) {
+    let Some(window) = app.get_webview_window("main") else {
+        // The page picks the link up once the new window has loaded
+        set_pending(link);
+        if let Err(e) = create_main_window(app) {
+            eprintln!("Failed to open main window: {}", e);
+        }
+        return;
+    };
+
+    let _ = window.show();
+    let _ = window.unminimize();
+    let _ = window.set_focus();
+    match &link {
+        DeepLink::Project { project_id } => {
+            let _ = window.emit("load-project", project_id);
+        }
+        DeepLink::New { .. } => {
+            let _ = window.emit("new-project", &link);
+        }
+        DeepLink::OAuthCallback { .. } => {}
+    }
+}
+
+/// Recreate the main window from its configuration
+fn create_main_window(app: &tauri::AppHandle) -> Result<(), tauri::Error> {
+    let Some(config) = app.config().app.windows.iter().find(|window| window.label == "main") else {
+        return Ok(());
+    };
+    tauri::WebviewWindowBuilder::from_config(app, config)?.build()?;
+    Ok(())
+}
+
+/// Exchange the OAuth callback's code for tokens and report the result
+fn complete_oauth(app: &tauri::AppHandle, url: String) {
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = match crate::database::get_pool().await {
+            Ok(pool) => crate::oauth::complete_authorization(pool.as_ref(), &url).await,
+            Err(e) => Err(format!("Database error: {}", e)),
+        };
+
+        match result {
+            Ok(_) => {
+                crate::auth::invalidate_auth_cache(&handle).await;
+                crate::auth::emit_auth_event(&handle, crate::auth::AuthEvent::SignedIn {
+                    source: "oauth".to_string(),
+                    email: None,
+                });
+                let _ = handle.emit("oauth-complete", ());
+            }
+            Err(e) => {
+                eprintln!("OAuth sign-in failed: {}", e);
+                let _ = handle.emit("oauth-error", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project_link() {
+        assert_eq!(
+            DeepLink::parse("vibing2://project/3f2c9a1e-7b4d-4c1a-9e2f-1a2b3c4d5e6f").unwrap(),
+            DeepLink::Project {
+                project_id: "3f2c9a1e-7b4d-4c1a-9e2f-1a2b3c4d5e6f".to_string()
+            }
+        );
+        assert!(DeepLink::parse("vibing2://project/").is_err());
+        assert!(DeepLink::parse("vibing2://project/..%2Fsecrets").is_err());
+    }
+
+    #[test]
+    fn test_parse_new_link() {
+        assert_eq!(
+            DeepLink::parse("vibing2://new?prompt=A%20landing%20page&agent=ui-designer").unwrap(),
+            DeepLink::New {
+                prompt: Some("A landing page".to_string()),
+                agent_id: Some("ui-designer".to_string()),
+            }
+        );
+        assert_eq!(
+            DeepLink::parse("vibing2://new?prompt=+").unwrap(),
+            DeepLink::New {
+                prompt: None,
+                agent_id: None
+            }
+        );
+
+        let long = format!("vibing2://new?prompt={}", "a".repeat(MAX_PROMPT_CHARS + 10));
+        match DeepLink::parse(&long).unwrap() {
+            DeepLink::New { prompt, .. } => assert_eq!(prompt.unwrap().len(), MAX_PROMPT_CHARS),
+            other => panic!("unexpected link: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_other_links() {
+        assert!(matches!(
+            DeepLink::parse("vibing2://oauth/callback?code=abc&state=xyz").unwrap(),
+            DeepLink::OAuthCallback { .. }
+        ));
+        assert!(DeepLink::parse("vibing2://settings").is_err());
+        assert!(DeepLink::parse("https://project/abc").is_err());
+        assert!(DeepLink::parse("not a link").is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! Syncing edits on disk back into projects
+//!
+//! Once a project is written to disk (see `scaffold`), its folder is watched.
+//! Edits made there in other tools (an editor, a formatter, `git checkout`)
+//! are saved to the project's files, so agents read the latest code, and
+//! reported as `project-files-changed`.
+//!
+//! The folder's manifest tells edits apart from the app's own writes: a file
+//! whose content matches the manifest was written by the app. Files only
+//! ever created on disk are picked up too, except in dependency and build
+//! folders, binary files, and files over `MAX_FILE_BYTES`.
+
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::Emitter;
+
+/// Quiet period before a burst of file events is synced
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Larger files aren't synced
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Folders never synced, wherever they are in the project
+const IGNORED_DIRS: &[&str] = &[".vibing2", ".git", "node_modules", "dist", "build", "target", ".next"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+/// Payload of `project-files-changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct FilesChanged {
+    pub project_id: String,
+    pub changes: Vec<FileChange>,
+}
+
+/// A watched project folder; watching stops when dropped
+struct Watch {
+    root: PathBuf,
+    _watcher: notify::RecommendedWatcher,
+}
+
+fn watches() -> &'static Mutex<HashMap<String, Watch>> {
+    static WATCHES: OnceLock<Mutex<HashMap<String, Watch>>> = OnceLock::new();
+    WATCHES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Watch a project's folder, replacing any earlier watch of the project
+pub fn watch(app: &tauri::AppHandle, project_id: &str, root: &Path) -> Result<(), String> {
+    if let Ok(watches) = watches().lock() {
+        if watches.get(project_id).is_some_and(|watch| watch.root == root) {
+            return Ok(());
+        }
+    }
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        if let Ok(event) = result {
+            if !event.kind.is_access() {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+    watcher
+        .watch(root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+
+    let app = app.clone();
+    let id = project_id.to_string();
+    let folder = root.to_path_buf();
+    tauri::async_runtime::spawn(async move {
+        while let Some(path) = rx.recv().await {
+            // Let the editor finish writing before syncing
+            let mut paths = BTreeSet::from([path]);
+            loop {
+                match tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                    Ok(Some(path)) => {
+                        paths.insert(path);
+                    }
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+
+            let changed: Vec<String> = paths.iter().filter_map(|path| relative_path(&folder, path)).collect();
+            if changed.is_empty() {
+                continue;
+            }
+            let result = match crate::database::get_pool().await {
+                Ok(pool) => sync(pool.as_ref(), &id, &folder, &changed).await,
+                Err(e) => Err(format!("Database error: {}", e)),
+            };
+            match result {
+                Ok(changes) if changes.is_empty() => {}
+                Ok(changes) => {
+                    println!("📝 Synced {} edited file(s) into project {}", changes.len(), id);
+                    let _ = app.emit(
+                        "project-files-changed",
+                        FilesChanged {
+                            project_id: id.clone(),
+                            changes,
+                        },
+                    );
+                }
+                Err(e) => eprintln!("Failed to sync edits in {}: {}", folder.display(), e),
+            }
+        }
+    });
+
+    println!("👀 Watching {} for edits to project {}", root.display(), project_id);
+    if let Ok(mut watches) = watches().lock() {
+        watches.insert(
+            project_id.to_string(),
+            Watch {
+                root: root.to_path_buf(),
+                _watcher: watcher,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Stop watching a project's folder
+pub fn unwatch(project_id: &str) {
+    if let Ok(mut watches) = watches().lock() {
+        watches.remove(project_id);
+    }
+}
+
+/// Watch every project that has been written to disk
+pub async fn watch_all(app: &tauri::AppHandle, pool: &SqlitePool) {
+    let rows = match sqlx::query("SELECT id, local_path FROM projects WHERE local_path IS NOT NULL")
+        .fetch_all(pool)
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Failed to list project folders: {}", e);
+            return;
+        }
+    };
+    for row in rows {
+        let project_id: String = row.get("id");
+        let root = PathBuf::from(row.get::<String, _>("local_path"));
+        // Folders moved or deleted since are skipped
+        if root.is_dir() {
+            if let Err(e) = watch(app, &project_id, &root) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+}
+
+/// `path` relative to `root` with `/` separators, or `None` if it's outside
+/// `root` or in an ignored folder
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        let Component::Normal(part) = component else {
+            return None;
+        };
+        let part = part.to_str()?;
+        if IGNORED_DIRS.contains(&part) {
+            return None;
+        }
+        parts.push(part);
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// A file's text, or `None` if it's missing, a folder, too large, or binary
+async fn read_text(file: &Path) -> Option<String> {
+    let metadata = tokio::fs::metadata(file).await.ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    String::from_utf8(tokio::fs::read(file).await.ok()?).ok()
+}
+
+/// Save edits on disk to the project's files
+///
+/// Looks at `changed` (paths relative to `root`) and every file in the
+/// manifest, since removing a folder only reports the folder. An edit on
+/// disk wins over an unwritten change to the same file in the project, being
+/// the later of the two.
+pub async fn sync(
+    pool: &SqlitePool,
+    project_id: &str,
+    root: &Path,
+    changed: &[String],
+) -> Result<Vec<FileChange>, String> {
+    let Some(mut manifest) = crate::scaffold::read_manifest(root).await else {
+        return Ok(Vec::new());
+    };
+    if manifest.project_id != project_id {
+        return Ok(Vec::new());
+    }
+    let stored = crate::scaffold::project_files(pool, project_id).await?;
+
+    let candidates: BTreeSet<String> = manifest.files.keys().cloned().chain(changed.iter().cloned()).collect();
+    let mut changes = Vec::new();
+    for path in candidates {
+        let Ok(file) = crate::sandbox::jailed(root, &path) else {
+            continue;
+        };
+        let recorded = manifest.files.get(&path).cloned();
+        let current = stored.get(&path);
+
+        let Some(content) = read_text(&file).await else {
+            // Deleted on disk; only files the app wrote are removed from the project
+            if tokio::fs::try_exists(&file).await.unwrap_or(true) || recorded.is_none() {
+                continue;
+            }
+            manifest.files.remove(&path);
+            if current.is_some() {
+                delete_file(pool, project_id, &path).await?;
+                changes.push(FileChange {
+                    path,
+                    kind: ChangeKind::Deleted,
+                });
+            }
+            continue;
+        };
+
+        let hash = crate::scaffold::hash(content.as_bytes());
+        if recorded.as_ref() == Some(&hash) {
+            // The app's own write
+            continue;
+        }
+        manifest.files.insert(path.clone(), hash);
+        if current == Some(&content) {
+            continue;
+        }
+
+        save_file(pool, project_id, &path, &content).await?;
+        changes.push(FileChange {
+            kind: if current.is_some() { ChangeKind::Modified } else { ChangeKind::Created },
+            path,
+        });
+    }
+
+    manifest.written_at = chrono::Utc::now().to_rfc3339();
+    crate::scaffold::write_manifest(root, &manifest).await?;
+    Ok(changes)
+}
+
+/// Save a file edited on disk; `index.html` goes to `current_code` for
+/// projects that keep their page there
+async fn save_file(pool: &SqlitePool, project_id: &str, path: &str, content: &str) -> Result<(), String> {
+    if path == crate::scaffold::CURRENT_CODE_PATH && !has_file(pool, project_id, path).await? {
+        sqlx::query("UPDATE projects SET current_code = ?, updated_at = datetime('now') WHERE id = ?")
+            .bind(content)
+            .bind(project_id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        return Ok(());
+    }
+    crate::agent_tools::save_file(pool, project_id, path, content)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", path, e))
+}
+
+async fn delete_file(pool: &SqlitePool, project_id: &str, path: &str) -> Result<(), String> {
+    if path == crate::scaffold::CURRENT_CODE_PATH && !has_file(pool, project_id, path).await? {
+        sqlx::query("UPDATE projects SET current_code = NULL, updated_at = datetime('now') WHERE id = ?")
+            .bind(project_id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        return Ok(());
+    }
+    sqlx::query("DELETE FROM project_files WHERE project_id = ? AND path = ?")
+        .bind(project_id)
+        .bind(path)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    sqlx::query("UPDATE projects SET updated_at = datetime('now') WHERE id = ?")
+        .bind(project_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(())
+}
+
+async fn has_file(pool: &SqlitePool, project_id: &str, path: &str) -> Result<bool, String> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM project_files WHERE project_id = ? AND path = ?")
+        .bind(project_id)
+        .bind(path)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scaffold::{materialize, ConflictPolicy};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_relative_path() {
+        let root = Path::new("/projects/site");
+        assert_eq!(relative_path(root, Path::new("/projects/site/src/app.js")).as_deref(), Some("src/app.js"));
+        assert_eq!(relative_path(root, Path::new("/projects/site/node_modules/x/index.js")), None);
+        assert_eq!(relative_path(root, Path::new("/projects/site/.vibing2/manifest.json")), None);
+        assert_eq!(relative_path(root, Path::new("/projects/other/app.js")), None);
+        assert_eq!(relative_path(root, root), None);
+    }
+
+    #[tokio::test]
+    async fn test_sync_edits() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, current_code, user_id) VALUES ('p1', 'Site', 'website', '<h1>Hi</h1>', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        crate::agent_tools::save_file(&pool, "p1", "style.css", "body {}").await.unwrap();
+        crate::agent_tools::save_file(&pool, "p1", "app.js", "run()").await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        materialize(&pool, "p1", dir.path(), ConflictPolicy::Keep).await.unwrap();
+
+        // Nothing edited yet
+        assert!(sync(&pool, "p1", dir.path(), &[]).await.unwrap().is_empty());
+
+        std::fs::write(dir.path().join("style.css"), "body { margin: 0 }").unwrap();
+        std::fs::write(dir.path().join("index.html"), "<h1>Hello</h1>").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "# Notes").unwrap();
+        std::fs::write(dir.path().join("logo.png"), [0x89, 0x50, 0xff, 0xfe]).unwrap();
+        std::fs::remove_file(dir.path().join("app.js")).unwrap();
+        let changed: Vec<String> = ["style.css", "index.html", "notes.md", "logo.png", "app.js"]
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+
+        let changes = sync(&pool, "p1", dir.path(), &changed).await.unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                FileChange { path: "app.js".to_string(), kind: ChangeKind::Deleted },
+                FileChange { path: "index.html".to_string(), kind: ChangeKind::Modified },
+                FileChange { path: "notes.md".to_string(), kind: ChangeKind::Created },
+                FileChange { path: "style.css".to_string(), kind: ChangeKind::Modified },
+            ]
+        );
+
+        let files = crate::scaffold::project_files(&pool, "p1").await.unwrap();
+        assert_eq!(files.get("style.css").unwrap(), "body { margin: 0 }");
+        assert_eq!(files.get("index.html").unwrap(), "<h1>Hello</h1>");
+        assert_eq!(files.get("notes.md").unwrap(), "# Notes");
+        assert!(!files.contains_key("app.js"));
+        assert!(!files.contains_key("logo.png"));
+
+        // Synced edits are in the manifest, so writing the project back is a no-op
+        assert!(sync(&pool, "p1", dir.path(), &changed).await.unwrap().is_empty());
+        let report = materialize(&pool, "p1", dir.path(), ConflictPolicy::Keep).await.unwrap();
+        assert!(report.written.is_empty() && report.conflicts.is_empty());
+    }
+}
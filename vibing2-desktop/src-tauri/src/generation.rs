@@ -0,0 +1,1100 @@
+//! Agent generations shared by the SSE route and the IPC command
+//!
+//! [`start`] resolves the provider for a [`StreamRequest`], applies the
+//! retry policy, and relays the output as [`GenerationEvent`]s. These are the
+//! events `/api/agent/stream` sends over SSE and `start_generation` sends
+//! over a Tauri channel, so the frontend can use either transport. Usage is
+//! recorded in the ledger when a generation ends, and for project
+//! generations the prompt and reply are saved to the project's messages,
+//! after the project's post-processors (see [`crate::post_process`]).
+//!
+//! With `tools` set, the model can call the project tools in
+//! [`crate::agent_tools`]; each call and its result are relayed as
+//! `tool_use` and `tool_result` events.
+//!
+//! When stream recording is turned on in settings, the raw provider frames
+//! of each session are written to disk, and [`replay`] re-emits them through
+//! the same parsers without calling the provider.
+
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::attachments::{self, Image};
+use crate::budgets::Remaining;
+use crate::llm::{
+    self, ChatMessage, ContentBlock, ErrorCode, GenerationRequest, LlmError, Priority, QueueStatus, ResponseFormat,
+    RetryStatus, SchemaError, StreamEvent, ToolCall, ToolResult,
+};
+use crate::post_process::PostProcessor;
+use crate::sharing::{self, Access, Principal};
+use crate::usage::{self, Usage};
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct StreamRequest {
+    pub prompt: String,
+    pub agent_id: Option<String>,
+    /// `anthropic`, `openai`, `custom`, `ollama`, or `demo`; inferred from
+    /// `model` when omitted, including models pulled into a local Ollama
+    pub provider: Option<String>,
+    /// Provider model name; the provider's default when omitted
+    pub model: Option<String>,
+    /// Project the generation is for; the prompt and reply are saved to its
+    /// messages and its usage recorded in the ledger
+    pub project_id: Option<String>,
+    pub files: Option<Vec<FileContent>>,
+    #[schema(value_type = Option<Object>)]
+    pub context: Option<serde_json::Value>,
+    /// Let the agent read the project's files, and change them with write
+    /// access; requires `project_id`
+    #[serde(default)]
+    pub tools: bool,
+    /// Prompt template to render; `prompt` is available to it as
+    /// `{{prompt}}`
+    pub template_id: Option<String>,
+    /// Values for the template's other variables
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// Ask for a JSON reply conforming to a schema; a reply that doesn't is
+    /// reported with a `validation_error` event before `done`
+    pub response_format: Option<ResponseFormat>,
+    /// Who goes first when the provider is rate limiting: `interactive`
+    /// (the default) or `background`
+    #[serde(default)]
+    pub priority: Priority,
+    /// Stream session the generation runs in, set by the caller; names the
+    /// recording when stream recording is on
+    #[serde(skip)]
+    pub session_id: Option<String>,
+    /// System prompt to use instead of the agent's, set by the caller
+    #[serde(skip)]
+    pub system_prompt: Option<String>,
+    /// Reply length cap, set by the caller; the provider default when unset
+    #[serde(skip)]
+    pub max_tokens: Option<u32>,
+}
+
+/// A file attached to the prompt: text, or an image for vision models
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct FileContent {
+    pub path: String,
+    /// File text, or base64 data (or a `data:` URL) for an image
+    #[serde(default)]
+    pub content: String,
+    /// `image/png`, `image/jpeg`, `image/gif`, or `image/webp` to send
+    /// `content` as an image
+    pub media_type: Option<String>,
+    /// Uploaded image (see `/api/attachments`) to send instead of `content`
+    pub attachment_id: Option<String>,
+}
+
+impl FileContent {
+    fn is_image(&self) -> bool {
+        self.attachment_id.is_some() || self.media_type.as_deref().is_some_and(|t| t.starts_with("image/"))
+    }
+}
+
+/// Most images sent with one prompt
+const MAX_IMAGES: usize = 20;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StreamResponse {
+    pub id: String,
+    pub content: String,
+    pub role: String,
+    pub done: bool,
+    /// Token usage, sent only on the final `done` event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Cost of the generation in USD, sent on the final `done` event when the
+    /// model is priced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+/// A trial run of an agent, see [`test_agent`]
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct AgentTest {
+    /// Input to run; a short sample request when omitted
+    pub input: Option<String>,
+    /// Unsaved system prompt to try instead of the agent's
+    pub system_prompt: Option<String>,
+    /// Model to run; a cheap model from the agent's provider when omitted
+    pub model: Option<String>,
+}
+
+/// Input for agent test runs that don't bring their own
+const AGENT_TEST_INPUT: &str =
+    "Introduce yourself in two sentences, then outline how you would build a landing page for a small bakery.";
+
+/// Reply length cap for agent test runs
+const AGENT_TEST_MAX_TOKENS: u32 = 512;
+
+/// Result of [`complete`]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CompletionResponse {
+    /// Message ID; the reply is saved under it for project generations
+    pub id: String,
+    pub content: String,
+    pub usage: Usage,
+    /// Cost in USD, when the model is priced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    /// How the reply breaks `response_format`, when it does
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub validation_errors: Vec<SchemaError>,
+}
+
+/// Why a generation failed
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct StreamError {
+    pub code: ErrorCode,
+    /// Whether sending the same request again may succeed
+    pub retryable: bool,
+    /// Human-readable description, including the provider's message
+    pub message: String,
+}
+
+impl From<&LlmError> for StreamError {
+    fn from(error: &LlmError) -> Self {
+        Self {
+            code: error.code(),
+            retryable: error.is_transient(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// An event of a running generation
+///
+/// Serialized as `{"event": ..., "data": ...}`; over SSE, `event` is the
+/// SSE event name and `data` its payload.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum GenerationEvent {
+    /// A chunk of output, or the final `done` message with usage
+    Message(StreamResponse),
+    /// A failed attempt is being retried or failed over
+    Retry(RetryStatus),
+    /// The provider is rate limiting; the generation waits in the queue
+    RateLimited(QueueStatus),
+    /// The app is offline; the generation waits for the connection
+    Offline { provider: String },
+    /// The model called a project tool
+    ToolUse(ToolCall),
+    /// A tool call finished; its result was sent back to the model
+    ToolResult(ToolResult),
+    /// The finished reply doesn't conform to `response_format`
+    ValidationError { errors: Vec<SchemaError> },
+    /// The generation failed; a final `done` message follows
+    Error(StreamError),
+}
+
+impl GenerationEvent {
+    /// SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            GenerationEvent::Message(_) => "message",
+            GenerationEvent::Retry(_) => "retry",
+            GenerationEvent::RateLimited(_) => "rate_limited",
+            GenerationEvent::Offline { .. } => "offline",
+            GenerationEvent::ToolUse(_) => "tool_use",
+            GenerationEvent::ToolResult(_) => "tool_result",
+            GenerationEvent::ValidationError { .. } => "validation_error",
+            GenerationEvent::Error(_) => "error",
+        }
+    }
+
+    /// SSE data payload
+    pub fn data(&self) -> String {
+        let data = match self {
+            GenerationEvent::Message(response) => serde_json::to_string(response),
+            GenerationEvent::Retry(status) => serde_json::to_string(status),
+            GenerationEvent::RateLimited(status) => serde_json::to_string(status),
+            GenerationEvent::Offline { provider } => {
+                serde_json::to_string(&serde_json::json!({ "provider": provider }))
+            }
+            GenerationEvent::ToolUse(call) => serde_json::to_string(call),
+            GenerationEvent::ToolResult(result) => serde_json::to_string(result),
+            GenerationEvent::ValidationError { errors } => {
+                serde_json::to_string(&serde_json::json!({ "errors": errors }))
+            }
+            GenerationEvent::Error(error) => serde_json::to_string(error),
+        };
+        data.unwrap_or_default()
+    }
+}
+
+/// Resolve the provider for `request` and start generating
+///
+/// When the request names a project, its saved conversation is sent along,
+/// cut down to the model's context window with the project's strategy.
+/// With `template_id`, the prompt is rendered from that template first.
+/// With `agent_id`, the agent's system prompt and temperature apply, and its
+/// preferred model is used unless the request picks a model or another
+/// provider. A chosen model is checked against the provider's catalog.
+///
+/// Fails before any event if the project isn't readable by `principal`,
+/// tools are asked for without a project, the template can't be rendered,
+/// the agent is unknown, the agent's or project's daily budget is spent,
+/// the model isn't offered, the response schema isn't an object, an image
+/// is invalid or the provider can't take images, or the provider can't be
+/// configured. The stream ends with a `done` message;
+/// when `cancel` completes first, the generation stops early and still ends
+/// with one.
+pub async fn start(
+    pool: &SqlitePool,
+    principal: Principal<'_>,
+    request: StreamRequest,
+    cancel: impl Future<Output = ()> + Send + 'static,
+) -> Result<BoxStream<'static, GenerationEvent>, LlmError> {
+    start_branch(pool, principal, request, cancel, None).await
+}
+
+/// Render the request's prompt template into `prompt`, if it names one
+pub(crate) async fn render_prompt(
+    pool: &SqlitePool,
+    principal: Principal<'_>,
+    request: &mut StreamRequest,
+) -> Result<(), LlmError> {
+    if let Some(template_id) = request.template_id.take() {
+        let mut values = request.variables.clone();
+        values.insert("prompt".to_string(), request.prompt.clone());
+        request.prompt = crate::templates::render_template(pool, principal.user_id, &template_id, &values)
+            .await
+            .map_err(LlmError::Config)?;
+    }
+    Ok(())
+}
+
+/// [`start`], saving the reply as one of the candidate replies to the
+/// already saved prompt `branch` (see [`crate::comparison`]) instead of
+/// saving a new exchange
+pub(crate) async fn start_branch(
+    pool: &SqlitePool,
+    principal: Principal<'_>,
+    mut request: StreamRequest,
+    cancel: impl Future<Output = ()> + Send + 'static,
+    branch: Option<String>,
+) -> Result<BoxStream<'static, GenerationEvent>, LlmError> {
+    render_prompt(pool, principal, &mut request).await?;
+
+    if let Some(project_id) = &request.project_id {
+        sharing::require_access(pool, project_id, principal, Access::Read)
+            .await
+            .map_err(LlmError::Config)?;
+    }
+
+    if let Some(format) = &request.response_format {
+        format.check()?;
+    }
+
+    let agent = match request.agent_id.as_deref().filter(|id| !id.is_empty()) {
+        Some(id) => Some(
+            crate::agents::get(pool, id)
+                .await
+                .map_err(|e| LlmError::Config(format!("Database error: {}", e)))?
+                .ok_or_else(|| LlmError::Config(format!("Unknown agent: {}", id)))?,
+        ),
+        None => None,
+    };
+    // Refuse once the project's or agent's daily budget is spent
+    let agent_id = agent.as_ref().map(|agent| agent.id.as_str());
+    let remaining = crate::budgets::check(pool, request.project_id.as_deref(), agent_id).await?;
+
+    // Tools and post-processors that write files need write access
+    let access = match &request.project_id {
+        Some(project_id) => match sharing::require_access(pool, project_id, principal, Access::Write).await {
+            Ok(()) => Access::Write,
+            Err(_) => Access::Read,
+        },
+        None => Access::Read,
+    };
+    // Tool calls are limited to the agent's declared permissions, or the
+    // defaults without one
+    let tools = match (&request.project_id, request.tools) {
+        (Some(project_id), true) => {
+            let gate = match &agent {
+                Some(agent) => crate::tool_policy::ToolGate::for_agent(agent),
+                None => crate::tool_policy::ToolGate::without_agent(),
+            };
+            Some(Arc::new(crate::agent_tools::registry_for_project(pool, project_id, principal, access, gate)))
+        }
+        (None, true) => return Err(LlmError::Config("Tools require a project_id".to_string())),
+        (_, false) => None,
+    };
+    let model = request.model.clone().or_else(|| {
+        let agent = agent.as_ref()?;
+        let provider_matches = request
+            .provider
+            .as_deref()
+            .is_none_or(|provider| llm::ProviderKind::parse(provider).ok() == llm::ProviderKind::infer(&agent.model));
+        provider_matches.then(|| agent.model.clone())
+    });
+
+    let mut policy = llm::load_policy(pool).await;
+    policy.queue = Some(llm::RateLimitQueue::new(pool.clone(), request.priority));
+    let requested = model.is_some();
+    let images = load_images(pool, principal.user_id, request.files.as_deref().unwrap_or_default()).await?;
+    let (provider, model) = llm::resolve(pool, request.provider.as_deref(), model.as_deref()).await?;
+    if requested {
+        llm::validate_model(provider.as_ref(), &model).await?;
+    }
+    if !images.is_empty() && !provider.supports_images() {
+        return Err(LlmError::Config(format!("The {} provider can't take images", provider.kind().as_str())));
+    }
+
+    // A misconfigured failover shouldn't block the primary provider
+    let failover = match &policy.failover_provider {
+        Some(name) if name.as_str() != provider.kind().as_str() => {
+            match llm::resolve(pool, Some(name), policy.failover_model.as_deref()).await {
+                Ok(failover) => Some(failover),
+                Err(e) => {
+                    eprintln!("Failover provider unavailable: {}", e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let mut generation = generation_request(&request, model, images);
+    if let Some(session_id) = &request.session_id {
+        if llm::recording_enabled(pool).await {
+            match llm::Recorder::create(session_id) {
+                Ok(recorder) => generation.recorder = Some(recorder),
+                Err(e) => eprintln!("Stream recording unavailable: {}", e),
+            }
+        }
+    }
+    if let Some(agent) = &agent {
+        generation.system = Some(agent.system_prompt.clone());
+        generation.temperature = Some(agent.temperature);
+    }
+    if let Some(system_prompt) = &request.system_prompt {
+        generation.system = Some(system_prompt.clone());
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        generation.max_tokens = max_tokens;
+    }
+    if let Some(project_id) = &request.project_id {
+        let prompt = &generation.messages[0].content;
+        match llm::build_context(pool, project_id, prompt, &generation.model, generation.max_tokens).await {
+            Ok(context) => {
+                // The history summary follows the agent's instructions
+                generation.system = match (generation.system.take(), context.summary) {
+                    (Some(system), Some(summary)) => Some(format!("{}\n\n{}", system, summary)),
+                    (system, summary) => system.or(summary),
+                };
+                generation.messages.splice(0..0, context.messages);
+            }
+            Err(e) => eprintln!("Failed to load project history: {}", e),
+        }
+    }
+
+    let estimate = Usage {
+        input_tokens: generation.estimate_input_tokens(),
+        output_tokens: 0,
+        model: generation.model.clone(),
+        stop_reason: String::new(),
+    };
+
+    // What the project looked like before this run, for reviewing its changes
+    if let (Some(project_id), Access::Write) = (&request.project_id, access) {
+        if let Err(e) = crate::snapshots::record(pool, project_id, crate::snapshots::SnapshotKind::BeforeRun).await {
+            eprintln!("Failed to snapshot project {}: {}", project_id, e);
+        }
+    }
+
+    let events = match tools {
+        Some(tools) => llm::generate_with_tools(provider, failover, generation, policy, tools),
+        None => llm::generate(provider, failover, generation, policy),
+    };
+    let post_processors = match &request.project_id {
+        Some(project_id) => crate::post_process::for_project(pool, project_id, access).await,
+        None => Vec::new(),
+    };
+    let label = agent.as_ref().map_or_else(|| estimate.model.clone(), |agent| agent.name.clone());
+    let sink = Sink {
+        pool: pool.clone(),
+        user_id: principal.user_id.to_string(),
+        project_id: request.project_id,
+        prompt: request.prompt,
+        agent_id: agent.map(|agent| agent.id),
+        post_processors,
+        branch,
+    };
+
+    Ok(Box::pin(relay(events, estimate, remaining, request.response_format, sink, label, cancel)))
+}
+
+/// Decode the request's image files, loading uploaded ones by ID
+async fn load_images(pool: &SqlitePool, user_id: &str, files: &[FileContent]) -> Result<Vec<Image>, LlmError> {
+    let files: Vec<&FileContent> = files.iter().filter(|file| file.is_image()).collect();
+    if files.len() > MAX_IMAGES {
+        return Err(LlmError::Config(format!("At most {} images can be sent at once", MAX_IMAGES)));
+    }
+
+    let mut images = Vec::with_capacity(files.len());
+    for file in files {
+        let image = match &file.attachment_id {
+            Some(id) => attachments::load(pool, user_id, id)
+                .await
+                .map_err(|e| LlmError::Config(format!("Database error: {}", e)))?
+                .ok_or_else(|| LlmError::Config(format!("Attachment not found: {}", id)))?,
+            None => attachments::decode_image(&file.content, file.media_type.as_deref())
+                .map_err(|e| LlmError::Config(format!("{}: {}", file.path, e)))?,
+        };
+        images.push(image);
+    }
+    Ok(images)
+}
+
+/// Build the provider request, appending attached text files to the prompt
+/// and sending images as content blocks after it
+fn generation_request(request: &StreamRequest, model: String, images: Vec<Image>) -> GenerationRequest {
+    let mut prompt = request.prompt.clone();
+    for file in request.files.iter().flatten().filter(|file| !file.is_image()) {
+        prompt.push_str(&format!("\n\n--- {} ---\n{}", file.path, file.content));
+    }
+
+    let mut message = ChatMessage::user(prompt);
+    if !images.is_empty() {
+        message.blocks.push(ContentBlock::Text { text: message.content.clone() });
+        message
+            .blocks
+            .extend(images.iter().map(|image| ContentBlock::image(&image.media_type, image.base64())));
+    }
+
+    GenerationRequest {
+        model,
+        system: None,
+        messages: vec![message],
+        max_tokens: llm::DEFAULT_MAX_TOKENS,
+        temperature: None,
+        tools: Vec::new(),
+        response_format: request.response_format.clone(),
+        recorder: None,
+    }
+}
+
+/// Where a finished generation is recorded
+struct Sink {
+    pool: SqlitePool,
+    user_id: String,
+    project_id: Option<String>,
+    /// The prompt as the user wrote it, without attached files
+    prompt: String,
+    /// Agent the reply is attributed to
+    agent_id: Option<String>,
+    /// Run on the reply before it's saved
+    post_processors: Vec<PostProcessor>,
+    /// Saved prompt the reply is a candidate answer to, in a comparison
+    branch: Option<String>,
+}
+
+impl Sink {
+    async fn record(&self, usage: &Usage) {
+        let (project_id, agent_id) = (self.project_id.as_deref(), self.agent_id.as_deref());
+        if let Err(e) = usage::record(&self.pool, &self.user_id, project_id, agent_id, usage).await {
+            eprintln!("Failed to record usage: {}", e);
+        }
+    }
+
+    /// Save the prompt and reply to the project's messages, post-processing
+    /// the reply first
+    async fn save_messages(&self, reply_id: &str, reply: &str) {
+        let Some(project_id) = &self.project_id else {
+            return;
+        };
+        let reply = crate::post_process::run(&self.pool, project_id, &self.post_processors, reply).await;
+        let agent_id = self.agent_id.as_deref();
+        let saved = match &self.branch {
+            Some(prompt_id) => {
+                crate::comparison::save_candidate(&self.pool, project_id, prompt_id, reply_id, &reply.content, agent_id)
+                    .await
+            }
+            None => save_exchange(&self.pool, project_id, &self.prompt, reply_id, &reply.content, agent_id).await,
+        };
+        if let Err(e) = saved {
+            eprintln!("Failed to save messages: {}", e);
+        }
+
+        // Commit what the generation changed, in projects kept in git
+        if self.branch.is_none() {
+            let (pool, project_id, prompt) = (self.pool.clone(), project_id.clone(), self.prompt.clone());
+            tokio::spawn(async move {
+                match crate::git::auto_commit(&pool, &project_id, &prompt).await {
+                    Ok(Some(commit)) => println!("📌 Committed {} in project {}", &commit.hash[..7], project_id),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Failed to commit project {}: {}", project_id, e),
+                }
+            });
+        }
+    }
+}
+
+/// Append a user prompt and the assistant reply to a project's messages
+///
+/// Both rows are written in one transaction, so history never holds a
+/// prompt without its reply. The reply is attributed to `agent_id`.
+async fn save_exchange(
+    pool: &SqlitePool,
+    project_id: &str,
+    prompt: &str,
+    reply_id: &str,
+    reply: &str,
+    agent_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let prompt_id = uuid::Uuid::new_v4().to_string();
+    let mut tx = pool.begin().await?;
+
+    for (id, role, content, agent_id) in [
+        (prompt_id.as_str(), "user", prompt, None),
+        (reply_id, "assistant", reply, agent_id),
+    ] {
+        sqlx::query(
+            "INSERT INTO messages (id, role, content, project_id, agent_id, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(role)
+        .bind(content)
+        .bind(project_id)
+        .bind(agent_id)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("UPDATE projects SET updated_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+fn message(content: String, done: bool, usage: Option<Usage>) -> GenerationEvent {
+    message_with_id(uuid::Uuid::new_v4().to_string(), content, done, usage)
+}
+
+fn message_with_id(id: String, content: String, done: bool, usage: Option<Usage>) -> GenerationEvent {
+    GenerationEvent::Message(StreamResponse {
+        id,
+        content,
+        role: "assistant".to_string(),
+        done,
+        cost_usd: usage.as_ref().and_then(usage::cost),
+        usage,
+    })
+}
+
+/// Relay provider events, ending with a `done` message carrying usage
+///
+/// `estimate` is recorded (with output tokens estimated from the streamed
+/// text) when the generation ends before the provider reports usage. Any
+/// reply, including one cut short by cancellation, is saved to the project
+/// under the `done` message's id. With `format`, a reply that finished is
+/// validated against its schema. A reply that outgrows what's `remaining`
+/// of a budget is cut off with a `budget_exceeded` error. Once polled, the
+/// generation counts toward the app's
+/// [activity](crate::server::streams::Activity) under `label` until the
+/// stream ends or is dropped, and can be cancelled from there too.
+fn relay(
+    mut events: llm::EventStream,
+    estimate: Usage,
+    remaining: Remaining,
+    format: Option<ResponseFormat>,
+    sink: Sink,
+    label: String,
+    cancel: impl Future<Output = ()> + Send + 'static,
+) -> impl futures::Stream<Item = GenerationEvent> + Send {
+    async_stream::stream! {
+        tokio::pin!(cancel);
+
+        let mut activity = crate::server::streams::track_activity(label);
+        let mut output = String::new();
+        let mut reported = None;
+        let mut stop_reason = "end_turn";
+        let price = usage::price(&estimate.model);
+
+        loop {
+            let event = tokio::select! {
+                event = events.next() => event,
+                _ = &mut cancel => {
+                    stop_reason = "cancelled";
+                    break;
+                }
+                _ = activity.cancelled() => {
+                    stop_reason = "cancelled";
+                    break;
+                }
+            };
+
+            match event {
+                Some(Ok(StreamEvent::Text(text))) => {
+                    output.push_str(&text);
+                    yield message(text, false, None);
+
+                    let output_tokens = usage::estimate_tokens(&output);
+                    if remaining.exceeded_by(estimate.input_tokens, output_tokens, price) {
+                        stop_reason = "budget_exceeded";
+                        let message = "The daily budget ran out during the generation".to_string();
+                        yield error_event(&LlmError::BudgetExceeded(message));
+                        break;
+                    }
+                }
+                Some(Ok(StreamEvent::Retry(status))) => yield GenerationEvent::Retry(status),
+                Some(Ok(StreamEvent::RateLimited(status))) => yield GenerationEvent::RateLimited(status),
+                Some(Ok(StreamEvent::Offline(provider))) => yield GenerationEvent::Offline { provider },
+                Some(Ok(StreamEvent::ToolUse(call))) => yield GenerationEvent::ToolUse(call),
+                Some(Ok(StreamEvent::ToolResult(result))) => yield GenerationEvent::ToolResult(result),
+                Some(Ok(StreamEvent::Done(usage))) => {
+                    reported = Some(usage);
+                    break;
+                }
+                Some(Err(e)) => {
+                    stop_reason = "error";
+                    yield error_event(&e);
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        if matches!(stop_reason, "error" | "budget_exceeded") {
+            activity.fail();
+        }
+
+        // Send final done event with usage, recording it in the ledger first
+        let usage = reported.unwrap_or_else(|| Usage {
+            output_tokens: usage::estimate_tokens(&output),
+            stop_reason: stop_reason.to_string(),
+            ..estimate
+        });
+        sink.record(&usage).await;
+
+        if let Some(format) = format.filter(|_| stop_reason == "end_turn") {
+            if let Err(errors) = llm::validate_response(&format, &output) {
+                yield GenerationEvent::ValidationError { errors };
+            }
+        }
+
+        let reply_id = uuid::Uuid::new_v4().to_string();
+        if !output.is_empty() {
+            sink.save_messages(&reply_id, &output).await;
+        }
+
+        yield message_with_id(reply_id, String::new(), true, Some(usage));
+    }
+}
+
+/// Run a generation to the end and return the whole reply
+///
+/// For short utility calls (titles, commit messages) that don't need
+/// streaming. Everything [`start`] does applies: usage is recorded, and for
+/// project generations the exchange is saved.
+pub async fn complete(
+    pool: &SqlitePool,
+    principal: Principal<'_>,
+    request: StreamRequest,
+) -> Result<CompletionResponse, StreamError> {
+    let mut events = start(pool, principal, request, futures::future::pending())
+        .await
+        .map_err(|e| StreamError::from(&e))?;
+
+    // Drain to the `done` message even after an error, so usage is recorded
+    let mut content = String::new();
+    let mut validation_errors = Vec::new();
+    let mut failure = None;
+    while let Some(event) = events.next().await {
+        match event {
+            GenerationEvent::Message(StreamResponse { done: false, content: chunk, .. }) => content.push_str(&chunk),
+            GenerationEvent::Message(StreamResponse { id, usage: Some(usage), cost_usd, .. }) => {
+                return match failure {
+                    Some(error) => Err(error),
+                    None => Ok(CompletionResponse {
+                        id,
+                        content,
+                        usage,
+                        cost_usd,
+                        validation_errors,
+                    }),
+                };
+            }
+            GenerationEvent::ValidationError { errors } => validation_errors = errors,
+            GenerationEvent::Error(error) => failure = Some(error),
+            _ => {}
+        }
+    }
+
+    Err(failure.unwrap_or_else(|| StreamError {
+        code: ErrorCode::ProviderError,
+        retryable: true,
+        message: "Generation ended without a reply".to_string(),
+    }))
+}
+
+/// Model agent test runs use for an agent preferring `model`: the
+/// provider's cheapest, or the model itself for local and demo providers
+pub fn test_model(model: &str) -> String {
+    match llm::ProviderKind::infer(model) {
+        Some(llm::ProviderKind::Anthropic) => "claude-haiku-4-5".to_string(),
+        Some(llm::ProviderKind::OpenAI) => "gpt-4o-mini".to_string(),
+        _ => model.to_string(),
+    }
+}
+
+/// Run an agent's system prompt on a short input with a cheap model
+///
+/// Replies are capped at a few hundred tokens and saved nowhere; usage is
+/// still recorded. `test.system_prompt` tries a draft without saving it.
+pub async fn test_agent(
+    pool: &SqlitePool,
+    principal: Principal<'_>,
+    agent: &crate::agents::Agent,
+    test: AgentTest,
+) -> Result<CompletionResponse, StreamError> {
+    let request = StreamRequest {
+        prompt: test
+            .input
+            .filter(|input| !input.trim().is_empty())
+            .unwrap_or_else(|| AGENT_TEST_INPUT.to_string()),
+        agent_id: Some(agent.id.clone()),
+        provider: None,
+        model: Some(test.model.unwrap_or_else(|| test_model(&agent.model))),
+        project_id: None,
+        files: None,
+        context: None,
+        tools: false,
+        template_id: None,
+        variables: HashMap::new(),
+        response_format: None,
+        priority: Priority::Interactive,
+        session_id: None,
+        system_prompt: test.system_prompt.filter(|prompt| !prompt.trim().is_empty()),
+        max_tokens: Some(AGENT_TEST_MAX_TOKENS),
+    };
+    complete(pool, principal, request).await
+}
+
+/// Re-emit a recorded session's provider stream as generation events
+///
+/// Nothing is recorded in the ledger or saved to the project; each recorded
+/// provider call ends with its own `done` message.
+pub async fn replay(session_id: &str) -> Result<BoxStream<'static, GenerationEvent>, LlmError> {
+    let mut events = llm::replay(session_id).await?;
+
+    Ok(Box::pin(async_stream::stream! {
+        while let Some(event) = events.next().await {
+            yield match event {
+                Ok(StreamEvent::Text(text)) => message(text, false, None),
+                Ok(StreamEvent::Retry(status)) => GenerationEvent::Retry(status),
+                Ok(StreamEvent::RateLimited(status)) => GenerationEvent::RateLimited(status),
+                Ok(StreamEvent::Offline(provider)) => GenerationEvent::Offline { provider },
+                Ok(StreamEvent::ToolUse(call)) => GenerationEvent::ToolUse(call),
+                Ok(StreamEvent::ToolResult(result)) => GenerationEvent::ToolResult(result),
+                Ok(StreamEvent::Done(usage)) => message(String::new(), true, Some(usage)),
+                Err(e) => error_event(&e),
+            };
+        }
+    }))
+}
+
+fn error_event(error: &LlmError) -> GenerationEvent {
+    GenerationEvent::Error(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::create_test_pool;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_event_schema() {
+        let event = error_event(&LlmError::Status {
+            provider: "Anthropic",
+            status: 429,
+            message: "slow down".to_string(),
+            retry_after: None,
+        });
+        assert_eq!(event.name(), "error");
+        assert_eq!(
+            event.data(),
+            r#"{"code":"rate_limited","retryable":true,"message":"Anthropic returned 429: slow down"}"#
+        );
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"event": "error", "data": {
+                "code": "rate_limited",
+                "retryable": true,
+                "message": "Anthropic returned 429: slow down"
+            }})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_demo_generation_ends_with_usage() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+
+        let request = StreamRequest {
+            prompt: "Build a landing page".to_string(),
+            agent_id: None,
+            provider: Some("demo".to_string()),
+            model: None,
+            project_id: None,
+            files: None,
+            context: None,
+            tools: false,
+            template_id: None,
+            variables: HashMap::new(),
+            response_format: None,
+            priority: Priority::Interactive,
+            session_id: None,
+            system_prompt: None,
+            max_tokens: None,
+        };
+        let events: Vec<GenerationEvent> = start(&pool, Principal::desktop(), request, futures::future::pending())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        match events.last() {
+            Some(GenerationEvent::Message(StreamResponse { done: true, usage: Some(usage), cost_usd, .. })) => {
+                assert_eq!(usage.model, "vibing2-demo");
+                assert!(usage.input_tokens > 0);
+                // The demo model is free
+                assert_eq!(*cost_usd, Some(0.0));
+            }
+            other => panic!("expected final done message, got {:?}", other),
+        }
+
+        let recorded: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM usage_events")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(recorded, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reply_breaking_schema_is_reported() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+
+        let request = StreamRequest {
+            prompt: "Describe the site as JSON".to_string(),
+            agent_id: None,
+            provider: Some("demo".to_string()),
+            model: None,
+            project_id: None,
+            files: None,
+            context: None,
+            tools: false,
+            template_id: None,
+            variables: HashMap::new(),
+            response_format: Some(ResponseFormat {
+                name: "site".to_string(),
+                schema: serde_json::json!({"type": "object", "required": ["title"]}),
+            }),
+            priority: Priority::Interactive,
+            session_id: None,
+            system_prompt: None,
+            max_tokens: None,
+        };
+        let events: Vec<GenerationEvent> = start(&pool, Principal::desktop(), request, futures::future::pending())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        // The demo reply is prose, so it fails validation just before `done`
+        let [.., GenerationEvent::ValidationError { errors }, GenerationEvent::Message(StreamResponse { done: true, .. })] =
+            events.as_slice()
+        else {
+            panic!("expected a validation error before done, got {:?}", events.last());
+        };
+        assert_eq!(errors[0].path, "");
+    }
+
+    #[tokio::test]
+    async fn test_images_need_a_vision_provider() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+
+        let screenshot = |content: &str| FileContent {
+            path: "screenshot.png".to_string(),
+            content: content.to_string(),
+            media_type: Some("image/png".to_string()),
+            attachment_id: None,
+        };
+        let request = |files| StreamRequest {
+            prompt: "Build this page".to_string(),
+            agent_id: None,
+            provider: Some("demo".to_string()),
+            model: None,
+            project_id: None,
+            files: Some(files),
+            context: None,
+            tools: false,
+            template_id: None,
+            variables: HashMap::new(),
+            response_format: None,
+            priority: Priority::Interactive,
+            session_id: None,
+            system_prompt: None,
+            max_tokens: None,
+        };
+
+        let pixel = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+        let error = start(&pool, Principal::desktop(), request(vec![screenshot(pixel)]), futures::future::pending())
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("can't take images"));
+
+        let error = start(&pool, Principal::desktop(), request(vec![screenshot("bm90IGFuIGltYWdl")]), futures::future::pending())
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("screenshot.png: Unsupported image type"));
+    }
+
+    #[tokio::test]
+    async fn test_complete_returns_whole_reply() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+
+        let request = StreamRequest {
+            prompt: "Title for a bakery site".to_string(),
+            agent_id: None,
+            provider: Some("demo".to_string()),
+            model: None,
+            project_id: None,
+            files: None,
+            context: None,
+            tools: false,
+            template_id: None,
+            variables: HashMap::new(),
+            response_format: None,
+            priority: Priority::Interactive,
+            session_id: None,
+            system_prompt: None,
+            max_tokens: None,
+        };
+        let completion = complete(&pool, Principal::desktop(), request).await.unwrap();
+        assert!(!completion.content.is_empty());
+        assert_eq!(completion.usage.model, "vibing2-demo");
+        assert!(completion.validation_errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_agent_test_run() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+
+        assert_eq!(test_model("claude-opus-4-5"), "claude-haiku-4-5");
+        assert_eq!(test_model("gpt-4.1"), "gpt-4o-mini");
+        assert_eq!(test_model("llama3.2"), "llama3.2");
+
+        let agent = crate::agents::get(&pool, "ui-designer").await.unwrap().unwrap();
+        let test = AgentTest {
+            system_prompt: Some("You only speak in haiku.".to_string()),
+            model: Some("vibing2-demo".to_string()),
+            ..Default::default()
+        };
+        let completion = test_agent(&pool, Principal::desktop(), &agent, test).await.unwrap();
+        assert!(!completion.content.is_empty());
+        assert_eq!(completion.usage.model, "vibing2-demo");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_agent_is_rejected() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+
+        let request = StreamRequest {
+            prompt: "Design an API".to_string(),
+            agent_id: Some("time-traveler".to_string()),
+            provider: Some("demo".to_string()),
+            model: None,
+            project_id: None,
+            files: None,
+            context: None,
+            tools: false,
+            template_id: None,
+            variables: HashMap::new(),
+            response_format: None,
+            priority: Priority::Interactive,
+            session_id: None,
+            system_prompt: None,
+            max_tokens: None,
+        };
+        let error = start(&pool, Principal::desktop(), request, futures::future::pending())
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("time-traveler"));
+    }
+
+    #[tokio::test]
+    async fn test_project_generation_saves_messages() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Demo', 'web', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let request = StreamRequest {
+            prompt: "Add a footer".to_string(),
+            agent_id: None,
+            provider: Some("demo".to_string()),
+            model: None,
+            project_id: Some("p1".to_string()),
+            files: Some(vec![FileContent {
+                path: "index.html".to_string(),
+                content: "<main></main>".to_string(),
+                media_type: None,
+                attachment_id: None,
+            }]),
+            context: None,
+            tools: false,
+            template_id: None,
+            variables: HashMap::new(),
+            response_format: None,
+            priority: Priority::Interactive,
+            session_id: None,
+            system_prompt: None,
+            max_tokens: None,
+        };
+        let events: Vec<GenerationEvent> = start(&pool, Principal::desktop(), request, futures::future::pending())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let Some(GenerationEvent::Message(done)) = events.last() else {
+            panic!("expected final done message");
+        };
+
+        let rows: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT id, role, content FROM messages WHERE project_id = 'p1' ORDER BY rowid")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(rows.len(), 2);
+        // The prompt is saved as written, without the attached files
+        assert_eq!((rows[0].1.as_str(), rows[0].2.as_str()), ("user", "Add a footer"));
+        assert_eq!(rows[1].1, "assistant");
+        assert!(!rows[1].2.is_empty());
+        assert_eq!(rows[1].0, done.id);
+    }
+}
@@ -0,0 +1,430 @@
+//! Git for projects written to disk
+//!
+//! Runs the `git` command line in a project's folder (see `scaffold`), so
+//! the user's own git configuration and credentials apply. Before each
+//! commit the project is written out again, so commits hold what the app
+//! has. Once a folder is a repository, every generation in the project is
+//! committed with a message the model writes from the diff.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+
+/// Diffs are cut off past this many bytes
+pub const MAX_DIFF_BYTES: usize = 256 * 1024;
+
+/// How much of the staged diff the commit message is written from
+const MESSAGE_DIFF_CHARS: usize = 12_000;
+
+/// Author used when git has no identity configured
+const FALLBACK_NAME: &str = "Vibing2";
+const FALLBACK_EMAIL: &str = "vibing2@localhost";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GitCommit {
+    pub hash: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Untracked,
+    Conflicted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusEntry {
+    pub path: String,
+    pub status: FileStatus,
+    /// Staged for the next commit
+    pub staged: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GitStatus {
+    /// `None` on a detached HEAD
+    pub branch: Option<String>,
+    pub files: Vec<StatusEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GitDiff {
+    pub diff: String,
+    pub truncated: bool,
+}
+
+/// Commits run one at a time, so concurrent generations don't collide on
+/// the index lock
+fn commit_lock() -> &'static tokio::sync::Mutex<()> {
+    static LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| tokio::sync::Mutex::new(()))
+}
+
+/// Run git in `root` and return its output
+///
+/// Hooks are turned off: the folder's files come from the model, and a
+/// hook it wrote would otherwise run on the next commit.
+async fn run(root: &Path, args: &[&str]) -> Result<String, String> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["-c", "core.quotepath=false", "-c", "core.hooksPath=/dev/null"])
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => "Git isn't installed".to_string(),
+            _ => format!("Failed to run git: {}", e),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args.first().unwrap_or(&""), stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub fn is_repo(root: &Path) -> bool {
+    root.join(".git").exists()
+}
+
+/// The folder a project was written to
+pub async fn project_root(pool: &SqlitePool, project_id: &str) -> Result<PathBuf, String> {
+    let local_path: Option<Option<String>> = sqlx::query_scalar("SELECT local_path FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    match local_path {
+        None => Err(format!("Project not found: {}", project_id)),
+        Some(None) => Err("The project hasn't been written to disk yet".to_string()),
+        Some(Some(path)) => Ok(PathBuf::from(path)),
+    }
+}
+
+/// The project's repository, writing the project out first so it's current
+async fn refreshed_repo(pool: &SqlitePool, project_id: &str) -> Result<PathBuf, String> {
+    let root = project_root(pool, project_id).await?;
+    if !is_repo(&root) {
+        return Err("The project isn't a git repository; initialize one first".to_string());
+    }
+    crate::scaffold::materialize(pool, project_id, &root, crate::scaffold::ConflictPolicy::Keep).await?;
+    Ok(root)
+}
+
+/// Make `root` a repository with a first commit of what's there
+///
+/// The app's manifest is excluded. Does nothing to an existing repository.
+pub async fn init(root: &Path) -> Result<(), String> {
+    if is_repo(root) {
+        return Ok(());
+    }
+    run(root, &["init"]).await?;
+
+    let exclude = root.join(".git").join("info").join("exclude");
+    let mut excluded = tokio::fs::read_to_string(&exclude).await.unwrap_or_default();
+    if !excluded.is_empty() && !excluded.ends_with('\n') {
+        excluded.push('\n');
+    }
+    excluded.push_str("# Written by Vibing2\n.vibing2/\n");
+    if let Some(parent) = exclude.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", exclude.display(), e))?;
+    }
+    tokio::fs::write(&exclude, excluded)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", exclude.display(), e))?;
+
+    run(root, &["add", "-A"]).await?;
+    let identity = identity_args(root).await;
+    let mut args: Vec<&str> = identity.iter().map(String::as_str).collect();
+    args.extend(["commit", "--no-verify", "--allow-empty", "-m", "Initial commit"]);
+    run(root, &args).await?;
+    Ok(())
+}
+
+/// `-c` options for a fallback author, if git has no identity configured
+async fn identity_args(root: &Path) -> Vec<String> {
+    if run(root, &["config", "user.email"]).await.is_ok_and(|email| !email.trim().is_empty()) {
+        return Vec::new();
+    }
+    vec![
+        "-c".to_string(),
+        format!("user.name={}", FALLBACK_NAME),
+        "-c".to_string(),
+        format!("user.email={}", FALLBACK_EMAIL),
+    ]
+}
+
+/// Stage everything and commit it; `None` if there was nothing to commit
+pub async fn commit(root: &Path, message: &str) -> Result<Option<GitCommit>, String> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err("A commit message is required".to_string());
+    }
+
+    let _guard = commit_lock().lock().await;
+    run(root, &["add", "-A"]).await?;
+    if staged_diff(root, &["--name-only"]).await?.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let identity = identity_args(root).await;
+    let mut args: Vec<&str> = identity.iter().map(String::as_str).collect();
+    args.extend(["commit", "--no-verify", "-q", "-m", message]);
+    run(root, &args).await?;
+
+    let hash = run(root, &["rev-parse", "HEAD"]).await?;
+    Ok(Some(GitCommit {
+        hash: hash.trim().to_string(),
+        message: message.to_string(),
+    }))
+}
+
+async fn staged_diff(root: &Path, args: &[&str]) -> Result<String, String> {
+    let mut all = vec!["diff", "--cached"];
+    all.extend(args);
+    run(root, &all).await
+}
+
+pub async fn status(root: &Path) -> Result<GitStatus, String> {
+    Ok(parse_status(&run(root, &["status", "--porcelain=v1", "--branch"]).await?))
+}
+
+/// Parse `git status --porcelain=v1 --branch`
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+    for line in output.lines() {
+        if let Some(branch) = line.strip_prefix("## ") {
+            let branch = branch.strip_prefix("No commits yet on ").unwrap_or(branch);
+            let name = branch.split("...").next().unwrap_or(branch).split(' ').next().unwrap_or_default();
+            status.branch = (!name.is_empty() && !branch.starts_with("HEAD (no branch)")).then(|| name.to_string());
+            continue;
+        }
+        if line.len() < 4 {
+            continue;
+        }
+        let (code, path) = line.split_at(3);
+        let mut code = code.chars();
+        let (index, worktree) = (code.next().unwrap_or(' '), code.next().unwrap_or(' '));
+        // Renames read `old -> new`
+        let path = path.rsplit(" -> ").next().unwrap_or(path).trim_matches('"').to_string();
+
+        let file_status = match (index, worktree) {
+            ('?', '?') => FileStatus::Untracked,
+            ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => FileStatus::Conflicted,
+            ('R', _) => FileStatus::Renamed,
+            ('A', _) => FileStatus::Added,
+            ('D', _) | (_, 'D') => FileStatus::Deleted,
+            _ => FileStatus::Modified,
+        };
+        status.files.push(StatusEntry {
+            path,
+            status: file_status,
+            staged: !matches!(index, ' ' | '?'),
+        });
+    }
+    status
+}
+
+/// Changes since the last commit, optionally for one file
+///
+/// Files git doesn't track yet aren't in the diff; `status` lists them.
+pub async fn diff(root: &Path, path: Option<&str>) -> Result<GitDiff, String> {
+    let mut args = vec!["diff", "HEAD"];
+    if let Some(path) = path {
+        crate::sandbox::jailed(root, path)?;
+        args.extend(["--", path]);
+    }
+    let mut diff = run(root, &args).await?;
+
+    let truncated = diff.len() > MAX_DIFF_BYTES;
+    if truncated {
+        let mut end = MAX_DIFF_BYTES;
+        while !diff.is_char_boundary(end) {
+            end -= 1;
+        }
+        diff.truncate(end);
+    }
+    Ok(GitDiff { diff, truncated })
+}
+
+pub async fn init_project(pool: &SqlitePool, project_id: &str) -> Result<PathBuf, String> {
+    let root = match project_root(pool, project_id).await {
+        Ok(root) => root,
+        Err(_) => crate::scaffold::default_target(pool, project_id).await?,
+    };
+    crate::scaffold::materialize(pool, project_id, &root, crate::scaffold::ConflictPolicy::Keep).await?;
+    init(&root).await?;
+    Ok(root)
+}
+
+pub async fn commit_project(pool: &SqlitePool, project_id: &str, message: &str) -> Result<Option<GitCommit>, String> {
+    let root = refreshed_repo(pool, project_id).await?;
+    commit(&root, message).await
+}
+
+/// Commit what a generation changed, if the project is a repository
+///
+/// The message is written by the default model from the staged diff, or
+/// made from `prompt` if that fails (offline, no key).
+pub async fn auto_commit(pool: &SqlitePool, project_id: &str, prompt: &str) -> Result<Option<GitCommit>, String> {
+    let Ok(root) = project_root(pool, project_id).await else {
+        return Ok(None);
+    };
+    if !is_repo(&root) {
+        return Ok(None);
+    }
+    crate::scaffold::materialize(pool, project_id, &root, crate::scaffold::ConflictPolicy::Keep).await?;
+
+    let diff = {
+        let _guard = commit_lock().lock().await;
+        run(&root, &["add", "-A"]).await?;
+        staged_diff(&root, &[]).await?
+    };
+    if diff.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let message = match write_message(pool, &diff).await {
+        Some(message) => message,
+        None => fallback_message(prompt),
+    };
+    commit(&root, &message).await
+}
+
+/// Ask the model for a commit message describing `diff`
+async fn write_message(pool: &SqlitePool, diff: &str) -> Option<String> {
+    let diff: String = diff.chars().take(MESSAGE_DIFF_CHARS).collect();
+    let request = crate::generation::StreamRequest {
+        prompt: format!("Write a git commit message for this diff.\n\n{}", diff),
+        agent_id: None,
+        provider: None,
+        model: None,
+        project_id: None,
+        files: None,
+        context: None,
+        tools: false,
+        template_id: None,
+        variables: Default::default(),
+        response_format: None,
+        priority: crate::llm::Priority::Background,
+        session_id: None,
+        system_prompt: Some(
+            "You write git commit messages. Reply with only the message: an imperative subject line under \
+             72 characters, optionally followed by a blank line and a short body. No quotes or code fences."
+                .to_string(),
+        ),
+        max_tokens: Some(200),
+    };
+    let reply = crate::generation::complete(pool, crate::sharing::Principal::desktop(), request)
+        .await
+        .ok()?;
+    let message = reply.content.trim().trim_matches('`').trim();
+    (!message.is_empty()).then(|| message.to_string())
+}
+
+/// A commit message from the prompt's first line
+fn fallback_message(prompt: &str) -> String {
+    let line = prompt.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    if line.is_empty() {
+        return "Update project".to_string();
+    }
+    let mut subject: String = line.chars().take(72).collect();
+    if subject.len() < line.len() {
+        subject.truncate(subject.trim_end().len());
+        subject.push('…');
+    }
+    subject
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let status = parse_status(
+            "## main...origin/main [ahead 1]\n M index.html\nA  src/app.js\n D old.css\nR  a.js -> b.js\n?? notes.md\nUU merge.txt\n",
+        );
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        let summary: Vec<(&str, FileStatus, bool)> = status
+            .files
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry.status, entry.staged))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("index.html", FileStatus::Modified, false),
+                ("src/app.js", FileStatus::Added, true),
+                ("old.css", FileStatus::Deleted, false),
+                ("b.js", FileStatus::Renamed, true),
+                ("notes.md", FileStatus::Untracked, false),
+                ("merge.txt", FileStatus::Conflicted, true),
+            ]
+        );
+
+        assert_eq!(parse_status("## No commits yet on trunk\n").branch.as_deref(), Some("trunk"));
+        assert_eq!(parse_status("## HEAD (no branch)\n").branch, None);
+    }
+
+    #[test]
+    fn test_fallback_message() {
+        assert_eq!(fallback_message("\n  Add a pricing page\nwith three tiers"), "Add a pricing page");
+        assert_eq!(fallback_message(""), "Update project");
+        assert_eq!(fallback_message(&"a".repeat(100)).chars().count(), 73);
+    }
+
+    #[tokio::test]
+    async fn test_init_commit_status_diff() {
+        if run(Path::new("."), &["--version"]).await.is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".vibing2")).unwrap();
+        std::fs::write(root.join(".vibing2").join("manifest.json"), "{}").unwrap();
+        std::fs::write(root.join("index.html"), "<h1>Hi</h1>\n").unwrap();
+
+        init(root).await.unwrap();
+        assert!(is_repo(root));
+        assert!(status(root).await.unwrap().files.is_empty());
+        assert!(commit(root, "Nothing").await.unwrap().is_none());
+
+        std::fs::write(root.join("index.html"), "<h1>Hello</h1>\n").unwrap();
+        let changed = status(root).await.unwrap();
+        assert_eq!(changed.files.len(), 1);
+        assert_eq!(changed.files[0].status, FileStatus::Modified);
+        let diff = diff(root, Some("index.html")).await.unwrap();
+        assert!(diff.diff.contains("+<h1>Hello</h1>"));
+        assert!(super::diff(root, Some("../outside")).await.is_err());
+
+        let commit = commit(root, "Say hello").await.unwrap().unwrap();
+        assert_eq!(commit.hash.len(), 40);
+        assert!(status(root).await.unwrap().files.is_empty());
+
+        // Hooks in the repository never run
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let hook = root.join(".git").join("hooks").join("pre-commit");
+            std::fs::create_dir_all(hook.parent().unwrap()).unwrap();
+            std::fs::write(&hook, "#!/bin/sh\ntouch hooked\n").unwrap();
+            std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+            std::fs::write(root.join("index.html"), "<h1>Hooked?</h1>\n").unwrap();
+            assert!(super::commit(root, "Try the hook").await.unwrap().is_some());
+            assert!(!root.join("hooked").exists());
+        }
+    }
+}
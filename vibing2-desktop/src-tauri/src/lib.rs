@@ -1,6 +1,51 @@
 // Library module for testing
+pub mod agent_files;
+pub mod agent_history;
+pub mod agent_icons;
+pub mod agent_lint;
+pub mod agent_manifest;
+pub mod agent_registry;
+pub mod agent_tools;
+pub mod agents;
+pub mod attachments;
 pub mod auth;
+pub mod autostart;
+pub mod biometric;
+pub mod budgets;
 pub mod commands;
+pub mod comparison;
 pub mod database;
+pub mod deep_link;
+pub mod file_sync;
+pub mod generation;
+pub mod git;
+pub mod llm;
+pub mod locale;
+pub mod network;
+pub mod oauth;
+pub mod pairing;
+pub mod passwords;
+pub mod post_process;
+pub mod power;
+pub mod project_agents;
+pub mod project_templates;
+pub mod proxy;
+pub mod quick_capture;
+pub mod sandbox;
+pub mod scaffold;
+pub mod search;
+pub mod schedules;
+pub mod server;
+pub mod sessions;
+pub mod sharing;
+pub mod snapshots;
+pub mod taskbar;
+pub mod teams;
+pub mod telemetry;
+pub mod templates;
+pub mod tool_policy;
+pub mod totp;
 pub mod tray;
-// pub mod updater;
+pub mod updater;
+pub mod usage;
+pub mod vault;
@@ -0,0 +1,400 @@
+// Anthropic Messages API provider
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use serde_json::{json, Value};
+
+use std::collections::HashMap;
+
+use super::frames::{frames, sse_data, Frames};
+use super::{
+    check_status, connection_error, http_client, ChatMessage, EventStream, GenerationRequest, LlmError,
+    ModelInfo, Provider, ProviderKind, StreamEvent, ToolCall,
+};
+use crate::usage::Usage;
+
+const NAME: &str = "Anthropic";
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+
+pub struct AnthropicProvider {
+    api_key: String,
+    base_url: String,
+    /// `api_key` is an OAuth access token rather than a console key
+    oauth: bool,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: String, base_url: Option<String>, oauth: bool) -> Self {
+        Self {
+            api_key,
+            base_url: base_url
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            oauth,
+        }
+    }
+
+    /// Authenticated request to an API path such as `/v1/messages`
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
+        let client = http_client();
+
+        // Subscription sign ins use OAuth bearer tokens, not console keys
+        let builder = if self.oauth {
+            client
+                .request(method, url)
+                .bearer_auth(&self.api_key)
+                .header("anthropic-beta", "oauth-2025-04-20")
+        } else {
+            client.request(method, url).header("x-api-key", &self.api_key)
+        };
+
+        builder.header("anthropic-version", API_VERSION)
+    }
+}
+
+impl Provider for AnthropicProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Anthropic
+    }
+
+    fn default_model(&self) -> &'static str {
+        "claude-sonnet-4-5"
+    }
+
+    fn supports_images(&self) -> bool {
+        true
+    }
+
+    fn stream(&self, request: GenerationRequest) -> BoxFuture<'_, Result<EventStream, LlmError>> {
+        Box::pin(async move {
+            let messages: Vec<Value> = request.messages.iter().map(message_json).collect();
+            let mut body = json!({
+                "model": request.model,
+                "max_tokens": request.max_tokens,
+                "stream": true,
+                "messages": messages,
+            });
+            // No native JSON mode here, so the schema goes in the instructions
+            let instruction = request.response_format.as_ref().map(|format| format.instruction());
+            let system = match (&request.system, instruction) {
+                (Some(system), Some(instruction)) => Some(format!("{}\n\n{}", system, instruction)),
+                (system, instruction) => system.clone().or(instruction),
+            };
+            if let Some(system) = system {
+                body["system"] = json!(system);
+            }
+            if let Some(temperature) = request.temperature {
+                body["temperature"] = json!(temperature);
+            }
+            if !request.tools.is_empty() {
+                body["tools"] = json!(request.tools);
+            }
+
+            let response = self
+                .request(reqwest::Method::POST, "/v1/messages")
+                .json(&body)
+                .send()
+                .await
+                .map_err(connection_error(NAME))?;
+            let response = check_status(NAME, response).await?;
+
+            let recorder = super::recorder::attempt(&request, ProviderKind::Anthropic);
+            Ok(events(frames(NAME, response, b"\n\n", recorder), &request.model))
+        })
+    }
+
+    fn models(&self) -> BoxFuture<'_, Result<Vec<ModelInfo>, LlmError>> {
+        Box::pin(async move {
+            let response = self
+                .request(reqwest::Method::GET, "/v1/models?limit=1000")
+                .send()
+                .await
+                .map_err(connection_error(NAME))?;
+            let body: Value = check_status(NAME, response)
+                .await?
+                .json()
+                .await
+                .map_err(connection_error(NAME))?;
+
+            Ok(parse_models(&body))
+        })
+    }
+}
+
+/// Parse Messages API SSE frames into stream events
+pub(super) fn events(mut frames: Frames, model: &str) -> EventStream {
+    let mut state = MessageState::new(model);
+
+    Box::pin(async_stream::try_stream! {
+        while let Some(frame) = frames.next().await {
+            let Some(data) = sse_data(&frame?) else {
+                continue;
+            };
+            let data: Value = serde_json::from_str(&data).map_err(|e| LlmError::Protocol {
+                provider: NAME,
+                message: e.to_string(),
+            })?;
+
+            if let Some(event) = state.apply(&data)? {
+                yield event;
+            }
+            if state.stopped {
+                break;
+            }
+        }
+        if !state.stopped {
+            Err::<(), _>(LlmError::Connection {
+                provider: NAME,
+                message: "Stream ended before message_stop".to_string(),
+            })?;
+        }
+        yield StreamEvent::Done(state.usage);
+    })
+}
+
+/// Models from a `GET /v1/models` response
+fn parse_models(body: &Value) -> Vec<ModelInfo> {
+    body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            let id = model["id"].as_str()?;
+            let display_name = model["display_name"].as_str().map(String::from);
+            Some(ModelInfo::new(ProviderKind::Anthropic, id, display_name))
+        })
+        .collect()
+}
+
+fn message_json(message: &ChatMessage) -> Value {
+    if message.blocks.is_empty() {
+        json!({"role": message.role, "content": message.content})
+    } else {
+        json!({"role": message.role, "content": message.blocks})
+    }
+}
+
+/// A `tool_use` block whose input JSON is still streaming in
+struct PendingToolUse {
+    id: String,
+    name: String,
+    input_json: String,
+}
+
+/// Accumulates usage and tool calls across the Messages API stream events
+struct MessageState {
+    usage: Usage,
+    stopped: bool,
+    /// Open tool_use blocks by content block index
+    tools: HashMap<u64, PendingToolUse>,
+}
+
+impl MessageState {
+    fn new(model: &str) -> Self {
+        Self {
+            usage: Usage {
+                input_tokens: 0,
+                output_tokens: 0,
+                model: model.to_string(),
+                stop_reason: "end_turn".to_string(),
+            },
+            stopped: false,
+            tools: HashMap::new(),
+        }
+    }
+
+    /// Apply one event, returning text or a completed tool call
+    fn apply(&mut self, event: &Value) -> Result<Option<StreamEvent>, LlmError> {
+        let index = event["index"].as_u64().unwrap_or(0);
+
+        let tokens = |value: Option<&Value>| value.and_then(Value::as_u64).map(|n| n as u32);
+
+        match event["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                let message = &event["message"];
+                if let Some(model) = message["model"].as_str() {
+                    self.usage.model = model.to_string();
+                }
+                if let Some(input) = tokens(message.pointer("/usage/input_tokens")) {
+                    self.usage.input_tokens = input;
+                }
+                if let Some(output) = tokens(message.pointer("/usage/output_tokens")) {
+                    self.usage.output_tokens = output;
+                }
+            }
+            "content_block_start" if event["content_block"]["type"] == "tool_use" => {
+                let block = &event["content_block"];
+                self.tools.insert(index, PendingToolUse {
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    input_json: String::new(),
+                });
+            }
+            "content_block_delta" if event["delta"]["type"] == "text_delta" => {
+                return Ok(event["delta"]["text"].as_str().map(|text| StreamEvent::Text(text.to_string())));
+            }
+            "content_block_delta" if event["delta"]["type"] == "input_json_delta" => {
+                if let Some(tool) = self.tools.get_mut(&index) {
+                    tool.input_json.push_str(event["delta"]["partial_json"].as_str().unwrap_or_default());
+                }
+            }
+            "content_block_stop" => {
+                if let Some(tool) = self.tools.remove(&index) {
+                    // Tools without parameters stream no input at all
+                    let input = if tool.input_json.trim().is_empty() {
+                        json!({})
+                    } else {
+                        serde_json::from_str(&tool.input_json).map_err(|e| LlmError::Protocol {
+                            provider: NAME,
+                            message: format!("Invalid tool input for {}: {}", tool.name, e),
+                        })?
+                    };
+                    return Ok(Some(StreamEvent::ToolUse(ToolCall {
+                        id: tool.id,
+                        name: tool.name,
+                        input,
+                    })));
+                }
+            }
+            "message_delta" => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    self.usage.stop_reason = reason.to_string();
+                }
+                if let Some(output) = tokens(event.pointer("/usage/output_tokens")) {
+                    self.usage.output_tokens = output;
+                }
+            }
+            "message_stop" => self.stopped = true,
+            "error" => {
+                let message = event["error"]["message"]
+                    .as_str()
+                    .unwrap_or("stream error")
+                    .to_string();
+
+                // Map in-stream errors onto the HTTP status the API would
+                // have used, so retries treat them alike
+                let status = match event["error"]["type"].as_str() {
+                    Some("overloaded_error") => 529,
+                    Some("rate_limit_error") => 429,
+                    Some("api_error") => 500,
+                    _ => return Err(LlmError::Protocol { provider: NAME, message }),
+                };
+                return Err(LlmError::Status { provider: NAME, status, message, retry_after: None });
+            }
+            _ => {}
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_state_collects_text_and_usage() {
+        let mut state = MessageState::new("claude-sonnet-4-5");
+        let events = [
+            json!({"type": "message_start", "message": {"model": "claude-sonnet-4-5-20250929", "usage": {"input_tokens": 25, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hello"}}),
+            json!({"type": "ping"}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "max_tokens"}, "usage": {"output_tokens": 15}}),
+            json!({"type": "message_stop"}),
+        ];
+
+        let text: Vec<StreamEvent> = events
+            .iter()
+            .filter_map(|event| state.apply(event).unwrap())
+            .collect();
+
+        assert_eq!(text, vec![StreamEvent::Text("Hello".to_string())]);
+        assert!(state.stopped);
+        assert_eq!(state.usage.input_tokens, 25);
+        assert_eq!(state.usage.output_tokens, 15);
+        assert_eq!(state.usage.model, "claude-sonnet-4-5-20250929");
+        assert_eq!(state.usage.stop_reason, "max_tokens");
+    }
+
+    #[test]
+    fn test_message_state_assembles_tool_use() {
+        let mut state = MessageState::new("claude-sonnet-4-5");
+        let events = [
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_01", "name": "read_file", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"path\": \"src/"}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "app.js\"}"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 40}}),
+        ];
+
+        let emitted: Vec<StreamEvent> = events
+            .iter()
+            .filter_map(|event| state.apply(event).unwrap())
+            .collect();
+
+        assert_eq!(
+            emitted,
+            vec![StreamEvent::ToolUse(ToolCall {
+                id: "toolu_01".to_string(),
+                name: "read_file".to_string(),
+                input: json!({"path": "src/app.js"}),
+            })]
+        );
+        assert_eq!(state.usage.stop_reason, "tool_use");
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let body = json!({
+            "data": [
+                {"type": "model", "id": "claude-sonnet-4-5-20250929", "display_name": "Claude Sonnet 4.5"},
+                {"type": "model", "id": "claude-haiku-4-5-20251001", "display_name": "Claude Haiku 4.5"}
+            ],
+            "has_more": false
+        });
+        let models = parse_models(&body);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].display_name, "Claude Sonnet 4.5");
+        assert!(models[0].matches("claude-sonnet-4-5"));
+    }
+
+    #[test]
+    fn test_message_json_uses_blocks() {
+        let plain = message_json(&ChatMessage::user("hi"));
+        assert_eq!(plain, json!({"role": "user", "content": "hi"}));
+
+        let result = ChatMessage::with_blocks("user", vec![crate::llm::ContentBlock::ToolResult {
+            tool_use_id: "toolu_01".to_string(),
+            content: "ok".to_string(),
+            is_error: false,
+        }]);
+        assert_eq!(
+            message_json(&result),
+            json!({"role": "user", "content": [{"type": "tool_result", "tool_use_id": "toolu_01", "content": "ok"}]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_is_an_error() {
+        let frames: Frames = Box::pin(futures::stream::iter([
+            Ok(r#"data: {"type": "message_start", "message": {"usage": {"input_tokens": 5}}}"#.to_string()),
+            Ok(r#"data: {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}"#.to_string()),
+        ]));
+
+        let events: Vec<Result<StreamEvent, LlmError>> = events(frames, "claude-sonnet-4-5").collect().await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[1], Err(error) if error.code() == crate::llm::ErrorCode::Network));
+    }
+
+    #[test]
+    fn test_message_state_surfaces_errors() {
+        let mut state = MessageState::new("claude-sonnet-4-5");
+        let error = json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}});
+        assert!(matches!(state.apply(&error), Err(LlmError::Status { status: 529, .. })));
+
+        let invalid = json!({"type": "error", "error": {"type": "invalid_request_error", "message": "Bad"}});
+        assert!(matches!(state.apply(&invalid), Err(LlmError::Protocol { .. })));
+    }
+}
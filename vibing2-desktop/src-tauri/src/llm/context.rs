@@ -0,0 +1,275 @@
+// Context window management - fit a project's history into the model window
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+use super::ChatMessage;
+use crate::usage::estimate_tokens;
+
+/// Tokens held back for the provider's own framing and estimate error
+const SAFETY_MARGIN: u32 = 1024;
+
+/// Turns kept by the sliding-window strategy
+pub const SLIDING_WINDOW_TURNS: usize = 20;
+
+/// Longest excerpt of each turn kept in a summary
+const SUMMARY_EXCERPT_CHARS: usize = 200;
+
+/// How a project's history is cut down when it doesn't fit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Drop the oldest turns until the rest fits
+    #[default]
+    Drop,
+    /// Replace the oldest turns with a short summary in the system prompt
+    Summarize,
+    /// Keep only the most recent turns, then drop to fit
+    SlidingWindow,
+}
+
+impl ContextStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContextStrategy::Drop => "drop",
+            ContextStrategy::Summarize => "summarize",
+            ContextStrategy::SlidingWindow => "sliding_window",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<ContextStrategy, String> {
+        match value {
+            "drop" => Ok(ContextStrategy::Drop),
+            "summarize" => Ok(ContextStrategy::Summarize),
+            "sliding_window" => Ok(ContextStrategy::SlidingWindow),
+            other => Err(format!("Unknown context strategy: {}", other)),
+        }
+    }
+}
+
+/// History that fits the model window
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Context {
+    /// Summary of omitted turns, for the system prompt
+    pub summary: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    /// Turns left out (dropped or summarized)
+    pub omitted: usize,
+}
+
+/// Context window of a model in tokens (conservative for unknown models)
+pub fn context_window(model: &str) -> u32 {
+    let model = model.to_lowercase();
+    if model.starts_with("claude") {
+        200_000
+    } else if model.starts_with("gpt-4.1") {
+        1_000_000
+    } else if model.starts_with("gpt-4o") || model.starts_with("gpt-4-turbo") || ["o1", "o3", "o4"].iter().any(|p| model.starts_with(p)) {
+        128_000
+    } else if model.starts_with("gpt-3.5") {
+        16_385
+    } else if model.starts_with("llama3.1") || model.starts_with("llama3.2") || model.starts_with("qwen2.5") {
+        32_768
+    } else {
+        8_192
+    }
+}
+
+/// Load a project's history and fit it, with the new prompt, into `model`'s
+/// window
+pub async fn build_context(
+    pool: &SqlitePool,
+    project_id: &str,
+    prompt: &str,
+    model: &str,
+    max_output_tokens: u32,
+) -> Result<Context, sqlx::Error> {
+    let strategy = project_strategy(pool, project_id).await?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT role, content
+        FROM messages
+        WHERE project_id = ? AND role IN ('user', 'assistant') AND branch_status IS NULL
+        ORDER BY created_at ASC, rowid ASC
+        "#
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await?;
+
+    let history: Vec<ChatMessage> = rows
+        .iter()
+        .map(|row| ChatMessage {
+            role: row.get("role"),
+            content: row.get("content"),
+            blocks: Vec::new(),
+        })
+        .collect();
+
+    let budget = context_window(model)
+        .saturating_sub(max_output_tokens)
+        .saturating_sub(estimate_tokens(prompt))
+        .saturating_sub(SAFETY_MARGIN);
+
+    Ok(fit(history, budget, strategy))
+}
+
+/// Strategy configured for a project (`drop` if unset or unknown)
+pub async fn project_strategy(pool: &SqlitePool, project_id: &str) -> Result<ContextStrategy, sqlx::Error> {
+    let value: Option<String> = sqlx::query_scalar("SELECT context_strategy FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(value
+        .and_then(|value| ContextStrategy::parse(&value).ok())
+        .unwrap_or_default())
+}
+
+/// Cut `history` down to `budget` tokens using `strategy`
+pub fn fit(history: Vec<ChatMessage>, budget: u32, strategy: ContextStrategy) -> Context {
+    let total = history.len();
+    let mut history = history;
+
+    if strategy == ContextStrategy::SlidingWindow && history.len() > SLIDING_WINDOW_TURNS {
+        history.drain(..history.len() - SLIDING_WINDOW_TURNS);
+    }
+
+    // Keep the newest turns that fit
+    let mut used = 0u32;
+    let mut keep_from = history.len();
+    for (index, message) in history.iter().enumerate().rev() {
+        let cost = estimate_tokens(&message.content);
+        if used + cost > budget {
+            break;
+        }
+        used += cost;
+        keep_from = index;
+    }
+
+    let mut kept = history.split_off(keep_from);
+    let mut dropped = history;
+
+    // Providers expect the conversation to open with a user turn
+    while kept.first().is_some_and(|message| message.role != "user") {
+        let message = kept.remove(0);
+        used -= estimate_tokens(&message.content);
+        dropped.push(message);
+    }
+
+    let summary = match strategy {
+        ContextStrategy::Summarize if !dropped.is_empty() => {
+            Some(summarize(&dropped, budget.saturating_sub(used)))
+        }
+        _ => None,
+    };
+
+    Context {
+        summary: summary.filter(|summary| !summary.is_empty()),
+        omitted: total - kept.len(),
+        messages: kept,
+    }
+}
+
+/// Extractive summary: the start of each omitted turn, newest kept when the
+/// budget runs out
+fn summarize(turns: &[ChatMessage], budget: u32) -> String {
+    let mut lines = Vec::new();
+    let mut used = 0u32;
+
+    for turn in turns.iter().rev() {
+        let excerpt: String = turn.content.split_whitespace().collect::<Vec<_>>().join(" ");
+        let excerpt = match excerpt.char_indices().nth(SUMMARY_EXCERPT_CHARS) {
+            Some((end, _)) => format!("{}…", &excerpt[..end]),
+            None => excerpt,
+        };
+        let line = format!("- {}: {}", turn.role, excerpt);
+
+        let cost = estimate_tokens(&line);
+        if used + cost > budget {
+            break;
+        }
+        used += cost;
+        lines.push(line);
+    }
+
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    lines.reverse();
+    format!("Summary of earlier conversation:\n{}", lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turns(count: usize, size: usize) -> Vec<ChatMessage> {
+        (0..count)
+            .map(|i| {
+                let content = format!("{}{}", i, "x".repeat(size));
+                if i % 2 == 0 {
+                    ChatMessage::user(content)
+                } else {
+                    ChatMessage::assistant(content)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_everything_fits() {
+        let context = fit(turns(4, 10), 1_000, ContextStrategy::Drop);
+        assert_eq!(context.messages.len(), 4);
+        assert_eq!(context.omitted, 0);
+        assert_eq!(context.summary, None);
+    }
+
+    #[test]
+    fn test_drop_keeps_newest_turns_starting_with_user() {
+        // Each turn is ~26 tokens; room for three, but the oldest of those
+        // is an assistant turn and goes too
+        let context = fit(turns(6, 100), 80, ContextStrategy::Drop);
+        assert_eq!(context.messages.len(), 2);
+        assert_eq!(context.messages[0].role, "user");
+        assert!(context.messages[0].content.starts_with('4'));
+        assert_eq!(context.omitted, 4);
+        assert_eq!(context.summary, None);
+    }
+
+    #[test]
+    fn test_sliding_window_caps_turns() {
+        let context = fit(turns(30, 10), 100_000, ContextStrategy::SlidingWindow);
+        assert_eq!(context.messages.len(), SLIDING_WINDOW_TURNS);
+        assert_eq!(context.omitted, 10);
+    }
+
+    #[test]
+    fn test_summarize_replaces_dropped_turns() {
+        // Turns 2-5 are kept; what's left of the budget covers one excerpt,
+        // and the newest omitted turn wins
+        let context = fit(turns(6, 100), 150, ContextStrategy::Summarize);
+        assert_eq!(context.omitted, 2);
+
+        let summary = context.summary.unwrap();
+        assert!(summary.starts_with("Summary of earlier conversation:"));
+        assert!(summary.contains("- assistant: 1"));
+        assert!(!summary.contains("- user: 0"));
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("claude-sonnet-4-5"), 200_000);
+        assert_eq!(context_window("gpt-4o-mini"), 128_000);
+        assert_eq!(context_window("mystery"), 8_192);
+    }
+
+    #[test]
+    fn test_strategy_round_trip() {
+        for strategy in [ContextStrategy::Drop, ContextStrategy::Summarize, ContextStrategy::SlidingWindow] {
+            assert_eq!(ContextStrategy::parse(strategy.as_str()).unwrap(), strategy);
+        }
+        assert!(ContextStrategy::parse("shuffle").is_err());
+    }
+}
@@ -0,0 +1,55 @@
+// Demo provider - canned response for trying the app without an API key
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use std::time::Duration;
+use tokio_stream::wrappers::IntervalStream;
+
+use super::{EventStream, GenerationRequest, LlmError, Provider, ProviderKind, StreamEvent};
+use crate::usage::{self, Usage};
+
+const MESSAGES: [&str; 11] = [
+    "I'll help you with that request.",
+    "Let me analyze your requirements...",
+    "Here's what I'll create for you:",
+    "\n```javascript",
+    "// Sample code implementation",
+    "function helloWorld() {",
+    "  console.log('Hello from Vibing2!');",
+    "}",
+    "```\n",
+    "This implementation provides a basic structure.",
+    "Would you like me to add more features?",
+];
+
+/// Streams a fixed response, one line every 100ms
+pub struct DemoProvider;
+
+impl Provider for DemoProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Demo
+    }
+
+    fn default_model(&self) -> &'static str {
+        "vibing2-demo"
+    }
+
+    fn stream(&self, request: GenerationRequest) -> BoxFuture<'_, Result<EventStream, LlmError>> {
+        Box::pin(async move {
+            let ticks = IntervalStream::new(tokio::time::interval(Duration::from_millis(100)));
+            let usage = Usage {
+                input_tokens: request.estimate_input_tokens(),
+                output_tokens: MESSAGES.iter().map(|m| usage::estimate_tokens(m)).sum(),
+                model: request.model,
+                stop_reason: "end_turn".to_string(),
+            };
+
+            let text = ticks
+                .zip(futures::stream::iter(MESSAGES))
+                .map(|(_, message)| Ok(StreamEvent::Text(message.to_string())));
+            let done = futures::stream::once(async move { Ok(StreamEvent::Done(usage)) });
+
+            let events: EventStream = Box::pin(text.chain(done));
+            Ok(events)
+        })
+    }
+}
@@ -0,0 +1,117 @@
+// Frame decoding for streamed response bodies (SSE and NDJSON)
+use futures::stream::{BoxStream, StreamExt};
+
+use super::recorder::Recorder;
+use super::LlmError;
+
+/// Decoded frames of a response body
+pub(super) type Frames = BoxStream<'static, Result<String, LlmError>>;
+
+/// Splits a byte stream on a delimiter, buffering partial frames
+///
+/// Works on bytes so multi-byte characters split across chunks survive.
+pub(super) struct FrameDecoder {
+    buffer: Vec<u8>,
+    delimiter: &'static [u8],
+}
+
+impl FrameDecoder {
+    pub fn new(delimiter: &'static [u8]) -> Self {
+        Self {
+            buffer: Vec::new(),
+            delimiter,
+        }
+    }
+
+    /// Add a chunk and return every frame it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        // Normalize CRLF so "\n\n" also matches "\r\n\r\n"
+        self.buffer.extend(chunk.iter().filter(|b| **b != b'\r'));
+
+        let mut frames = Vec::new();
+        while let Some(pos) = self
+            .buffer
+            .windows(self.delimiter.len())
+            .position(|window| window == self.delimiter)
+        {
+            let frame: Vec<u8> = self.buffer.drain(..pos + self.delimiter.len()).collect();
+            let frame = String::from_utf8_lossy(&frame[..pos]).trim().to_string();
+            if !frame.is_empty() {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+
+    /// Whatever is left once the body ends
+    pub fn finish(self) -> Option<String> {
+        let rest = String::from_utf8_lossy(&self.buffer).trim().to_string();
+        (!rest.is_empty()).then_some(rest)
+    }
+}
+
+/// The `data:` payload of a server-sent event frame (multiple lines joined)
+pub(super) fn sse_data(frame: &str) -> Option<String> {
+    let lines: Vec<&str> = frame
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Stream a response body as delimited frames, recording each one when a
+/// `recorder` is given
+pub(super) fn frames(
+    provider: &'static str,
+    response: reqwest::Response,
+    delimiter: &'static [u8],
+    recorder: Option<Recorder>,
+) -> Frames {
+    let mut body = response.bytes_stream();
+
+    Box::pin(async_stream::try_stream! {
+        let mut decoder = FrameDecoder::new(delimiter);
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(super::connection_error(provider))?;
+            for frame in decoder.push(&chunk) {
+                if let Some(recorder) = &recorder {
+                    recorder.frame(&frame);
+                }
+                yield frame;
+            }
+        }
+        if let Some(frame) = decoder.finish() {
+            if let Some(recorder) = &recorder {
+                recorder.frame(&frame);
+            }
+            yield frame;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_decoder_buffers_partial_frames() {
+        let mut decoder = FrameDecoder::new(b"\n\n");
+        assert!(decoder.push(b"data: {\"a\"").is_empty());
+        assert_eq!(decoder.push(b":1}\r\n\r\ndata: x\n\n"), vec!["data: {\"a\":1}", "data: x"]);
+
+        // A multi-byte character split across chunks
+        let snowman = "data: \u{2603}\n\n".as_bytes();
+        assert!(decoder.push(&snowman[..7]).is_empty());
+        assert_eq!(decoder.push(&snowman[7..]), vec!["data: \u{2603}"]);
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn test_sse_data() {
+        assert_eq!(sse_data("event: ping\ndata: {}"), Some("{}".to_string()));
+        assert_eq!(sse_data("data: a\ndata: b"), Some("a\nb".to_string()));
+        assert_eq!(sse_data(": comment"), None);
+    }
+}
@@ -0,0 +1,561 @@
+//! LLM providers behind a common streaming interface
+//!
+//! Each [`Provider`] turns a [`GenerationRequest`] into a stream of
+//! [`StreamEvent`]s ending with [`StreamEvent::Done`], so the streaming
+//! endpoint and commands don't depend on any one vendor's wire format.
+//! [`resolve`] picks the provider for a request from an explicit provider
+//! name or, failing that, the model name, and [`generate`] adds retries and
+//! failover on top.
+
+mod anthropic;
+mod context;
+mod demo;
+mod frames;
+mod models;
+mod ollama;
+mod openai;
+mod queue;
+mod recorder;
+mod retry;
+mod schema;
+mod tools;
+
+pub use anthropic::AnthropicProvider;
+pub use context::{build_context, context_window, project_strategy, Context, ContextStrategy};
+pub use demo::DemoProvider;
+pub use models::{invalidate_models, list_models, validate_model, ModelCatalog, ModelInfo, ProviderStatus};
+pub use ollama::{detect as detect_ollama, OllamaProvider, OllamaStatus, BASE_URL_SETTING as OLLAMA_BASE_URL_SETTING};
+pub use openai::OpenAiProvider;
+pub use queue::{Priority, QueueStatus, RateLimitQueue, Ticket};
+pub use recorder::{enabled as recording_enabled, replay, Recorder, SETTING_KEY as RECORD_STREAMS_SETTING};
+pub use retry::{generate, load_policy, RetryPolicy, RetryStatus};
+pub use schema::{validate as validate_response, ResponseFormat, SchemaError};
+pub use tools::{generate_with_tools, Tool, ToolCall, ToolDefinition, ToolRegistry, ToolResult};
+
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::usage::{self, Usage};
+
+/// Output token cap when the caller doesn't set one
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// A single conversation turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// `user` or `assistant`
+    pub role: String,
+    pub content: String,
+    /// Structured content (tool calls and results); replaces `content` when
+    /// non-empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<ContentBlock>,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn with_blocks(role: &str, blocks: Vec<ContentBlock>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: String::new(),
+            blocks,
+        }
+    }
+
+    /// Plain-text view, for providers without structured content; images
+    /// are left out (see [`ChatMessage::images`])
+    pub fn text(&self) -> String {
+        if self.blocks.is_empty() {
+            return self.content.clone();
+        }
+
+        self.blocks
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.clone()),
+                ContentBlock::ToolUse { name, input, .. } => Some(format!("[called {} with {}]", name, input)),
+                ContentBlock::ToolResult { content, .. } => Some(content.clone()),
+                ContentBlock::Image { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Images attached to the message, as (media type, base64 data)
+    pub fn images(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.blocks.iter().filter_map(|block| match block {
+            ContentBlock::Image { source } => Some((source.media_type.as_str(), source.data.as_str())),
+            _ => None,
+        })
+    }
+}
+
+/// A piece of structured message content, in Anthropic's wire format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
+    },
+    Image {
+        source: ImageSource,
+    },
+}
+
+/// Inline image data of a [`ContentBlock::Image`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageSource {
+    /// Always `base64`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub media_type: String,
+    pub data: String,
+}
+
+impl ContentBlock {
+    pub fn image(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        ContentBlock::Image {
+            source: ImageSource {
+                kind: "base64".to_string(),
+                media_type: media_type.into(),
+                data: data.into(),
+            },
+        }
+    }
+}
+
+
+/// Provider-neutral generation request
+#[derive(Debug, Clone)]
+pub struct GenerationRequest {
+    pub model: String,
+    pub system: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u32,
+    /// Sampling temperature; the provider's default when unset
+    pub temperature: Option<f32>,
+    /// Tools the model may call; ignored by providers without tool support
+    pub tools: Vec<ToolDefinition>,
+    /// Ask for JSON conforming to a schema
+    pub response_format: Option<ResponseFormat>,
+    /// Records the raw provider stream for replay
+    pub recorder: Option<Recorder>,
+}
+
+impl GenerationRequest {
+    /// Stand-in request for parsing a recorded stream, which only needs the
+    /// model
+    fn replayed(model: String) -> Self {
+        Self {
+            model,
+            system: None,
+            messages: Vec::new(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: None,
+            tools: Vec::new(),
+            response_format: None,
+            recorder: None,
+        }
+    }
+
+    /// Estimated prompt size, for providers that don't report usage
+    pub fn estimate_input_tokens(&self) -> u32 {
+        let system = self.system.as_deref().map(usage::estimate_tokens).unwrap_or(0);
+        self.messages
+            .iter()
+            .map(|message| usage::estimate_tokens(&message.text()))
+            .sum::<u32>()
+            + system
+    }
+}
+
+/// An item of a generation stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// A chunk of assistant text
+    Text(String),
+    /// A failed attempt is about to be retried (see [`generate`])
+    Retry(RetryStatus),
+    /// Waiting out a rate limit in the queue (see [`RateLimitQueue`])
+    RateLimited(QueueStatus),
+    /// Waiting for the internet to reach this provider (see [`generate`])
+    Offline(String),
+    /// The model called a tool; generation stops with `tool_use` afterwards
+    ToolUse(ToolCall),
+    /// A tool call's result, fed back by [`generate_with_tools`]
+    ToolResult(ToolResult),
+    /// End of the generation; always the last event
+    Done(Usage),
+}
+
+pub type EventStream = BoxStream<'static, Result<StreamEvent, LlmError>>;
+
+/// Errors from resolving or talking to a provider
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum LlmError {
+    #[error("{provider} returned {status}: {message}")]
+    Status {
+        provider: &'static str,
+        status: u16,
+        message: String,
+        /// How long the provider asked us to wait (`Retry-After`), if it said
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Connection to {provider} failed: {message}")]
+    Connection {
+        provider: &'static str,
+        message: String,
+    },
+
+    #[error("Unexpected response from {provider}: {message}")]
+    Protocol {
+        provider: &'static str,
+        message: String,
+    },
+
+    #[error("{0}")]
+    Config(String),
+
+    /// An agent's or project's daily budget is spent (see [`crate::budgets`])
+    #[error("{0}")]
+    BudgetExceeded(String),
+}
+
+/// Machine-readable kind of an [`LlmError`], for clients deciding how to react
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The provider rejected the key or token
+    AuthInvalid,
+    RateLimited,
+    /// The provider is temporarily over capacity
+    Overloaded,
+    /// The prompt and history don't fit the model's context window
+    ContextTooLong,
+    /// The provider couldn't be reached, or the connection dropped
+    Network,
+    /// The request can't succeed as sent: unknown model, missing key, bad input
+    InvalidRequest,
+    /// Any other provider failure
+    ProviderError,
+    /// An agent's or project's daily budget is spent
+    BudgetExceeded,
+}
+
+impl LlmError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            LlmError::Status { status, message, .. } => match status {
+                401 | 403 => ErrorCode::AuthInvalid,
+                429 => ErrorCode::RateLimited,
+                503 | 529 => ErrorCode::Overloaded,
+                408 => ErrorCode::Network,
+                413 => ErrorCode::ContextTooLong,
+                400..=499 if mentions_context_length(message) => ErrorCode::ContextTooLong,
+                400..=499 => ErrorCode::InvalidRequest,
+                _ => ErrorCode::ProviderError,
+            },
+            LlmError::Connection { .. } => ErrorCode::Network,
+            LlmError::Protocol { message, .. } if mentions_context_length(message) => ErrorCode::ContextTooLong,
+            LlmError::Protocol { .. } => ErrorCode::ProviderError,
+            LlmError::Config(_) => ErrorCode::InvalidRequest,
+            LlmError::BudgetExceeded(_) => ErrorCode::BudgetExceeded,
+        }
+    }
+}
+
+/// Providers report an oversized prompt as a plain 400 with one of these
+fn mentions_context_length(message: &str) -> bool {
+    let message = message.to_lowercase();
+    ["prompt is too long", "context length", "context window", "maximum context", "too many tokens"]
+        .iter()
+        .any(|phrase| message.contains(phrase))
+}
+
+/// A streaming chat backend
+pub trait Provider: Send + Sync {
+    fn kind(&self) -> ProviderKind;
+
+    /// Whether image content blocks are sent to the model
+    fn supports_images(&self) -> bool {
+        false
+    }
+
+    /// Model used when the request doesn't name one
+    fn default_model(&self) -> &'static str;
+
+    /// Start a generation; the returned stream ends with [`StreamEvent::Done`]
+    fn stream(&self, request: GenerationRequest) -> BoxFuture<'_, Result<EventStream, LlmError>>;
+
+    /// Models the provider offers; just the default model unless overridden
+    fn models(&self) -> BoxFuture<'_, Result<Vec<ModelInfo>, LlmError>> {
+        let model = ModelInfo::new(self.kind(), self.default_model(), None);
+        Box::pin(async move { Ok(vec![model]) })
+    }
+}
+
+/// Providers a request can be routed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Canned response, no network access
+    Demo,
+    Anthropic,
+    OpenAI,
+    /// OpenAI-compatible endpoint stored as the `custom` credential
+    Custom,
+    Ollama,
+}
+
+impl ProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::Demo => "demo",
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::OpenAI => "openai",
+            ProviderKind::Custom => "custom",
+            ProviderKind::Ollama => "ollama",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<ProviderKind, LlmError> {
+        match value.to_lowercase().as_str() {
+            "demo" => Ok(ProviderKind::Demo),
+            "anthropic" | "claude" => Ok(ProviderKind::Anthropic),
+            "openai" => Ok(ProviderKind::OpenAI),
+            "custom" => Ok(ProviderKind::Custom),
+            "ollama" => Ok(ProviderKind::Ollama),
+            other => Err(LlmError::Config(format!("Unknown provider: {}", other))),
+        }
+    }
+
+    /// Runs on this machine, without network access
+    pub fn is_local(&self) -> bool {
+        matches!(self, ProviderKind::Ollama | ProviderKind::Demo)
+    }
+
+    /// Guess the provider from a model name
+    pub fn infer(model: &str) -> Option<ProviderKind> {
+        let model = model.to_lowercase();
+        if model.starts_with("claude") {
+            Some(ProviderKind::Anthropic)
+        } else if model.starts_with("gpt-") || ["o1", "o3", "o4"].iter().any(|p| model.starts_with(p)) {
+            Some(ProviderKind::OpenAI)
+        } else if model.contains(':')
+            || ["llama", "mistral", "qwen", "gemma", "phi", "deepseek"].iter().any(|p| model.starts_with(p))
+        {
+            Some(ProviderKind::Ollama)
+        } else if model.starts_with("vibing2-demo") {
+            Some(ProviderKind::Demo)
+        } else {
+            None
+        }
+    }
+}
+
+/// Pick and configure the provider for a request
+///
+/// An explicit `provider` wins; otherwise it is inferred from `model`, or
+/// found among the models a local Ollama has pulled, and requests naming
+/// neither go to Anthropic's default model. The demo provider only answers
+/// when named. Returns the provider and the model to ask it for.
+pub async fn resolve(
+    pool: &SqlitePool,
+    provider: Option<&str>,
+    model: Option<&str>,
+) -> Result<(Arc<dyn Provider>, String), LlmError> {
+    let model = model.map(str::trim).filter(|model| !model.is_empty());
+
+    let kind = match (provider, model) {
+        (Some(provider), _) => ProviderKind::parse(provider)?,
+        (None, Some(model)) => match ProviderKind::infer(model) {
+            Some(kind) => kind,
+            None if ollama_offers(pool, model).await => ProviderKind::Ollama,
+            None => {
+                return Err(LlmError::Config(format!(
+                    "Can't infer the provider for model {}; set provider",
+                    model
+                )))
+            }
+        },
+        (None, None) => ProviderKind::Anthropic,
+    };
+
+    let provider: Arc<dyn Provider> = match kind {
+        ProviderKind::Demo => Arc::new(DemoProvider),
+        ProviderKind::Anthropic => {
+            let creds = load_key(pool, crate::auth::Provider::Anthropic).await?;
+            Arc::new(AnthropicProvider::new(creds.api_key, creds.base_url, creds.oauth))
+        }
+        ProviderKind::OpenAI => {
+            let creds = load_key(pool, crate::auth::Provider::OpenAI).await?;
+            Arc::new(OpenAiProvider::new(ProviderKind::OpenAI, creds.api_key, creds.base_url))
+        }
+        ProviderKind::Custom => {
+            let creds = load_key(pool, crate::auth::Provider::Custom).await?;
+            if creds.base_url.as_deref().unwrap_or_default().is_empty() {
+                return Err(LlmError::Config("Custom provider requires a base URL".to_string()));
+            }
+            Arc::new(OpenAiProvider::new(ProviderKind::Custom, creds.api_key, creds.base_url))
+        }
+        ProviderKind::Ollama => Arc::new(OllamaProvider::new(ollama::configured_base_url(pool).await)),
+    };
+
+    let model = model.unwrap_or(provider.default_model()).to_string();
+    Ok((provider, model))
+}
+
+/// Whether the local Ollama has pulled `model`; false when it isn't running
+async fn ollama_offers(pool: &SqlitePool, model: &str) -> bool {
+    let provider = OllamaProvider::new(ollama::configured_base_url(pool).await);
+    models::provider_models(&provider, false)
+        .await
+        .is_ok_and(|models| models.iter().any(|info| info.matches(model)))
+}
+
+async fn load_key(
+    pool: &SqlitePool,
+    provider: crate::auth::Provider,
+) -> Result<crate::auth::ProviderCredentials, LlmError> {
+    crate::auth::load_provider_key(pool, provider)
+        .await
+        .map_err(LlmError::Config)
+}
+
+/// Shared HTTP client (connection pooling across generations), through
+/// the configured proxy
+fn http_client() -> reqwest::Client {
+    crate::proxy::client()
+}
+
+/// Turn a non-success response into [`LlmError::Status`] with the
+/// provider's error message
+async fn check_status(
+    provider: &'static str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, LlmError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|json| {
+            json.pointer("/error/message")
+                .or_else(|| json.get("error"))
+                .and_then(|message| message.as_str().map(String::from))
+        })
+        .unwrap_or(body);
+
+    Err(LlmError::Status {
+        provider,
+        status: status.as_u16(),
+        message,
+        retry_after,
+    })
+}
+
+fn connection_error(provider: &'static str) -> impl Fn(reqwest::Error) -> LlmError {
+    move |e| LlmError::Connection {
+        provider,
+        message: e.to_string(),
+    }
+}
+
+/// Map OpenAI-style finish reasons onto Anthropic's stop reasons
+fn normalize_stop_reason(reason: &str) -> String {
+    match reason {
+        "stop" => "end_turn".to_string(),
+        "length" => "max_tokens".to_string(),
+        "tool_calls" => "tool_use".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_provider() {
+        assert_eq!(ProviderKind::infer("claude-sonnet-4-5"), Some(ProviderKind::Anthropic));
+        assert_eq!(ProviderKind::infer("gpt-4o-mini"), Some(ProviderKind::OpenAI));
+        assert_eq!(ProviderKind::infer("llama3.1:8b"), Some(ProviderKind::Ollama));
+        assert_eq!(ProviderKind::infer("mystery-model"), None);
+    }
+
+    #[tokio::test]
+    async fn test_unnamed_provider_is_never_demo() {
+        let temp_db = tempfile::NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        assert!(resolve(&pool, None, None).await.is_err());
+
+        crate::auth::store_credentials_in_db(&pool, "sk-ant-api03-key", None, None).await.unwrap();
+        let (provider, model) = resolve(&pool, None, None).await.unwrap();
+        assert_eq!(provider.kind(), ProviderKind::Anthropic);
+        assert_eq!(model, provider.default_model());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let status = |status, message: &str| LlmError::Status { provider: "test", status, message: message.to_string(), retry_after: None };
+        assert_eq!(status(401, "invalid x-api-key").code(), ErrorCode::AuthInvalid);
+        assert_eq!(status(429, "slow down").code(), ErrorCode::RateLimited);
+        assert_eq!(status(529, "Overloaded").code(), ErrorCode::Overloaded);
+        assert_eq!(status(400, "prompt is too long: 210000 tokens > 200000 maximum").code(), ErrorCode::ContextTooLong);
+        assert_eq!(status(400, "max_tokens: must be positive").code(), ErrorCode::InvalidRequest);
+        assert_eq!(status(500, "Internal error").code(), ErrorCode::ProviderError);
+
+        let dropped = LlmError::Connection { provider: "test", message: "reset".to_string() };
+        assert_eq!(dropped.code(), ErrorCode::Network);
+        assert_eq!(LlmError::Config("Unknown provider: x".to_string()).code(), ErrorCode::InvalidRequest);
+    }
+
+    #[test]
+    fn test_normalize_stop_reason() {
+        assert_eq!(normalize_stop_reason("stop"), "end_turn");
+        assert_eq!(normalize_stop_reason("length"), "max_tokens");
+        assert_eq!(normalize_stop_reason("content_filter"), "content_filter");
+    }
+}
@@ -0,0 +1,250 @@
+// Model catalog - models each configured provider offers, with capabilities
+use futures::future::BoxFuture;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use super::{context_window, resolve, LlmError, Provider, ProviderKind};
+use crate::usage::{self, ModelPrice};
+
+/// How long a provider's model list is reused before asking again
+const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How long to wait for a provider to list its models
+const LIST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Providers in the order the catalog lists them
+const PROVIDERS: [ProviderKind; 5] = [
+    ProviderKind::Anthropic,
+    ProviderKind::OpenAI,
+    ProviderKind::Custom,
+    ProviderKind::Ollama,
+    ProviderKind::Demo,
+];
+
+/// A model a provider can run
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ModelInfo {
+    pub id: String,
+    #[schema(value_type = String)]
+    pub provider: ProviderKind,
+    pub display_name: String,
+    /// Prompt plus output tokens the model accepts
+    pub context_window: u32,
+    /// Whether the model can call project tools (`tools` in a stream request)
+    pub supports_tools: bool,
+    /// Runs on this machine, without network access
+    pub local: bool,
+    /// `None` when the model isn't in the price table
+    pub price: Option<ModelPrice>,
+}
+
+impl ModelInfo {
+    pub fn new(provider: ProviderKind, id: impl Into<String>, display_name: Option<String>) -> Self {
+        let id = id.into();
+        Self {
+            display_name: display_name.unwrap_or_else(|| id.clone()),
+            provider,
+            context_window: context_window(&id),
+            supports_tools: provider == ProviderKind::Anthropic,
+            local: provider.is_local(),
+            price: usage::price(&id),
+            id,
+        }
+    }
+
+    /// Whether a request for `model` runs this model
+    ///
+    /// Aliases match the dated versions they point to (`claude-sonnet-4-5`
+    /// matches `claude-sonnet-4-5-20250929`), and untagged Ollama names match
+    /// `:latest`.
+    pub fn matches(&self, model: &str) -> bool {
+        let alias = model
+            .strip_suffix("-latest")
+            .or_else(|| model.strip_suffix("-0"))
+            .unwrap_or(model);
+        let dated = self
+            .id
+            .strip_prefix(alias)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .is_some_and(|date| !date.is_empty() && date.chars().all(|c| c.is_ascii_digit()));
+
+        self.id == model
+            || dated
+            || (self.provider == ProviderKind::Ollama && self.id == format!("{}:latest", model))
+    }
+}
+
+/// Whether a provider could list its models
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProviderStatus {
+    #[schema(value_type = String)]
+    pub provider: ProviderKind,
+    /// Credentials are saved (always true for Ollama and the demo)
+    pub configured: bool,
+    /// The provider answered the last model listing
+    pub available: bool,
+    pub error: Option<String>,
+}
+
+/// Every model available right now, by provider
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ModelCatalog {
+    pub models: Vec<ModelInfo>,
+    pub providers: Vec<ProviderStatus>,
+}
+
+type Cache = Mutex<HashMap<ProviderKind, (Instant, Vec<ModelInfo>)>>;
+
+fn cache() -> &'static Cache {
+    static CACHE: std::sync::OnceLock<Cache> = std::sync::OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Forget cached model lists, e.g. after credentials change
+pub fn invalidate_models() {
+    if let Ok(mut cache) = cache().lock() {
+        cache.clear();
+    }
+}
+
+/// A provider's models, from the cache unless `refresh` is set
+pub(super) async fn provider_models(provider: &dyn Provider, refresh: bool) -> Result<Vec<ModelInfo>, LlmError> {
+    let kind = provider.kind();
+    if !refresh {
+        let cached = cache().lock().ok().and_then(|cache| cache.get(&kind).cloned());
+        if let Some((listed_at, models)) = cached {
+            if listed_at.elapsed() < CACHE_TTL {
+                return Ok(models);
+            }
+        }
+    }
+
+    let models = tokio::time::timeout(LIST_TIMEOUT, provider.models())
+        .await
+        .map_err(|_| LlmError::Connection {
+            provider: kind.as_str(),
+            message: "Timed out listing models".to_string(),
+        })??;
+
+    if let Ok(mut cache) = cache().lock() {
+        cache.insert(kind, (Instant::now(), models.clone()));
+    }
+    Ok(models)
+}
+
+/// List the models of every configured provider
+///
+/// Providers are asked in parallel; one that is unreachable is reported in
+/// `providers` instead of failing the whole catalog. While offline, only
+/// local providers are asked.
+pub async fn list_models(pool: &SqlitePool, refresh: bool) -> ModelCatalog {
+    let mut lookups: Vec<BoxFuture<'_, (ProviderStatus, Vec<ModelInfo>)>> = Vec::new();
+    let online = crate::network::is_online();
+
+    for kind in PROVIDERS {
+        lookups.push(Box::pin(async move {
+            if !online && !kind.is_local() {
+                let status = ProviderStatus {
+                    provider: kind,
+                    configured: resolve(pool, Some(kind.as_str()), None).await.is_ok(),
+                    available: false,
+                    error: Some("Offline".to_string()),
+                };
+                return (status, Vec::new());
+            }
+
+            let provider = match resolve(pool, Some(kind.as_str()), None).await {
+                Ok((provider, _)) => provider,
+                Err(e) => {
+                    let status = ProviderStatus {
+                        provider: kind,
+                        configured: false,
+                        available: false,
+                        error: Some(e.to_string()),
+                    };
+                    return (status, Vec::new());
+                }
+            };
+
+            match provider_models(provider.as_ref(), refresh).await {
+                Ok(models) => {
+                    let status = ProviderStatus { provider: kind, configured: true, available: true, error: None };
+                    (status, models)
+                }
+                Err(e) => {
+                    let status = ProviderStatus {
+                        provider: kind,
+                        configured: true,
+                        available: false,
+                        error: Some(e.to_string()),
+                    };
+                    (status, Vec::new())
+                }
+            }
+        }));
+    }
+
+    let mut catalog = ModelCatalog { models: Vec::new(), providers: Vec::new() };
+    for (status, models) in futures::future::join_all(lookups).await {
+        catalog.providers.push(status);
+        catalog.models.extend(models);
+    }
+    catalog
+}
+
+/// Check that `provider` offers `model`
+///
+/// Custom endpoints aren't checked, and neither is any provider whose model
+/// list can't be fetched right now, so an outage doesn't block generations.
+pub async fn validate_model(provider: &dyn Provider, model: &str) -> Result<(), LlmError> {
+    let kind = provider.kind();
+    if kind == ProviderKind::Custom {
+        return Ok(());
+    }
+
+    match provider_models(provider, false).await {
+        Ok(models) if !models.iter().any(|info| info.matches(model)) => Err(LlmError::Config(format!(
+            "Unknown {} model: {} (see /api/models)",
+            kind.as_str(),
+            model
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_matching() {
+        let dated = ModelInfo::new(ProviderKind::Anthropic, "claude-sonnet-4-5-20250929", None);
+        assert!(dated.matches("claude-sonnet-4-5"));
+        assert!(dated.matches("claude-sonnet-4-5-20250929"));
+        assert!(!dated.matches("claude-sonnet-4"));
+        assert!(!dated.matches("claude"));
+        assert!(dated.supports_tools);
+
+        let local = ModelInfo::new(ProviderKind::Ollama, "llama3.1:latest", None);
+        assert!(local.matches("llama3.1"));
+        assert!(!local.matches("llama3.1:70b"));
+        assert!(local.local);
+        assert_eq!(local.price.unwrap().input_per_mtok, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_demo_model_is_listed() {
+        let temp_db = tempfile::NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let (demo, model) = resolve(&pool, Some("demo"), None).await.unwrap();
+        assert!(validate_model(demo.as_ref(), &model).await.is_ok());
+        assert!(validate_model(demo.as_ref(), "gpt-5").await.is_err());
+    }
+}
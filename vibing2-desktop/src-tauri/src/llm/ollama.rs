@@ -0,0 +1,308 @@
+// Ollama chat provider (local models, no API key)
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{Row, SqlitePool};
+use std::time::Duration;
+
+use super::frames::{frames, Frames};
+use super::{
+    check_status, connection_error, http_client, normalize_stop_reason, EventStream, GenerationRequest,
+    LlmError, ModelInfo, Provider, ProviderKind, StreamEvent,
+};
+use crate::usage::Usage;
+
+const NAME: &str = "Ollama";
+const DEFAULT_BASE_URL: &str = "http://127.0.0.1:11434";
+
+/// Settings key for the Ollama base URL
+pub const BASE_URL_SETTING: &str = "ollama_base_url";
+
+/// How long to wait for the daemon to answer a detection probe
+const DETECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct OllamaProvider {
+    base_url: String,
+}
+
+impl OllamaProvider {
+    /// `base_url` defaults to the local Ollama daemon; a bare `host:port`
+    /// (as in `OLLAMA_HOST`) is accepted
+    pub fn new(base_url: Option<String>) -> Self {
+        let base_url = match base_url.filter(|url| !url.is_empty()) {
+            Some(url) if url.contains("://") => url,
+            Some(host) => format!("http://{}", host),
+            None => DEFAULT_BASE_URL.to_string(),
+        };
+        Self { base_url }
+    }
+}
+
+/// The Ollama base URL from settings, else `OLLAMA_HOST`
+pub async fn configured_base_url(pool: &SqlitePool) -> Option<String> {
+    let setting = sqlx::query("SELECT value FROM settings WHERE key = ?")
+        .bind(BASE_URL_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .map(|row| row.get::<String, _>("value"))
+        .filter(|url| !url.trim().is_empty());
+
+    setting.or_else(|| std::env::var("OLLAMA_HOST").ok())
+}
+
+/// Whether an Ollama daemon answers at the configured URL
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaStatus {
+    pub base_url: String,
+    pub running: bool,
+    /// Daemon version, when running
+    pub version: Option<String>,
+    /// Locally pulled models, when running
+    pub models: Vec<ModelInfo>,
+    pub error: Option<String>,
+}
+
+/// Probe the Ollama daemon and, when it answers, list its models
+///
+/// The model list goes through the catalog cache, so models found here are
+/// what `/api/models` reports and what requested models are checked against.
+pub async fn detect(pool: &SqlitePool) -> OllamaStatus {
+    let provider = OllamaProvider::new(configured_base_url(pool).await);
+    let mut status = OllamaStatus {
+        base_url: provider.base_url.clone(),
+        running: false,
+        version: None,
+        models: Vec::new(),
+        error: None,
+    };
+
+    let probe = http_client()
+        .get(format!("{}/api/version", provider.base_url.trim_end_matches('/')))
+        .timeout(DETECT_TIMEOUT)
+        .send()
+        .await;
+    let body: Value = match probe {
+        Ok(response) if response.status().is_success() => response.json().await.unwrap_or_default(),
+        Ok(response) => {
+            status.error = Some(format!("Unexpected response: {}", response.status()));
+            return status;
+        }
+        Err(e) => {
+            status.error = Some(format!("Not reachable at {}: {}", provider.base_url, e));
+            return status;
+        }
+    };
+
+    status.running = true;
+    status.version = body["version"].as_str().map(String::from);
+    match super::models::provider_models(&provider, true).await {
+        Ok(models) => status.models = models,
+        Err(e) => status.error = Some(e.to_string()),
+    }
+    status
+}
+
+impl Provider for OllamaProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Ollama
+    }
+
+    fn default_model(&self) -> &'static str {
+        "llama3.1"
+    }
+
+    /// Only vision models (llava, llama3.2-vision, ...) use them; others
+    /// ignore the images
+    fn supports_images(&self) -> bool {
+        true
+    }
+
+    fn stream(&self, request: GenerationRequest) -> BoxFuture<'_, Result<EventStream, LlmError>> {
+        Box::pin(async move {
+            let mut messages = Vec::new();
+            if let Some(system) = &request.system {
+                messages.push(json!({"role": "system", "content": system}));
+            }
+            messages.extend(request.messages.iter().map(|m| {
+                let mut message = json!({"role": m.role, "content": m.text()});
+                let images: Vec<&str> = m.images().map(|(_, data)| data).collect();
+                if !images.is_empty() {
+                    message["images"] = json!(images);
+                }
+                message
+            }));
+
+            let mut options = json!({"num_predict": request.max_tokens});
+            if let Some(temperature) = request.temperature {
+                options["temperature"] = json!(temperature);
+            }
+
+            let mut body = json!({
+                "model": request.model,
+                "messages": messages,
+                "stream": true,
+                "options": options,
+            });
+            if let Some(format) = &request.response_format {
+                body["format"] = format.schema.clone();
+            }
+
+            let response = http_client()
+                .post(format!("{}/api/chat", self.base_url.trim_end_matches('/')))
+                .json(&body)
+                .send()
+                .await
+                .map_err(connection_error(NAME))?;
+            let response = check_status(NAME, response).await?;
+
+            // Newline-delimited JSON, one object per chunk
+            let recorder = super::recorder::attempt(&request, ProviderKind::Ollama);
+            Ok(events(frames(NAME, response, b"\n", recorder), &request.model))
+        })
+    }
+
+    fn models(&self) -> BoxFuture<'_, Result<Vec<ModelInfo>, LlmError>> {
+        Box::pin(async move {
+            let response = http_client()
+                .get(format!("{}/api/tags", self.base_url.trim_end_matches('/')))
+                .send()
+                .await
+                .map_err(connection_error(NAME))?;
+            let body: Value = check_status(NAME, response)
+                .await?
+                .json()
+                .await
+                .map_err(connection_error(NAME))?;
+
+            Ok(parse_models(&body))
+        })
+    }
+}
+
+/// Models from a `GET /api/tags` response
+fn parse_models(body: &Value) -> Vec<ModelInfo> {
+    body["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["name"].as_str())
+        .map(|name| ModelInfo::new(ProviderKind::Ollama, name, None))
+        .collect()
+}
+
+/// Parse `/api/chat` lines into stream events
+pub(super) fn events(mut lines: Frames, model: &str) -> EventStream {
+    let mut usage = Usage {
+        input_tokens: 0,
+        output_tokens: 0,
+        model: model.to_string(),
+        stop_reason: "end_turn".to_string(),
+    };
+
+    Box::pin(async_stream::try_stream! {
+        let mut finished = false;
+        while let Some(line) = lines.next().await {
+            let chunk: Value = serde_json::from_str(&line?).map_err(|e| LlmError::Protocol {
+                provider: NAME,
+                message: e.to_string(),
+            })?;
+
+            if let Some(text) = apply_chunk(&mut usage, &chunk)? {
+                yield StreamEvent::Text(text);
+            }
+            if chunk["done"].as_bool() == Some(true) {
+                finished = true;
+                break;
+            }
+        }
+        if !finished {
+            Err::<(), _>(LlmError::Connection {
+                provider: NAME,
+                message: "Stream ended before the final chunk".to_string(),
+            })?;
+        }
+        yield StreamEvent::Done(usage);
+    })
+}
+
+/// Apply one `/api/chat` chunk, returning any text it carries
+fn apply_chunk(usage: &mut Usage, chunk: &Value) -> Result<Option<String>, LlmError> {
+    if let Some(error) = chunk["error"].as_str() {
+        return Err(LlmError::Protocol {
+            provider: NAME,
+            message: error.to_string(),
+        });
+    }
+
+    if chunk["done"].as_bool() == Some(true) {
+        usage.input_tokens = chunk["prompt_eval_count"].as_u64().unwrap_or(0) as u32;
+        usage.output_tokens = chunk["eval_count"].as_u64().unwrap_or(0) as u32;
+        if let Some(reason) = chunk["done_reason"].as_str() {
+            usage.stop_reason = normalize_stop_reason(reason);
+        }
+    }
+
+    Ok(chunk["message"]["content"]
+        .as_str()
+        .filter(|text| !text.is_empty())
+        .map(String::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_from_host() {
+        assert_eq!(OllamaProvider::new(None).base_url, DEFAULT_BASE_URL);
+        assert_eq!(OllamaProvider::new(Some("10.0.0.5:11434".to_string())).base_url, "http://10.0.0.5:11434");
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let body = json!({"models": [{"name": "llama3.1:latest", "size": 4920753328u64}, {"name": "qwen2.5-coder:7b"}]});
+        let models = parse_models(&body);
+        assert_eq!(models.len(), 2);
+        assert!(models[0].matches("llama3.1"));
+        assert!(models.iter().all(|model| model.local));
+    }
+
+    #[tokio::test]
+    async fn test_base_url_setting_wins() {
+        let temp_db = tempfile::NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        sqlx::query("INSERT INTO settings (id, key, value, updated_at) VALUES ('s1', ?, 'gpu-box:11434', datetime('now'))")
+            .bind(BASE_URL_SETTING)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(configured_base_url(&pool).await.as_deref(), Some("gpu-box:11434"));
+    }
+
+    #[test]
+    fn test_apply_chunk() {
+        let mut usage = Usage {
+            input_tokens: 0,
+            output_tokens: 0,
+            model: "llama3.1".to_string(),
+            stop_reason: "end_turn".to_string(),
+        };
+
+        let text = apply_chunk(&mut usage, &json!({"message": {"role": "assistant", "content": "Hi"}, "done": false}));
+        assert_eq!(text.unwrap(), Some("Hi".to_string()));
+
+        let done = json!({"message": {"content": ""}, "done": true, "done_reason": "length", "prompt_eval_count": 12, "eval_count": 40});
+        assert_eq!(apply_chunk(&mut usage, &done).unwrap(), None);
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 40));
+        assert_eq!(usage.stop_reason, "max_tokens");
+
+        assert!(apply_chunk(&mut usage, &json!({"error": "model not found"})).is_err());
+    }
+}
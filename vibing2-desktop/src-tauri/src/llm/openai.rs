@@ -0,0 +1,311 @@
+// OpenAI Chat Completions provider (also used for compatible endpoints)
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use serde_json::{json, Value};
+
+use super::frames::{frames, sse_data, Frames};
+use super::{
+    check_status, connection_error, http_client, normalize_stop_reason, ChatMessage, EventStream,
+    GenerationRequest, LlmError, ModelInfo, Provider, ProviderKind, StreamEvent,
+};
+use crate::usage::{self, Usage};
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+pub struct OpenAiProvider {
+    kind: ProviderKind,
+    api_key: String,
+    base_url: String,
+}
+
+impl OpenAiProvider {
+    /// `base_url` includes the version segment, e.g. `https://api.openai.com/v1`
+    pub fn new(kind: ProviderKind, api_key: String, base_url: Option<String>) -> Self {
+        Self {
+            kind,
+            api_key,
+            base_url: base_url
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        name(self.kind)
+    }
+}
+
+/// Display name for errors
+pub(super) fn name(kind: ProviderKind) -> &'static str {
+    match kind {
+        ProviderKind::Custom => "Custom endpoint",
+        _ => "OpenAI",
+    }
+}
+
+impl Provider for OpenAiProvider {
+    fn kind(&self) -> ProviderKind {
+        self.kind
+    }
+
+    fn default_model(&self) -> &'static str {
+        "gpt-4o"
+    }
+
+    fn supports_images(&self) -> bool {
+        true
+    }
+
+    fn stream(&self, request: GenerationRequest) -> BoxFuture<'_, Result<EventStream, LlmError>> {
+        Box::pin(async move {
+            let name = self.name();
+
+            let mut messages = Vec::new();
+            if let Some(system) = &request.system {
+                messages.push(json!({"role": "system", "content": system}));
+            }
+            messages.extend(request.messages.iter().map(message_json));
+
+            let mut body = json!({
+                "model": request.model,
+                "max_tokens": request.max_tokens,
+                "stream": true,
+                "stream_options": {"include_usage": true},
+                "messages": messages,
+            });
+            if let Some(temperature) = request.temperature {
+                body["temperature"] = json!(temperature);
+            }
+            if let Some(format) = &request.response_format {
+                body["response_format"] = json!({
+                    "type": "json_schema",
+                    "json_schema": {"name": format.name, "schema": format.schema},
+                });
+            }
+
+            let response = http_client()
+                .post(format!("{}/chat/completions", self.base_url.trim_end_matches('/')))
+                .bearer_auth(&self.api_key)
+                .json(&body)
+                .send()
+                .await
+                .map_err(connection_error(name))?;
+            let response = check_status(name, response).await?;
+
+            let recorder = super::recorder::attempt(&request, self.kind);
+            Ok(events(name, frames(name, response, b"\n\n", recorder), &request))
+        })
+    }
+
+    fn models(&self) -> BoxFuture<'_, Result<Vec<ModelInfo>, LlmError>> {
+        Box::pin(async move {
+            let name = self.name();
+            let response = http_client()
+                .get(format!("{}/models", self.base_url.trim_end_matches('/')))
+                .bearer_auth(&self.api_key)
+                .send()
+                .await
+                .map_err(connection_error(name))?;
+            let body: Value = check_status(name, response)
+                .await?
+                .json()
+                .await
+                .map_err(connection_error(name))?;
+
+            Ok(parse_models(self.kind, &body))
+        })
+    }
+}
+
+/// Chat message JSON, with images as `image_url` content parts
+fn message_json(message: &ChatMessage) -> Value {
+    let images: Vec<Value> = message
+        .images()
+        .map(|(media_type, data)| {
+            json!({"type": "image_url", "image_url": {"url": format!("data:{};base64,{}", media_type, data)}})
+        })
+        .collect();
+    if images.is_empty() {
+        return json!({"role": message.role, "content": message.text()});
+    }
+
+    let mut parts = vec![json!({"type": "text", "text": message.text()})];
+    parts.extend(images);
+    json!({"role": message.role, "content": parts})
+}
+
+/// Parse Chat Completions SSE frames into stream events
+pub(super) fn events(name: &'static str, mut frames: Frames, request: &GenerationRequest) -> EventStream {
+    let mut state = CompletionState::new(request);
+
+    Box::pin(async_stream::try_stream! {
+        while let Some(frame) = frames.next().await {
+            let Some(data) = sse_data(&frame?) else {
+                continue;
+            };
+            if data == "[DONE]" {
+                break;
+            }
+            let chunk: Value = serde_json::from_str(&data).map_err(|e| LlmError::Protocol {
+                provider: name,
+                message: e.to_string(),
+            })?;
+
+            if let Some(text) = state.apply(&chunk) {
+                yield StreamEvent::Text(text);
+            }
+        }
+        yield StreamEvent::Done(state.finish());
+    })
+}
+
+/// Model ids the chat endpoint rejects
+const NON_CHAT_MARKERS: &[&str] = &["audio", "realtime", "tts", "transcribe", "image", "embedding", "search"];
+
+/// Chat models from a `GET /models` response
+///
+/// OpenAI lists every model on the account, so only chat families are kept;
+/// compatible endpoints are taken as-is.
+fn parse_models(kind: ProviderKind, body: &Value) -> Vec<ModelInfo> {
+    body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["id"].as_str())
+        .filter(|id| {
+            kind != ProviderKind::OpenAI
+                || (["gpt-", "o1", "o3", "o4"].iter().any(|prefix| id.starts_with(prefix))
+                    && !NON_CHAT_MARKERS.iter().any(|marker| id.contains(marker)))
+        })
+        .map(|id| ModelInfo::new(kind, id, None))
+        .collect()
+}
+
+/// Accumulates text and usage across completion chunks
+///
+/// Compatible servers don't always report usage, so output is kept for an
+/// estimate.
+struct CompletionState {
+    usage: Usage,
+    reported: bool,
+    estimated_input: u32,
+    output: String,
+}
+
+impl CompletionState {
+    fn new(request: &GenerationRequest) -> Self {
+        Self {
+            usage: Usage {
+                input_tokens: 0,
+                output_tokens: 0,
+                model: request.model.clone(),
+                stop_reason: "end_turn".to_string(),
+            },
+            reported: false,
+            estimated_input: request.estimate_input_tokens(),
+            output: String::new(),
+        }
+    }
+
+    fn apply(&mut self, chunk: &Value) -> Option<String> {
+        if let Some(model) = chunk["model"].as_str() {
+            self.usage.model = model.to_string();
+        }
+        if let Some(reported) = chunk.get("usage").filter(|usage| usage.is_object()) {
+            self.reported = true;
+            self.usage.input_tokens = reported["prompt_tokens"].as_u64().unwrap_or(0) as u32;
+            self.usage.output_tokens = reported["completion_tokens"].as_u64().unwrap_or(0) as u32;
+        }
+
+        let choice = chunk["choices"].get(0)?;
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.usage.stop_reason = normalize_stop_reason(reason);
+        }
+
+        let text = choice["delta"]["content"].as_str().filter(|text| !text.is_empty())?;
+        self.output.push_str(text);
+        Some(text.to_string())
+    }
+
+    fn finish(mut self) -> Usage {
+        if !self.reported {
+            self.usage.input_tokens = self.estimated_input;
+            self.usage.output_tokens = usage::estimate_tokens(&self.output);
+        }
+        self.usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ContentBlock;
+
+    fn request() -> GenerationRequest {
+        GenerationRequest {
+            model: "gpt-4o".to_string(),
+            system: None,
+            messages: vec![ChatMessage::user("Say hi")],
+            max_tokens: 16,
+            temperature: None,
+            tools: Vec::new(),
+            response_format: None,
+            recorder: None,
+        }
+    }
+
+    #[test]
+    fn test_images_become_content_parts() {
+        let message = ChatMessage::with_blocks(
+            "user",
+            vec![ContentBlock::Text { text: "Build this".to_string() }, ContentBlock::image("image/png", "AAAA")],
+        );
+        assert_eq!(
+            message_json(&message),
+            json!({"role": "user", "content": [
+                {"type": "text", "text": "Build this"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+            ]})
+        );
+        assert_eq!(message_json(&ChatMessage::user("Hi")), json!({"role": "user", "content": "Hi"}));
+    }
+
+    #[test]
+    fn test_parse_models_keeps_chat_models() {
+        let body = json!({"data": [{"id": "gpt-4o"}, {"id": "o3-mini"}, {"id": "text-embedding-3-small"}, {"id": "gpt-4o-realtime-preview"}]});
+        let ids: Vec<String> = parse_models(ProviderKind::OpenAI, &body).into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, ["gpt-4o", "o3-mini"]);
+
+        assert_eq!(parse_models(ProviderKind::Custom, &body).len(), 4);
+    }
+
+    #[test]
+    fn test_completion_state_reported_usage() {
+        let mut state = CompletionState::new(&request());
+        assert_eq!(
+            state.apply(&json!({"model": "gpt-4o-2024-08-06", "choices": [{"delta": {"content": "Hi"}, "finish_reason": null}]})),
+            Some("Hi".to_string())
+        );
+        assert_eq!(state.apply(&json!({"choices": [{"delta": {}, "finish_reason": "stop"}]})), None);
+        assert_eq!(
+            state.apply(&json!({"choices": [], "usage": {"prompt_tokens": 9, "completion_tokens": 2}})),
+            None
+        );
+
+        let usage = state.finish();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (9, 2));
+        assert_eq!(usage.model, "gpt-4o-2024-08-06");
+        assert_eq!(usage.stop_reason, "end_turn");
+    }
+
+    #[test]
+    fn test_completion_state_estimates_missing_usage() {
+        let mut state = CompletionState::new(&request());
+        state.apply(&json!({"choices": [{"delta": {"content": "Hello there"}, "finish_reason": "length"}]}));
+
+        let usage = state.finish();
+        assert_eq!(usage.input_tokens, usage::estimate_tokens("Say hi"));
+        assert_eq!(usage.output_tokens, usage::estimate_tokens("Hello there"));
+        assert_eq!(usage.stop_reason, "max_tokens");
+    }
+}
@@ -0,0 +1,278 @@
+// Rate-limit queue - Generations waiting out a provider's 429, by priority
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::sync::OnceLock;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// How often a waiting generation checks its place in the queue
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How urgently a generation should go once a rate limit lifts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Someone is watching the reply stream in
+    #[default]
+    Interactive,
+    /// Utility and scheduled work that can wait
+    Background,
+}
+
+impl Priority {
+    /// Sort key; lower goes first
+    fn rank(self) -> i64 {
+        match self {
+            Priority::Interactive => 0,
+            Priority::Background => 1,
+        }
+    }
+}
+
+/// Reported while a generation waits for its provider's rate limit to lift
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct QueueStatus {
+    pub provider: String,
+    /// Generations for the same provider that go first
+    pub position: usize,
+    pub priority: Priority,
+    /// When the provider's retry window ends (RFC 3339)
+    pub resume_at: String,
+}
+
+/// Where rate-limited generations wait their turn
+///
+/// Waiting generations are rows of `generation_queue`, ordered by priority
+/// and then arrival. A provider stays held until the latest `resume_at` of
+/// its rows, including rows left by a previous run, so restarting the app
+/// doesn't send requests into a window the provider asked us to respect.
+#[derive(Debug, Clone)]
+pub struct RateLimitQueue {
+    pool: SqlitePool,
+    priority: Priority,
+    /// Hold when a 429 doesn't carry `Retry-After`
+    pub default_window: Duration,
+    /// Longest a generation waits before failing with the rate limit
+    pub max_wait: Duration,
+}
+
+/// A generation's place in the queue; leaves it when dropped
+#[derive(Debug)]
+pub struct Ticket {
+    pool: SqlitePool,
+    id: String,
+    provider: String,
+    priority: Priority,
+    /// Last status returned by [`Ticket::changed`]
+    reported: Option<QueueStatus>,
+}
+
+/// Identifies this run's rows; rows of other runs only hold their window
+fn run_id() -> &'static str {
+    static RUN_ID: OnceLock<String> = OnceLock::new();
+    RUN_ID.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+impl RateLimitQueue {
+    pub fn new(pool: SqlitePool, priority: Priority) -> Self {
+        Self {
+            pool,
+            priority,
+            default_window: Duration::from_secs(30),
+            max_wait: Duration::from_secs(10 * 60),
+        }
+    }
+
+    /// Whether `provider` is rate limited or has generations waiting
+    pub async fn is_held(&self, provider: &str) -> bool {
+        let held: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM generation_queue WHERE provider = ? AND (run_id = ? OR resume_at > ?) LIMIT 1",
+        )
+        .bind(provider)
+        .bind(run_id())
+        .bind(now_ms())
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap_or_default();
+        held.is_some()
+    }
+
+    /// Join the queue for `provider`, holding it for `window` when the
+    /// provider just answered 429
+    pub async fn join(&self, provider: &str, window: Option<Duration>) -> Result<Ticket, sqlx::Error> {
+        let now = now_ms();
+
+        // Earlier runs' rows only matter until their window ends
+        sqlx::query("DELETE FROM generation_queue WHERE run_id != ? AND resume_at <= ?")
+            .bind(run_id())
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let resume_at = now + window.map_or(0, |window| window.as_millis() as i64);
+        sqlx::query(
+            "INSERT INTO generation_queue (id, run_id, provider, priority, enqueued_at, resume_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(run_id())
+        .bind(provider)
+        .bind(self.priority.rank())
+        .bind(now)
+        .bind(resume_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(Ticket {
+            pool: self.pool.clone(),
+            id,
+            provider: provider.to_string(),
+            priority: self.priority,
+            reported: None,
+        })
+    }
+}
+
+impl Ticket {
+    /// Current position and the end of the provider's window
+    pub async fn status(&self) -> Result<QueueStatus, sqlx::Error> {
+        let (position, resume_at) = self.position().await?;
+        Ok(self.describe(position, resume_at))
+    }
+
+    /// Generations ahead of this one, and the provider's window end in ms
+    async fn position(&self) -> Result<(usize, i64), sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM generation_queue ahead
+                 WHERE ahead.provider = mine.provider AND ahead.run_id = mine.run_id
+                   AND (ahead.priority < mine.priority
+                        OR (ahead.priority = mine.priority AND ahead.rowid < mine.rowid))) AS position,
+                (SELECT MAX(resume_at) FROM generation_queue held
+                 WHERE held.provider = mine.provider) AS resume_at
+            FROM generation_queue mine
+            WHERE mine.id = ?
+            "#,
+        )
+        .bind(&self.id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let position: i64 = row.get("position");
+        Ok((position as usize, row.get("resume_at")))
+    }
+
+    fn describe(&self, position: usize, resume_at: i64) -> QueueStatus {
+        QueueStatus {
+            provider: self.provider.clone(),
+            position,
+            priority: self.priority,
+            resume_at: chrono::DateTime::from_timestamp_millis(resume_at)
+                .map(|resume_at| resume_at.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Wait for this generation's status to change, or for its turn
+    ///
+    /// Returns the new status while it waits, and `None` once it is first
+    /// in line and the window has passed. A queue that can't be read
+    /// doesn't hold anyone up.
+    pub async fn changed(&mut self) -> Option<QueueStatus> {
+        loop {
+            let (position, resume_at) = self.position().await.ok()?;
+            let remaining = (resume_at - now_ms()).max(0) as u64;
+            if position == 0 && remaining == 0 {
+                return None;
+            }
+
+            let status = self.describe(position, resume_at);
+            if self.reported.as_ref() != Some(&status) {
+                self.reported = Some(status.clone());
+                return Some(status);
+            }
+
+            let delay = match remaining {
+                0 => POLL_INTERVAL,
+                remaining => POLL_INTERVAL.min(Duration::from_millis(remaining)),
+            };
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let pool = self.pool.clone();
+        let id = std::mem::take(&mut self.id);
+
+        runtime.spawn(async move {
+            let left = sqlx::query("DELETE FROM generation_queue WHERE id = ? AND resume_at <= ?")
+                .bind(&id)
+                .bind(now_ms())
+                .execute(&pool)
+                .await;
+            // Still inside the window: keep holding the provider, but out of line
+            if left.is_ok_and(|left| left.rows_affected() == 0) {
+                let _ = sqlx::query("UPDATE generation_queue SET run_id = '' WHERE id = ?")
+                    .bind(&id)
+                    .execute(&pool)
+                    .await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::create_test_pool;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_interactive_goes_before_background() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+
+        let background = RateLimitQueue::new(pool.clone(), Priority::Background);
+        let interactive = RateLimitQueue::new(pool.clone(), Priority::Interactive);
+
+        let first = background.join("anthropic", None).await.unwrap();
+        let second = interactive.join("anthropic", None).await.unwrap();
+        let other = background.join("openai", None).await.unwrap();
+
+        assert_eq!(second.status().await.unwrap().position, 0);
+        assert_eq!(first.status().await.unwrap().position, 1);
+        assert_eq!(other.status().await.unwrap().position, 0);
+        assert!(interactive.is_held("anthropic").await);
+    }
+
+    #[tokio::test]
+    async fn test_waits_out_the_window() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+        let queue = RateLimitQueue::new(pool.clone(), Priority::Interactive);
+
+        let started = std::time::Instant::now();
+        let mut ticket = queue.join("anthropic", Some(Duration::from_millis(200))).await.unwrap();
+        let status = ticket.changed().await.unwrap();
+        assert_eq!(status.position, 0);
+        assert_eq!(ticket.changed().await, None);
+        assert!(started.elapsed() >= Duration::from_millis(150));
+
+        // A window outlives the generation that hit it
+        let mut held = queue.join("openai", Some(Duration::from_secs(60))).await.unwrap();
+        assert!(held.changed().await.is_some());
+        drop(held);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(queue.is_held("openai").await);
+    }
+}
@@ -0,0 +1,192 @@
+// Debug recording of raw provider streams, and their replay
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::frames::Frames;
+use super::{anthropic, ollama, openai, EventStream, GenerationRequest, LlmError, ProviderKind};
+
+/// Settings key that turns recording on
+pub const SETTING_KEY: &str = "llm_record_streams";
+
+/// Writes every raw frame a provider sends for one session to
+/// `recordings/<session>.jsonl` next to the database
+///
+/// Each provider call starts with a line naming the provider and model,
+/// followed by one line per frame, so a session with retries, failover, or
+/// tool rounds replays call by call.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    started: Instant,
+    file: Arc<Mutex<std::fs::File>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    /// Milliseconds since the session started
+    ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    provider: Option<ProviderKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    frame: Option<String>,
+}
+
+impl Recorder {
+    pub fn create(session_id: &str) -> Result<Self, String> {
+        let path = recording_path(session_id)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let file = std::fs::File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+
+        Ok(Self {
+            started: Instant::now(),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    fn write(&self, mut entry: Entry) {
+        entry.ms = self.started.elapsed().as_millis() as u64;
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        line.push('\n');
+        if let Ok(mut file) = self.file.lock() {
+            if let Err(e) = file.write_all(line.as_bytes()) {
+                eprintln!("Failed to record stream: {}", e);
+            }
+        }
+    }
+
+    pub(super) fn frame(&self, frame: &str) {
+        self.write(Entry {
+            ms: 0,
+            provider: None,
+            model: None,
+            frame: Some(frame.to_string()),
+        });
+    }
+}
+
+/// Start recording a provider call, returning the recorder for its frames
+pub(super) fn attempt(request: &GenerationRequest, provider: ProviderKind) -> Option<Recorder> {
+    let recorder = request.recorder.clone()?;
+    recorder.write(Entry {
+        ms: 0,
+        provider: Some(provider),
+        model: Some(request.model.clone()),
+        frame: None,
+    });
+    Some(recorder)
+}
+
+/// Whether recording is turned on in settings
+pub async fn enabled(pool: &SqlitePool) -> bool {
+    sqlx::query_scalar::<_, String>("SELECT value FROM settings WHERE key = ?")
+        .bind(SETTING_KEY)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .is_some_and(|value| value == "true")
+}
+
+fn recording_path(session_id: &str) -> Result<PathBuf, String> {
+    // Session IDs are UUIDs; anything else could escape the directory
+    if session_id.is_empty() || !session_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid session ID: {}", session_id));
+    }
+
+    let db_path = crate::database::get_db_path();
+    let dir = db_path.parent().map(|dir| dir.join("recordings")).unwrap_or_else(|| PathBuf::from("recordings"));
+    Ok(dir.join(format!("{}.jsonl", session_id)))
+}
+
+/// Re-run a recorded session through the same parsers as the live stream
+///
+/// Nothing is sent to a provider; each recorded call ends with its own
+/// `Done`.
+pub async fn replay(session_id: &str) -> Result<EventStream, LlmError> {
+    let path = recording_path(session_id).map_err(LlmError::Config)?;
+    let contents = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|_| LlmError::Config(format!("No recording for session {}", session_id)))?;
+
+    parse_recording(&contents)
+}
+
+fn parse_recording(contents: &str) -> Result<EventStream, LlmError> {
+    let mut calls: Vec<(ProviderKind, String, Vec<String>)> = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: Entry = serde_json::from_str(line)
+            .map_err(|e| LlmError::Config(format!("Corrupt recording: {}", e)))?;
+        match (entry.provider, entry.frame) {
+            (Some(provider), _) => calls.push((provider, entry.model.unwrap_or_default(), Vec::new())),
+            (None, Some(frame)) => match calls.last_mut() {
+                Some((_, _, frames)) => frames.push(frame),
+                None => return Err(LlmError::Config("Corrupt recording: frame before any call".to_string())),
+            },
+            (None, None) => {}
+        }
+    }
+
+    let streams: Vec<EventStream> = calls
+        .into_iter()
+        .map(|(provider, model, frames)| {
+            let frames: Frames = Box::pin(stream::iter(frames.into_iter().map(Ok)));
+            match provider {
+                ProviderKind::Anthropic => anthropic::events(frames, &model),
+                ProviderKind::Ollama => ollama::events(frames, &model),
+                ProviderKind::OpenAI | ProviderKind::Custom => {
+                    openai::events(openai::name(provider), frames, &GenerationRequest::replayed(model))
+                }
+                // The demo provider has no frames to record
+                ProviderKind::Demo => Box::pin(stream::empty()),
+            }
+        })
+        .collect();
+
+    Ok(Box::pin(stream::iter(streams).flatten()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::StreamEvent;
+
+    #[test]
+    fn test_session_ids_stay_in_the_directory() {
+        assert!(recording_path("5f0c8a2e-1b7d-4c53-9d0e-2a6b8f1e4c77").is_ok());
+        assert!(recording_path("../vibing2").is_err());
+        assert!(recording_path("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replay_parses_recorded_frames() {
+        let recording = [
+            r#"{"ms":0,"provider":"ollama","model":"llama3.1"}"#,
+            r#"{"ms":40,"frame":"{\"message\":{\"content\":\"Hel\"},\"done\":false}"}"#,
+            r#"{"ms":80,"frame":"{\"message\":{\"content\":\"lo\"},\"done\":false}"}"#,
+            r#"{"ms":95,"frame":"{\"message\":{\"content\":\"\"},\"done\":true,\"prompt_eval_count\":5,\"eval_count\":2}"}"#,
+        ]
+        .join("\n");
+
+        let events: Vec<StreamEvent> = parse_recording(&recording)
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert_eq!(events[0], StreamEvent::Text("Hel".to_string()));
+        assert_eq!(events[1], StreamEvent::Text("lo".to_string()));
+        match &events[2] {
+            StreamEvent::Done(usage) => assert_eq!((usage.input_tokens, usage.output_tokens), (5, 2)),
+            other => panic!("expected done, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,396 @@
+// Retry with backoff and failover between providers
+use futures::stream::StreamExt;
+use rand::Rng;
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{ErrorCode, EventStream, GenerationRequest, LlmError, Provider, RateLimitQueue, StreamEvent};
+
+/// How generations recover from provider errors
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per provider, including the first
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Provider to switch to once the primary gives up
+    pub failover_provider: Option<String>,
+    /// Model for the failover provider (its default when unset)
+    pub failover_model: Option<String>,
+    /// Where to wait out rate limits instead of retrying into them; 429s
+    /// are retried like other transient errors when unset
+    pub queue: Option<RateLimitQueue>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            failover_provider: None,
+            failover_model: None,
+            queue: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (1-based): exponential, capped,
+    /// with the upper half jittered so clients don't retry in lockstep
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+/// Reported before each retry or failover so the UI can show "retrying…"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetryStatus {
+    /// Attempt about to start (2 for the first retry)
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    /// Provider the next attempt goes to
+    pub provider: String,
+    /// Set when switching to the failover provider
+    pub failover: bool,
+    /// The error that triggered the retry
+    pub reason: String,
+}
+
+impl LlmError {
+    /// Rate limits, overload, server errors, and dropped connections
+    pub fn is_transient(&self) -> bool {
+        match self {
+            LlmError::Status { status, .. } => matches!(status, 408 | 429) || *status >= 500,
+            LlmError::Connection { .. } => true,
+            LlmError::Protocol { .. } | LlmError::Config(_) | LlmError::BudgetExceeded(_) => false,
+        }
+    }
+
+    /// How long the provider asked to be left alone
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            LlmError::Status { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// Stream a generation, retrying transient failures and then failing over
+///
+/// Errors are only retried before any text or tool call has been streamed; once output
+/// has started, a failure ends the stream with that error. Non-transient
+/// errors skip straight to the failover provider, if any.
+///
+/// With a rate-limit queue in `policy`, a 429 puts the generation in the
+/// queue until the provider's window has passed and its turn comes, and
+/// generations for a provider that is already held join the queue before
+/// calling it. Each change of place is reported with a `RateLimited`
+/// event; after the queue's `max_wait`, 429s are retried as usual.
+///
+/// While the app is offline, attempts on remote providers wait for the
+/// connection to return, reported once with an `Offline` event.
+pub fn generate(
+    primary: Arc<dyn Provider>,
+    failover: Option<(Arc<dyn Provider>, String)>,
+    request: GenerationRequest,
+    policy: RetryPolicy,
+) -> EventStream {
+    let mut routes = vec![(primary, request.clone())];
+    if let Some((provider, model)) = failover {
+        routes.push((provider, GenerationRequest { model, ..request }));
+    }
+    let names: Vec<String> = routes.iter().map(|(p, _)| p.kind().as_str().to_string()).collect();
+    let max_attempts = policy.max_attempts.max(1);
+
+    Box::pin(async_stream::try_stream! {
+        'routes: for (index, (provider, request)) in routes.into_iter().enumerate() {
+            let mut attempt = 1;
+            let mut window = None;
+            let queue_deadline = policy.queue.as_ref().map(|queue| tokio::time::Instant::now() + queue.max_wait);
+
+            loop {
+                if let (Some(queue), Some(deadline)) = (&policy.queue, queue_deadline) {
+                    if window.is_some() || queue.is_held(&names[index]).await {
+                        match queue.join(&names[index], window.take()).await {
+                            Ok(mut ticket) => {
+                                while let Ok(Some(status)) = tokio::time::timeout_at(deadline, ticket.changed()).await {
+                                    yield StreamEvent::RateLimited(status);
+                                }
+                            }
+                            Err(e) => eprintln!("Rate-limit queue unavailable: {}", e),
+                        }
+                    }
+                }
+
+                if !provider.kind().is_local() && !crate::network::is_online() {
+                    yield StreamEvent::Offline(names[index].clone());
+                    crate::network::wait_online().await;
+                }
+
+                let mut emitted = false;
+                let error = match provider.stream(request.clone()).await {
+                    Err(e) => e,
+                    Ok(mut events) => {
+                        let mut failure = None;
+                        while let Some(event) = events.next().await {
+                            match event {
+                                Ok(event) => {
+                                    emitted |= matches!(event, StreamEvent::Text(_) | StreamEvent::ToolUse(_));
+                                    yield event;
+                                }
+                                Err(e) => {
+                                    failure = Some(e);
+                                    break;
+                                }
+                            }
+                        }
+                        match failure {
+                            Some(e) => e,
+                            None => break 'routes,
+                        }
+                    }
+                };
+
+                if emitted {
+                    Err::<(), _>(error)?;
+                    break 'routes;
+                }
+
+                if let (Some(queue), Some(deadline)) = (&policy.queue, queue_deadline) {
+                    if error.code() == ErrorCode::RateLimited && tokio::time::Instant::now() < deadline {
+                        window = Some(error.retry_after().unwrap_or(queue.default_window));
+                        continue;
+                    }
+                }
+
+                if error.is_transient() && attempt < max_attempts {
+                    let delay = policy.backoff(attempt);
+                    attempt += 1;
+                    yield StreamEvent::Retry(RetryStatus {
+                        attempt,
+                        max_attempts,
+                        delay_ms: delay.as_millis() as u64,
+                        provider: names[index].clone(),
+                        failover: false,
+                        reason: error.to_string(),
+                    });
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+
+                let Some(next) = names.get(index + 1) else {
+                    Err::<(), _>(error)?;
+                    break 'routes;
+                };
+
+                yield StreamEvent::Retry(RetryStatus {
+                    attempt: 1,
+                    max_attempts,
+                    delay_ms: 0,
+                    provider: next.clone(),
+                    failover: true,
+                    reason: error.to_string(),
+                });
+                break;
+            }
+        }
+    })
+}
+
+/// Load the retry policy from settings, falling back to defaults
+pub async fn load_policy(pool: &SqlitePool) -> RetryPolicy {
+    let rows = sqlx::query(
+        "SELECT key, value FROM settings WHERE key IN ('llm_max_attempts', 'llm_failover_provider', 'llm_failover_model')"
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+
+    let mut policy = RetryPolicy::default();
+    for row in rows {
+        let key: String = row.get("key");
+        let value: String = row.get("value");
+        match key.as_str() {
+            "llm_max_attempts" => policy.max_attempts = value.parse().unwrap_or(policy.max_attempts),
+            "llm_failover_provider" if !value.is_empty() => policy.failover_provider = Some(value),
+            "llm_failover_model" if !value.is_empty() => policy.failover_model = Some(value),
+            _ => {}
+        }
+    }
+
+    policy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatMessage, ProviderKind};
+    use crate::usage::Usage;
+    use futures::future::BoxFuture;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails with `error` for the first `failures` calls, then answers "ok"
+    struct Flaky {
+        kind: ProviderKind,
+        failures: u32,
+        error: LlmError,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl Provider for Flaky {
+        fn kind(&self) -> ProviderKind {
+            self.kind
+        }
+
+        fn default_model(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn stream(&self, request: GenerationRequest) -> BoxFuture<'_, Result<EventStream, LlmError>> {
+            Box::pin(async move {
+                if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                    return Err(self.error.clone());
+                }
+                let usage = Usage {
+                    input_tokens: 1,
+                    output_tokens: 1,
+                    model: request.model,
+                    stop_reason: "end_turn".to_string(),
+                };
+                let events: EventStream = Box::pin(futures::stream::iter(vec![
+                    Ok(StreamEvent::Text("ok".to_string())),
+                    Ok(StreamEvent::Done(usage)),
+                ]));
+                Ok(events)
+            })
+        }
+    }
+
+    fn flaky(kind: ProviderKind, failures: u32, status: u16) -> (Arc<dyn Provider>, Arc<AtomicU32>) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let provider = Flaky {
+            kind,
+            failures,
+            error: LlmError::Status {
+                provider: "test",
+                status,
+                message: "nope".to_string(),
+                retry_after: None,
+            },
+            calls: calls.clone(),
+        };
+        (Arc::new(provider), calls)
+    }
+
+    fn request() -> GenerationRequest {
+        GenerationRequest {
+            model: "primary-model".to_string(),
+            system: None,
+            messages: vec![ChatMessage::user("hi")],
+            max_tokens: 16,
+            temperature: None,
+            tools: Vec::new(),
+            response_format: None,
+            recorder: None,
+        }
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff_is_capped_and_jittered() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..10 {
+            let delay = policy.backoff(attempt);
+            assert!(delay <= policy.max_delay);
+        }
+        let first = policy.backoff(1);
+        assert!(first >= policy.base_delay / 2 && first <= policy.base_delay);
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let status = |status| LlmError::Status { provider: "test", status, message: String::new(), retry_after: None };
+        assert!(status(429).is_transient());
+        assert!(status(529).is_transient());
+        assert!(!status(401).is_transient());
+        assert!(!LlmError::Config("no key".to_string()).is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let (provider, calls) = flaky(ProviderKind::Anthropic, 2, 503);
+        let events: Vec<_> = generate(provider, None, request(), fast_policy()).collect().await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        let retries = events.iter().filter(|e| matches!(e, Ok(StreamEvent::Retry(_)))).count();
+        assert_eq!(retries, 2);
+        assert!(matches!(events.last(), Some(Ok(StreamEvent::Done(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_after_non_transient_error() {
+        let (primary, primary_calls) = flaky(ProviderKind::Anthropic, u32::MAX, 401);
+        let (secondary, _) = flaky(ProviderKind::Ollama, 0, 200);
+        let events: Vec<_> = generate(primary, Some((secondary, "llama3.1".to_string())), request(), fast_policy())
+            .collect()
+            .await;
+
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 1);
+        match &events[0] {
+            Ok(StreamEvent::Retry(status)) => {
+                assert!(status.failover);
+                assert_eq!(status.provider, "ollama");
+            }
+            other => panic!("expected failover status, got {:?}", other),
+        }
+        match events.last() {
+            Some(Ok(StreamEvent::Done(usage))) => assert_eq!(usage.model, "llama3.1"),
+            other => panic!("expected done, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_wait_in_the_queue() {
+        let temp_db = tempfile::NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+        let mut queue = RateLimitQueue::new(pool, crate::llm::Priority::Interactive);
+        queue.default_window = Duration::from_millis(50);
+
+        let (provider, calls) = flaky(ProviderKind::Anthropic, 1, 429);
+        let policy = RetryPolicy { queue: Some(queue), ..fast_policy() };
+        let events: Vec<_> = generate(provider, None, request(), policy).collect().await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        match &events[0] {
+            Ok(StreamEvent::RateLimited(status)) => assert_eq!(status.provider, "anthropic"),
+            other => panic!("expected rate limit status, got {:?}", other),
+        }
+        assert!(!events.iter().any(|e| matches!(e, Ok(StreamEvent::Retry(_)))));
+        assert!(matches!(events.last(), Some(Ok(StreamEvent::Done(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let (provider, calls) = flaky(ProviderKind::OpenAI, u32::MAX, 500);
+        let events: Vec<_> = generate(provider, None, request(), fast_policy()).collect().await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(matches!(events.last(), Some(Err(LlmError::Status { status: 500, .. }))));
+    }
+}
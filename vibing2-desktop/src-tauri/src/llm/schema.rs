@@ -0,0 +1,257 @@
+// JSON output mode - schema-constrained replies and their validation
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::LlmError;
+
+/// Ask for a JSON reply conforming to a schema
+///
+/// Providers with a native JSON mode are constrained to the schema; the
+/// others are instructed to follow it. Either way the finished reply is
+/// checked with [`validate`].
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ResponseFormat {
+    /// Label for the schema, sent to providers that name schemas
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// JSON Schema the reply must conform to
+    #[schema(value_type = Object)]
+    pub schema: Value,
+}
+
+fn default_name() -> String {
+    "response".to_string()
+}
+
+impl ResponseFormat {
+    /// Reject schemas that aren't objects before anything is sent
+    pub fn check(&self) -> Result<(), LlmError> {
+        if !self.schema.is_object() {
+            return Err(LlmError::Config("response_format.schema must be a JSON object".to_string()));
+        }
+        Ok(())
+    }
+
+    /// System prompt text for providers without a native JSON mode
+    pub fn instruction(&self) -> String {
+        format!(
+            "Respond only with a JSON value that conforms to this JSON Schema, with no other text:\n{}",
+            self.schema
+        )
+    }
+}
+
+/// Where and how a reply broke its schema
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SchemaError {
+    /// JSON Pointer to the offending value; empty for the whole reply
+    pub path: String,
+    pub message: String,
+}
+
+impl SchemaError {
+    fn new(path: &str, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Parse a reply as JSON and check it against `format`
+///
+/// A Markdown code fence around the JSON is tolerated, since models add one
+/// even when told not to.
+pub fn validate(format: &ResponseFormat, output: &str) -> Result<Value, Vec<SchemaError>> {
+    let value: Value = serde_json::from_str(strip_fence(output))
+        .map_err(|e| vec![SchemaError::new("", format!("Reply isn't valid JSON: {}", e))])?;
+
+    let mut errors = Vec::new();
+    check_value(&format.schema, &value, "", &mut errors);
+    if errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(errors)
+    }
+}
+
+fn strip_fence(output: &str) -> &str {
+    let trimmed = output.trim();
+    let Some(body) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    // Skip the language tag on the opening line
+    let body = body.split_once('\n').map_or("", |(_, rest)| rest);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// Check `value` against the commonly used subset of JSON Schema: `type`,
+/// `enum`, `const`, `properties`, `required`, `additionalProperties`,
+/// `items`, `anyOf`, and the length and range bounds
+fn check_value(schema: &Value, value: &Value, path: &str, errors: &mut Vec<SchemaError>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+            errors.push(SchemaError::new(path, format!("Expected {}, got {}", types.join(" or "), type_name(value))));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            errors.push(SchemaError::new(path, format!("{} isn't one of the allowed values", value)));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(SchemaError::new(path, format!("Expected {}", expected)));
+        }
+    }
+
+    if let Some(options) = schema.get("anyOf").and_then(Value::as_array) {
+        let matches = options.iter().any(|option| {
+            let mut option_errors = Vec::new();
+            check_value(option, value, path, &mut option_errors);
+            option_errors.is_empty()
+        });
+        if !matches {
+            errors.push(SchemaError::new(path, "Doesn't match any of the allowed schemas"));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            for name in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+                if let Some(name) = name.as_str().filter(|name| !object.contains_key(*name)) {
+                    errors.push(SchemaError::new(path, format!("Missing required property {}", name)));
+                }
+            }
+
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, item) in object {
+                let item_path = format!("{}/{}", path, name.replace('~', "~0").replace('/', "~1"));
+                match (properties.and_then(|p| p.get(name)), schema.get("additionalProperties")) {
+                    (Some(item_schema), _) => check_value(item_schema, item, &item_path, errors),
+                    (None, Some(Value::Bool(false))) => {
+                        errors.push(SchemaError::new(&item_path, "Property isn't allowed"));
+                    }
+                    (None, Some(extra)) => check_value(extra, item, &item_path, errors),
+                    (None, None) => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            check_bound(schema, "minItems", "maxItems", items.len(), "items", path, errors);
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    check_value(item_schema, item, &format!("{}/{}", path, index), errors);
+                }
+            }
+        }
+        Value::String(text) => {
+            check_bound(schema, "minLength", "maxLength", text.chars().count(), "characters", path, errors);
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64).filter(|min| number < *min) {
+                errors.push(SchemaError::new(path, format!("Must be at least {}", minimum)));
+            }
+            if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64).filter(|max| number > *max) {
+                errors.push(SchemaError::new(path, format!("Must be at most {}", maximum)));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_bound(
+    schema: &serde_json::Map<String, Value>,
+    min_key: &str,
+    max_key: &str,
+    len: usize,
+    unit: &str,
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    let len = len as u64;
+    if let Some(min) = schema.get(min_key).and_then(Value::as_u64).filter(|min| len < *min) {
+        errors.push(SchemaError::new(path, format!("Needs at least {} {}", min, unit)));
+    }
+    if let Some(max) = schema.get(max_key).and_then(Value::as_u64).filter(|max| len > *max) {
+        errors.push(SchemaError::new(path, format!("Allows at most {} {}", max, unit)));
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn format() -> ResponseFormat {
+        ResponseFormat {
+            name: default_name(),
+            schema: json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string", "minLength": 1},
+                    "pages": {"type": "array", "items": {"type": "string"}, "maxItems": 3},
+                    "theme": {"enum": ["light", "dark"]},
+                    "version": {"type": "integer", "minimum": 1}
+                },
+                "required": ["title", "pages"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    #[test]
+    fn test_valid_reply() {
+        let reply = "```json\n{\"title\": \"Shop\", \"pages\": [\"home\"], \"version\": 2}\n```";
+        let value = validate(&format(), reply).unwrap();
+        assert_eq!(value["title"], "Shop");
+    }
+
+    #[test]
+    fn test_invalid_reply() {
+        let reply = r#"{"pages": ["a", "b", "c", 4], "theme": "neon", "version": 0.5, "extra": true}"#;
+        let errors = validate(&format(), reply).unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, ["", "/extra", "/pages", "/pages/3", "/theme", "/version"]);
+        assert!(errors[0].message.contains("title"));
+    }
+
+    #[test]
+    fn test_non_json_reply() {
+        let errors = validate(&format(), "Sure! Here it is.").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.starts_with("Reply isn't valid JSON"));
+    }
+}
@@ -0,0 +1,350 @@
+// Tool use - definitions, a handler registry, and the call/result loop
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::{generate, ChatMessage, ContentBlock, EventStream, GenerationRequest, LlmError, Provider, RetryPolicy, StreamEvent};
+use crate::tool_policy::{ToolGate, ToolPermission};
+use crate::usage::Usage;
+
+/// Model round trips allowed in one generation before tool calls stop
+/// being honored
+const MAX_TOOL_ROUNDS: usize = 8;
+
+/// A tool offered to the model, in Anthropic's wire format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema for the tool's input
+    pub input_schema: serde_json::Value,
+}
+
+/// A tool invocation requested by the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+}
+
+/// The outcome of a tool call, sent back to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolResult {
+    pub tool_use_id: String,
+    pub name: String,
+    pub content: String,
+    pub is_error: bool,
+}
+
+/// A local handler the model can call
+pub trait Tool: Send + Sync {
+    fn definition(&self) -> ToolDefinition;
+
+    /// Run the tool; errors are reported to the model, not the user
+    fn call(&self, input: serde_json::Value) -> BoxFuture<'_, Result<String, String>>;
+
+    /// Permission an agent needs to call the tool; `None` if any may
+    fn permission(&self) -> Option<ToolPermission> {
+        None
+    }
+
+    /// What a call will do, shown when the user is asked to approve it
+    fn describe_call(&self, _input: &serde_json::Value) -> Option<String> {
+        None
+    }
+}
+
+/// Tools available to a generation, looked up by name
+///
+/// With a gate, each call is checked against the running agent's
+/// permissions before it runs.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn Tool>>,
+    gate: Option<ToolGate>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.tools.push(Box::new(tool));
+    }
+
+    pub fn set_gate(&mut self, gate: ToolGate) {
+        self.gate = Some(gate);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|tool| tool.definition()).collect()
+    }
+
+    /// Run a call, turning unknown tools and handler errors into error results
+    pub async fn call(&self, call: &ToolCall) -> ToolResult {
+        let tool = self.tools.iter().find(|tool| tool.definition().name == call.name);
+
+        let outcome = match tool {
+            Some(tool) => match (&self.gate, tool.permission()) {
+                (Some(gate), Some(permission)) => match gate
+                    .check(&call.name, permission, tool.describe_call(&call.input).as_deref())
+                    .await
+                {
+                    Ok(()) => tool.call(call.input.clone()).await,
+                    Err(e) => Err(e),
+                },
+                _ => tool.call(call.input.clone()).await,
+            },
+            None => Err(format!("Unknown tool: {}", call.name)),
+        };
+
+        let (content, is_error) = match outcome {
+            Ok(content) => (content, false),
+            Err(error) => (error, true),
+        };
+
+        ToolResult {
+            tool_use_id: call.id.clone(),
+            name: call.name.clone(),
+            content,
+            is_error,
+        }
+    }
+}
+
+/// [`generate`], answering tool calls until the model stops asking
+///
+/// After a round that ends with `tool_use`, every call is run through
+/// `tools`, the assistant turn and the results are appended to the
+/// conversation, and the model is asked to continue. Text, calls, and
+/// results are all streamed; the final `Done` carries usage summed over
+/// every round.
+pub fn generate_with_tools(
+    primary: Arc<dyn Provider>,
+    failover: Option<(Arc<dyn Provider>, String)>,
+    mut request: GenerationRequest,
+    policy: RetryPolicy,
+    tools: Arc<ToolRegistry>,
+) -> EventStream {
+    request.tools = tools.definitions();
+
+    Box::pin(async_stream::try_stream! {
+        let mut total: Option<Usage> = None;
+
+        for round in 1..=MAX_TOOL_ROUNDS {
+            let mut events = generate(primary.clone(), failover.clone(), request.clone(), policy.clone());
+            let mut text = String::new();
+            let mut calls = Vec::new();
+            let mut done = None;
+
+            while let Some(event) = events.next().await {
+                match event? {
+                    StreamEvent::Text(chunk) => {
+                        text.push_str(&chunk);
+                        yield StreamEvent::Text(chunk);
+                    }
+                    StreamEvent::ToolUse(call) => {
+                        calls.push(call.clone());
+                        yield StreamEvent::ToolUse(call);
+                    }
+                    StreamEvent::Done(usage) => done = Some(usage),
+                    other => yield other,
+                }
+            }
+
+            let Some(usage) = done else {
+                Err::<(), _>(LlmError::Config("Generation ended without a result".to_string()))?;
+                break;
+            };
+            total = Some(match total.take() {
+                Some(total) => Usage {
+                    input_tokens: total.input_tokens + usage.input_tokens,
+                    output_tokens: total.output_tokens + usage.output_tokens,
+                    ..usage
+                },
+                None => usage,
+            });
+
+            let wants_tools = total.as_ref().is_some_and(|usage| usage.stop_reason == "tool_use");
+            if calls.is_empty() || !wants_tools || round == MAX_TOOL_ROUNDS {
+                break;
+            }
+
+            let mut assistant = Vec::new();
+            if !text.is_empty() {
+                assistant.push(ContentBlock::Text { text });
+            }
+            let mut results = Vec::new();
+
+            for call in calls {
+                let result = tools.call(&call).await;
+                yield StreamEvent::ToolResult(result.clone());
+
+                assistant.push(ContentBlock::ToolUse {
+                    id: call.id,
+                    name: call.name,
+                    input: call.input,
+                });
+                results.push(ContentBlock::ToolResult {
+                    tool_use_id: result.tool_use_id,
+                    content: result.content,
+                    is_error: result.is_error,
+                });
+            }
+
+            request.messages.push(ChatMessage::with_blocks("assistant", assistant));
+            request.messages.push(ChatMessage::with_blocks("user", results));
+        }
+
+        if let Some(total) = total {
+            yield StreamEvent::Done(total);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ProviderKind;
+    use std::sync::Mutex;
+
+    /// Calls `echo` on the first request, then answers with the tool result
+    struct Scripted {
+        requests: Mutex<Vec<GenerationRequest>>,
+    }
+
+    impl Provider for Scripted {
+        fn kind(&self) -> ProviderKind {
+            ProviderKind::Anthropic
+        }
+
+        fn default_model(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn stream(&self, request: GenerationRequest) -> BoxFuture<'_, Result<EventStream, LlmError>> {
+            Box::pin(async move {
+                let last = request.messages.last().cloned();
+                self.requests.lock().unwrap().push(request);
+
+                let usage = |stop_reason: &str| Usage {
+                    input_tokens: 10,
+                    output_tokens: 5,
+                    model: "scripted".to_string(),
+                    stop_reason: stop_reason.to_string(),
+                };
+
+                let events = match last.and_then(|message| message.blocks.into_iter().next()) {
+                    Some(ContentBlock::ToolResult { content, .. }) => vec![
+                        Ok(StreamEvent::Text(format!("Tool said {}", content))),
+                        Ok(StreamEvent::Done(usage("end_turn"))),
+                    ],
+                    _ => vec![
+                        Ok(StreamEvent::Text("Checking. ".to_string())),
+                        Ok(StreamEvent::ToolUse(ToolCall {
+                            id: "toolu_01".to_string(),
+                            name: "echo".to_string(),
+                            input: serde_json::json!({"value": "hi"}),
+                        })),
+                        Ok(StreamEvent::Done(usage("tool_use"))),
+                    ],
+                };
+
+                let events: EventStream = Box::pin(futures::stream::iter(events));
+                Ok(events)
+            })
+        }
+    }
+
+    struct Echo;
+
+    impl Tool for Echo {
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: "echo".to_string(),
+                description: "Echo the input value".to_string(),
+                input_schema: serde_json::json!({"type": "object", "properties": {"value": {"type": "string"}}}),
+            }
+        }
+
+        fn call(&self, input: serde_json::Value) -> BoxFuture<'_, Result<String, String>> {
+            Box::pin(async move {
+                input["value"]
+                    .as_str()
+                    .map(String::from)
+                    .ok_or_else(|| "value is required".to_string())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_round_trip() {
+        let provider = Arc::new(Scripted { requests: Mutex::new(Vec::new()) });
+        let mut registry = ToolRegistry::new();
+        registry.register(Echo);
+
+        let request = GenerationRequest {
+            model: "scripted".to_string(),
+            system: None,
+            messages: vec![ChatMessage::user("Use the tool")],
+            max_tokens: 64,
+            temperature: None,
+            tools: Vec::new(),
+            response_format: None,
+            recorder: None,
+        };
+
+        let events: Vec<StreamEvent> = generate_with_tools(
+            provider.clone(),
+            None,
+            request,
+            RetryPolicy::default(),
+            Arc::new(registry),
+        )
+        .map(|event| event.unwrap())
+        .collect()
+        .await;
+
+        assert!(events.contains(&StreamEvent::ToolResult(ToolResult {
+            tool_use_id: "toolu_01".to_string(),
+            name: "echo".to_string(),
+            content: "hi".to_string(),
+            is_error: false,
+        })));
+        assert!(events.contains(&StreamEvent::Text("Tool said hi".to_string())));
+        match events.last() {
+            Some(StreamEvent::Done(usage)) => {
+                assert_eq!((usage.input_tokens, usage.output_tokens), (20, 10));
+                assert_eq!(usage.stop_reason, "end_turn");
+            }
+            other => panic!("expected done, got {:?}", other),
+        }
+
+        // The continuation carried the assistant turn and the tool result
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].tools.len(), 1);
+        assert_eq!(requests[1].messages.len(), 3);
+        assert_eq!(requests[1].messages[1].role, "assistant");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tool_is_an_error_result() {
+        let registry = ToolRegistry::new();
+        let result = registry
+            .call(&ToolCall {
+                id: "toolu_02".to_string(),
+                name: "rm_rf".to_string(),
+                input: serde_json::json!({}),
+            })
+            .await;
+        assert!(result.is_error);
+    }
+}
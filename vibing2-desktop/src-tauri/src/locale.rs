@@ -0,0 +1,93 @@
+//! UI language
+//!
+//! The `locale` setting holds the language tag the UI is shown in (`en`,
+//! `es`, `pt-BR`). Text shipped in several languages, like the built-in
+//! agents' names and descriptions, is picked with [`pick`]: the exact locale
+//! first, then its language alone (`pt` for `pt-BR`), then the text as
+//! written.
+
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+pub const SETTING_KEY: &str = "locale";
+
+/// Locale used when the setting is unset
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// A language tag in canonical case (`pt_br` is `pt-BR`), or None if it
+/// isn't one
+pub fn normalize(tag: &str) -> Option<String> {
+    let mut subtags = tag.trim().split(['-', '_']);
+    let language = subtags.next()?;
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let mut normalized = language.to_ascii_lowercase();
+    for subtag in subtags {
+        if subtag.is_empty() || subtag.len() > 8 || !subtag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        normalized.push('-');
+        match subtag.len() {
+            // Region, e.g. BR
+            2 => normalized.push_str(&subtag.to_ascii_uppercase()),
+            // Script, e.g. Hant
+            4 if subtag.chars().all(|c| c.is_ascii_alphabetic()) => {
+                normalized.push_str(&subtag[..1].to_ascii_uppercase());
+                normalized.push_str(&subtag[1..].to_ascii_lowercase());
+            }
+            _ => normalized.push_str(&subtag.to_ascii_lowercase()),
+        }
+    }
+    Some(normalized)
+}
+
+/// The locale the UI is set to
+pub async fn current(pool: &SqlitePool) -> String {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(SETTING_KEY)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value
+        .as_deref()
+        .and_then(normalize)
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+}
+
+/// Keys to look up for `locale`, most specific first
+pub fn fallbacks(locale: &str) -> Vec<&str> {
+    let mut keys = vec![locale];
+    while let Some((shorter, _)) = keys.last().and_then(|key| key.rsplit_once('-')) {
+        keys.push(shorter);
+    }
+    keys
+}
+
+/// The variant for `locale`, if there is one
+pub fn pick<'a, T>(variants: &'a BTreeMap<String, T>, locale: &str) -> Option<&'a T> {
+    fallbacks(locale).into_iter().find_map(|key| variants.get(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_falls_back_to_language() {
+        assert_eq!(normalize("pt_br").as_deref(), Some("pt-BR"));
+        assert_eq!(normalize("ZH-hant-tw").as_deref(), Some("zh-Hant-TW"));
+        assert_eq!(normalize(" es ").as_deref(), Some("es"));
+        assert!(normalize("").is_none());
+        assert!(normalize("english").is_none());
+        assert!(normalize("en-").is_none());
+
+        let variants = BTreeMap::from([("pt".to_string(), "Olá"), ("zh-Hant".to_string(), "你好")]);
+        assert_eq!(pick(&variants, "pt-BR"), Some(&"Olá"));
+        assert_eq!(pick(&variants, "zh-Hant-TW"), Some(&"你好"));
+        assert_eq!(pick(&variants, "zh"), None);
+        assert_eq!(pick(&variants, "en"), None);
+    }
+}
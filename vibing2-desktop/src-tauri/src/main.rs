@@ -1,37 +1,247 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+pub mod agent_files;
+pub mod agent_history;
+pub mod agent_icons;
+pub mod agent_lint;
+pub mod agent_manifest;
+pub mod agent_registry;
+pub mod agent_tools;
+pub mod agents;
+pub mod attachments;
 pub mod auth;
+pub mod autostart;
+pub mod biometric;
+pub mod budgets;
 pub mod commands;
+pub mod comparison;
 pub mod database;
-// pub mod server;
+pub mod deep_link;
+pub mod file_sync;
+pub mod generation;
+pub mod git;
+pub mod llm;
+pub mod locale;
+pub mod network;
+pub mod oauth;
+pub mod pairing;
+pub mod passwords;
+pub mod post_process;
+pub mod power;
+pub mod project_agents;
+pub mod project_templates;
+pub mod proxy;
+pub mod quick_capture;
+pub mod sandbox;
+pub mod scaffold;
+pub mod search;
+pub mod schedules;
+pub mod sessions;
+pub mod sharing;
+pub mod snapshots;
+pub mod taskbar;
+pub mod teams;
+pub mod telemetry;
+pub mod templates;
+pub mod tool_policy;
+pub mod server;
+pub mod totp;
 pub mod tray;
-// pub mod updater;
+pub mod updater;
+pub mod usage;
+pub mod vault;
 
 use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
 
 fn main() {
+    // Structured logs for the embedded server; override with RUST_LOG.
+    // OTLP export is enabled later from settings.
+    telemetry::init_subscriber();
+
     tauri::Builder::default()
+        // Must be registered first: a second launch focuses this window and
+        // exits before opening the database or starting another server.
+        // Deep links passed to the second launch are forwarded here.
+        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+        }))
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        // .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|app, _shortcut, event| {
+                    if event.state() == ShortcutState::Pressed {
+                        quick_capture::open(app);
+                    }
+                })
+                .build(),
+        )
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            Some(vec![autostart::LAUNCH_ARG]),
+        ))
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(std::sync::Arc::new(auth::AuthCache::new()))
+        .manage(std::sync::Arc::new(server::ServerManager::new()))
+        .manage(std::sync::Arc::new(tray::UnreadCounter::default()))
+        // Lets the webview reach a server on the local (socket/pipe) transport
+        .register_asynchronous_uri_scheme_protocol(server::transport::PROXY_SCHEME, |_ctx, request, responder| {
+            tauri::async_runtime::spawn(async move {
+                responder.respond(server::transport::proxy_request(request).await);
+            });
+        })
         .setup(|app| {
-            // Initialize database asynchronously using Tauri's runtime
-            tauri::async_runtime::spawn(async {
+            // Reload the webview's static assets after frontend rebuilds
+            #[cfg(debug_assertions)]
+            {
+                use tauri::Emitter;
+
+                let handle = app.handle().clone();
+                app.state::<std::sync::Arc<server::ServerManager>>().on_static_reload(move || {
+                    let _ = handle.emit("static-reloaded", ());
+                });
+            }
+
+            // Initialize database asynchronously using Tauri's runtime,
+            // then start the embedded server if enabled in settings
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
                 match database::init_database().await {
                     Ok(_) => println!("✅ Database initialized successfully"),
-                    Err(e) => eprintln!("Failed to initialize database: {}", e),
+                    Err(e) => {
+                        eprintln!("Failed to initialize database: {}", e);
+                        return;
+                    }
+                }
+
+                // The tray may have queried the database before it was ready
+                if let Err(e) = tray::update_tray_menu(&handle) {
+                    eprintln!("Failed to update tray menu: {}", e);
+                }
+
+                if let Ok(pool) = database::get_pool().await {
+                    // Start in the tray when launched at login
+                    autostart::apply_on_launch(&handle, pool.as_ref()).await;
+
+                    let config = telemetry::load_config(pool.as_ref()).await;
+                    if config.enabled {
+                        if let Err(e) = telemetry::apply(&config) {
+                            eprintln!("{}", e);
+                        }
+                    }
+
+                    // Route outbound requests through the configured proxy
+                    if let Err(e) = proxy::apply(&proxy::load_config(pool.as_ref()).await) {
+                        eprintln!("{}", e);
+                    }
+
+                    // Pick up agent definitions dropped into the agents directory
+                    for import in agent_files::import_dir(pool.as_ref(), &agent_files::agents_dir()).await {
+                        match (import.agent, import.error) {
+                            (Some(agent), _) => println!("✅ Imported agent {} from {}", agent.id, import.path),
+                            (_, Some(e)) => eprintln!("Failed to import {}: {}", import.path, e),
+                            _ => {}
+                        }
+                    }
+
+                    // Sync edits made in the folders projects were written to
+                    file_sync::watch_all(&handle, pool.as_ref()).await;
+
+                    // Find a local Ollama so its models are ready for offline use
+                    tauri::async_runtime::spawn(async move {
+                        let ollama = llm::detect_ollama(pool.as_ref()).await;
+                        if ollama.running {
+                            println!("✅ Ollama detected at {} ({} models)", ollama.base_url, ollama.models.len());
+                        }
+                    });
+                }
+
+                if commands::server_autostart_enabled().await {
+                    let server = handle.state::<std::sync::Arc<server::ServerManager>>().inner().clone();
+                    match commands::start_embedded_server(&handle, &server).await {
+                        Ok(info) => println!("✅ Embedded server running at {}", info.url),
+                        Err(e) => eprintln!("{}", e),
+                    }
                 }
             });
 
+            // Periodically revalidate stored API keys
+            auth::spawn_revalidation_task(app.handle().clone());
+
+            // Watch connectivity; offline, only local models are offered
+            network::spawn_monitor(app.handle().clone());
+
+            // Lock encrypted credentials after inactivity
+            vault::spawn_idle_lock_task(app.handle().clone());
+
+            // Hold background tasks while asleep or saving power
+            power::spawn_monitor(app.handle().clone());
+
+            // Check for and download updates in the background
+            let updater = tauri::async_runtime::block_on(updater::init_updater(app.handle().clone()))?;
+            app.manage(updater);
+
+            // Prune expired server sessions in the background
+            sessions::spawn_prune_task();
+
+            // Check the remote agent registry, if one is configured
+            agent_registry::spawn_sync_task();
+
+            // Run scheduled agent tasks as they come due
+            schedules::spawn_task(app.handle().clone());
+
+            // Ask before agents use tools they weren't given
+            let handle = app.handle().clone();
+            tool_policy::set_prompt(move |escalation| {
+                let handle = handle.clone();
+                Box::pin(async move { tool_policy::ask_with_dialog(&handle, escalation).await })
+            });
+
             // Initialize system tray
             if let Err(e) = tray::create_tray(app.handle()) {
                 eprintln!("Failed to initialize system tray: {}", e);
             } else {
+                tray::listen_auth_events(app.handle());
+                tray::listen_generation_events(app.handle());
                 println!("✅ System tray initialized successfully");
             }
 
+            // Show generations and update downloads on the taskbar/dock
+            taskbar::listen(app.handle());
+
+            // Open the quick-capture window from anywhere
+            if let Err(e) = app.global_shortcut().register(quick_capture::SHORTCUT) {
+                eprintln!("Failed to register quick capture shortcut: {}", e);
+            }
+
+            // Route vibing2:// deep links (projects, new prompts, OAuth callback)
+            #[cfg(any(target_os = "linux", target_os = "windows"))]
+            if let Err(e) = app.deep_link().register(deep_link::SCHEME) {
+                eprintln!("Failed to register deep link scheme: {}", e);
+            }
+
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                for url in urls {
+                    deep_link::open_on_launch(app.handle(), url.as_str());
+                }
+            }
+
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    deep_link::open(&handle, url.as_str());
+                }
+            });
+
             #[cfg(debug_assertions)]
             {
                 let window = app.get_webview_window("main").unwrap();
@@ -39,20 +249,155 @@ fn main() {
             }
             Ok(())
         })
+        // Notifications count as read once the user looks at the window
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Focused(true) = event {
+                if window.label() == "main" {
+                    tray::clear_unread(window.app_handle());
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             commands::greet,
             commands::save_project,
             commands::load_project,
             commands::list_projects,
             commands::delete_project,
+            commands::attach_project_agent,
+            commands::detach_project_agent,
+            commands::share_project,
+            commands::unshare_project,
+            commands::list_project_shares,
+            commands::run_project_command,
+            commands::materialize_project,
+            commands::list_project_snapshots,
+            commands::diff_project_versions,
+            commands::search,
+            commands::git_init_project,
+            commands::git_commit_project,
+            commands::git_status,
+            commands::git_diff,
             commands::save_settings,
             commands::load_settings,
             commands::check_claude_auth,
             commands::save_api_key,
             commands::get_credentials,
+            commands::reveal_api_key,
+            commands::set_local_password,
+            commands::get_account_info,
+            commands::save_provider_key,
+            commands::remove_provider_key,
+            commands::list_providers,
+            commands::start_oauth_signin,
+            commands::complete_oauth_signin,
+            commands::oauth_sign_out,
+            commands::get_lock_status,
+            commands::enable_credential_lock,
+            commands::disable_credential_lock,
+            commands::unlock_credentials,
+            commands::lock_credentials,
+            commands::list_sessions,
+            commands::revoke_session,
+            commands::revoke_all_sessions,
+            commands::create_pairing_code,
+            commands::list_devices,
+            commands::revoke_device,
+            commands::list_users,
+            commands::set_user_role,
+            commands::start_server,
+            commands::stop_server,
+            commands::restart_server,
+            commands::get_server_info,
+            commands::get_server_limits,
+            commands::get_lan_urls,
+            commands::start_generation,
+            commands::compare_agents,
+            commands::pick_comparison_reply,
+            commands::complete_generation,
+            commands::test_agent,
+            commands::replay_stream,
+            commands::get_context_strategy,
+            commands::set_context_strategy,
+            commands::get_post_processors,
+            commands::set_post_processors,
+            commands::get_budget_status,
+            commands::set_budget,
+            commands::reset_budget,
+            commands::estimate_cost,
+            commands::list_models,
+            commands::detect_ollama,
+            commands::list_agents,
+            commands::get_agent,
+            commands::create_agent,
+            commands::validate_agent,
+            commands::update_agent,
+            commands::delete_agent,
+            commands::toggle_agent_favorite,
+            commands::set_agent_icon,
+            commands::remove_agent_icon,
+            commands::import_agent,
+            commands::import_agent_dir,
+            commands::export_agent,
+            commands::sync_agent_manifest,
+            commands::get_agent_registry,
+            commands::check_agent_registry,
+            commands::approve_registry_agent,
+            commands::reject_registry_agent,
+            commands::list_agent_teams,
+            commands::create_agent_team,
+            commands::delete_agent_team,
+            commands::apply_agent_team,
+            commands::get_agent_history,
+            commands::create_agent_schedule,
+            commands::list_agent_schedules,
+            commands::cancel_agent_schedule,
+            commands::list_prompt_templates,
+            commands::create_prompt_template,
+            commands::delete_prompt_template,
+            commands::upload_attachment,
+            commands::delete_attachment,
+            commands::render_prompt_template,
+            commands::list_templates,
+            commands::create_project_template,
+            commands::delete_project_template,
+            commands::create_project_from_template,
+            commands::get_server_config,
+            commands::set_server_config,
+            commands::update_server_limits,
             commands::update_tray_menu,
             commands::set_tray_badge,
+            commands::get_unread_count,
+            commands::set_tray_status,
+            commands::open_quick_capture,
+            commands::submit_quick_capture,
+            commands::take_deep_link,
+            commands::get_autostart,
+            commands::set_autostart,
+            commands::get_power_status,
+            updater::check_for_updates,
+            updater::install_update,
+            updater::download_update,
+            updater::is_update_available,
+            updater::get_app_version,
+            updater::get_update_config,
+            updater::set_update_config,
+            updater::get_update_status,
+            updater::pause_update_download,
+            updater::resume_update_download,
+            updater::get_release_history,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Drain the embedded server so the port is released on exit
+            if let tauri::RunEvent::Exit = event {
+                let server = app.state::<std::sync::Arc<server::ServerManager>>().inner().clone();
+                tauri::async_runtime::block_on(async move {
+                    server.stop().await;
+                    // Install an update deferred until quit
+                    updater::install_pending(app).await;
+                });
+                telemetry::shutdown();
+            }
+        });
 }
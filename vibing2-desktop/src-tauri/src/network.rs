@@ -0,0 +1,106 @@
+// Network module - Detects losing and regaining internet access
+//
+// The monitor probes a few provider endpoints through the proxy; any HTTP
+// answer counts as online. While offline:
+// - `network:offline` is emitted (and `network:online` once it's back)
+// - the model catalog lists only local models (Ollama, demo)
+// - generations for remote providers wait for the connection instead of
+//   failing (see `llm::generate`)
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+
+/// Endpoints probed for connectivity; reachable if any answers
+const PROBE_URLS: [&str; 2] = ["https://api.anthropic.com", "https://api.openai.com"];
+
+/// How long a probe waits for an answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often connectivity is checked while online
+const ONLINE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often connectivity is checked while offline, to resume quickly
+const OFFLINE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Payload of the `network:*` events
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct NetworkStatus {
+    pub online: bool,
+}
+
+fn online_sender() -> &'static watch::Sender<bool> {
+    static ONLINE: OnceLock<watch::Sender<bool>> = OnceLock::new();
+    ONLINE.get_or_init(|| watch::channel(true).0)
+}
+
+/// Whether the internet was reachable at the last check; true until the
+/// first check fails
+pub fn is_online() -> bool {
+    *online_sender().borrow()
+}
+
+/// Completes once the internet is reachable
+pub async fn wait_online() {
+    let mut online = online_sender().subscribe();
+    let _ = online.wait_for(|online| *online).await;
+}
+
+/// Record the result of a check; true if it changed the state
+fn set_online(online: bool) -> bool {
+    online_sender().send_if_modified(|current| {
+        let changed = *current != online;
+        *current = online;
+        changed
+    })
+}
+
+/// Whether any probe endpoint answers
+async fn probe() -> bool {
+    let client = crate::proxy::client();
+    let probes = PROBE_URLS
+        .iter()
+        .map(|url| client.head(*url).timeout(PROBE_TIMEOUT).send());
+    futures::future::join_all(probes).await.iter().any(|response| response.is_ok())
+}
+
+/// Spawn a background task that watches connectivity
+///
+/// Emits `network:offline` and `network:online` as it changes.
+pub fn spawn_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let online = probe().await;
+            if set_online(online) {
+                // Remote providers drop out of (or come back to) the catalog
+                crate::llm::invalidate_models();
+
+                let event = if online { "network:online" } else { "network:offline" };
+                println!("🌐 {}", if online { "Back online" } else { "Offline: using local models" });
+                let _ = app.emit(event, NetworkStatus { online });
+            }
+
+            tokio::time::sleep(if online { ONLINE_INTERVAL } else { OFFLINE_INTERVAL }).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_online_state() {
+        assert!(is_online());
+        assert!(!set_online(true));
+
+        // Other tests may generate meanwhile, so go back online right away
+        assert!(set_online(false));
+        assert!(!is_online());
+        let waiting = tokio::spawn(wait_online());
+        assert!(set_online(true));
+        waiting.await.unwrap();
+        assert!(is_online());
+    }
+}
@@ -0,0 +1,316 @@
+//! Browser-based OAuth sign-in for Anthropic accounts
+//!
+//! Lets Claude subscription users authorize the app in their browser instead
+//! of creating and pasting a console API key. Uses the authorization code
+//! flow with PKCE; the browser redirects back into the app through the
+//! `vibing2://oauth/callback` deep link.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+const AUTHORIZE_URL: &str = "https://claude.ai/oauth/authorize";
+const TOKEN_URL: &str = "https://console.anthropic.com/v1/oauth/token";
+const REDIRECT_URI: &str = "vibing2://oauth/callback";
+const SCOPES: &str = "org:create_api_key user:profile user:inference";
+
+/// Refresh tokens this many seconds before they actually expire
+const REFRESH_MARGIN_SECS: i64 = 300;
+
+/// Pending authorization requests: state -> PKCE code verifier
+static PENDING: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn pending() -> &'static Mutex<HashMap<String, String>> {
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn client_id() -> &'static str {
+    option_env!("VIBING2_OAUTH_CLIENT_ID").unwrap_or("vibing2-desktop")
+}
+
+/// OAuth token set as stored in the database
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<i64>,
+    pub scope: Option<String>,
+}
+
+impl OAuthTokens {
+    /// Whether the access token is expired or about to expire
+    pub fn needs_refresh(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => chrono::Utc::now().timestamp() + REFRESH_MARGIN_SECS >= expires_at,
+            None => false,
+        }
+    }
+}
+
+/// Token endpoint response
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+    scope: Option<String>,
+}
+
+/// Generate a random URL-safe string for PKCE verifiers and state values
+fn random_token(len: usize) -> String {
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+/// Derive the S256 PKCE challenge for a verifier
+fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Build the authorization URL and remember the PKCE verifier for the callback
+pub fn begin_authorization() -> Result<String, String> {
+    let verifier = random_token(64);
+    let state = random_token(32);
+
+    let url = reqwest::Url::parse_with_params(
+        AUTHORIZE_URL,
+        &[
+            ("code", "true"),
+            ("client_id", client_id()),
+            ("response_type", "code"),
+            ("redirect_uri", REDIRECT_URI),
+            ("scope", SCOPES),
+            ("code_challenge", &code_challenge(&verifier)),
+            ("code_challenge_method", "S256"),
+            ("state", &state),
+        ],
+    )
+    .map_err(|e| format!("Failed to build authorization URL: {}", e))?;
+
+    pending()
+        .lock()
+        .map_err(|_| "OAuth state lock poisoned".to_string())?
+        .insert(state, verifier);
+
+    Ok(url.to_string())
+}
+
+/// Whether a deep link URL is an OAuth callback
+pub fn is_callback_url(url: &str) -> bool {
+    url.starts_with(REDIRECT_URI)
+}
+
+/// Handle the deep link callback: validate state and exchange the code for tokens
+pub async fn complete_authorization(pool: &SqlitePool, callback_url: &str) -> Result<OAuthTokens, String> {
+    let url = reqwest::Url::parse(callback_url)
+        .map_err(|e| format!("Invalid callback URL: {}", e))?;
+
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    if let Some(error) = params.get("error") {
+        return Err(format!("Authorization denied: {}", error));
+    }
+
+    let code = params
+        .get("code")
+        .ok_or_else(|| "Callback is missing the authorization code".to_string())?;
+    let state = params
+        .get("state")
+        .ok_or_else(|| "Callback is missing the state parameter".to_string())?;
+
+    let verifier = pending()
+        .lock()
+        .map_err(|_| "OAuth state lock poisoned".to_string())?
+        .remove(state)
+        .ok_or_else(|| "Unknown or expired authorization request".to_string())?;
+
+    let response = crate::proxy::client()
+        .post(TOKEN_URL)
+        .json(&serde_json::json!({
+            "grant_type": "authorization_code",
+            "client_id": client_id(),
+            "code": code,
+            "state": state,
+            "redirect_uri": REDIRECT_URI,
+            "code_verifier": verifier,
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Token request failed: {}", e))?;
+
+    let tokens = parse_token_response(response, None).await?;
+    store_tokens(pool, &tokens).await?;
+
+    println!("✅ OAuth sign-in completed");
+    Ok(tokens)
+}
+
+/// Exchange a refresh token for a new access token
+async fn refresh_tokens(pool: &SqlitePool, tokens: &OAuthTokens) -> Result<OAuthTokens, String> {
+    let refresh_token = tokens
+        .refresh_token
+        .as_deref()
+        .ok_or_else(|| "Access token expired and no refresh token is available".to_string())?;
+
+    let response = crate::proxy::client()
+        .post(TOKEN_URL)
+        .json(&serde_json::json!({
+            "grant_type": "refresh_token",
+            "client_id": client_id(),
+            "refresh_token": refresh_token,
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Token refresh failed: {}", e))?;
+
+    let refreshed = parse_token_response(response, Some(refresh_token)).await?;
+    store_tokens(pool, &refreshed).await?;
+
+    println!("🔄 OAuth access token refreshed");
+    Ok(refreshed)
+}
+
+async fn parse_token_response(
+    response: reqwest::Response,
+    previous_refresh_token: Option<&str>,
+) -> Result<OAuthTokens, String> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Token endpoint returned {}: {}", status, body));
+    }
+
+    let body: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid token response: {}", e))?;
+
+    Ok(OAuthTokens {
+        access_token: body.access_token,
+        // Some servers omit the refresh token on refresh; keep the old one
+        refresh_token: body
+            .refresh_token
+            .or_else(|| previous_refresh_token.map(String::from)),
+        expires_at: body
+            .expires_in
+            .map(|secs| chrono::Utc::now().timestamp() + secs),
+        scope: body.scope,
+    })
+}
+
+/// Persist the token set (single row, like `auth_credentials`), encrypted
+/// when the app lock is enabled
+pub async fn store_tokens(pool: &SqlitePool, tokens: &OAuthTokens) -> Result<(), String> {
+    let refresh_token = match &tokens.refresh_token {
+        Some(token) => Some(crate::vault::seal(pool, token).await?),
+        None => None,
+    };
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO oauth_tokens (id, access_token, refresh_token, expires_at, scope, updated_at)
+         VALUES (1, ?1, ?2, ?3, ?4, datetime('now'))"
+    )
+    .bind(crate::vault::seal(pool, &tokens.access_token).await?)
+    .bind(refresh_token)
+    .bind(tokens.expires_at)
+    .bind(&tokens.scope)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(())
+}
+
+/// Load the stored token set, if any
+pub async fn load_tokens(pool: &SqlitePool) -> Result<Option<OAuthTokens>, String> {
+    let row = sqlx::query("SELECT access_token, refresh_token, expires_at, scope FROM oauth_tokens WHERE id = 1")
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let refresh_token: Option<String> = row.get("refresh_token");
+
+    Ok(Some(OAuthTokens {
+        access_token: crate::vault::open(row.get("access_token"))?,
+        refresh_token: refresh_token.as_deref().map(crate::vault::open).transpose()?,
+        expires_at: row.get("expires_at"),
+        scope: row.get("scope"),
+    }))
+}
+
+/// Return a usable access token, refreshing it first if it is about to expire
+pub async fn get_valid_access_token(pool: &SqlitePool) -> Result<String, String> {
+    let tokens = load_tokens(pool)
+        .await?
+        .ok_or_else(|| "Not signed in with OAuth".to_string())?;
+
+    if tokens.needs_refresh() {
+        return Ok(refresh_tokens(pool, &tokens).await?.access_token);
+    }
+
+    Ok(tokens.access_token)
+}
+
+/// Forget the stored OAuth tokens
+pub async fn sign_out(pool: &SqlitePool) -> Result<(), String> {
+    sqlx::query("DELETE FROM oauth_tokens WHERE id = 1")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_challenge_matches_rfc7636_example() {
+        // Example verifier/challenge pair from RFC 7636 appendix B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        assert_eq!(code_challenge(verifier), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+    }
+
+    #[test]
+    fn test_begin_authorization_registers_state() {
+        let url = begin_authorization().unwrap();
+        let parsed = reqwest::Url::parse(&url).unwrap();
+        let state = parsed
+            .query_pairs()
+            .find(|(k, _)| k == "state")
+            .map(|(_, v)| v.into_owned())
+            .unwrap();
+
+        assert!(pending().lock().unwrap().contains_key(&state));
+        assert!(url.contains("code_challenge_method=S256"));
+    }
+
+    #[test]
+    fn test_needs_refresh() {
+        let now = chrono::Utc::now().timestamp();
+        let mut tokens = OAuthTokens {
+            access_token: "a".to_string(),
+            refresh_token: None,
+            expires_at: Some(now + 3600),
+            scope: None,
+        };
+        assert!(!tokens.needs_refresh());
+
+        tokens.expires_at = Some(now + 60);
+        assert!(tokens.needs_refresh());
+
+        tokens.expires_at = None;
+        assert!(!tokens.needs_refresh());
+    }
+}
@@ -0,0 +1,281 @@
+//! Device pairing for LAN access to the embedded server
+//!
+//! The desktop app generates a short-lived pairing code which a remote
+//! browser (phone, tablet) exchanges for a long-lived, scoped bearer token.
+//! Tokens are stored hashed in the `devices` table and act on behalf of the
+//! local desktop user with at most the scope granted at pairing time.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How long a pairing code can be redeemed
+const CODE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Pairing code length (from an alphabet without look-alike characters)
+const CODE_LEN: usize = 8;
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Prefix distinguishing device tokens from session tokens
+pub const DEVICE_TOKEN_PREFIX: &str = "dev_";
+
+/// User that paired devices act on behalf of
+const DEVICE_OWNER: &str = "local-user";
+
+struct PendingCode {
+    scope: String,
+    expires_at: Instant,
+}
+
+static PENDING: OnceLock<Mutex<HashMap<String, PendingCode>>> = OnceLock::new();
+
+fn pending() -> &'static Mutex<HashMap<String, PendingCode>> {
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A freshly generated pairing code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingCode {
+    pub code: String,
+    pub scope: String,
+    pub expires_in_secs: u64,
+}
+
+/// Result of redeeming a pairing code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairedDevice {
+    pub device_id: String,
+    pub token: String,
+    pub scope: String,
+}
+
+/// Paired device as listed in the UI (never includes the token)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub scope: String,
+    pub ip_address: Option<String>,
+    pub created_at: String,
+    pub last_seen_at: Option<String>,
+}
+
+/// Identity resolved from a device token
+#[derive(Debug, Clone)]
+pub struct DeviceIdentity {
+    pub device_id: String,
+    pub user_id: String,
+    pub scope: String,
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LEN)
+        .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Normalize user-typed codes (case, spaces, dashes)
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Generate a new pairing code granting `scope` ("viewer" or "editor")
+pub fn create_pairing_code(scope: &str) -> Result<PairingCode, String> {
+    if !matches!(scope, "viewer" | "editor") {
+        return Err(format!("Devices can only be paired as viewer or editor, not {}", scope));
+    }
+
+    let code = generate_code();
+    let now = Instant::now();
+
+    let mut codes = pending()
+        .lock()
+        .map_err(|_| "Pairing state lock poisoned".to_string())?;
+    codes.retain(|_, pending| pending.expires_at > now);
+    codes.insert(
+        code.clone(),
+        PendingCode {
+            scope: scope.to_string(),
+            expires_at: now + CODE_TTL,
+        },
+    );
+
+    Ok(PairingCode {
+        code,
+        scope: scope.to_string(),
+        expires_in_secs: CODE_TTL.as_secs(),
+    })
+}
+
+/// Redeem a pairing code for a device token
+///
+/// Codes are single use; an expired or unknown code is rejected.
+pub async fn exchange_code(
+    pool: &SqlitePool,
+    code: &str,
+    device_name: &str,
+    ip_address: Option<&str>,
+) -> Result<PairedDevice, String> {
+    let pending_code = {
+        let mut codes = pending()
+            .lock()
+            .map_err(|_| "Pairing state lock poisoned".to_string())?;
+        codes.remove(&normalize_code(code))
+    };
+
+    let pending_code = match pending_code {
+        Some(pending_code) if pending_code.expires_at > Instant::now() => pending_code,
+        _ => return Err("Invalid or expired pairing code".to_string()),
+    };
+
+    let device_id = uuid::Uuid::new_v4().to_string();
+    let token = format!("{}{}", DEVICE_TOKEN_PREFIX, uuid::Uuid::new_v4().simple());
+    let name = if device_name.trim().is_empty() {
+        "Paired device"
+    } else {
+        device_name.trim()
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO devices (id, name, token_hash, scope, user_id, ip_address)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&device_id)
+    .bind(name)
+    .bind(hash_token(&token))
+    .bind(&pending_code.scope)
+    .bind(DEVICE_OWNER)
+    .bind(ip_address)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to store device: {}", e))?;
+
+    println!("📱 Paired device: {} ({})", name, pending_code.scope);
+
+    Ok(PairedDevice {
+        device_id,
+        token,
+        scope: pending_code.scope,
+    })
+}
+
+/// Resolve a device token, bumping its last-seen time
+pub async fn authenticate_device(
+    pool: &SqlitePool,
+    token: &str,
+) -> Result<Option<DeviceIdentity>, sqlx::Error> {
+    if !token.starts_with(DEVICE_TOKEN_PREFIX) {
+        return Ok(None);
+    }
+
+    let token_hash = hash_token(token);
+
+    let row = sqlx::query("SELECT id, user_id, scope FROM devices WHERE token_hash = ?")
+        .bind(&token_hash)
+        .fetch_optional(pool)
+        .await?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    sqlx::query("UPDATE devices SET last_seen_at = datetime('now') WHERE token_hash = ?")
+        .bind(&token_hash)
+        .execute(pool)
+        .await?;
+
+    Ok(Some(DeviceIdentity {
+        device_id: row.get("id"),
+        user_id: row.get("user_id"),
+        scope: row.get("scope"),
+    }))
+}
+
+/// List paired devices
+pub async fn list_devices(pool: &SqlitePool) -> Result<Vec<DeviceInfo>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT id, name, scope, ip_address, created_at, last_seen_at FROM devices ORDER BY created_at DESC",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| DeviceInfo {
+            id: row.get("id"),
+            name: row.get("name"),
+            scope: row.get("scope"),
+            ip_address: row.get("ip_address"),
+            created_at: row.get("created_at"),
+            last_seen_at: row.get("last_seen_at"),
+        })
+        .collect())
+}
+
+/// Revoke a paired device, invalidating its token
+pub async fn revoke_device(pool: &SqlitePool, device_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM devices WHERE id = ?")
+        .bind(device_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_normalize_code() {
+        assert_eq!(normalize_code("abcd-efgh"), "ABCDEFGH");
+        assert_eq!(normalize_code(" ab cd "), "ABCD");
+    }
+
+    #[test]
+    fn test_owner_scope_rejected() {
+        assert!(create_pairing_code("owner").is_err());
+        assert!(create_pairing_code("viewer").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pairing_roundtrip() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let code = create_pairing_code("editor").unwrap();
+        let paired = exchange_code(&pool, &code.code.to_lowercase(), "My Phone", Some("192.168.1.20"))
+            .await
+            .unwrap();
+
+        // Codes are single use
+        assert!(exchange_code(&pool, &code.code, "Other", None).await.is_err());
+
+        let identity = authenticate_device(&pool, &paired.token).await.unwrap().unwrap();
+        assert_eq!(identity.scope, "editor");
+        assert_eq!(identity.user_id, "local-user");
+
+        assert!(revoke_device(&pool, &paired.device_id).await.unwrap());
+        assert!(authenticate_device(&pool, &paired.token).await.unwrap().is_none());
+    }
+}
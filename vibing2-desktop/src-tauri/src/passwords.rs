@@ -0,0 +1,106 @@
+//! Account passwords for the embedded server
+//!
+//! Passwords are stored as Argon2id PHC strings. Accounts created before
+//! hashing hold plaintext, which still signs in locally and is replaced by a
+//! hash on the next successful sign in. The seeded local account's
+//! placeholder password never signs in; the owner sets a real one with
+//! `set_local_password`, and LAN mode can't be enabled until they have.
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use sqlx::SqlitePool;
+
+/// The desktop owner's account
+pub const LOCAL_USER_ID: &str = "local-user";
+
+pub const MIN_LENGTH: usize = 8;
+
+/// Hash a password for storage
+pub fn hash(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash password: {}", e))
+}
+
+/// Whether a stored password is a hash rather than legacy plaintext
+pub fn is_hashed(stored: &str) -> bool {
+    PasswordHash::new(stored).is_ok()
+}
+
+/// Check a password against its stored form
+///
+/// The local account only signs in with a hashed password, so its seeded
+/// placeholder is never accepted.
+pub fn verify(user_id: &str, stored: &str, password: &str) -> bool {
+    match PasswordHash::new(stored) {
+        Ok(hash) => Argon2::default().verify_password(password.as_bytes(), &hash).is_ok(),
+        Err(_) => user_id != LOCAL_USER_ID && stored == password,
+    }
+}
+
+/// Replace a user's password with a hash of `password`
+pub async fn set(pool: &SqlitePool, user_id: &str, password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_LENGTH {
+        return Err(format!("Password must be at least {} characters", MIN_LENGTH));
+    }
+
+    let result = sqlx::query("UPDATE users SET password = ? WHERE id = ?")
+        .bind(hash(password)?)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("User not found: {}", user_id));
+    }
+    Ok(())
+}
+
+/// Refuse LAN mode until the local account has a real (hashed) password
+pub async fn require_for_lan(pool: &SqlitePool) -> Result<(), String> {
+    let stored: Option<String> = sqlx::query_scalar("SELECT password FROM users WHERE id = ?")
+        .bind(LOCAL_USER_ID)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    match stored {
+        Some(stored) if is_hashed(&stored) => Ok(()),
+        _ => Err("Set a password for the local account before enabling LAN mode".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let stored = hash("correct horse").unwrap();
+        assert!(is_hashed(&stored));
+        assert!(verify("u1", &stored, "correct horse"));
+        assert!(!verify("u1", &stored, "wrong horse"));
+
+        // Legacy plaintext, except for the local account's placeholder
+        assert!(!is_hashed("local"));
+        assert!(verify("u1", "hunter22", "hunter22"));
+        assert!(!verify(LOCAL_USER_ID, "local", "local"));
+    }
+
+    #[tokio::test]
+    async fn test_lan_requires_local_password() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        assert!(require_for_lan(&pool).await.is_err());
+        assert!(set(&pool, LOCAL_USER_ID, "short").await.is_err());
+        set(&pool, LOCAL_USER_ID, "a real passphrase").await.unwrap();
+        require_for_lan(&pool).await.unwrap();
+    }
+}
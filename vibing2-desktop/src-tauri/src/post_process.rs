@@ -0,0 +1,441 @@
+//! Post-processing of assistant replies
+//!
+//! Each project can enable post-processors, run on a reply after it has
+//! streamed and before it is saved to the project's history:
+//! `extract_code` saves fenced code blocks as project files, `format` runs
+//! the extracted files through rustfmt or prettier in the project's workspace
+//! (see [`crate::sandbox`]), and `strip_markdown` saves the reply as plain
+//! text. A step that fails is logged and skipped; the reply is always saved.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::sandbox::{self, CommandRequest};
+use crate::sharing::Access;
+
+/// Seconds a formatter may take per file
+const FORMAT_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostProcessor {
+    /// Save fenced code blocks as project files
+    ExtractCode,
+    /// Format extracted files with rustfmt or prettier
+    Format,
+    /// Save the reply without markdown syntax
+    StripMarkdown,
+}
+
+impl PostProcessor {
+    /// Run order
+    pub const ALL: [PostProcessor; 3] = [PostProcessor::ExtractCode, PostProcessor::Format, PostProcessor::StripMarkdown];
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "extract_code" => Ok(PostProcessor::ExtractCode),
+            "format" => Ok(PostProcessor::Format),
+            "strip_markdown" => Ok(PostProcessor::StripMarkdown),
+            other => Err(format!("Unknown post-processor: {}", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PostProcessor::ExtractCode => "extract_code",
+            PostProcessor::Format => "format",
+            PostProcessor::StripMarkdown => "strip_markdown",
+        }
+    }
+
+    /// Whether the step changes project files
+    pub fn writes_files(self) -> bool {
+        matches!(self, PostProcessor::ExtractCode | PostProcessor::Format)
+    }
+}
+
+/// A fenced code block in a reply
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    pub language: Option<String>,
+    /// File the block is for, from the fence's info string
+    pub path: Option<String>,
+    pub content: String,
+}
+
+impl CodeBlock {
+    /// The block's path, or a conventional file name for its language
+    pub fn file_name(&self) -> Option<String> {
+        if let Some(path) = &self.path {
+            return Some(path.clone());
+        }
+        let name = match self.language.as_deref()? {
+            "html" => "index.html",
+            "css" => "styles.css",
+            "js" | "javascript" => "script.js",
+            _ => return None,
+        };
+        Some(name.to_string())
+    }
+}
+
+/// A reply after post-processing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Processed {
+    /// The reply as it should be saved
+    pub content: String,
+    /// Project files written
+    pub files: Vec<String>,
+}
+
+/// Post-processors enabled for a project, in run order
+pub async fn enabled(pool: &SqlitePool, project_id: &str) -> Result<Vec<PostProcessor>, sqlx::Error> {
+    let value: Option<String> = sqlx::query_scalar("SELECT post_processors FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await?;
+
+    let names: Vec<String> = value
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default();
+    Ok(PostProcessor::ALL
+        .into_iter()
+        .filter(|processor| names.iter().any(|name| name == processor.as_str()))
+        .collect())
+}
+
+/// Post-processors to run on a reply for a principal with `access`
+///
+/// Steps that write files are left out without write access.
+pub async fn for_project(pool: &SqlitePool, project_id: &str, access: Access) -> Vec<PostProcessor> {
+    match enabled(pool, project_id).await {
+        Ok(processors) => processors
+            .into_iter()
+            .filter(|processor| access >= Access::Write || !processor.writes_files())
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to load post-processors: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Enable exactly `processors` for a project
+pub async fn set(pool: &SqlitePool, project_id: &str, processors: &[PostProcessor]) -> Result<(), String> {
+    let names: Vec<&str> = PostProcessor::ALL
+        .into_iter()
+        .filter(|processor| processors.contains(processor))
+        .map(PostProcessor::as_str)
+        .collect();
+    let value = serde_json::to_string(&names).map_err(|e| e.to_string())?;
+
+    let result = sqlx::query("UPDATE projects SET post_processors = ?, updated_at = ? WHERE id = ?")
+        .bind(value)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(project_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Project not found: {}", project_id));
+    }
+    Ok(())
+}
+
+/// Run `processors` on a reply in a project
+pub async fn run(pool: &SqlitePool, project_id: &str, processors: &[PostProcessor], reply: &str) -> Processed {
+    let mut processed = Processed {
+        content: reply.to_string(),
+        files: Vec::new(),
+    };
+
+    for processor in PostProcessor::ALL.into_iter().filter(|p| processors.contains(p)) {
+        let result = match processor {
+            PostProcessor::ExtractCode => extract_files(pool, project_id, reply).await.map(|files| processed.files = files),
+            PostProcessor::Format => format_files(pool, project_id, &processed.files).await,
+            PostProcessor::StripMarkdown => {
+                processed.content = strip_markdown(&processed.content);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("Post-processor {} failed: {}", processor.as_str(), e);
+        }
+    }
+    processed
+}
+
+/// Save a reply's code blocks as project files; later blocks for the same
+/// file win
+async fn extract_files(pool: &SqlitePool, project_id: &str, reply: &str) -> Result<Vec<String>, String> {
+    let mut files: Vec<String> = Vec::new();
+    for block in code_blocks(reply) {
+        let Some(path) = block.file_name().filter(|path| valid_path(path)) else {
+            continue;
+        };
+        crate::agent_tools::save_file(pool, project_id, &path, &block.content)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Format files in the project's workspace and save the results
+async fn format_files(pool: &SqlitePool, project_id: &str, files: &[String]) -> Result<(), String> {
+    let files: Vec<(&String, &str)> = files.iter().filter_map(|path| Some((path, formatter(path)?))).collect();
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let root = sandbox::materialize(pool, project_id).await?;
+    for (path, formatter) in files {
+        let request = CommandRequest {
+            command: format!("{} '{}'", formatter, path),
+            cwd: None,
+            timeout_secs: Some(FORMAT_TIMEOUT_SECS),
+        };
+        let output = sandbox::run(&root, &request).await?;
+        if !output.success() {
+            eprintln!("{}", output.summary());
+            continue;
+        }
+
+        let formatted = tokio::fs::read_to_string(sandbox::jailed(&root, path)?)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        crate::agent_tools::save_file(pool, project_id, path, &formatted)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Formatter command for a file, by extension
+fn formatter(path: &str) -> Option<&'static str> {
+    match path.rsplit_once('.')?.1.to_ascii_lowercase().as_str() {
+        "rs" => Some("rustfmt --edition 2021"),
+        "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "css" | "scss" | "html" | "json" | "md" | "vue" => {
+            Some("npx --no-install prettier --write")
+        }
+        _ => None,
+    }
+}
+
+fn valid_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.starts_with('/')
+        && !path.contains(['\\', '\'', ':'])
+        && !path.split('/').any(|segment| segment.is_empty() || segment == "." || segment == "..")
+}
+
+/// The closed fenced code blocks in `text`
+///
+/// The fence's info string gives the language and, optionally, the file:
+/// ```` ```js src/app.js ````, ```` ```js:src/app.js ````, or
+/// ```` ```js title="src/app.js" ````.
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(String, CodeBlock)> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match &mut open {
+            None => {
+                let Some(fence) = fence(trimmed) else {
+                    continue;
+                };
+                let (language, path) = parse_info(trimmed[fence.len()..].trim());
+                open = Some((fence, CodeBlock { language, path, content: String::new() }));
+            }
+            Some((fence, block)) => {
+                if closes(trimmed, fence) {
+                    let (_, block) = open.take().expect("block is open");
+                    blocks.push(block);
+                } else {
+                    block.content.push_str(line);
+                    block.content.push('\n');
+                }
+            }
+        }
+    }
+    blocks
+}
+
+/// The fence opening a line: three or more backticks or tildes
+fn fence(line: &str) -> Option<String> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let fence: String = line.chars().take_while(|c| *c == marker).collect();
+    (fence.len() >= 3).then_some(fence)
+}
+
+/// Whether a line closes the block opened by `fence`
+fn closes(line: &str, fence: &str) -> bool {
+    line.starts_with(fence) && line.trim_start_matches(&fence[..1]).trim().is_empty()
+}
+
+fn parse_info(info: &str) -> (Option<String>, Option<String>) {
+    let mut words = info.split_whitespace();
+    let Some(first) = words.next() else {
+        return (None, None);
+    };
+
+    let (language, mut path) = match first.split_once(':') {
+        Some((language, path)) => (Some(language), Some(path)),
+        None if first.contains(['.', '/']) => (None, Some(first)),
+        None => (Some(first), None),
+    };
+    for word in words {
+        let value = ["title=", "file=", "filename="]
+            .iter()
+            .find_map(|key| word.strip_prefix(key))
+            .unwrap_or(word);
+        if path.is_none() && value.contains(['.', '/']) {
+            path = Some(value.trim_matches(['"', '\'']));
+        }
+    }
+
+    (
+        language.map(str::to_ascii_lowercase).filter(|language| !language.is_empty()),
+        path.map(str::to_string).filter(|path| !path.is_empty()),
+    )
+}
+
+/// `text` without markdown syntax; code is kept, without its fences
+pub fn strip_markdown(text: &str) -> String {
+    let mut lines = Vec::new();
+    let mut fence_marker: Option<String> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = &fence_marker {
+            if closes(trimmed, marker) {
+                fence_marker = None;
+            } else {
+                lines.push(line.to_string());
+            }
+            continue;
+        }
+        if let Some(marker) = fence(trimmed) {
+            fence_marker = Some(marker);
+            continue;
+        }
+
+        let line = trimmed
+            .strip_prefix("> ")
+            .unwrap_or(trimmed.strip_prefix('>').unwrap_or(line));
+        let heading = line.trim_start_matches('#');
+        let line = if heading.len() < line.len() && heading.starts_with(' ') {
+            heading.trim_start()
+        } else {
+            line
+        };
+        lines.push(strip_inline(line));
+    }
+
+    let mut text = lines.join("\n");
+    if text.is_empty() {
+        return text;
+    }
+    text.push('\n');
+    text
+}
+
+/// Remove emphasis and inline code markers, and reduce links and images to
+/// their text
+fn strip_inline(line: &str) -> String {
+    let line = line.replace("**", "").replace("__", "").replace('`', "");
+
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line.as_str();
+    while let Some(start) = rest.find('[') {
+        let link = rest[start + 1..]
+            .split_once("](")
+            .and_then(|(label, after)| Some((label, after.find(')')?)))
+            .filter(|(label, _)| !label.contains('['));
+        let Some((label, url_end)) = link else {
+            out.push_str(&rest[..=start]);
+            rest = &rest[start + 1..];
+            continue;
+        };
+
+        let before = &rest[..start];
+        out.push_str(before.strip_suffix('!').unwrap_or(before));
+        out.push_str(label);
+        rest = &rest[start + 1 + label.len() + 2 + url_end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    const REPLY: &str = "## Landing page\n\
+        Here is the **page**:\n\
+        ```html\n<h1>Hi</h1>\n```\n\
+        And the [styles](https://example.com):\n\
+        ```css src/site.css\nh1 { color: red; }\n```\n\
+        ```bash\nnpm install\n```\n";
+
+    #[test]
+    fn test_code_blocks() {
+        let blocks = code_blocks(REPLY);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].file_name().as_deref(), Some("index.html"));
+        assert_eq!(blocks[0].content, "<h1>Hi</h1>\n");
+        assert_eq!(blocks[1].path.as_deref(), Some("src/site.css"));
+        assert_eq!(blocks[2].file_name(), None);
+
+        assert_eq!(parse_info("js:src/app.js"), (Some("js".to_string()), Some("src/app.js".to_string())));
+        assert_eq!(parse_info(r#"ts title="lib/a.ts""#), (Some("ts".to_string()), Some("lib/a.ts".to_string())));
+        assert!(code_blocks("```rust\nfn main() {}\n").is_empty());
+
+        assert!(valid_path("src/site.css"));
+        assert!(!valid_path("../site.css"));
+        assert!(!valid_path("/etc/hosts"));
+    }
+
+    #[test]
+    fn test_strip_markdown() {
+        assert_eq!(
+            strip_markdown(REPLY),
+            "Landing page\nHere is the page:\n<h1>Hi</h1>\nAnd the styles:\nh1 { color: red; }\nnpm install\n"
+        );
+        assert_eq!(strip_markdown("> quoted ![logo](a.png) and [x] box"), "quoted logo and [x] box\n");
+    }
+
+    #[tokio::test]
+    async fn test_extract_code_to_project_files() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Site', 'website', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(enabled(&pool, "p1").await.unwrap().is_empty());
+        set(&pool, "p1", &[PostProcessor::StripMarkdown, PostProcessor::ExtractCode]).await.unwrap();
+        let processors = enabled(&pool, "p1").await.unwrap();
+        assert_eq!(processors, vec![PostProcessor::ExtractCode, PostProcessor::StripMarkdown]);
+        assert_eq!(for_project(&pool, "p1", Access::Read).await, vec![PostProcessor::StripMarkdown]);
+        assert!(set(&pool, "missing", &[]).await.is_err());
+
+        let processed = run(&pool, "p1", &processors, REPLY).await;
+        assert_eq!(processed.files, vec!["index.html", "src/site.css"]);
+        assert!(processed.content.starts_with("Landing page\n"));
+
+        let content: String = sqlx::query_scalar("SELECT content FROM project_files WHERE project_id = 'p1' AND path = 'src/site.css'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(content, "h1 { color: red; }\n");
+    }
+}
@@ -0,0 +1,289 @@
+// Power module - Holds background work while the machine sleeps or saves power
+//
+// Update checks, scheduled agent runs, and agent registry sync wait in
+// `wait_active` before each run. They're held:
+// - for a short grace period after waking from sleep, so the network and
+//   disks are back before anything runs
+// - while in low power, which follows the OS battery saver by default (see
+//   `LowPowerMode`)
+//
+// Sleep isn't reported to the app directly. The monitor notices it on wake,
+// when its last tick took far longer than it should have by either clock.
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{watch, Notify};
+
+/// Setting holding the `LowPowerMode`
+pub const SETTING_KEY: &str = "low_power_mode";
+
+/// How often the power source is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How much longer than `CHECK_INTERVAL` a tick must take to count as sleep
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// How long background work stays held after waking
+const WAKE_GRACE: Duration = Duration::from_secs(30);
+
+/// When background work counts as low power
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LowPowerMode {
+    /// While the OS battery saver (low power mode on macOS) is on
+    #[default]
+    BatterySaver,
+    /// Whenever running on battery
+    OnBattery,
+    /// Never; only sleep holds background work
+    Off,
+}
+
+impl LowPowerMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "battery_saver" => Some(LowPowerMode::BatterySaver),
+            "on_battery" => Some(LowPowerMode::OnBattery),
+            "off" => Some(LowPowerMode::Off),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LowPowerMode::BatterySaver => "battery_saver",
+            LowPowerMode::OnBattery => "on_battery",
+            LowPowerMode::Off => "off",
+        }
+    }
+
+    fn applies(self, source: PowerSource) -> bool {
+        match self {
+            LowPowerMode::BatterySaver => source.battery_saver,
+            LowPowerMode::OnBattery => source.on_battery || source.battery_saver,
+            LowPowerMode::Off => false,
+        }
+    }
+}
+
+/// Where the machine's power comes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PowerSource {
+    pub on_battery: bool,
+    pub battery_saver: bool,
+}
+
+/// Power state, emitted as `power:changed`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PowerStatus {
+    #[serde(flatten)]
+    pub source: PowerSource,
+    pub mode: LowPowerMode,
+    /// Just woke from sleep
+    pub waking: bool,
+    /// Background work is held
+    pub paused: bool,
+}
+
+fn status_sender() -> &'static watch::Sender<PowerStatus> {
+    static STATUS: OnceLock<watch::Sender<PowerStatus>> = OnceLock::new();
+    STATUS.get_or_init(|| watch::channel(PowerStatus::default()).0)
+}
+
+fn refresh_signal() -> &'static Notify {
+    static REFRESH: OnceLock<Notify> = OnceLock::new();
+    REFRESH.get_or_init(Notify::new)
+}
+
+pub fn current_status() -> PowerStatus {
+    *status_sender().borrow()
+}
+
+/// Completes once background work may run
+pub async fn wait_active() {
+    let mut status = status_sender().subscribe();
+    let _ = status.wait_for(|status| !status.paused).await;
+}
+
+/// Check the power state now, e.g. after the mode setting changed
+pub fn refresh() {
+    refresh_signal().notify_one();
+}
+
+pub async fn load_mode(pool: &SqlitePool) -> LowPowerMode {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(SETTING_KEY)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.as_deref().and_then(LowPowerMode::parse).unwrap_or_default()
+}
+
+/// Whether a tick that should have taken `expected` shows the machine slept
+fn slept(expected: Duration, wall: Duration, monotonic: Duration) -> bool {
+    // The monotonic clock stops during sleep on macOS and Linux but not on
+    // Windows; the wall clock always jumps
+    wall.max(monotonic) > expected + SLEEP_THRESHOLD
+}
+
+/// Spawn a background task that follows sleep and the power source
+///
+/// Emits `power:wake` on waking from sleep and `power:changed` whenever the
+/// status changes.
+pub fn spawn_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_tick = (Instant::now(), SystemTime::now());
+        let mut woke_at: Option<Instant> = None;
+
+        loop {
+            let now = (Instant::now(), SystemTime::now());
+            let wall = now.1.duration_since(last_tick.1).unwrap_or_default();
+            if slept(CHECK_INTERVAL, wall, now.0 - last_tick.0) {
+                println!("💤 Woke from sleep; holding background tasks for {}s", WAKE_GRACE.as_secs());
+                woke_at = Some(now.0);
+                let _ = app.emit("power:wake", ());
+            }
+
+            let mode = match crate::database::get_pool().await {
+                Ok(pool) => load_mode(pool.as_ref()).await,
+                Err(_) => LowPowerMode::default(),
+            };
+            let source = tokio::task::spawn_blocking(power_source).await.unwrap_or_default();
+            let waking = woke_at.is_some_and(|woke_at| woke_at.elapsed() < WAKE_GRACE);
+            let status = PowerStatus {
+                source,
+                mode,
+                waking,
+                paused: waking || mode.applies(source),
+            };
+
+            let changed = status_sender().send_if_modified(|current| {
+                let changed = *current != status;
+                *current = status;
+                changed
+            });
+            if changed {
+                let _ = app.emit("power:changed", status);
+            }
+
+            last_tick = (Instant::now(), SystemTime::now());
+            tokio::select! {
+                _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                _ = refresh_signal().notified() => {}
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn power_source() -> PowerSource {
+    let pmset = |args: &[&str]| {
+        std::process::Command::new("pmset")
+            .args(args)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+            .unwrap_or_default()
+    };
+
+    PowerSource {
+        on_battery: pmset(&["-g", "batt"]).contains("'Battery Power'"),
+        battery_saver: pmset(&["-g"]).lines().any(|line| {
+            let mut parts = line.split_whitespace();
+            parts.next() == Some("lowpowermode") && parts.next() == Some("1")
+        }),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn power_source() -> PowerSource {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if unsafe { GetSystemPowerStatus(&mut status) }.is_err() {
+        return PowerSource::default();
+    }
+    PowerSource {
+        on_battery: status.ACLineStatus == 0,
+        battery_saver: status.SystemStatusFlag == 1,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn power_source() -> PowerSource {
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).unwrap_or_default().trim().to_string();
+
+    // On battery when there are mains supplies and none is plugged in
+    let mut mains = Vec::new();
+    if let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") {
+        for entry in entries.flatten() {
+            if read(entry.path().join("type")) == "Mains" {
+                mains.push(read(entry.path().join("online")) == "1");
+            }
+        }
+    }
+
+    PowerSource {
+        on_battery: !mains.is_empty() && !mains.contains(&true),
+        // Set by power-profiles-daemon's power-saver profile
+        battery_saver: read("/sys/firmware/acpi/platform_profile".into()) == "low-power",
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn power_source() -> PowerSource {
+    PowerSource::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_low_power_mode() {
+        let saver = PowerSource { on_battery: true, battery_saver: true };
+        let battery = PowerSource { on_battery: true, battery_saver: false };
+        assert!(LowPowerMode::BatterySaver.applies(saver));
+        assert!(!LowPowerMode::BatterySaver.applies(battery));
+        assert!(LowPowerMode::OnBattery.applies(battery));
+        assert!(!LowPowerMode::OnBattery.applies(PowerSource::default()));
+        assert!(!LowPowerMode::Off.applies(saver));
+
+        for mode in [LowPowerMode::BatterySaver, LowPowerMode::OnBattery, LowPowerMode::Off] {
+            assert_eq!(LowPowerMode::parse(mode.as_str()), Some(mode));
+        }
+        assert_eq!(LowPowerMode::parse("sometimes"), None);
+    }
+
+    #[test]
+    fn test_slept() {
+        let secs = Duration::from_secs;
+        assert!(!slept(secs(15), secs(15), secs(15)));
+        assert!(!slept(secs(15), secs(20), secs(16)));
+        // macOS/Linux: only the wall clock moved on
+        assert!(slept(secs(15), secs(3600), secs(15)));
+        // Windows: both clocks moved on
+        assert!(slept(secs(15), secs(3600), secs(3600)));
+    }
+
+    #[tokio::test]
+    async fn test_load_mode() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(load_mode(&pool).await, LowPowerMode::BatterySaver);
+
+        sqlx::query("INSERT INTO settings (id, key, value) VALUES (?, ?, ?)")
+            .bind("setting-low-power")
+            .bind(SETTING_KEY)
+            .bind("on_battery")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(load_mode(&pool).await, LowPowerMode::OnBattery);
+    }
+}
@@ -0,0 +1,126 @@
+//! Agents attached to a project
+//!
+//! A project's active agents are rows of `project_agents`, ordered by
+//! `position`. Attaching appends an agent; detaching removes it and closes
+//! the gap. Deleting an agent or a project removes its rows.
+
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// IDs of a project's agents, in order
+pub async fn list(pool: &SqlitePool, project_id: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT agent_id FROM project_agents WHERE project_id = ? ORDER BY position")
+        .bind(project_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Replace a project's agents, keeping the given order
+///
+/// Takes a connection so it can run inside the caller's transaction.
+pub async fn set(conn: &mut SqliteConnection, project_id: &str, agent_ids: &[String]) -> Result<(), String> {
+    sqlx::query("DELETE FROM project_agents WHERE project_id = ?")
+        .bind(project_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to save project agents: {}", e))?;
+
+    for (position, agent_id) in agent_ids.iter().enumerate() {
+        insert(conn, project_id, agent_id, position as i64).await?;
+    }
+    Ok(())
+}
+
+/// Add an agent after a project's others; attaching it again is a no-op
+pub async fn attach(pool: &SqlitePool, project_id: &str, agent_id: &str) -> Result<Vec<String>, String> {
+    let mut conn = pool.acquire().await.map_err(|e| format!("Database error: {}", e))?;
+    let position: i64 =
+        sqlx::query_scalar("SELECT COALESCE(MAX(position) + 1, 0) FROM project_agents WHERE project_id = ?")
+            .bind(project_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    insert(&mut *conn, project_id, agent_id, position).await?;
+
+    list(pool, project_id).await.map_err(|e| format!("Database error: {}", e))
+}
+
+/// Remove an agent from a project; `false` if it wasn't attached
+pub async fn detach(pool: &SqlitePool, project_id: &str, agent_id: &str) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let position: Option<i64> =
+        sqlx::query_scalar("DELETE FROM project_agents WHERE project_id = ? AND agent_id = ? RETURNING position")
+            .bind(project_id)
+            .bind(agent_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    let Some(position) = position else {
+        return Ok(false);
+    };
+
+    sqlx::query("UPDATE project_agents SET position = position - 1 WHERE project_id = ? AND position > ?")
+        .bind(project_id)
+        .bind(position)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+async fn insert(conn: &mut SqliteConnection, project_id: &str, agent_id: &str, position: i64) -> Result<(), String> {
+    let known: Option<i64> = sqlx::query_scalar("SELECT 1 FROM agents WHERE id = ?")
+        .bind(agent_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if known.is_none() {
+        return Err(format!("Agent not found: {}", agent_id));
+    }
+
+    sqlx::query("INSERT OR IGNORE INTO project_agents (project_id, agent_id, position) VALUES (?, ?, ?)")
+        .bind(project_id)
+        .bind(agent_id)
+        .bind(position)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to save project agents: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_attach_and_detach() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Site', 'website', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        attach(&pool, "p1", "ui-designer").await.unwrap();
+        attach(&pool, "p1", "backend-architect").await.unwrap();
+        let agents = attach(&pool, "p1", "ui-designer").await.unwrap();
+        assert_eq!(agents, vec!["ui-designer", "backend-architect"]);
+        assert!(attach(&pool, "p1", "time-traveler").await.is_err());
+
+        assert!(detach(&pool, "p1", "ui-designer").await.unwrap());
+        assert!(!detach(&pool, "p1", "ui-designer").await.unwrap());
+        let agents = attach(&pool, "p1", "devops-engineer").await.unwrap();
+        assert_eq!(agents, vec!["backend-architect", "devops-engineer"]);
+
+        let mut conn = pool.acquire().await.unwrap();
+        let order = vec!["devops-engineer".to_string(), "ui-designer".to_string()];
+        set(&mut *conn, "p1", &order).await.unwrap();
+        drop(conn);
+        assert_eq!(list(&pool, "p1").await.unwrap(), order);
+
+        // Deleting the project removes its agents
+        sqlx::query("DELETE FROM projects WHERE id = 'p1'").execute(&pool).await.unwrap();
+        assert!(list(&pool, "p1").await.unwrap().is_empty());
+    }
+}
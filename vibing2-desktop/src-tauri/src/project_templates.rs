@@ -0,0 +1,481 @@
+//! Project templates
+//!
+//! A project template is a starter for a new project: files to begin from,
+//! a first prompt, and the agents to work on it. Built-in templates ship
+//! with the app; users can save their own. Creating a project from one
+//! seeds its files, records the prompt, and makes the agents the project's
+//! active agents (see [`crate::project_agents`]).
+//!
+//! Not to be confused with prompt templates ([`crate::templates`]).
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+/// ID prefix of templates that ship with the app
+const BUILTIN_PREFIX: &str = "builtin-";
+
+/// Most files a user template can hold
+const MAX_FILES: usize = 200;
+
+struct Builtin {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    project_type: &'static str,
+    files: &'static [(&'static str, &'static str)],
+    prompt: &'static str,
+    agents: &'static [&'static str],
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        id: "static-site",
+        name: "Static site",
+        description: "HTML, CSS, and JavaScript with no build step",
+        project_type: "website",
+        files: &[
+            (
+                "index.html",
+                "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n  <meta charset=\"UTF-8\">\n  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n  <title>My Site</title>\n  <link rel=\"stylesheet\" href=\"style.css\">\n</head>\n<body>\n  <header><h1>My Site</h1></header>\n  <main></main>\n  <script src=\"script.js\"></script>\n</body>\n</html>\n",
+            ),
+            (
+                "style.css",
+                "*, *::before, *::after { box-sizing: border-box; }\n\nbody {\n  margin: 0;\n  font-family: system-ui, sans-serif;\n  line-height: 1.5;\n}\n",
+            ),
+            ("script.js", "document.addEventListener('DOMContentLoaded', () => {\n});\n"),
+        ],
+        prompt: "Build out this site with a hero section, a features section, and a footer.",
+        agents: &["ui-designer", "frontend-developer"],
+    },
+    Builtin {
+        id: "canvas-game",
+        name: "Canvas game",
+        description: "A canvas with a game loop and keyboard input",
+        project_type: "game",
+        files: &[
+            (
+                "index.html",
+                "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n  <meta charset=\"UTF-8\">\n  <title>Game</title>\n  <style>body { margin: 0; background: #111; } canvas { display: block; margin: 0 auto; }</style>\n</head>\n<body>\n  <canvas id=\"game\" width=\"800\" height=\"600\"></canvas>\n  <script src=\"game.js\"></script>\n</body>\n</html>\n",
+            ),
+            (
+                "game.js",
+                "const canvas = document.getElementById('game');\nconst ctx = canvas.getContext('2d');\nconst keys = new Set();\n\naddEventListener('keydown', (e) => keys.add(e.key));\naddEventListener('keyup', (e) => keys.delete(e.key));\n\nlet last = performance.now();\nfunction loop(now) {\n  const dt = (now - last) / 1000;\n  last = now;\n  update(dt);\n  draw();\n  requestAnimationFrame(loop);\n}\n\nfunction update(dt) {}\n\nfunction draw() {\n  ctx.clearRect(0, 0, canvas.width, canvas.height);\n}\n\nrequestAnimationFrame(loop);\n",
+            ),
+        ],
+        prompt: "Turn this into a playable game with a player, obstacles, a score, and a restart button.",
+        agents: &["frontend-developer", "performance-engineer"],
+    },
+    Builtin {
+        id: "dashboard",
+        name: "Dashboard",
+        description: "A dashboard layout with sample data",
+        project_type: "dashboard",
+        files: &[
+            (
+                "index.html",
+                "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n  <meta charset=\"UTF-8\">\n  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n  <title>Dashboard</title>\n</head>\n<body>\n  <aside id=\"nav\"></aside>\n  <main>\n    <section id=\"cards\"></section>\n    <section id=\"table\"></section>\n  </main>\n  <script src=\"data.js\"></script>\n  <script src=\"app.js\"></script>\n</body>\n</html>\n",
+            ),
+            (
+                "data.js",
+                "const SAMPLE_DATA = [\n  { month: 'Jan', revenue: 12000, users: 340 },\n  { month: 'Feb', revenue: 15400, users: 410 },\n  { month: 'Mar', revenue: 14100, users: 452 },\n];\n",
+            ),
+            ("app.js", "document.addEventListener('DOMContentLoaded', () => {\n  console.log(SAMPLE_DATA);\n});\n"),
+        ],
+        prompt: "Build a dashboard from the sample data with summary cards, a chart, and a sortable table.",
+        agents: &["ui-ux-designer", "frontend-developer"],
+    },
+    Builtin {
+        id: "rest-api",
+        name: "REST API",
+        description: "A Node.js HTTP server with a health endpoint",
+        project_type: "api",
+        files: &[
+            (
+                "package.json",
+                "{\n  \"name\": \"api\",\n  \"version\": \"0.1.0\",\n  \"private\": true,\n  \"scripts\": {\n    \"start\": \"node server.js\",\n    \"test\": \"node --test\"\n  }\n}\n",
+            ),
+            (
+                "server.js",
+                "const http = require('node:http');\n\nconst server = http.createServer((req, res) => {\n  if (req.method === 'GET' && req.url === '/health') {\n    res.writeHead(200, { 'Content-Type': 'application/json' });\n    return res.end(JSON.stringify({ status: 'ok' }));\n  }\n  res.writeHead(404, { 'Content-Type': 'application/json' });\n  res.end(JSON.stringify({ error: 'Not found' }));\n});\n\nserver.listen(process.env.PORT || 3000);\n",
+            ),
+        ],
+        prompt: "Add CRUD endpoints for a resource with input validation, JSON errors, and tests.",
+        agents: &["backend-architect", "test-automator", "security-auditor"],
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateFile {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub project_type: String,
+    pub files: Vec<TemplateFile>,
+    /// First prompt for projects created from the template
+    pub prompt: Option<String>,
+    /// Agent IDs in execution order
+    pub agents: Vec<String>,
+    /// Ships with the app; can't be deleted
+    pub builtin: bool,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewProjectTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub project_type: String,
+    #[serde(default)]
+    pub files: Vec<TemplateFile>,
+    pub prompt: Option<String>,
+    /// Agent IDs in execution order
+    #[serde(default)]
+    pub agents: Vec<String>,
+}
+
+/// Templates that ship with the app
+pub fn builtin_templates() -> Vec<ProjectTemplate> {
+    BUILTINS
+        .iter()
+        .map(|builtin| ProjectTemplate {
+            id: format!("{}{}", BUILTIN_PREFIX, builtin.id),
+            name: builtin.name.to_string(),
+            description: Some(builtin.description.to_string()),
+            project_type: builtin.project_type.to_string(),
+            files: builtin
+                .files
+                .iter()
+                .map(|(path, content)| TemplateFile {
+                    path: path.to_string(),
+                    content: content.to_string(),
+                })
+                .collect(),
+            prompt: Some(builtin.prompt.to_string()),
+            agents: builtin.agents.iter().map(|id| id.to_string()).collect(),
+            builtin: true,
+            created_at: None,
+        })
+        .collect()
+}
+
+/// Built-in templates plus the user's own, optionally for one project type
+pub async fn list(
+    pool: &SqlitePool,
+    user_id: &str,
+    project_type: Option<&str>,
+) -> Result<Vec<ProjectTemplate>, sqlx::Error> {
+    let mut templates: Vec<ProjectTemplate> = builtin_templates()
+        .into_iter()
+        .filter(|template| project_type.is_none_or(|project_type| template.project_type == project_type))
+        .collect();
+
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, description, project_type, files, prompt, agents, created_at
+        FROM project_templates
+        WHERE user_id = ? AND (? IS NULL OR project_type = ?)
+        ORDER BY name
+        "#,
+    )
+    .bind(user_id)
+    .bind(project_type)
+    .bind(project_type)
+    .fetch_all(pool)
+    .await?;
+
+    templates.extend(rows.iter().map(template_from_row));
+    Ok(templates)
+}
+
+/// A built-in template or one of the user's own
+pub async fn get(pool: &SqlitePool, user_id: &str, id: &str) -> Result<Option<ProjectTemplate>, sqlx::Error> {
+    if id.starts_with(BUILTIN_PREFIX) {
+        return Ok(builtin_templates().into_iter().find(|template| template.id == id));
+    }
+
+    let row = sqlx::query(
+        r#"
+        SELECT id, name, description, project_type, files, prompt, agents, created_at
+        FROM project_templates
+        WHERE id = ? AND user_id = ?
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(template_from_row))
+}
+
+/// A file path inside the project, with `/` separators
+fn clean_path(path: &str) -> Result<String, String> {
+    let relative = crate::sandbox::relative_path(path)?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if parts.is_empty() {
+        return Err("File path is required".to_string());
+    }
+    Ok(parts.join("/"))
+}
+
+/// Save a new template for `user_id`
+pub async fn create(pool: &SqlitePool, user_id: &str, template: NewProjectTemplate) -> Result<ProjectTemplate, String> {
+    let name = template.name.trim();
+    if name.is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    let project_type = template.project_type.trim();
+    if project_type.is_empty() {
+        return Err("Project type is required".to_string());
+    }
+    if template.files.len() > MAX_FILES {
+        return Err(format!("A template can hold at most {} files", MAX_FILES));
+    }
+
+    let mut files: Vec<TemplateFile> = Vec::with_capacity(template.files.len());
+    for file in template.files {
+        let path = clean_path(&file.path)?;
+        if files.iter().any(|seen| seen.path == path) {
+            return Err(format!("{} is in the template twice", path));
+        }
+        files.push(TemplateFile { path, content: file.content });
+    }
+    for agent_id in &template.agents {
+        let exists = crate::agents::get(pool, agent_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some();
+        if !exists {
+            return Err(format!("Agent not found: {}", agent_id));
+        }
+    }
+    let prompt = template.prompt.as_deref().map(str::trim).filter(|prompt| !prompt.is_empty());
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        r#"
+        INSERT INTO project_templates (id, user_id, name, description, project_type, files, prompt, agents)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(name)
+    .bind(&template.description)
+    .bind(project_type)
+    .bind(serde_json::to_string(&files).unwrap_or_else(|_| "[]".to_string()))
+    .bind(prompt)
+    .bind(serde_json::to_string(&template.agents).unwrap_or_else(|_| "[]".to_string()))
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save template: {}", e))?;
+
+    get(pool, user_id, &id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| "Template was not saved".to_string())
+}
+
+/// Delete one of the user's templates; projects created from it are kept
+pub async fn delete(pool: &SqlitePool, user_id: &str, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM project_templates WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Create a project named `name` from a template and return its ID
+///
+/// Agents the template names that no longer exist are left out.
+pub async fn create_project(pool: &SqlitePool, user_id: &str, template_id: &str, name: &str) -> Result<String, String> {
+    let template = get(pool, user_id, template_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Template not found: {}", template_id))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Project name is required".to_string());
+    }
+
+    let mut agents = Vec::with_capacity(template.agents.len());
+    for agent_id in template.agents {
+        let exists = crate::agents::get(pool, &agent_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some();
+        if exists {
+            agents.push(agent_id);
+        }
+    }
+
+    let project_id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    sqlx::query(
+        r#"
+        INSERT INTO projects (id, name, description, project_type, prompt, user_id, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&project_id)
+    .bind(name)
+    .bind(&template.description)
+    .bind(&template.project_type)
+    .bind(&template.prompt)
+    .bind(user_id)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to create project: {}", e))?;
+
+    for file in &template.files {
+        sqlx::query(
+            r#"
+            INSERT INTO project_files (id, project_id, path, content, language)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&project_id)
+        .bind(&file.path)
+        .bind(&file.content)
+        .bind(crate::agent_tools::language_for(&file.path))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to create project: {}", e))?;
+    }
+
+    crate::project_agents::set(&mut *tx, &project_id, &agents).await?;
+    tx.commit().await.map_err(|e| format!("Failed to create project: {}", e))?;
+    Ok(project_id)
+}
+
+fn template_from_row(row: &sqlx::sqlite::SqliteRow) -> ProjectTemplate {
+    let files: String = row.get("files");
+    let agents: String = row.get("agents");
+    ProjectTemplate {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        project_type: row.get("project_type"),
+        files: serde_json::from_str(&files).unwrap_or_default(),
+        prompt: row.get("prompt"),
+        agents: serde_json::from_str(&agents).unwrap_or_default(),
+        builtin: false,
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_builtins_reference_bundled_agents() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        for template in builtin_templates() {
+            assert!(!template.files.is_empty(), "{} has no files", template.id);
+            for agent_id in &template.agents {
+                let agent = crate::agents::get(&pool, agent_id).await.unwrap();
+                assert!(agent.is_some(), "{} names unknown agent {}", template.id, agent_id);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_project_from_template() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let project_id = create_project(&pool, "local-user", "builtin-static-site", "  Portfolio ")
+            .await
+            .unwrap();
+        let row = sqlx::query("SELECT name, project_type, prompt FROM projects WHERE id = ?")
+            .bind(&project_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<String, _>("name"), "Portfolio");
+        assert_eq!(row.get::<String, _>("project_type"), "website");
+        assert!(row.get::<Option<String>, _>("prompt").is_some());
+
+        let paths: Vec<String> = sqlx::query_scalar("SELECT path FROM project_files WHERE project_id = ? ORDER BY path")
+            .bind(&project_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(paths, vec!["index.html", "script.js", "style.css"]);
+        assert_eq!(
+            crate::project_agents::list(&pool, &project_id).await.unwrap(),
+            vec!["ui-designer", "frontend-developer"]
+        );
+
+        assert!(create_project(&pool, "local-user", "builtin-missing", "X").await.is_err());
+        assert!(create_project(&pool, "local-user", "builtin-static-site", " ").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_user_templates() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        let new_template = |path: &str, agents: &[&str]| NewProjectTemplate {
+            name: "Blog".to_string(),
+            description: None,
+            project_type: "website".to_string(),
+            files: vec![TemplateFile {
+                path: path.to_string(),
+                content: "# Hello".to_string(),
+            }],
+            prompt: Some("Write three posts".to_string()),
+            agents: agents.iter().map(|id| id.to_string()).collect(),
+        };
+
+        assert!(create(&pool, "local-user", new_template("../escape.md", &[])).await.is_err());
+        assert!(create(&pool, "local-user", new_template("post.md", &["no-such-agent"])).await.is_err());
+
+        let template = create(&pool, "local-user", new_template("./posts/first.md", &["frontend-developer"]))
+            .await
+            .unwrap();
+        assert_eq!(template.files[0].path, "posts/first.md");
+        assert_eq!(template.agents, vec!["frontend-developer"]);
+
+        let websites = list(&pool, "local-user", Some("website")).await.unwrap();
+        assert!(websites.iter().any(|t| t.id == template.id));
+        assert!(list(&pool, "local-user", Some("game")).await.unwrap().iter().all(|t| t.builtin));
+
+        let project_id = create_project(&pool, "local-user", &template.id, "My blog").await.unwrap();
+        let content: String = sqlx::query_scalar("SELECT content FROM project_files WHERE project_id = ?")
+            .bind(&project_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(content, "# Hello");
+
+        // Other users can't see or delete it
+        assert!(get(&pool, "someone-else", &template.id).await.unwrap().is_none());
+        assert!(!delete(&pool, "someone-else", &template.id).await.unwrap());
+        assert!(delete(&pool, "local-user", &template.id).await.unwrap());
+    }
+}
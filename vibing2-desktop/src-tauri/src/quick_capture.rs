@@ -0,0 +1,203 @@
+//! Quick prompt capture
+//!
+//! A small always-on-top window, opened from the tray or with a global
+//! shortcut, where the user types a prompt. [`submit`] turns the prompt into
+//! a new project, starts generating for it, and opens the main window on
+//! the project. Generation events are emitted as `quick-capture-event` so
+//! the main window can follow the reply as it streams in.
+
+use futures::StreamExt;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use tauri::{Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::generation::{GenerationEvent, StreamRequest};
+use crate::llm::Priority;
+use crate::sharing::Principal;
+
+/// Label of the quick-capture window
+pub const WINDOW_LABEL: &str = "quick-capture";
+
+/// Global shortcut that opens the quick-capture window
+pub const SHORTCUT: &str = "CommandOrControl+Shift+Space";
+
+/// Longest project name taken from a prompt
+const MAX_NAME_CHARS: usize = 60;
+
+/// A generation event for a project started from the quick-capture window
+#[derive(Debug, Serialize)]
+pub struct QuickCaptureEvent {
+    pub project_id: String,
+    #[serde(flatten)]
+    pub event: GenerationEvent,
+}
+
+/// Show the quick-capture window, creating it on first use
+pub fn open(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+
+    let window = WebviewWindowBuilder::new(app, WINDOW_LABEL, WebviewUrl::App("/quick-capture".into()))
+        .title("Quick Prompt")
+        .inner_size(560.0, 160.0)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .focused(true)
+        .build();
+    if let Err(e) = window {
+        eprintln!("Failed to open quick capture window: {}", e);
+    }
+}
+
+/// Start a new project from a captured prompt
+///
+/// Creates the project, starts generating with `agent_id` (or no agent),
+/// hides the quick-capture window, and shows the main window with
+/// `load-project`. Returns the new project's ID once the generation has
+/// started; the reply is saved to the project as it finishes.
+pub async fn submit(app: &tauri::AppHandle, prompt: String, agent_id: Option<String>) -> Result<String, String> {
+    let prompt = prompt.trim().to_string();
+    if prompt.is_empty() {
+        return Err("Type a prompt first".to_string());
+    }
+
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let agent_id = agent_id.filter(|id| !id.is_empty());
+    let project_id = create_project(pool.as_ref(), &prompt, agent_id.as_deref()).await?;
+
+    let request = StreamRequest {
+        prompt,
+        agent_id,
+        provider: None,
+        model: None,
+        project_id: Some(project_id.clone()),
+        files: None,
+        context: None,
+        tools: false,
+        template_id: None,
+        variables: HashMap::new(),
+        response_format: None,
+        priority: Priority::Interactive,
+        session_id: Some(uuid::Uuid::new_v4().to_string()),
+        system_prompt: None,
+        max_tokens: None,
+    };
+    let mut events = crate::generation::start(pool.as_ref(), Principal::desktop(), request, futures::future::pending())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let handle = app.clone();
+    let id = project_id.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = events.next().await {
+            let _ = handle.emit(
+                "quick-capture-event",
+                QuickCaptureEvent {
+                    project_id: id.clone(),
+                    event,
+                },
+            );
+        }
+    });
+
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.hide();
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        let _ = window.emit("load-project", project_id.clone());
+    }
+    if let Err(e) = crate::tray::update_tray_menu(app) {
+        eprintln!("Failed to update tray menu: {}", e);
+    }
+
+    Ok(project_id)
+}
+
+/// Insert a project for `prompt`, named after its first line
+async fn create_project(pool: &SqlitePool, prompt: &str, agent_id: Option<&str>) -> Result<String, String> {
+    let project_id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO projects (id, name, prompt, project_type, user_id, created_at, updated_at)
+         VALUES (?, ?, ?, 'web', 'local-user', ?, ?)",
+    )
+    .bind(&project_id)
+    .bind(project_name(prompt))
+    .bind(prompt)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to create project: {}", e))?;
+
+    let agent_ids: Vec<String> = agent_id.into_iter().map(str::to_string).collect();
+    crate::project_agents::set(&mut *tx, &project_id, &agent_ids).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+    Ok(project_id)
+}
+
+/// The prompt's first line, shortened to fit a project name
+fn project_name(prompt: &str) -> String {
+    let line = prompt.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("Quick prompt");
+    if line.chars().count() <= MAX_NAME_CHARS {
+        return line.to_string();
+    }
+    let mut name: String = line.chars().take(MAX_NAME_CHARS - 1).collect();
+    name.truncate(name.trim_end().len());
+    name.push('…');
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_project_name() {
+        assert_eq!(project_name("\n  Landing page for a bakery\nwith a menu"), "Landing page for a bakery");
+        assert_eq!(project_name("   "), "Quick prompt");
+
+        let long = "Build a dashboard ".repeat(10);
+        let name = project_name(&long);
+        assert_eq!(name.chars().count(), MAX_NAME_CHARS);
+        assert!(name.ends_with("Build…"));
+    }
+
+    #[tokio::test]
+    async fn test_create_project() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let project_id = create_project(&pool, "A portfolio site", Some("ui-designer")).await.unwrap();
+        let (name, prompt): (String, String) = sqlx::query_as("SELECT name, prompt FROM projects WHERE id = ?")
+            .bind(&project_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(name, "A portfolio site");
+        assert_eq!(prompt, "A portfolio site");
+        assert_eq!(crate::project_agents::list(&pool, &project_id).await.unwrap(), vec!["ui-designer"]);
+
+        // An unknown agent leaves no project behind
+        assert!(create_project(&pool, "A blog", Some("missing-agent")).await.is_err());
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects").fetch_one(&pool).await.unwrap();
+        assert_eq!(count, 1);
+    }
+}
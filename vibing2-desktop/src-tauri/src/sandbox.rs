@@ -0,0 +1,468 @@
+//! Command execution in project workspaces
+//!
+//! Builds and tests run in a project's workspace: a folder next to the
+//! database holding a copy of the project's files, refreshed before every
+//! command. A command is one program and its arguments, started without a
+//! shell, in the workspace or a folder inside it. Arguments can't name
+//! paths outside the workspace, a denylist keeps out privilege escalation,
+//! shells, and system tools, and the environment is reduced to what builds
+//! need, so credentials in it don't leak. Commands are killed after their
+//! timeout and their output is capped.
+//!
+//! This is not isolation. Commands run as the user, and interpreters
+//! (`node -e`, `python -c`) or programs that start others (`env`, `xargs`,
+//! `find -exec`) can do anything the user can. The checks catch mistakes,
+//! not a hostile model, which is why agents only run commands the user
+//! approves one at a time (see `tool_policy`).
+
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+pub const MAX_TIMEOUT_SECS: u64 = 600;
+
+/// Output kept per stream; the rest is read and dropped
+pub const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// How long to wait for output after a command exits or is killed, in case
+/// a process it started still holds the pipe
+const OUTPUT_GRACE: Duration = Duration::from_secs(2);
+
+/// Programs commands can't start, matched on the file name without extension
+const DENIED_PROGRAMS: &[&str] = &[
+    // Privilege escalation
+    "sudo", "su", "doas", "pkexec", "runas",
+    // Shells, which would get around the rest of the checks
+    "sh", "bash", "zsh", "fish", "dash", "ksh", "csh", "tcsh", "cmd", "powershell", "pwsh",
+    // System administration
+    "shutdown", "reboot", "halt", "poweroff", "launchctl", "systemctl", "crontab", "mkfs", "dd", "diskutil",
+    "chown", "kill", "killall", "pkill", "taskkill", "osascript",
+    // Remote access
+    "ssh", "scp", "sftp", "rsync", "telnet", "nc", "ncat", "netcat",
+];
+
+/// Folders project files can't be in: the repository and the app's manifest
+const RESERVED_DIRS: &[&str] = &[".git", ".vibing2"];
+
+/// Environment variables commands inherit; everything else is dropped
+const ENV_PASSTHROUGH: &[&str] = &[
+    "PATH", "PATHEXT", "LANG", "LC_ALL", "TERM", "TMPDIR", "TEMP", "TMP", "SYSTEMROOT", "COMSPEC",
+];
+
+#[derive(Debug, Clone)]
+pub struct CommandRequest {
+    /// Program and arguments, e.g. `npm test`; quotes group words
+    pub command: String,
+    /// Folder to run in, relative to the workspace
+    pub cwd: Option<String>,
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOutput {
+    pub command: String,
+    /// `None` if the command was killed
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,
+    /// Output past `MAX_OUTPUT_BYTES` was dropped
+    pub truncated: bool,
+    pub duration_ms: u64,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// The outcome and output as text, for the model
+    pub fn summary(&self) -> String {
+        let status = match (self.timed_out, self.exit_code) {
+            (true, _) => format!("timed out after {} ms", self.duration_ms),
+            (false, Some(code)) => format!("exited with code {}", code),
+            (false, None) => "was killed".to_string(),
+        };
+        let mut summary = format!("`{}` {}", self.command, status);
+        if !self.stdout.is_empty() {
+            summary.push_str(&format!("\n\nstdout:\n{}", self.stdout));
+        }
+        if !self.stderr.is_empty() {
+            summary.push_str(&format!("\n\nstderr:\n{}", self.stderr));
+        }
+        if self.truncated {
+            summary.push_str(&format!("\n\n(output truncated to {} KB per stream)", MAX_OUTPUT_BYTES / 1024));
+        }
+        summary
+    }
+}
+
+/// Where a project's workspace lives, next to the database
+pub fn workspace_dir(project_id: &str) -> Result<PathBuf, String> {
+    let valid = !project_id.is_empty()
+        && project_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid project id: {}", project_id));
+    }
+
+    crate::database::get_db_path()
+        .parent()
+        .map(|dir| dir.join("workspaces").join(project_id))
+        .ok_or_else(|| "No data directory for workspaces".to_string())
+}
+
+/// A relative path of plain components, rejecting absolute paths, `..`,
+/// and the `.git` and `.vibing2` folders
+pub fn relative_path(relative: &str) -> Result<PathBuf, String> {
+    let relative = relative.trim().trim_start_matches("./");
+    let mut path = PathBuf::new();
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(part)
+                if RESERVED_DIRS
+                    .iter()
+                    .any(|reserved| part.to_string_lossy().eq_ignore_ascii_case(reserved)) =>
+            {
+                return Err(format!("Path is reserved: {}", relative));
+            }
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("Path is outside the project: {}", relative)),
+        }
+    }
+    Ok(path)
+}
+
+/// A path inside `root`, rejecting absolute paths, `..`, and symlinks
+///
+/// A symlink below `root`, such as one a command created, could lead
+/// anywhere, so a path through one is refused.
+pub fn jailed(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let mut path = root.to_path_buf();
+    for part in relative_path(relative)?.components() {
+        path.push(part);
+        if std::fs::symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_symlink()) {
+            return Err(format!("Path goes through a symlink: {}", relative.trim()));
+        }
+    }
+    Ok(path)
+}
+
+/// Write a project's files into its workspace and return the workspace
+///
+/// Files the command created earlier (build output, dependencies) are kept.
+pub async fn materialize(pool: &SqlitePool, project_id: &str) -> Result<PathBuf, String> {
+    let root = workspace_dir(project_id)?;
+    let rows = sqlx::query("SELECT path, content FROM project_files WHERE project_id = ?")
+        .bind(project_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    tokio::fs::create_dir_all(&root)
+        .await
+        .map_err(|e| format!("Failed to create workspace: {}", e))?;
+    for row in rows {
+        let path: String = row.get("path");
+        let content: String = row.get("content");
+        let file = jailed(&root, &path)?;
+
+        if tokio::fs::read(&file).await.is_ok_and(|current| current == content.as_bytes()) {
+            continue;
+        }
+        if let Some(parent) = file.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        }
+        tokio::fs::write(&file, content)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(root)
+}
+
+/// Split a command line into words, honoring single and double quotes
+///
+/// Shell operators are rejected rather than passed on as arguments, since no
+/// shell runs the command.
+pub fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+
+    for c in command.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None => match c {
+                '\'' | '"' => {
+                    quote = Some(c);
+                    in_word = true;
+                }
+                '|' | '&' | ';' | '<' | '>' | '`' | '$' => {
+                    return Err(format!(
+                        "Shell syntax ({}) isn't supported; run one command at a time",
+                        c
+                    ));
+                }
+                c if c.is_whitespace() => {
+                    if in_word {
+                        words.push(std::mem::take(&mut word));
+                        in_word = false;
+                    }
+                }
+                c => {
+                    word.push(c);
+                    in_word = true;
+                }
+            },
+        }
+    }
+    if quote.is_some() {
+        return Err("Unclosed quote in command".to_string());
+    }
+    if in_word {
+        words.push(word);
+    }
+    if words.is_empty() {
+        return Err("command is required".to_string());
+    }
+    Ok(words)
+}
+
+/// Reject denied programs and arguments naming paths outside the workspace
+pub fn check_words(words: &[String]) -> Result<(), String> {
+    let program = Path::new(&words[0])
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if DENIED_PROGRAMS.contains(&program.as_str()) {
+        return Err(format!("{} isn't allowed", program));
+    }
+
+    for word in words {
+        // Covers `--out=/tmp` as well as `/tmp`
+        let value = word.split_once('=').map_or(word.as_str(), |(_, value)| value);
+        for candidate in [word.as_str(), value] {
+            let escapes = candidate.starts_with('~')
+                || candidate.starts_with('/')
+                || candidate.starts_with('\\')
+                || Path::new(candidate).is_absolute()
+                || candidate.split(['/', '\\']).any(|segment| segment == "..");
+            if escapes {
+                return Err(format!("Paths outside the project aren't allowed: {}", word));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run a command in `root`, a project workspace
+///
+/// A command that fails or times out still returns its output; errors are
+/// for commands that were refused or couldn't start.
+pub async fn run(root: &Path, request: &CommandRequest) -> Result<CommandOutput, String> {
+    let words = split_command(&request.command)?;
+    check_words(&words)?;
+
+    let dir = jailed(root, request.cwd.as_deref().unwrap_or_default())?;
+    if !dir.is_dir() {
+        return Err(format!("No such folder in the project: {}", request.cwd.as_deref().unwrap_or_default()));
+    }
+    let timeout = Duration::from_secs(request.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).clamp(1, MAX_TIMEOUT_SECS));
+
+    let mut command = tokio::process::Command::new(&words[0]);
+    command
+        .args(&words[1..])
+        .current_dir(&dir)
+        .env_clear()
+        .env("HOME", root)
+        .env("USERPROFILE", root)
+        .env("CI", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    for name in ENV_PASSTHROUGH {
+        if let Some(value) = std::env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+
+    let started = Instant::now();
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", words[0], e))?;
+    let stdout = tokio::spawn(read_capped(child.stdout.take().expect("stdout is piped")));
+    let stderr = tokio::spawn(read_capped(child.stderr.take().expect("stderr is piped")));
+
+    let (exit_code, timed_out) = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => (status.map_err(|e| format!("Failed to run {}: {}", words[0], e))?.code(), false),
+        Err(_) => {
+            let _ = child.kill().await;
+            (None, true)
+        }
+    };
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let (stdout, stdout_truncated) = collect(stdout).await;
+    let (stderr, stderr_truncated) = collect(stderr).await;
+
+    Ok(CommandOutput {
+        command: request.command.trim().to_string(),
+        exit_code,
+        stdout,
+        stderr,
+        timed_out,
+        truncated: stdout_truncated || stderr_truncated,
+        duration_ms,
+    })
+}
+
+/// Read a stream to the end, keeping the first `MAX_OUTPUT_BYTES`
+async fn read_capped(mut reader: impl AsyncRead + Unpin) -> (String, bool) {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 8192];
+
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let room = MAX_OUTPUT_BYTES - kept.len();
+                if n > room {
+                    truncated = true;
+                }
+                kept.extend_from_slice(&buf[..n.min(room)]);
+            }
+        }
+    }
+    (String::from_utf8_lossy(&kept).into_owned(), truncated)
+}
+
+async fn collect(reader: tokio::task::JoinHandle<(String, bool)>) -> (String, bool) {
+    match tokio::time::timeout(OUTPUT_GRACE, reader).await {
+        Ok(Ok(output)) => output,
+        _ => (String::new(), false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(command: &str) -> CommandRequest {
+        CommandRequest {
+            command: command.to_string(),
+            cwd: None,
+            timeout_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_split_and_check_command() {
+        assert_eq!(
+            split_command(r#"npm run "build:prod" -- --name 'a b'"#).unwrap(),
+            vec!["npm", "run", "build:prod", "--", "--name", "a b"]
+        );
+        assert!(split_command("npm test | tee log").is_err());
+        assert!(split_command("echo $HOME").is_err());
+        assert!(split_command("echo 'open").is_err());
+        assert!(split_command("   ").is_err());
+
+        let check = |command: &str| check_words(&split_command(command).unwrap());
+        assert!(check("cargo test --release").is_ok());
+        assert!(check("./gradlew build").is_ok());
+        assert!(check("sudo npm install").is_err());
+        assert!(check("/bin/bash -c ls").is_err());
+        assert!(check("cat ../other/secrets.txt").is_err());
+        assert!(check("cat ~/.ssh/id_rsa").is_err());
+        assert!(check("tsc --outDir=/tmp/out").is_err());
+    }
+
+    #[test]
+    fn test_jailed() {
+        let root = Path::new("/workspaces/p1");
+        assert_eq!(jailed(root, "src/main.rs").unwrap(), root.join("src").join("main.rs"));
+        assert_eq!(jailed(root, "").unwrap(), root);
+        assert!(jailed(root, "../p2").is_err());
+        assert!(jailed(root, "/etc").is_err());
+        assert!(jailed(root, ".git/hooks/pre-commit").is_err());
+        assert!(jailed(root, "src/.GIT/config").is_err());
+        assert!(jailed(root, ".vibing2/manifest.json").is_err());
+        assert!(jailed(root, ".github/workflows/ci.yml").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_jailed_refuses_symlinks() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("src")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.path().join("link")).unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", root.path().join("src").join("passwd")).unwrap();
+
+        assert!(jailed(root.path(), "src/main.rs").is_ok());
+        assert!(jailed(root.path(), "link").is_err());
+        assert!(jailed(root.path(), "link/file.txt").is_err());
+        assert!(jailed(root.path(), "src/passwd").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_in_workspace() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("src")).unwrap();
+        std::fs::write(root.path().join("src").join("index.js"), "").unwrap();
+
+        let output = run(root.path(), &request("echo 'hello world'")).await.unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout, "hello world\n");
+
+        let listed = run(
+            root.path(),
+            &CommandRequest {
+                cwd: Some("src".to_string()),
+                ..request("ls")
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(listed.stdout, "index.js\n");
+
+        let failed = run(root.path(), &request("ls missing")).await.unwrap();
+        assert!(!failed.success());
+        assert!(!failed.stderr.is_empty());
+
+        assert!(run(root.path(), &CommandRequest { cwd: Some("..".to_string()), ..request("ls") }).await.is_err());
+        assert!(run(root.path(), &request("no-such-program-here")).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_and_output_cap() {
+        let root = tempfile::tempdir().unwrap();
+        let output = run(
+            root.path(),
+            &CommandRequest {
+                timeout_secs: Some(1),
+                ..request("yes")
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(output.timed_out);
+        assert_eq!(output.exit_code, None);
+        assert!(output.truncated);
+        assert_eq!(output.stdout.len(), MAX_OUTPUT_BYTES);
+    }
+}
@@ -0,0 +1,345 @@
+//! Writing projects to disk
+//!
+//! A project is written to a folder of its own, by default under the
+//! `default_project_path` setting and named after the project. The folder
+//! keeps a manifest (`.vibing2/manifest.json`) of what was last written, so
+//! later writes only touch files that changed:
+//!
+//! - Files unchanged on disk since the last write are updated
+//! - Files edited on disk are conflicts: kept unless the caller overwrites
+//! - Files removed from the project are deleted unless edited on disk
+//!
+//! The project's `current_code` is written as `index.html` unless the
+//! project has a file there already.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The manifest, relative to the project folder
+pub const MANIFEST_PATH: &str = ".vibing2/manifest.json";
+
+/// Where `current_code` is written
+pub(crate) const CURRENT_CODE_PATH: &str = "index.html";
+
+/// What was last written to a project folder
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub project_id: String,
+    /// SHA-256 of each written file, by path
+    pub files: BTreeMap<String, String>,
+    pub written_at: String,
+}
+
+/// What to do with files edited on disk that the project also changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Leave the edited file; it stays a conflict until resolved
+    #[default]
+    Keep,
+    /// Replace it with the project's version
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaterializeReport {
+    pub root: String,
+    pub written: Vec<String>,
+    pub unchanged: usize,
+    pub deleted: Vec<String>,
+    /// Files edited on disk that weren't overwritten
+    pub conflicts: Vec<String>,
+}
+
+pub(crate) fn hash(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Expand a leading `~` to the home folder
+pub fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest.trim_start_matches(['/', '\\']),
+        _ => return PathBuf::from(path),
+    };
+    match dirs::home_dir() {
+        Some(home) if rest.is_empty() => home,
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Read a folder's manifest; `None` if it has none or it can't be read
+pub async fn read_manifest(root: &Path) -> Option<Manifest> {
+    let json = tokio::fs::read_to_string(root.join(MANIFEST_PATH)).await.ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub(crate) async fn write_manifest(root: &Path, manifest: &Manifest) -> Result<(), String> {
+    let path = root.join(MANIFEST_PATH);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to write manifest: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| format!("Failed to write manifest: {}", e))?;
+    tokio::fs::write(&path, json)
+        .await
+        .map_err(|e| format!("Failed to write manifest: {}", e))
+}
+
+/// A project's files by path, with `current_code` as `index.html`
+pub async fn project_files(pool: &SqlitePool, project_id: &str) -> Result<BTreeMap<String, String>, String> {
+    let current_code: Option<Option<String>> = sqlx::query_scalar("SELECT current_code FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let Some(current_code) = current_code else {
+        return Err(format!("Project not found: {}", project_id));
+    };
+
+    let rows = sqlx::query("SELECT path, content FROM project_files WHERE project_id = ?")
+        .bind(project_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let mut files: BTreeMap<String, String> = rows
+        .iter()
+        .map(|row| (row.get::<String, _>("path"), row.get::<String, _>("content")))
+        .collect();
+
+    if let Some(code) = current_code.filter(|code| !code.trim().is_empty()) {
+        files.entry(CURRENT_CODE_PATH.to_string()).or_insert(code);
+    }
+    Ok(files)
+}
+
+/// Where a project is written when no folder is given
+///
+/// The folder it was last written to, or a new one named after it under
+/// `default_project_path`. A name already taken by another project's folder
+/// gets the project ID appended.
+pub async fn default_target(pool: &SqlitePool, project_id: &str) -> Result<PathBuf, String> {
+    let row = sqlx::query("SELECT name, local_path FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Project not found: {}", project_id))?;
+    if let Some(local_path) = row.get::<Option<String>, _>("local_path") {
+        return Ok(PathBuf::from(local_path));
+    }
+
+    let base: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'default_project_path'")
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let base = expand_home(base.as_deref().unwrap_or("~/Documents/Vibing2Projects"));
+
+    let name: String = row.get("name");
+    let slug = Some(crate::agent_files::slug(&name))
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| "project".to_string());
+    let root = base.join(&slug);
+    match read_manifest(&root).await {
+        Some(manifest) if manifest.project_id != project_id => {
+            let suffix: String = project_id.chars().take(8).collect();
+            Ok(base.join(format!("{}-{}", slug, suffix)))
+        }
+        _ => Ok(root),
+    }
+}
+
+/// Write a project's files to `root`, touching only what changed since the
+/// last write there
+///
+/// Refuses a folder another project was written to.
+pub async fn materialize(
+    pool: &SqlitePool,
+    project_id: &str,
+    root: &Path,
+    policy: ConflictPolicy,
+) -> Result<MaterializeReport, String> {
+    let files = project_files(pool, project_id).await?;
+    let previous = read_manifest(root).await.unwrap_or_default();
+    if !previous.project_id.is_empty() && previous.project_id != project_id {
+        return Err(format!(
+            "{} already holds another project ({})",
+            root.display(),
+            previous.project_id
+        ));
+    }
+
+    tokio::fs::create_dir_all(root)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", root.display(), e))?;
+
+    let mut report = MaterializeReport {
+        root: root.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    let mut manifest = Manifest {
+        project_id: project_id.to_string(),
+        ..Default::default()
+    };
+
+    for (path, content) in &files {
+        let file = crate::sandbox::jailed(root, path)?;
+        let wanted = hash(content.as_bytes());
+        let recorded = previous.files.get(path);
+
+        let write = match tokio::fs::read(&file).await {
+            Err(_) => true,
+            Ok(current) => {
+                let current = hash(&current);
+                if current == wanted {
+                    report.unchanged += 1;
+                    false
+                } else if recorded == Some(&current) || policy == ConflictPolicy::Overwrite {
+                    true
+                } else {
+                    // Still a conflict next time, until the file is resolved
+                    report.conflicts.push(path.clone());
+                    if let Some(recorded) = recorded {
+                        manifest.files.insert(path.clone(), recorded.clone());
+                    }
+                    continue;
+                }
+            }
+        };
+
+        if write {
+            if let Some(parent) = file.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            }
+            tokio::fs::write(&file, content)
+                .await
+                .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            report.written.push(path.clone());
+        }
+        manifest.files.insert(path.clone(), wanted);
+    }
+
+    // Files removed from the project since the last write
+    for (path, recorded) in &previous.files {
+        if files.contains_key(path) {
+            continue;
+        }
+        let Ok(file) = crate::sandbox::jailed(root, path) else {
+            continue;
+        };
+        // Edited on disk: left in place and no longer tracked
+        if tokio::fs::read(&file).await.is_ok_and(|current| &hash(&current) == recorded) {
+            tokio::fs::remove_file(&file)
+                .await
+                .map_err(|e| format!("Failed to delete {}: {}", path, e))?;
+            report.deleted.push(path.clone());
+        }
+    }
+
+    manifest.written_at = chrono::Utc::now().to_rfc3339();
+    write_manifest(root, &manifest).await?;
+
+    sqlx::query("UPDATE projects SET local_path = ? WHERE id = ?")
+        .bind(&report.root)
+        .bind(project_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    async fn save(pool: &SqlitePool, path: &str, content: &str) {
+        crate::agent_tools::save_file(pool, "p1", path, content).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_incremental_materialize() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, current_code, user_id) VALUES ('p1', 'Site', 'website', '<h1>Hi</h1>', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        save(&pool, "src/app.js", "console.log(1)").await;
+        save(&pool, "style.css", "body {}").await;
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("site");
+
+        let report = materialize(&pool, "p1", &root, ConflictPolicy::Keep).await.unwrap();
+        assert_eq!(report.written, vec!["index.html", "src/app.js", "style.css"]);
+        assert_eq!(std::fs::read_to_string(root.join("src/app.js")).unwrap(), "console.log(1)");
+        assert_eq!(read_manifest(&root).await.unwrap().files.len(), 3);
+        assert_eq!(default_target(&pool, "p1").await.unwrap(), root);
+
+        let report = materialize(&pool, "p1", &root, ConflictPolicy::Keep).await.unwrap();
+        assert!(report.written.is_empty());
+        assert_eq!(report.unchanged, 3);
+
+        // Edited on disk and in the project: a conflict
+        std::fs::write(root.join("style.css"), "body { color: red }").unwrap();
+        save(&pool, "style.css", "body { margin: 0 }").await;
+        save(&pool, "src/app.js", "console.log(2)").await;
+        let report = materialize(&pool, "p1", &root, ConflictPolicy::Keep).await.unwrap();
+        assert_eq!(report.written, vec!["src/app.js"]);
+        assert_eq!(report.conflicts, vec!["style.css"]);
+        assert_eq!(std::fs::read_to_string(root.join("style.css")).unwrap(), "body { color: red }");
+
+        let report = materialize(&pool, "p1", &root, ConflictPolicy::Overwrite).await.unwrap();
+        assert_eq!(report.written, vec!["style.css"]);
+        assert_eq!(std::fs::read_to_string(root.join("style.css")).unwrap(), "body { margin: 0 }");
+
+        // Removed from the project: deleted unless edited on disk
+        sqlx::query("DELETE FROM project_files WHERE project_id = 'p1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        std::fs::write(root.join("style.css"), "body {}").unwrap();
+        let report = materialize(&pool, "p1", &root, ConflictPolicy::Keep).await.unwrap();
+        assert_eq!(report.deleted, vec!["src/app.js"]);
+        assert!(root.join("style.css").exists());
+        assert_eq!(read_manifest(&root).await.unwrap().files.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_another_projects_folder() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        for id in ["p1", "p2"] {
+            sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES (?, 'Site', 'website', 'local-user')")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+
+        materialize(&pool, "p1", dir.path(), ConflictPolicy::Keep).await.unwrap();
+        assert!(materialize(&pool, "p2", dir.path(), ConflictPolicy::Keep).await.is_err());
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(expand_home("/srv/projects"), PathBuf::from("/srv/projects"));
+        assert_eq!(expand_home("~user/projects"), PathBuf::from("~user/projects"));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(expand_home("~"), home);
+            assert_eq!(expand_home("~/Documents"), home.join("Documents"));
+        }
+    }
+}
@@ -0,0 +1,466 @@
+//! Scheduled agent tasks
+//!
+//! A schedule runs an agent on a project with a fixed prompt ("review the
+//! project and suggest improvements") on a cron schedule, in local time.
+//! Runs go through [`crate::generation::complete`] at background priority
+//! with the project tools, so the prompt and reply land in the project's
+//! messages like any other generation. A schedule that came due while the
+//! app was closed runs once at the next check, then resumes its cadence.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use tauri::Emitter;
+
+use crate::generation::StreamRequest;
+use crate::sharing::Principal;
+
+/// How often due schedules are looked for
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How far ahead a schedule's next run is looked for
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Schedule {
+    pub id: String,
+    pub project_id: String,
+    pub agent_id: String,
+    pub prompt: String,
+    /// Five-field cron expression, or `@hourly`, `@daily`, `@weekly`, `@monthly`
+    pub cron: String,
+    pub next_run_at: String,
+    pub last_run_at: Option<String>,
+    /// Error of the last run, if it failed
+    pub last_error: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewSchedule {
+    pub project_id: String,
+    pub agent_id: String,
+    pub prompt: String,
+    pub cron: String,
+}
+
+/// Outcome of one scheduled run
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleRun {
+    pub schedule_id: String,
+    pub project_id: String,
+    pub agent_id: String,
+    /// The saved reply, if the run succeeded
+    pub message_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A parsed cron expression: minute, hour, day of month, month, day of week
+///
+/// Fields take `*`, numbers, ranges (`1-5`), lists (`1,15`), and steps
+/// (`*/15`, `0-30/10`). Days of the week run from 0 (Sunday) to 6; 7 is
+/// Sunday too. As in cron, when both day fields are restricted a day
+/// matching either one matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("Cron expressions have five fields, not {}", fields.len()));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7).map_err(|e| format!("Day of week: {}", e))?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Cron {
+            minutes: parse_field(minute, 0, 59).map_err(|e| format!("Minute: {}", e))?,
+            hours: parse_field(hour, 0, 23).map_err(|e| format!("Hour: {}", e))?,
+            days: parse_field(day, 1, 31).map_err(|e| format!("Day of month: {}", e))?,
+            months: parse_field(month, 1, 12).map_err(|e| format!("Month: {}", e))?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// The first matching minute after `after`
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = time + Duration::days(MAX_LOOKAHEAD_DAYS);
+
+        while time < limit {
+            let date = time.date();
+            if self.months & (1 << date.month()) == 0 {
+                let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = date.and_hms_opt(time.hour(), 0, 0)? + Duration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    /// The first matching local time after `after`
+    pub fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut local = after.with_timezone(&Local).naive_local();
+        // A time skipped by a daylight-saving change doesn't exist locally
+        for _ in 0..4 {
+            let next = self.next_after(local)?;
+            if let Some(time) = Local.from_local_datetime(&next).earliest() {
+                return Some(time.with_timezone(&Utc));
+            }
+            local = next;
+        }
+        None
+    }
+}
+
+/// A cron field as a bit set of the values it matches
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step {:?}", step))?;
+                if step == 0 {
+                    return Err("step can't be 0".to_string());
+                }
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let number = |value: &str| -> Result<u32, String> {
+            let number: u32 = value.parse().map_err(|_| format!("invalid value {:?}", value))?;
+            if number < min || number > max {
+                return Err(format!("{} is outside {}-{}", number, min, max));
+            }
+            Ok(number)
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                None if step.is_some() => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            },
+        };
+        if start > end {
+            return Err(format!("range {} is backwards", range));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// Schedule an agent task on a project
+pub async fn create(pool: &SqlitePool, user_id: &str, schedule: NewSchedule) -> Result<Schedule, String> {
+    let prompt = schedule.prompt.trim();
+    if prompt.is_empty() {
+        return Err("A scheduled task needs a prompt".to_string());
+    }
+    let cron = Cron::parse(&schedule.cron)?;
+    let next_run_at = cron
+        .next_run(Utc::now())
+        .ok_or_else(|| format!("{} never runs", schedule.cron.trim()))?;
+    if crate::agents::get(pool, &schedule.agent_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .is_none()
+    {
+        return Err(format!("Agent not found: {}", schedule.agent_id));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        r#"
+        INSERT INTO agent_schedules (id, user_id, project_id, agent_id, prompt, cron, next_run_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(&schedule.project_id)
+    .bind(&schedule.agent_id)
+    .bind(prompt)
+    .bind(schedule.cron.trim())
+    .bind(next_run_at.to_rfc3339())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save schedule: {}", e))?;
+
+    get(pool, user_id, &id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| "Schedule was not saved".to_string())
+}
+
+/// One of the user's schedules
+pub async fn get(pool: &SqlitePool, user_id: &str, id: &str) -> Result<Option<Schedule>, sqlx::Error> {
+    let row = sqlx::query(&format!("{} WHERE id = ? AND user_id = ?", SELECT))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(schedule_from_row))
+}
+
+/// The user's schedules, optionally for one project, soonest first
+pub async fn list(pool: &SqlitePool, user_id: &str, project_id: Option<&str>) -> Result<Vec<Schedule>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "{} WHERE user_id = ? AND (? IS NULL OR project_id = ?) ORDER BY next_run_at",
+        SELECT
+    ))
+    .bind(user_id)
+    .bind(project_id)
+    .bind(project_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().map(schedule_from_row).collect())
+}
+
+/// Cancel one of the user's schedules; false if there's no such schedule
+pub async fn cancel(pool: &SqlitePool, user_id: &str, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM agent_schedules WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+const SELECT: &str = "SELECT id, user_id, project_id, agent_id, prompt, cron, next_run_at, last_run_at, last_error, created_at FROM agent_schedules";
+
+fn schedule_from_row(row: &sqlx::sqlite::SqliteRow) -> Schedule {
+    Schedule {
+        id: row.get("id"),
+        project_id: row.get("project_id"),
+        agent_id: row.get("agent_id"),
+        prompt: row.get("prompt"),
+        cron: row.get("cron"),
+        next_run_at: row.get("next_run_at"),
+        last_run_at: row.get("last_run_at"),
+        last_error: row.get("last_error"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Run every schedule that is due, moving each to its next run first
+pub async fn run_due(pool: &SqlitePool) -> Result<Vec<ScheduleRun>, sqlx::Error> {
+    let now = Utc::now();
+    let rows = sqlx::query(&format!("{} WHERE next_run_at <= ? ORDER BY next_run_at", SELECT))
+        .bind(now.to_rfc3339())
+        .fetch_all(pool)
+        .await?;
+
+    let mut runs = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let user_id: String = row.get("user_id");
+        let schedule = schedule_from_row(row);
+
+        // A schedule whose expression no longer parses stops running
+        let next_run_at = Cron::parse(&schedule.cron).ok().and_then(|cron| cron.next_run(now));
+        sqlx::query("UPDATE agent_schedules SET next_run_at = ?, last_run_at = ? WHERE id = ?")
+            .bind(next_run_at.map_or_else(|| "9999-12-31T00:00:00+00:00".to_string(), |time| time.to_rfc3339()))
+            .bind(now.to_rfc3339())
+            .bind(&schedule.id)
+            .execute(pool)
+            .await?;
+
+        let run = run(pool, &user_id, &schedule).await;
+        sqlx::query("UPDATE agent_schedules SET last_error = ? WHERE id = ?")
+            .bind(&run.error)
+            .bind(&schedule.id)
+            .execute(pool)
+            .await?;
+        runs.push(run);
+    }
+    Ok(runs)
+}
+
+async fn run(pool: &SqlitePool, user_id: &str, schedule: &Schedule) -> ScheduleRun {
+    let request = StreamRequest {
+        prompt: schedule.prompt.clone(),
+        agent_id: Some(schedule.agent_id.clone()),
+        provider: None,
+        model: None,
+        project_id: Some(schedule.project_id.clone()),
+        files: None,
+        context: None,
+        tools: true,
+        template_id: None,
+        variables: HashMap::new(),
+        response_format: None,
+        priority: crate::llm::Priority::Background,
+        session_id: None,
+        system_prompt: None,
+        max_tokens: None,
+    };
+    let principal = Principal {
+        user_id,
+        device_id: None,
+        is_owner: user_id == Principal::desktop().user_id,
+    };
+
+    let result = crate::generation::complete(pool, principal, request).await;
+    ScheduleRun {
+        schedule_id: schedule.id.clone(),
+        project_id: schedule.project_id.clone(),
+        agent_id: schedule.agent_id.clone(),
+        message_id: result.as_ref().ok().map(|completion| completion.id.clone()),
+        error: result.err().map(|error| error.message),
+    }
+}
+
+/// Run due schedules in the background, announcing each run to the
+/// frontend (`schedule-ran`) and in the tray
+pub fn spawn_task(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        // After sleep, check once instead of for every missed tick
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            crate::power::wait_active().await;
+
+            let pool = match crate::database::get_pool().await {
+                Ok(pool) => pool,
+                Err(e) => {
+                    eprintln!("Scheduled tasks skipped: {}", e);
+                    continue;
+                }
+            };
+
+            let runs = match run_due(pool.as_ref()).await {
+                Ok(runs) => runs,
+                Err(e) => {
+                    eprintln!("Scheduled tasks failed: {}", e);
+                    continue;
+                }
+            };
+            for run in &runs {
+                match &run.error {
+                    Some(error) => {
+                        eprintln!("Scheduled {} run failed: {}", run.agent_id, error);
+                        crate::tray::notify(&app, &format!("Scheduled {} run failed", run.agent_id));
+                    }
+                    None => {
+                        println!("⏰ Ran scheduled {} task on project {}", run.agent_id, run.project_id);
+                        crate::tray::notify(&app, &format!("Scheduled {} run finished", run.agent_id));
+                    }
+                }
+                let _ = app.emit("schedule-ran", run);
+            }
+            if !runs.is_empty() {
+                if let Err(e) = crate::tray::update_tray_menu(&app) {
+                    eprintln!("Failed to update tray menu: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn at(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let nightly = Cron::parse("0 2 * * *").unwrap();
+        assert_eq!(nightly.next_after(at("2026-10-15 01:59")), Some(at("2026-10-15 02:00")));
+        assert_eq!(nightly.next_after(at("2026-10-15 02:00")), Some(at("2026-10-16 02:00")));
+
+        let quarter_hours = Cron::parse("*/15 9-17 * * 1-5").unwrap();
+        // 2026-10-17 is a Saturday
+        assert_eq!(quarter_hours.next_after(at("2026-10-16 17:50")), Some(at("2026-10-19 09:00")));
+        assert_eq!(quarter_hours.next_after(at("2026-10-19 09:01")), Some(at("2026-10-19 09:15")));
+
+        let yearly = Cron::parse("30 6 1 1 *").unwrap();
+        assert_eq!(yearly.next_after(at("2026-10-15 00:00")), Some(at("2027-01-01 06:30")));
+
+        // Either day field matches when both are restricted
+        let either = Cron::parse("0 0 13 * 5").unwrap();
+        assert_eq!(either.next_after(at("2026-10-15 00:00")), Some(at("2026-10-16 00:00")));
+        assert_eq!(Cron::parse("@weekly").unwrap(), Cron::parse("0 0 * * 7").unwrap());
+
+        assert!(Cron::parse("0 0 30 2 *").unwrap().next_after(at("2026-10-15 00:00")).is_none());
+        assert!(Cron::parse("0 2 * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert!(Cron::parse("5-1 * * * *").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_schedules() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Site', 'website', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let new_schedule = |agent_id: &str, cron: &str| NewSchedule {
+            project_id: "p1".to_string(),
+            agent_id: agent_id.to_string(),
+            prompt: "Review the project and suggest improvements".to_string(),
+            cron: cron.to_string(),
+        };
+        assert!(create(&pool, "local-user", new_schedule("missing", "@daily")).await.is_err());
+        assert!(create(&pool, "local-user", new_schedule("ui-designer", "0 0 31 2 *")).await.is_err());
+
+        let schedule = create(&pool, "local-user", new_schedule("ui-designer", "@daily")).await.unwrap();
+        assert!(schedule.last_run_at.is_none());
+        assert_eq!(list(&pool, "local-user", Some("p1")).await.unwrap(), vec![schedule.clone()]);
+        assert!(list(&pool, "other-user", None).await.unwrap().is_empty());
+        assert!(run_due(&pool).await.unwrap().is_empty());
+
+        assert!(!cancel(&pool, "other-user", &schedule.id).await.unwrap());
+        assert!(cancel(&pool, "local-user", &schedule.id).await.unwrap());
+        assert!(list(&pool, "local-user", None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,421 @@
+//! Global search
+//!
+//! Searches project names and descriptions, message contents, and code
+//! (project files and `current_code`) through the full-text indexes the
+//! database keeps (see `database::create_search_index`). Results come back
+//! grouped by kind, best match first, each with a snippet of the matching
+//! text and the offsets of the matched terms in it, so the command palette
+//! can highlight them.
+//!
+//! Every word of the query must match, as a prefix of a word in the text.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+/// Results per group
+pub const MAX_RESULTS: i64 = 20;
+
+/// Words of a query used; the rest are ignored
+const MAX_TERMS: usize = 10;
+
+/// About how much text a snippet shows
+const SNIPPET_CHARS: usize = 160;
+
+/// Text shown before the first match in a snippet
+const SNIPPET_LEAD_CHARS: usize = 50;
+
+/// What to search
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchScope {
+    #[default]
+    All,
+    Projects,
+    Messages,
+    Code,
+}
+
+/// Kind of result, one group each
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultKind {
+    Project,
+    Message,
+    Code,
+}
+
+/// A matched term in a snippet, in UTF-16 code units (as JavaScript counts)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub project_id: String,
+    pub project_name: String,
+    /// The matching message, for message results
+    pub message_id: Option<String>,
+    /// The matching file, for code results; `index.html` for `current_code`
+    pub path: Option<String>,
+    pub snippet: String,
+    pub highlights: Vec<Highlight>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchGroup {
+    pub kind: ResultKind,
+    pub hits: Vec<SearchHit>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SearchResults {
+    pub query: String,
+    /// Groups with results, in the order projects, messages, code
+    pub groups: Vec<SearchGroup>,
+}
+
+/// The query's words, lowercased
+fn terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .map(|word| word.replace('"', "").to_lowercase())
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .take(MAX_TERMS)
+        .collect()
+}
+
+/// An FTS5 query matching every term as a prefix, in `columns`
+///
+/// Terms are quoted, so FTS5 operators in the query are searched for as text.
+fn match_expression(terms: &[String], columns: &str) -> String {
+    let phrases: Vec<String> = terms.iter().map(|term| format!("\"{}\"*", term)).collect();
+    format!("{{{}}} : ({})", columns, phrases.join(" "))
+}
+
+/// Search everything in `scope` for `query`
+pub async fn search(pool: &SqlitePool, query: &str, scope: SearchScope) -> Result<SearchResults, sqlx::Error> {
+    let terms = terms(query);
+    let mut results = SearchResults {
+        query: query.trim().to_string(),
+        groups: Vec::new(),
+    };
+    if terms.is_empty() {
+        return Ok(results);
+    }
+
+    let mut groups = Vec::new();
+    if matches!(scope, SearchScope::All | SearchScope::Projects) {
+        groups.push((ResultKind::Project, projects(pool, &terms).await?));
+    }
+    if matches!(scope, SearchScope::All | SearchScope::Messages) {
+        groups.push((ResultKind::Message, messages(pool, &terms).await?));
+    }
+    if matches!(scope, SearchScope::All | SearchScope::Code) {
+        groups.push((ResultKind::Code, code(pool, &terms).await?));
+    }
+
+    results.groups = groups
+        .into_iter()
+        .filter(|(_, hits)| !hits.is_empty())
+        .map(|(kind, hits)| SearchGroup { kind, hits })
+        .collect();
+    Ok(results)
+}
+
+async fn projects(pool: &SqlitePool, terms: &[String]) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT p.id, p.name, p.description
+        FROM projects_fts JOIN projects p ON p.rowid = projects_fts.rowid
+        WHERE projects_fts MATCH ?
+        ORDER BY rank
+        LIMIT ?
+        "#,
+    )
+    .bind(match_expression(terms, "name description"))
+    .bind(MAX_RESULTS)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let name: String = row.get("name");
+            let description: Option<String> = row.get("description");
+            // Show the name when it matched, else the description
+            let (snippet, highlights) = match excerpt(&name, terms) {
+                (snippet, highlights) if !highlights.is_empty() => (snippet, highlights),
+                named => description.as_deref().map(|text| excerpt(text, terms)).unwrap_or(named),
+            };
+            SearchHit {
+                project_id: row.get("id"),
+                project_name: name,
+                message_id: None,
+                path: None,
+                snippet,
+                highlights,
+            }
+        })
+        .collect())
+}
+
+async fn messages(pool: &SqlitePool, terms: &[String]) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT m.id, m.content, p.id AS project_id, p.name
+        FROM messages_fts
+        JOIN messages m ON m.rowid = messages_fts.rowid
+        JOIN projects p ON p.id = m.project_id
+        WHERE messages_fts MATCH ?
+        ORDER BY rank
+        LIMIT ?
+        "#,
+    )
+    .bind(match_expression(terms, "content"))
+    .bind(MAX_RESULTS)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let (snippet, highlights) = excerpt(row.get::<&str, _>("content"), terms);
+            SearchHit {
+                project_id: row.get("project_id"),
+                project_name: row.get("name"),
+                message_id: Some(row.get("id")),
+                path: None,
+                snippet,
+                highlights,
+            }
+        })
+        .collect())
+}
+
+/// Project files first, then projects' `current_code`
+async fn code(pool: &SqlitePool, terms: &[String]) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let files = sqlx::query(
+        r#"
+        SELECT f.path, f.content, p.id AS project_id, p.name
+        FROM project_files_fts
+        JOIN project_files f ON f.rowid = project_files_fts.rowid
+        JOIN projects p ON p.id = f.project_id
+        WHERE project_files_fts MATCH ?
+        ORDER BY rank
+        LIMIT ?
+        "#,
+    )
+    .bind(match_expression(terms, "path content"))
+    .bind(MAX_RESULTS)
+    .fetch_all(pool)
+    .await?;
+
+    let current_code = sqlx::query(
+        r#"
+        SELECT p.id AS project_id, p.name, p.current_code AS content
+        FROM projects_fts JOIN projects p ON p.rowid = projects_fts.rowid
+        WHERE projects_fts MATCH ?
+        ORDER BY rank
+        LIMIT ?
+        "#,
+    )
+    .bind(match_expression(terms, "current_code"))
+    .bind(MAX_RESULTS)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(files
+        .iter()
+        .map(|row| (row, row.get::<String, _>("path")))
+        .chain(
+            current_code
+                .iter()
+                .map(|row| (row, crate::scaffold::CURRENT_CODE_PATH.to_string())),
+        )
+        .take(MAX_RESULTS as usize)
+        .map(|(row, path)| {
+            let content: Option<String> = row.get("content");
+            // A file matched on its path shows the path
+            let (snippet, highlights) = match excerpt(content.as_deref().unwrap_or_default(), terms) {
+                (_, highlights) if highlights.is_empty() => excerpt(&path, terms),
+                found => found,
+            };
+            SearchHit {
+                project_id: row.get("project_id"),
+                project_name: row.get("name"),
+                message_id: None,
+                path: Some(path),
+                snippet,
+                highlights,
+            }
+        })
+        .collect())
+}
+
+/// Byte ranges in `text` where a word starts with one of `terms`
+fn matches(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    // Lowercase, remembering where each lowercased byte came from
+    let mut lower = String::with_capacity(text.len());
+    let mut origin = Vec::with_capacity(text.len() + 1);
+    for (i, c) in text.char_indices() {
+        for lc in c.to_lowercase() {
+            lower.push(lc);
+            origin.extend(std::iter::repeat(i).take(lc.len_utf8()));
+        }
+    }
+    origin.push(text.len());
+
+    let mut ranges = Vec::new();
+    for term in terms {
+        let mut from = 0;
+        while let Some(found) = lower[from..].find(term.as_str()).map(|i| from + i) {
+            let end = found + term.len();
+            from = end;
+            let word_start = !matches!(lower[..found].chars().next_back(), Some(c) if c.is_alphanumeric());
+            if word_start {
+                ranges.push((origin[found], origin[end]));
+            }
+        }
+    }
+
+    // Sorted, with overlapping matches merged
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// A one-line excerpt of `text` around its first match, and the matches in it
+fn excerpt(text: &str, terms: &[String]) -> (String, Vec<Highlight>) {
+    let found = matches(text, terms);
+
+    // Start a little before the first match, on a character boundary
+    let first = found.first().map_or(0, |(start, _)| *start);
+    let start = text[..first]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_LEAD_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let end = text[start..]
+        .char_indices()
+        .nth(SNIPPET_CHARS)
+        .map_or(text.len(), |(i, _)| start + i);
+
+    let (prefix, suffix) = (if start > 0 { "…" } else { "" }, if end < text.len() { "…" } else { "" });
+    let body: String = text[start..end]
+        .chars()
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect();
+    let snippet = format!("{}{}{}", prefix, body, suffix);
+
+    // Whitespace was replaced one for one, so byte offsets still line up
+    let offset = prefix.len() as isize - start as isize;
+    let utf16 = |byte: usize| snippet[..byte].encode_utf16().count();
+    let highlights = found
+        .into_iter()
+        .filter(|(match_start, match_end)| *match_start >= start && *match_end <= end)
+        .map(|(match_start, match_end)| Highlight {
+            start: utf16((match_start as isize + offset) as usize),
+            end: utf16((match_end as isize + offset) as usize),
+        })
+        .collect();
+    (snippet, highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    /// The highlighted text of each highlight
+    fn highlighted(snippet: &str, highlights: &[Highlight]) -> Vec<String> {
+        let units: Vec<u16> = snippet.encode_utf16().collect();
+        highlights
+            .iter()
+            .map(|h| String::from_utf16(&units[h.start..h.end]).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_terms_and_match_expression() {
+        assert_eq!(terms("  Dark \"MODE\" - "), vec!["dark", "mode"]);
+        assert_eq!(
+            match_expression(&terms("dark OR mode"), "content"),
+            r#"{content} : ("dark"* "or"* "mode"*)"#
+        );
+    }
+
+    #[test]
+    fn test_snippet_highlights() {
+        let (snippet, highlights) = excerpt("Add a Dark mode toggle\nand darken the footer", &terms("dark"));
+        assert_eq!(snippet, "Add a Dark mode toggle and darken the footer");
+        assert_eq!(highlighted(&snippet, &highlights), vec!["Dark", "dark"]);
+
+        // Not inside words
+        let (_, highlights) = excerpt("undark", &terms("dark"));
+        assert!(highlights.is_empty());
+
+        // Long text is cut around the first match; offsets count UTF-16 units
+        let text = format!("{} 🎨 palette {}", "x".repeat(300), "y".repeat(300));
+        let (snippet, highlights) = excerpt(&text, &terms("palette"));
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert_eq!(highlighted(&snippet, &highlights), vec!["palette"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_groups() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, description, project_type, current_code, user_id) VALUES ('p1', 'Weather Dashboard', 'Forecast charts', 'dashboard', '<div class=\"forecast\"></div>', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO messages (id, role, content, project_id) VALUES ('m1', 'user', 'Show a seven day forecast', 'p1')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        crate::agent_tools::save_file(&pool, "p1", "src/forecast.js", "export function forecastFor(city) {}")
+            .await
+            .unwrap();
+
+        let results = search(&pool, "forecast", SearchScope::All).await.unwrap();
+        let kinds: Vec<ResultKind> = results.groups.iter().map(|group| group.kind).collect();
+        assert_eq!(kinds, vec![ResultKind::Project, ResultKind::Message, ResultKind::Code]);
+
+        let project = &results.groups[0].hits[0];
+        assert_eq!(project.snippet, "Forecast charts");
+        assert_eq!(highlighted(&project.snippet, &project.highlights), vec!["Forecast"]);
+        assert_eq!(results.groups[1].hits[0].message_id.as_deref(), Some("m1"));
+        let paths: Vec<&str> = results.groups[2].hits.iter().filter_map(|hit| hit.path.as_deref()).collect();
+        assert_eq!(paths, vec!["src/forecast.js", "index.html"]);
+
+        // Prefixes match, and every word must
+        let code = search(&pool, "forecastF", SearchScope::Code).await.unwrap();
+        assert_eq!(code.groups.len(), 1);
+        assert_eq!(code.groups[0].hits.len(), 1);
+        assert!(search(&pool, "forecast tomorrow", SearchScope::All).await.unwrap().groups.is_empty());
+
+        // The index follows updates and deletes
+        sqlx::query("UPDATE projects SET name = 'Rain Radar', description = NULL WHERE id = 'p1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let renamed = search(&pool, "radar", SearchScope::Projects).await.unwrap();
+        assert_eq!(renamed.groups[0].hits[0].project_name, "Rain Radar");
+        sqlx::query("DELETE FROM projects WHERE id = 'p1'").execute(&pool).await.unwrap();
+        assert!(search(&pool, "forecast", SearchScope::All).await.unwrap().groups.is_empty());
+
+        // Operators are searched for as text, not parsed
+        assert!(search(&pool, "NEAR( \"AND* ^", SearchScope::All).await.is_ok());
+        assert!(search(&pool, "  ", SearchScope::All).await.unwrap().groups.is_empty());
+    }
+}
@@ -1,156 +1,397 @@
 // Agents API endpoints
 use axum::{
-    extract::{State, Path},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use serde::{Deserialize, Serialize};
+use crate::server::api::auth::bearer_token;
+use crate::server::middleware::auth::{resolve_user, AuthUser};
 use crate::server::ServerState;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Agent {
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub category: String,
-    pub capabilities: Vec<String>,
-    pub model: String,
-    pub icon: String,
+pub use crate::agent_icons::NewIcon;
+pub use crate::agent_lint::{Diagnostic, SaveError};
+pub use crate::agents::{Agent, AgentFilter, AgentUpdate, NewAgent};
+pub use crate::generation::{AgentTest, CompletionResponse};
+
+fn failure(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    ).into_response()
+}
+
+/// 400 with the diagnostics for an invalid agent, 500 otherwise
+fn save_failure(error: SaveError) -> Response {
+    let message = error.to_string();
+    match error {
+        SaveError::Invalid(diagnostics) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": message,
+                "diagnostics": diagnostics
+            })),
+        ).into_response(),
+        SaveError::Failed(_) => failure(StatusCode::INTERNAL_SERVER_ERROR, message),
+    }
 }
 
-/// List all available agents
+/// List agents, optionally filtered by text, category, capability, or model
+///
+/// The route is public; with a bearer token each agent's `favorite` is set
+/// for that user, and `favorites_only` requires one.
+#[utoipa::path(
+    get,
+    path = "/api/agents/list",
+    tag = "agents",
+    params(AgentFilter),
+    responses(
+        (status = 200, description = "Available agents"),
+        (status = 401, description = "`favorites_only` without a valid bearer token"),
+    )
+)]
 pub async fn list_agents(
-    State(_state): State<ServerState>,
-) -> impl IntoResponse {
-    // Return predefined agents list
-    let agents = vec![
-        Agent {
-            id: "frontend-architect".to_string(),
-            name: "Frontend Architect".to_string(),
-            description: "Expert in React, Vue, Angular, and modern frontend architecture".to_string(),
-            category: "Frontend".to_string(),
-            capabilities: vec![
-                "Component architecture".to_string(),
-                "State management".to_string(),
-                "Performance optimization".to_string(),
-            ],
-            model: "claude-3-opus".to_string(),
-            icon: "🏗️".to_string(),
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Query(filter): Query<AgentFilter>,
+) -> Response {
+    let user = match bearer_token(&headers) {
+        Some(token) => match resolve_user(&state, token).await {
+            Ok(user) => user,
+            Err(e) => return failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to verify session: {}", e)),
         },
-        Agent {
-            id: "backend-architect".to_string(),
-            name: "Backend Architect".to_string(),
-            description: "Specializes in scalable backend systems and API design".to_string(),
-            category: "Backend".to_string(),
-            capabilities: vec![
-                "API design".to_string(),
-                "Microservices".to_string(),
-                "Database architecture".to_string(),
-            ],
-            model: "claude-3-opus".to_string(),
-            icon: "⚙️".to_string(),
-        },
-        Agent {
-            id: "database-architect".to_string(),
-            name: "Database Architect".to_string(),
-            description: "Expert in database design, optimization, and migration".to_string(),
-            category: "Database".to_string(),
-            capabilities: vec![
-                "Schema design".to_string(),
-                "Query optimization".to_string(),
-                "Data modeling".to_string(),
-            ],
-            model: "claude-3-opus".to_string(),
-            icon: "🗄️".to_string(),
-        },
-        Agent {
-            id: "ui-designer".to_string(),
-            name: "UI/UX Designer".to_string(),
-            description: "Creates beautiful, intuitive user interfaces".to_string(),
-            category: "Design".to_string(),
-            capabilities: vec![
-                "UI design".to_string(),
-                "User experience".to_string(),
-                "Design systems".to_string(),
-            ],
-            model: "claude-3-opus".to_string(),
-            icon: "🎨".to_string(),
-        },
-        Agent {
-            id: "devops-engineer".to_string(),
-            name: "DevOps Engineer".to_string(),
-            description: "Infrastructure automation and CI/CD specialist".to_string(),
-            category: "DevOps".to_string(),
-            capabilities: vec![
-                "CI/CD pipelines".to_string(),
-                "Container orchestration".to_string(),
-                "Infrastructure as code".to_string(),
-            ],
-            model: "claude-3-opus".to_string(),
-            icon: "🚀".to_string(),
-        },
-    ];
+        None => None,
+    };
+    if filter.favorites_only.unwrap_or(false) && user.is_none() {
+        return failure(StatusCode::UNAUTHORIZED, "Sign in to list favorite agents".to_string());
+    }
 
-    Json(serde_json::json!({
-        "success": true,
-        "agents": agents,
-        "total": agents.len()
-    }))
+    let user_id = user.as_ref().map(|user| user.id.as_str());
+    match crate::agents::list(&state.db_pool, user_id, &filter).await {
+        Ok(agents) => Json(serde_json::json!({
+            "success": true,
+            "agents": agents,
+            "total": agents.len()
+        })).into_response(),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to list agents: {}", e)),
+    }
 }
 
 /// Get a specific agent by ID
+#[utoipa::path(
+    get,
+    path = "/api/agents/{id}",
+    tag = "agents",
+    params(("id" = String, Path, description = "Agent ID")),
+    responses(
+        (status = 200, description = "Agent details"),
+        (status = 404, description = "Agent not found"),
+    )
+)]
 pub async fn get_agent(
-    State(_state): State<ServerState>,
+    State(state): State<ServerState>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
-    // Mock implementation - return agent if found
-    let agents = vec![
-        ("frontend-architect", Agent {
-            id: "frontend-architect".to_string(),
-            name: "Frontend Architect".to_string(),
-            description: "Expert in React, Vue, Angular, and modern frontend architecture".to_string(),
-            category: "Frontend".to_string(),
-            capabilities: vec![
-                "Component architecture".to_string(),
-                "State management".to_string(),
-                "Performance optimization".to_string(),
-            ],
-            model: "claude-3-opus".to_string(),
-            icon: "🏗️".to_string(),
-        }),
-        ("backend-architect", Agent {
-            id: "backend-architect".to_string(),
-            name: "Backend Architect".to_string(),
-            description: "Specializes in scalable backend systems and API design".to_string(),
-            category: "Backend".to_string(),
-            capabilities: vec![
-                "API design".to_string(),
-                "Microservices".to_string(),
-                "Database architecture".to_string(),
-            ],
-            model: "claude-3-opus".to_string(),
-            icon: "⚙️".to_string(),
-        }),
-    ];
-
-    for (agent_id, agent) in agents {
-        if agent_id == id {
-            return (
-                StatusCode::OK,
-                Json(serde_json::json!({
-                    "success": true,
-                    "agent": agent
-                })),
-            ).into_response();
-        }
+) -> Response {
+    match crate::agents::get(&state.db_pool, &id).await {
+        Ok(Some(agent)) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "agent": agent
+            })),
+        ).into_response(),
+        Ok(None) => failure(StatusCode::NOT_FOUND, "Agent not found".to_string()),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load agent: {}", e)),
     }
+}
 
-    (
-        StatusCode::NOT_FOUND,
-        Json(serde_json::json!({
-            "success": false,
-            "message": "Agent not found"
-        })),
-    ).into_response()
-}
\ No newline at end of file
+/// Define a custom agent
+#[utoipa::path(
+    post,
+    path = "/api/agents",
+    tag = "agents",
+    request_body = NewAgent,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Agent created", body = Agent),
+        (status = 400, description = "The agent has errors; `diagnostics` lists them"),
+    )
+)]
+pub async fn create_agent(
+    State(state): State<ServerState>,
+    Json(payload): Json<NewAgent>,
+) -> Response {
+    match crate::agents::create(&state.db_pool, payload).await {
+        Ok(agent) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "success": true,
+                "agent": agent
+            })),
+        ).into_response(),
+        Err(e) => save_failure(e),
+    }
+}
+
+/// Check a draft agent without saving it
+#[utoipa::path(
+    post,
+    path = "/api/agents/validate",
+    tag = "agents",
+    request_body = NewAgent,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Whether the agent could be saved, and every problem found", body = [Diagnostic]),
+    )
+)]
+pub async fn validate_agent(Json(payload): Json<NewAgent>) -> Response {
+    let diagnostics = crate::agent_lint::lint(&crate::agents::draft(String::new(), payload));
+    Json(serde_json::json!({
+        "success": true,
+        "valid": !crate::agent_lint::has_errors(&diagnostics),
+        "diagnostics": diagnostics
+    })).into_response()
+}
+
+/// Edit an agent, built-in or custom
+#[utoipa::path(
+    put,
+    path = "/api/agents/{id}",
+    tag = "agents",
+    params(("id" = String, Path, description = "Agent ID")),
+    request_body = AgentUpdate,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Agent updated", body = Agent),
+        (status = 400, description = "The edited agent has errors; `diagnostics` lists them"),
+        (status = 404, description = "Agent not found"),
+    )
+)]
+pub async fn update_agent(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Json(payload): Json<AgentUpdate>,
+) -> Response {
+    match crate::agents::update(&state.db_pool, &id, payload).await {
+        Ok(Some(agent)) => Json(serde_json::json!({
+            "success": true,
+            "agent": agent
+        })).into_response(),
+        Ok(None) => failure(StatusCode::NOT_FOUND, "Agent not found".to_string()),
+        Err(e) => save_failure(e),
+    }
+}
+
+/// Delete a custom agent
+#[utoipa::path(
+    delete,
+    path = "/api/agents/{id}",
+    tag = "agents",
+    params(("id" = String, Path, description = "Agent ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Agent deleted"),
+        (status = 404, description = "Agent not found, or built in"),
+    )
+)]
+pub async fn delete_agent(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+) -> Response {
+    match crate::agents::delete(&state.db_pool, &id).await {
+        Ok(true) => Json(serde_json::json!({
+            "success": true,
+            "message": "Agent deleted"
+        })).into_response(),
+        Ok(false) => failure(StatusCode::NOT_FOUND, "Agent not found".to_string()),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete agent: {}", e)),
+    }
+}
+
+/// An agent's icon: its uploaded SVG or PNG, or a generated initials avatar
+///
+/// Responses carry an ETag and are revalidated on each use, so a new upload
+/// shows up at once while unchanged icons cost a 304.
+#[utoipa::path(
+    get,
+    path = "/api/agents/{id}/icon",
+    tag = "agents",
+    params(("id" = String, Path, description = "Agent ID")),
+    responses(
+        (status = 200, description = "The icon image", content_type = "image/svg+xml"),
+        (status = 304, description = "The cached icon is current"),
+        (status = 404, description = "Agent not found"),
+    )
+)]
+pub async fn get_agent_icon(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let icon = match crate::agent_icons::load(&state.db_pool, &id).await {
+        Ok(Some(icon)) => icon,
+        Ok(None) => return failure(StatusCode::NOT_FOUND, "Agent not found".to_string()),
+        Err(e) => return failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load icon: {}", e)),
+    };
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("public, no-cache"));
+    if let Ok(etag) = HeaderValue::from_str(&icon.etag) {
+        response_headers.insert(header::ETAG, etag);
+    }
+
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim().trim_start_matches("W/") == icon.etag));
+    if cached {
+        return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+    }
+
+    if let Ok(media_type) = HeaderValue::from_str(&icon.media_type) {
+        response_headers.insert(header::CONTENT_TYPE, media_type);
+    }
+    // Uploaded SVGs may carry scripts; never let them run
+    response_headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static("default-src 'none'; style-src 'unsafe-inline'; sandbox"),
+    );
+    response_headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+
+    (StatusCode::OK, response_headers, icon.bytes).into_response()
+}
+
+/// Upload an SVG or PNG icon for an agent, replacing any earlier one
+#[utoipa::path(
+    put,
+    path = "/api/agents/{id}/icon",
+    tag = "agents",
+    params(("id" = String, Path, description = "Agent ID")),
+    request_body = NewIcon,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Icon stored"),
+        (status = 400, description = "Not base64, not SVG or PNG, or over 256 KB"),
+        (status = 404, description = "Agent not found"),
+    )
+)]
+pub async fn upload_agent_icon(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Json(payload): Json<NewIcon>,
+) -> Response {
+    match crate::agents::get(&state.db_pool, &id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return failure(StatusCode::NOT_FOUND, "Agent not found".to_string()),
+        Err(e) => return failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load agent: {}", e)),
+    }
+
+    match crate::agent_icons::set(&state.db_pool, &id, payload).await {
+        Ok(icon) => Json(serde_json::json!({
+            "success": true,
+            "media_type": icon.media_type,
+            "etag": icon.etag
+        })).into_response(),
+        Err(e) => failure(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// Remove an agent's uploaded icon, going back to its initials avatar
+#[utoipa::path(
+    delete,
+    path = "/api/agents/{id}/icon",
+    tag = "agents",
+    params(("id" = String, Path, description = "Agent ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Icon removed"),
+        (status = 404, description = "Agent not found, or has no uploaded icon"),
+    )
+)]
+pub async fn delete_agent_icon(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+) -> Response {
+    match crate::agent_icons::delete(&state.db_pool, &id).await {
+        Ok(true) => Json(serde_json::json!({
+            "success": true,
+            "message": "Icon removed"
+        })).into_response(),
+        Ok(false) => failure(StatusCode::NOT_FOUND, "Agent has no uploaded icon".to_string()),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to remove icon: {}", e)),
+    }
+}
+
+/// Favorite or unfavorite an agent for the signed-in user
+#[utoipa::path(
+    post,
+    path = "/api/agents/{id}/favorite",
+    tag = "agents",
+    params(("id" = String, Path, description = "Agent ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Whether the agent is now a favorite"),
+        (status = 404, description = "Agent not found"),
+    )
+)]
+pub async fn toggle_favorite(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Response {
+    match crate::agents::get(&state.db_pool, &id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return failure(StatusCode::NOT_FOUND, "Agent not found".to_string()),
+        Err(e) => return failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load agent: {}", e)),
+    }
+
+    match crate::agents::toggle_favorite(&state.db_pool, &user.id, &id).await {
+        Ok(favorite) => Json(serde_json::json!({
+            "success": true,
+            "favorite": favorite
+        })).into_response(),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// Try an agent on a short sample input with a cheap model
+///
+/// Replies are capped at a few hundred tokens and saved nowhere. Pass
+/// `system_prompt` to try a draft before saving it.
+#[utoipa::path(
+    post,
+    path = "/api/agents/{id}/test",
+    tag = "agents",
+    params(("id" = String, Path, description = "Agent ID")),
+    request_body = AgentTest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The agent's reply and its token usage", body = CompletionResponse),
+        (status = 400, description = "Invalid request or provider not configured"),
+        (status = 404, description = "Agent not found"),
+        (status = 429, description = "Provider rate limit"),
+        (status = 502, description = "Provider error"),
+    )
+)]
+pub async fn test_agent(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    payload: Option<Json<AgentTest>>,
+) -> Response {
+    let agent = match crate::agents::get(&state.db_pool, &id).await {
+        Ok(Some(agent)) => agent,
+        Ok(None) => return failure(StatusCode::NOT_FOUND, "Agent not found".to_string()),
+        Err(e) => return failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load agent: {}", e)),
+    };
+
+    let test = payload.map(|Json(test)| test).unwrap_or_default();
+    match crate::generation::test_agent(&state.db_pool, user.principal(), &agent, test).await {
+        Ok(completion) => Json(completion).into_response(),
+        Err(error) => super::stream::completion_failure(error),
+    }
+}
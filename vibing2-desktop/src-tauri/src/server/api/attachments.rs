@@ -0,0 +1,76 @@
+// Image attachment API endpoints
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use crate::attachments::{self, NewAttachment};
+use crate::server::ServerState;
+use crate::server::middleware::auth::AuthUser;
+
+fn failure(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    ).into_response()
+}
+
+/// Upload an image to reference from stream requests by ID
+#[utoipa::path(
+    post,
+    path = "/api/attachments",
+    tag = "attachments",
+    request_body = NewAttachment,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Image stored", body = crate::attachments::Attachment),
+        (status = 400, description = "Not base64, an unsupported image type, or over 5 MB"),
+    )
+)]
+pub async fn upload_attachment(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<NewAttachment>,
+) -> Response {
+    match attachments::create(&state.db_pool, &user.id, payload).await {
+        Ok(attachment) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "success": true,
+                "attachment": attachment
+            })),
+        ).into_response(),
+        Err(e) => failure(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// Delete one of the caller's images
+#[utoipa::path(
+    delete,
+    path = "/api/attachments/{id}",
+    tag = "attachments",
+    params(("id" = String, Path, description = "Attachment ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Image deleted"),
+        (status = 404, description = "Attachment not found"),
+    )
+)]
+pub async fn delete_attachment(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Response {
+    match attachments::delete(&state.db_pool, &user.id, &id).await {
+        Ok(true) => Json(serde_json::json!({
+            "success": true,
+            "message": "Attachment deleted"
+        })).into_response(),
+        Ok(false) => failure(StatusCode::NOT_FOUND, "Attachment not found".to_string()),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete attachment: {}", e)),
+    }
+}
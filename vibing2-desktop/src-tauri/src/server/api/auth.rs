@@ -1,28 +1,46 @@
 // Authentication API endpoints
 use axum::{
-    extract::State,
-    http::{StatusCode, HeaderMap},
-    response::IntoResponse,
+    extract::{ConnectInfo, Extension, Path, State},
+    http::{StatusCode, HeaderMap, header},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use sqlx::{Row, SqlitePool};
+use std::net::SocketAddr;
+use std::time::Duration;
+use crate::server::middleware::auth::AuthUser;
 use crate::server::ServerState;
+use crate::sessions::{self, DeviceInfo};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SignInRequest {
     pub email: String,
     pub password: String,
+    /// Current TOTP code, required when two-factor sign in is enforced
+    pub totp_code: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SignUpRequest {
     pub name: String,
     pub email: String,
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PairDeviceRequest {
+    pub code: String,
+    pub device_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TotpCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AuthResponse {
     pub success: bool,
     pub user: Option<User>,
@@ -30,7 +48,7 @@ pub struct AuthResponse {
     pub message: Option<String>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct User {
     pub id: String,
     pub name: String,
@@ -39,10 +57,43 @@ pub struct User {
 }
 
 /// Handle user sign in
+#[utoipa::path(
+    post,
+    path = "/api/auth/signin",
+    tag = "auth",
+    request_body = SignInRequest,
+    responses(
+        (status = 200, description = "Signed in", body = AuthResponse),
+        (status = 401, description = "Invalid credentials, or a two-factor code is required"),
+        (status = 403, description = "Password sign in from another machine"),
+        (status = 429, description = "Too many failed attempts"),
+    )
+)]
 pub async fn signin(
     State(state): State<ServerState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<SignInRequest>,
-) -> impl IntoResponse {
+) -> Response {
+    // Reject early while the IP or account is locked out
+    let throttle_keys = throttle_keys(addr, &payload.email);
+    if let Err(retry_after) = check_throttle(&state, &throttle_keys) {
+        return too_many_attempts(retry_after);
+    }
+
+    // Other machines onboard by pairing, never with a password
+    if !addr.ip().is_loopback() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(AuthResponse {
+                success: false,
+                user: None,
+                token: None,
+                message: Some("Password sign in is only available on this machine; pair this device instead".to_string()),
+            }),
+        ).into_response();
+    }
+
     // Validate input
     if payload.email.is_empty() || payload.password.is_empty() {
         return (
@@ -53,21 +104,22 @@ pub async fn signin(
                 token: None,
                 message: Some("Email and password are required".to_string()),
             }),
-        );
+        ).into_response();
     }
 
     // Query the database for the user
-    let user = match sqlx::query!(
-        "SELECT id, name, email, password_hash, created_at FROM users WHERE email = ?",
-        payload.email
+    let user = match sqlx::query(
+        "SELECT id, name, email, password, created_at FROM users WHERE email = ?"
     )
+    .bind(&payload.email)
     .fetch_one(&state.db_pool)
     .await
     {
         Ok(record) => {
-            // Verify password (simplified - should use proper password hashing)
-            // In production, use argon2 or bcrypt for password verification
-            if record.password_hash != payload.password {
+            let user_id: String = record.get("id");
+            let stored: String = record.get("password");
+            if !crate::passwords::verify(&user_id, &stored, &payload.password) {
+                record_failure(&state, &throttle_keys);
                 return (
                     StatusCode::UNAUTHORIZED,
                     Json(AuthResponse {
@@ -76,17 +128,20 @@ pub async fn signin(
                         token: None,
                         message: Some("Invalid credentials".to_string()),
                     }),
-                );
+                ).into_response();
             }
 
-            User {
-                id: record.id,
-                name: record.name,
-                email: record.email,
-                created_at: record.created_at,
+            // Upgrade a legacy plaintext password to a hash
+            if !crate::passwords::is_hashed(&stored) {
+                if let Err(e) = crate::passwords::set(&state.db_pool, &user_id, &payload.password).await {
+                    eprintln!("Failed to rehash password: {}", e);
+                }
             }
+
+            user_from_row(&record)
         }
         Err(_) => {
+            record_failure(&state, &throttle_keys);
             return (
                 StatusCode::UNAUTHORIZED,
                 Json(AuthResponse {
@@ -95,21 +150,35 @@ pub async fn signin(
                     token: None,
                     message: Some("Invalid credentials".to_string()),
                 }),
-            );
+            ).into_response();
         }
     };
 
-    // Generate a simple token (in production, use JWT)
-    let token = format!("token_{}", uuid::Uuid::new_v4());
+    // Second factor, when enforced and the user has enrolled
+    if let Err(response) = check_second_factor(&state, &user.id, payload.totp_code.as_deref(), &throttle_keys).await {
+        return response;
+    }
 
-    // Store session in database
-    let _ = sqlx::query!(
-        "INSERT INTO sessions (user_id, token, expires_at) VALUES (?, ?, datetime('now', '+7 days'))",
-        user.id,
-        token
-    )
-    .execute(&state.db_pool)
-    .await;
+    // Successful sign in clears the failure history
+    for key in &throttle_keys {
+        state.login_throttle.record_success(key);
+    }
+
+    // Create a session for this device
+    let token = match sessions::create_session(&state.db_pool, &user.id, &device_info(&headers, addr)).await {
+        Ok(token) => token,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthResponse {
+                    success: false,
+                    user: None,
+                    token: None,
+                    message: Some(format!("Failed to create session: {}", e)),
+                }),
+            ).into_response();
+        }
+    };
 
     (
         StatusCode::OK,
@@ -119,14 +188,33 @@ pub async fn signin(
             token: Some(token),
             message: None,
         }),
-    )
+    ).into_response()
 }
 
 /// Handle user sign up
+#[utoipa::path(
+    post,
+    path = "/api/auth/signup",
+    tag = "auth",
+    request_body = SignUpRequest,
+    responses(
+        (status = 201, description = "Account created", body = AuthResponse),
+        (status = 409, description = "User already exists"),
+        (status = 429, description = "Too many failed attempts"),
+    )
+)]
 pub async fn signup(
     State(state): State<ServerState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<SignUpRequest>,
-) -> impl IntoResponse {
+) -> Response {
+    // Reject early while the IP or account is locked out
+    let throttle_keys = throttle_keys(addr, &payload.email);
+    if let Err(retry_after) = check_throttle(&state, &throttle_keys) {
+        return too_many_attempts(retry_after);
+    }
+
     // Validate input
     if payload.email.is_empty() || payload.password.is_empty() || payload.name.is_empty() {
         return (
@@ -137,15 +225,18 @@ pub async fn signup(
                 token: None,
                 message: Some("All fields are required".to_string()),
             }),
-        );
+        ).into_response();
     }
 
     // Check if user already exists
-    let exists = sqlx::query!("SELECT id FROM users WHERE email = ?", payload.email)
+    let exists = sqlx::query("SELECT id FROM users WHERE email = ?")
+        .bind(&payload.email)
         .fetch_optional(&state.db_pool)
         .await;
 
     if exists.is_ok() && exists.unwrap().is_some() {
+        // Repeated probing for existing emails counts against the caller
+        record_failure(&state, &throttle_keys);
         return (
             StatusCode::CONFLICT,
             Json(AuthResponse {
@@ -154,21 +245,36 @@ pub async fn signup(
                 token: None,
                 message: Some("User already exists".to_string()),
             }),
-        );
+        ).into_response();
     }
 
-    // Create new user (simplified - should use proper password hashing)
+    let password = match crate::passwords::hash(&payload.password) {
+        Ok(password) => password,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthResponse {
+                    success: false,
+                    user: None,
+                    token: None,
+                    message: Some(e),
+                }),
+            ).into_response();
+        }
+    };
+
+    // Create new user
     let user_id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().to_rfc3339();
 
-    match sqlx::query!(
-        "INSERT INTO users (id, name, email, password_hash, created_at) VALUES (?, ?, ?, ?, ?)",
-        user_id,
-        payload.name,
-        payload.email,
-        payload.password, // Should be hashed in production
-        now
+    match sqlx::query(
+        "INSERT INTO users (id, name, email, password, created_at) VALUES (?, ?, ?, ?, ?)"
     )
+    .bind(&user_id)
+    .bind(&payload.name)
+    .bind(&payload.email)
+    .bind(&password)
+    .bind(&now)
     .execute(&state.db_pool)
     .await
     {
@@ -180,27 +286,20 @@ pub async fn signup(
                 created_at: now,
             };
 
-            // Generate token
-            let token = format!("token_{}", uuid::Uuid::new_v4());
-
-            // Store session
-            let _ = sqlx::query!(
-                "INSERT INTO sessions (user_id, token, expires_at) VALUES (?, ?, datetime('now', '+7 days'))",
-                user_id,
-                token
-            )
-            .execute(&state.db_pool)
-            .await;
+            // Create a session for this device
+            let token = sessions::create_session(&state.db_pool, &user_id, &device_info(&headers, addr))
+                .await
+                .ok();
 
             (
                 StatusCode::CREATED,
                 Json(AuthResponse {
                     success: true,
                     user: Some(user),
-                    token: Some(token),
+                    token,
                     message: None,
                 }),
-            )
+            ).into_response()
         }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -210,25 +309,26 @@ pub async fn signup(
                 token: None,
                 message: Some(format!("Failed to create user: {}", e)),
             }),
-        ),
+        ).into_response(),
     }
 }
 
 /// Handle user sign out
+#[utoipa::path(
+    post,
+    path = "/api/auth/signout",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Signed out"),
+    )
+)]
 pub async fn signout(
     State(state): State<ServerState>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    // Get token from headers
-    if let Some(auth_header) = headers.get("authorization") {
-        if let Ok(auth_str) = auth_header.to_str() {
-            let token = auth_str.trim_start_matches("Bearer ");
-
-            // Delete session
-            let _ = sqlx::query!("DELETE FROM sessions WHERE token = ?", token)
-                .execute(&state.db_pool)
-                .await;
-        }
+    // Delete the session for the presented token
+    if let Some(token) = bearer_token(&headers) {
+        let _ = sessions::revoke_token(&state.db_pool, token).await;
     }
 
     Json(serde_json::json!({
@@ -238,6 +338,15 @@ pub async fn signout(
 }
 
 /// Get current session
+#[utoipa::path(
+    get,
+    path = "/api/auth/session",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Current user"),
+        (status = 401, description = "Invalid or expired session"),
+    )
+)]
 pub async fn get_session(
     State(state): State<ServerState>,
     headers: HeaderMap,
@@ -270,23 +379,18 @@ pub async fn get_session(
     };
 
     // Query session and user
-    match sqlx::query!(
+    match sqlx::query(
         "SELECT u.id, u.name, u.email, u.created_at
          FROM sessions s
          JOIN users u ON s.user_id = u.id
-         WHERE s.token = ? AND s.expires_at > datetime('now')",
-        token
+         WHERE s.token = ? AND s.expires_at > datetime('now')"
     )
+    .bind(token)
     .fetch_one(&state.db_pool)
     .await
     {
         Ok(record) => {
-            let user = User {
-                id: record.id,
-                name: record.name,
-                email: record.email,
-                created_at: record.created_at,
-            };
+            let user = user_from_row(&record);
 
             (
                 StatusCode::OK,
@@ -304,4 +408,441 @@ pub async fn get_session(
             })),
         ),
     }
-}
\ No newline at end of file
+}
+
+/// List active sessions for the signed-in user
+#[utoipa::path(
+    get,
+    path = "/api/auth/sessions",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Active sessions for the signed-in user"),
+        (status = 401, description = "Invalid or expired session"),
+    )
+)]
+pub async fn list_sessions(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let Some((user_id, token)) = authenticate(&state.db_pool, &headers).await else {
+        return unauthorized().into_response();
+    };
+
+    match sessions::list_sessions(&state.db_pool, Some(&user_id), Some(&token)).await {
+        Ok(sessions) => Json(serde_json::json!({
+            "success": true,
+            "sessions": sessions
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("Failed to list sessions: {}", e)
+            })),
+        ).into_response(),
+    }
+}
+
+/// Revoke a single session belonging to the signed-in user
+#[utoipa::path(
+    delete,
+    path = "/api/auth/sessions/{id}",
+    tag = "auth",
+    params(("id" = String, Path, description = "Session ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Session revoked"),
+        (status = 404, description = "Session not found"),
+    )
+)]
+pub async fn revoke_session(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some((user_id, _)) = authenticate(&state.db_pool, &headers).await else {
+        return unauthorized().into_response();
+    };
+
+    match sessions::revoke_session(&state.db_pool, &id, Some(&user_id)).await {
+        Ok(true) => Json(serde_json::json!({
+            "success": true,
+            "message": "Session revoked"
+        })).into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Session not found"
+            })),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("Failed to revoke session: {}", e)
+            })),
+        ).into_response(),
+    }
+}
+
+/// Revoke every other session belonging to the signed-in user
+#[utoipa::path(
+    delete,
+    path = "/api/auth/sessions",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Other sessions revoked"),
+    )
+)]
+pub async fn revoke_all_sessions(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let Some((user_id, token)) = authenticate(&state.db_pool, &headers).await else {
+        return unauthorized().into_response();
+    };
+
+    match sessions::revoke_all_sessions(&state.db_pool, Some(&user_id), Some(&token)).await {
+        Ok(revoked) => Json(serde_json::json!({
+            "success": true,
+            "revoked": revoked
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("Failed to revoke sessions: {}", e)
+            })),
+        ).into_response(),
+    }
+}
+
+/// Start TOTP enrollment, returning the secret and provisioning URI
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/enroll",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "TOTP secret and otpauth:// provisioning URI"),
+        (status = 403, description = "Paired devices cannot manage two-factor authentication"),
+    )
+)]
+pub async fn enroll_totp(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+) -> Response {
+    if user.device_id.is_some() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Paired devices cannot manage two-factor authentication"
+            })),
+        ).into_response();
+    }
+
+    match crate::totp::begin_enrollment(&state.db_pool, &user.id, &user.email).await {
+        Ok(enrollment) => Json(serde_json::json!({
+            "success": true,
+            "secret": enrollment.secret,
+            "otpauth_uri": enrollment.otpauth_uri
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("Failed to start enrollment: {}", e)
+            })),
+        ).into_response(),
+    }
+}
+
+/// Confirm TOTP enrollment with a code from the authenticator app
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/confirm",
+    tag = "auth",
+    request_body = TotpCodeRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Two-factor authentication enabled"),
+        (status = 401, description = "Invalid two-factor code"),
+    )
+)]
+pub async fn confirm_totp(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<TotpCodeRequest>,
+) -> Response {
+    match crate::totp::confirm_enrollment(&state.db_pool, &user.id, &payload.code).await {
+        Ok(true) => Json(serde_json::json!({
+            "success": true,
+            "message": "Two-factor authentication enabled"
+        })).into_response(),
+        Ok(false) => invalid_totp_code(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": e
+            })),
+        ).into_response(),
+    }
+}
+
+/// Disable TOTP, requiring a current code
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/disable",
+    tag = "auth",
+    request_body = TotpCodeRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Two-factor authentication disabled"),
+        (status = 401, description = "Invalid two-factor code"),
+    )
+)]
+pub async fn disable_totp(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<TotpCodeRequest>,
+) -> Response {
+    let result = match crate::totp::verify_user_code(&state.db_pool, &user.id, &payload.code).await {
+        Ok(true) => crate::totp::disable(&state.db_pool, &user.id).await,
+        Ok(false) => return invalid_totp_code(),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(()) => Json(serde_json::json!({
+            "success": true,
+            "message": "Two-factor authentication disabled"
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": e
+            })),
+        ).into_response(),
+    }
+}
+
+/// Exchange a pairing code shown in the desktop app for a device token
+#[utoipa::path(
+    post,
+    path = "/api/auth/pair",
+    tag = "auth",
+    request_body = PairDeviceRequest,
+    responses(
+        (status = 201, description = "Device token and granted scope"),
+        (status = 401, description = "Invalid or expired pairing code"),
+        (status = 429, description = "Too many failed attempts"),
+    )
+)]
+pub async fn pair_device(
+    State(state): State<ServerState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<PairDeviceRequest>,
+) -> Response {
+    // Pairing codes are short, so guessing is throttled like sign in
+    let throttle_key = format!("pair:{}", addr.ip());
+    if let Err(retry_after) = check_throttle(&state, std::slice::from_ref(&throttle_key)) {
+        return too_many_attempts(retry_after);
+    }
+
+    let device_name = payload
+        .device_name
+        .unwrap_or_else(|| sessions::describe_device(
+            headers.get(header::USER_AGENT).and_then(|value| value.to_str().ok()),
+        ));
+
+    match crate::pairing::exchange_code(&state.db_pool, &payload.code, &device_name, Some(&addr.ip().to_string())).await {
+        Ok(device) => {
+            state.login_throttle.record_success(&throttle_key);
+            (
+                StatusCode::CREATED,
+                Json(serde_json::json!({
+                    "success": true,
+                    "device_id": device.device_id,
+                    "token": device.token,
+                    "scope": device.scope
+                })),
+            ).into_response()
+        }
+        Err(e) => {
+            state.login_throttle.record_failure(&throttle_key);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({
+                    "success": false,
+                    "message": e
+                })),
+            ).into_response()
+        }
+    }
+}
+
+/// Extract the bearer token from the Authorization header
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()
+        .map(|value| value.trim_start_matches("Bearer ").trim())
+        .filter(|token| !token.is_empty())
+}
+
+/// Resolve the user for the presented token, returning (user_id, token)
+async fn authenticate(pool: &SqlitePool, headers: &HeaderMap) -> Option<(String, String)> {
+    let token = bearer_token(headers)?;
+    let user_id = sessions::touch_session(pool, token).await.ok()??;
+    Some((user_id, token.to_string()))
+}
+
+fn user_from_row(row: &sqlx::sqlite::SqliteRow) -> User {
+    User {
+        id: row.get("id"),
+        name: row.get::<Option<String>, _>("name").unwrap_or_default(),
+        email: row.get("email"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Capture device details for a new session
+fn device_info(headers: &HeaderMap, addr: SocketAddr) -> DeviceInfo {
+    DeviceInfo {
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from),
+        ip_address: Some(addr.ip().to_string()),
+    }
+}
+
+fn unauthorized() -> impl IntoResponse {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({
+            "success": false,
+            "message": "Invalid or expired session"
+        })),
+    )
+}
+
+/// Require a valid TOTP code when 2FA is enforced and the user is enrolled
+///
+/// A missing code gets a 401 with `two_factor_required` so clients can prompt
+/// for it; a wrong code counts as a failed sign in. Sign in fails when
+/// whether the code is needed can't be checked.
+async fn check_second_factor(
+    state: &ServerState,
+    user_id: &str,
+    code: Option<&str>,
+    throttle_keys: &[String],
+) -> Result<(), Response> {
+    let required = crate::totp::is_required(&state.db_pool)
+        .await
+        .map_err(|e| second_factor_error(format!("Database error: {}", e)))?;
+    if !required {
+        return Ok(());
+    }
+    let enrolled = crate::totp::is_enrolled(&state.db_pool, user_id)
+        .await
+        .map_err(second_factor_error)?;
+    if !enrolled {
+        return Ok(());
+    }
+
+    let Some(code) = code else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "success": false,
+                "two_factor_required": true,
+                "message": "Two-factor code required"
+            })),
+        ).into_response());
+    };
+
+    match crate::totp::verify_user_code(&state.db_pool, user_id, code).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            record_failure(state, throttle_keys);
+            Err(invalid_totp_code())
+        }
+        Err(e) => Err(second_factor_error(e)),
+    }
+}
+
+fn second_factor_error(message: String) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    ).into_response()
+}
+
+fn invalid_totp_code() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({
+            "success": false,
+            "two_factor_required": true,
+            "message": "Invalid two-factor code"
+        })),
+    ).into_response()
+}
+
+/// Throttle keys for an auth attempt: one per IP and one per account
+fn throttle_keys(addr: SocketAddr, email: &str) -> [String; 2] {
+    [
+        format!("ip:{}", addr.ip()),
+        format!("account:{}", email.trim().to_lowercase()),
+    ]
+}
+
+/// Return the longest remaining lockout across the given keys
+fn check_throttle(state: &ServerState, keys: &[String]) -> Result<(), Duration> {
+    let retry_after = keys
+        .iter()
+        .filter_map(|key| state.login_throttle.check(key).err())
+        .max();
+
+    match retry_after {
+        Some(duration) => Err(duration),
+        None => Ok(()),
+    }
+}
+
+fn record_failure(state: &ServerState, keys: &[String]) {
+    for key in keys {
+        state.login_throttle.record_failure(key);
+    }
+}
+
+/// 429 response with a Retry-After header (whole seconds, rounded up)
+fn too_many_attempts(retry_after: Duration) -> Response {
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, seconds.to_string())],
+        Json(AuthResponse {
+            success: false,
+            user: None,
+            token: None,
+            message: Some(format!(
+                "Too many failed attempts. Try again in {} seconds.",
+                seconds
+            )),
+        }),
+    ).into_response()
+}
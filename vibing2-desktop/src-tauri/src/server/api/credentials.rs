@@ -0,0 +1,137 @@
+// Credential management API endpoints (owner only)
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use crate::auth::{self, Provider};
+use crate::server::ServerState;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SaveProviderKeyRequest {
+    pub api_key: String,
+    pub base_url: Option<String>,
+}
+
+/// List providers and whether each has a key configured
+#[utoipa::path(
+    get,
+    path = "/api/credentials",
+    tag = "credentials",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Providers and whether each has a key configured"),
+        (status = 403, description = "Owner role required"),
+    )
+)]
+pub async fn list_providers(
+    State(state): State<ServerState>,
+) -> impl IntoResponse {
+    match auth::list_provider_info(&state.db_pool).await {
+        Ok(providers) => Json(serde_json::json!({
+            "success": true,
+            "providers": providers
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": e
+            })),
+        ).into_response(),
+    }
+}
+
+/// Validate and store a provider key
+#[utoipa::path(
+    put,
+    path = "/api/credentials/{provider}",
+    tag = "credentials",
+    params(("provider" = String, Path, description = "anthropic, openai, or custom")),
+    request_body = SaveProviderKeyRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Key validated and saved"),
+        (status = 400, description = "Invalid key or provider"),
+        (status = 403, description = "Owner role required"),
+    )
+)]
+pub async fn save_provider_key(
+    State(state): State<ServerState>,
+    Path(provider): Path<String>,
+    Json(payload): Json<SaveProviderKeyRequest>,
+) -> impl IntoResponse {
+    let provider = match Provider::parse(&provider) {
+        Ok(provider) => provider,
+        Err(e) => return bad_request(&e).into_response(),
+    };
+
+    match auth::validate_provider_key(provider, &payload.api_key, payload.base_url.as_deref()).await {
+        Ok(true) => {}
+        Ok(false) => return bad_request("Invalid API key").into_response(),
+        Err(e) => return bad_request(&format!("Validation failed: {}", e)).into_response(),
+    }
+
+    match auth::store_provider_key(&state.db_pool, provider, &payload.api_key, payload.base_url.as_deref()).await {
+        Ok(()) => Json(serde_json::json!({
+            "success": true,
+            "message": format!("{} API key saved", provider.display_name())
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": e
+            })),
+        ).into_response(),
+    }
+}
+
+/// Remove a provider key
+#[utoipa::path(
+    delete,
+    path = "/api/credentials/{provider}",
+    tag = "credentials",
+    params(("provider" = String, Path, description = "anthropic, openai, or custom")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Key removed"),
+        (status = 403, description = "Owner role required"),
+    )
+)]
+pub async fn delete_provider_key(
+    State(state): State<ServerState>,
+    Path(provider): Path<String>,
+) -> impl IntoResponse {
+    let provider = match Provider::parse(&provider) {
+        Ok(provider) => provider,
+        Err(e) => return bad_request(&e).into_response(),
+    };
+
+    match auth::delete_provider_key(&state.db_pool, provider).await {
+        Ok(()) => Json(serde_json::json!({
+            "success": true,
+            "message": format!("{} API key removed", provider.display_name())
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": e
+            })),
+        ).into_response(),
+    }
+}
+
+fn bad_request(message: &str) -> impl IntoResponse {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
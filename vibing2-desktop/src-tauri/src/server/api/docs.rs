@@ -0,0 +1,170 @@
+// API documentation - OpenAPI spec for the embedded server
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use super::{agents, attachments, auth, credentials, health, models, projects, stream, templates};
+use crate::server::middleware::csrf;
+
+/// OpenAPI document served at `/api/openapi.json`
+///
+/// Swagger UI is served at `/api/docs`. Handlers are annotated with
+/// `#[utoipa::path]`; add new routes to `paths` below.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Vibing2 Local API",
+        description = "HTTP API of the Vibing2 desktop app's embedded server. Protected routes take a session or paired-device token as `Authorization: Bearer <token>`; mutating browser requests also need the `X-CSRF-Token` header."
+    ),
+    paths(
+        auth::signin,
+        auth::signup,
+        auth::signout,
+        auth::get_session,
+        auth::list_sessions,
+        auth::revoke_session,
+        auth::revoke_all_sessions,
+        auth::enroll_totp,
+        auth::confirm_totp,
+        auth::disable_totp,
+        auth::pair_device,
+        csrf::issue_csrf_token,
+        projects::list_projects,
+        projects::save_project,
+        projects::load_project,
+        projects::get_project,
+        projects::update_project,
+        projects::delete_project,
+        projects::list_shares,
+        projects::share_project,
+        projects::unshare_project,
+        agents::list_agents,
+        agents::get_agent,
+        agents::create_agent,
+        agents::validate_agent,
+        agents::update_agent,
+        agents::delete_agent,
+        agents::get_agent_icon,
+        agents::upload_agent_icon,
+        agents::delete_agent_icon,
+        agents::toggle_favorite,
+        agents::test_agent,
+        stream::handle_stream,
+        stream::handle_complete,
+        stream::handle_compare,
+        stream::pick_reply,
+        stream::list_streams,
+        stream::get_stream,
+        stream::replay_stream,
+        attachments::upload_attachment,
+        attachments::delete_attachment,
+        models::list_models,
+        templates::list_templates,
+        templates::create_template,
+        templates::delete_template,
+        templates::render_template,
+        credentials::list_providers,
+        credentials::save_provider_key,
+        credentials::delete_provider_key,
+        super::health,
+        health::live,
+        health::ready,
+        super::metrics,
+    ),
+    components(schemas(
+        auth::SignInRequest,
+        auth::SignUpRequest,
+        auth::PairDeviceRequest,
+        auth::TotpCodeRequest,
+        auth::AuthResponse,
+        auth::User,
+        projects::Project,
+        projects::ProjectFile,
+        projects::SaveProjectRequest,
+        projects::LoadProjectRequest,
+        projects::ShareProjectRequest,
+        crate::sharing::ProjectShare,
+        crate::sharing::Access,
+        crate::sharing::Grantee,
+        agents::Agent,
+        agents::NewAgent,
+        agents::AgentUpdate,
+        agents::AgentTest,
+        agents::NewIcon,
+        agents::Diagnostic,
+        crate::agent_lint::Severity,
+        crate::tool_policy::ToolPermission,
+        stream::StreamRequest,
+        stream::FileContent,
+        crate::comparison::CompareRequest,
+        stream::PickRequest,
+        crate::attachments::Attachment,
+        crate::attachments::NewAttachment,
+        stream::StreamResponse,
+        crate::generation::CompletionResponse,
+        crate::generation::StreamError,
+        crate::llm::ErrorCode,
+        crate::llm::ResponseFormat,
+        crate::llm::Priority,
+        crate::llm::QueueStatus,
+        crate::llm::SchemaError,
+        crate::usage::Usage,
+        crate::usage::ModelPrice,
+        crate::usage::CostEstimate,
+        crate::server::streams::StreamSession,
+        crate::server::streams::SessionStatus,
+        crate::llm::ModelInfo,
+        crate::llm::ProviderStatus,
+        crate::llm::ModelCatalog,
+        crate::templates::PromptTemplate,
+        crate::templates::NewPromptTemplate,
+        templates::RenderTemplateRequest,
+        credentials::SaveProviderKeyRequest,
+        health::Readiness,
+        health::ComponentHealth,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Sign in, sessions, two-factor, and device pairing"),
+        (name = "projects", description = "Projects, files, and sharing"),
+        (name = "agents", description = "Agent catalog"),
+        (name = "stream", description = "Streaming generations"),
+        (name = "attachments", description = "Images for vision prompts"),
+        (name = "models", description = "Model catalog per provider"),
+        (name = "templates", description = "Prompt templates"),
+        (name = "credentials", description = "Provider API keys (owner only)"),
+        (name = "system", description = "Health and metrics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Registers the `bearer` security scheme referenced by protected routes
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_covers_routes() {
+        let spec = ApiDoc::openapi();
+        let paths = &spec.paths.paths;
+
+        assert!(paths.contains_key("/api/auth/signin"));
+        assert!(paths.contains_key("/api/projects/{id}"));
+        assert!(paths.contains_key("/api/agent/stream"));
+        assert!(spec.components.unwrap().security_schemes.contains_key("bearer"));
+    }
+}
@@ -0,0 +1,152 @@
+// Health API endpoints - Liveness and readiness probes
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::time::Instant;
+use utoipa::ToSchema;
+use crate::server::ServerState;
+
+/// State of one readiness component
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentHealth {
+    /// `ok` or `error`
+    pub status: String,
+    pub message: Option<String>,
+    /// Time taken by the check
+    pub latency_ms: u64,
+}
+
+/// Readiness breakdown for the frontend's startup state
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Readiness {
+    /// `ready` when every component is ok, otherwise `starting`
+    pub status: String,
+    /// Database answers queries
+    pub database: ComponentHealth,
+    /// Schema is at the version this build expects
+    pub migrations: ComponentHealth,
+    /// Static directory exists and contains index.html
+    pub static_files: ComponentHealth,
+    pub timestamp: String,
+}
+
+impl ComponentHealth {
+    fn from_result(result: Result<(), String>, started: Instant) -> Self {
+        let latency_ms = started.elapsed().as_millis() as u64;
+        match result {
+            Ok(()) => Self {
+                status: "ok".to_string(),
+                message: None,
+                latency_ms,
+            },
+            Err(message) => Self {
+                status: "error".to_string(),
+                message: Some(message),
+                latency_ms,
+            },
+        }
+    }
+
+    fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+/// Liveness probe: the process is up and serving requests
+#[utoipa::path(
+    get,
+    path = "/api/health/live",
+    tag = "system",
+    responses(
+        (status = 200, description = "Server process is alive"),
+    )
+)]
+pub async fn live() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "status": "alive",
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+    }))
+}
+
+/// Readiness probe: database, migrations, and static files are usable
+///
+/// Responds 503 with the same body while any component is failing.
+#[utoipa::path(
+    get,
+    path = "/api/health/ready",
+    tag = "system",
+    responses(
+        (status = 200, description = "All components ready", body = Readiness),
+        (status = 503, description = "At least one component not ready", body = Readiness),
+    )
+)]
+pub async fn ready(State(state): State<ServerState>) -> Response {
+    let readiness = check_readiness(&state).await;
+    let status = if readiness.status == "ready" {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(readiness)).into_response()
+}
+
+async fn check_readiness(state: &ServerState) -> Readiness {
+    let started = Instant::now();
+    let database = sqlx::query("SELECT 1")
+        .execute(&state.db_pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Database unreachable: {}", e));
+    let database = ComponentHealth::from_result(database, started);
+
+    let started = Instant::now();
+    let migrations = match crate::database::schema_version(&state.db_pool).await {
+        Ok(version) if version >= crate::database::SCHEMA_VERSION => Ok(()),
+        Ok(version) => Err(format!(
+            "Schema version {} is behind {}",
+            version,
+            crate::database::SCHEMA_VERSION
+        )),
+        Err(e) => Err(format!("Failed to read schema version: {}", e)),
+    };
+    let migrations = ComponentHealth::from_result(migrations, started);
+
+    let started = Instant::now();
+    let static_files = if state.static_dir.join("index.html").is_file() {
+        Ok(())
+    } else {
+        Err(format!("No index.html in {}", state.static_dir.display()))
+    };
+    let static_files = ComponentHealth::from_result(static_files, started);
+
+    let all_ok = database.is_ok() && migrations.is_ok() && static_files.is_ok();
+
+    Readiness {
+        status: if all_ok { "ready" } else { "starting" }.to_string(),
+        database,
+        migrations,
+        static_files,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_component_from_result() {
+        let ok = ComponentHealth::from_result(Ok(()), Instant::now());
+        assert!(ok.is_ok());
+        assert!(ok.message.is_none());
+
+        let failed = ComponentHealth::from_result(Err("down".to_string()), Instant::now());
+        assert!(!failed.is_ok());
+        assert_eq!(failed.message.as_deref(), Some("down"));
+    }
+}
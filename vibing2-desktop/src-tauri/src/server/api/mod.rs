@@ -2,50 +2,150 @@
 use axum::{
     Router,
     routing::{get, post},
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 
+pub mod attachments;
 pub mod auth;
+pub mod credentials;
+pub mod docs;
+pub mod health;
+pub mod models;
 pub mod projects;
 pub mod agents;
 pub mod stream;
+pub mod templates;
 
 use crate::server::ServerState;
+use crate::server::middleware::auth::authorize;
+use crate::server::middleware::csrf::{csrf_middleware, issue_csrf_token};
+use crate::server::middleware::lan::lan_guard_middleware;
+use crate::server::middleware::rate_limit::rate_limit_middleware;
 
 /// Create all API routes
-pub fn create_api_routes() -> Router<ServerState> {
+///
+/// Who may call each route is declared in one place,
+/// [`ROUTE_PERMISSIONS`](crate::server::middleware::auth::ROUTE_PERMISSIONS),
+/// and enforced by the `authorize` route layer: auth, agent catalog, and
+/// health routes are public, viewers may only read projects, editors may
+/// modify them and run generations, and only owners may manage credentials.
+///
+/// Every mutating request from a browser must also carry the CSRF token
+/// issued by `/auth/csrf`, and all routes are rate limited per IP and token.
+/// In LAN mode, requests from other machines need a token for everything but
+/// sign-in and pairing.
+pub fn create_api_routes(state: ServerState) -> Router<ServerState> {
     Router::new()
         // Authentication routes
         .route("/auth/signin", post(auth::signin))
         .route("/auth/signup", post(auth::signup))
         .route("/auth/signout", post(auth::signout))
         .route("/auth/session", get(auth::get_session))
+        .route("/auth/csrf", get(issue_csrf_token))
+        .route(
+            "/auth/sessions",
+            get(auth::list_sessions).delete(auth::revoke_all_sessions),
+        )
+        .route("/auth/sessions/:id", axum::routing::delete(auth::revoke_session))
+        .route("/auth/pair", post(auth::pair_device))
+
+        // Two-factor enrollment routes
+        .route("/auth/2fa/enroll", post(auth::enroll_totp))
+        .route("/auth/2fa/confirm", post(auth::confirm_totp))
+        .route("/auth/2fa/disable", post(auth::disable_totp))
+
+        // Agent routes
+        .route("/agents", post(agents::create_agent))
+        .route("/agents/list", get(agents::list_agents))
+        .route("/agents/validate", post(agents::validate_agent))
+        .route(
+            "/agents/:id",
+            get(agents::get_agent)
+                .put(agents::update_agent)
+                .delete(agents::delete_agent),
+        )
+        .route(
+            "/agents/:id/icon",
+            get(agents::get_agent_icon)
+                .put(agents::upload_agent_icon)
+                .delete(agents::delete_agent_icon),
+        )
+        .route("/agents/:id/favorite", post(agents::toggle_favorite))
+        .route("/agents/:id/test", post(agents::test_agent))
+
+        // Health and metrics
+        .route("/health", get(health))
+        .route("/health/live", get(health::live))
+        .route("/health/ready", get(health::ready))
+        .route("/metrics", get(metrics))
 
         // Project management routes
         .route("/projects/list", get(projects::list_projects))
         .route("/projects/save", post(projects::save_project))
         .route("/projects/load", post(projects::load_project))
-        .route("/projects/:id", get(projects::get_project))
-        .route("/projects/:id", post(projects::update_project))
-        .route("/projects/:id", axum::routing::delete(projects::delete_project))
-
-        // Agent routes
-        .route("/agents/list", get(agents::list_agents))
-        .route("/agents/:id", get(agents::get_agent))
+        .route(
+            "/projects/:id",
+            get(projects::get_project)
+                .post(projects::update_project)
+                .delete(projects::delete_project),
+        )
+        .route(
+            "/projects/:id/shares",
+            get(projects::list_shares).post(projects::share_project),
+        )
+        .route(
+            "/projects/:id/shares/:share_id",
+            axum::routing::delete(projects::unshare_project),
+        )
 
         // Streaming routes
         .route("/agent/stream", post(stream::handle_stream))
+        .route("/agent/complete", post(stream::handle_complete))
+        .route("/agent/compare", post(stream::handle_compare))
+        .route("/agent/compare/pick", post(stream::pick_reply))
+        .route("/agent/streams", get(stream::list_streams))
+        .route("/agent/streams/:id", get(stream::get_stream))
+        .route("/agent/streams/:id/replay", get(stream::replay_stream))
 
-        // Health and metrics
-        .route("/health", get(health))
-        .route("/metrics", get(metrics))
+        // Image attachments
+        .route("/attachments", post(attachments::upload_attachment))
+        .route("/attachments/:id", axum::routing::delete(attachments::delete_attachment))
+
+        // Model catalog
+        .route("/models", get(models::list_models))
+
+        // Prompt template routes
+        .route("/templates", get(templates::list_templates).post(templates::create_template))
+        .route("/templates/:id", axum::routing::delete(templates::delete_template))
+        .route("/templates/:id/render", post(templates::render_template))
+
+        // Credential management routes
+        .route("/credentials", get(credentials::list_providers))
+        .route(
+            "/credentials/:provider",
+            axum::routing::put(credentials::save_provider_key)
+                .delete(credentials::delete_provider_key),
+        )
+
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), authorize))
+        .layer(axum::middleware::from_fn(csrf_middleware))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), lan_guard_middleware))
+        .layer(axum::middleware::from_fn_with_state(state, rate_limit_middleware))
 }
 
 /// Health check endpoint
+#[utoipa::path(
+    get,
+    path = "/api/health",
+    tag = "system",
+    responses(
+        (status = 200, description = "Server is healthy"),
+    )
+)]
 async fn health() -> impl IntoResponse {
     Json(json!({
         "status": "healthy",
@@ -55,15 +155,45 @@ async fn health() -> impl IntoResponse {
 }
 
 /// Metrics endpoint
-async fn metrics(State(state): State<ServerState>) -> impl IntoResponse {
-    // TODO: Implement actual metrics collection
-    Json(json!({
-        "uptime": 0,
-        "requests_total": 0,
-        "active_connections": 0,
-        "memory_usage": 0,
-        "cpu_usage": 0,
-    }))
+///
+/// Returns JSON by default, or the Prometheus text format when requested via
+/// `?format=prometheus` or an `Accept` header preferring text (as Prometheus
+/// scrapers send).
+#[utoipa::path(
+    get,
+    path = "/api/metrics",
+    tag = "system",
+    params(("format" = Option<String>, Query, description = "`prometheus` for the text exposition format")),
+    responses(
+        (status = 200, description = "Request counts, latency, connections, DB pool stats, and uptime"),
+    )
+)]
+async fn metrics(
+    State(state): State<ServerState>,
+    Query(query): Query<MetricsQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let prometheus = query.format.as_deref() == Some("prometheus")
+        || accept.starts_with("text/plain")
+        || accept.starts_with("application/openmetrics-text");
+
+    if prometheus {
+        (
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+            state.metrics.render_prometheus(&state.db_pool),
+        ).into_response()
+    } else {
+        Json(state.metrics.snapshot(&state.db_pool)).into_response()
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct MetricsQuery {
+    format: Option<String>,
 }
 
 /// Generic error response
@@ -0,0 +1,44 @@
+// Model catalog API endpoints
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use crate::server::ServerState;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ModelsQuery {
+    /// Ask each provider again instead of using the cached lists
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// List the models each configured provider offers
+///
+/// Providers that can't be reached are reported in `providers` with their
+/// error rather than failing the request.
+#[utoipa::path(
+    get,
+    path = "/api/models",
+    tag = "models",
+    params(ModelsQuery),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Models and provider status", body = crate::llm::ModelCatalog),
+    )
+)]
+pub async fn list_models(
+    State(state): State<ServerState>,
+    Query(query): Query<ModelsQuery>,
+) -> impl IntoResponse {
+    let catalog = crate::llm::list_models(&state.db_pool, query.refresh).await;
+
+    Json(serde_json::json!({
+        "success": true,
+        "models": catalog.models,
+        "providers": catalog.providers
+    }))
+}
@@ -1,14 +1,18 @@
 // Projects API endpoints
 use axum::{
-    extract::{State, Path},
+    extract::{Extension, State, Path, Query},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
 use crate::server::ServerState;
+use crate::server::middleware::auth::AuthUser;
+use crate::sharing::{self, Access, Grantee};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct Project {
     pub id: String,
     pub name: String,
@@ -20,14 +24,14 @@ pub struct Project {
     pub user_id: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ProjectFile {
     pub path: String,
     pub content: String,
     pub language: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SaveProjectRequest {
     pub name: String,
     pub description: Option<String>,
@@ -35,60 +39,254 @@ pub struct SaveProjectRequest {
     pub files: Vec<ProjectFile>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LoadProjectRequest {
     pub id: String,
 }
 
-/// List all projects for the current user
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ShareProjectRequest {
+    pub grantee_type: Grantee,
+    pub grantee_id: String,
+    pub access: Access,
+}
+
+/// Default and maximum page sizes for `/projects/list`
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// Query parameters for `/projects/list`
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListProjectsQuery {
+    /// Page size (default 20, max 100)
+    pub limit: Option<u32>,
+    /// Rows to skip; ignored when `cursor` is set
+    pub offset: Option<u32>,
+    /// `next_cursor` from a previous page
+    pub cursor: Option<String>,
+    /// Case-insensitive match on name or description
+    pub search: Option<String>,
+    /// `updated` (default), `created`, or `name`
+    pub sort: Option<String>,
+    /// `desc` (default) or `asc`
+    pub order: Option<String>,
+    /// Only projects of this type (e.g. `website`, `game`)
+    #[serde(rename = "type")]
+    #[param(rename = "type")]
+    pub project_type: Option<String>,
+}
+
+impl ListProjectsQuery {
+    fn page_size(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    fn start_offset(&self) -> Result<u32, String> {
+        match self.cursor.as_deref() {
+            Some(cursor) => decode_cursor(cursor),
+            None => Ok(self.offset.unwrap_or(0)),
+        }
+    }
+
+    /// ORDER BY clause from a fixed whitelist
+    fn order_by(&self) -> &'static str {
+        let descending = !matches!(self.order.as_deref(), Some("asc"));
+        match (self.sort.as_deref(), descending) {
+            (Some("name"), true) => "name COLLATE NOCASE DESC, id DESC",
+            (Some("name"), false) => "name COLLATE NOCASE ASC, id ASC",
+            (Some("created"), true) => "created_at DESC, id DESC",
+            (Some("created"), false) => "created_at ASC, id ASC",
+            (_, true) => "updated_at DESC, id DESC",
+            (_, false) => "updated_at ASC, id ASC",
+        }
+    }
+}
+
+/// Cursors are opaque to clients; they encode the next offset
+fn encode_cursor(offset: u32) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(format!("o:{}", offset))
+}
+
+fn decode_cursor(cursor: &str) -> Result<u32, String> {
+    use base64::Engine;
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|value| value.strip_prefix("o:").and_then(|offset| offset.parse().ok()))
+        .ok_or_else(|| "Invalid cursor".to_string())
+}
+
+/// Append the visibility and filter conditions shared by the count and page
+/// queries
+fn push_project_filters<'a>(
+    builder: &mut QueryBuilder<'a, Sqlite>,
+    user: &'a AuthUser,
+    query: &'a ListProjectsQuery,
+) {
+    // Paired devices only see projects shared with the device itself
+    match user.device_id.as_deref() {
+        Some(device_id) => {
+            builder
+                .push(" WHERE id IN (SELECT project_id FROM project_shares WHERE grantee_type = 'device' AND grantee_id = ")
+                .push_bind(device_id)
+                .push(")");
+        }
+        None => {
+            builder
+                .push(" WHERE (user_id = ")
+                .push_bind(&user.id)
+                .push(" OR id IN (SELECT project_id FROM project_shares WHERE grantee_type = 'user' AND grantee_id = ")
+                .push_bind(&user.id)
+                .push("))");
+        }
+    }
+
+    if let Some(search) = query.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        let pattern = format!(
+            "%{}%",
+            search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        builder
+            .push(" AND (name LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR description LIKE ")
+            .push_bind(pattern)
+            .push(" ESCAPE '\\')");
+    }
+
+    if let Some(project_type) = query.project_type.as_deref().filter(|t| !t.is_empty()) {
+        builder.push(" AND project_type = ").push_bind(project_type);
+    }
+}
+
+/// List projects owned by or shared with the current user
+///
+/// Paginated with `limit`/`offset` or `cursor`; filterable by `search` and
+/// `type`; sortable by `sort`/`order`. Returns the total match count and a
+/// `next_cursor` when more pages remain.
+#[utoipa::path(
+    get,
+    path = "/api/projects/list",
+    tag = "projects",
+    params(ListProjectsQuery),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "A page of projects owned by or shared with the caller"),
+        (status = 400, description = "Invalid cursor"),
+    )
+)]
 pub async fn list_projects(
     State(state): State<ServerState>,
-) -> impl IntoResponse {
-    // For now, return mock data - in production, check auth and query DB
-    let projects = vec![
-        Project {
-            id: "1".to_string(),
-            name: "Sample Project".to_string(),
-            description: Some("A sample project".to_string()),
-            prompt: "Create a web app".to_string(),
-            files: vec![],
-            created_at: chrono::Utc::now().to_rfc3339(),
-            updated_at: chrono::Utc::now().to_rfc3339(),
-            user_id: "user_1".to_string(),
-        },
-    ];
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<ListProjectsQuery>,
+) -> Response {
+    let limit = query.page_size();
+    let offset = match query.start_offset() {
+        Ok(offset) => offset,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "success": false,
+                    "message": message
+                }))
+            ).into_response();
+        }
+    };
+
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM projects");
+    push_project_filters(&mut count_query, &user, &query);
+    let total = match count_query.build_query_scalar::<i64>().fetch_one(&state.db_pool).await {
+        Ok(total) => total,
+        Err(e) => return database_error("Failed to list projects", e),
+    };
+
+    let mut page_query = QueryBuilder::new("SELECT id FROM projects");
+    push_project_filters(&mut page_query, &user, &query);
+    page_query
+        .push(" ORDER BY ")
+        .push(query.order_by())
+        .push(" LIMIT ")
+        .push_bind(limit as i64)
+        .push(" OFFSET ")
+        .push_bind(offset as i64);
+
+    let rows = match page_query.build().fetch_all(&state.db_pool).await {
+        Ok(rows) => rows,
+        Err(e) => return database_error("Failed to list projects", e),
+    };
+
+    let mut projects = Vec::new();
+    for row in rows {
+        let id: String = row.get("id");
+        match fetch_project(&state.db_pool, &id).await {
+            Ok(Some(project)) => projects.push(project),
+            Ok(None) => {}
+            Err(e) => return database_error("Failed to list projects", e),
+        }
+    }
+
+    let next_offset = offset as i64 + limit as i64;
+    let next_cursor = (next_offset < total).then(|| encode_cursor(next_offset as u32));
 
     Json(serde_json::json!({
         "success": true,
-        "projects": projects
-    }))
+        "projects": projects,
+        "total": total,
+        "limit": limit,
+        "offset": offset,
+        "next_cursor": next_cursor
+    })).into_response()
 }
 
 /// Save a new project or update existing
+#[utoipa::path(
+    post,
+    path = "/api/projects/save",
+    tag = "projects",
+    request_body = SaveProjectRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Project created", body = Project),
+        (status = 403, description = "Editor role required"),
+    )
+)]
 pub async fn save_project(
     State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
     Json(payload): Json<SaveProjectRequest>,
-) -> impl IntoResponse {
+) -> Response {
     // Generate project ID
     let project_id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().to_rfc3339();
 
     // Save to database
-    match sqlx::query!(
-        "INSERT INTO projects (id, name, description, prompt, files_json, created_at, updated_at, user_id)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-        project_id,
-        payload.name,
-        payload.description,
-        payload.prompt,
-        serde_json::to_string(&payload.files).unwrap_or_else(|_| "[]".to_string()),
-        now,
-        now,
-        "user_1" // Should get from auth
-    )
-    .execute(&state.db_pool)
-    .await
-    {
+    let result = async {
+        let mut tx = state.db_pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO projects (id, name, description, prompt, project_type, created_at, updated_at, user_id)
+             VALUES (?, ?, ?, ?, 'web', ?, ?, ?)"
+        )
+        .bind(&project_id)
+        .bind(&payload.name)
+        .bind(&payload.description)
+        .bind(&payload.prompt)
+        .bind(&now)
+        .bind(&now)
+        .bind(&user.id)
+        .execute(&mut *tx)
+        .await?;
+
+        write_files(&mut tx, &project_id, &payload.files).await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
         Ok(_) => {
             let project = Project {
                 id: project_id,
@@ -98,7 +296,7 @@ pub async fn save_project(
                 files: payload.files,
                 created_at: now.clone(),
                 updated_at: now,
-                user_id: "user_1".to_string(),
+                user_id: user.id,
             };
 
             (
@@ -107,142 +305,156 @@ pub async fn save_project(
                     "success": true,
                     "project": project
                 })),
-            )
+            ).into_response()
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "success": false,
-                "message": format!("Failed to save project: {}", e)
-            })),
-        ),
+        Err(e) => database_error("Failed to save project", e),
     }
 }
 
 /// Load a specific project
+#[utoipa::path(
+    post,
+    path = "/api/projects/load",
+    tag = "projects",
+    request_body = LoadProjectRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Project with files", body = Project),
+        (status = 404, description = "Project not found"),
+    )
+)]
 pub async fn load_project(
     State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
     Json(payload): Json<LoadProjectRequest>,
-) -> impl IntoResponse {
-    match sqlx::query!(
-        "SELECT id, name, description, prompt, files_json, created_at, updated_at, user_id
-         FROM projects WHERE id = ?",
-        payload.id
-    )
-    .fetch_one(&state.db_pool)
-    .await
-    {
-        Ok(record) => {
-            let files: Vec<ProjectFile> = serde_json::from_str(&record.files_json)
-                .unwrap_or_else(|_| vec![]);
-
-            let project = Project {
-                id: record.id,
-                name: record.name,
-                description: record.description,
-                prompt: record.prompt,
-                files,
-                created_at: record.created_at,
-                updated_at: record.updated_at,
-                user_id: record.user_id,
-            };
-
-            Json(serde_json::json!({
-                "success": true,
-                "project": project
-            }))
-        }
-        Err(_) => (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({
-                "success": false,
-                "message": "Project not found"
-            })),
-        ).into_response(),
+) -> Response {
+    if let Err(response) = check_access(&state, &user, &payload.id, Access::Read).await {
+        return response;
     }
+
+    project_response(&state, &payload.id).await
 }
 
 /// Get a specific project
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Project with files", body = Project),
+        (status = 404, description = "Project not found"),
+    )
+)]
 pub async fn get_project(
     State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
-    match sqlx::query!(
-        "SELECT id, name, description, prompt, files_json, created_at, updated_at, user_id
-         FROM projects WHERE id = ?",
-        id
+) -> Response {
+    if let Err(response) = check_access(&state, &user, &id, Access::Read).await {
+        return response;
+    }
+
+    project_response(&state, &id).await
+}
+
+/// Update an existing project
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project ID")),
+    request_body = SaveProjectRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Updated project", body = Project),
+        (status = 403, description = "Write access required"),
+        (status = 404, description = "Project not found"),
     )
-    .fetch_one(&state.db_pool)
-    .await
-    {
-        Ok(record) => {
-            let files: Vec<ProjectFile> = serde_json::from_str(&record.files_json)
-                .unwrap_or_else(|_| vec![]);
+)]
+pub async fn update_project(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(payload): Json<SaveProjectRequest>,
+) -> Response {
+    if let Err(response) = check_access(&state, &user, &id, Access::Write).await {
+        return response;
+    }
 
-            let project = Project {
-                id: record.id,
-                name: record.name,
-                description: record.description,
-                prompt: record.prompt,
-                files,
-                created_at: record.created_at,
-                updated_at: record.updated_at,
-                user_id: record.user_id,
-            };
+    let now = chrono::Utc::now().to_rfc3339();
 
-            Json(serde_json::json!({
-                "success": true,
-                "project": project
-            }))
-        }
-        Err(_) => (
+    let result = async {
+        let mut tx = state.db_pool.begin().await?;
+
+        let updated = sqlx::query(
+            "UPDATE projects SET name = ?, description = ?, prompt = ?, updated_at = ?
+             WHERE id = ?"
+        )
+        .bind(&payload.name)
+        .bind(&payload.description)
+        .bind(&payload.prompt)
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        write_files(&mut tx, &id, &payload.files).await?;
+        tx.commit().await?;
+
+        Ok::<_, sqlx::Error>(updated)
+    }
+    .await;
+
+    match result {
+        Ok(0) => (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({
                 "success": false,
                 "message": "Project not found"
             })),
         ).into_response(),
+        Ok(_) => project_response(&state, &id).await,
+        Err(e) => database_error("Failed to update project", e),
     }
 }
 
-/// Update an existing project
-pub async fn update_project(
+/// Delete a project
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{id}",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Project deleted"),
+        (status = 403, description = "Write access required"),
+        (status = 404, description = "Project not found"),
+    )
+)]
+pub async fn delete_project(
     State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
     Path(id): Path<String>,
-    Json(payload): Json<SaveProjectRequest>,
-) -> impl IntoResponse {
-    let now = chrono::Utc::now().to_rfc3339();
+) -> Response {
+    if let Err(response) = check_access(&state, &user, &id, Access::Write).await {
+        return response;
+    }
 
-    match sqlx::query!(
-        "UPDATE projects SET name = ?, description = ?, prompt = ?, files_json = ?, updated_at = ?
-         WHERE id = ?",
-        payload.name,
-        payload.description,
-        payload.prompt,
-        serde_json::to_string(&payload.files).unwrap_or_else(|_| "[]".to_string()),
-        now,
-        id
-    )
-    .execute(&state.db_pool)
-    .await
+    // SQLite CASCADE removes files, messages, and shares
+    match sqlx::query("DELETE FROM projects WHERE id = ?")
+        .bind(&id)
+        .execute(&state.db_pool)
+        .await
     {
         Ok(result) => {
             if result.rows_affected() > 0 {
-                let project = Project {
-                    id: id.clone(),
-                    name: payload.name,
-                    description: payload.description,
-                    prompt: payload.prompt,
-                    files: payload.files,
-                    created_at: now.clone(), // Should keep original
-                    updated_at: now,
-                    user_id: "user_1".to_string(),
-                };
-
                 Json(serde_json::json!({
                     "success": true,
-                    "project": project
-                }))
+                    "message": "Project deleted successfully"
+                })).into_response()
             } else {
                 (
                     StatusCode::NOT_FOUND,
@@ -253,47 +465,283 @@ pub async fn update_project(
                 ).into_response()
             }
         }
+        Err(e) => database_error("Failed to delete project", e),
+    }
+}
+
+/// Load a project with its files
+async fn fetch_project(pool: &SqlitePool, id: &str) -> Result<Option<Project>, sqlx::Error> {
+    let Some(record) = sqlx::query(
+        "SELECT id, name, description, prompt, created_at, updated_at, user_id
+         FROM projects WHERE id = ?"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let files = sqlx::query("SELECT path, content, language FROM project_files WHERE project_id = ? ORDER BY path")
+        .bind(id)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| ProjectFile {
+            path: row.get("path"),
+            content: row.get("content"),
+            language: row.get("language"),
+        })
+        .collect();
+
+    Ok(Some(Project {
+        id: record.get("id"),
+        name: record.get("name"),
+        description: record.get("description"),
+        prompt: record.get::<Option<String>, _>("prompt").unwrap_or_default(),
+        files,
+        created_at: record.get("created_at"),
+        updated_at: record.get("updated_at"),
+        user_id: record.get("user_id"),
+    }))
+}
+
+/// Replace a project's files
+async fn write_files(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    project_id: &str,
+    files: &[ProjectFile],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM project_files WHERE project_id = ?")
+        .bind(project_id)
+        .execute(&mut **tx)
+        .await?;
+
+    for file in files {
+        sqlx::query(
+            "INSERT INTO project_files (id, project_id, path, content, language) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(project_id)
+        .bind(&file.path)
+        .bind(&file.content)
+        .bind(&file.language)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn project_response(state: &ServerState, id: &str) -> Response {
+    match fetch_project(&state.db_pool, id).await {
+        Ok(Some(project)) => Json(serde_json::json!({
+            "success": true,
+            "project": project
+        })).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Project not found"
+            })),
+        ).into_response(),
+        Err(e) => database_error("Failed to load project", e),
+    }
+}
+
+fn database_error(context: &str, e: sqlx::Error) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({
+            "success": false,
+            "message": format!("{}: {}", context, e)
+        })),
+    ).into_response()
+}
+
+/// List who a project is shared with
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/shares",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Project shares", body = [crate::sharing::ProjectShare]),
+        (status = 404, description = "Project not found"),
+    )
+)]
+pub async fn list_shares(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Response {
+    if let Err(response) = check_access(&state, &user, &id, Access::Read).await {
+        return response;
+    }
+
+    match sharing::list_shares(&state.db_pool, &id).await {
+        Ok(shares) => Json(serde_json::json!({
+            "success": true,
+            "shares": shares
+        })).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({
                 "success": false,
-                "message": format!("Failed to update project: {}", e)
+                "message": format!("Failed to list shares: {}", e)
             })),
         ).into_response(),
     }
 }
 
-/// Delete a project
-pub async fn delete_project(
+/// Share a project with another user or paired device
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/shares",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project ID")),
+    request_body = ShareProjectRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Share created or updated", body = crate::sharing::ProjectShare),
+        (status = 403, description = "Write access required"),
+    )
+)]
+pub async fn share_project(
     State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
-    match sqlx::query!("DELETE FROM projects WHERE id = ?", id)
-        .execute(&state.db_pool)
-        .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Json(serde_json::json!({
-                    "success": true,
-                    "message": "Project deleted successfully"
-                }))
-            } else {
-                (
-                    StatusCode::NOT_FOUND,
-                    Json(serde_json::json!({
-                        "success": false,
-                        "message": "Project not found"
-                    })),
-                ).into_response()
-            }
-        }
+    Json(payload): Json<ShareProjectRequest>,
+) -> Response {
+    if let Err(response) = check_access(&state, &user, &id, Access::Write).await {
+        return response;
+    }
+
+    match sharing::share_project(&state.db_pool, &id, payload.grantee_type, &payload.grantee_id, payload.access).await {
+        Ok(share) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "success": true,
+                "share": share
+            })),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": e
+            })),
+        ).into_response(),
+    }
+}
+
+/// Remove a project share
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{id}/shares/{share_id}",
+    tag = "projects",
+    params(
+        ("id" = String, Path, description = "Project ID"),
+        ("share_id" = String, Path, description = "Share ID"),
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Share removed"),
+        (status = 404, description = "Share not found"),
+    )
+)]
+pub async fn unshare_project(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path((id, share_id)): Path<(String, String)>,
+) -> Response {
+    if let Err(response) = check_access(&state, &user, &id, Access::Write).await {
+        return response;
+    }
+
+    match sharing::unshare_project(&state.db_pool, &id, &share_id).await {
+        Ok(true) => Json(serde_json::json!({
+            "success": true,
+            "message": "Share removed"
+        })).into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Share not found"
+            })),
+        ).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({
                 "success": false,
-                "message": format!("Failed to delete project: {}", e)
+                "message": format!("Failed to remove share: {}", e)
             })),
         ).into_response(),
     }
-}
\ No newline at end of file
+}
+
+/// Check the caller's access to a project, returning the error response if denied
+///
+/// Projects the caller cannot see at all are reported as not found.
+async fn check_access(
+    state: &ServerState,
+    user: &AuthUser,
+    project_id: &str,
+    needed: Access,
+) -> Result<(), Response> {
+    match sharing::project_access(&state.db_pool, project_id, user.principal()).await {
+        Ok(Some(access)) if access >= needed => Ok(()),
+        Ok(Some(_)) => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("You need {} access to this project", needed.as_str())
+            })),
+        ).into_response()),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Project not found"
+            })),
+        ).into_response()),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("Failed to check project access: {}", e)
+            })),
+        ).into_response()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        assert_eq!(decode_cursor(&encode_cursor(40)), Ok(40));
+        assert!(decode_cursor("not-a-cursor").is_err());
+    }
+
+    #[test]
+    fn test_list_query_defaults() {
+        let query = ListProjectsQuery::default();
+        assert_eq!(query.page_size(), DEFAULT_PAGE_SIZE);
+        assert_eq!(query.start_offset(), Ok(0));
+        assert_eq!(query.order_by(), "updated_at DESC, id DESC");
+
+        let query = ListProjectsQuery {
+            limit: Some(1000),
+            sort: Some("name".to_string()),
+            order: Some("asc".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(query.page_size(), MAX_PAGE_SIZE);
+        assert_eq!(query.order_by(), "name COLLATE NOCASE ASC, id ASC");
+    }
+}
@@ -1,113 +1,417 @@
 // Streaming API endpoints for agent interactions
 use axum::{
-    extract::State,
-    http::{StatusCode, HeaderMap, header},
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
     response::{IntoResponse, Response, Sse, sse::Event},
     Json,
 };
-use futures::stream::{Stream, StreamExt};
-use serde::{Deserialize, Serialize};
+use futures::stream::{BoxStream, Stream, StreamExt};
 use std::convert::Infallible;
 use std::time::Duration;
-use tokio::time::interval;
-use tokio_stream::wrappers::IntervalStream;
+use crate::comparison::{self, CompareRequest};
+use crate::generation::{self, GenerationEvent};
+use crate::llm::ErrorCode;
+use crate::server::middleware::auth::{AuthUser, Role};
+use crate::server::streams::{SessionGuard, SessionInfo, StreamSession};
 use crate::server::ServerState;
+use crate::sharing::{self, Access};
 
-#[derive(Debug, Deserialize)]
-pub struct StreamRequest {
-    pub prompt: String,
-    pub agent_id: Option<String>,
-    pub files: Option<Vec<FileContent>>,
-    pub context: Option<serde_json::Value>,
+pub use crate::generation::{FileContent, StreamRequest, StreamResponse};
+
+/// Handle streaming agent responses
+///
+/// The provider comes from `provider`, or is inferred from `model`; requests
+/// naming neither get the built-in demo response. When `max_streams`
+/// generations are already running, the request waits in a queue and a
+/// `queued` event reports its position; the session ID is returned in the
+/// `X-Stream-Id` header.
+#[utoipa::path(
+    post,
+    path = "/api/agent/stream",
+    tag = "stream",
+    request_body = StreamRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Server-sent events, each carrying a JSON StreamResponse; the final event has `done` set and carries `usage` and, for priced models, `cost_usd`. Retries and failover are announced with a `retry` event, and with `tools` set each project tool call is sent as a `tool_use` event followed by a `tool_result` event. While the provider is rate limiting, the generation waits in a queue ordered by `priority` and each change of place is sent as a `rate_limited` event carrying a QueueStatus. While the app is offline, generations for remote providers wait for the connection, announced with an `offline` event naming the provider. With `response_format`, a reply that doesn't conform to the schema is reported with a `validation_error` event before `done`; unrecoverable provider failures, and a daily budget running out midway (`budget_exceeded`), are sent as an `error` event carrying a StreamError with a machine-readable `code` and a `retryable` flag."),
+        (status = 400, description = "Unknown provider or model, no key configured for the provider, tools requested without a project, or the agent's or project's daily budget is spent; the body carries the error `code`"),
+    )
+)]
+pub async fn handle_stream(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Json(mut payload): Json<StreamRequest>,
+) -> Response {
+    let info = SessionInfo {
+        user_id: user.id.clone(),
+        project_id: payload.project_id.clone(),
+        agent_id: payload.agent_id.clone(),
+        provider: payload.provider.clone(),
+        model: payload.model.clone(),
+    };
+
+    // Listed as a session, and counted as active, until the client disconnects
+    let session = state.streams.register(info);
+    let session_id = session.id().to_string();
+    payload.session_id = Some(session_id.clone());
+
+    // End the stream early (with the final done event) on shutdown
+    let cancel = state.shutdown.clone().triggered();
+    let events = match generation::start(&state.db_pool, user.principal(), payload, cancel).await {
+        Ok(events) => events,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": e.to_string(),
+                    "code": e.code(),
+                    "status": 400
+                })),
+            ).into_response();
+        }
+    };
+
+    let stream = sse_events(events.map(generation_sse).boxed(), session, state.clone(), state.metrics.track_stream());
+
+    let mut response = Sse::new(stream)
+        .keep_alive(
+            axum::response::sse::KeepAlive::new()
+                .interval(Duration::from_secs(30))
+                .text("keep-alive"),
+        )
+        .into_response();
+    if let Ok(value) = session_id.parse() {
+        response.headers_mut().insert("x-stream-id", value);
+    }
+    response
 }
 
-#[derive(Debug, Deserialize)]
-pub struct FileContent {
-    pub path: String,
-    pub content: String,
+/// Run a generation to completion and return the whole reply
+///
+/// Takes the same request as `/api/agent/stream`, for short utility calls
+/// (titles, commit messages) where streaming isn't worth it.
+#[utoipa::path(
+    post,
+    path = "/api/agent/complete",
+    tag = "stream",
+    request_body = StreamRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The reply with its usage", body = generation::CompletionResponse),
+        (status = 400, description = "The request can't succeed as sent; the body is a StreamError"),
+        (status = 402, description = "The agent's or project's daily budget is spent"),
+        (status = 429, description = "The provider is rate limiting"),
+        (status = 502, description = "The provider failed or couldn't be reached"),
+        (status = 503, description = "The provider is overloaded"),
+    )
+)]
+pub async fn handle_complete(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<StreamRequest>,
+) -> Response {
+    match generation::complete(&state.db_pool, user.principal(), payload).await {
+        Ok(completion) => Json(completion).into_response(),
+        Err(error) => completion_failure(error),
+    }
 }
 
-#[derive(Debug, Serialize)]
-pub struct StreamResponse {
-    pub id: String,
-    pub content: String,
-    pub role: String,
-    pub done: bool,
+/// Error response for a generation that failed before finishing
+pub(crate) fn completion_failure(error: generation::StreamError) -> Response {
+    let status = match error.code {
+        ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        ErrorCode::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::Network | ErrorCode::ProviderError => StatusCode::BAD_GATEWAY,
+        ErrorCode::BudgetExceeded => StatusCode::PAYMENT_REQUIRED,
+        ErrorCode::AuthInvalid | ErrorCode::ContextTooLong | ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "error": error.message,
+            "code": error.code,
+            "retryable": error.retryable,
+            "status": status.as_u16()
+        })),
+    ).into_response()
 }
 
-/// Handle streaming agent responses
-pub async fn handle_stream(
+/// Run a prompt through two agents side by side
+///
+/// Takes the fields of `/api/agent/stream` plus the two `agent_ids`, and
+/// streams both generations at once. For a project, the prompt is saved once
+/// and each reply as a candidate answer to it, kept out of the conversation
+/// until one is picked with `/api/agent/compare/pick`.
+#[utoipa::path(
+    post,
+    path = "/api/agent/compare",
+    tag = "stream",
+    request_body = CompareRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Server-sent events as from `/api/agent/stream`, interleaved, with each payload wrapped as `{\"agent_id\": ..., \"data\": ...}`; each agent's generation ends with its own `done` message"),
+        (status = 400, description = "Not two different agents, or either generation can't start (see `/api/agent/stream`); the body carries the error `code`"),
+    )
+)]
+pub async fn handle_compare(
     State(state): State<ServerState>,
-    Json(payload): Json<StreamRequest>,
-) -> impl IntoResponse {
-    // Create SSE stream
-    let stream = create_agent_stream(payload).await;
+    Extension(user): Extension<AuthUser>,
+    Json(mut payload): Json<CompareRequest>,
+) -> Response {
+    let info = SessionInfo {
+        user_id: user.id.clone(),
+        project_id: payload.request.project_id.clone(),
+        agent_id: None,
+        provider: payload.request.provider.clone(),
+        model: payload.request.model.clone(),
+    };
 
-    Sse::new(stream)
+    let session = state.streams.register(info);
+    let session_id = session.id().to_string();
+    payload.request.session_id = Some(session_id.clone());
+
+    let cancel = state.shutdown.clone().triggered();
+    let events = match comparison::start(&state.db_pool, user.principal(), payload, cancel).await {
+        Ok(events) => events,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": e.to_string(),
+                    "code": e.code(),
+                    "status": 400
+                })),
+            ).into_response();
+        }
+    };
+
+    let events = events.map(|event| {
+        let sse = match event.event {
+            GenerationEvent::Message(_) => Event::default(),
+            _ => Event::default().event(event.event.name()),
+        };
+        sse.data(event.data())
+    });
+    let stream = sse_events(events.boxed(), session, state.clone(), state.metrics.track_stream());
+
+    let mut response = Sse::new(stream)
         .keep_alive(
             axum::response::sse::KeepAlive::new()
                 .interval(Duration::from_secs(30))
                 .text("keep-alive"),
         )
+        .into_response();
+    if let Ok(value) = session_id.parse() {
+        response.headers_mut().insert("x-stream-id", value);
+    }
+    response
 }
 
-/// Create the agent response stream
-async fn create_agent_stream(
-    request: StreamRequest,
-) -> impl Stream<Item = Result<Event, Infallible>> {
-    // For demo purposes, stream a mock response
-    // In production, this would connect to Claude API
-    let messages = vec![
-        "I'll help you with that request.",
-        "Let me analyze your requirements...",
-        "Here's what I'll create for you:",
-        "\n```javascript",
-        "// Sample code implementation",
-        "function helloWorld() {",
-        "  console.log('Hello from Vibing2!');",
-        "}",
-        "```\n",
-        "This implementation provides a basic structure.",
-        "Would you like me to add more features?",
-    ];
-
-    // Create interval stream for demo
-    let mut interval_stream = IntervalStream::new(interval(Duration::from_millis(100)));
-    let mut message_index = 0;
-    let total_messages = messages.len();
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
+pub struct PickRequest {
+    pub project_id: String,
+    /// The reply to keep
+    pub message_id: String,
+}
 
+/// Keep one reply of a comparison in the conversation
+///
+/// The other replies to the same prompt are marked rejected. Picking another
+/// reply of the comparison later switches to it.
+#[utoipa::path(
+    post,
+    path = "/api/agent/compare/pick",
+    tag = "stream",
+    request_body = PickRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "The reply was picked"),
+        (status = 400, description = "The message isn't a reply from a comparison in the project"),
+        (status = 403, description = "No write access to the project"),
+    )
+)]
+pub async fn pick_reply(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<PickRequest>,
+) -> Response {
+    if let Err(e) = sharing::require_access(&state.db_pool, &payload.project_id, user.principal(), Access::Write).await {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": e,
+                "status": 403
+            })),
+        ).into_response();
+    }
+
+    match comparison::pick(&state.db_pool, &payload.project_id, &payload.message_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": e,
+                "status": 400
+            })),
+        ).into_response(),
+    }
+}
+
+/// The SSE event for a generation event; chunks use the default `message`
+/// event, as before
+fn generation_sse(event: GenerationEvent) -> Event {
+    let sse = match event {
+        GenerationEvent::Message(_) => Event::default(),
+        _ => Event::default().event(event.name()),
+    };
+    sse.data(event.data())
+}
+
+fn sse_events(
+    mut events: BoxStream<'static, Event>,
+    mut session: SessionGuard,
+    state: ServerState,
+    connection: crate::server::metrics::ConnectionGuard,
+) -> impl Stream<Item = Result<Event, Infallible>> {
     async_stream::stream! {
-        while let Some(_) = interval_stream.next().await {
-            if message_index < total_messages {
-                let response = StreamResponse {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    content: messages[message_index].to_string(),
-                    role: "assistant".to_string(),
-                    done: message_index == total_messages - 1,
-                };
-
-                let data = serde_json::to_string(&response).unwrap_or_default();
-                yield Ok(Event::default().data(data));
-
-                message_index += 1;
-            } else {
-                break;
+        let _connection = connection;
+
+        // Wait for a free slot; on shutdown, skip ahead so the generation
+        // ends with its done event right away
+        if !session.can_start() {
+            if let Some(queued) = state.streams.get(session.id()) {
+                let data = serde_json::json!({
+                    "id": queued.id,
+                    "position": queued.queue_position,
+                    "max_streams": state.streams.max_streams(),
+                });
+                yield Ok(Event::default().event("queued").data(data.to_string()));
             }
         }
+        tokio::select! {
+            _ = session.start() => {}
+            _ = state.shutdown.clone().triggered() => {}
+        }
 
-        // Send final done event
-        let final_response = StreamResponse {
-            id: uuid::Uuid::new_v4().to_string(),
-            content: "".to_string(),
-            role: "assistant".to_string(),
-            done: true,
-        };
+        while let Some(event) = events.next().await {
+            yield Ok(event);
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+pub struct StreamsQuery {
+    /// Only sessions for this project
+    pub project_id: Option<String>,
+}
+
+/// Sessions `user` may see: everyone's for owners, otherwise their own
+fn visible_sessions(state: &ServerState, user: &AuthUser, project_id: Option<&str>) -> Vec<StreamSession> {
+    let user_id = (user.role != Role::Owner || user.device_id.is_some()).then_some(user.id.as_str());
+    state.streams.list(user_id, project_id)
+}
 
-        let data = serde_json::to_string(&final_response).unwrap_or_default();
-        yield Ok(Event::default().data(data));
+/// List queued and running generations
+///
+/// Owners see every session; other users see their own.
+#[utoipa::path(
+    get,
+    path = "/api/agent/streams",
+    tag = "stream",
+    params(StreamsQuery),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Sessions in the order they were started, with the running and queued counts and the concurrency cap"),
+    )
+)]
+pub async fn list_streams(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<StreamsQuery>,
+) -> impl IntoResponse {
+    let sessions = visible_sessions(&state, &user, query.project_id.as_deref());
+    let running = sessions
+        .iter()
+        .filter(|session| session.status == crate::server::streams::SessionStatus::Running)
+        .count();
+
+    Json(serde_json::json!({
+        "streams": sessions,
+        "running": running,
+        "queued": sessions.len() - running,
+        "max_streams": state.streams.max_streams(),
+    }))
+}
+
+/// Inspect one generation session
+#[utoipa::path(
+    get,
+    path = "/api/agent/streams/{id}",
+    tag = "stream",
+    params(("id" = String, Path, description = "Session ID from the `X-Stream-Id` header")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The session", body = StreamSession),
+        (status = 404, description = "No such session, or it belongs to another user"),
+    )
+)]
+pub async fn get_stream(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Response {
+    match visible_sessions(&state, &user, None).into_iter().find(|session| session.id == id) {
+        Some(session) => Json(session).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Stream not found",
+                "status": 404
+            })),
+        ).into_response(),
     }
 }
 
+/// Replay a recorded session
+///
+/// Re-emits the raw provider frames recorded for the session (with stream
+/// recording turned on in settings) through the same parsers as a live
+/// generation, without calling the provider or recording usage.
+#[utoipa::path(
+    get,
+    path = "/api/agent/streams/{id}/replay",
+    tag = "stream",
+    params(("id" = String, Path, description = "Session ID from the `X-Stream-Id` header")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Server-sent events in the same format as `/api/agent/stream`; each recorded provider call ends with a `done` message"),
+        (status = 404, description = "No recording for the session"),
+    )
+)]
+pub async fn replay_stream(
+    State(_state): State<ServerState>,
+    Path(id): Path<String>,
+) -> Response {
+    let mut events = match generation::replay(&id).await {
+        Ok(events) => events,
+        Err(e) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": e.to_string(),
+                    "status": 404
+                })),
+            ).into_response();
+        }
+    };
+
+    let stream = async_stream::stream! {
+        while let Some(event) = events.next().await {
+            yield Ok::<_, Infallible>(generation_sse(event));
+        }
+    };
+    Sse::new(stream).into_response()
+}
+
 /// Alternative WebSocket handler for bidirectional streaming
 pub async fn handle_websocket(
     ws: axum::extract::ws::WebSocketUpgrade,
@@ -120,8 +424,28 @@ async fn handle_socket(
     mut socket: axum::extract::ws::WebSocket,
     state: ServerState,
 ) {
-    // Handle WebSocket messages
-    while let Some(msg) = socket.recv().await {
+    let _connection = state.metrics.track_websocket();
+    let stopping = state.shutdown.clone().triggered();
+    tokio::pin!(stopping);
+
+    // Handle WebSocket messages until the client or the server closes
+    loop {
+        let msg = tokio::select! {
+            msg = socket.recv() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            _ = &mut stopping => {
+                let _ = socket
+                    .send(axum::extract::ws::Message::Close(Some(axum::extract::ws::CloseFrame {
+                        code: axum::extract::ws::close_code::AWAY,
+                        reason: "Server shutting down".into(),
+                    })))
+                    .await;
+                break;
+            }
+        };
+
         match msg {
             Ok(axum::extract::ws::Message::Text(text)) => {
                 // Parse message and handle accordingly
@@ -132,6 +456,8 @@ async fn handle_socket(
                         content: format!("Received: {}", request.prompt),
                         role: "assistant".to_string(),
                         done: false,
+                        usage: None,
+                        cost_usd: None,
                     };
 
                     if let Ok(response_text) = serde_json::to_string(&response) {
@@ -0,0 +1,148 @@
+// Prompt template API endpoints
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+use crate::server::ServerState;
+use crate::server::middleware::auth::AuthUser;
+use crate::templates::{self, NewPromptTemplate};
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListTemplatesQuery {
+    /// Only templates for this project type (plus untyped ones)
+    pub project_type: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RenderTemplateRequest {
+    /// Values for the template's variables
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+fn failure(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    ).into_response()
+}
+
+/// List built-in templates and the caller's own
+#[utoipa::path(
+    get,
+    path = "/api/templates",
+    tag = "templates",
+    params(ListTemplatesQuery),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Prompt templates", body = [crate::templates::PromptTemplate]),
+    )
+)]
+pub async fn list_templates(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<ListTemplatesQuery>,
+) -> Response {
+    let project_type = query.project_type.as_deref().filter(|t| !t.is_empty());
+
+    match templates::list(&state.db_pool, &user.id, project_type).await {
+        Ok(templates) => Json(serde_json::json!({
+            "success": true,
+            "templates": templates
+        })).into_response(),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to list templates: {}", e)),
+    }
+}
+
+/// Save a prompt template
+#[utoipa::path(
+    post,
+    path = "/api/templates",
+    tag = "templates",
+    request_body = NewPromptTemplate,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Template created", body = crate::templates::PromptTemplate),
+        (status = 400, description = "Missing name or body"),
+    )
+)]
+pub async fn create_template(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<NewPromptTemplate>,
+) -> Response {
+    match templates::create(&state.db_pool, &user.id, payload).await {
+        Ok(template) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "success": true,
+                "template": template
+            })),
+        ).into_response(),
+        Err(e) => failure(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// Delete one of the caller's templates
+#[utoipa::path(
+    delete,
+    path = "/api/templates/{id}",
+    tag = "templates",
+    params(("id" = String, Path, description = "Template ID")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Template deleted"),
+        (status = 404, description = "Template not found, or built in"),
+    )
+)]
+pub async fn delete_template(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Response {
+    match templates::delete(&state.db_pool, &user.id, &id).await {
+        Ok(true) => Json(serde_json::json!({
+            "success": true,
+            "message": "Template deleted"
+        })).into_response(),
+        Ok(false) => failure(StatusCode::NOT_FOUND, "Template not found".to_string()),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete template: {}", e)),
+    }
+}
+
+/// Render a template with the given variables
+#[utoipa::path(
+    post,
+    path = "/api/templates/{id}/render",
+    tag = "templates",
+    params(("id" = String, Path, description = "Template ID")),
+    request_body = RenderTemplateRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Rendered prompt"),
+        (status = 400, description = "Unknown template or missing variable"),
+    )
+)]
+pub async fn render_template(
+    State(state): State<ServerState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(payload): Json<RenderTemplateRequest>,
+) -> Response {
+    match templates::render_template(&state.db_pool, &user.id, &id, &payload.variables).await {
+        Ok(prompt) => Json(serde_json::json!({
+            "success": true,
+            "prompt": prompt
+        })).into_response(),
+        Err(e) => failure(StatusCode::BAD_REQUEST, e),
+    }
+}
@@ -1,8 +1,9 @@
 // Server configuration module
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
 use std::time::Duration;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub port: u16,
     pub host: String,
@@ -11,6 +12,322 @@ pub struct ServerConfig {
     pub max_body_size: usize,
     pub enable_compression: bool,
     pub enable_logging: bool,
+    pub rate_limit: RateLimitConfig,
+    pub cors: CorsConfig,
+    pub transport: Transport,
+    /// Prefix every route is mounted under (e.g. `/vibing2`), or empty to
+    /// serve from the root; see [`normalize_base_path`]
+    #[serde(default)]
+    pub base_path: String,
+    /// Generations that may stream at once; further requests wait in a
+    /// queue (0 disables the limit)
+    #[serde(default = "default_max_streams")]
+    pub max_streams: usize,
+}
+
+fn default_max_streams() -> usize {
+    4
+}
+
+/// How the server accepts connections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// TCP on `host`:`port`
+    #[default]
+    Tcp,
+    /// Unix domain socket (macOS/Linux) or named pipe (Windows), reached by
+    /// the webview through the `vibing2-local` URI scheme
+    Local,
+}
+
+impl Transport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Transport::Tcp => "tcp",
+            Transport::Local => "local",
+        }
+    }
+
+    pub fn parse(value: &str) -> Transport {
+        match value {
+            "local" => Transport::Local,
+            _ => Transport::Tcp,
+        }
+    }
+}
+
+/// Cross-origin policy; the server's own origin is always allowed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CorsConfig {
+    /// Extra origins allowed to call the server (`*` for any)
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Allow cookies and auth headers on cross-origin requests
+    #[serde(default)]
+    pub allow_credentials: bool,
+}
+
+/// Settings read from `server.toml` in the app data directory
+///
+/// Every field is optional; fields that are present take precedence over app
+/// settings, and `VIBING2_SERVER_*` environment variables take precedence
+/// over the file (see [`ServerConfigFile::from_env`]).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfigFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<Transport>,
+    /// Mount all routes under this prefix for reverse-proxy deployments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_size: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<usize>,
+    /// Concurrent generations before requests queue (0 for no limit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_streams: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_compression: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_logging: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+impl ServerConfigFile {
+    pub fn path() -> std::path::PathBuf {
+        crate::database::get_db_path().with_file_name("server.toml")
+    }
+
+    /// Load the config file, treating a missing file as empty
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(&Self::path())
+    }
+
+    pub fn load_from(path: &std::path::Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|e| format!("Invalid server config {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read server config {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Write the config file, creating the app data directory if needed
+    pub fn save(&self) -> Result<(), String> {
+        self.save_to(&Self::path())
+    }
+
+    pub fn save_to(&self, path: &std::path::Path) -> Result<(), String> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize server config: {}", e))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+
+        std::fs::write(path, contents)
+            .map_err(|e| format!("Failed to write server config {}: {}", path.display(), e))
+    }
+
+    /// Overrides from `VIBING2_SERVER_*` environment variables
+    ///
+    /// Supported: `HOST`, `PORT`, `TRANSPORT`, `BASE_PATH`, `TIMEOUT_SECS`,
+    /// `MAX_BODY_SIZE`, `MAX_CONNECTIONS`, `MAX_STREAMS`, `COMPRESSION`,
+    /// `LOGGING`, and `CORS_ORIGINS` (comma-separated).
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_vars(|name| std::env::var(format!("VIBING2_SERVER_{}", name)).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        fn parsed<T: std::str::FromStr>(
+            var: &impl Fn(&str) -> Option<String>,
+            name: &str,
+        ) -> Result<Option<T>, String> {
+            match var(name) {
+                Some(value) => value.trim().parse().map(Some).map_err(|_| {
+                    format!("Invalid value for VIBING2_SERVER_{}: {:?}", name, value)
+                }),
+                None => Ok(None),
+            }
+        }
+
+        let transport = match var("TRANSPORT").as_deref().map(str::trim) {
+            Some("tcp") => Some(Transport::Tcp),
+            Some("local") => Some(Transport::Local),
+            Some(value) => {
+                return Err(format!(
+                    "Invalid value for VIBING2_SERVER_TRANSPORT: {:?} (expected \"tcp\" or \"local\")",
+                    value
+                ))
+            }
+            None => None,
+        };
+
+        let cors = var("CORS_ORIGINS").map(|origins| CorsConfig {
+            allowed_origins: origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(String::from)
+                .collect(),
+            allow_credentials: false,
+        });
+
+        Ok(Self {
+            host: var("HOST"),
+            port: parsed(&var, "PORT")?,
+            transport,
+            base_path: var("BASE_PATH"),
+            timeout_secs: parsed(&var, "TIMEOUT_SECS")?,
+            max_body_size: parsed(&var, "MAX_BODY_SIZE")?,
+            max_connections: parsed(&var, "MAX_CONNECTIONS")?,
+            max_streams: parsed(&var, "MAX_STREAMS")?,
+            enable_compression: parsed(&var, "COMPRESSION")?,
+            enable_logging: parsed(&var, "LOGGING")?,
+            cors,
+            rate_limit: None,
+        })
+    }
+
+    /// Fields set in `overrides` replace those in `self`
+    pub fn merge(self, overrides: Self) -> Self {
+        Self {
+            host: overrides.host.or(self.host),
+            port: overrides.port.or(self.port),
+            transport: overrides.transport.or(self.transport),
+            base_path: overrides.base_path.or(self.base_path),
+            timeout_secs: overrides.timeout_secs.or(self.timeout_secs),
+            max_body_size: overrides.max_body_size.or(self.max_body_size),
+            max_connections: overrides.max_connections.or(self.max_connections),
+            max_streams: overrides.max_streams.or(self.max_streams),
+            enable_compression: overrides.enable_compression.or(self.enable_compression),
+            enable_logging: overrides.enable_logging.or(self.enable_logging),
+            cors: overrides.cors.or(self.cors),
+            rate_limit: overrides.rate_limit.or(self.rate_limit),
+        }
+    }
+
+    /// Apply the file's overrides to `config` and validate the result
+    pub fn apply(self, mut config: ServerConfig) -> Result<ServerConfig, String> {
+        if let Some(host) = self.host {
+            let ip: IpAddr = host
+                .trim()
+                .parse()
+                .map_err(|_| format!("host: {:?} is not an IP address", host))?;
+            config = config.with_host(ip);
+        }
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if let Some(transport) = self.transport {
+            config.transport = transport;
+        }
+        if let Some(base_path) = self.base_path {
+            config = config.with_base_path(&base_path).map_err(|e| format!("base_path: {}", e))?;
+        }
+        if let Some(timeout_secs) = self.timeout_secs {
+            config.timeout = Duration::from_secs(timeout_secs);
+        }
+        if let Some(max_body_size) = self.max_body_size {
+            config.max_body_size = max_body_size;
+        }
+        if let Some(max_connections) = self.max_connections {
+            config.max_connections = max_connections;
+        }
+        if let Some(max_streams) = self.max_streams {
+            config.max_streams = max_streams;
+        }
+        if let Some(enable_compression) = self.enable_compression {
+            config.enable_compression = enable_compression;
+        }
+        if let Some(enable_logging) = self.enable_logging {
+            config.enable_logging = enable_logging;
+        }
+        if let Some(cors) = self.cors {
+            config.cors = cors;
+        }
+        if let Some(rate_limit) = self.rate_limit {
+            config.rate_limit = rate_limit;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl From<&ServerConfig> for ServerConfigFile {
+    /// Every field of `config`, e.g. to show the effective configuration
+    fn from(config: &ServerConfig) -> Self {
+        Self {
+            host: Some(config.host.clone()),
+            port: Some(config.port),
+            transport: Some(config.transport),
+            base_path: Some(config.base_path.clone()),
+            timeout_secs: Some(config.timeout.as_secs()),
+            max_body_size: Some(config.max_body_size),
+            max_connections: Some(config.max_connections),
+            max_streams: Some(config.max_streams),
+            enable_compression: Some(config.enable_compression),
+            enable_logging: Some(config.enable_logging),
+            cors: Some(config.cors.clone()),
+            rate_limit: Some(config.rate_limit.clone()),
+        }
+    }
+}
+
+/// Token-bucket limits for `/api/*`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Sustained requests per minute per client IP
+    pub ip_per_minute: u32,
+    /// Burst capacity per client IP
+    pub ip_burst: u32,
+    /// Sustained requests per minute per bearer token
+    pub token_per_minute: u32,
+    /// Burst capacity per bearer token
+    pub token_burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ip_per_minute: 300,
+            ip_burst: 60,
+            token_per_minute: 600,
+            token_burst: 120,
+        }
+    }
+}
+
+/// Request limits that can be changed while the server runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerLimits {
+    /// Seconds a handler may take to respond (0 disables the timeout)
+    pub timeout_secs: u64,
+    /// Maximum request body size in bytes
+    pub max_body_size: usize,
+    /// Maximum concurrent in-flight requests (0 disables the limit)
+    pub max_connections: usize,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        ServerConfig::default().limits()
+    }
 }
 
 impl ServerConfig {
@@ -23,15 +340,124 @@ impl ServerConfig {
             max_body_size: 10 * 1024 * 1024, // 10MB
             enable_compression: true,
             enable_logging: true,
+            rate_limit: RateLimitConfig::default(),
+            cors: CorsConfig::default(),
+            transport: Transport::default(),
+            base_path: String::new(),
+            max_streams: default_max_streams(),
+        }
+    }
+
+    pub fn limits(&self) -> ServerLimits {
+        ServerLimits {
+            timeout_secs: self.timeout.as_secs(),
+            max_body_size: self.max_body_size,
+            max_connections: self.max_connections,
         }
     }
 
+    pub fn with_limits(mut self, limits: &ServerLimits) -> Self {
+        self.timeout = Duration::from_secs(limits.timeout_secs);
+        self.max_body_size = limits.max_body_size;
+        self.max_connections = limits.max_connections;
+        self
+    }
+
+    /// Bind to `host` instead of loopback (LAN mode when non-loopback)
+    pub fn with_host(mut self, host: IpAddr) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// Mount all routes under `base_path` (validated and normalized)
+    pub fn with_base_path(mut self, base_path: &str) -> Result<Self, String> {
+        self.base_path = normalize_base_path(base_path)?;
+        Ok(self)
+    }
+
+    pub fn bind_ip(&self) -> IpAddr {
+        self.host.parse().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    /// Whether the server is reachable from other machines
+    pub fn is_lan(&self) -> bool {
+        self.transport == Transport::Tcp && !self.bind_ip().is_loopback()
+    }
+
+    /// Check values that would otherwise fail at bind time or make the
+    /// server unusable
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host.parse::<IpAddr>().is_err() {
+            return Err(format!("host: {:?} is not an IP address", self.host));
+        }
+        if self.max_body_size == 0 {
+            return Err("max_body_size: must be greater than zero".to_string());
+        }
+        if self.transport == Transport::Local && !self.bind_ip().is_loopback() {
+            return Err("transport: \"local\" cannot be combined with a non-loopback host".to_string());
+        }
+        if self.rate_limit.enabled {
+            let rl = &self.rate_limit;
+            if rl.ip_per_minute == 0 || rl.ip_burst == 0 || rl.token_per_minute == 0 || rl.token_burst == 0 {
+                return Err("rate_limit: rates and bursts must be greater than zero (set enabled = false to disable)".to_string());
+            }
+        }
+        for origin in &self.cors.allowed_origins {
+            let valid = origin == "*"
+                || origin.starts_with("http://")
+                || origin.starts_with("https://")
+                || origin.starts_with("tauri://");
+            if !valid {
+                return Err(format!(
+                    "cors.allowed_origins: {:?} must be \"*\" or start with http://, https://, or tauri://",
+                    origin
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn address(&self) -> String {
         format!("{}:{}", self.host, self.port)
     }
 
+    /// URL for this machine; loopback even when bound for LAN access
     pub fn url(&self) -> String {
-        format!("http://{}:{}", self.host, self.port)
+        let origin = match self.transport {
+            Transport::Tcp => format!("http://127.0.0.1:{}", self.port),
+            Transport::Local => crate::server::transport::proxy_url(),
+        };
+        format!("{}{}", origin.trim_end_matches('/'), self.base_path)
+    }
+}
+
+/// Normalize a route prefix to `/segment[/segment...]` without a trailing
+/// slash; empty and `/` mean no prefix
+///
+/// Segments may only contain ASCII letters, digits, `-`, `_`, and `.`, and
+/// may not be `.` or `..`.
+pub fn normalize_base_path(value: &str) -> Result<String, String> {
+    let segments: Vec<&str> = value
+        .trim()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    for segment in &segments {
+        let valid = *segment != "."
+            && *segment != ".."
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(format!("Invalid base path: {}", value));
+        }
+    }
+
+    if segments.is_empty() {
+        Ok(String::new())
+    } else {
+        Ok(format!("/{}", segments.join("/")))
     }
 }
 
@@ -39,4 +465,82 @@ impl Default for ServerConfig {
     fn default() -> Self {
         Self::new(3456)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_base_path() {
+        assert_eq!(normalize_base_path("").unwrap(), "");
+        assert_eq!(normalize_base_path("/").unwrap(), "");
+        assert_eq!(normalize_base_path("/vibing2/").unwrap(), "/vibing2");
+        assert_eq!(normalize_base_path("apps//vibing2").unwrap(), "/apps/vibing2");
+        assert!(normalize_base_path("/../etc").is_err());
+        assert!(normalize_base_path("/a b").is_err());
+    }
+
+    #[test]
+    fn test_config_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.toml");
+        assert_eq!(ServerConfigFile::load_from(&path).unwrap(), ServerConfigFile::default());
+
+        let file = ServerConfigFile {
+            port: Some(8080),
+            transport: Some(Transport::Tcp),
+            base_path: Some("/vibing2".to_string()),
+            ..Default::default()
+        };
+        file.save_to(&path).unwrap();
+        assert_eq!(ServerConfigFile::load_from(&path).unwrap(), file);
+
+        std::fs::write(&path, "prot = 8080\n").unwrap();
+        assert!(ServerConfigFile::load_from(&path).unwrap_err().contains("prot"));
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let vars = |name: &str| match name {
+            "PORT" => Some("9000".to_string()),
+            "MAX_STREAMS" => Some("2".to_string()),
+            "CORS_ORIGINS" => Some("https://a.example, https://b.example".to_string()),
+            _ => None,
+        };
+        let env = ServerConfigFile::from_vars(vars).unwrap();
+        let file = ServerConfigFile {
+            port: Some(8080),
+            max_connections: Some(5),
+            ..Default::default()
+        };
+
+        let config = file.merge(env).apply(ServerConfig::default()).unwrap();
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.max_connections, 5);
+        assert_eq!(config.max_streams, 2);
+        assert_eq!(config.cors.allowed_origins.len(), 2);
+
+        let invalid = ServerConfigFile::from_vars(|name| (name == "PORT").then(|| "http".to_string()));
+        assert!(invalid.unwrap_err().contains("VIBING2_SERVER_PORT"));
+    }
+
+    #[test]
+    fn test_apply_validates() {
+        let apply = |file: ServerConfigFile| file.apply(ServerConfig::default());
+
+        assert!(apply(ServerConfigFile { host: Some("localhost".to_string()), ..Default::default() }).is_err());
+        assert!(apply(ServerConfigFile { max_body_size: Some(0), ..Default::default() }).is_err());
+        assert!(apply(ServerConfigFile { base_path: Some("/a b".to_string()), ..Default::default() }).is_err());
+        assert!(apply(ServerConfigFile {
+            cors: Some(CorsConfig { allowed_origins: vec!["example.com".to_string()], allow_credentials: false }),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_url_includes_base_path() {
+        let config = ServerConfig::new(3456).with_base_path("vibing2/").unwrap();
+        assert_eq!(config.url(), "http://127.0.0.1:3456/vibing2");
+    }
+}
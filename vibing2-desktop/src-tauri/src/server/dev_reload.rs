@@ -0,0 +1,63 @@
+// Dev reload module - Watch the static directory during development
+use notify::{RecursiveMode, Watcher};
+use std::sync::Arc;
+use std::time::Duration;
+use super::static_files::StaticFiles;
+
+/// Quiet period before a burst of file events counts as one rebuild
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Called after the static cache has been invalidated
+pub type ReloadHook = Arc<dyn Fn() + Send + Sync>;
+
+/// Watch the static root, clearing cached ETags and calling `on_reload`
+/// after each frontend rebuild
+///
+/// Watching stops when the returned watcher is dropped. Returns `None` if
+/// the directory can't be watched (for example before the first build).
+pub fn watch_static_dir(files: Arc<StaticFiles>, on_reload: Option<ReloadHook>) -> Option<notify::RecommendedWatcher> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let mut watcher = match notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        if let Ok(event) = result {
+            if !event.kind.is_access() {
+                let _ = tx.send(());
+            }
+        }
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("⚠️  Failed to create static file watcher: {}", e);
+            return None;
+        }
+    };
+
+    if let Err(e) = watcher.watch(files.root(), RecursiveMode::Recursive) {
+        eprintln!("⚠️  Not watching {}: {}", files.root().display(), e);
+        return None;
+    }
+
+    println!("👀 Watching {} for frontend rebuilds", files.root().display());
+
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            // Let the rebuild finish writing before invalidating
+            loop {
+                match tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                    Ok(Some(())) => continue,
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+
+            files.clear();
+            println!("🔄 Static files changed, cache cleared");
+
+            if let Some(on_reload) = &on_reload {
+                on_reload();
+            }
+        }
+    });
+
+    Some(watcher)
+}
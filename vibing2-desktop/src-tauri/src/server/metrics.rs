@@ -0,0 +1,229 @@
+// Metrics module - Request, latency, and connection metrics for the server
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Latency histogram bucket upper bounds, in seconds
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Default, Clone)]
+struct RouteStats {
+    /// Request count per status code
+    statuses: BTreeMap<u16, u64>,
+    /// Cumulative counts per latency bucket (plus +Inf as the last entry)
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    latency_sum: f64,
+    count: u64,
+}
+
+/// Metrics registry shared through `ServerState`
+#[derive(Debug)]
+pub struct Metrics {
+    started_at: Instant,
+    /// Keyed by (method, matched route)
+    routes: Mutex<BTreeMap<(String, String), RouteStats>>,
+    active_streams: Arc<AtomicI64>,
+    active_websockets: Arc<AtomicI64>,
+}
+
+/// Decrements a connection gauge when dropped
+#[derive(Debug)]
+pub struct ConnectionGuard(Arc<AtomicI64>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// JSON view of the registry
+#[derive(Debug, Serialize)]
+pub struct MetricsSnapshot {
+    pub uptime_secs: u64,
+    pub requests_total: u64,
+    pub active_streams: i64,
+    pub active_websockets: i64,
+    pub db_pool_size: u32,
+    pub db_pool_idle: usize,
+    pub routes: Vec<RouteSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteSnapshot {
+    pub method: String,
+    pub route: String,
+    pub requests: u64,
+    pub statuses: BTreeMap<u16, u64>,
+    pub avg_latency_ms: f64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            started_at: Instant::now(),
+            routes: Mutex::new(BTreeMap::new()),
+            active_streams: Arc::new(AtomicI64::new(0)),
+            active_websockets: Arc::new(AtomicI64::new(0)),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a finished request
+    pub fn record_request(&self, method: &str, route: &str, status: u16, latency: Duration) {
+        let Ok(mut routes) = self.routes.lock() else {
+            return;
+        };
+
+        let stats = routes
+            .entry((method.to_string(), route.to_string()))
+            .or_default();
+
+        let secs = latency.as_secs_f64();
+        *stats.statuses.entry(status).or_insert(0) += 1;
+        stats.count += 1;
+        stats.latency_sum += secs;
+
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            if secs <= *bound {
+                stats.buckets[i] += 1;
+            }
+        }
+        stats.buckets[LATENCY_BUCKETS.len()] += 1;
+    }
+
+    /// Count an open SSE stream until the guard is dropped
+    pub fn track_stream(&self) -> ConnectionGuard {
+        self.active_streams.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self.active_streams.clone())
+    }
+
+    /// Count an open WebSocket until the guard is dropped
+    pub fn track_websocket(&self) -> ConnectionGuard {
+        self.active_websockets.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self.active_websockets.clone())
+    }
+
+    pub fn snapshot(&self, pool: &sqlx::SqlitePool) -> MetricsSnapshot {
+        let routes = self.routes.lock().map(|r| r.clone()).unwrap_or_default();
+
+        MetricsSnapshot {
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            requests_total: routes.values().map(|stats| stats.count).sum(),
+            active_streams: self.active_streams.load(Ordering::Relaxed),
+            active_websockets: self.active_websockets.load(Ordering::Relaxed),
+            db_pool_size: pool.size(),
+            db_pool_idle: pool.num_idle(),
+            routes: routes
+                .into_iter()
+                .map(|((method, route), stats)| RouteSnapshot {
+                    method,
+                    route,
+                    requests: stats.count,
+                    avg_latency_ms: if stats.count > 0 {
+                        stats.latency_sum / stats.count as f64 * 1000.0
+                    } else {
+                        0.0
+                    },
+                    statuses: stats.statuses,
+                })
+                .collect(),
+        }
+    }
+
+    /// Render in the Prometheus text exposition format
+    pub fn render_prometheus(&self, pool: &sqlx::SqlitePool) -> String {
+        let routes = self.routes.lock().map(|r| r.clone()).unwrap_or_default();
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP vibing2_uptime_seconds Seconds since the server started");
+        let _ = writeln!(out, "# TYPE vibing2_uptime_seconds gauge");
+        let _ = writeln!(out, "vibing2_uptime_seconds {}", self.started_at.elapsed().as_secs());
+
+        let _ = writeln!(out, "# HELP vibing2_http_requests_total HTTP requests by route and status");
+        let _ = writeln!(out, "# TYPE vibing2_http_requests_total counter");
+        for ((method, route), stats) in &routes {
+            for (status, count) in &stats.statuses {
+                let _ = writeln!(
+                    out,
+                    "vibing2_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    method, escape_label(route), status, count
+                );
+            }
+        }
+
+        let _ = writeln!(out, "# HELP vibing2_http_request_duration_seconds HTTP request latency by route");
+        let _ = writeln!(out, "# TYPE vibing2_http_request_duration_seconds histogram");
+        for ((method, route), stats) in &routes {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, escape_label(route));
+            for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "vibing2_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, stats.buckets[i]
+                );
+            }
+            let _ = writeln!(
+                out,
+                "vibing2_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, stats.buckets[LATENCY_BUCKETS.len()]
+            );
+            let _ = writeln!(out, "vibing2_http_request_duration_seconds_sum{{{}}} {}", labels, stats.latency_sum);
+            let _ = writeln!(out, "vibing2_http_request_duration_seconds_count{{{}}} {}", labels, stats.count);
+        }
+
+        let gauges = [
+            ("vibing2_active_streams", "Open SSE generation streams", self.active_streams.load(Ordering::Relaxed)),
+            ("vibing2_active_websockets", "Open WebSocket connections", self.active_websockets.load(Ordering::Relaxed)),
+            ("vibing2_db_pool_connections", "Database pool connections", pool.size() as i64),
+            ("vibing2_db_pool_idle_connections", "Idle database pool connections", pool.num_idle() as i64),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_request_buckets() {
+        let metrics = Metrics::new();
+        metrics.record_request("GET", "/api/health", 200, Duration::from_millis(3));
+        metrics.record_request("GET", "/api/health", 500, Duration::from_millis(300));
+
+        let routes = metrics.routes.lock().unwrap();
+        let stats = &routes[&("GET".to_string(), "/api/health".to_string())];
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.statuses[&200], 1);
+        assert_eq!(stats.buckets[0], 1); // <= 5ms
+        assert_eq!(stats.buckets[6], 2); // <= 500ms
+        assert_eq!(stats.buckets[LATENCY_BUCKETS.len()], 2);
+    }
+
+    #[test]
+    fn test_connection_guard() {
+        let metrics = Metrics::new();
+        let guard = metrics.track_stream();
+        assert_eq!(metrics.active_streams.load(Ordering::Relaxed), 1);
+        drop(guard);
+        assert_eq!(metrics.active_streams.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,319 @@
+// Authentication middleware - Resolves bearer tokens to users
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use crate::server::{api::auth::bearer_token, ServerState};
+use crate::{pairing, sessions};
+
+/// Account roles, ordered from least to most privileged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can read projects and agents
+    Viewer,
+    /// Can also create, update, and delete projects and run generations
+    Editor,
+    /// Can also manage credentials and other users
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Role, String> {
+        match value {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "owner" => Ok(Role::Owner),
+            other => Err(format!("Unknown role: {}", other)),
+        }
+    }
+}
+
+/// Authenticated user attached to request extensions by [`authorize`]
+///
+/// Handlers behind the middleware can take `Extension<AuthUser>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthUser {
+    pub id: String,
+    pub name: Option<String>,
+    pub email: String,
+    pub role: Role,
+    /// Set when the request was authenticated with a paired-device token
+    pub device_id: Option<String>,
+    #[serde(skip)]
+    pub token: String,
+}
+
+impl AuthUser {
+    /// Identity used for per-project access checks
+    pub fn principal(&self) -> crate::sharing::Principal<'_> {
+        crate::sharing::Principal {
+            user_id: &self.id,
+            device_id: self.device_id.as_deref(),
+            is_owner: self.role == Role::Owner && self.device_id.is_none(),
+        }
+    }
+}
+
+/// Who may call a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// No token required; handlers may still read one themselves
+    Public,
+    /// Any valid session or device token
+    Authenticated,
+    /// A valid token for a user with at least this role
+    Role(Role),
+}
+
+/// One row of the route permission table
+#[derive(Debug, Clone, Copy)]
+pub struct RoutePermission {
+    /// HTTP method, or `*` for any
+    pub method: &'static str,
+    /// Route template relative to `/api`, with `:param` segments
+    pub path: &'static str,
+    pub permission: Permission,
+}
+
+const fn route(method: &'static str, path: &'static str, permission: Permission) -> RoutePermission {
+    RoutePermission { method, path, permission }
+}
+
+const EDITOR: Permission = Permission::Role(Role::Editor);
+const OWNER: Permission = Permission::Role(Role::Owner);
+
+/// Every API route and who may call it, checked by [`authorize`]
+///
+/// Routes missing from the table are owner-only, so a new route without an
+/// entry fails closed.
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    // Authentication
+    route("POST", "/auth/signin", Permission::Public),
+    route("POST", "/auth/signup", Permission::Public),
+    route("POST", "/auth/signout", Permission::Public),
+    route("GET", "/auth/session", Permission::Public),
+    route("GET", "/auth/csrf", Permission::Public),
+    route("POST", "/auth/pair", Permission::Public),
+    route("*", "/auth/sessions", Permission::Authenticated),
+    route("DELETE", "/auth/sessions/:id", Permission::Authenticated),
+    route("POST", "/auth/2fa/enroll", Permission::Authenticated),
+    route("POST", "/auth/2fa/confirm", Permission::Authenticated),
+    route("POST", "/auth/2fa/disable", Permission::Authenticated),
+    // Agents
+    route("GET", "/agents/list", Permission::Public),
+    route("GET", "/agents/:id", Permission::Public),
+    route("POST", "/agents", EDITOR),
+    route("POST", "/agents/validate", EDITOR),
+    route("PUT", "/agents/:id", EDITOR),
+    route("DELETE", "/agents/:id", EDITOR),
+    route("GET", "/agents/:id/icon", Permission::Public),
+    route("PUT", "/agents/:id/icon", EDITOR),
+    route("DELETE", "/agents/:id/icon", EDITOR),
+    route("POST", "/agents/:id/favorite", Permission::Authenticated),
+    route("POST", "/agents/:id/test", EDITOR),
+    // Health and metrics
+    route("GET", "/health", Permission::Public),
+    route("GET", "/health/live", Permission::Public),
+    route("GET", "/health/ready", Permission::Public),
+    route("GET", "/metrics", Permission::Public),
+    // Projects
+    route("GET", "/projects/list", Permission::Authenticated),
+    route("POST", "/projects/load", Permission::Authenticated),
+    route("POST", "/projects/save", EDITOR),
+    route("GET", "/projects/:id", Permission::Authenticated),
+    route("POST", "/projects/:id", EDITOR),
+    route("DELETE", "/projects/:id", EDITOR),
+    route("GET", "/projects/:id/shares", Permission::Authenticated),
+    route("POST", "/projects/:id/shares", EDITOR),
+    route("DELETE", "/projects/:id/shares/:share_id", EDITOR),
+    // Generation
+    route("POST", "/agent/stream", EDITOR),
+    route("POST", "/agent/complete", EDITOR),
+    route("POST", "/agent/compare", EDITOR),
+    route("POST", "/agent/compare/pick", EDITOR),
+    route("GET", "/agent/streams", Permission::Authenticated),
+    route("GET", "/agent/streams/:id", Permission::Authenticated),
+    // Recordings hold every user's prompts and replies
+    route("GET", "/agent/streams/:id/replay", OWNER),
+    route("POST", "/attachments", EDITOR),
+    route("DELETE", "/attachments/:id", EDITOR),
+    route("GET", "/models", Permission::Authenticated),
+    // Prompt templates
+    route("GET", "/templates", Permission::Authenticated),
+    route("POST", "/templates", EDITOR),
+    route("DELETE", "/templates/:id", EDITOR),
+    route("POST", "/templates/:id/render", Permission::Authenticated),
+    // Credentials
+    route("*", "/credentials", OWNER),
+    route("*", "/credentials/:provider", OWNER),
+];
+
+/// Look up the permission for a request relative to `/api`
+pub fn permission_for(method: &Method, path: &str) -> Permission {
+    // HEAD is allowed wherever GET is
+    let method = if method == Method::HEAD { "GET" } else { method.as_str() };
+
+    ROUTE_PERMISSIONS
+        .iter()
+        .find(|entry| (entry.method == "*" || entry.method == method) && template_matches(entry.path, path))
+        .map(|entry| entry.permission)
+        .unwrap_or(OWNER)
+}
+
+fn template_matches(template: &str, path: &str) -> bool {
+    let template: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+
+    template.len() == path.len()
+        && template
+            .iter()
+            .zip(&path)
+            .all(|(expected, actual)| (expected.starts_with(':') && !actual.is_empty()) || expected == actual)
+}
+
+/// Enforce [`ROUTE_PERMISSIONS`] for every API route
+///
+/// For non-public routes, looks up the `Authorization: Bearer` token in the
+/// sessions (or paired devices) table, checks the user's role, and inserts
+/// the matching [`AuthUser`] into the request extensions. Responds with 401
+/// if the token is missing, unknown, or expired, and 403 if the role is too
+/// low.
+pub async fn authorize(
+    State(state): State<ServerState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let required = match permission_for(request.method(), request.uri().path()) {
+        Permission::Public => return next.run(request).await,
+        Permission::Authenticated => None,
+        Permission::Role(role) => Some(role),
+    };
+
+    let Some(token) = bearer_token(request.headers()).map(String::from) else {
+        return unauthorized("Missing bearer token");
+    };
+
+    let user = match resolve_user(&state, &token).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return unauthorized("Invalid or expired session"),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": format!("Failed to verify session: {}", e),
+                    "status": StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                })),
+            ).into_response();
+        }
+    };
+
+    if let Some(role) = required {
+        if user.role < role {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "error": format!("This action requires the {} role (you are {})", role.as_str(), user.role.as_str()),
+                    "status": StatusCode::FORBIDDEN.as_u16(),
+                })),
+            ).into_response();
+        }
+    }
+
+    request.extensions_mut().insert(user);
+    next.run(request).await
+}
+
+/// Resolve a session or paired-device token to its user
+///
+/// Device tokens act as the device's owner, capped at the scope granted when
+/// the device was paired.
+pub(crate) async fn resolve_user(state: &ServerState, token: &str) -> Result<Option<AuthUser>, sqlx::Error> {
+    let (user_id, device) = if token.starts_with(pairing::DEVICE_TOKEN_PREFIX) {
+        match pairing::authenticate_device(&state.db_pool, token).await? {
+            Some(device) => (device.user_id.clone(), Some(device)),
+            None => return Ok(None),
+        }
+    } else {
+        match sessions::touch_session(&state.db_pool, token).await? {
+            Some(user_id) => (user_id, None),
+            None => return Ok(None),
+        }
+    };
+
+    let row = sqlx::query("SELECT id, name, email, role FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+    Ok(row.map(|row| {
+        // Unknown roles fall back to the least privileged one
+        let mut role = Role::parse(row.get("role")).unwrap_or(Role::Viewer);
+        if let Some(device) = &device {
+            role = role.min(Role::parse(&device.scope).unwrap_or(Role::Viewer));
+        }
+
+        AuthUser {
+            id: row.get("id"),
+            name: row.get("name"),
+            email: row.get("email"),
+            role,
+            device_id: device.map(|d| d.device_id),
+            token: token.to_string(),
+        }
+    }))
+}
+
+fn unauthorized(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(axum::http::header::WWW_AUTHENTICATE, "Bearer")],
+        Json(serde_json::json!({
+            "error": message,
+            "status": StatusCode::UNAUTHORIZED.as_u16(),
+        })),
+    ).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_ordering() {
+        assert!(Role::Owner > Role::Editor);
+        assert!(Role::Editor > Role::Viewer);
+        assert_eq!(Role::parse("editor").unwrap(), Role::Editor);
+        assert!(Role::parse("admin").is_err());
+    }
+
+    #[test]
+    fn test_permission_table() {
+        assert_eq!(permission_for(&Method::POST, "/auth/signin"), Permission::Public);
+        assert_eq!(permission_for(&Method::GET, "/projects/abc"), Permission::Authenticated);
+        assert_eq!(permission_for(&Method::HEAD, "/projects/abc"), Permission::Authenticated);
+        assert_eq!(permission_for(&Method::DELETE, "/projects/abc"), EDITOR);
+        assert_eq!(permission_for(&Method::DELETE, "/projects/abc/shares/s1"), EDITOR);
+        assert_eq!(permission_for(&Method::PUT, "/credentials/openai"), OWNER);
+    }
+
+    #[test]
+    fn test_unlisted_routes_fail_closed() {
+        assert_eq!(permission_for(&Method::GET, "/admin/secret"), OWNER);
+        assert_eq!(permission_for(&Method::GET, "/projects/"), OWNER);
+        assert!(!template_matches("/projects/:id", "/projects"));
+    }
+}
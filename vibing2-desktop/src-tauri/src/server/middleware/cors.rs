@@ -0,0 +1,79 @@
+// CORS middleware - Cross-origin policy for the embedded server
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use crate::server::{config::ServerConfig, middleware::csrf::CSRF_HEADER};
+
+/// Origins the desktop webview loads the app from
+const WEBVIEW_ORIGINS: [&str; 3] = ["tauri://localhost", "http://tauri.localhost", "https://tauri.localhost"];
+
+/// Build the CORS layer applied to every route
+///
+/// Always allows the server's own origin and the desktop webview; anything
+/// else must be listed in `ServerConfig::cors`. A `*` entry allows any
+/// origin, but then credentials are never allowed.
+pub fn cors_layer(config: &ServerConfig) -> CorsLayer {
+    let allow_any = config.cors.allowed_origins.iter().any(|origin| origin == "*");
+
+    let layer = CorsLayer::new()
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static(CSRF_HEADER),
+        ])
+        .expose_headers([header::CONTENT_TYPE]);
+
+    if allow_any {
+        if config.cors.allow_credentials {
+            eprintln!("⚠️  CORS allows any origin; ignoring allow_credentials");
+        }
+        return layer.allow_origin(AllowOrigin::any());
+    }
+
+    layer
+        .allow_origin(allowed_origins(config))
+        .allow_credentials(config.cors.allow_credentials)
+}
+
+fn allowed_origins(config: &ServerConfig) -> Vec<HeaderValue> {
+    let own = [
+        format!("http://127.0.0.1:{}", config.port),
+        format!("http://localhost:{}", config.port),
+        crate::server::transport::proxy_url(),
+    ];
+
+    own.iter()
+        .map(String::as_str)
+        .chain(WEBVIEW_ORIGINS)
+        .chain(config.cors.allowed_origins.iter().map(String::as_str))
+        .filter_map(|origin| match HeaderValue::from_str(origin.trim_end_matches('/')) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                eprintln!("⚠️  Ignoring invalid CORS origin: {}", origin);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_origins_include_own_origin() {
+        let mut config = ServerConfig::new(4000);
+        config.cors.allowed_origins = vec!["https://example.com/".to_string()];
+
+        let origins = allowed_origins(&config);
+        assert!(origins.contains(&HeaderValue::from_static("http://127.0.0.1:4000")));
+        assert!(origins.contains(&HeaderValue::from_static("tauri://localhost")));
+        assert!(origins.contains(&HeaderValue::from_static("https://example.com")));
+    }
+}
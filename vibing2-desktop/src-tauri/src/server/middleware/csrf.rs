@@ -0,0 +1,134 @@
+// CSRF middleware - Double-submit token check for browser-originated writes
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rand::RngCore;
+
+/// Cookie holding the CSRF token
+pub const CSRF_COOKIE: &str = "vibing2_csrf";
+
+/// Header that must echo the cookie value on mutating requests
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Issue a CSRF token as both a cookie and a JSON body
+///
+/// The cookie is readable by scripts on purpose: the double-submit pattern
+/// relies on same-origin pages copying it into the `X-CSRF-Token` header,
+/// which a cross-site page cannot do.
+#[utoipa::path(
+    get,
+    path = "/api/auth/csrf",
+    tag = "auth",
+    responses(
+        (status = 200, description = "CSRF token, also set as the vibing2_csrf cookie"),
+    )
+)]
+pub async fn issue_csrf_token() -> Response {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let cookie = format!("{}={}; Path=/; SameSite=Strict", CSRF_COOKIE, token);
+
+    let mut response = Json(serde_json::json!({
+        "success": true,
+        "csrf_token": token
+    })).into_response();
+
+    if let Ok(value) = HeaderValue::from_str(&cookie) {
+        response.headers_mut().insert(header::SET_COOKIE, value);
+    }
+
+    response
+}
+
+/// Require a matching CSRF cookie and header on browser POST/PUT/PATCH/DELETE
+///
+/// Requests without any browser markers (`Origin`, `Cookie`, `Sec-Fetch-Site`)
+/// come from non-browser clients such as scripts or paired-device apps, which
+/// cannot be driven cross-site, and pass through unchanged.
+pub async fn csrf_middleware(request: Request, next: Next) -> Response {
+    if is_safe_method(request.method()) || !is_browser_request(request.headers()) {
+        return next.run(request).await;
+    }
+
+    let cookie_token = cookie_value(request.headers(), CSRF_COOKIE);
+    let header_token = request
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    match (cookie_token, header_token) {
+        (Some(cookie), Some(header)) if constant_time_eq(cookie.as_bytes(), header.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "Missing or invalid CSRF token",
+                "status": StatusCode::FORBIDDEN.as_u16(),
+            })),
+        ).into_response(),
+    }
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE)
+}
+
+fn is_browser_request(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::ORIGIN)
+        || headers.contains_key(header::COOKIE)
+        || headers.contains_key("sec-fetch-site")
+}
+
+/// Find a cookie value across all `Cookie` headers
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_value() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, "theme=dark; vibing2_csrf=abc123".parse().unwrap());
+
+        assert_eq!(cookie_value(&headers, CSRF_COOKIE), Some("abc123"));
+        assert_eq!(cookie_value(&headers, "missing"), None);
+    }
+
+    #[test]
+    fn test_browser_detection() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_browser_request(&headers));
+
+        headers.insert(header::ORIGIN, "http://evil.example".parse().unwrap());
+        assert!(is_browser_request(&headers));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"tok"));
+    }
+}
@@ -0,0 +1,73 @@
+// LAN middleware - Mandatory token auth for requests from other machines
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::net::SocketAddr;
+use crate::server::{api::auth::bearer_token, middleware::auth::resolve_user, ServerState};
+
+/// API routes a remote client may call before it has a token
+const LAN_PUBLIC_PATHS: [&str; 5] = [
+    "/auth/csrf",
+    "/auth/pair",
+    "/health",
+    "/health/live",
+    "/health/ready",
+];
+
+/// Require a valid bearer token on every API request from a non-loopback peer
+///
+/// Only active when the server is bound beyond loopback. Remote clients can
+/// fetch a CSRF token or redeem a pairing code; everything else, including
+/// password sign in and sign-up and the otherwise public agent and metrics
+/// routes, needs a session or device token. Local requests are unaffected.
+pub async fn lan_guard_middleware(
+    State(state): State<ServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.config.is_lan() || LAN_PUBLIC_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let is_remote = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| !addr.ip().is_loopback())
+        // Without peer info, assume the worst
+        .unwrap_or(true);
+
+    if !is_remote {
+        return next.run(request).await;
+    }
+
+    let Some(token) = bearer_token(request.headers()) else {
+        return unauthorized("LAN access requires a bearer token");
+    };
+
+    match resolve_user(&state, token).await {
+        Ok(Some(_)) => next.run(request).await,
+        Ok(None) => unauthorized("Invalid or expired session"),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": format!("Failed to verify session: {}", e),
+                "status": StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            })),
+        ).into_response(),
+    }
+}
+
+fn unauthorized(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(axum::http::header::WWW_AUTHENTICATE, "Bearer")],
+        Json(serde_json::json!({
+            "error": message,
+            "status": StatusCode::UNAUTHORIZED.as_u16(),
+        })),
+    ).into_response()
+}
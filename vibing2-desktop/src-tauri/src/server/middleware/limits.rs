@@ -0,0 +1,159 @@
+// Limits middleware - Body size, timeout, and concurrency limits from ServerConfig
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use crate::server::{config::ServerLimits, ServerState};
+
+/// Limits read on every request so they can be changed while serving
+#[derive(Debug)]
+pub struct RuntimeLimits {
+    timeout_ms: AtomicU64,
+    max_body_size: AtomicUsize,
+    max_connections: AtomicUsize,
+    in_flight: AtomicUsize,
+}
+
+/// Releases an in-flight slot when dropped
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl RuntimeLimits {
+    pub fn new(limits: &ServerLimits) -> Self {
+        let runtime = Self {
+            timeout_ms: AtomicU64::new(0),
+            max_body_size: AtomicUsize::new(0),
+            max_connections: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+        };
+        runtime.update(limits);
+        runtime
+    }
+
+    /// Apply new limits; requests already in flight keep their old timeout
+    pub fn update(&self, limits: &ServerLimits) {
+        self.timeout_ms.store(limits.timeout_secs.saturating_mul(1000), Ordering::Relaxed);
+        self.max_body_size.store(limits.max_body_size, Ordering::Relaxed);
+        self.max_connections.store(limits.max_connections, Ordering::Relaxed);
+    }
+
+    pub fn current(&self) -> ServerLimits {
+        ServerLimits {
+            timeout_secs: self.timeout_ms.load(Ordering::Relaxed) / 1000,
+            max_body_size: self.max_body_size.load(Ordering::Relaxed),
+            max_connections: self.max_connections.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Claim an in-flight slot, or `None` when at capacity
+    fn acquire(&self) -> Option<InFlightGuard<'_>> {
+        let max = self.max_connections.load(Ordering::Relaxed);
+        let previous = self.in_flight.fetch_add(1, Ordering::AcqRel);
+        let guard = InFlightGuard(&self.in_flight);
+
+        if max > 0 && previous >= max {
+            return None;
+        }
+        Some(guard)
+    }
+}
+
+/// Enforce `max_connections`, `max_body_size`, and `timeout`
+///
+/// Requests over the concurrency limit get 503, oversized bodies get 413
+/// (up front from `Content-Length`, or while streaming), and handlers that
+/// don't produce a response in time get 408. The timeout covers producing
+/// the response head only, so SSE and WebSocket streams can outlive it.
+pub async fn limits_middleware(
+    State(state): State<ServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limits = state.limits.clone();
+
+    let Some(_slot) = limits.acquire() else {
+        let mut response = error_response(StatusCode::SERVICE_UNAVAILABLE, "Server is at capacity");
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, header::HeaderValue::from_static("1"));
+        return response;
+    };
+
+    let max_body_size = limits.max_body_size.load(Ordering::Relaxed);
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    if content_length.is_some_and(|length| length > max_body_size) {
+        return error_response(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large");
+    }
+
+    let request = request.map(|body| Body::new(http_body_util::Limited::new(body, max_body_size)));
+
+    let timeout = Duration::from_millis(limits.timeout_ms.load(Ordering::Relaxed));
+    if timeout.is_zero() {
+        return next.run(request).await;
+    }
+
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => error_response(StatusCode::REQUEST_TIMEOUT, "Request timed out"),
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "error": message,
+            "status": status.as_u16(),
+        })),
+    ).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire_respects_max_connections() {
+        let limits = RuntimeLimits::new(&ServerLimits {
+            timeout_secs: 30,
+            max_body_size: 1024,
+            max_connections: 1,
+        });
+
+        let first = limits.acquire();
+        assert!(first.is_some());
+        assert!(limits.acquire().is_none());
+
+        drop(first);
+        assert!(limits.acquire().is_some());
+    }
+
+    #[test]
+    fn test_update_is_visible() {
+        let limits = RuntimeLimits::new(&ServerLimits::default());
+        let updated = ServerLimits {
+            timeout_secs: 5,
+            max_body_size: 512,
+            max_connections: 4,
+        };
+
+        limits.update(&updated);
+        assert_eq!(limits.current(), updated);
+    }
+}
@@ -0,0 +1,144 @@
+// Logging middleware - Structured request logs with request IDs
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+use tracing::Instrument;
+use crate::server::ServerState;
+
+/// Header carrying the request ID in both directions
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Largest error body that gets the request ID added
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// Request ID inserted into request extensions for handlers that want it
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Assign a request ID and log the request as a structured tracing event
+///
+/// A well-formed incoming `X-Request-ID` is reused so IDs can be correlated
+/// across a proxy; otherwise a new UUID is generated. The ID is echoed in the
+/// response header and added as `request_id` to JSON error bodies. Events are
+/// skipped when `ServerConfig::enable_logging` is off.
+pub async fn logging_middleware(
+    State(state): State<ServerState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid_request_id(value))
+        .map(String::from)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    request.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let response = next.run(request).instrument(span.clone()).await;
+
+    let status = response.status();
+    let mut response = if status.is_client_error() || status.is_server_error() {
+        with_request_id_in_body(response, &request_id).await
+    } else {
+        response
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    if state.config.enable_logging {
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let _enter = span.enter();
+
+        if status.is_server_error() {
+            tracing::error!(%method, path, status = status.as_u16(), latency_ms, "request failed");
+        } else if status.is_client_error() {
+            tracing::warn!(%method, path, status = status.as_u16(), latency_ms, "request rejected");
+        } else {
+            tracing::info!(%method, path, status = status.as_u16(), latency_ms, "request completed");
+        }
+    }
+
+    response
+}
+
+/// Accept client IDs that are short and header-safe
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 128
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Add `request_id` to a JSON object error body; other bodies pass through
+async fn with_request_id_in_body(response: Response, request_id: &str) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ERROR_BODY).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.insert("request_id".to_string(), serde_json::Value::from(request_id));
+            let body = serde_json::to_vec(&object).unwrap_or_else(|_| bytes.to_vec());
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(body)
+        }
+        _ => Body::from(bytes),
+    };
+
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn test_is_valid_request_id() {
+        assert!(is_valid_request_id("8f14e45f-ceea-4b8f-9c1a-1f2d3e4a5b6c"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("has spaces"));
+        assert!(!is_valid_request_id(&"a".repeat(129)));
+    }
+
+    #[tokio::test]
+    async fn test_error_body_gets_request_id() {
+        let response = (
+            StatusCode::NOT_FOUND,
+            axum::Json(serde_json::json!({ "error": "Project not found", "status": 404 })),
+        ).into_response();
+
+        let response = with_request_id_in_body(response, "req-1").await;
+        let bytes = axum::body::to_bytes(response.into_body(), MAX_ERROR_BODY).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(body["request_id"], "req-1");
+        assert_eq!(body["error"], "Project not found");
+    }
+}
@@ -0,0 +1,35 @@
+// Metrics middleware - Records request counts and latency per route
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+use crate::server::ServerState;
+
+/// Record method, matched route, status, and latency for every request
+///
+/// Uses the route template (`/api/projects/:id`) rather than the raw path so
+/// label cardinality stays bounded; unmatched requests (static files) are
+/// grouped under `static`.
+pub async fn metrics_middleware(
+    State(state): State<ServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "static".to_string());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    state
+        .metrics
+        .record_request(&method, &route, response.status().as_u16(), started.elapsed());
+
+    response
+}
@@ -1,8 +1,18 @@
 // Middleware module
 pub mod cors;
 pub mod auth;
+pub mod csrf;
+pub mod lan;
+pub mod limits;
 pub mod logging;
+pub mod metrics;
+pub mod rate_limit;
 
 pub use cors::cors_layer;
-pub use auth::auth_middleware;
-pub use logging::logging_middleware;
\ No newline at end of file
+pub use auth::authorize;
+pub use csrf::csrf_middleware;
+pub use lan::lan_guard_middleware;
+pub use limits::{limits_middleware, RuntimeLimits};
+pub use logging::{logging_middleware, RequestId};
+pub use metrics::metrics_middleware;
+pub use rate_limit::rate_limit_middleware;
\ No newline at end of file
@@ -0,0 +1,82 @@
+// Rate limit middleware - Per-IP and per-token limits for the API router
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::net::SocketAddr;
+use crate::server::{api::auth::bearer_token, utils::rate_limit::RateLimitDecision, ServerState};
+
+/// Apply the token-bucket limits from `ServerConfig::rate_limit`
+///
+/// Every request takes a token from its client IP's bucket, and requests with
+/// a bearer token also take one from that token's bucket. The tighter of the
+/// two decisions is reported in `X-RateLimit-*` headers; rejected requests get
+/// 429 with `Retry-After`.
+pub async fn rate_limit_middleware(
+    State(state): State<ServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limits = &state.config.rate_limit;
+    if !limits.enabled {
+        return next.run(request).await;
+    }
+
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut decision = state
+        .rate_limiter
+        .check(&format!("ip:{}", ip), limits.ip_per_minute, limits.ip_burst);
+
+    if let Some(token) = bearer_token(request.headers()) {
+        let token_decision = state
+            .rate_limiter
+            .check(&format!("token:{}", token), limits.token_per_minute, limits.token_burst);
+
+        if !token_decision.allowed || (decision.allowed && token_decision.remaining < decision.remaining) {
+            decision = token_decision;
+        }
+    }
+
+    if !decision.allowed {
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({
+                "error": "Rate limit exceeded",
+                "status": StatusCode::TOO_MANY_REQUESTS.as_u16(),
+            })),
+        ).into_response();
+
+        let retry_after = decision.retry_after.as_secs().max(1);
+        if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+            response.headers_mut().insert("retry-after", value);
+        }
+        insert_rate_limit_headers(response.headers_mut(), &decision);
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    insert_rate_limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    let values = [
+        ("x-ratelimit-limit", decision.limit.to_string()),
+        ("x-ratelimit-remaining", decision.remaining.to_string()),
+        ("x-ratelimit-reset", decision.reset_after.as_secs().to_string()),
+    ];
+
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+}
@@ -2,31 +2,37 @@
 use axum::{
     Router,
     extract::State,
-    http::{StatusCode, Method, header},
+    http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
 use tower::ServiceBuilder;
 use tower_http::{
-    services::{ServeDir, ServeFile},
-    cors::{CorsLayer, Any},
+    compression::CompressionLayer,
     trace::TraceLayer,
 };
 use std::{
     net::SocketAddr,
     path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
-use tokio::net::TcpListener;
+use tokio::{net::TcpListener, sync::Mutex, task::JoinHandle};
 use serde::{Deserialize, Serialize};
 
 pub mod config;
+#[cfg(debug_assertions)]
+pub mod dev_reload;
+pub mod metrics;
 pub mod static_files;
 pub mod api;
 pub mod middleware;
+pub mod shutdown;
+pub mod streams;
+pub mod transport;
 pub mod utils;
 
-use config::ServerConfig;
+use config::{ServerConfig, ServerLimits, Transport};
 use api::create_api_routes;
 
 #[derive(Clone)]
@@ -34,97 +40,337 @@ pub struct ServerState {
     pub config: Arc<ServerConfig>,
     pub static_dir: PathBuf,
     pub db_pool: sqlx::SqlitePool,
+    pub login_throttle: Arc<utils::LoginThrottle>,
+    pub metrics: Arc<metrics::Metrics>,
+    pub rate_limiter: Arc<utils::RateLimiter>,
+    pub limits: Arc<middleware::RuntimeLimits>,
+    pub shutdown: shutdown::ShutdownSignal,
+    pub static_files: Arc<static_files::StaticFiles>,
+    pub streams: Arc<streams::StreamSessions>,
 }
 
-#[derive(Debug, Serialize)]
+/// How long `ServerManager::stop` waits for in-flight requests to drain
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerInfo {
     pub url: String,
     pub port: u16,
     pub status: String,
+    /// Address the server is bound to (`127.0.0.1` unless in LAN mode)
+    #[serde(default)]
+    pub host: String,
+    /// `tcp`, or `local` for a Unix socket / named pipe
+    #[serde(default)]
+    pub transport: String,
+    /// Prefix all routes are mounted under, empty when served from the root
+    #[serde(default)]
+    pub base_path: String,
+}
+
+impl ServerInfo {
+    /// Info reported while no server is running
+    pub fn stopped() -> Self {
+        Self {
+            url: String::new(),
+            port: 0,
+            status: "stopped".to_string(),
+            host: String::new(),
+            transport: String::new(),
+            base_path: String::new(),
+        }
+    }
+}
+
+struct RunningServer {
+    info: ServerInfo,
+    task: JoinHandle<()>,
+    limits: Arc<middleware::RuntimeLimits>,
+    shutdown: shutdown::Shutdown,
+    /// Static directory watcher (development builds only)
+    #[cfg(debug_assertions)]
+    _watcher: Option<notify::RecommendedWatcher>,
+}
+
+/// Owns the running embedded server so it can be stopped and restarted
+///
+/// Managed as Tauri state; at most one server runs at a time.
+#[derive(Default)]
+pub struct ServerManager {
+    running: Mutex<Option<RunningServer>>,
+    /// Called when static files change in development builds
+    static_reload_hook: std::sync::Mutex<Option<Arc<dyn Fn() + Send + Sync>>>,
+}
+
+impl ServerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a callback for frontend rebuilds picked up by the dev
+    /// static-file watcher; applies to servers started afterwards
+    pub fn on_static_reload(&self, hook: impl Fn() + Send + Sync + 'static) {
+        if let Ok(mut slot) = self.static_reload_hook.lock() {
+            *slot = Some(Arc::new(hook));
+        }
+    }
+
+    /// Start the server, or return the running one's info
+    pub async fn start(
+        &self,
+        static_dir: PathBuf,
+        db_pool: sqlx::SqlitePool,
+        config: ServerConfig,
+    ) -> Result<ServerInfo, ServerError> {
+        let mut running = self.running.lock().await;
+
+        if let Some(server) = running.as_ref() {
+            if !server.task.is_finished() {
+                return Ok(server.info.clone());
+            }
+        }
+
+        let started = start_server(static_dir, db_pool, config).await?;
+        let info = started.info.clone();
+
+        #[cfg(debug_assertions)]
+        let watcher = {
+            let hook = self.static_reload_hook.lock().ok().and_then(|hook| hook.clone());
+            dev_reload::watch_static_dir(started.static_files.clone(), hook)
+        };
+
+        *running = Some(RunningServer {
+            info: started.info,
+            task: started.task,
+            limits: started.limits,
+            shutdown: started.shutdown,
+            #[cfg(debug_assertions)]
+            _watcher: watcher,
+        });
+
+        Ok(info)
+    }
+
+    /// Stop the server, returning whether one was running
+    ///
+    /// Stops accepting connections, closes open streams, and waits up to
+    /// `DRAIN_TIMEOUT` for in-flight requests before aborting. The listening
+    /// port is released by the time this returns.
+    pub async fn stop(&self) -> bool {
+        let Some(mut server) = self.running.lock().await.take() else {
+            return false;
+        };
+
+        server.shutdown.trigger();
+
+        if tokio::time::timeout(DRAIN_TIMEOUT, &mut server.task).await.is_err() {
+            eprintln!("⚠️  Server did not drain within {:?}, aborting", DRAIN_TIMEOUT);
+            server.task.abort();
+            let _ = server.task.await;
+        }
+
+        println!("🛑 Server stopped (port {})", server.info.port);
+        true
+    }
+
+    /// Apply new request limits to the running server, if any
+    pub async fn update_limits(&self, limits: &ServerLimits) -> bool {
+        match self.running.lock().await.as_ref() {
+            Some(server) if !server.task.is_finished() => {
+                server.limits.update(limits);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Info for the running server, or a stopped placeholder
+    pub async fn info(&self) -> ServerInfo {
+        match self.running.lock().await.as_ref() {
+            Some(server) if !server.task.is_finished() => server.info.clone(),
+            _ => ServerInfo::stopped(),
+        }
+    }
+}
+
+/// A server returned by `start_server`
+pub struct StartedServer {
+    pub info: ServerInfo,
+    /// Task serving requests; completes once shutdown has drained
+    pub task: JoinHandle<()>,
+    /// Live request limits
+    pub limits: Arc<middleware::RuntimeLimits>,
+    /// Triggers graceful shutdown
+    pub shutdown: shutdown::Shutdown,
+    /// Static files with their cached ETags
+    pub static_files: Arc<static_files::StaticFiles>,
 }
 
 /// Initialize and start the embedded HTTP server
+///
+/// Over TCP, binds `config.host` on `config.port` if it's free, otherwise on
+/// any available port; `config.port` is replaced by the port actually used.
+/// The local transport listens on a socket or pipe and has no port.
 pub async fn start_server(
     static_dir: PathBuf,
     db_pool: sqlx::SqlitePool,
-) -> Result<ServerInfo, ServerError> {
-    // Find an available port
-    let port = utils::port::find_available_port()?;
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    mut config: ServerConfig,
+) -> Result<StartedServer, ServerError> {
+    let addr = match config.transport {
+        Transport::Tcp => {
+            // Try the preferred port, then find an available one
+            let port = utils::port::choose_port(config.bind_ip(), config.port)?;
+            config.port = port;
+            Some(SocketAddr::new(config.bind_ip(), port))
+        }
+        Transport::Local => {
+            config.port = 0;
+            None
+        }
+    };
 
-    // Create server configuration
-    let config = Arc::new(ServerConfig::new(port));
+    if config.is_lan() {
+        println!("📡 LAN mode enabled, binding {}", config.address());
+    }
+
+    let limits = Arc::new(middleware::RuntimeLimits::new(&config.limits()));
+    let config = Arc::new(config);
+    let (shutdown, shutdown_signal) = shutdown::Shutdown::new();
+
+    // Hash static files up front so ETags are ready for the first request
+    let static_root = static_dir.clone();
+    let static_files = tokio::task::spawn_blocking(move || static_files::StaticFiles::load(static_root))
+        .await
+        .map_err(|e| ServerError::ConfigError(format!("Failed to index static files: {}", e)))?;
+    let static_files = Arc::new(static_files);
 
     // Create shared state
     let state = ServerState {
         config: config.clone(),
         static_dir: static_dir.clone(),
         db_pool,
+        login_throttle: Arc::new(utils::LoginThrottle::new()),
+        metrics: Arc::new(metrics::Metrics::new()),
+        rate_limiter: Arc::new(utils::RateLimiter::new()),
+        limits: limits.clone(),
+        shutdown: shutdown_signal.clone(),
+        static_files: static_files.clone(),
+        streams: Arc::new(streams::StreamSessions::new(config.max_streams)),
     };
 
     // Build the application router
     let app = create_app(state).await?;
 
-    // Create TCP listener
-    let listener = TcpListener::bind(addr).await?;
-
-    println!("🚀 Server starting on http://127.0.0.1:{}", port);
-
     // Spawn the server in the background
-    tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, app).await {
-            eprintln!("Server error: {}", e);
+    let task = match addr {
+        Some(addr) => {
+            let listener = TcpListener::bind(addr).await?;
+
+            tokio::spawn(async move {
+                let service = app.into_make_service_with_connect_info::<SocketAddr>();
+                if let Err(e) = axum::serve(listener, service)
+                    .with_graceful_shutdown(shutdown_signal.triggered())
+                    .await
+                {
+                    eprintln!("Server error: {}", e);
+                }
+            })
         }
-    });
+        None => transport::serve_local(app, shutdown_signal).await?,
+    };
+
+    println!("🚀 Server starting on {}", config.url());
 
-    Ok(ServerInfo {
-        url: format!("http://127.0.0.1:{}", port),
-        port,
+    let info = ServerInfo {
+        url: config.url(),
+        port: config.port,
         status: "running".to_string(),
+        host: config.host.clone(),
+        transport: config.transport.as_str().to_string(),
+        base_path: config.base_path.clone(),
+    };
+
+    Ok(StartedServer {
+        info,
+        task,
+        limits,
+        shutdown,
+        static_files,
     })
 }
 
 /// Create the main application router
 async fn create_app(state: ServerState) -> Result<Router, ServerError> {
-    let static_dir = state.static_dir.clone();
-
-    // Serve index.html for the root path
-    let index_service = ServeFile::new(static_dir.join("index.html"));
-
-    // Serve static files from the Next.js build
-    let static_service = ServeDir::new(&static_dir)
-        .not_found_service(index_service.clone());
+    // Compress dynamic responses; already-encoded static files and SSE
+    // streams are left alone by the default predicate
+    let compress = state.config.enable_compression;
+    let compression_layer = CompressionLayer::new().gzip(compress).br(compress);
 
     // Create API routes
-    let api_routes = create_api_routes();
+    let api_routes = create_api_routes(state.clone());
 
-    // Build the main router
-    let app = Router::new()
+    let cors_layer = middleware::cors_layer(&state.config);
+    let metrics_layer = axum::middleware::from_fn_with_state(state.clone(), middleware::metrics_middleware);
+    let limits_layer = axum::middleware::from_fn_with_state(state.clone(), middleware::limits_middleware);
+    let logging_layer = axum::middleware::from_fn_with_state(state.clone(), middleware::logging_middleware);
+
+    let routes = Router::new()
         // API routes
         .nest("/api", api_routes)
-        // Health check endpoint
+        // Health check endpoints
         .route("/health", axum::routing::get(health_check))
-        // Static files and fallback to index.html for client-side routing
-        .fallback_service(static_service)
+        .route("/health/live", axum::routing::get(api::health::live))
+        .route("/health/ready", axum::routing::get(api::health::ready))
+        // Static files (with ETags and pre-compressed variants) and fallback
+        // to index.html for client-side routing
+        .fallback(static_files::static_handler);
+
+    // Mount everything under the base path for reverse proxies that forward
+    // the prefix unchanged; the bare prefix redirects to its trailing-slash
+    // form so relative asset URLs resolve
+    let base_path = state.config.base_path.clone();
+    let app = if base_path.is_empty() {
+        routes
+    } else {
+        let index = format!("{}/", base_path);
+        Router::new()
+            .route(&base_path, axum::routing::get(move || std::future::ready(axum::response::Redirect::permanent(&index))))
+            .nest(&base_path, routes)
+    };
+
+    // Swagger UI is registered at its full path since it links to the spec
+    // by absolute URL
+    let mut openapi = <api::docs::ApiDoc as utoipa::OpenApi>::openapi();
+    if !base_path.is_empty() {
+        openapi.servers = Some(vec![utoipa::openapi::Server::new(base_path.clone())]);
+    }
+
+    // Build the main router
+    let app = app
+        // OpenAPI spec and Swagger UI
+        .merge(
+            utoipa_swagger_ui::SwaggerUi::new(format!("{}/api/docs", base_path))
+                .url(format!("{}/api/openapi.json", base_path), openapi),
+        )
+        // Body size, timeout, and concurrency limits; the body limit is
+        // enforced by the limits middleware instead of axum's 2MB default
+        .layer(axum::extract::DefaultBodyLimit::disable())
+        .layer(limits_layer)
+        // Record per-route metrics
+        .layer(metrics_layer)
         // Add state
         .with_state(state)
         // Add middleware
         .layer(
             ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
                 .layer(
-                    CorsLayer::new()
-                        .allow_origin(Any)
-                        .allow_methods([
-                            Method::GET,
-                            Method::POST,
-                            Method::PUT,
-                            Method::DELETE,
-                            Method::OPTIONS,
-                        ])
-                        .allow_headers(Any)
-                        .expose_headers([header::CONTENT_TYPE])
+                    TraceLayer::new_for_http()
+                        .make_span_with(tower_http::trace::DefaultMakeSpan::new().level(tracing::Level::INFO)),
                 )
+                .layer(compression_layer)
+                // Inside compression so error bodies can be rewritten
+                .layer(logging_layer)
+                .layer(cors_layer)
         );
 
     Ok(app)
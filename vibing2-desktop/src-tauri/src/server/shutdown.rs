@@ -0,0 +1,55 @@
+// Shutdown module - Graceful shutdown signal shared with long-lived handlers
+use tokio::sync::watch;
+
+/// Owner side of the shutdown signal, held by `ServerManager`
+#[derive(Debug)]
+pub struct Shutdown {
+    sender: watch::Sender<bool>,
+}
+
+/// Receiver side, cloned into `ServerState` for handlers that hold
+/// connections open (SSE streams, WebSockets)
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal {
+    receiver: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    pub fn new() -> (Self, ShutdownSignal) {
+        let (sender, receiver) = watch::channel(false);
+        (Self { sender }, ShutdownSignal { receiver })
+    }
+
+    /// Tell the server and all open streams to wind down
+    pub fn trigger(&self) {
+        let _ = self.sender.send(true);
+    }
+}
+
+impl ShutdownSignal {
+    pub fn is_triggered(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Resolve once shutdown has been triggered (or the owner is dropped)
+    pub async fn triggered(mut self) {
+        let _ = self.receiver.wait_for(|stopping| *stopping).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trigger_wakes_signal() {
+        let (shutdown, signal) = Shutdown::new();
+        assert!(!signal.is_triggered());
+
+        let waiter = tokio::spawn(signal.clone().triggered());
+        shutdown.trigger();
+
+        waiter.await.unwrap();
+        assert!(signal.is_triggered());
+    }
+}
@@ -1,60 +1,244 @@
 // Static file serving module
 use axum::{
     body::Body,
-    extract::Path,
-    http::{header, StatusCode, HeaderValue},
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
     response::{IntoResponse, Response},
 };
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
 use tokio::fs;
+use crate::server::ServerState;
 
-/// Serve static files with proper MIME types
-pub async fn serve_static_file(
-    Path(path): Path<String>,
-    static_dir: PathBuf,
-) -> impl IntoResponse {
-    let file_path = static_dir.join(&path);
+/// Next.js emits content-hashed file names under this prefix
+const HASHED_ASSET_PREFIX: &str = "_next/static/";
 
-    // Security: Prevent directory traversal
-    if !file_path.starts_with(&static_dir) {
-        return (StatusCode::FORBIDDEN, "Access denied").into_response();
+/// Validators for a file on disk
+#[derive(Debug, Clone)]
+struct FileMeta {
+    /// Strong ETag derived from the content hash
+    etag: String,
+    modified: SystemTime,
+    len: u64,
+}
+
+/// Static file root with cached content-hash ETags
+///
+/// ETags are computed for every file when the server starts; files that
+/// change afterwards are rehashed on their next request.
+#[derive(Debug)]
+pub struct StaticFiles {
+    root: PathBuf,
+    entries: RwLock<HashMap<PathBuf, FileMeta>>,
+}
+
+impl StaticFiles {
+    /// Hash every file under `root` (blocking; run off the async runtime)
+    pub fn load(root: PathBuf) -> Self {
+        let mut entries = HashMap::new();
+        let mut pending = vec![root.clone()];
+
+        while let Some(dir) = pending.pop() {
+            let Ok(read_dir) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+
+            for entry in read_dir.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if let (Ok(contents), Ok(metadata)) = (std::fs::read(&path), entry.metadata()) {
+                    entries.insert(path, file_meta(&contents, &metadata));
+                }
+            }
+        }
+
+        println!("📦 Indexed {} static files", entries.len());
+
+        Self {
+            root,
+            entries: RwLock::new(entries),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
     }
 
-    // Check if file exists
-    if !file_path.exists() || !file_path.is_file() {
-        // Try index.html for client-side routing
-        let index_path = static_dir.join("index.html");
-        if index_path.exists() {
-            return serve_file(index_path).await;
+    /// Drop all cached ETags, including those of `.br`/`.gz` variants
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
         }
-        return (StatusCode::NOT_FOUND, "File not found").into_response();
     }
 
-    serve_file(file_path).await
+    /// Cached validators for `path`, rehashing if the file changed on disk
+    async fn meta(&self, path: &Path) -> Option<FileMeta> {
+        let metadata = fs::metadata(path).await.ok()?;
+        let modified = metadata.modified().ok()?;
+
+        if let Some(meta) = self.entries.read().ok()?.get(path) {
+            if meta.modified == modified && meta.len == metadata.len() {
+                return Some(meta.clone());
+            }
+        }
+
+        let contents = fs::read(path).await.ok()?;
+        let meta = file_meta(&contents, &metadata);
+        if let Ok(mut entries) = self.entries.write() {
+            entries.insert(path.to_path_buf(), meta.clone());
+        }
+        Some(meta)
+    }
+
+    /// Map a request path to a file, falling back to index.html for
+    /// client-side routes
+    fn resolve(&self, request_path: &str) -> Option<PathBuf> {
+        let relative = Path::new(request_path.trim_start_matches('/'));
+
+        // Security: Prevent directory traversal
+        if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+            return None;
+        }
+
+        let file_path = self.root.join(relative);
+        let candidates = [
+            file_path.clone(),
+            file_path.join("index.html"),
+            file_path.with_extension("html"),
+            self.root.join("index.html"),
+        ];
+
+        candidates.into_iter().find(|candidate| candidate.is_file())
+    }
+}
+
+fn file_meta(contents: &[u8], metadata: &std::fs::Metadata) -> FileMeta {
+    let digest = Sha256::digest(contents);
+    let hash: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+
+    FileMeta {
+        etag: format!("\"{}\"", hash),
+        modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        len: metadata.len(),
+    }
 }
 
-/// Serve a specific file with proper headers
-async fn serve_file(path: PathBuf) -> Response {
-    match fs::read(&path).await {
-        Ok(contents) => {
-            let mime_type = get_mime_type(&path);
-
-            Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, mime_type)
-                .header(
-                    header::CACHE_CONTROL,
-                    HeaderValue::from_static("public, max-age=3600"),
-                )
-                .body(Body::from(contents))
-                .unwrap()
+/// Serve static files with ETags, conditional requests, and pre-compressed
+/// `.br`/`.gz` variants
+///
+/// Used as the router fallback. Every response carries
+/// `Vary: Accept-Encoding` since the chosen variant depends on that header.
+pub async fn static_handler(
+    State(state): State<ServerState>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    if method != Method::GET && method != Method::HEAD {
+        return (StatusCode::METHOD_NOT_ALLOWED, "Method not allowed").into_response();
+    }
+
+    let files = &state.static_files;
+    let Some(path) = files.resolve(uri.path()) else {
+        return (StatusCode::NOT_FOUND, "File not found").into_response();
+    };
+
+    // Prefer a pre-compressed sibling the client accepts
+    let mut variant = (path.clone(), None);
+    for encoding in accepted_encodings(&headers) {
+        let extension = if encoding == "br" { "br" } else { "gz" };
+        let compressed = PathBuf::from(format!("{}.{}", path.display(), extension));
+        if compressed.is_file() {
+            variant = (compressed, Some(encoding));
+            break;
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response(),
     }
+    let (file_path, encoding) = variant;
+
+    let Some(meta) = files.meta(&file_path).await else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response();
+    };
+
+    let relative = path.strip_prefix(files.root()).unwrap_or(&path);
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    response_headers.insert(header::CACHE_CONTROL, cache_control(relative));
+    if let Ok(etag) = HeaderValue::from_str(&meta.etag) {
+        response_headers.insert(header::ETAG, etag);
+    }
+    if let Ok(last_modified) = HeaderValue::from_str(&http_date(meta.modified)) {
+        response_headers.insert(header::LAST_MODIFIED, last_modified);
+    }
+
+    if is_not_modified(&headers, &meta) {
+        return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+    }
+
+    let contents = match fs::read(&file_path).await {
+        Ok(contents) => contents,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response(),
+    };
+
+    response_headers.insert(header::CONTENT_TYPE, get_mime_type(&path));
+    if let Some(encoding) = encoding {
+        response_headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    }
+    response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(contents.len()));
+
+    let body = if method == Method::HEAD {
+        Body::empty()
+    } else {
+        Body::from(contents)
+    };
+
+    (StatusCode::OK, response_headers, body).into_response()
+}
+
+/// Hashed Next.js assets never change; HTML must always be revalidated
+fn cache_control(relative: &Path) -> HeaderValue {
+    let relative = relative.to_string_lossy().replace('\\', "/");
+
+    if relative.starts_with(HASHED_ASSET_PREFIX) {
+        HeaderValue::from_static("public, max-age=31536000, immutable")
+    } else if relative.ends_with(".html") {
+        HeaderValue::from_static("no-cache")
+    } else {
+        HeaderValue::from_static("public, max-age=3600")
+    }
+}
+
+/// Evaluate `If-None-Match`, or `If-Modified-Since` when no ETag was sent
+fn is_not_modified(headers: &HeaderMap, meta: &FileMeta) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return if_none_match.split(',').map(str::trim).any(|tag| {
+            tag == "*" || tag.trim_start_matches("W/") == meta.etag
+        });
+    }
+
+    let Some(since) = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+    else {
+        return false;
+    };
+
+    let modified = chrono::DateTime::<chrono::Utc>::from(meta.modified).timestamp();
+    modified <= since.timestamp()
+}
+
+fn http_date(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
 }
 
 /// Get MIME type based on file extension
-fn get_mime_type(path: &PathBuf) -> HeaderValue {
+fn get_mime_type(path: &Path) -> HeaderValue {
     let extension = path
         .extension()
         .and_then(|ext| ext.to_str())
@@ -84,29 +268,91 @@ fn get_mime_type(path: &PathBuf) -> HeaderValue {
     HeaderValue::from_static(mime)
 }
 
-/// Serve gzipped content if available
-pub async fn serve_compressed(path: PathBuf) -> Response {
-    let gz_path = PathBuf::from(format!("{}.gz", path.display()));
-
-    if gz_path.exists() {
-        match fs::read(&gz_path).await {
-            Ok(contents) => {
-                let mime_type = get_mime_type(&path);
-
-                return Response::builder()
-                    .status(StatusCode::OK)
-                    .header(header::CONTENT_TYPE, mime_type)
-                    .header(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"))
-                    .header(
-                        header::CACHE_CONTROL,
-                        HeaderValue::from_static("public, max-age=31536000"),
-                    )
-                    .body(Body::from(contents))
-                    .unwrap();
-            }
-            Err(_) => {}
-        }
+/// Encodings with a pre-compressed variant the client accepts, best first
+fn accepted_encodings(headers: &HeaderMap) -> Vec<&'static str> {
+    let accepted: Vec<(String, f32)> = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|part| {
+            let mut pieces = part.split(';');
+            let name = pieces.next().unwrap_or("").trim().to_ascii_lowercase();
+            let quality = pieces
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            (name, quality)
+        })
+        .collect();
+
+    ["br", "gzip"]
+        .into_iter()
+        .filter(|encoding| {
+            accepted
+                .iter()
+                .any(|(name, quality)| (name == encoding || name == "*") && *quality > 0.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepted_encodings() {
+        let mut headers = HeaderMap::new();
+        assert!(accepted_encodings(&headers).is_empty());
+
+        headers.insert(header::ACCEPT_ENCODING, "gzip, deflate, br".parse().unwrap());
+        assert_eq!(accepted_encodings(&headers), vec!["br", "gzip"]);
+
+        headers.insert(header::ACCEPT_ENCODING, "gzip, br;q=0".parse().unwrap());
+        assert_eq!(accepted_encodings(&headers), vec!["gzip"]);
+    }
+
+    #[test]
+    fn test_cache_control() {
+        assert_eq!(
+            cache_control(Path::new("_next/static/chunks/main-abc123.js")),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(cache_control(Path::new("index.html")), "no-cache");
+        assert_eq!(cache_control(Path::new("favicon.ico")), "public, max-age=3600");
+    }
+
+    #[test]
+    fn test_conditional_requests() {
+        let meta = FileMeta {
+            etag: "\"abc\"".to_string(),
+            modified: SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+            len: 3,
+        };
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_not_modified(&headers, &meta));
+
+        headers.insert(header::IF_MODIFIED_SINCE, http_date(meta.modified).parse().unwrap());
+        assert!(is_not_modified(&headers, &meta));
+
+        // If-None-Match takes precedence over If-Modified-Since
+        headers.insert(header::IF_NONE_MATCH, "\"other\"".parse().unwrap());
+        assert!(!is_not_modified(&headers, &meta));
+
+        headers.insert(header::IF_NONE_MATCH, "\"other\", W/\"abc\"".parse().unwrap());
+        assert!(is_not_modified(&headers, &meta));
     }
 
-    serve_file(path).await
-}
\ No newline at end of file
+    #[test]
+    fn test_resolve_rejects_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "<html></html>").unwrap();
+        std::fs::write(dir.path().join("about.html"), "about").unwrap();
+
+        let files = StaticFiles::load(dir.path().to_path_buf());
+        assert_eq!(files.resolve("/about"), Some(dir.path().join("about.html")));
+        assert_eq!(files.resolve("/missing/route"), Some(dir.path().join("index.html")));
+        assert_eq!(files.resolve("/../secret"), None);
+    }
+}
@@ -0,0 +1,349 @@
+// Stream sessions - Tracks running generations and queues those over the cap
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tokio::sync::{watch, Notify, OwnedSemaphorePermit, Semaphore};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStatus {
+    /// Waiting for a free slot
+    Queued,
+    Running,
+}
+
+/// A generation known to the server, queued or running
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StreamSession {
+    pub id: String,
+    pub user_id: String,
+    pub project_id: Option<String>,
+    pub agent_id: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub status: SessionStatus,
+    /// Sessions queued ahead of this one (0 once running)
+    pub queue_position: usize,
+    pub queued_at: String,
+    pub started_at: Option<String>,
+    /// Registration order, used for queue positions
+    #[serde(skip)]
+    seq: u64,
+}
+
+/// What a new session is for
+#[derive(Debug, Clone, Default)]
+pub struct SessionInfo {
+    pub user_id: String,
+    pub project_id: Option<String>,
+    pub agent_id: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Active generations shared through `ServerState`
+///
+/// At most `max_streams` sessions run at once; the rest wait in FIFO order
+/// for a slot.
+#[derive(Debug)]
+pub struct StreamSessions {
+    max_streams: usize,
+    /// `None` when streams are unlimited
+    slots: Option<Arc<Semaphore>>,
+    sessions: Mutex<BTreeMap<String, StreamSession>>,
+    next_seq: AtomicU64,
+}
+
+/// Keeps a session listed until dropped; holds its slot once started
+#[derive(Debug)]
+pub struct SessionGuard {
+    sessions: Arc<StreamSessions>,
+    id: String,
+    slot: Option<OwnedSemaphorePermit>,
+}
+
+/// Generations running anywhere in the app, for status indicators
+#[derive(Debug, Clone, Default)]
+pub struct Activity {
+    /// Running generations, oldest first
+    pub generations: Vec<ActiveGeneration>,
+    /// Whether the last generation to finish ended in an error
+    pub failed: bool,
+    /// Whether queued sessions are held back from starting
+    pub paused: bool,
+}
+
+/// A generation counted in [`Activity`]
+#[derive(Debug, Clone)]
+pub struct ActiveGeneration {
+    pub id: String,
+    /// The agent's name, or the model when no agent was used
+    pub label: String,
+    pub started_at: Instant,
+    cancel: Arc<Notify>,
+}
+
+/// Counts a generation as running until dropped
+#[derive(Debug)]
+pub struct ActivityGuard {
+    id: String,
+    cancel: Arc<Notify>,
+    failed: bool,
+}
+
+fn activity_sender() -> &'static watch::Sender<Activity> {
+    static ACTIVITY: OnceLock<watch::Sender<Activity>> = OnceLock::new();
+    ACTIVITY.get_or_init(|| watch::channel(Activity::default()).0)
+}
+
+/// Follow [`Activity`] as generations start and finish
+pub fn subscribe_activity() -> watch::Receiver<Activity> {
+    activity_sender().subscribe()
+}
+
+pub fn current_activity() -> Activity {
+    activity_sender().borrow().clone()
+}
+
+/// Count a generation as running
+///
+/// Unlike sessions, this covers every generation, including those started
+/// over IPC or by a schedule.
+pub fn track_activity(label: String) -> ActivityGuard {
+    let generation = ActiveGeneration {
+        id: uuid::Uuid::new_v4().to_string(),
+        label,
+        started_at: Instant::now(),
+        cancel: Arc::new(Notify::new()),
+    };
+    let guard = ActivityGuard {
+        id: generation.id.clone(),
+        cancel: generation.cancel.clone(),
+        failed: false,
+    };
+    activity_sender().send_modify(|activity| activity.generations.push(generation));
+    guard
+}
+
+/// Ask a running generation to stop; false if it already finished
+pub fn cancel_generation(id: &str) -> bool {
+    let activity = activity_sender().borrow();
+    let Some(generation) = activity.generations.iter().find(|generation| generation.id == id) else {
+        return false;
+    };
+    generation.cancel.notify_one();
+    true
+}
+
+/// Hold queued sessions back from starting, or let them go again
+///
+/// Running generations are unaffected.
+pub fn set_queue_paused(paused: bool) {
+    activity_sender().send_if_modified(|activity| {
+        let changed = activity.paused != paused;
+        activity.paused = paused;
+        changed
+    });
+}
+
+impl ActivityGuard {
+    /// Report the generation as failed when it ends
+    pub fn fail(&mut self) {
+        self.failed = true;
+    }
+
+    /// Completes once [`cancel_generation`] is called for this generation
+    pub async fn cancelled(&self) {
+        self.cancel.notified().await
+    }
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        let failed = self.failed;
+        activity_sender().send_modify(|activity| {
+            activity.generations.retain(|generation| generation.id != self.id);
+            activity.failed = failed;
+        });
+    }
+}
+
+impl StreamSessions {
+    /// `max_streams` of 0 disables the limit
+    pub fn new(max_streams: usize) -> Self {
+        Self {
+            max_streams,
+            slots: (max_streams > 0).then(|| Arc::new(Semaphore::new(max_streams))),
+            sessions: Mutex::new(BTreeMap::new()),
+            next_seq: AtomicU64::new(0),
+        }
+    }
+
+    pub fn max_streams(&self) -> usize {
+        self.max_streams
+    }
+
+    /// Add a queued session; call [`SessionGuard::start`] to run it
+    pub fn register(self: &Arc<Self>, info: SessionInfo) -> SessionGuard {
+        let id = uuid::Uuid::new_v4().to_string();
+        let session = StreamSession {
+            id: id.clone(),
+            user_id: info.user_id,
+            project_id: info.project_id,
+            agent_id: info.agent_id,
+            provider: info.provider,
+            model: info.model,
+            status: SessionStatus::Queued,
+            queue_position: 0,
+            queued_at: chrono::Utc::now().to_rfc3339(),
+            started_at: None,
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+        };
+
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.insert(id.clone(), session);
+        }
+
+        SessionGuard {
+            sessions: self.clone(),
+            id,
+            slot: None,
+        }
+    }
+
+    /// Sessions in registration order, optionally only one user's or one
+    /// project's
+    pub fn list(&self, user_id: Option<&str>, project_id: Option<&str>) -> Vec<StreamSession> {
+        let Ok(sessions) = self.sessions.lock() else {
+            return Vec::new();
+        };
+
+        let mut queued: Vec<&StreamSession> = sessions
+            .values()
+            .filter(|session| session.status == SessionStatus::Queued)
+            .collect();
+        queued.sort_by_key(|session| session.seq);
+
+        let mut listed: Vec<StreamSession> = sessions
+            .values()
+            .filter(|session| user_id.is_none_or(|user_id| session.user_id == user_id))
+            .filter(|session| project_id.is_none_or(|project_id| session.project_id.as_deref() == Some(project_id)))
+            .cloned()
+            .map(|mut session| {
+                session.queue_position = queued.iter().take_while(|queued| queued.seq < session.seq).count();
+                if session.status == SessionStatus::Running {
+                    session.queue_position = 0;
+                }
+                session
+            })
+            .collect();
+        listed.sort_by_key(|session| session.seq);
+        listed
+    }
+
+    pub fn get(&self, id: &str) -> Option<StreamSession> {
+        self.list(None, None).into_iter().find(|session| session.id == id)
+    }
+
+    fn update(&self, id: &str, apply: impl FnOnce(&mut StreamSession)) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            if let Some(session) = sessions.get_mut(id) {
+                apply(session);
+            }
+        }
+    }
+}
+
+impl SessionGuard {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether a slot is free right now and the queue isn't paused
+    pub fn can_start(&self) -> bool {
+        !activity_sender().borrow().paused
+            && self
+                .sessions
+                .slots
+                .as_ref()
+                .is_none_or(|slots| slots.available_permits() > 0)
+    }
+
+    /// Wait for the queue to be resumed and for a slot, then mark the
+    /// session running
+    pub async fn start(&mut self) {
+        // The sender is never dropped
+        let _ = subscribe_activity().wait_for(|activity| !activity.paused).await;
+        if let Some(slots) = &self.sessions.slots {
+            // The semaphore is never closed
+            self.slot = slots.clone().acquire_owned().await.ok();
+        }
+
+        self.sessions.update(&self.id, |session| {
+            session.status = SessionStatus::Running;
+            session.started_at = Some(chrono::Utc::now().to_rfc3339());
+        });
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        if let Ok(mut sessions) = self.sessions.sessions.lock() {
+            sessions.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(user_id: &str) -> SessionInfo {
+        SessionInfo {
+            user_id: user_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_excess_sessions_queue() {
+        let sessions = Arc::new(StreamSessions::new(1));
+
+        let mut first = sessions.register(info("alice"));
+        first.start().await;
+        let mut second = sessions.register(info("bob"));
+        assert!(!second.can_start());
+
+        let listed = sessions.list(None, None);
+        assert_eq!(listed[0].status, SessionStatus::Running);
+        assert_eq!(listed[1].status, SessionStatus::Queued);
+        assert_eq!(listed[1].queue_position, 0);
+        assert_eq!(sessions.list(Some("bob"), None).len(), 1);
+
+        // Ending the first session hands its slot to the queued one
+        drop(first);
+        tokio::time::timeout(std::time::Duration::from_secs(1), second.start())
+            .await
+            .unwrap();
+        assert_eq!(sessions.get(second.id()).unwrap().status, SessionStatus::Running);
+
+        drop(second);
+        assert!(sessions.list(None, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_sessions_never_queue() {
+        let sessions = Arc::new(StreamSessions::new(0));
+        let mut guards = Vec::new();
+        for _ in 0..10 {
+            let mut guard = sessions.register(info("alice"));
+            assert!(guard.can_start());
+            guard.start().await;
+            guards.push(guard);
+        }
+        assert_eq!(sessions.list(Some("alice"), None).len(), 10);
+    }
+}
@@ -0,0 +1,274 @@
+// Transport module - Serve the API over a Unix socket or named pipe
+//
+// Local transport keeps the server off TCP entirely, so other local
+// processes can't reach it through a port. The webview talks to it through
+// the `vibing2-local` URI scheme, which `proxy_request` forwards over the
+// socket.
+use axum::{
+    body::Bytes,
+    extract::ConnectInfo,
+    http::{header, HeaderValue, Request, Response, StatusCode, Uri},
+    Extension, Router,
+};
+use http_body_util::{BodyExt, Full};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::task::{JoinHandle, JoinSet};
+use tower::ServiceExt;
+use super::shutdown::ShutdownSignal;
+
+/// URI scheme the webview uses to reach a local-transport server
+pub const PROXY_SCHEME: &str = "vibing2-local";
+
+/// Origin the webview sees for the proxy scheme
+pub fn proxy_url() -> String {
+    if cfg!(windows) {
+        format!("http://{}.localhost", PROXY_SCHEME)
+    } else {
+        format!("{}://localhost", PROXY_SCHEME)
+    }
+}
+
+/// Socket path (Unix) or pipe name (Windows) for the local transport
+pub fn local_endpoint() -> String {
+    #[cfg(unix)]
+    {
+        crate::database::get_db_path()
+            .with_file_name("server.sock")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[cfg(windows)]
+    {
+        let user = std::env::var("USERNAME").unwrap_or_else(|_| "user".to_string());
+        format!(r"\\.\pipe\vibing2-{}", user)
+    }
+}
+
+/// Bind the local endpoint and serve `app` until shutdown
+///
+/// Binding happens before returning so errors reach the caller. On Unix the
+/// socket is only accessible to the current user.
+pub async fn serve_local(app: Router, shutdown: ShutdownSignal) -> Result<JoinHandle<()>, std::io::Error> {
+    // Requests over the socket count as loopback for rate limiting and LAN checks
+    let app = app.layer(Extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0)))));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = local_endpoint();
+        // Remove a stale socket left by a previous run
+        if std::path::Path::new(&path).exists() {
+            std::fs::remove_file(&path)?;
+        }
+
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+        Ok(tokio::spawn(async move {
+            let mut connections = JoinSet::new();
+            let stopping = shutdown.clone().triggered();
+            tokio::pin!(stopping);
+
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            connections.spawn(serve_connection(stream, app.clone(), shutdown.clone()));
+                        }
+                        Err(e) => eprintln!("Socket accept error: {}", e),
+                    },
+                    _ = &mut stopping => break,
+                }
+            }
+
+            drop(listener);
+            while connections.join_next().await.is_some() {}
+            let _ = std::fs::remove_file(&path);
+        }))
+    }
+
+    #[cfg(windows)]
+    {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let name = local_endpoint();
+        let mut pipe = ServerOptions::new()
+            .first_pipe_instance(true)
+            .reject_remote_clients(true)
+            .create(&name)?;
+
+        Ok(tokio::spawn(async move {
+            let mut connections = JoinSet::new();
+            let stopping = shutdown.clone().triggered();
+            tokio::pin!(stopping);
+
+            loop {
+                tokio::select! {
+                    connected = pipe.connect() => {
+                        if let Err(e) = connected {
+                            eprintln!("Pipe connect error: {}", e);
+                            continue;
+                        }
+
+                        // Create the next instance before handing this one off
+                        let next = match ServerOptions::new().reject_remote_clients(true).create(&name) {
+                            Ok(next) => next,
+                            Err(e) => {
+                                eprintln!("Failed to create pipe instance: {}", e);
+                                break;
+                            }
+                        };
+                        let client = std::mem::replace(&mut pipe, next);
+                        connections.spawn(serve_connection(client, app.clone(), shutdown.clone()));
+                    }
+                    _ = &mut stopping => break,
+                }
+            }
+
+            while connections.join_next().await.is_some() {}
+        }))
+    }
+}
+
+/// Serve one connection, finishing in-flight requests on shutdown
+async fn serve_connection<IO>(io: IO, app: Router, shutdown: ShutdownSignal)
+where
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = hyper::service::service_fn(move |request: Request<hyper::body::Incoming>| {
+        app.clone().oneshot(request)
+    });
+
+    let builder = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
+    let connection = builder.serve_connection_with_upgrades(TokioIo::new(io), service);
+    tokio::pin!(connection);
+
+    let stopping = shutdown.triggered();
+    tokio::pin!(stopping);
+    let mut draining = false;
+
+    loop {
+        tokio::select! {
+            result = connection.as_mut() => {
+                if let Err(e) = result {
+                    eprintln!("Connection error: {}", e);
+                }
+                break;
+            }
+            _ = &mut stopping, if !draining => {
+                draining = true;
+                connection.as_mut().graceful_shutdown();
+            }
+        }
+    }
+}
+
+/// Forward a `vibing2-local://` request from the webview to the server
+///
+/// Responses are buffered, so SSE streams arrive all at once; the WebSocket
+/// endpoint isn't reachable through the proxy.
+pub async fn proxy_request(request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+    match forward(request).await {
+        Ok(response) => response,
+        Err(e) => {
+            let body = serde_json::json!({
+                "error": format!("Local server unavailable: {}", e),
+                "status": StatusCode::BAD_GATEWAY.as_u16(),
+            });
+
+            Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(serde_json::to_vec(&body).unwrap_or_default())
+                .unwrap_or_default()
+        }
+    }
+}
+
+async fn forward(request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
+    let io = connect_local().await?;
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(io)).await?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+
+    // The server expects origin-form targets
+    let (mut parts, body) = request.into_parts();
+    let path_and_query = parts
+        .uri
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_else(|| "/".to_string());
+    parts.uri = path_and_query.parse::<Uri>()?;
+    parts.headers.insert(header::HOST, HeaderValue::from_static("localhost"));
+
+    let response = sender
+        .send_request(Request::from_parts(parts, Full::new(Bytes::from(body))))
+        .await?;
+
+    let (parts, body) = response.into_parts();
+    let body = body.collect().await?.to_bytes();
+
+    Ok(Response::from_parts(parts, body.to_vec()))
+}
+
+#[cfg(unix)]
+async fn connect_local() -> std::io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(local_endpoint()).await
+}
+
+#[cfg(windows)]
+async fn connect_local() -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+
+    // ERROR_PIPE_BUSY: every instance is in use, wait briefly and retry
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    for _ in 0..20 {
+        match ClientOptions::new().open(local_endpoint()) {
+            Ok(client) => return Ok(client),
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    ClientOptions::new().open(local_endpoint())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_proxy_round_trip_over_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var("TEST_DATABASE_PATH", dir.path().join("vibing2.db"));
+
+        let app = Router::new().route("/ping", axum::routing::get(|| async { "pong" }));
+        let (shutdown, signal) = super::super::shutdown::Shutdown::new();
+        let task = serve_local(app, signal).await.unwrap();
+
+        let request = Request::builder()
+            .uri(format!("{}/ping", proxy_url()))
+            .body(Vec::new())
+            .unwrap();
+        let response = proxy_request(request).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), b"pong");
+
+        shutdown.trigger();
+        task.await.unwrap();
+        assert!(!std::path::Path::new(&local_endpoint()).exists());
+
+        std::env::remove_var("TEST_DATABASE_PATH");
+    }
+}
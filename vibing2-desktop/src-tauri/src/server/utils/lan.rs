@@ -0,0 +1,75 @@
+// LAN utilities - Addresses the server can be reached at from other machines
+use serde::Serialize;
+use std::net::IpAddr;
+
+/// A URL other devices on the network can use to reach the server
+#[derive(Debug, Clone, Serialize)]
+pub struct LanUrl {
+    pub interface: String,
+    pub address: String,
+    pub url: String,
+}
+
+/// List non-loopback URLs for a server bound to `host`:`port`, with
+/// `base_path` appended
+///
+/// An unspecified bind address (`0.0.0.0` / `::`) lists every interface of
+/// that address family; a specific address lists only itself.
+pub fn lan_urls(host: IpAddr, port: u16, base_path: &str) -> Result<Vec<LanUrl>, std::io::Error> {
+    let interfaces = if_addrs::get_if_addrs()?;
+
+    let urls = interfaces
+        .into_iter()
+        .filter(|iface| !iface.is_loopback())
+        .filter(|iface| {
+            let ip = iface.ip();
+            if host.is_unspecified() {
+                ip.is_ipv4() == host.is_ipv4()
+            } else {
+                ip == host
+            }
+        })
+        // Skip IPv6 link-local addresses, which need a zone to be usable
+        .filter(|iface| !matches!(iface.ip(), IpAddr::V6(ip) if (ip.segments()[0] & 0xffc0) == 0xfe80))
+        .map(|iface| {
+            let ip = iface.ip();
+            let url = match ip {
+                IpAddr::V4(_) => format!("http://{}:{}{}", ip, port, base_path),
+                IpAddr::V6(_) => format!("http://[{}]:{}{}", ip, port, base_path),
+            };
+            LanUrl {
+                interface: iface.name,
+                address: ip.to_string(),
+                url,
+            }
+        })
+        .collect();
+
+    Ok(urls)
+}
+
+/// Parse a bind address setting, rejecting anything that isn't an IP
+pub fn parse_bind_address(value: &str) -> Result<IpAddr, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid bind address: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bind_address() {
+        assert_eq!(parse_bind_address("0.0.0.0").unwrap().to_string(), "0.0.0.0");
+        assert!(parse_bind_address(" 192.168.1.20 ").is_ok());
+        assert!(parse_bind_address("eth0").is_err());
+    }
+
+    #[test]
+    fn test_lan_urls_exclude_loopback() {
+        let urls = lan_urls("0.0.0.0".parse().unwrap(), 3456, "").unwrap();
+        assert!(urls.iter().all(|url| !url.address.starts_with("127.")));
+    }
+}
@@ -0,0 +1,144 @@
+// Login throttling - Exponential lockout for repeated auth failures
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Failures allowed before lockouts start
+const FREE_ATTEMPTS: u32 = 5;
+
+/// First lockout duration, doubled for each further failure
+const BASE_LOCKOUT: Duration = Duration::from_secs(1);
+
+/// Upper bound on a single lockout
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Entry count that triggers pruning of stale entries
+const MAX_ENTRIES: usize = 1024;
+
+/// Failures older than this are forgotten
+const FAILURE_WINDOW: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone)]
+struct Attempts {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// In-memory failure tracker keyed by IP address or account
+///
+/// Keys are free-form; callers use prefixes like `ip:` and `account:` so
+/// both dimensions can be tracked in one store.
+#[derive(Debug, Default)]
+pub struct LoginThrottle {
+    entries: Mutex<HashMap<String, Attempts>>,
+}
+
+impl LoginThrottle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check whether a key is currently locked out
+    ///
+    /// Returns the remaining lockout as the error value.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    /// Record a failed attempt for a key
+    pub fn record_failure(&self, key: &str) {
+        self.record_failure_at(key, Instant::now());
+    }
+
+    /// Clear the failure history for a key after a successful attempt
+    pub fn record_success(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => return Ok(()),
+        };
+
+        match entries.get(key).and_then(|attempts| attempts.locked_until) {
+            Some(until) if until > now => Err(until - now),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_failure_at(&self, key: &str, now: Instant) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        // Keep the map bounded when many distinct IPs/accounts fail
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, attempts| !is_stale(attempts, now));
+        }
+
+        let attempts = entries.entry(key.to_string()).or_insert(Attempts {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+
+        if is_stale(attempts, now) {
+            attempts.failures = 0;
+            attempts.locked_until = None;
+        }
+
+        attempts.failures += 1;
+        attempts.last_failure = now;
+
+        if attempts.failures >= FREE_ATTEMPTS {
+            attempts.locked_until = Some(now + lockout_duration(attempts.failures));
+        }
+    }
+}
+
+/// Lockout duration after `failures` consecutive failures
+fn lockout_duration(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(FREE_ATTEMPTS).min(16);
+    BASE_LOCKOUT
+        .saturating_mul(1u32 << exponent)
+        .min(MAX_LOCKOUT)
+}
+
+fn is_stale(attempts: &Attempts, now: Instant) -> bool {
+    let locked = attempts.locked_until.map_or(false, |until| until > now);
+    !locked && now.duration_since(attempts.last_failure) > FAILURE_WINDOW
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_after_free_attempts() {
+        let throttle = LoginThrottle::new();
+        let now = Instant::now();
+
+        for _ in 0..FREE_ATTEMPTS - 1 {
+            throttle.record_failure_at("ip:1.2.3.4", now);
+        }
+        assert!(throttle.check_at("ip:1.2.3.4", now).is_ok());
+
+        throttle.record_failure_at("ip:1.2.3.4", now);
+        assert!(throttle.check_at("ip:1.2.3.4", now).is_err());
+        assert!(throttle.check_at("ip:5.6.7.8", now).is_ok());
+
+        throttle.record_success("ip:1.2.3.4");
+        assert!(throttle.check_at("ip:1.2.3.4", now).is_ok());
+    }
+
+    #[test]
+    fn test_lockout_duration_grows_and_caps() {
+        assert_eq!(lockout_duration(FREE_ATTEMPTS), BASE_LOCKOUT);
+        assert_eq!(lockout_duration(FREE_ATTEMPTS + 3), BASE_LOCKOUT * 8);
+        assert_eq!(lockout_duration(FREE_ATTEMPTS + 40), MAX_LOCKOUT);
+    }
+}
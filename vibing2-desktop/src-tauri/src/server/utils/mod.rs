@@ -1,6 +1,11 @@
 // Server utilities module
 pub mod port;
 pub mod path;
+pub mod lan;
+pub mod lockout;
+pub mod rate_limit;
 
 pub use port::find_available_port;
-pub use path::resolve_static_path;
\ No newline at end of file
+pub use path::resolve_static_path;
+pub use lockout::LoginThrottle;
+pub use rate_limit::RateLimiter;
\ No newline at end of file
@@ -1,5 +1,5 @@
 // Port utilities - Find available ports for the server
-use std::net::{TcpListener, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, TcpListener, SocketAddr};
 
 /// Find an available port in the given range
 pub fn find_available_port() -> Result<u16, std::io::Error> {
@@ -21,8 +21,25 @@ pub fn find_available_port_in_range(start: u16, end: u16) -> Result<u16, std::io
 
 /// Check if a specific port is available
 pub fn is_port_available(port: u16) -> bool {
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    TcpListener::bind(addr).is_ok()
+    is_port_available_on(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+}
+
+/// Check if a port is available on a specific bind address
+pub fn is_port_available_on(ip: IpAddr, port: u16) -> bool {
+    TcpListener::bind(SocketAddr::new(ip, port)).is_ok()
+}
+
+/// Use `preferred` if it's free on `ip`, otherwise any available port
+pub fn choose_port(ip: IpAddr, preferred: u16) -> Result<u16, std::io::Error> {
+    if preferred != 0 && is_port_available_on(ip, preferred) {
+        return Ok(preferred);
+    }
+
+    let port = find_available_port()?;
+    if preferred != 0 {
+        println!("⚠️  Preferred port {} is in use, using {}", preferred, port);
+    }
+    Ok(port)
 }
 
 #[cfg(test)]
@@ -46,4 +63,15 @@ mod tests {
         // Port 80 might be available or not depending on the system
         let _ = is_port_available(80);
     }
+
+    #[test]
+    fn test_choose_port_prefers_free_port() {
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert_eq!(choose_port(localhost, 59998).unwrap(), 59998);
+
+        // Occupied preferred ports fall back to the scan range
+        let _listener = TcpListener::bind(SocketAddr::new(localhost, 59997)).unwrap();
+        let port = choose_port(localhost, 59997).unwrap();
+        assert!(port >= 3000 && port <= 9000);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+// Rate limiting - Token buckets keyed by IP address or bearer token
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Bucket count that triggers pruning of idle buckets
+const MAX_BUCKETS: usize = 4096;
+
+/// Buckets untouched for this long are dropped when pruning
+const IDLE_BUCKET_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Outcome of taking a token from a bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    /// Bucket capacity
+    pub limit: u32,
+    /// Whole tokens left after this request
+    pub remaining: u32,
+    /// Time until the next token is available
+    pub retry_after: Duration,
+    /// Time until the bucket is full again
+    pub reset_after: Duration,
+}
+
+/// In-memory token bucket limiter
+///
+/// Keys are free-form; the middleware uses `ip:` and `token:` prefixes so
+/// both dimensions share one store.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one token from `key`'s bucket
+    ///
+    /// Buckets hold up to `burst` tokens and refill at `per_minute` tokens per
+    /// minute.
+    pub fn check(&self, key: &str, per_minute: u32, burst: u32) -> RateLimitDecision {
+        self.check_at(key, per_minute, burst, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, per_minute: u32, burst: u32, now: Instant) -> RateLimitDecision {
+        let capacity = burst.max(1) as f64;
+        let refill_per_sec = per_minute.max(1) as f64 / 60.0;
+
+        let Ok(mut buckets) = self.buckets.lock() else {
+            // Fail open rather than rejecting every request
+            return RateLimitDecision {
+                allowed: true,
+                limit: burst,
+                remaining: burst,
+                retry_after: Duration::ZERO,
+                reset_after: Duration::ZERO,
+            };
+        };
+
+        if buckets.len() >= MAX_BUCKETS {
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated) < IDLE_BUCKET_TTL);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        let retry_after = if allowed {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec)
+        };
+
+        RateLimitDecision {
+            allowed,
+            limit: burst.max(1),
+            remaining: bucket.tokens.floor() as u32,
+            retry_after,
+            reset_after: Duration::from_secs_f64((capacity - bucket.tokens) / refill_per_sec),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_exhausts_and_refills() {
+        let limiter = RateLimiter::new();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("ip:1.2.3.4", 60, 3, now).allowed);
+        }
+
+        let denied = limiter.check_at("ip:1.2.3.4", 60, 3, now);
+        assert!(!denied.allowed);
+        assert_eq!(denied.remaining, 0);
+        assert_eq!(denied.retry_after, Duration::from_secs(1));
+
+        // Other keys are independent
+        assert!(limiter.check_at("ip:5.6.7.8", 60, 3, now).allowed);
+
+        // One token per second at 60/minute
+        assert!(limiter.check_at("ip:1.2.3.4", 60, 3, now + Duration::from_secs(1)).allowed);
+    }
+}
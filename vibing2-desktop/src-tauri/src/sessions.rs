@@ -0,0 +1,297 @@
+//! Server account sessions
+//!
+//! Sessions are bearer tokens issued by the embedded server on sign in/up.
+//! This module owns the `sessions` table: creating sessions with device info,
+//! listing and revoking them, and pruning expired rows in the background.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::time::Duration;
+
+/// How long a newly issued session stays valid
+pub const SESSION_TTL_DAYS: i64 = 7;
+
+/// How often expired sessions are pruned
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Session information returned to clients (never includes the token)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub user_id: String,
+    pub user_email: Option<String>,
+    pub device_name: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: String,
+    pub last_seen_at: String,
+    pub expires_at: String,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+/// Device details captured when a session is created
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Create a new session for a user and return its bearer token
+pub async fn create_session(
+    pool: &SqlitePool,
+    user_id: &str,
+    device: &DeviceInfo,
+) -> Result<String, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let token = format!("token_{}", uuid::Uuid::new_v4());
+    let device_name = describe_device(device.user_agent.as_deref());
+
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (id, user_id, token, device_name, user_agent, ip_address, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?, datetime('now', ?))
+        "#,
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(&token)
+    .bind(&device_name)
+    .bind(&device.user_agent)
+    .bind(&device.ip_address)
+    .bind(format!("+{} days", SESSION_TTL_DAYS))
+    .execute(pool)
+    .await?;
+
+    Ok(token)
+}
+
+/// List active (unexpired) sessions, optionally restricted to one user
+///
+/// `current_token` marks the matching session as `current`.
+pub async fn list_sessions(
+    pool: &SqlitePool,
+    user_id: Option<&str>,
+    current_token: Option<&str>,
+) -> Result<Vec<SessionInfo>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.id, s.user_id, u.email AS user_email, s.token, s.device_name,
+               s.user_agent, s.ip_address, s.created_at, s.last_seen_at, s.expires_at
+        FROM sessions s
+        LEFT JOIN users u ON s.user_id = u.id
+        WHERE s.expires_at > datetime('now')
+          AND (?1 IS NULL OR s.user_id = ?1)
+        ORDER BY s.last_seen_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let sessions = rows
+        .iter()
+        .map(|row| {
+            let token: String = row.get("token");
+            SessionInfo {
+                id: row.get("id"),
+                user_id: row.get("user_id"),
+                user_email: row.get("user_email"),
+                device_name: row.get("device_name"),
+                user_agent: row.get("user_agent"),
+                ip_address: row.get("ip_address"),
+                created_at: row.get("created_at"),
+                last_seen_at: row.get("last_seen_at"),
+                expires_at: row.get("expires_at"),
+                current: current_token == Some(token.as_str()),
+            }
+        })
+        .collect();
+
+    Ok(sessions)
+}
+
+/// Look up the user ID for a valid session token and bump its `last_seen_at`
+pub async fn touch_session(pool: &SqlitePool, token: &str) -> Result<Option<String>, sqlx::Error> {
+    let user_id: Option<String> = sqlx::query_scalar(
+        "SELECT user_id FROM sessions WHERE token = ? AND expires_at > datetime('now')",
+    )
+    .bind(token)
+    .fetch_optional(pool)
+    .await?;
+
+    if user_id.is_some() {
+        sqlx::query("UPDATE sessions SET last_seen_at = datetime('now') WHERE token = ?")
+            .bind(token)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(user_id)
+}
+
+/// Revoke a single session by ID, optionally restricted to one user
+///
+/// Returns `false` if no matching session existed.
+pub async fn revoke_session(
+    pool: &SqlitePool,
+    session_id: &str,
+    user_id: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM sessions WHERE id = ?1 AND (?2 IS NULL OR user_id = ?2)")
+        .bind(session_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revoke a session by its bearer token (sign out)
+pub async fn revoke_token(pool: &SqlitePool, token: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM sessions WHERE token = ?")
+        .bind(token)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Revoke all sessions, optionally restricted to one user and keeping one token
+///
+/// Returns the number of sessions revoked.
+pub async fn revoke_all_sessions(
+    pool: &SqlitePool,
+    user_id: Option<&str>,
+    keep_token: Option<&str>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM sessions WHERE (?1 IS NULL OR user_id = ?1) AND (?2 IS NULL OR token != ?2)",
+    )
+    .bind(user_id)
+    .bind(keep_token)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Delete expired sessions, returning the number of rows removed
+pub async fn prune_expired_sessions(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM sessions WHERE expires_at <= datetime('now')")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Spawn a background task that prunes expired sessions every hour
+pub fn spawn_prune_task() {
+    tauri::async_runtime::spawn(async {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let pool = match crate::database::get_pool().await {
+                Ok(pool) => pool,
+                Err(e) => {
+                    eprintln!("Session pruning skipped: {}", e);
+                    continue;
+                }
+            };
+
+            match prune_expired_sessions(pool.as_ref()).await {
+                Ok(0) => {}
+                Ok(count) => println!("🧹 Pruned {} expired sessions", count),
+                Err(e) => eprintln!("Failed to prune sessions: {}", e),
+            }
+        }
+    });
+}
+
+/// Build a short human-readable device description from a user agent
+pub fn describe_device(user_agent: Option<&str>) -> String {
+    let Some(ua) = user_agent else {
+        return "Unknown device".to_string();
+    };
+
+    let browser = if ua.contains("Edg/") {
+        "Edge"
+    } else if ua.contains("Firefox/") {
+        "Firefox"
+    } else if ua.contains("Chrome/") {
+        "Chrome"
+    } else if ua.contains("Safari/") {
+        "Safari"
+    } else if ua.contains("curl/") {
+        "curl"
+    } else {
+        "Browser"
+    };
+
+    let os = if ua.contains("iPhone") || ua.contains("iPad") {
+        "iOS"
+    } else if ua.contains("Android") {
+        "Android"
+    } else if ua.contains("Mac OS X") || ua.contains("Macintosh") {
+        "macOS"
+    } else if ua.contains("Windows") {
+        "Windows"
+    } else if ua.contains("Linux") {
+        "Linux"
+    } else {
+        return browser.to_string();
+    };
+
+    format!("{} on {}", browser, os)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_describe_device() {
+        let mac_chrome = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0 Safari/537.36";
+        assert_eq!(describe_device(Some(mac_chrome)), "Chrome on macOS");
+
+        let iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 Version/17.0 Mobile/15E148 Safari/604.1";
+        assert_eq!(describe_device(Some(iphone)), "Safari on iOS");
+
+        assert_eq!(describe_device(Some("curl/8.4.0")), "curl");
+        assert_eq!(describe_device(None), "Unknown device");
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let device = DeviceInfo {
+            user_agent: Some("curl/8.4.0".to_string()),
+            ip_address: Some("127.0.0.1".to_string()),
+        };
+        let first = create_session(&pool, "local-user", &device).await.unwrap();
+        let second = create_session(&pool, "local-user", &device).await.unwrap();
+
+        let sessions = list_sessions(&pool, Some("local-user"), Some(&first)).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+
+        assert_eq!(touch_session(&pool, &second).await.unwrap(), Some("local-user".to_string()));
+
+        let revoked = revoke_all_sessions(&pool, Some("local-user"), Some(&first)).await.unwrap();
+        assert_eq!(revoked, 1);
+        assert_eq!(touch_session(&pool, &second).await.unwrap(), None);
+
+        sqlx::query("UPDATE sessions SET expires_at = datetime('now', '-1 day')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(prune_expired_sessions(&pool).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,276 @@
+//! Per-project sharing ACLs
+//!
+//! A project's owner (its `user_id`) always has write access, and
+//! installation owners can manage every project. Other local users only see a
+//! project once it has been shared with them, at read or write level. Paired
+//! devices act on behalf of the desktop user but are limited to the projects
+//! explicitly shared with the device.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use utoipa::ToSchema;
+
+/// Access level granted on a project, ordered from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl Access {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Write => "write",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Access, String> {
+        match value {
+            "read" => Ok(Access::Read),
+            "write" => Ok(Access::Write),
+            other => Err(format!("Unknown access level: {}", other)),
+        }
+    }
+}
+
+/// Who a project is shared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Grantee {
+    User,
+    Device,
+}
+
+impl Grantee {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Grantee::User => "user",
+            Grantee::Device => "device",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Grantee, String> {
+        match value {
+            "user" => Ok(Grantee::User),
+            "device" => Ok(Grantee::Device),
+            other => Err(format!("Unknown grantee type: {}", other)),
+        }
+    }
+}
+
+/// A single share entry
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProjectShare {
+    pub id: String,
+    pub project_id: String,
+    pub grantee_type: Grantee,
+    pub grantee_id: String,
+    pub access: Access,
+    pub created_at: String,
+}
+
+/// Identity a project access check is made for
+#[derive(Debug, Clone, Copy)]
+pub struct Principal<'a> {
+    pub user_id: &'a str,
+    /// Set when acting through a paired-device token
+    pub device_id: Option<&'a str>,
+    /// Installation owners can access every project (never set for devices)
+    pub is_owner: bool,
+}
+
+impl Principal<'_> {
+    /// The local owner on this machine, rather than another account or a
+    /// paired device
+    pub fn is_local_owner(&self) -> bool {
+        self.user_id == crate::passwords::LOCAL_USER_ID && self.device_id.is_none()
+    }
+}
+
+impl Principal<'static> {
+    /// The desktop app itself, acting as the local owner
+    pub fn desktop() -> Self {
+        Principal {
+            user_id: "local-user",
+            device_id: None,
+            is_owner: true,
+        }
+    }
+}
+
+/// Grant (or change) access on a project, returning the share
+pub async fn share_project(
+    pool: &SqlitePool,
+    project_id: &str,
+    grantee_type: Grantee,
+    grantee_id: &str,
+    access: Access,
+) -> Result<ProjectShare, String> {
+    let grantee_exists: i64 = match grantee_type {
+        Grantee::User => sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id = ?"),
+        Grantee::Device => sqlx::query_scalar("SELECT COUNT(*) FROM devices WHERE id = ?"),
+    }
+    .bind(grantee_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if grantee_exists == 0 {
+        return Err(format!("Unknown {}: {}", grantee_type.as_str(), grantee_id));
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO project_shares (id, project_id, grantee_type, grantee_id, access)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(project_id, grantee_type, grantee_id) DO UPDATE SET access = excluded.access
+        "#,
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(project_id)
+    .bind(grantee_type.as_str())
+    .bind(grantee_id)
+    .bind(access.as_str())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to share project: {}", e))?;
+
+    let row = sqlx::query(
+        "SELECT id, project_id, grantee_type, grantee_id, access, created_at FROM project_shares WHERE project_id = ? AND grantee_type = ? AND grantee_id = ?",
+    )
+    .bind(project_id)
+    .bind(grantee_type.as_str())
+    .bind(grantee_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(share_from_row(&row))
+}
+
+/// Remove a share from a project
+pub async fn unshare_project(pool: &SqlitePool, project_id: &str, share_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM project_shares WHERE id = ? AND project_id = ?")
+        .bind(share_id)
+        .bind(project_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// List everyone a project is shared with
+pub async fn list_shares(pool: &SqlitePool, project_id: &str) -> Result<Vec<ProjectShare>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT id, project_id, grantee_type, grantee_id, access, created_at FROM project_shares WHERE project_id = ? ORDER BY created_at",
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(share_from_row).collect())
+}
+
+/// Resolve the access a principal has on a project
+///
+/// Returns `None` when the project does not exist or is not visible to them.
+pub async fn project_access(
+    pool: &SqlitePool,
+    project_id: &str,
+    principal: Principal<'_>,
+) -> Result<Option<Access>, sqlx::Error> {
+    let owner: Option<String> = sqlx::query_scalar("SELECT user_id FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await?;
+
+    let Some(owner) = owner else {
+        return Ok(None);
+    };
+
+    if principal.device_id.is_none() && (principal.is_owner || owner == principal.user_id) {
+        return Ok(Some(Access::Write));
+    }
+
+    let levels: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT access FROM project_shares
+        WHERE project_id = ? AND grantee_type = ? AND grantee_id = ?
+        "#,
+    )
+    .bind(project_id)
+    .bind(if principal.device_id.is_some() { "device" } else { "user" })
+    .bind(principal.device_id.unwrap_or(principal.user_id))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(levels.iter().filter_map(|level| Access::parse(level).ok()).max())
+}
+
+/// Require at least `needed` access on a project
+pub async fn require_access(
+    pool: &SqlitePool,
+    project_id: &str,
+    principal: Principal<'_>,
+    needed: Access,
+) -> Result<(), String> {
+    match project_access(pool, project_id, principal)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+    {
+        Some(access) if access >= needed => Ok(()),
+        Some(_) => Err(format!("You need {} access to this project", needed.as_str())),
+        None => Err(format!("Project not found: {}", project_id)),
+    }
+}
+
+fn share_from_row(row: &sqlx::sqlite::SqliteRow) -> ProjectShare {
+    ProjectShare {
+        id: row.get("id"),
+        project_id: row.get("project_id"),
+        grantee_type: Grantee::parse(row.get("grantee_type")).unwrap_or(Grantee::User),
+        grantee_id: row.get("grantee_id"),
+        access: Access::parse(row.get("access")).unwrap_or(Access::Read),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_share_grants_access() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        sqlx::query("INSERT INTO users (id, name, email, password) VALUES ('guest', 'Guest', 'guest@example.com', 'x')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Demo', 'web', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let guest = Principal { user_id: "guest", device_id: None, is_owner: false };
+        assert_eq!(project_access(&pool, "p1", guest).await.unwrap(), None);
+
+        share_project(&pool, "p1", Grantee::User, "guest", Access::Read).await.unwrap();
+        assert_eq!(project_access(&pool, "p1", guest).await.unwrap(), Some(Access::Read));
+        assert!(require_access(&pool, "p1", guest, Access::Write).await.is_err());
+
+        // Re-sharing updates the level instead of duplicating
+        let share = share_project(&pool, "p1", Grantee::User, "guest", Access::Write).await.unwrap();
+        assert_eq!(list_shares(&pool, "p1").await.unwrap().len(), 1);
+        assert!(require_access(&pool, "p1", guest, Access::Write).await.is_ok());
+
+        assert!(unshare_project(&pool, "p1", &share.id).await.unwrap());
+        assert_eq!(project_access(&pool, "p1", guest).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,371 @@
+//! Project snapshots and diffs
+//!
+//! A snapshot records a project's files (with `current_code` as
+//! `index.html`, as in [`crate::scaffold::project_files`]) at a point in
+//! time:
+//!
+//! - before each agent run that can change the project
+//! - on each save whose files differ from the latest snapshot, which covers
+//!   revisions of `current_code`
+//!
+//! Diffs compare two versions file by file. A version is a snapshot ID,
+//! `current` for the project as it is now, or `last_run` for the snapshot
+//! taken before the latest agent run, so `last_run` → `current` is what that
+//! run changed. Only the newest `MAX_SNAPSHOTS` per project are kept.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::BTreeMap;
+
+/// Snapshots kept per project
+const MAX_SNAPSHOTS: i64 = 50;
+
+/// Lines of unchanged context around each change
+const CONTEXT_LINES: usize = 3;
+
+/// The project as it is now
+pub const CURRENT: &str = "current";
+
+/// The snapshot taken before the latest agent run
+pub const LAST_RUN: &str = "last_run";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotKind {
+    /// Taken before an agent run
+    BeforeRun,
+    /// Taken when the project was saved
+    Save,
+}
+
+impl SnapshotKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            SnapshotKind::BeforeRun => "before_run",
+            SnapshotKind::Save => "save",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "before_run" => SnapshotKind::BeforeRun,
+            _ => SnapshotKind::Save,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub kind: SnapshotKind,
+    pub file_count: usize,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileDiffStatus {
+    Added,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub status: FileDiffStatus,
+    /// Unified diff of the file
+    pub diff: String,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectDiff {
+    /// Snapshot ID or `current` the diff starts from; `None` if there was no
+    /// snapshot, in which case every file counts as added
+    pub from: Option<String>,
+    pub to: String,
+    /// Changed files, by path
+    pub files: Vec<FileDiff>,
+}
+
+fn files_hash(files: &BTreeMap<String, String>) -> String {
+    let json = serde_json::to_string(files).unwrap_or_default();
+    crate::scaffold::hash(json.as_bytes())
+}
+
+/// Record the project's files; `None` for a save that changed nothing since
+/// the latest snapshot
+pub async fn record(pool: &SqlitePool, project_id: &str, kind: SnapshotKind) -> Result<Option<Snapshot>, String> {
+    let files = crate::scaffold::project_files(pool, project_id).await?;
+    let hash = files_hash(&files);
+
+    if kind == SnapshotKind::Save {
+        let latest: Option<String> = sqlx::query_scalar(
+            "SELECT hash FROM project_snapshots WHERE project_id = ? ORDER BY created_at DESC, rowid DESC LIMIT 1",
+        )
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+        if latest.as_deref() == Some(hash.as_str()) {
+            return Ok(None);
+        }
+    }
+
+    let snapshot = Snapshot {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        file_count: files.len(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let json = serde_json::to_string(&files).map_err(|e| format!("Failed to save snapshot: {}", e))?;
+    sqlx::query("INSERT INTO project_snapshots (id, project_id, kind, files, hash, created_at) VALUES (?, ?, ?, ?, ?, ?)")
+        .bind(&snapshot.id)
+        .bind(project_id)
+        .bind(kind.as_str())
+        .bind(json)
+        .bind(&hash)
+        .bind(&snapshot.created_at)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save snapshot: {}", e))?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM project_snapshots
+        WHERE project_id = ? AND id NOT IN (
+            SELECT id FROM project_snapshots WHERE project_id = ?
+            ORDER BY created_at DESC, rowid DESC LIMIT ?
+        )
+        "#,
+    )
+    .bind(project_id)
+    .bind(project_id)
+    .bind(MAX_SNAPSHOTS)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to prune snapshots: {}", e))?;
+
+    Ok(Some(snapshot))
+}
+
+/// A project's snapshots, newest first
+pub async fn list(pool: &SqlitePool, project_id: &str) -> Result<Vec<Snapshot>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, kind, (SELECT COUNT(*) FROM json_each(files)) AS file_count, created_at
+        FROM project_snapshots
+        WHERE project_id = ?
+        ORDER BY created_at DESC, rowid DESC
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| Snapshot {
+            id: row.get("id"),
+            kind: SnapshotKind::parse(row.get::<String, _>("kind").as_str()),
+            file_count: row.get::<i64, _>("file_count") as usize,
+            created_at: row.get("created_at"),
+        })
+        .collect())
+}
+
+/// A version's files and the snapshot ID or `current` it resolved to;
+/// `None` for `last_run` before any run
+async fn version(
+    pool: &SqlitePool,
+    project_id: &str,
+    version: &str,
+) -> Result<Option<(String, BTreeMap<String, String>)>, String> {
+    if version == CURRENT {
+        let files = crate::scaffold::project_files(pool, project_id).await?;
+        return Ok(Some((CURRENT.to_string(), files)));
+    }
+
+    let row = if version == LAST_RUN {
+        sqlx::query(
+            r#"
+            SELECT id, files FROM project_snapshots
+            WHERE project_id = ? AND kind = 'before_run'
+            ORDER BY created_at DESC, rowid DESC LIMIT 1
+            "#,
+        )
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await
+    } else {
+        sqlx::query("SELECT id, files FROM project_snapshots WHERE project_id = ? AND id = ?")
+            .bind(project_id)
+            .bind(version)
+            .fetch_optional(pool)
+            .await
+    }
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    match row {
+        Some(row) => {
+            let files: String = row.get("files");
+            let files = serde_json::from_str(&files).map_err(|e| format!("Unreadable snapshot: {}", e))?;
+            Ok(Some((row.get("id"), files)))
+        }
+        None if version == LAST_RUN => Ok(None),
+        None => Err(format!("Snapshot not found: {}", version)),
+    }
+}
+
+/// Diff two versions of a project (snapshot IDs, `current`, or `last_run`)
+pub async fn diff(pool: &SqlitePool, project_id: &str, from: &str, to: &str) -> Result<ProjectDiff, String> {
+    let from = version(pool, project_id, from).await?;
+    let (to, new) = version(pool, project_id, to)
+        .await?
+        .ok_or_else(|| "No agent run has been recorded yet".to_string())?;
+
+    let (from, old) = match from {
+        Some((id, files)) => (Some(id), files),
+        None => (None, BTreeMap::new()),
+    };
+    Ok(ProjectDiff {
+        from,
+        to,
+        files: diff_files(&old, &new),
+    })
+}
+
+/// Unified diffs of the files that differ between `old` and `new`
+pub fn diff_files(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<FileDiff> {
+    let mut paths: Vec<&String> = old.keys().chain(new.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut diffs = Vec::new();
+    for path in paths {
+        let (before, after) = (old.get(path), new.get(path));
+        let status = match (before, after) {
+            (None, Some(_)) => FileDiffStatus::Added,
+            (Some(_), None) => FileDiffStatus::Deleted,
+            (Some(before), Some(after)) if before != after => FileDiffStatus::Modified,
+            _ => continue,
+        };
+
+        let (before, after) = (before.map_or("", String::as_str), after.map_or("", String::as_str));
+        let text_diff = similar::TextDiff::from_lines(before, after);
+        let (mut additions, mut deletions) = (0, 0);
+        for change in text_diff.iter_all_changes() {
+            match change.tag() {
+                similar::ChangeTag::Insert => additions += 1,
+                similar::ChangeTag::Delete => deletions += 1,
+                similar::ChangeTag::Equal => {}
+            }
+        }
+        let header_old = if status == FileDiffStatus::Added { "/dev/null".to_string() } else { format!("a/{}", path) };
+        let header_new = if status == FileDiffStatus::Deleted { "/dev/null".to_string() } else { format!("b/{}", path) };
+        let diff = text_diff
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .header(&header_old, &header_new)
+            .to_string();
+
+        diffs.push(FileDiff {
+            path: path.clone(),
+            status,
+            diff,
+            additions,
+            deletions,
+        });
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn files(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(path, content)| (path.to_string(), content.to_string())).collect()
+    }
+
+    #[test]
+    fn test_diff_files() {
+        let old = files(&[("index.html", "<h1>Hi</h1>\n<p>One</p>\n"), ("old.css", "a {}\n"), ("same.js", "x\n")]);
+        let new = files(&[("index.html", "<h1>Hello</h1>\n<p>One</p>\n"), ("new.js", "run()\n"), ("same.js", "x\n")]);
+
+        let diffs = diff_files(&old, &new);
+        let summary: Vec<(&str, FileDiffStatus, usize, usize)> = diffs
+            .iter()
+            .map(|diff| (diff.path.as_str(), diff.status, diff.additions, diff.deletions))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("index.html", FileDiffStatus::Modified, 1, 1),
+                ("new.js", FileDiffStatus::Added, 1, 0),
+                ("old.css", FileDiffStatus::Deleted, 0, 1),
+            ]
+        );
+        assert!(diffs[0].diff.starts_with("--- a/index.html\n+++ b/index.html\n@@"));
+        assert!(diffs[0].diff.contains("-<h1>Hi</h1>\n+<h1>Hello</h1>\n <p>One</p>\n"));
+        assert!(diffs[1].diff.starts_with("--- /dev/null\n+++ b/new.js\n"));
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_and_last_run_diff() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, current_code, user_id) VALUES ('p1', 'Site', 'website', '<h1>Hi</h1>', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Before any run, everything counts as added
+        let diff = diff(&pool, "p1", LAST_RUN, CURRENT).await.unwrap();
+        assert_eq!(diff.from, None);
+        assert_eq!(diff.files.len(), 1);
+        assert!(super::diff(&pool, "p1", CURRENT, LAST_RUN).await.is_err());
+
+        let saved = record(&pool, "p1", SnapshotKind::Save).await.unwrap().unwrap();
+        assert!(record(&pool, "p1", SnapshotKind::Save).await.unwrap().is_none());
+        let before = record(&pool, "p1", SnapshotKind::BeforeRun).await.unwrap().unwrap();
+
+        crate::agent_tools::save_file(&pool, "p1", "app.js", "run()\n").await.unwrap();
+        let diff = super::diff(&pool, "p1", LAST_RUN, CURRENT).await.unwrap();
+        assert_eq!(diff.from.as_deref(), Some(before.id.as_str()));
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.files[0].path, "app.js");
+        assert_eq!(diff.files[0].status, FileDiffStatus::Added);
+
+        assert!(super::diff(&pool, "p1", &saved.id, &before.id).await.unwrap().files.is_empty());
+        assert!(super::diff(&pool, "p1", "missing", CURRENT).await.is_err());
+
+        let snapshots = list(&pool, "p1").await.unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].id, before.id);
+        assert_eq!(snapshots[0].kind, SnapshotKind::BeforeRun);
+        assert_eq!(snapshots[0].file_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_are_pruned() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Site', 'website', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        for _ in 0..MAX_SNAPSHOTS + 5 {
+            record(&pool, "p1", SnapshotKind::BeforeRun).await.unwrap();
+        }
+        assert_eq!(list(&pool, "p1").await.unwrap().len() as i64, MAX_SNAPSHOTS);
+    }
+}
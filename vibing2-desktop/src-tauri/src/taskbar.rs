@@ -0,0 +1,160 @@
+//! Taskbar and dock progress
+//!
+//! Shows native progress on the main window's taskbar button (Windows), dock
+//! icon (macOS), or launcher entry (Linux, where supported) while work is in
+//! flight:
+//!
+//! - An update download shows how far it has got, or paused
+//! - Otherwise running generations show an indeterminate bar, since their
+//!   length isn't known ahead of time
+//!
+//! Generations come from the stream session manager's activity; downloads
+//! from the updater's `update-*` events.
+
+use std::sync::Mutex;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{Listener, Manager};
+
+/// What's in flight
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Work {
+    generations: usize,
+    download: Option<Download>,
+}
+
+/// An update download's percentage
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Download {
+    Running(f64),
+    Paused(f64),
+}
+
+/// The bar to show for some work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bar {
+    Indeterminate,
+    Normal(u64),
+    Paused(u64),
+}
+
+static WORK: Mutex<Work> = Mutex::new(Work {
+    generations: 0,
+    download: None,
+});
+
+/// Keep the taskbar progress in sync with generations and update downloads
+pub fn listen(app: &tauri::AppHandle) {
+    let handle = app.clone();
+    let mut activity = crate::server::streams::subscribe_activity();
+    tauri::async_runtime::spawn(async move {
+        while activity.changed().await.is_ok() {
+            let generations = activity.borrow_and_update().generations.len();
+            update(&handle, |work| work.generations = generations);
+        }
+    });
+
+    let handle = app.clone();
+    app.listen_any("update-download-progress", move |event| {
+        let percentage = payload(event.payload())
+            .and_then(|status| status.get("percentage")?.as_f64())
+            .unwrap_or(0.0);
+        update(&handle, |work| work.download = Some(Download::Running(percentage)));
+    });
+
+    let handle = app.clone();
+    app.listen_any("update-download-paused", move |event| {
+        let percentage = payload(event.payload())
+            .and_then(|status| {
+                let downloaded = status.get("downloaded")?.as_f64()?;
+                let total = status.get("total")?.as_f64().filter(|total| *total > 0.0)?;
+                Some(downloaded / total * 100.0)
+            })
+            .unwrap_or(0.0);
+        update(&handle, |work| work.download = Some(Download::Paused(percentage)));
+    });
+
+    // Any of these ends the download
+    for event in ["update-downloaded", "update-deferred", "update-error", "update-not-available"] {
+        let handle = app.clone();
+        app.listen_any(event, move |_| {
+            update(&handle, |work| work.download = None);
+        });
+    }
+}
+
+fn payload(payload: &str) -> Option<serde_json::Value> {
+    serde_json::from_str(payload).ok()
+}
+
+/// Change what's in flight and show it, if the bar changed
+fn update(app: &tauri::AppHandle, change: impl FnOnce(&mut Work)) {
+    let (before, after) = {
+        let Ok(mut work) = WORK.lock() else {
+            return;
+        };
+        let before = bar(&work);
+        change(&mut work);
+        (before, bar(&work))
+    };
+    if before == after {
+        return;
+    }
+
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let state = match after {
+        Some(Bar::Indeterminate) => ProgressBarState {
+            status: Some(ProgressBarStatus::Indeterminate),
+            progress: None,
+        },
+        Some(Bar::Normal(progress)) => ProgressBarState {
+            status: Some(ProgressBarStatus::Normal),
+            progress: Some(progress),
+        },
+        Some(Bar::Paused(progress)) => ProgressBarState {
+            status: Some(ProgressBarStatus::Paused),
+            progress: Some(progress),
+        },
+        None => ProgressBarState {
+            status: Some(ProgressBarStatus::None),
+            progress: None,
+        },
+    };
+    if let Err(e) = window.set_progress_bar(state) {
+        eprintln!("Failed to update taskbar progress: {}", e);
+    }
+}
+
+/// The bar for `work`; a download's known progress wins over generations
+fn bar(work: &Work) -> Option<Bar> {
+    let percent = |percentage: f64| percentage.clamp(0.0, 100.0).round() as u64;
+    match work.download {
+        Some(Download::Running(percentage)) => Some(Bar::Normal(percent(percentage))),
+        Some(Download::Paused(percentage)) => Some(Bar::Paused(percent(percentage))),
+        None if work.generations > 0 => Some(Bar::Indeterminate),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(&Work::default()), None);
+
+        let mut work = Work {
+            generations: 2,
+            download: None,
+        };
+        assert_eq!(bar(&work), Some(Bar::Indeterminate));
+
+        work.download = Some(Download::Running(41.6));
+        assert_eq!(bar(&work), Some(Bar::Normal(42)));
+
+        work.download = Some(Download::Paused(140.0));
+        assert_eq!(bar(&work), Some(Bar::Paused(100)));
+    }
+}
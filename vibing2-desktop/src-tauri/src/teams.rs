@@ -0,0 +1,238 @@
+//! Agent teams
+//!
+//! A team is an ordered list of agents saved under a name, optionally for
+//! one project type. Applying a team to a project records it as the
+//! project's team and makes its agents the project's active agents (see
+//! [`crate::project_agents`]), in the team's execution order.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Team {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Project type the team is meant for, if any
+    pub project_type: Option<String>,
+    /// Agent IDs in execution order
+    pub agents: Vec<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NewTeam {
+    pub name: String,
+    pub description: Option<String>,
+    pub project_type: Option<String>,
+    /// Agent IDs in execution order
+    pub agents: Vec<String>,
+}
+
+/// The user's teams, optionally those for one project type (and untyped ones)
+pub async fn list(pool: &SqlitePool, user_id: &str, project_type: Option<&str>) -> Result<Vec<Team>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, description, project_type, created_at
+        FROM agent_teams
+        WHERE user_id = ? AND (? IS NULL OR project_type = ? OR project_type IS NULL)
+        ORDER BY name
+        "#,
+    )
+    .bind(user_id)
+    .bind(project_type)
+    .bind(project_type)
+    .fetch_all(pool)
+    .await?;
+
+    let mut teams = Vec::with_capacity(rows.len());
+    for row in &rows {
+        teams.push(team_from_row(pool, row).await?);
+    }
+    Ok(teams)
+}
+
+/// One of the user's teams
+pub async fn get(pool: &SqlitePool, user_id: &str, id: &str) -> Result<Option<Team>, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT id, name, description, project_type, created_at FROM agent_teams WHERE id = ? AND user_id = ?",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(row) => Ok(Some(team_from_row(pool, &row).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Save a new team for `user_id`
+pub async fn create(pool: &SqlitePool, user_id: &str, team: NewTeam) -> Result<Team, String> {
+    let name = team.name.trim();
+    if name.is_empty() {
+        return Err("Team name is required".to_string());
+    }
+    if team.agents.is_empty() {
+        return Err("A team needs at least one agent".to_string());
+    }
+    let mut seen = HashSet::new();
+    for agent_id in &team.agents {
+        if !seen.insert(agent_id) {
+            return Err(format!("{} is in the team twice", agent_id));
+        }
+        let exists = crate::agents::get(pool, agent_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some();
+        if !exists {
+            return Err(format!("Agent not found: {}", agent_id));
+        }
+    }
+    let project_type = team.project_type.as_deref().map(str::trim).filter(|t| !t.is_empty());
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    sqlx::query(
+        r#"
+        INSERT INTO agent_teams (id, user_id, name, description, project_type)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(name)
+    .bind(&team.description)
+    .bind(project_type)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to save team: {}", e))?;
+
+    for (position, agent_id) in team.agents.iter().enumerate() {
+        sqlx::query("INSERT INTO agent_team_members (team_id, agent_id, position) VALUES (?, ?, ?)")
+            .bind(&id)
+            .bind(agent_id)
+            .bind(position as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to save team: {}", e))?;
+    }
+    tx.commit().await.map_err(|e| format!("Failed to save team: {}", e))?;
+
+    get(pool, user_id, &id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| "Team was not saved".to_string())
+}
+
+/// Delete one of the user's teams; projects using it keep their agents
+pub async fn delete(pool: &SqlitePool, user_id: &str, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM agent_teams WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Make a team's agents a project's active agents
+///
+/// The caller checks the user may write to the project.
+pub async fn apply(pool: &SqlitePool, user_id: &str, team_id: &str, project_id: &str) -> Result<Team, String> {
+    let team = get(pool, user_id, team_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Team not found: {}", team_id))?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    let result = sqlx::query("UPDATE projects SET team_id = ?, updated_at = ? WHERE id = ?")
+        .bind(&team.id)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to apply team: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Project not found: {}", project_id));
+    }
+    crate::project_agents::set(&mut *tx, project_id, &team.agents).await?;
+    tx.commit().await.map_err(|e| format!("Failed to apply team: {}", e))?;
+    Ok(team)
+}
+
+async fn team_from_row(pool: &SqlitePool, row: &sqlx::sqlite::SqliteRow) -> Result<Team, sqlx::Error> {
+    let id: String = row.get("id");
+    let agents = sqlx::query_scalar("SELECT agent_id FROM agent_team_members WHERE team_id = ? ORDER BY position")
+        .bind(&id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Team {
+        id,
+        name: row.get("name"),
+        description: row.get("description"),
+        project_type: row.get("project_type"),
+        agents,
+        created_at: row.get("created_at"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_apply_team_to_project() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let new_team = |agents: &[&str]| NewTeam {
+            name: "Full stack".to_string(),
+            description: None,
+            project_type: Some("website".to_string()),
+            agents: agents.iter().map(|id| id.to_string()).collect(),
+        };
+        assert!(create(&pool, "local-user", new_team(&[])).await.is_err());
+        assert!(create(&pool, "local-user", new_team(&["ui-designer", "ui-designer"])).await.is_err());
+        assert!(create(&pool, "local-user", new_team(&["missing"])).await.is_err());
+
+        let team = create(&pool, "local-user", new_team(&["ui-designer", "backend-architect"]))
+            .await
+            .unwrap();
+        assert_eq!(team.agents, vec!["ui-designer", "backend-architect"]);
+        assert_eq!(list(&pool, "local-user", Some("website")).await.unwrap().len(), 1);
+        assert!(list(&pool, "local-user", Some("game")).await.unwrap().is_empty());
+
+        sqlx::query("INSERT INTO projects (id, name, project_type, user_id) VALUES ('p1', 'Site', 'website', 'local-user')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        apply(&pool, "local-user", &team.id, "p1").await.unwrap();
+        let team_id: String = sqlx::query_scalar("SELECT team_id FROM projects WHERE id = 'p1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(team_id, team.id);
+        assert_eq!(
+            crate::project_agents::list(&pool, "p1").await.unwrap(),
+            vec!["ui-designer", "backend-architect"]
+        );
+        assert!(apply(&pool, "local-user", &team.id, "missing").await.is_err());
+
+        // Deleting the team leaves the project's agents in place
+        assert!(delete(&pool, "local-user", &team.id).await.unwrap());
+        let team_id: Option<String> = sqlx::query_scalar("SELECT team_id FROM projects WHERE id = 'p1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(team_id.is_none());
+    }
+}
@@ -0,0 +1,175 @@
+// Telemetry module - Tracing subscriber with optional OpenTelemetry export
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{Sampler, TracerProvider};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer, Registry};
+
+/// Default OTLP/gRPC collector endpoint
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:4317";
+
+type OtelLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Swaps the OpenTelemetry layer in and out at runtime
+static OTEL_LAYER: OnceLock<reload::Handle<Option<OtelLayer>, Registry>> = OnceLock::new();
+
+/// Active exporter, kept so it can be flushed on shutdown or reconfigure
+static PROVIDER: Mutex<Option<TracerProvider>> = Mutex::new(None);
+
+/// OTLP export settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// OTLP/gRPC collector endpoint
+    pub endpoint: String,
+    /// Fraction of traces to export, from 0.0 to 1.0
+    pub sample_ratio: f64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+/// Install the global subscriber: console logs filtered by `RUST_LOG`, plus
+/// a slot for the OpenTelemetry layer that `apply` fills in later
+pub fn init_subscriber() {
+    let (otel_layer, handle) = reload::Layer::new(None::<OtelLayer>);
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    if tracing_subscriber::registry()
+        .with(otel_layer)
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .try_init()
+        .is_ok()
+    {
+        let _ = OTEL_LAYER.set(handle);
+    }
+}
+
+/// Start or stop OTLP export to match `config`
+///
+/// Must be called from within the Tokio runtime; the batch exporter spawns
+/// its worker there.
+pub fn apply(config: &TelemetryConfig) -> Result<(), String> {
+    let Some(handle) = OTEL_LAYER.get() else {
+        return Err("Tracing subscriber is not initialized".to_string());
+    };
+
+    shutdown();
+
+    if !config.enabled {
+        handle
+            .modify(|layer| *layer = None)
+            .map_err(|e| format!("Failed to disable telemetry: {}", e))?;
+        return Ok(());
+    }
+
+    let provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(&config.endpoint),
+        )
+        .with_trace_config(
+            opentelemetry_sdk::trace::Config::default()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    config.sample_ratio.clamp(0.0, 1.0),
+                ))))
+                .with_resource(opentelemetry_sdk::Resource::new(vec![
+                    KeyValue::new("service.name", "vibing2-desktop"),
+                    KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+                ])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .map_err(|e| format!("Failed to start OTLP exporter: {}", e))?;
+
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("vibing2-desktop"))
+        .boxed();
+
+    handle
+        .modify(|slot| *slot = Some(layer))
+        .map_err(|e| format!("Failed to enable telemetry: {}", e))?;
+
+    if let Ok(mut active) = PROVIDER.lock() {
+        *active = Some(provider);
+    }
+
+    println!("📈 Exporting traces to {} (sample ratio {})", config.endpoint, config.sample_ratio);
+    Ok(())
+}
+
+/// Flush and stop the active exporter, if any
+pub fn shutdown() {
+    let provider = PROVIDER.lock().ok().and_then(|mut active| active.take());
+    if let Some(provider) = provider {
+        let _ = provider.shutdown();
+    }
+}
+
+/// Load telemetry settings, falling back to defaults
+pub async fn load_config(pool: &SqlitePool) -> TelemetryConfig {
+    let rows = sqlx::query(
+        "SELECT key, value FROM settings WHERE key IN ('telemetry_enabled', 'telemetry_endpoint', 'telemetry_sample_ratio')"
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+
+    let mut config = TelemetryConfig::default();
+    for row in rows {
+        let key: String = row.get("key");
+        let value: String = row.get("value");
+        match key.as_str() {
+            "telemetry_enabled" => config.enabled = value == "true",
+            "telemetry_endpoint" if !value.is_empty() => config.endpoint = value,
+            "telemetry_sample_ratio" => config.sample_ratio = value.parse().unwrap_or(1.0),
+            _ => {}
+        }
+    }
+
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_load_config_defaults_and_overrides() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(load_config(&pool).await, TelemetryConfig::default());
+
+        for (key, value) in [("telemetry_enabled", "true"), ("telemetry_sample_ratio", "0.25")] {
+            sqlx::query("INSERT INTO settings (id, key, value, updated_at) VALUES (?, ?, ?, datetime('now'))")
+                .bind(key)
+                .bind(key)
+                .bind(value)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let config = load_config(&pool).await;
+        assert!(config.enabled);
+        assert_eq!(config.sample_ratio, 0.25);
+        assert_eq!(config.endpoint, DEFAULT_ENDPOINT);
+    }
+}
@@ -0,0 +1,332 @@
+//! Prompt templates
+//!
+//! A template is a prompt with `{{variable}}` placeholders. Built-in
+//! templates ship for each project type; users can save their own. A stream
+//! request that names a template gets its prompt rendered from it, with the
+//! request's own prompt available as `{{prompt}}`.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// ID prefix of templates that ship with the app
+const BUILTIN_PREFIX: &str = "builtin-";
+
+/// Built-in templates as (project type, name, description, body)
+const BUILTINS: &[(&str, &str, &str, &str)] = &[
+    (
+        "website",
+        "Website",
+        "A responsive multi-section website",
+        "Build a responsive website: {{prompt}}\n\nUse semantic HTML, a mobile-first layout, and accessible color contrast. Put all pages in index.html unless asked otherwise.",
+    ),
+    (
+        "mobile-app",
+        "Mobile app",
+        "A touch-first app screen flow",
+        "Build a mobile app UI: {{prompt}}\n\nDesign for touch targets of at least 44px, a bottom navigation bar, and screens that fit a 390px-wide viewport.",
+    ),
+    (
+        "game",
+        "Browser game",
+        "A canvas game with a game loop and controls",
+        "Build a browser game: {{prompt}}\n\nUse a single <canvas> with a requestAnimationFrame game loop, keyboard and touch controls, a score display, and a restart button.",
+    ),
+    (
+        "api",
+        "API",
+        "A REST API with documented endpoints",
+        "Design and implement a REST API: {{prompt}}\n\nList every endpoint with its method, request body, and response, validate input, and return JSON errors with proper status codes.",
+    ),
+    (
+        "dashboard",
+        "Dashboard",
+        "A data dashboard with charts and filters",
+        "Build a data dashboard: {{prompt}}\n\nInclude summary cards, at least one chart, a filterable table, and sample data so it renders without a backend.",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Project type the template is meant for, if any
+    pub project_type: Option<String>,
+    pub body: String,
+    /// Placeholders in `body`, in order of first use
+    pub variables: Vec<String>,
+    /// Ships with the app; can't be deleted
+    pub builtin: bool,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NewPromptTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub project_type: Option<String>,
+    pub body: String,
+}
+
+/// Templates that ship with the app, one per project type
+pub fn builtin_templates() -> Vec<PromptTemplate> {
+    BUILTINS
+        .iter()
+        .map(|(project_type, name, description, body)| PromptTemplate {
+            id: format!("{}{}", BUILTIN_PREFIX, project_type),
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            project_type: Some(project_type.to_string()),
+            body: body.to_string(),
+            variables: variables(body),
+            builtin: true,
+            created_at: None,
+        })
+        .collect()
+}
+
+/// Placeholder names in `body`, each listed once
+pub fn variables(body: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for (_, name) in placeholders(body) {
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Substitute every `{{name}}` in `body`
+///
+/// Fails if a placeholder has no value. Text that isn't a valid placeholder
+/// (e.g. `{{ }}` or `{{a-b}}`) is left as is.
+pub fn render(body: &str, values: &HashMap<String, String>) -> Result<String, String> {
+    let mut rendered = String::with_capacity(body.len());
+    let mut rest = 0;
+
+    for (range, name) in placeholders(body) {
+        let value = values
+            .get(name)
+            .ok_or_else(|| format!("Missing template variable: {}", name))?;
+        rendered.push_str(&body[rest..range.start]);
+        rendered.push_str(value);
+        rest = range.end;
+    }
+
+    rendered.push_str(&body[rest..]);
+    Ok(rendered)
+}
+
+/// Byte ranges and names of the `{{name}}` placeholders in `body`
+fn placeholders(body: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let mut from = 0;
+
+    while let Some(open) = body[from..].find("{{").map(|i| from + i) {
+        let Some(close) = body[open + 2..].find("}}").map(|i| open + 2 + i) else {
+            break;
+        };
+        let name = body[open + 2..close].trim();
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if valid {
+            found.push((open..close + 2, name));
+            from = close + 2;
+        } else {
+            from = open + 2;
+        }
+    }
+
+    found
+}
+
+/// Built-in templates plus the user's own, optionally for one project type
+pub async fn list(
+    pool: &SqlitePool,
+    user_id: &str,
+    project_type: Option<&str>,
+) -> Result<Vec<PromptTemplate>, sqlx::Error> {
+    let mut templates: Vec<PromptTemplate> = builtin_templates()
+        .into_iter()
+        .filter(|template| project_type.is_none() || template.project_type.as_deref() == project_type)
+        .collect();
+
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, description, project_type, body, created_at
+        FROM prompt_templates
+        WHERE user_id = ? AND (? IS NULL OR project_type = ? OR project_type IS NULL)
+        ORDER BY name
+        "#,
+    )
+    .bind(user_id)
+    .bind(project_type)
+    .bind(project_type)
+    .fetch_all(pool)
+    .await?;
+
+    templates.extend(rows.iter().map(template_from_row));
+    Ok(templates)
+}
+
+/// A built-in template or one of the user's own
+pub async fn get(pool: &SqlitePool, user_id: &str, id: &str) -> Result<Option<PromptTemplate>, sqlx::Error> {
+    if id.starts_with(BUILTIN_PREFIX) {
+        return Ok(builtin_templates().into_iter().find(|template| template.id == id));
+    }
+
+    let row = sqlx::query(
+        "SELECT id, name, description, project_type, body, created_at FROM prompt_templates WHERE id = ? AND user_id = ?",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(template_from_row))
+}
+
+/// Save a new template for `user_id`
+pub async fn create(pool: &SqlitePool, user_id: &str, template: NewPromptTemplate) -> Result<PromptTemplate, String> {
+    let name = template.name.trim();
+    if name.is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    if template.body.trim().is_empty() {
+        return Err("Template body is required".to_string());
+    }
+    let project_type = template.project_type.as_deref().map(str::trim).filter(|t| !t.is_empty());
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        r#"
+        INSERT INTO prompt_templates (id, user_id, name, description, project_type, body)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(name)
+    .bind(&template.description)
+    .bind(project_type)
+    .bind(&template.body)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save template: {}", e))?;
+
+    get(pool, user_id, &id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| "Template was not saved".to_string())
+}
+
+/// Delete one of the user's templates
+pub async fn delete(pool: &SqlitePool, user_id: &str, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM prompt_templates WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Render a template by ID
+pub async fn render_template(
+    pool: &SqlitePool,
+    user_id: &str,
+    id: &str,
+    values: &HashMap<String, String>,
+) -> Result<String, String> {
+    let template = get(pool, user_id, id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Template not found: {}", id))?;
+
+    render(&template.body, values)
+}
+
+fn template_from_row(row: &sqlx::sqlite::SqliteRow) -> PromptTemplate {
+    let body: String = row.get("body");
+    PromptTemplate {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        project_type: row.get("project_type"),
+        variables: variables(&body),
+        body,
+        builtin: false,
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render_substitutes_variables() {
+        let body = "Build {{ thing }} for {{audience}}; {{thing}} again. Keep {{ }} and {{a-b}}.";
+        assert_eq!(variables(body), vec!["thing", "audience"]);
+
+        let rendered = render(body, &values(&[("thing", "a blog"), ("audience", "cooks")])).unwrap();
+        assert_eq!(rendered, "Build a blog for cooks; a blog again. Keep {{ }} and {{a-b}}.");
+
+        let missing = render(body, &values(&[("thing", "a blog")])).unwrap_err();
+        assert!(missing.contains("audience"));
+    }
+
+    #[test]
+    fn test_builtins_cover_project_types() {
+        let builtins = builtin_templates();
+        for project_type in ["website", "mobile-app", "game", "api", "dashboard"] {
+            let template = builtins
+                .iter()
+                .find(|t| t.project_type.as_deref() == Some(project_type))
+                .unwrap();
+            assert_eq!(template.variables, vec!["prompt"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_user_templates() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let template = create(
+            &pool,
+            "local-user",
+            NewPromptTemplate {
+                name: "Portfolio".to_string(),
+                description: None,
+                project_type: Some("website".to_string()),
+                body: "A portfolio for {{name}}: {{prompt}}".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(template.variables, vec!["name", "prompt"]);
+
+        let websites = list(&pool, "local-user", Some("website")).await.unwrap();
+        assert_eq!(websites.len(), 2);
+        assert!(list(&pool, "local-user", Some("game")).await.unwrap().iter().all(|t| t.builtin));
+
+        let rendered = render_template(&pool, "local-user", &template.id, &values(&[("name", "Ada"), ("prompt", "dark theme")]))
+            .await
+            .unwrap();
+        assert_eq!(rendered, "A portfolio for Ada: dark theme");
+
+        // Other users can't see or delete it
+        assert!(get(&pool, "someone-else", &template.id).await.unwrap().is_none());
+        assert!(!delete(&pool, "someone-else", &template.id).await.unwrap());
+        assert!(delete(&pool, "local-user", &template.id).await.unwrap());
+    }
+}
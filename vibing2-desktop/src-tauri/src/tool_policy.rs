@@ -0,0 +1,213 @@
+//! Per-agent tool permissions
+//!
+//! Each agent declares which kinds of local tool it may use; generations
+//! that don't run as an agent get the project file tools. The tool registry
+//! checks every call against that set through a [`ToolGate`]; a call outside
+//! it asks the user, via a desktop dialog, whether to allow it for the rest
+//! of the generation. Shell commands run with the user's own access, so each
+//! one is put to the user, whatever the agent declares. Without a way to ask
+//! (tests, headless runs) the call is denied.
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+use utoipa::ToSchema;
+
+use crate::agents::Agent;
+
+/// A kind of local tool an agent may invoke
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPermission {
+    ReadFiles,
+    WriteFiles,
+    RunShell,
+    Network,
+}
+
+impl ToolPermission {
+    /// What the permission allows, for prompts and error messages
+    pub fn describe(self) -> &'static str {
+        match self {
+            ToolPermission::ReadFiles => "read project files",
+            ToolPermission::WriteFiles => "write project files",
+            ToolPermission::RunShell => "run shell commands",
+            ToolPermission::Network => "access the network",
+        }
+    }
+}
+
+/// Permissions of agents that don't declare any: the project file tools
+pub const DEFAULT_PERMISSIONS: &[ToolPermission] = &[ToolPermission::ReadFiles, ToolPermission::WriteFiles];
+
+pub fn default_permissions() -> Vec<ToolPermission> {
+    DEFAULT_PERMISSIONS.to_vec()
+}
+
+/// Permissions the user approves call by call, even when declared
+const ALWAYS_ASK: &[ToolPermission] = &[ToolPermission::RunShell];
+
+/// A tool call that needs the user's approval
+#[derive(Debug, Clone, Serialize)]
+pub struct Escalation {
+    /// `None` when the generation isn't running as an agent
+    pub agent_id: Option<String>,
+    pub agent_name: Option<String>,
+    pub tool: String,
+    pub permission: ToolPermission,
+    /// What the call does, e.g. the command line to run
+    pub detail: Option<String>,
+}
+
+impl Escalation {
+    /// Who is asking, for prompts and error messages
+    fn who(&self) -> String {
+        match &self.agent_name {
+            Some(name) => format!("The {} agent", name),
+            None => "The assistant".to_string(),
+        }
+    }
+}
+
+type Prompt = Box<dyn Fn(Escalation) -> BoxFuture<'static, bool> + Send + Sync>;
+
+static PROMPT: OnceLock<Prompt> = OnceLock::new();
+
+/// Set how escalations are put to the user; only the first call has effect
+pub fn set_prompt(prompt: impl Fn(Escalation) -> BoxFuture<'static, bool> + Send + Sync + 'static) {
+    let _ = PROMPT.set(Box::new(prompt));
+}
+
+/// Ask with a native Allow/Deny dialog
+pub async fn ask_with_dialog(app: &tauri::AppHandle, escalation: Escalation) -> bool {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let message = match (&escalation.detail, ALWAYS_ASK.contains(&escalation.permission)) {
+        (Some(detail), true) => format!(
+            "{} wants to run this command on your computer:\n\n{}\n\n\
+             It isn't isolated: it can read and change your files and use the network. Allow it?",
+            escalation.who(),
+            detail
+        ),
+        _ => format!(
+            "{} wants to {} (tool: {}), which it isn't permitted to do.\n\n\
+             Allow it for the rest of this generation?",
+            escalation.who(),
+            escalation.permission.describe(),
+            escalation.tool
+        ),
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .message(message)
+        .title("Allow agent tool?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom("Allow".to_string(), "Deny".to_string()))
+        .show(move |allowed| {
+            let _ = tx.send(allowed);
+        });
+
+    rx.await.unwrap_or(false)
+}
+
+/// Checks one generation's tool calls against an agent's permissions
+pub struct ToolGate {
+    agent_id: Option<String>,
+    agent_name: Option<String>,
+    /// Declared permissions plus any the user granted during the generation
+    allowed: Mutex<HashSet<ToolPermission>>,
+}
+
+impl ToolGate {
+    pub fn for_agent(agent: &Agent) -> Self {
+        Self {
+            agent_id: Some(agent.id.clone()),
+            agent_name: Some(agent.name.clone()),
+            allowed: Mutex::new(agent.permissions.iter().copied().collect()),
+        }
+    }
+
+    /// For generations that don't run as an agent: the default permissions
+    pub fn without_agent() -> Self {
+        Self {
+            agent_id: None,
+            agent_name: None,
+            allowed: Mutex::new(DEFAULT_PERMISSIONS.iter().copied().collect()),
+        }
+    }
+
+    fn allows(&self, permission: ToolPermission) -> bool {
+        self.allowed.lock().map(|allowed| allowed.contains(&permission)).unwrap_or(false)
+    }
+
+    /// Let a call through, asking the user if it needs an undeclared
+    /// permission or one that is always asked for
+    pub async fn check(&self, tool: &str, permission: ToolPermission, detail: Option<&str>) -> Result<(), String> {
+        let always_ask = ALWAYS_ASK.contains(&permission);
+        if !always_ask && self.allows(permission) {
+            return Ok(());
+        }
+
+        let escalation = Escalation {
+            agent_id: self.agent_id.clone(),
+            agent_name: self.agent_name.clone(),
+            tool: tool.to_string(),
+            permission,
+            detail: detail.map(str::to_string),
+        };
+        let who = escalation.who();
+        let granted = match PROMPT.get() {
+            Some(prompt) => prompt(escalation).await,
+            None => false,
+        };
+        if !granted {
+            return Err(format!(
+                "{} needs the user's approval to {}, and the user didn't allow it",
+                who,
+                permission.describe()
+            ));
+        }
+
+        if !always_ask {
+            if let Ok(mut allowed) = self.allowed.lock() {
+                allowed.insert(permission);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_gate_denies_undeclared_permissions() {
+        let agent = crate::agent_manifest::bundled()
+            .agents
+            .iter()
+            .map(Agent::from)
+            .next()
+            .unwrap();
+        assert_eq!(agent.permissions, DEFAULT_PERMISSIONS);
+
+        let gate = ToolGate::for_agent(&agent);
+        assert!(gate.check("write_file", ToolPermission::WriteFiles, None).await.is_ok());
+        let denied = gate.check("run_command", ToolPermission::RunShell, Some("npm test")).await.unwrap_err();
+        assert!(denied.contains("run shell commands"));
+
+        // Shell commands are asked for even when declared, and without an agent
+        let mut shell_agent = agent.clone();
+        shell_agent.permissions.push(ToolPermission::RunShell);
+        let gate = ToolGate::for_agent(&shell_agent);
+        assert!(gate.check("run_command", ToolPermission::RunShell, Some("npm test")).await.is_err());
+        let gate = ToolGate::without_agent();
+        assert!(gate.check("read_file", ToolPermission::ReadFiles, None).await.is_ok());
+        assert!(gate.check("run_command", ToolPermission::RunShell, Some("npm test")).await.is_err());
+
+        let json = serde_json::to_string(&[ToolPermission::RunShell, ToolPermission::Network]).unwrap();
+        assert_eq!(json, r#"["run_shell","network"]"#);
+    }
+}
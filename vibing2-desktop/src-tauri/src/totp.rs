@@ -0,0 +1,293 @@
+//! TOTP two-factor authentication (RFC 6238) for server accounts
+//!
+//! Users enroll by scanning the `otpauth://` provisioning URI into an
+//! authenticator app and confirming one code. Sign-in on the embedded server
+//! then requires a current code, but only while the `server_two_factor`
+//! setting is on, since 2FA mainly matters once the server is exposed on a LAN.
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sqlx::{Row, SqlitePool};
+
+/// Settings key that turns on 2FA enforcement for the embedded server
+pub const SETTING_KEY: &str = "server_two_factor";
+
+/// Issuer shown in authenticator apps
+const ISSUER: &str = "Vibing2";
+
+const DIGITS: u32 = 6;
+const STEP_SECS: u64 = 30;
+
+/// Accept codes from one step either side to tolerate clock drift
+const SKEW_STEPS: i64 = 1;
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Secret and provisioning URI returned when starting enrollment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Enrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Whether the embedded server should require TOTP codes at sign in
+pub async fn is_required(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(SETTING_KEY)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(value.map_or(false, |value| value == "true"))
+}
+
+/// Generate a new secret for a user, replacing any unconfirmed one
+///
+/// 2FA stays disabled until [`confirm_enrollment`] succeeds.
+pub async fn begin_enrollment(
+    pool: &SqlitePool,
+    user_id: &str,
+    account: &str,
+) -> Result<Enrollment, String> {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let secret = base32_encode(&bytes);
+
+    let stored = crate::vault::seal(pool, &secret).await?;
+
+    sqlx::query("UPDATE users SET totp_secret = ?, totp_enabled = 0, totp_last_step = NULL WHERE id = ?")
+        .bind(&stored)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(Enrollment {
+        otpauth_uri: provisioning_uri(&secret, account),
+        secret,
+    })
+}
+
+/// Confirm enrollment with a code from the authenticator app
+pub async fn confirm_enrollment(pool: &SqlitePool, user_id: &str, code: &str) -> Result<bool, String> {
+    if !verify_user_code(pool, user_id, code).await? {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE users SET totp_enabled = 1 WHERE id = ?")
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    println!("🔐 Two-factor authentication enabled for {}", user_id);
+    Ok(true)
+}
+
+/// Turn 2FA off for a user and forget the secret
+pub async fn disable(pool: &SqlitePool, user_id: &str) -> Result<(), String> {
+    sqlx::query("UPDATE users SET totp_secret = NULL, totp_enabled = 0, totp_last_step = NULL WHERE id = ?")
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(())
+}
+
+/// Whether a user has confirmed 2FA enrollment
+pub async fn is_enrolled(pool: &SqlitePool, user_id: &str) -> Result<bool, String> {
+    let enabled: Option<i64> = sqlx::query_scalar("SELECT totp_enabled FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(enabled == Some(1))
+}
+
+/// Check a code against the user's stored secret
+///
+/// Each time step can only be used once, so an observed code cannot be
+/// replayed within its validity window.
+pub async fn verify_user_code(pool: &SqlitePool, user_id: &str, code: &str) -> Result<bool, String> {
+    let row = sqlx::query("SELECT totp_secret, totp_last_step FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let Some(row) = row else {
+        return Ok(false);
+    };
+    let Some(stored): Option<String> = row.get("totp_secret") else {
+        return Ok(false);
+    };
+    let last_step: Option<i64> = row.get("totp_last_step");
+
+    let secret = base32_decode(&crate::vault::open(&stored)?)
+        .ok_or_else(|| "Stored TOTP secret is corrupt".to_string())?;
+
+    let Some(step) = matching_step(&secret, code, unix_time()) else {
+        return Ok(false);
+    };
+
+    if last_step.map_or(false, |last| step <= last) {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE users SET totp_last_step = ? WHERE id = ?")
+        .bind(step)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(true)
+}
+
+/// `otpauth://` URI for QR code provisioning
+pub fn provisioning_uri(secret: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = ISSUER,
+        account = urlencode(account),
+        secret = secret,
+        digits = DIGITS,
+        period = STEP_SECS,
+    )
+}
+
+/// Return the time step a code is valid for, if any, within the allowed skew
+fn matching_step(secret: &[u8], code: &str, now: u64) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = (now / STEP_SECS) as i64;
+    (-SKEW_STEPS..=SKEW_STEPS)
+        .map(|offset| current + offset)
+        .filter(|step| *step >= 0)
+        .find(|step| format_code(hotp(secret, *step as u64)) == code)
+}
+
+/// HOTP value (RFC 4226) for a counter
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    binary % 10u32.pow(DIGITS)
+}
+
+fn format_code(value: u32) -> String {
+    format!("{:0width$}", value, width = DIGITS as usize)
+}
+
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    output
+}
+
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.chars().filter(|c| *c != '=' && !c.is_whitespace()) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+fn urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'@' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        // RFC 6238 appendix B, SHA1, truncated to 6 digits
+        let secret = b"12345678901234567890";
+        assert_eq!(format_code(hotp(secret, 59 / STEP_SECS)), "287082");
+        assert_eq!(format_code(hotp(secret, 1111111109 / STEP_SECS)), "081804");
+        assert_eq!(format_code(hotp(secret, 1234567890 / STEP_SECS)), "005924");
+    }
+
+    #[test]
+    fn test_matching_step_allows_skew() {
+        let secret = b"12345678901234567890";
+        let now = 1111111109;
+        let code = format_code(hotp(secret, now / STEP_SECS - 1));
+
+        assert_eq!(matching_step(secret, &code, now), Some((now / STEP_SECS - 1) as i64));
+        assert_eq!(matching_step(secret, &code, now + 3 * STEP_SECS), None);
+        assert_eq!(matching_step(secret, "abc", now), None);
+    }
+
+    #[test]
+    fn test_base32_roundtrip() {
+        let bytes = b"12345678901234567890";
+        let encoded = base32_encode(bytes);
+        assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32_decode(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let uri = provisioning_uri("ABC", "me@example.com");
+        assert!(uri.starts_with("otpauth://totp/Vibing2:me@example.com?secret=ABC"));
+    }
+}
@@ -3,33 +3,123 @@
 //! Provides a native system tray icon with menu items for quick access to:
 //! - Window visibility controls
 //! - Project management
+//! - Quick prompt capture
 //! - Recent projects (dynamically loaded from database)
-//! - Settings and updates
+//! - Favorite agents, each starting a new project with that agent
+//! - Settings, launch at login, and updates
 //! - Application information
 //!
 //! Features:
 //! - Native macOS integration with proper icons
 //! - Dynamic menu updates based on application state
-//! - Badge indicators for notifications
-//! - Recent projects submenu (last 5 projects)
+//! - Unread notification badges (dock, taskbar overlay, or tray icon)
+//! - Status icons for idle, generating, and failed generations
+//! - Progress, cancel, and queue controls while generations run
+//! - Recent projects submenu (last 5 projects, configurable)
+//! - Favorite agents submenu
+//! - User-defined quick actions that open a route in the window
 
 use tauri::{
-    menu::{MenuBuilder, MenuEvent, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder},
+    menu::{CheckMenuItemBuilder, MenuBuilder, MenuEvent, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
     Emitter, Manager,
 };
 use crate::database;
+use crate::server::streams::Activity;
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
 
 /// Menu item identifiers for handling events
 const MENU_SHOW_HIDE: &str = "show_hide";
 const MENU_NEW_PROJECT: &str = "new_project";
+const MENU_QUICK_CAPTURE: &str = "quick_capture";
 const MENU_SETTINGS: &str = "settings";
+const MENU_LAUNCH_AT_LOGIN: &str = "launch_at_login";
 const MENU_CHECK_UPDATES: &str = "check_updates";
 const MENU_ABOUT: &str = "about";
 const MENU_QUIT: &str = "quit";
 const MENU_RECENT_PREFIX: &str = "recent_";
+const MENU_FAVORITE_AGENT_PREFIX: &str = "favorite_agent_";
+const MENU_GENERATION_PROGRESS: &str = "generation_progress";
+const MENU_CANCEL_GENERATION: &str = "cancel_generation";
+const MENU_PAUSE_QUEUE: &str = "pause_queue";
+const MENU_QUICK_ACTION_PREFIX: &str = "quick_action_";
+
+/// Setting holding the [`TrayLayout`] as JSON
+pub const LAYOUT_SETTING: &str = "tray_layout";
+
+/// Most recent projects the menu can list
+const MAX_RECENT_PROJECTS: u32 = 20;
+const MAX_QUICK_ACTIONS: usize = 10;
+
+/// What the tray menu shows, from settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrayLayout {
+    pub show_recent_projects: bool,
+    /// How many recent projects to list (1-20)
+    pub recent_projects: u32,
+    /// Extra entries, each opening a route in the window
+    pub quick_actions: Vec<QuickAction>,
+}
+
+impl Default for TrayLayout {
+    fn default() -> Self {
+        Self {
+            show_recent_projects: true,
+            recent_projects: 5,
+            quick_actions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuickAction {
+    pub label: String,
+    /// Route in the app, e.g. `/projects`
+    pub route: String,
+}
+
+impl TrayLayout {
+    /// Check the layout before saving it, trimming labels and routes and
+    /// clamping the number of recent projects
+    pub fn validate(mut self) -> Result<Self, String> {
+        if self.quick_actions.len() > MAX_QUICK_ACTIONS {
+            return Err(format!("The tray holds at most {} quick actions", MAX_QUICK_ACTIONS));
+        }
+        for action in &mut self.quick_actions {
+            action.label = action.label.trim().to_string();
+            action.route = action.route.trim().to_string();
+            if action.label.is_empty() {
+                return Err("Quick actions need a label".to_string());
+            }
+            let is_route = action.route.starts_with('/')
+                && !action.route.starts_with("//")
+                && !action.route.chars().any(|c| c.is_whitespace() || c.is_control());
+            if !is_route {
+                return Err(format!("Not a route in the app: {}", action.route));
+            }
+        }
+        self.recent_projects = self.recent_projects.clamp(1, MAX_RECENT_PROJECTS);
+        Ok(self)
+    }
+}
+
+/// The saved tray layout, or the default when unset or unreadable
+pub async fn load_layout(pool: &sqlx::SqlitePool) -> TrayLayout {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(LAYOUT_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
 
 /// Project information for recent projects menu
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,77 +131,217 @@ struct RecentProject {
     updated_at: String,
 }
 
+/// What the tray icon shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrayStatus {
+    Idle,
+    /// A generation is running
+    Generating,
+    /// The last generation failed
+    Attention,
+}
+
+impl TrayStatus {
+    /// Color of the dot drawn over the app icon, as RGBA
+    fn dot_color(self) -> Option<[u8; 4]> {
+        match self {
+            TrayStatus::Idle => None,
+            TrayStatus::Generating => Some([0x3b, 0x82, 0xf6, 0xff]),
+            TrayStatus::Attention => Some([0xef, 0x44, 0x44, 0xff]),
+        }
+    }
+}
+
+impl From<&Activity> for TrayStatus {
+    fn from(activity: &Activity) -> Self {
+        if !activity.generations.is_empty() {
+            TrayStatus::Generating
+        } else if activity.failed {
+            TrayStatus::Attention
+        } else {
+            TrayStatus::Idle
+        }
+    }
+}
+
+/// Latest requested menu rebuild; rebuilds that finish after a newer one
+/// was requested are dropped
+static MENU_REBUILD: AtomicU64 = AtomicU64::new(0);
+
+/// The current menu's progress line, updated every second while shown
+static PROGRESS_ITEM: Mutex<Option<tauri::menu::MenuItem<tauri::Wry>>> = Mutex::new(None);
+
+/// The status the tray icon shows, kept so badge updates can redraw it
+static STATUS: Mutex<TrayStatus> = Mutex::new(TrayStatus::Idle);
+
+/// Badge background and digit colors, as RGBA
+const BADGE_COLOR: [u8; 4] = [0xef, 0x44, 0x44, 0xff];
+const BADGE_TEXT_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+/// 3x5 bitmaps of the badge glyphs, one row per entry, high bit leftmost
+const GLYPHS: [(char, [u8; 5]); 11] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+];
+
+/// Unread notifications, kept in app state
+///
+/// Background work the user didn't start from the window (see [`notify`])
+/// adds to the count; focusing the main window clears it.
+#[derive(Debug, Default)]
+pub struct UnreadCounter(AtomicU32);
+
+impl UnreadCounter {
+    pub fn get(&self) -> u32 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 /// Initialize the system tray icon and menu
 ///
 /// Creates a native system tray icon with a comprehensive menu including:
 /// - Show/Hide window toggle
 /// - Create new project
 /// - Recent projects submenu (last 5 projects from database)
+/// - Favorite agents submenu
 /// - Settings access
 /// - Check for updates
 /// - About information
 /// - Quit application
 ///
+/// The submenus show a "Loading…" item until the database has answered;
+/// nothing here waits on it.
+///
 /// # Arguments
 /// * `app` - The Tauri application handle
 ///
 /// # Returns
 /// * `Result<(), tauri::Error>` - Success or error
 pub fn create_tray(app: &tauri::AppHandle) -> Result<(), tauri::Error> {
-    let menu = build_tray_menu(app)?;
+    let menu = build_tray_menu(app, &TrayLayout::default(), None, None)?;
 
     let _tray = TrayIconBuilder::with_id("main")
         .icon(app.default_window_icon().unwrap().clone())
+        .icon_as_template(cfg!(target_os = "macos"))
         .menu(&menu)
-        .show_menu_on_left_click(true)
+        // The menu is on left-click on macOS and on right-click elsewhere,
+        // where left-click toggles the window
+        .show_menu_on_left_click(cfg!(target_os = "macos"))
         .on_menu_event(handle_menu_event)
         .on_tray_icon_event(handle_tray_event)
         .tooltip("Vibing2 - AI Development Platform")
         .build(app)?;
 
-    Ok(())
+    update_tray_menu(app)
 }
 
 /// Build the system tray menu with all items
 ///
 /// Constructs a complete menu structure including:
 /// - Standard menu items (show/hide, new project, etc.)
-/// - Recent projects submenu with dynamic content from database
+/// - While a generation runs, its progress and controls to cancel it or
+///   pause the queue; "Resume Queue" stays until the queue is resumed
+/// - Recent projects and favorite agents submenus, showing "Loading…" when
+///   their entries are `None`; recent projects only if `layout` shows them
+/// - The layout's quick actions
 /// - Separators for visual organization
 ///
 /// # Arguments
 /// * `app` - The Tauri application handle
+/// * `layout` - The user's tray layout
+/// * `recent` - Recent projects, once loaded
+/// * `favorites` - Favorite agents, once loaded
 ///
 /// # Returns
 /// * `Result<Menu, tauri::Error>` - The constructed menu or error
-fn build_tray_menu(app: &tauri::AppHandle) -> Result<tauri::menu::Menu<tauri::Wry>, tauri::Error> {
-    let app_handle = app.clone();
-
-    // Build recent projects submenu (async operation)
-    let recent_submenu = tauri::async_runtime::block_on(async move {
-        build_recent_projects_submenu(&app_handle).await
-    })?;
+fn build_tray_menu(
+    app: &tauri::AppHandle,
+    layout: &TrayLayout,
+    recent: Option<&[RecentProject]>,
+    favorites: Option<&[crate::agents::Agent]>,
+) -> Result<tauri::menu::Menu<tauri::Wry>, tauri::Error> {
+    let favorites_submenu = build_favorite_agents_submenu(app, favorites)?;
 
     // Build main menu
-    let menu = MenuBuilder::new(app)
+    let mut menu = MenuBuilder::new(app)
         .item(
             &MenuItemBuilder::with_id(MENU_SHOW_HIDE, "Show/Hide Window")
                 .accelerator("Cmd+H")
                 .build(app)?,
         )
-        .separator()
+        .separator();
+
+    // Generation controls
+    let activity = crate::server::streams::current_activity();
+    let progress = match progress_line(&activity) {
+        Some(line) => {
+            let progress = MenuItemBuilder::with_id(MENU_GENERATION_PROGRESS, line)
+                .enabled(false)
+                .build(app)?;
+            menu = menu.item(&progress).item(
+                &MenuItemBuilder::with_id(MENU_CANCEL_GENERATION, "Cancel Current Generation")
+                    .build(app)?,
+            );
+            Some(progress)
+        }
+        None => None,
+    };
+    if let Ok(mut item) = PROGRESS_ITEM.lock() {
+        *item = progress;
+    }
+    if !activity.generations.is_empty() || activity.paused {
+        let text = if activity.paused { "Resume Queue" } else { "Pause Queue" };
+        menu = menu
+            .item(&MenuItemBuilder::with_id(MENU_PAUSE_QUEUE, text).build(app)?)
+            .separator();
+    }
+
+    menu = menu
         .item(
             &MenuItemBuilder::with_id(MENU_NEW_PROJECT, "Create New Project")
                 .accelerator("Cmd+N")
                 .build(app)?,
         )
-        .item(&recent_submenu)
+        .item(
+            &MenuItemBuilder::with_id(MENU_QUICK_CAPTURE, "Quick Prompt…")
+                .accelerator(crate::quick_capture::SHORTCUT)
+                .build(app)?,
+        );
+    if layout.show_recent_projects {
+        menu = menu.item(&build_recent_projects_submenu(app, recent)?);
+    }
+    menu = menu.item(&favorites_submenu);
+
+    if !layout.quick_actions.is_empty() {
+        menu = menu.separator();
+        for action in &layout.quick_actions {
+            let menu_id = format!("{}{}", MENU_QUICK_ACTION_PREFIX, action.route);
+            menu = menu.item(&MenuItemBuilder::with_id(menu_id, truncate_string(&action.label, 40)).build(app)?);
+        }
+    }
+
+    let menu = menu
         .separator()
         .item(
             &MenuItemBuilder::with_id(MENU_SETTINGS, "Settings")
                 .accelerator("Cmd+,")
                 .build(app)?,
         )
+        .item(
+            &CheckMenuItemBuilder::with_id(MENU_LAUNCH_AT_LOGIN, "Launch at Login")
+                .checked(crate::autostart::is_enabled(app))
+                .build(app)?,
+        )
         .item(
             &MenuItemBuilder::with_id(MENU_CHECK_UPDATES, "Check for Updates")
                 .build(app)?,
@@ -130,25 +360,55 @@ fn build_tray_menu(app: &tauri::AppHandle) -> Result<tauri::menu::Menu<tauri::Wr
     Ok(menu)
 }
 
+/// The progress line for the latest running generation: its agent and
+/// elapsed time, and how many others are running
+fn progress_line(activity: &crate::server::streams::Activity) -> Option<String> {
+    let generation = activity.generations.last()?;
+    let mut line = format!("{} — {}", generation.label, format_elapsed(generation.started_at.elapsed()));
+    if activity.generations.len() > 1 {
+        line.push_str(&format!(" (+{} more)", activity.generations.len() - 1));
+    }
+    Some(line)
+}
+
+/// Format a duration as `m:ss`, or `h:mm:ss` past an hour
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Disabled placeholder for a submenu whose entries are still loading
+fn loading_item(app: &tauri::AppHandle) -> Result<tauri::menu::MenuItem<tauri::Wry>, tauri::Error> {
+    MenuItemBuilder::new("Loading…").enabled(false).build(app)
+}
+
 /// Build the recent projects submenu
 ///
-/// Fetches the 5 most recently updated projects from the database
-/// and creates menu items for each. If no projects exist, shows
-/// a disabled "No Recent Projects" item.
+/// Creates a menu item for each of the most recently updated projects.
+/// If no projects exist, shows a disabled "No Recent Projects" item, and
+/// a disabled "Loading…" item while `projects` is `None`.
 ///
 /// # Arguments
 /// * `app` - The Tauri application handle
+/// * `projects` - The recent projects, once loaded
 ///
 /// # Returns
 /// * `Result<Submenu, tauri::Error>` - The submenu or error
-async fn build_recent_projects_submenu(
+fn build_recent_projects_submenu(
     app: &tauri::AppHandle,
+    projects: Option<&[RecentProject]>,
 ) -> Result<tauri::menu::Submenu<tauri::Wry>, tauri::Error> {
     let mut submenu_builder = SubmenuBuilder::new(app, "Recent Projects");
 
-    // Fetch recent projects from database
-    match fetch_recent_projects().await {
-        Ok(projects) if !projects.is_empty() => {
+    match projects {
+        None => {
+            submenu_builder = submenu_builder.item(&loading_item(app)?);
+        }
+        Some(projects) if !projects.is_empty() => {
             // Add menu item for each recent project
             for project in projects {
                 let menu_id = format!("{}{}", MENU_RECENT_PREFIX, project.id);
@@ -166,8 +426,8 @@ async fn build_recent_projects_submenu(
                 );
             }
         }
-        Ok(_) | Err(_) => {
-            // No projects or error - show disabled item
+        Some(_) => {
+            // No projects - show disabled item
             submenu_builder = submenu_builder.item(
                 &MenuItemBuilder::new("No Recent Projects")
                     .enabled(false)
@@ -179,14 +439,71 @@ async fn build_recent_projects_submenu(
     submenu_builder.build()
 }
 
+/// Build the favorite agents submenu
+///
+/// Lists the local user's favorite agents; clicking one starts a new
+/// project with that agent. Shows a disabled "No Favorite Agents" item
+/// when there are none, and a disabled "Loading…" item while `agents` is
+/// `None`.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle
+/// * `agents` - The favorite agents, once loaded
+///
+/// # Returns
+/// * `Result<Submenu, tauri::Error>` - The submenu or error
+fn build_favorite_agents_submenu(
+    app: &tauri::AppHandle,
+    agents: Option<&[crate::agents::Agent]>,
+) -> Result<tauri::menu::Submenu<tauri::Wry>, tauri::Error> {
+    let mut submenu_builder = SubmenuBuilder::new(app, "New Project with Agent");
+
+    match agents {
+        None => {
+            submenu_builder = submenu_builder.item(&loading_item(app)?);
+        }
+        Some(agents) if !agents.is_empty() => {
+            for agent in agents {
+                let menu_id = format!("{}{}", MENU_FAVORITE_AGENT_PREFIX, agent.id);
+                let menu_text = format!("{} {}", agent.icon, truncate_string(&agent.name, 40));
+
+                submenu_builder = submenu_builder.item(
+                    &MenuItemBuilder::with_id(&menu_id, menu_text)
+                        .build(app)?
+                );
+            }
+        }
+        Some(_) => {
+            submenu_builder = submenu_builder.item(
+                &MenuItemBuilder::new("No Favorite Agents")
+                    .enabled(false)
+                    .build(app)?
+            );
+        }
+    }
+
+    submenu_builder.build()
+}
+
+/// Fetch the local user's favorite agents from the database
+async fn fetch_favorite_agents() -> Result<Vec<crate::agents::Agent>, Box<dyn std::error::Error + Send + Sync>> {
+    let pool = database::get_pool().await?;
+    let filter = crate::agents::AgentFilter {
+        favorites_only: Some(true),
+        ..Default::default()
+    };
+
+    Ok(crate::agents::list(&pool, Some("local-user"), &filter).await?)
+}
+
 /// Fetch recent projects from the database
 ///
-/// Retrieves the 5 most recently updated projects for the local user
+/// Retrieves the `limit` most recently updated projects for the local user
 /// ordered by update timestamp in descending order.
 ///
 /// # Returns
-/// * `Result<Vec<RecentProject>, Box<dyn std::error::Error>>` - Projects or error
-async fn fetch_recent_projects() -> Result<Vec<RecentProject>, Box<dyn std::error::Error>> {
+/// * `Result<Vec<RecentProject>, Box<dyn std::error::Error + Send + Sync>>` - Projects or error
+async fn fetch_recent_projects(limit: u32) -> Result<Vec<RecentProject>, Box<dyn std::error::Error + Send + Sync>> {
     let pool = database::get_pool().await?;
 
     // Use query instead of query_as! to avoid compile-time SQL checking
@@ -196,9 +513,10 @@ async fn fetch_recent_projects() -> Result<Vec<RecentProject>, Box<dyn std::erro
         FROM projects
         WHERE user_id = 'local-user'
         ORDER BY updated_at DESC
-        LIMIT 5
+        LIMIT ?
         "#
     )
+    .bind(limit)
     .fetch_all(&*pool)
     .await?;
 
@@ -221,29 +539,22 @@ async fn fetch_recent_projects() -> Result<Vec<RecentProject>, Box<dyn std::erro
 /// Routes menu events to appropriate handlers based on the menu item ID:
 /// - Show/Hide: Toggle main window visibility
 /// - New Project: Navigate to create project page
+/// - Quick Prompt: Open the quick-capture window
 /// - Settings: Navigate to settings page
 /// - Check Updates: Check for application updates
 /// - About: Show about dialog
 /// - Recent Project: Load and navigate to selected project
+/// - Favorite Agent: Start a new project with the selected agent
+/// - Cancel Generation: Stop the latest running generation
+/// - Pause/Resume Queue: Hold queued generations back, or let them go
+/// - Quick Action: Open the action's route in the window
 ///
 /// # Arguments
 /// * `app` - The Tauri application handle
 /// * `event` - The menu event containing the clicked item ID
 fn handle_menu_event(app: &tauri::AppHandle, event: MenuEvent) {
     match event.id().as_ref() {
-        MENU_SHOW_HIDE => {
-            if let Some(window) = app.get_webview_window("main") {
-                match window.is_visible() {
-                    Ok(true) => {
-                        let _ = window.hide();
-                    }
-                    Ok(false) | Err(_) => {
-                        let _ = window.show();
-                        let _ = window.set_focus();
-                    }
-                }
-            }
-        }
+        MENU_SHOW_HIDE => toggle_main_window(app),
 
         MENU_NEW_PROJECT => {
             if let Some(window) = app.get_webview_window("main") {
@@ -254,6 +565,10 @@ fn handle_menu_event(app: &tauri::AppHandle, event: MenuEvent) {
             }
         }
 
+        MENU_QUICK_CAPTURE => {
+            crate::quick_capture::open(app);
+        }
+
         MENU_SETTINGS => {
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.show();
@@ -263,6 +578,17 @@ fn handle_menu_event(app: &tauri::AppHandle, event: MenuEvent) {
             }
         }
 
+        MENU_LAUNCH_AT_LOGIN => {
+            let enabled = crate::autostart::is_enabled(app);
+            if let Err(e) = crate::autostart::set_enabled(app, !enabled) {
+                eprintln!("{}", e);
+            }
+            // Keep the check mark in line if the change failed
+            if let Err(e) = update_tray_menu(app) {
+                eprintln!("Failed to update tray menu: {}", e);
+            }
+        }
+
         MENU_CHECK_UPDATES => {
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.show();
@@ -276,33 +602,86 @@ fn handle_menu_event(app: &tauri::AppHandle, event: MenuEvent) {
             show_about_dialog(app);
         }
 
+        MENU_CANCEL_GENERATION => {
+            // The progress line shows the latest generation
+            let activity = crate::server::streams::current_activity();
+            if let Some(generation) = activity.generations.last() {
+                crate::server::streams::cancel_generation(&generation.id);
+            }
+        }
+
+        MENU_PAUSE_QUEUE => {
+            let paused = crate::server::streams::current_activity().paused;
+            crate::server::streams::set_queue_paused(!paused);
+        }
+
         id if id.starts_with(MENU_RECENT_PREFIX) => {
             // Extract project ID and load project
             let project_id = id.trim_start_matches(MENU_RECENT_PREFIX);
             load_recent_project(app, project_id);
         }
 
+        id if id.starts_with(MENU_FAVORITE_AGENT_PREFIX) => {
+            let agent_id = id.trim_start_matches(MENU_FAVORITE_AGENT_PREFIX);
+            new_project_with_agent(app, agent_id);
+        }
+
+        id if id.starts_with(MENU_QUICK_ACTION_PREFIX) => {
+            let route = id.trim_start_matches(MENU_QUICK_ACTION_PREFIX);
+            open_route(app, route);
+        }
+
         _ => {}
     }
 }
 
 /// Handle tray icon click events
 ///
-/// On left-click with primary mouse button: Toggle main window visibility
-/// This provides quick access to show/hide the application window.
+/// Follows each platform's convention:
+/// - macOS: left-click opens the menu (see `create_tray`)
+/// - Windows: left-click toggles the main window, right-click opens the menu
+/// - Everywhere: double-click shows and focuses the main window
+///
+/// Linux doesn't report tray clicks; any click opens the menu there, which
+/// starts with "Show/Hide Window".
 ///
 /// # Arguments
-/// * `app` - The Tauri application handle
+/// * `tray` - The tray icon
 /// * `event` - The tray icon event
-fn handle_tray_event(_tray: &tauri::tray::TrayIcon, event: TrayIconEvent) {
-    if let TrayIconEvent::Click {
-        button: MouseButton::Left,
-        button_state: MouseButtonState::Up,
-        ..
-    } = event
-    {
-        // Left-click toggles window visibility (handled by menu_on_left_click)
-        // This is already handled by the menu, but we can add additional logic here if needed
+fn handle_tray_event(tray: &tauri::tray::TrayIcon, event: TrayIconEvent) {
+    let app = tray.app_handle();
+    match event {
+        TrayIconEvent::Click {
+            button: MouseButton::Left,
+            button_state: MouseButtonState::Up,
+            ..
+        } if !cfg!(target_os = "macos") => toggle_main_window(app),
+        TrayIconEvent::DoubleClick {
+            button: MouseButton::Left,
+            ..
+        } => show_main_window(app),
+        _ => {}
+    }
+}
+
+/// Hide the main window if it's showing, otherwise show and focus it
+fn toggle_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        match window.is_visible() {
+            Ok(true) => {
+                let _ = window.hide();
+            }
+            Ok(false) | Err(_) => show_main_window(app),
+        }
+    }
+}
+
+/// Show, restore, and focus the main window
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
     }
 }
 
@@ -392,72 +771,368 @@ fn load_recent_project(app: &tauri::AppHandle, project_id: &str) {
     }
 }
 
+/// Start a new project with an agent
+///
+/// Shows the main window and emits `new-project-with-agent` so the
+/// frontend opens the create page with the agent selected.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle
+/// * `agent_id` - The ID of the agent to start with
+fn new_project_with_agent(app: &tauri::AppHandle, agent_id: &str) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        let _ = window.emit("new-project-with-agent", agent_id.to_string());
+    }
+}
+
 /// Update the tray menu dynamically
 ///
-/// Rebuilds the tray menu with updated recent projects.
+/// Rebuilds the tray menu with updated recent projects and favorite agents.
 /// Call this function when projects are created, updated, or deleted
 /// to keep the menu in sync with the database.
 ///
+/// Returns right away: the database is queried in the background and the
+/// new menu swapped in with `set_menu` once it's ready. The current menu
+/// stays up meanwhile, and a rebuild overtaken by a newer one is dropped.
+///
 /// # Arguments
 /// * `app` - The Tauri application handle
 ///
 /// # Returns
 /// * `Result<(), tauri::Error>` - Success or error
 pub fn update_tray_menu(app: &tauri::AppHandle) -> Result<(), tauri::Error> {
-    // In Tauri 2.0, we need to recreate the tray icon with new menu
-    // This is a workaround as direct menu updates may not be available
-    let menu = build_tray_menu(app)?;
+    let rebuild = MENU_REBUILD.fetch_add(1, Ordering::SeqCst) + 1;
+    let app = app.clone();
 
-    // Try to get existing tray and update its menu
+    tauri::async_runtime::spawn(async move {
+        let layout = match database::get_pool().await {
+            Ok(pool) => load_layout(&pool).await,
+            Err(_) => TrayLayout::default(),
+        };
+        let recent = if layout.show_recent_projects {
+            fetch_recent_projects(layout.recent_projects).await.unwrap_or_else(|e| {
+                eprintln!("Failed to load recent projects: {}", e);
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+        let favorites = fetch_favorite_agents().await.unwrap_or_else(|e| {
+            eprintln!("Failed to load favorite agents: {}", e);
+            Vec::new()
+        });
+        if MENU_REBUILD.load(Ordering::SeqCst) != rebuild {
+            return;
+        }
+
+        let Some(tray) = app.tray_by_id("main") else {
+            return;
+        };
+        let menu = build_tray_menu(&app, &layout, Some(&recent), Some(&favorites));
+        if let Err(e) = menu.and_then(|menu| tray.set_menu(Some(menu))) {
+            eprintln!("Failed to update tray menu: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Keep the tray tooltip in sync with credential state
+///
+/// Listens for the `auth:*` events emitted by the auth module and updates
+/// the tooltip so the tray reflects sign-in, lock, and invalid-key states
+/// without polling.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle
+pub fn listen_auth_events(app: &tauri::AppHandle) {
+    use tauri::Listener;
+
+    let states = [
+        ("auth:signed-in", "Vibing2 - AI Development Platform"),
+        ("auth:unlocked", "Vibing2 - AI Development Platform"),
+        ("auth:signed-out", "Vibing2 - Not signed in"),
+        ("auth:locked", "Vibing2 - Credentials locked"),
+        ("auth:key-invalid", "Vibing2 - API key needs attention"),
+    ];
+
+    for (event, tooltip) in states {
+        let handle = app.clone();
+        app.listen_any(event, move |_| {
+            if let Some(tray) = handle.tray_by_id("main") {
+                let _ = tray.set_tooltip(Some(tooltip));
+            }
+        });
+    }
+}
+
+/// Show a short notice in the tray tooltip
+///
+/// Used for background work the user didn't start from the window, such as
+/// scheduled agent runs. The next auth event restores the usual tooltip.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle
+/// * `message` - The notice, shown after the app name
+pub fn notify(app: &tauri::AppHandle, message: &str) {
     if let Some(tray) = app.tray_by_id("main") {
-        tray.set_menu(Some(menu))?;
+        let _ = tray.set_tooltip(Some(&format!("Vibing2 - {}", message)));
     }
 
-    Ok(())
+    if let Some(unread) = app.try_state::<std::sync::Arc<UnreadCounter>>() {
+        let count = unread.0.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Err(e) = set_tray_badge(app, count) {
+            eprintln!("Failed to update unread badge: {}", e);
+        }
+    }
 }
 
-/// Set tray icon badge (macOS only)
+/// Keep the tray icon and menu in sync with running generations
+///
+/// Shows the generating icon while any generation runs. Once the last one
+/// finishes, the icon goes back to idle, or shows attention if that
+/// generation failed, until the next one starts. The menu is rebuilt as
+/// generations start and finish or the queue is paused, and its progress
+/// line ticks every second.
 ///
-/// Displays a badge on the tray icon to indicate notifications
-/// or unread items. Pass `None` to remove the badge.
+/// # Arguments
+/// * `app` - The Tauri application handle
+pub fn listen_generation_events(app: &tauri::AppHandle) {
+    let handle = app.clone();
+    let mut activity = crate::server::streams::subscribe_activity();
+    tauri::async_runtime::spawn(async move {
+        let mut shown = (0, false);
+        while activity.changed().await.is_ok() {
+            let current = activity.borrow_and_update().clone();
+            if let Err(e) = set_status(&handle, TrayStatus::from(&current)) {
+                eprintln!("Failed to update tray status: {}", e);
+            }
+
+            let menu_state = (current.generations.len(), current.paused);
+            if menu_state != shown {
+                shown = menu_state;
+                if let Err(e) = update_tray_menu(&handle) {
+                    eprintln!("Failed to update tray menu: {}", e);
+                }
+            }
+        }
+    });
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            let Some(line) = progress_line(&crate::server::streams::current_activity()) else {
+                continue;
+            };
+            let item = PROGRESS_ITEM.lock().ok().and_then(|item| item.clone());
+            if let Some(item) = item {
+                let _ = item.set_text(line);
+            }
+        }
+    });
+}
+
+/// Switch the tray icon to show a status
 ///
-/// Note: Badge display is platform-specific. macOS shows badges,
-/// other platforms may ignore this.
+/// Idle shows the plain app icon; the other states add a colored dot. On
+/// macOS the idle and generating icons are template images that follow the
+/// menu bar's appearance, while attention keeps its color so it stands out.
+/// On Linux the unread count is drawn over the icon as well. Emits
+/// `tray-status` with the new status.
 ///
 /// # Arguments
 /// * `app` - The Tauri application handle
-/// * `badge` - Optional badge text (typically a number)
+/// * `status` - The status to show
 ///
 /// # Returns
 /// * `Result<(), tauri::Error>` - Success or error
-#[cfg(target_os = "macos")]
-pub fn set_tray_badge(
-    app: &tauri::AppHandle,
-    badge: Option<&str>,
-) -> Result<(), tauri::Error> {
-    if let Some(tray) = app.tray_by_id("main") {
-        tray.set_icon_as_template(true)?;
-        // Note: Tauri 2.0 doesn't have direct badge support yet
-        // This would need platform-specific implementation
-        // For now, we can use tooltip to show notifications
-        if let Some(count) = badge {
-            tray.set_tooltip(Some(&format!("Vibing2 - {} notifications", count)))?;
-        } else {
-            tray.set_tooltip(Some("Vibing2 - AI Development Platform"))?;
+pub fn set_status(app: &tauri::AppHandle, status: TrayStatus) -> Result<(), tauri::Error> {
+    if let Ok(mut current) = STATUS.lock() {
+        *current = status;
+    }
+    refresh_icon(app)?;
+
+    let _ = app.emit("tray-status", status);
+    Ok(())
+}
+
+/// Redraw the tray icon for the current status (and, on Linux, the unread
+/// count)
+fn refresh_icon(app: &tauri::AppHandle) -> Result<(), tauri::Error> {
+    let (Some(tray), Some(icon)) = (app.tray_by_id("main"), app.default_window_icon()) else {
+        return Ok(());
+    };
+    let status = STATUS.lock().map(|status| *status).unwrap_or(TrayStatus::Idle);
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+
+    if let Some(color) = status.dot_color() {
+        draw_dot(&mut rgba, width, height, color);
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(unread) = app.try_state::<std::sync::Arc<UnreadCounter>>() {
+        if unread.get() > 0 {
+            let radius = width.min(height) as f32 * 0.3;
+            let center = (width as f32 - radius, radius);
+            draw_badge(&mut rgba, width, height, center, radius, &badge_text(unread.get()));
         }
     }
+
+    tray.set_icon(Some(tauri::image::Image::new_owned(rgba, width, height)))?;
+    #[cfg(target_os = "macos")]
+    tray.set_icon_as_template(status != TrayStatus::Attention)?;
     Ok(())
 }
 
-#[cfg(not(target_os = "macos"))]
-pub fn set_tray_badge(
-    _app: &tauri::AppHandle,
-    _badge: Option<&str>,
-) -> Result<(), tauri::Error> {
-    // No-op on non-macOS platforms
+/// Draw a filled dot in the bottom-right corner of an RGBA image
+fn draw_dot(rgba: &mut [u8], width: u32, height: u32, color: [u8; 4]) {
+    let radius = width.min(height) as f32 * 0.2;
+    let center = (width as f32 - radius - 1.0, height as f32 - radius - 1.0);
+    fill_circle(rgba, width, height, center, radius, color);
+}
+
+fn fill_circle(rgba: &mut [u8], width: u32, height: u32, (cx, cy): (f32, f32), radius: f32, color: [u8; 4]) {
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                set_pixel(rgba, width, x, y, color);
+            }
+        }
+    }
+}
+
+fn set_pixel(rgba: &mut [u8], width: u32, x: u32, y: u32, color: [u8; 4]) {
+    let offset = ((y * width + x) * 4) as usize;
+    if let Some(pixel) = rgba.get_mut(offset..offset + 4) {
+        pixel.copy_from_slice(&color);
+    }
+}
+
+/// Badge text for an unread count: the count, or `9+` past nine
+fn badge_text(count: u32) -> String {
+    if count > 9 {
+        "9+".to_string()
+    } else {
+        count.to_string()
+    }
+}
+
+/// Draw a badge: a filled circle with `text` centered in it, in the
+/// [`GLYPHS`] font scaled to fit
+fn draw_badge(rgba: &mut [u8], width: u32, height: u32, center: (f32, f32), radius: f32, text: &str) {
+    fill_circle(rgba, width, height, center, radius, BADGE_COLOR);
+
+    let glyphs: Vec<[u8; 5]> = text
+        .chars()
+        .filter_map(|c| GLYPHS.iter().find(|(glyph, _)| *glyph == c).map(|(_, rows)| *rows))
+        .collect();
+    if glyphs.is_empty() {
+        return;
+    }
+    // Glyphs are 3 wide with a 1 pixel gap, and fill about 60% of the circle
+    let columns = glyphs.len() as u32 * 4 - 1;
+    let scale = ((radius * 1.2) / columns.max(5) as f32).floor().max(1.0) as u32;
+    let left = (center.0 - (columns * scale) as f32 / 2.0).round().max(0.0) as u32;
+    let top = (center.1 - (5 * scale) as f32 / 2.0).round().max(0.0) as u32;
+
+    for (index, rows) in glyphs.iter().enumerate() {
+        let glyph_left = left + index as u32 * 4 * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = glyph_left + column * scale + dx;
+                        let y = top + row as u32 * scale + dy;
+                        if x < width && y < height {
+                            set_pixel(rgba, width, x, y, BADGE_TEXT_COLOR);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Show the main window at `route`
+///
+/// # Arguments
+/// * `app` - The Tauri application handle
+/// * `route` - Route in the app, e.g. `/projects`
+fn open_route(app: &tauri::AppHandle, route: &str) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        // Quoted as a JS string so the route can't break out of it
+        if let Ok(route) = serde_json::to_string(route) {
+            let _ = window.eval(&format!("window.location.href = {}", route));
+        }
+    }
+}
+
+/// Show an unread count on the app's icon
+///
+/// Sets the count in [`UnreadCounter`] and shows it the way each platform
+/// does: the dock badge on macOS, an overlay on the taskbar button on
+/// Windows, and a badge drawn over the tray icon on Linux, where the
+/// taskbar badge is also set for desktops that support one. A count of 0
+/// removes the badge. Emits `unread-count` with the new count.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle
+/// * `count` - Unread notifications
+///
+/// # Returns
+/// * `Result<(), tauri::Error>` - Success or error
+pub fn set_tray_badge(app: &tauri::AppHandle, count: u32) -> Result<(), tauri::Error> {
+    if let Some(unread) = app.try_state::<std::sync::Arc<UnreadCounter>>() {
+        unread.0.store(count, Ordering::SeqCst);
+    }
+
+    if let Some(window) = app.get_webview_window("main") {
+        #[cfg(any(target_os = "macos", target_os = "linux"))]
+        window.set_badge_count((count > 0).then_some(count as i64))?;
+
+        #[cfg(target_os = "windows")]
+        window.set_overlay_icon((count > 0).then(|| badge_icon(count)))?;
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+        let _ = window;
+    }
+
+    #[cfg(target_os = "linux")]
+    refresh_icon(app)?;
+
+    let _ = app.emit("unread-count", count);
     Ok(())
 }
 
+/// Clear the unread count; called when the main window is focused
+pub fn clear_unread(app: &tauri::AppHandle) {
+    let unread = app.try_state::<std::sync::Arc<UnreadCounter>>().map(|unread| unread.get());
+    if unread.unwrap_or_default() > 0 {
+        if let Err(e) = set_tray_badge(app, 0) {
+            eprintln!("Failed to clear unread badge: {}", e);
+        }
+    }
+}
+
+/// A standalone badge image for the Windows taskbar overlay
+#[cfg(target_os = "windows")]
+fn badge_icon(count: u32) -> tauri::image::Image<'static> {
+    const SIZE: u32 = 32;
+    let mut rgba = vec![0u8; (SIZE * SIZE * 4) as usize];
+    let radius = SIZE as f32 / 2.0;
+    draw_badge(&mut rgba, SIZE, SIZE, (radius, radius), radius, &badge_text(count));
+    tauri::image::Image::new_owned(rgba, SIZE, SIZE)
+}
+
 /// Truncate a string to a maximum length with ellipsis
 ///
 /// # Arguments
@@ -485,6 +1160,82 @@ mod tests {
         assert_eq!(truncate_string("Exact", 5), "Exact");
     }
 
+    #[test]
+    fn test_status_from_activity() {
+        let idle = Activity::default();
+        assert_eq!(TrayStatus::from(&idle), TrayStatus::Idle);
+        let failed = Activity {
+            failed: true,
+            ..Activity::default()
+        };
+        assert_eq!(TrayStatus::from(&failed), TrayStatus::Attention);
+
+        // A running generation outranks the last failure
+        let _generation = crate::server::streams::track_activity("UI Designer".to_string());
+        let running = Activity {
+            failed: true,
+            ..crate::server::streams::current_activity()
+        };
+        assert_eq!(TrayStatus::from(&running), TrayStatus::Generating);
+        assert!(progress_line(&running).unwrap().contains(" — 0:0"));
+    }
+
+    #[test]
+    fn test_layout_validation() {
+        let layout: TrayLayout = serde_json::from_str(r#"{"recent_projects": 50}"#).unwrap();
+        assert!(layout.show_recent_projects);
+        assert_eq!(layout.validate().unwrap().recent_projects, MAX_RECENT_PROJECTS);
+
+        let with_action = |label: &str, route: &str| TrayLayout {
+            quick_actions: vec![QuickAction {
+                label: label.to_string(),
+                route: route.to_string(),
+            }],
+            ..TrayLayout::default()
+        };
+        let layout = with_action(" Agents ", " /agents?tab=favorites ").validate().unwrap();
+        assert_eq!(layout.quick_actions[0].label, "Agents");
+        assert_eq!(layout.quick_actions[0].route, "/agents?tab=favorites");
+
+        assert!(with_action("", "/agents").validate().is_err());
+        assert!(with_action("Site", "https://example.com").validate().is_err());
+        assert!(with_action("Site", "//example.com").validate().is_err());
+        assert!(with_action("Site", "/a b").validate().is_err());
+    }
+
+    #[test]
+    fn test_draw_badge() {
+        assert_eq!(badge_text(3), "3");
+        assert_eq!(badge_text(42), "9+");
+
+        let mut rgba = vec![0u8; 32 * 32 * 4];
+        draw_badge(&mut rgba, 32, 32, (16.0, 16.0), 16.0, "1");
+        let pixels: Vec<&[u8]> = rgba.chunks(4).collect();
+        assert_eq!(pixels[0], [0, 0, 0, 0]);
+        assert_eq!(pixels[16 * 32 + 4], BADGE_COLOR);
+        // The "1" is drawn in the middle of the badge
+        assert!(pixels[14 * 32..18 * 32].iter().any(|pixel| *pixel == BADGE_TEXT_COLOR));
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_secs(5)), "0:05");
+        assert_eq!(format_elapsed(Duration::from_secs(125)), "2:05");
+        assert_eq!(format_elapsed(Duration::from_secs(3725)), "1:02:05");
+    }
+
+    #[test]
+    fn test_draw_dot() {
+        let mut rgba = vec![0u8; 32 * 32 * 4];
+        let red = [0xff, 0, 0, 0xff];
+        draw_dot(&mut rgba, 32, 32, red);
+
+        let pixel = |x: usize, y: usize| &rgba[(y * 32 + x) * 4..(y * 32 + x) * 4 + 4];
+        assert_eq!(pixel(25, 25), red);
+        assert_eq!(pixel(0, 0), [0, 0, 0, 0]);
+        assert_eq!(pixel(31, 0), [0, 0, 0, 0]);
+    }
+
     #[test]
     fn test_menu_id_constants() {
         assert!(MENU_SHOW_HIDE.len() > 0);
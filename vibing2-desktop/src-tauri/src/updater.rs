@@ -1,8 +1,13 @@
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tauri::{AppHandle, Manager, Emitter};
-use tokio::sync::Mutex;
-use tokio::time::{interval, Duration};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use tokio::sync::{watch, Mutex};
+use tokio::time::{interval, Duration, Instant};
 
 /// Update status information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,10 +27,19 @@ pub enum UpdateStatus {
         total: u64,
         percentage: f64,
     },
+    /// Download paused; resumes from `downloaded`
+    Paused {
+        downloaded: u64,
+        total: u64,
+    },
     /// Update downloaded and ready to install
     Downloaded {
         version: String,
     },
+    /// Update downloaded and installing when the app quits
+    Deferred {
+        version: String,
+    },
     /// Update is being installed
     Installing {
         version: String,
@@ -36,8 +50,12 @@ pub enum UpdateStatus {
     },
 }
 
+/// Setting holding the [`UpdateConfig`] as JSON
+pub const CONFIG_SETTING: &str = "update_config";
+
 /// Update configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UpdateConfig {
     /// Check for updates on launch (after delay)
     pub check_on_launch: bool,
@@ -47,10 +65,37 @@ pub struct UpdateConfig {
     pub check_interval_hours: u64,
     /// Enable automatic download
     pub auto_download: bool,
-    /// Enable automatic installation
+    /// Install and restart once downloaded, outside quiet hours and never
+    /// during a generation
     pub auto_install: bool,
+    /// Install downloaded updates when the app quits instead of restarting
+    /// for them
+    pub install_on_quit: bool,
+    /// Hours when updates are never installed automatically
+    pub quiet_hours: Option<QuietHours>,
     /// Show notifications
     pub show_notifications: bool,
+    /// Download bandwidth cap in KiB/s; unlimited when unset
+    pub max_download_kbps: Option<u64>,
+}
+
+/// A daily window in local time, from `start` up to `end` (hours, 0-23);
+/// wraps past midnight when `end` is before `start`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl QuietHours {
+    /// Whether `hour` falls in the window
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&hour)
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
 }
 
 impl Default for UpdateConfig {
@@ -61,16 +106,54 @@ impl Default for UpdateConfig {
             check_interval_hours: 6,
             auto_download: true,
             auto_install: false, // Require user confirmation for installation
+            install_on_quit: false,
+            quiet_hours: None,
             show_notifications: true,
+            max_download_kbps: None,
         }
     }
 }
 
+/// The saved update config, or the defaults
+pub async fn load_config(pool: &sqlx::SqlitePool) -> UpdateConfig {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(CONFIG_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+pub async fn save_config(pool: &sqlx::SqlitePool, config: &UpdateConfig) -> Result<(), String> {
+    let value = serde_json::to_string(config).map_err(|e| e.to_string())?;
+    sqlx::query(
+        r#"
+        INSERT INTO settings (id, key, value, updated_at)
+        VALUES (?, ?, ?, datetime('now'))
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(format!("setting-{}", CONFIG_SETTING))
+    .bind(CONFIG_SETTING)
+    .bind(value)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save setting {}: {}", CONFIG_SETTING, e))?;
+    Ok(())
+}
+
 /// Updater manager
 pub struct UpdaterManager {
     app: AppHandle,
     config: Arc<Mutex<UpdateConfig>>,
     current_status: Arc<Mutex<UpdateStatus>>,
+    /// Whether update downloads are paused
+    paused: watch::Sender<bool>,
+    /// Downloaded update waiting for the app to quit
+    pending_install: Mutex<Option<Update>>,
 }
 
 impl UpdaterManager {
@@ -80,6 +163,8 @@ impl UpdaterManager {
             app,
             config: Arc::new(Mutex::new(UpdateConfig::default())),
             current_status: Arc::new(Mutex::new(UpdateStatus::UpToDate)),
+            paused: watch::channel(false).0,
+            pending_install: Mutex::new(None),
         }
     }
 
@@ -98,7 +183,8 @@ impl UpdaterManager {
             let delay = config.launch_delay;
             tokio::spawn(async move {
                 tokio::time::sleep(Duration::from_secs(delay)).await;
-                if let Err(e) = check_for_updates_internal(app.clone()).await {
+                crate::power::wait_active().await;
+                if let Err(e) = check_for_updates_internal(app.clone(), false).await {
                     eprintln!("Launch update check failed: {}", e);
                 }
             });
@@ -110,10 +196,13 @@ impl UpdaterManager {
         tokio::spawn(async move {
             let mut interval = interval(Duration::from_secs(interval_hours * 3600));
             interval.tick().await; // Skip first tick
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
             loop {
                 interval.tick().await;
-                if let Err(e) = check_for_updates_internal(app.clone()).await {
+                // Not while asleep or saving power
+                crate::power::wait_active().await;
+                if let Err(e) = check_for_updates_internal(app.clone(), false).await {
                     eprintln!("Background update check failed: {}", e);
                 }
             }
@@ -125,6 +214,16 @@ impl UpdaterManager {
         self.current_status.lock().await.clone()
     }
 
+    /// Pause the update download, keeping what's been downloaded
+    pub fn pause_download(&self) {
+        self.paused.send_replace(true);
+    }
+
+    /// Continue a paused update download where it stopped
+    pub fn resume_download(&self) {
+        self.paused.send_replace(false);
+    }
+
     /// Update the current status
     pub async fn set_status(&self, status: UpdateStatus) {
         *self.current_status.lock().await = status.clone();
@@ -136,10 +235,22 @@ impl UpdaterManager {
     }
 }
 
+/// The updater, going through the proxy from settings
+fn updater(app: &AppHandle) -> tauri_plugin_updater::Result<tauri_plugin_updater::Updater> {
+    let mut builder = app.updater_builder();
+    if let Some(url) = crate::proxy::url() {
+        builder = builder.proxy(url);
+    }
+    builder.build()
+}
+
 /// Check for updates (internal implementation)
-async fn check_for_updates_internal(app: AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+///
+/// Downloads an available update when `download` is set or the config's
+/// `auto_download` is on.
+async fn check_for_updates_internal(app: AppHandle, download: bool) -> Result<(), Box<dyn std::error::Error>> {
     // Get the updater handle
-    let handle = app.updater_builder().build()?;
+    let handle = updater(&app)?;
 
     // Check for updates
     match handle.check().await {
@@ -148,7 +259,10 @@ async fn check_for_updates_internal(app: AppHandle) -> Result<(), Box<dyn std::e
 
             // Extract release notes and date
             let release_notes = update.body.clone().unwrap_or_else(|| "No release notes available".to_string());
-            let release_date = update.date.clone().unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+            let release_date = update
+                .date
+                .map(|date| date.to_string())
+                .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
 
             // Emit update available event
             let _ = app.emit("update-available", UpdateStatus::Available {
@@ -157,42 +271,18 @@ async fn check_for_updates_internal(app: AppHandle) -> Result<(), Box<dyn std::e
                 release_date: release_date.clone(),
             });
 
-            println!("Starting download...");
+            if !download && !update_config(&app).await.auto_download {
+                println!("Automatic download is off; not downloading {}", update.version);
+                return Ok(());
+            }
 
-            // Download with progress tracking
-            let mut downloaded = 0u64;
-            let total = update.content_length.unwrap_or(0);
-
-            update
-                .download(
-                    |chunk_length, _content_length| {
-                        downloaded += chunk_length as u64;
-                        let percentage = if total > 0 {
-                            (downloaded as f64 / total as f64) * 100.0
-                        } else {
-                            0.0
-                        };
-
-                        // Emit download progress
-                        let _ = app.emit("update-download-progress", UpdateStatus::Downloading {
-                            downloaded,
-                            total,
-                            percentage,
-                        });
-                    },
-                    || {
-                        println!("Download finished");
-                    },
-                )
-                .await?;
-
-            // Emit download complete event
-            let _ = app.emit("update-downloaded", UpdateStatus::Downloaded {
-                version: update.version.clone(),
-            });
+            println!("Starting download...");
 
+            fetch_bundle(&app, &update).await?;
             println!("Update downloaded successfully");
 
+            apply_install_policy(&app, update).await;
+
             Ok(())
         }
         Ok(None) => {
@@ -210,10 +300,146 @@ async fn check_for_updates_internal(app: AppHandle) -> Result<(), Box<dyn std::e
     }
 }
 
+/// Download an update's bundle and keep it, reporting progress and failure
+/// as `update-*` events
+async fn fetch_bundle(app: &AppHandle, update: &Update) -> Result<Vec<u8>, String> {
+    // Download with progress tracking, from a patch when one applies
+    let progress_app = app.clone();
+    let downloaded = download_bundle(app, update, move |downloaded, total| {
+        let percentage = if total > 0 {
+            (downloaded as f64 / total as f64) * 100.0
+        } else {
+            0.0
+        };
+
+        // Emit download progress
+        let _ = progress_app.emit("update-download-progress", UpdateStatus::Downloading {
+            downloaded,
+            total,
+            percentage,
+        });
+    })
+    .await
+    .map_err(|e| e.to_string());
+
+    // Keep the bundle to install, and to patch from next release
+    let cached = downloaded.and_then(|bundle| {
+        cache_bundle(app, &update.version, &bundle).map_err(|e| e.to_string())?;
+        Ok(bundle)
+    });
+
+    match cached {
+        Ok(bundle) => {
+            println!("Download finished");
+            let _ = app.emit("update-downloaded", UpdateStatus::Downloaded {
+                version: update.version.clone(),
+            });
+            Ok(bundle)
+        }
+        Err(message) => {
+            eprintln!("Update download error: {}", message);
+            let _ = app.emit("update-error", UpdateStatus::Error {
+                message: message.clone(),
+            });
+            Err(message)
+        }
+    }
+}
+
+// ============================================================================
+// Install Policy
+// ============================================================================
+//
+// Once an update is downloaded, `UpdateConfig` decides what happens:
+//
+// - `install_on_quit`: the update is kept and installed by `install_pending`
+//   as the app exits, without a restart prompt.
+// - `auto_install`: the app installs and restarts as soon as no generation
+//   is running and it's outside `quiet_hours`.
+// - Otherwise the user installs it with `install_update`.
+
+/// How often a waiting automatic install checks again
+const INSTALL_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The updater's configuration, or the defaults before it's set up
+async fn update_config(app: &AppHandle) -> UpdateConfig {
+    match app.try_state::<Arc<UpdaterManager>>() {
+        Some(manager) => manager.config.lock().await.clone(),
+        None => UpdateConfig::default(),
+    }
+}
+
+/// Install a downloaded update as the config says
+async fn apply_install_policy(app: &AppHandle, update: Update) {
+    let config = update_config(app).await;
+
+    if config.install_on_quit {
+        let version = update.version.clone();
+        if let Some(manager) = app.try_state::<Arc<UpdaterManager>>() {
+            *manager.pending_install.lock().await = Some(update);
+            let _ = app.emit("update-deferred", UpdateStatus::Deferred { version });
+        }
+    } else if config.auto_install {
+        let app = app.clone();
+        tokio::spawn(async move {
+            // Re-read the config each time: the user may change it while we wait
+            while let Some(reason) = install_blocked(&update_config(&app).await) {
+                println!("Update {} waiting to install: {}", update.version, reason);
+                tokio::time::sleep(INSTALL_RETRY_INTERVAL).await;
+            }
+            if let Err(e) = install_cached(&app, &update) {
+                eprintln!("Automatic update install failed: {}", e);
+                return;
+            }
+            app.restart();
+        });
+    }
+}
+
+/// Why updates can't be installed automatically right now, if they can't
+fn install_blocked(config: &UpdateConfig) -> Option<&'static str> {
+    use chrono::Timelike;
+
+    if !crate::server::streams::current_activity().generations.is_empty() {
+        return Some("a generation is running");
+    }
+    let hour = chrono::Local::now().hour();
+    if config.quiet_hours.is_some_and(|quiet| quiet.contains(hour)) {
+        return Some("quiet hours");
+    }
+    None
+}
+
+/// Install an update from its downloaded bundle
+fn install_cached(app: &AppHandle, update: &Update) -> Result<(), String> {
+    let bundle = cached_bundle(app, &update.version)
+        .ok_or_else(|| format!("Update {} hasn't been downloaded", update.version))?;
+    let _ = app.emit("update-installing", UpdateStatus::Installing {
+        version: update.version.clone(),
+    });
+    update.install(bundle).map_err(|e| e.to_string())
+}
+
+/// Install the update deferred until quit, if there is one
+///
+/// Call as the app exits.
+pub async fn install_pending(app: &AppHandle) {
+    let Some(manager) = app.try_state::<Arc<UpdaterManager>>() else {
+        return;
+    };
+    let Some(update) = manager.pending_install.lock().await.take() else {
+        return;
+    };
+    match install_cached(app, &update) {
+        Ok(()) => println!("Installed update {} on quit", update.version),
+        Err(e) => eprintln!("Failed to install update on quit: {}", e),
+    }
+}
+
 /// Tauri command to manually check for updates
 #[tauri::command]
 pub async fn check_for_updates(app: AppHandle) -> Result<UpdateStatus, String> {
-    match check_for_updates_internal(app).await {
+    match check_for_updates_internal(app, false).await {
         Ok(_) => Ok(UpdateStatus::UpToDate),
         Err(e) => Ok(UpdateStatus::Error {
             message: e.to_string(),
@@ -224,9 +450,12 @@ pub async fn check_for_updates(app: AppHandle) -> Result<UpdateStatus, String> {
 /// Tauri command to install the downloaded update
 #[tauri::command]
 pub async fn install_update(app: AppHandle) -> Result<(), String> {
-    let handle = app.updater_builder()
-        .build()
-        .map_err(|e| e.to_string())?;
+    let handle = updater(&app).map_err(|e| e.to_string())?;
+
+    // Installing restarts the app, which would cut generations off
+    if !crate::server::streams::current_activity().generations.is_empty() {
+        return Err("Wait for running generations to finish before installing".to_string());
+    }
 
     // Check if update is available
     match handle.check().await {
@@ -236,18 +465,14 @@ pub async fn install_update(app: AppHandle) -> Result<(), String> {
                 version: update.version.clone(),
             });
 
-            // Install and restart
-            update
-                .download_and_install(
-                    |chunk_length, content_length| {
-                        println!("Downloaded {} of {:?} bytes", chunk_length, content_length);
-                    },
-                    || {
-                        println!("Download finished, installing...");
-                    },
-                )
-                .await
-                .map_err(|e| e.to_string())?;
+            // Install the bundle downloaded earlier, or download it now
+            let bundle = match cached_bundle(&app, &update.version) {
+                Some(bundle) => bundle,
+                None => fetch_bundle(&app, &update).await?,
+            };
+
+            println!("Installing update...");
+            update.install(bundle).map_err(|e| e.to_string())?;
 
             Ok(())
         }
@@ -256,6 +481,428 @@ pub async fn install_update(app: AppHandle) -> Result<(), String> {
     }
 }
 
+// ============================================================================
+// Delta Updates
+// ============================================================================
+//
+// A release manifest can list binary patches alongside each platform's full
+// bundle:
+//
+//   "platforms": {
+//     "darwin-aarch64": {
+//       "url": "https://.../Vibing2.app.tar.gz",
+//       "signature": "...",
+//       "patches": [
+//         { "from": "1.0.0", "url": "https://.../1.0.0-1.1.0.patch", "sha256": "...", "size": 4194304 }
+//       ]
+//     }
+//   }
+//
+// Each patch is a bsdiff from the bundle of version `from` to the new bundle.
+// The bundle of the running version is kept from when it was downloaded, so
+// a patch only applies when that copy exists. The patch must match its
+// SHA-256, and the patched bundle must carry the release's signature like a
+// full download; if anything doesn't check out, the full bundle is
+// downloaded instead.
+
+/// A binary patch from an earlier release's bundle to an update's bundle
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PatchArtifact {
+    /// Version whose bundle the patch applies to
+    pub from: String,
+    pub url: String,
+    /// Hex SHA-256 of the patch file
+    pub sha256: String,
+    /// Patch size in bytes, for progress before the server reports it
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// Download an update's bundle, patching the running version's bundle when
+/// the manifest has a patch for it and falling back to the full bundle
+///
+/// `on_progress` gets the bytes downloaded so far and the expected total.
+async fn download_bundle(
+    app: &AppHandle,
+    update: &Update,
+    mut on_progress: impl FnMut(u64, u64) + Send,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    match download_delta(app, update, &mut on_progress).await {
+        Ok(Some(bundle)) => {
+            println!("Applied delta update from {}", update.current_version);
+            return Ok(bundle);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Delta update failed, downloading the full bundle: {}", e),
+    }
+
+    let part = partial_path(app, &format!("bundle-{}", update.version))?;
+    let bundle = download_resumable(app, update.download_url.as_str(), &part, None, &mut on_progress).await?;
+    verify_signature(app, &bundle, &update.signature)?;
+    Ok(bundle)
+}
+
+/// The update's bundle rebuilt from a patch, or `None` when no patch applies
+async fn download_delta(
+    app: &AppHandle,
+    update: &Update,
+    on_progress: &mut (impl FnMut(u64, u64) + Send),
+) -> Result<Option<Vec<u8>>, String> {
+    let Some(patch) = find_patch(&update.raw_json, &update.target, &update.current_version) else {
+        return Ok(None);
+    };
+    let Some(base) = cached_bundle(app, &update.current_version) else {
+        return Ok(None);
+    };
+
+    let part = partial_path(app, &format!("patch-{}-{}", patch.from, update.version)).map_err(|e| e.to_string())?;
+    let patch_bytes = download_resumable(app, &patch.url, &part, patch.size, on_progress).await?;
+
+    if !patch.sha256.eq_ignore_ascii_case(&sha256_hex(&patch_bytes)) {
+        return Err("Patch doesn't match its checksum".to_string());
+    }
+    let bundle = apply_patch(&base, &patch_bytes).map_err(|e| format!("Failed to apply patch: {}", e))?;
+    verify_signature(app, &bundle, &update.signature)?;
+    Ok(Some(bundle))
+}
+
+/// The patch for `target` that applies to `current_version`, if the manifest
+/// lists one
+fn find_patch(manifest: &serde_json::Value, target: &str, current_version: &str) -> Option<PatchArtifact> {
+    let patches = manifest.get("platforms")?.get(target)?.get("patches")?;
+    let patches: Vec<PatchArtifact> = serde_json::from_value(patches.clone()).ok()?;
+    patches.into_iter().find(|patch| patch.from == current_version)
+}
+
+/// Rebuild a bundle from the bundle it was diffed against
+fn apply_patch(base: &[u8], patch: &[u8]) -> std::io::Result<Vec<u8>> {
+    let patcher = qbsdiff::Bspatch::new(patch)?;
+    let mut bundle = Vec::with_capacity(patcher.hint_target_size() as usize);
+    patcher.apply(base, &mut bundle)?;
+    Ok(bundle)
+}
+
+/// Check a downloaded or patched bundle against the release's minisign signature, using
+/// the updater's configured public key
+fn verify_signature(app: &AppHandle, bundle: &[u8], signature: &str) -> Result<(), String> {
+    use base64::Engine;
+
+    let pubkey = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .ok_or("No updater public key is configured")?;
+
+    // Both are base64 encodings of the minisign files
+    let decode = |value: &str| {
+        base64::engine::general_purpose::STANDARD
+            .decode(value.trim())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+    };
+    let public_key = decode(pubkey)
+        .and_then(|key| minisign_verify::PublicKey::decode(&key).ok())
+        .ok_or("Invalid updater public key")?;
+    let signature = decode(signature)
+        .and_then(|signature| minisign_verify::Signature::decode(&signature).ok())
+        .ok_or("Invalid update signature")?;
+
+    public_key
+        .verify(bundle, &signature, true)
+        .map_err(|_| "Bundle doesn't match the release signature".to_string())
+}
+
+// ============================================================================
+// Resumable Downloads
+// ============================================================================
+//
+// Bundles and patches are downloaded into `<name>.part` files in the updates
+// directory. A paused download drops its connection and reconnects with a
+// `Range` request from the end of the file when resumed; the same happens
+// when an update check after a restart finds the file from an earlier run.
+// Servers that ignore the range get the download restarted from scratch.
+
+/// Download `url` into `part`, continuing from what's already there, and
+/// return the whole file once it's complete
+///
+/// Waits while downloads are paused and keeps to the configured bandwidth
+/// cap. `size_hint` stands in for the total until the server reports one.
+async fn download_resumable(
+    app: &AppHandle,
+    url: &str,
+    part: &Path,
+    size_hint: Option<u64>,
+    on_progress: &mut (impl FnMut(u64, u64) + Send),
+) -> Result<Vec<u8>, String> {
+    let failed = |e: &dyn std::fmt::Display| format!("Failed to download {}: {}", url, e);
+    let mut paused = match app.try_state::<Arc<UpdaterManager>>() {
+        Some(manager) => manager.paused.subscribe(),
+        None => watch::channel(false).1,
+    };
+    if let Some(dir) = part.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| failed(&e))?;
+    }
+
+    loop {
+        let _ = paused.wait_for(|paused| !*paused).await;
+        let limit = bandwidth_limit(app).await;
+
+        let offset = std::fs::metadata(part).map(|metadata| metadata.len()).unwrap_or(0);
+        let mut request = crate::proxy::client().get(url);
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let response = request.send().await.map_err(|e| failed(&e))?;
+        if offset > 0 && response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // The file changed on the server since the partial download
+            let _ = std::fs::remove_file(part);
+            continue;
+        }
+        let response = response.error_for_status().map_err(|e| failed(&e))?;
+
+        let resumed = offset > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(part)
+            .map_err(|e| failed(&e))?;
+        let mut downloaded = if resumed { offset } else { 0 };
+        let total = response
+            .content_length()
+            .map(|length| downloaded + length)
+            .or(size_hint)
+            .unwrap_or(0);
+
+        let started = Instant::now();
+        let mut received = 0u64;
+        let mut chunks = response.bytes_stream();
+        let complete = loop {
+            tokio::select! {
+                chunk = chunks.next() => match chunk {
+                    Some(chunk) => {
+                        let chunk = chunk.map_err(|e| failed(&e))?;
+                        file.write_all(&chunk).map_err(|e| failed(&e))?;
+                        downloaded += chunk.len() as u64;
+                        received += chunk.len() as u64;
+                        on_progress(downloaded, total);
+
+                        if let Some(delay) = throttle_delay(received, limit, started.elapsed()) {
+                            tokio::time::sleep(delay).await;
+                        }
+                    }
+                    None => break true,
+                },
+                true = async { paused.wait_for(|paused| *paused).await.is_ok() } => break false,
+            }
+        };
+        file.flush().map_err(|e| failed(&e))?;
+        drop(file);
+
+        if complete {
+            let bytes = std::fs::read(part).map_err(|e| failed(&e))?;
+            let _ = std::fs::remove_file(part);
+            return Ok(bytes);
+        }
+        let _ = app.emit("update-download-paused", UpdateStatus::Paused { downloaded, total });
+    }
+}
+
+/// The configured bandwidth cap in KiB/s, if any
+async fn bandwidth_limit(app: &AppHandle) -> Option<u64> {
+    let manager = app.try_state::<Arc<UpdaterManager>>()?;
+    let limit = manager.config.lock().await.max_download_kbps;
+    limit.filter(|kbps| *kbps > 0)
+}
+
+/// How long to wait so that `received` bytes over `elapsed` stay within
+/// `limit_kbps`
+fn throttle_delay(received: u64, limit_kbps: Option<u64>, elapsed: Duration) -> Option<Duration> {
+    let limit = limit_kbps? * 1024;
+    let due = Duration::from_secs_f64(received as f64 / limit as f64);
+    due.checked_sub(elapsed).filter(|delay| !delay.is_zero())
+}
+
+/// Where the unfinished download of `name` is kept
+fn partial_path(app: &AppHandle, name: &str) -> Result<PathBuf, tauri::Error> {
+    Ok(updates_dir(app)?.join(format!("{}.part", name)))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Where downloaded bundles are kept
+fn updates_dir(app: &AppHandle) -> Result<PathBuf, tauri::Error> {
+    Ok(app.path().app_data_dir()?.join("updates"))
+}
+
+fn bundle_path(app: &AppHandle, version: &str) -> Result<PathBuf, tauri::Error> {
+    Ok(updates_dir(app)?.join(format!("bundle-{}", version)))
+}
+
+/// The downloaded bundle for `version`, if it was kept
+fn cached_bundle(app: &AppHandle, version: &str) -> Option<Vec<u8>> {
+    std::fs::read(bundle_path(app, version).ok()?).ok()
+}
+
+/// Keep the bundle for `version`, replacing older ones except the running
+/// version's, which the next update may still patch
+fn cache_bundle(app: &AppHandle, version: &str, bundle: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = updates_dir(app)?;
+    std::fs::create_dir_all(&dir)?;
+
+    let keep = [
+        format!("bundle-{}", version),
+        format!("bundle-{}", app.package_info().version),
+    ];
+    for entry in std::fs::read_dir(&dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Unfinished downloads left over are for releases that were skipped
+        if (name.starts_with("bundle-") || name.ends_with(".part")) && !keep.contains(&name) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+
+    std::fs::write(bundle_path(app, version)?, bundle)?;
+    Ok(())
+}
+
+// ============================================================================
+// Release History
+// ============================================================================
+//
+// The update manifest only carries the newest release's notes. The full
+// changelog is published next to it as `releases.json` on the update
+// server, a list of entries shaped like the manifest:
+//
+//   [
+//     { "version": "1.1.0", "notes": "...", "pub_date": "2025-10-13T00:00:00Z" },
+//     { "version": "1.0.0", "notes": "...", "pub_date": "2025-09-01T00:00:00Z" }
+//   ]
+//
+// The list is cached in the updates directory so the changelog still shows
+// offline, and refetched once the copy is older than `HISTORY_MAX_AGE`.
+
+/// How long the cached release history is used before refetching
+const HISTORY_MAX_AGE: Duration = Duration::from_secs(6 * 3600);
+
+/// Releases returned when no limit is given
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+/// One release's notes from the release history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseNotes {
+    pub version: String,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub pub_date: Option<String>,
+}
+
+/// Tauri command to get past releases' notes, newest first
+#[tauri::command]
+pub async fn get_release_history(app: AppHandle, limit: Option<usize>) -> Result<Vec<ReleaseNotes>, String> {
+    let path = history_path(&app).map_err(|e| e.to_string())?;
+    let cached: Option<Vec<ReleaseNotes>> = std::fs::read(&path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    let fresh = std::fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < HISTORY_MAX_AGE);
+
+    let releases = match cached {
+        Some(releases) if fresh => releases,
+        cached => match fetch_release_history(&app).await {
+            Ok(releases) => {
+                if let Err(e) = cache_release_history(&path, &releases) {
+                    eprintln!("Failed to cache release history: {}", e);
+                }
+                releases
+            }
+            // Offline or the server is down: show what we have
+            Err(e) => match cached {
+                Some(releases) => {
+                    eprintln!("Using cached release history: {}", e);
+                    releases
+                }
+                None => return Err(e),
+            },
+        },
+    };
+
+    Ok(newest_first(releases, limit.unwrap_or(DEFAULT_HISTORY_LIMIT)))
+}
+
+async fn fetch_release_history(app: &AppHandle) -> Result<Vec<ReleaseNotes>, String> {
+    let endpoint = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("endpoints"))
+        .and_then(|endpoints| endpoints.get(0))
+        .and_then(|endpoint| endpoint.as_str())
+        .ok_or("No update endpoint configured")?
+        .to_string();
+    let url = history_url(&endpoint)?;
+
+    crate::proxy::client()
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch release history: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid release history: {}", e))
+}
+
+/// `releases.json` on the update endpoint's server
+fn history_url(endpoint: &str) -> Result<reqwest::Url, String> {
+    reqwest::Url::parse(endpoint)
+        .and_then(|url| url.join("/releases.json"))
+        .map_err(|e| format!("Invalid update endpoint {}: {}", endpoint, e))
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf, tauri::Error> {
+    Ok(updates_dir(app)?.join("releases.json"))
+}
+
+fn cache_release_history(path: &std::path::Path, releases: &[ReleaseNotes]) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_vec(releases)?)?;
+    Ok(())
+}
+
+/// Sort releases by version, newest first, and keep the first `limit`
+fn newest_first(mut releases: Vec<ReleaseNotes>, limit: usize) -> Vec<ReleaseNotes> {
+    releases.sort_by_cached_key(|release| std::cmp::Reverse(version_key(&release.version)));
+    releases.truncate(limit);
+    releases
+}
+
+/// Numeric parts of a version (`v1.10.0-beta.1` -> `[1, 10, 0]`) for
+/// ordering
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
 /// Tauri command to get update configuration
 #[tauri::command]
 pub async fn get_update_config(
@@ -265,15 +912,40 @@ pub async fn get_update_config(
 }
 
 /// Tauri command to update configuration
+///
+/// The config is saved, so it applies again after a restart.
 #[tauri::command]
 pub async fn set_update_config(
     config: UpdateConfig,
     updater: tauri::State<'_, Arc<UpdaterManager>>,
 ) -> Result<(), String> {
+    let pool = crate::database::get_pool()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    save_config(pool.as_ref(), &config).await?;
+
     *updater.config.lock().await = config;
     Ok(())
 }
 
+/// Tauri command to pause the update download
+#[tauri::command]
+pub async fn pause_update_download(
+    updater: tauri::State<'_, Arc<UpdaterManager>>,
+) -> Result<(), String> {
+    updater.pause_download();
+    Ok(())
+}
+
+/// Tauri command to resume a paused update download
+#[tauri::command]
+pub async fn resume_update_download(
+    updater: tauri::State<'_, Arc<UpdaterManager>>,
+) -> Result<(), String> {
+    updater.resume_download();
+    Ok(())
+}
+
 /// Tauri command to get current update status
 #[tauri::command]
 pub async fn get_update_status(
@@ -285,7 +957,7 @@ pub async fn get_update_status(
 /// Tauri command to download update without installing
 #[tauri::command]
 pub async fn download_update(app: AppHandle) -> Result<(), String> {
-    check_for_updates_internal(app)
+    check_for_updates_internal(app, true)
         .await
         .map_err(|e| e.to_string())
 }
@@ -293,9 +965,7 @@ pub async fn download_update(app: AppHandle) -> Result<(), String> {
 /// Tauri command to check if update is available (quick check)
 #[tauri::command]
 pub async fn is_update_available(app: AppHandle) -> Result<bool, String> {
-    let handle = app.updater_builder()
-        .build()
-        .map_err(|e| e.to_string())?;
+    let handle = updater(&app).map_err(|e| e.to_string())?;
 
     match handle.check().await {
         Ok(Some(_)) => Ok(true),
@@ -314,8 +984,15 @@ pub fn get_app_version(app: AppHandle) -> String {
 pub async fn init_updater(app: AppHandle) -> Result<Arc<UpdaterManager>, Box<dyn std::error::Error>> {
     let manager = Arc::new(UpdaterManager::new(app.clone()));
 
-    // Initialize with default config
-    manager.init(UpdateConfig::default()).await;
+    // Initialize with the saved config
+    let config = match crate::database::get_pool().await {
+        Ok(pool) => load_config(pool.as_ref()).await,
+        Err(e) => {
+            eprintln!("Failed to load update config: {}", e);
+            UpdateConfig::default()
+        }
+    };
+    manager.init(config).await;
 
     // Start the updater service
     manager.start().await;
@@ -327,6 +1004,44 @@ pub async fn init_updater(app: AppHandle) -> Result<Arc<UpdaterManager>, Box<dyn
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_update_config_survives_reload() {
+        let temp_db = tempfile::NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(load_config(&pool).await, UpdateConfig::default());
+
+        let config = UpdateConfig {
+            auto_download: false,
+            auto_install: true,
+            install_on_quit: true,
+            quiet_hours: Some(QuietHours { start: 22, end: 7 }),
+            ..Default::default()
+        };
+        save_config(&pool, &config).await.unwrap();
+        assert_eq!(load_config(&pool).await, config);
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_cap_survives_reload() {
+        let temp_db = tempfile::NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+
+        let config = UpdateConfig {
+            max_download_kbps: Some(256),
+            ..Default::default()
+        };
+        save_config(&pool, &config).await.unwrap();
+        assert_eq!(load_config(&pool).await.max_download_kbps, Some(256));
+
+        // Lifting the cap is saved too
+        save_config(&pool, &UpdateConfig::default()).await.unwrap();
+        assert_eq!(load_config(&pool).await.max_download_kbps, None);
+    }
+
     #[test]
     fn test_update_config_default() {
         let config = UpdateConfig::default();
@@ -336,6 +1051,94 @@ mod tests {
         assert!(config.auto_download);
         assert!(!config.auto_install);
         assert!(config.show_notifications);
+        assert_eq!(config.max_download_kbps, None);
+        assert!(!config.install_on_quit);
+        assert_eq!(config.quiet_hours, None);
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let night = QuietHours { start: 22, end: 7 };
+        assert!(night.contains(23));
+        assert!(night.contains(0));
+        assert!(night.contains(6));
+        assert!(!night.contains(7));
+        assert!(!night.contains(12));
+
+        let lunch = QuietHours { start: 12, end: 14 };
+        assert!(lunch.contains(13));
+        assert!(!lunch.contains(14));
+        assert!(!lunch.contains(22));
+    }
+
+    #[test]
+    fn test_throttle_delay() {
+        // 100 KiB at 50 KiB/s takes two seconds
+        let delay = throttle_delay(100 * 1024, Some(50), Duration::from_millis(500)).unwrap();
+        assert_eq!(delay, Duration::from_millis(1500));
+        assert!(throttle_delay(100 * 1024, Some(50), Duration::from_secs(3)).is_none());
+        assert!(throttle_delay(100 * 1024, None, Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_find_patch() {
+        let manifest = serde_json::json!({
+            "version": "1.2.0",
+            "platforms": {
+                "darwin-aarch64": {
+                    "url": "https://example.com/Vibing2.app.tar.gz",
+                    "signature": "sig",
+                    "patches": [
+                        { "from": "1.0.0", "url": "https://example.com/1.0.0.patch", "sha256": "aa" },
+                        { "from": "1.1.0", "url": "https://example.com/1.1.0.patch", "sha256": "bb", "size": 42 }
+                    ]
+                },
+                "linux-x86_64": { "url": "https://example.com/vibing2.AppImage", "signature": "sig" }
+            }
+        });
+
+        let patch = find_patch(&manifest, "darwin-aarch64", "1.1.0").unwrap();
+        assert_eq!(patch.url, "https://example.com/1.1.0.patch");
+        assert_eq!(patch.size, Some(42));
+        assert!(find_patch(&manifest, "darwin-aarch64", "0.9.0").is_none());
+        assert!(find_patch(&manifest, "linux-x86_64", "1.1.0").is_none());
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let base = b"Vibing2 bundle, version 1.0.0, with some unchanged content".repeat(20);
+        let mut bundle = base.clone();
+        bundle.extend_from_slice(b" and a new feature in 1.1.0");
+
+        let mut patch = Vec::new();
+        qbsdiff::Bsdiff::new(&base, &bundle).compare(std::io::Cursor::new(&mut patch)).unwrap();
+        assert_eq!(apply_patch(&base, &patch).unwrap(), bundle);
+        assert!(apply_patch(&base, b"not a patch").is_err());
+
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_release_history() {
+        let release = |version: &str| ReleaseNotes {
+            version: version.to_string(),
+            notes: format!("Changes in {}", version),
+            pub_date: None,
+        };
+        let releases = vec![release("1.2.0"), release("1.10.0"), release("v1.9.1"), release("1.0.0")];
+
+        let versions: Vec<String> = newest_first(releases, 3).into_iter().map(|r| r.version).collect();
+        assert_eq!(versions, vec!["1.10.0", "v1.9.1", "1.2.0"]);
+        assert_eq!(version_key("2.0.0-beta.1"), vec![2, 0, 0]);
+
+        assert_eq!(
+            history_url("https://releases.vibing2.com/{{target}}/{{arch}}/{{current_version}}").unwrap().as_str(),
+            "https://releases.vibing2.com/releases.json"
+        );
+        assert!(history_url("not a url").is_err());
     }
 
     #[test]
@@ -347,7 +1150,7 @@ mod tests {
         };
 
         let json = serde_json::to_string(&status).unwrap();
-        assert!(json.contains("Available"));
+        assert!(json.contains(r#""status":"available""#));
         assert!(json.contains("1.1.0"));
     }
 }
@@ -0,0 +1,202 @@
+//! Token usage ledger
+//!
+//! One row per completed generation, written when a stream ends so cost
+//! tracking doesn't depend on the frontend. Rows outlive the project they
+//! were generated for (`project_id` is cleared when it is deleted).
+//!
+//! Costs are computed from a built-in per-model price table; local and
+//! demo models are free, and unknown models have no price.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+/// Token counts and outcome of a single generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Usage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub model: String,
+    /// Why generation stopped (`end_turn`, `max_tokens`, `cancelled`, ...)
+    pub stop_reason: String,
+}
+
+/// Rough token count for text without a tokenizer (about 4 bytes per token)
+pub fn estimate_tokens(text: &str) -> u32 {
+    text.len().div_ceil(4) as u32
+}
+
+/// USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+/// List prices by model-name prefix; the longest matching prefix wins
+const PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-haiku-4", 1.0, 5.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("o4-mini", 1.1, 4.4),
+    ("o3-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+    ("o1", 15.0, 60.0),
+];
+
+/// Price of `model`, or `None` when it isn't in the table
+pub fn price(model: &str) -> Option<ModelPrice> {
+    let model = model.to_lowercase();
+
+    // Local and demo models cost nothing
+    if matches!(
+        crate::llm::ProviderKind::infer(&model),
+        Some(crate::llm::ProviderKind::Ollama | crate::llm::ProviderKind::Demo)
+    ) {
+        return Some(ModelPrice { input_per_mtok: 0.0, output_per_mtok: 0.0 });
+    }
+
+    PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|&(_, input_per_mtok, output_per_mtok)| ModelPrice { input_per_mtok, output_per_mtok })
+}
+
+impl ModelPrice {
+    pub fn cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        (input_tokens as f64 * self.input_per_mtok + output_tokens as f64 * self.output_per_mtok) / 1_000_000.0
+    }
+}
+
+/// Cost of a finished generation in USD, if its model is priced
+pub fn cost(usage: &Usage) -> Option<f64> {
+    price(&usage.model).map(|price| price.cost(usage.input_tokens, usage.output_tokens))
+}
+
+/// What a prompt is expected to cost before it is sent
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CostEstimate {
+    pub model: String,
+    /// Estimated from the prompt length
+    pub input_tokens: u32,
+    /// Output cap the estimate assumes
+    pub max_output_tokens: u32,
+    /// `None` when the model isn't priced
+    pub price: Option<ModelPrice>,
+    pub input_cost_usd: Option<f64>,
+    /// Cost if the reply uses every output token
+    pub max_cost_usd: Option<f64>,
+}
+
+/// Estimate what sending `prompt` to `model` will cost
+pub fn estimate_cost(prompt: &str, model: &str, max_output_tokens: u32) -> CostEstimate {
+    let input_tokens = estimate_tokens(prompt);
+    let price = price(model);
+
+    CostEstimate {
+        model: model.to_string(),
+        input_tokens,
+        max_output_tokens,
+        price,
+        input_cost_usd: price.map(|price| price.cost(input_tokens, 0)),
+        max_cost_usd: price.map(|price| price.cost(input_tokens, max_output_tokens)),
+    }
+}
+
+/// Append a generation's usage to the ledger, with the agent it ran as
+pub async fn record(
+    pool: &SqlitePool,
+    user_id: &str,
+    project_id: Option<&str>,
+    agent_id: Option<&str>,
+    usage: &Usage,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO usage_events (id, user_id, project_id, agent_id, model, input_tokens, output_tokens, stop_reason, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(format!("usage_{}", uuid::Uuid::new_v4()))
+    .bind(user_id)
+    .bind(project_id)
+    .bind(agent_id)
+    .bind(&usage.model)
+    .bind(usage.input_tokens as i64)
+    .bind(usage.output_tokens as i64)
+    .bind(&usage.stop_reason)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::create_test_pool;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_prices() {
+        assert_eq!(price("claude-opus-4-5-20251101").unwrap().input_per_mtok, 5.0);
+        assert_eq!(price("claude-opus-4-1").unwrap().input_per_mtok, 15.0);
+        assert_eq!(price("gpt-4o-mini").unwrap().output_per_mtok, 0.6);
+        assert_eq!(price("llama3.1:8b").unwrap().input_per_mtok, 0.0);
+        assert!(price("mystery-model").is_none());
+
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            model: "claude-sonnet-4-5".to_string(),
+            stop_reason: "end_turn".to_string(),
+        };
+        assert!((cost(&usage).unwrap() - 4.5).abs() < 1e-9);
+
+        let estimate = estimate_cost("abcd", "claude-sonnet-4-5", 1000);
+        assert_eq!(estimate.input_tokens, 1);
+        assert!(estimate.max_cost_usd.unwrap() > estimate.input_cost_usd.unwrap());
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_record_usage() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let pool = create_test_pool(temp_db.path().to_str().unwrap()).await.unwrap();
+
+        let usage = Usage {
+            input_tokens: 12,
+            output_tokens: 34,
+            model: "claude-sonnet-4-5".to_string(),
+            stop_reason: "end_turn".to_string(),
+        };
+        record(&pool, "local-user", None, None, &usage).await.unwrap();
+
+        let (input, output): (i64, i64) = sqlx::query_as(
+            "SELECT SUM(input_tokens), SUM(output_tokens) FROM usage_events WHERE user_id = 'local-user'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((input, output), (12, 34));
+    }
+}
@@ -0,0 +1,512 @@
+//! Optional master-passphrase encryption for stored credentials
+//!
+//! When the app lock is enabled, API keys in `auth_credentials` and
+//! `provider_credentials`, OAuth tokens, users' TOTP secrets and the proxy
+//! password are encrypted with AES-256-GCM using a key derived
+//! from the user's passphrase with Argon2id. The derived key only lives in
+//! memory while the vault is unlocked and is dropped after a period of
+//! inactivity.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+/// Prefix marking an encrypted value in the database
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// Known plaintext encrypted with the vault key to verify passphrases
+const VERIFIER_PLAINTEXT: &str = "vibing2-vault";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// How often the idle watcher checks for inactivity
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default idle timeout before the vault locks itself
+pub const DEFAULT_IDLE_TIMEOUT_MINUTES: u64 = 15;
+
+#[derive(Default)]
+struct VaultState {
+    key: Option<[u8; 32]>,
+    last_activity: Option<Instant>,
+}
+
+static VAULT: OnceLock<Mutex<VaultState>> = OnceLock::new();
+
+fn state() -> &'static Mutex<VaultState> {
+    VAULT.get_or_init(|| Mutex::new(VaultState::default()))
+}
+
+/// Lock status returned to the frontend
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockStatus {
+    pub enabled: bool,
+    pub locked: bool,
+    pub idle_timeout_minutes: u64,
+}
+
+/// Derive a 256-bit key from a passphrase and salt
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt_with(key: &[u8; 32], plaintext: &str) -> Result<String, String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(payload)))
+}
+
+fn decrypt_with(key: &[u8; 32], value: &str) -> Result<String, String> {
+    let encoded = value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or_else(|| "Value is not encrypted".to_string())?;
+
+    let payload = STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Corrupt encrypted value: {}", e))?;
+
+    if payload.len() <= NONCE_LEN {
+        return Err("Corrupt encrypted value".to_string());
+    }
+
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Decryption failed - wrong passphrase?".to_string())?;
+
+    String::from_utf8(plaintext).map_err(|e| format!("Corrupt encrypted value: {}", e))
+}
+
+/// Whether a stored value is encrypted
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Load the vault configuration row: (salt, verifier, idle timeout minutes)
+async fn load_config(pool: &SqlitePool) -> Result<Option<(Vec<u8>, String, u64)>, String> {
+    let row = sqlx::query("SELECT salt, verifier, idle_timeout_minutes FROM vault_config WHERE id = 1")
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    match row {
+        Some(row) => {
+            let salt: String = row.get("salt");
+            let salt = STANDARD
+                .decode(salt)
+                .map_err(|e| format!("Corrupt vault salt: {}", e))?;
+            let minutes: i64 = row.get("idle_timeout_minutes");
+            Ok(Some((salt, row.get("verifier"), minutes.max(0) as u64)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Whether the app lock is enabled
+pub async fn is_enabled(pool: &SqlitePool) -> Result<bool, String> {
+    Ok(load_config(pool).await?.is_some())
+}
+
+/// Whether the vault key is currently held in memory
+pub fn is_unlocked() -> bool {
+    state().lock().map(|s| s.key.is_some()).unwrap_or(false)
+}
+
+fn current_key() -> Result<[u8; 32], String> {
+    let mut vault = state().lock().map_err(|_| "Vault lock poisoned".to_string())?;
+    let key = vault
+        .key
+        .ok_or_else(|| "Credentials are locked. Unlock with your passphrase first.".to_string())?;
+    vault.last_activity = Some(Instant::now());
+    Ok(key)
+}
+
+/// Encrypt a credential for storage if the app lock is enabled
+///
+/// Returns the value unchanged when the lock is disabled.
+pub async fn seal(pool: &SqlitePool, plaintext: &str) -> Result<String, String> {
+    if !is_enabled(pool).await? {
+        return Ok(plaintext.to_string());
+    }
+
+    encrypt_with(&current_key()?, plaintext)
+}
+
+/// Decrypt a stored credential, passing plaintext values through unchanged
+pub fn open(value: &str) -> Result<String, String> {
+    if !is_encrypted(value) {
+        return Ok(value.to_string());
+    }
+
+    decrypt_with(&current_key()?, value)
+}
+
+/// Current lock status
+pub async fn status(pool: &SqlitePool) -> Result<LockStatus, String> {
+    let config = load_config(pool).await?;
+
+    Ok(LockStatus {
+        enabled: config.is_some(),
+        locked: config.is_some() && !is_unlocked(),
+        idle_timeout_minutes: config
+            .map(|(_, _, minutes)| minutes)
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_MINUTES),
+    })
+}
+
+/// Unlock the vault with the passphrase
+pub async fn unlock(pool: &SqlitePool, passphrase: &str) -> Result<(), String> {
+    let (salt, verifier, _) = load_config(pool)
+        .await?
+        .ok_or_else(|| "App lock is not enabled".to_string())?;
+
+    let key = derive_key(passphrase, &salt)?;
+
+    if decrypt_with(&key, &verifier).ok().as_deref() != Some(VERIFIER_PLAINTEXT) {
+        return Err("Incorrect passphrase".to_string());
+    }
+
+    let mut vault = state().lock().map_err(|_| "Vault lock poisoned".to_string())?;
+    vault.key = Some(key);
+    vault.last_activity = Some(Instant::now());
+
+    Ok(())
+}
+
+/// Drop the vault key from memory
+pub fn lock() {
+    if let Ok(mut vault) = state().lock() {
+        vault.key = None;
+        vault.last_activity = None;
+    }
+}
+
+/// Re-encode every stored credential with the given transform
+async fn rewrite_credentials<F>(pool: &SqlitePool, transform: F) -> Result<(), String>
+where
+    F: Fn(&str) -> Result<String, String>,
+{
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let legacy: Option<String> = sqlx::query_scalar("SELECT api_key FROM auth_credentials WHERE id = 1")
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if let Some(api_key) = legacy {
+        sqlx::query("UPDATE auth_credentials SET api_key = ? WHERE id = 1")
+            .bind(transform(&api_key)?)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    let rows = sqlx::query("SELECT provider, api_key FROM provider_credentials")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    for row in rows {
+        let provider: String = row.get("provider");
+        let api_key: String = row.get("api_key");
+
+        sqlx::query("UPDATE provider_credentials SET api_key = ? WHERE provider = ?")
+            .bind(transform(&api_key)?)
+            .bind(&provider)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    let oauth = sqlx::query("SELECT access_token, refresh_token FROM oauth_tokens WHERE id = 1")
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if let Some(row) = oauth {
+        let access_token: String = row.get("access_token");
+        let refresh_token: Option<String> = row.get("refresh_token");
+
+        sqlx::query("UPDATE oauth_tokens SET access_token = ?, refresh_token = ? WHERE id = 1")
+            .bind(transform(&access_token)?)
+            .bind(refresh_token.as_deref().map(&transform).transpose()?)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    let secrets = sqlx::query("SELECT id, totp_secret FROM users WHERE totp_secret IS NOT NULL")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    for row in secrets {
+        let user_id: String = row.get("id");
+        let secret: String = row.get("totp_secret");
+
+        sqlx::query("UPDATE users SET totp_secret = ? WHERE id = ?")
+            .bind(transform(&secret)?)
+            .bind(&user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    let proxy_password: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(crate::proxy::PASSWORD_SETTING)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    // An empty password means none is set
+    if let Some(password) = proxy_password.filter(|password| !password.is_empty()) {
+        sqlx::query("UPDATE settings SET value = ? WHERE key = ?")
+            .bind(transform(&password)?)
+            .bind(crate::proxy::PASSWORD_SETTING)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))
+}
+
+/// Enable the app lock, encrypting all stored credentials with the passphrase
+pub async fn enable(pool: &SqlitePool, passphrase: &str, idle_timeout_minutes: u64) -> Result<(), String> {
+    if passphrase.len() < 8 {
+        return Err("Passphrase must be at least 8 characters".to_string());
+    }
+
+    if is_enabled(pool).await? {
+        return Err("App lock is already enabled".to_string());
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+
+    rewrite_credentials(pool, |value| {
+        if is_encrypted(value) {
+            Ok(value.to_string())
+        } else {
+            encrypt_with(&key, value)
+        }
+    })
+    .await?;
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO vault_config (id, salt, verifier, idle_timeout_minutes, updated_at)
+         VALUES (1, ?1, ?2, ?3, datetime('now'))"
+    )
+    .bind(STANDARD.encode(salt))
+    .bind(encrypt_with(&key, VERIFIER_PLAINTEXT)?)
+    .bind(idle_timeout_minutes as i64)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut vault = state().lock().map_err(|_| "Vault lock poisoned".to_string())?;
+    vault.key = Some(key);
+    vault.last_activity = Some(Instant::now());
+
+    Ok(())
+}
+
+/// Disable the app lock, decrypting all stored credentials
+pub async fn disable(pool: &SqlitePool, passphrase: &str) -> Result<(), String> {
+    unlock(pool, passphrase).await?;
+    let key = current_key()?;
+
+    rewrite_credentials(pool, |value| {
+        if is_encrypted(value) {
+            decrypt_with(&key, value)
+        } else {
+            Ok(value.to_string())
+        }
+    })
+    .await?;
+
+    sqlx::query("DELETE FROM vault_config WHERE id = 1")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    lock();
+    Ok(())
+}
+
+/// Spawn a watcher that locks the vault after the configured idle timeout
+///
+/// Emits `auth:locked` when it does so.
+pub fn spawn_idle_lock_task(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let last_activity = match state().lock() {
+                Ok(vault) if vault.key.is_some() => vault.last_activity,
+                _ => continue,
+            };
+
+            let pool = match crate::database::get_pool().await {
+                Ok(pool) => pool,
+                Err(_) => continue,
+            };
+
+            let timeout_minutes = match load_config(pool.as_ref()).await {
+                Ok(Some((_, _, minutes))) => minutes,
+                _ => continue,
+            };
+
+            // A timeout of zero disables auto-lock
+            if timeout_minutes == 0 {
+                continue;
+            }
+
+            let idle = last_activity.map(|t| t.elapsed()).unwrap_or_default();
+            if idle >= Duration::from_secs(timeout_minutes * 60) {
+                lock();
+                crate::auth::invalidate_auth_cache(&app).await;
+                println!("🔒 Credentials locked after {} minutes idle", timeout_minutes);
+                crate::auth::emit_auth_event(&app, crate::auth::AuthEvent::Locked {});
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let key = derive_key("correct horse battery staple", b"0123456789abcdef").unwrap();
+        let sealed = encrypt_with(&key, "sk-ant-test123").unwrap();
+
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("sk-ant-test123"));
+        assert_eq!(decrypt_with(&key, &sealed).unwrap(), "sk-ant-test123");
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let key = derive_key("passphrase-one", b"0123456789abcdef").unwrap();
+        let other = derive_key("passphrase-two", b"0123456789abcdef").unwrap();
+        let sealed = encrypt_with(&key, "secret").unwrap();
+
+        assert!(decrypt_with(&other, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_plaintext_passthrough() {
+        assert_eq!(open("sk-ant-plain").unwrap(), "sk-ant-plain");
+    }
+
+    async fn stored_totp_secret(pool: &SqlitePool) -> String {
+        sqlx::query_scalar("SELECT totp_secret FROM users WHERE id = 'local-user'")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn stored_proxy_password(pool: &SqlitePool) -> String {
+        sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(crate::proxy::PASSWORD_SETTING)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    // One test, since the vault key is global
+    #[tokio::test]
+    async fn test_enable_and_disable_rewrite_secrets() {
+        let temp_db = tempfile::NamedTempFile::new().unwrap();
+        let pool = crate::database::create_test_pool(temp_db.path().to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("UPDATE users SET totp_secret = 'JBSWY3DPEHPK3PXP' WHERE id = 'local-user'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO settings (id, key, value) VALUES ('s1', ?, 'hunter22')")
+            .bind(crate::proxy::PASSWORD_SETTING)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let tokens = crate::oauth::OAuthTokens {
+            access_token: "access-123".to_string(),
+            refresh_token: Some("refresh-123".to_string()),
+            expires_at: None,
+            scope: None,
+        };
+        crate::oauth::store_tokens(&pool, &tokens).await.unwrap();
+
+        enable(&pool, "correct horse battery staple", 0).await.unwrap();
+        let sealed = stored_totp_secret(&pool).await;
+        assert!(is_encrypted(&sealed));
+        assert_eq!(open(&sealed).unwrap(), "JBSWY3DPEHPK3PXP");
+        let sealed = stored_proxy_password(&pool).await;
+        assert!(is_encrypted(&sealed));
+        assert_eq!(open(&sealed).unwrap(), "hunter22");
+        let (access_token, refresh_token): (String, String) =
+            sqlx::query_as("SELECT access_token, refresh_token FROM oauth_tokens WHERE id = 1")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(is_encrypted(&access_token) && is_encrypted(&refresh_token));
+        let loaded = crate::oauth::load_tokens(&pool).await.unwrap().unwrap();
+        assert_eq!(loaded.access_token, "access-123");
+        assert_eq!(loaded.refresh_token.as_deref(), Some("refresh-123"));
+
+        // Tokens stored while the lock is on are encrypted too
+        crate::oauth::store_tokens(&pool, &tokens).await.unwrap();
+        let access_token: String = sqlx::query_scalar("SELECT access_token FROM oauth_tokens WHERE id = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(is_encrypted(&access_token));
+
+        disable(&pool, "correct horse battery staple").await.unwrap();
+        assert_eq!(stored_totp_secret(&pool).await, "JBSWY3DPEHPK3PXP");
+        assert_eq!(stored_proxy_password(&pool).await, "hunter22");
+        assert_eq!(crate::oauth::load_tokens(&pool).await.unwrap().unwrap().access_token, "access-123");
+        let access_token: String = sqlx::query_scalar("SELECT access_token FROM oauth_tokens WHERE id = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(access_token, "access-123");
+    }
+}
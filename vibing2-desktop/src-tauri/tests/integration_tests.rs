@@ -4,7 +4,7 @@ mod test_utils;
 use serial_test::serial;
 use vibing2_desktop::commands::{
     greet, save_project, load_project, list_projects, delete_project,
-    save_settings, load_settings, SaveProjectRequest, Message, Settings,
+    store_settings, load_settings, SaveProjectRequest, Message, SettingsUpdate,
 };
 
 // Test greet command
@@ -40,12 +40,15 @@ async fn test_save_project_create_new() {
         project_id: None,
         name: "Test Project".to_string(),
         project_type: "web-app".to_string(),
-        active_agents: "[]".to_string(),
+        active_agents: None,
         messages: vec![
             Message {
                 id: "msg-1".to_string(),
                 role: "user".to_string(),
                 content: "Create a todo app".to_string(),
+                agent_id: None,
+                parent_id: None,
+                branch_status: None,
             },
         ],
         current_code: Some("console.log('Hello');".to_string()),
@@ -88,12 +91,15 @@ async fn test_save_project_update_existing() {
         project_id: Some("proj-123".to_string()),
         name: "Updated Name".to_string(),
         project_type: "mobile-app".to_string(),
-        active_agents: "[\"agent1\"]".to_string(),
+        active_agents: Some(vec!["ui-designer".to_string()]),
         messages: vec![
             Message {
                 id: "msg-new".to_string(),
                 role: "user".to_string(),
                 content: "New message".to_string(),
+                agent_id: None,
+                parent_id: None,
+                branch_status: None,
             },
         ],
         current_code: Some("console.log('Updated');".to_string()),
@@ -110,6 +116,13 @@ async fn test_save_project_update_existing() {
         .unwrap();
     assert_eq!(row.0, "Updated Name");
 
+    // Verify agents were replaced
+    let agents: Vec<String> = sqlx::query_scalar("SELECT agent_id FROM project_agents WHERE project_id = 'proj-123'")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(agents, vec!["ui-designer"]);
+
     // Verify old messages were replaced
     test_utils::assert_message_count(&pool, "proj-123", 1).await;
 
@@ -128,7 +141,7 @@ async fn test_save_project_empty_messages() {
         project_id: None,
         name: "Empty Messages Project".to_string(),
         project_type: "web-app".to_string(),
-        active_agents: "[]".to_string(),
+        active_agents: None,
         messages: vec![],
         current_code: None,
     };
@@ -281,21 +294,22 @@ async fn test_delete_project_not_found() {
     std::env::remove_var("TEST_DATABASE_PATH");
 }
 
-// Test save_settings command
+// Test store_settings
 #[tokio::test]
 #[serial]
 async fn test_save_settings() {
     let (pool, _temp_db, db_path) = test_utils::setup_test_db().await;
     std::env::set_var("TEST_DATABASE_PATH", &db_path);
 
-    let settings = Settings {
+    let settings = SettingsUpdate {
         anthropic_api_key: Some("sk-test-key-123".to_string()),
-        theme: "dark".to_string(),
-        auto_save: true,
-        default_project_path: "/custom/path".to_string(),
+        theme: Some("dark".to_string()),
+        auto_save: Some(true),
+        default_project_path: Some("/custom/path".to_string()),
+        ..Default::default()
     };
 
-    let result = save_settings(settings).await;
+    let result = store_settings(&pool, settings).await;
     assert!(result.is_ok());
 
     // Verify settings were saved
@@ -309,7 +323,7 @@ async fn test_save_settings() {
     std::env::remove_var("TEST_DATABASE_PATH");
 }
 
-// Test save_settings command - update existing
+// Test store_settings - update existing
 #[tokio::test]
 #[serial]
 async fn test_save_settings_update() {
@@ -317,22 +331,24 @@ async fn test_save_settings_update() {
     std::env::set_var("TEST_DATABASE_PATH", &db_path);
 
     // Save initial settings
-    let settings1 = Settings {
+    let settings1 = SettingsUpdate {
         anthropic_api_key: Some("key-1".to_string()),
-        theme: "dark".to_string(),
-        auto_save: true,
-        default_project_path: "/path1".to_string(),
+        theme: Some("dark".to_string()),
+        auto_save: Some(true),
+        default_project_path: Some("/path1".to_string()),
+        ..Default::default()
     };
-    save_settings(settings1).await.unwrap();
+    store_settings(&pool, settings1).await.unwrap();
 
     // Update settings
-    let settings2 = Settings {
+    let settings2 = SettingsUpdate {
         anthropic_api_key: Some("key-2".to_string()),
-        theme: "light".to_string(),
-        auto_save: false,
-        default_project_path: "/path2".to_string(),
+        theme: Some("light".to_string()),
+        auto_save: Some(false),
+        default_project_path: Some("/path2".to_string()),
+        ..Default::default()
     };
-    save_settings(settings2).await.unwrap();
+    store_settings(&pool, settings2).await.unwrap();
 
     // Verify updated values
     let api_key = test_utils::get_setting_value(&pool, "anthropic_api_key").await;
@@ -345,6 +361,39 @@ async fn test_save_settings_update() {
     std::env::remove_var("TEST_DATABASE_PATH");
 }
 
+// Test store_settings - fields left out keep their saved values
+#[tokio::test]
+#[serial]
+async fn test_save_settings_partial_update() {
+    let (pool, _temp_db, db_path) = test_utils::setup_test_db().await;
+    std::env::set_var("TEST_DATABASE_PATH", &db_path);
+
+    let settings = SettingsUpdate {
+        theme: Some("light".to_string()),
+        server_autostart: Some(true),
+        ..Default::default()
+    };
+    store_settings(&pool, settings).await.unwrap();
+
+    let settings = SettingsUpdate {
+        theme: Some("dark".to_string()),
+        ..Default::default()
+    };
+    store_settings(&pool, settings).await.unwrap();
+
+    let theme = test_utils::get_setting_value(&pool, "theme").await;
+    assert_eq!(theme, Some("dark".to_string()));
+
+    let autostart = test_utils::get_setting_value(&pool, "server_autostart").await;
+    assert_eq!(autostart, Some("true".to_string()));
+
+    let api_key = test_utils::get_setting_value(&pool, "anthropic_api_key").await;
+    assert_eq!(api_key, None);
+
+    test_utils::cleanup_test_db(pool).await;
+    std::env::remove_var("TEST_DATABASE_PATH");
+}
+
 // Test load_settings command - defaults
 #[tokio::test]
 #[serial]
@@ -372,24 +421,35 @@ async fn test_load_settings_saved_values() {
     std::env::set_var("TEST_DATABASE_PATH", &db_path);
 
     // Save settings first
-    let settings = Settings {
-        anthropic_api_key: Some("saved-key".to_string()),
-        theme: "light".to_string(),
-        auto_save: false,
-        default_project_path: "/saved/path".to_string(),
+    let settings = SettingsUpdate {
+        anthropic_api_key: Some("sk-ant-saved-key-1234".to_string()),
+        theme: Some("light".to_string()),
+        auto_save: Some(false),
+        default_project_path: Some("/saved/path".to_string()),
+        ..Default::default()
     };
-    save_settings(settings).await.unwrap();
+    store_settings(&pool, settings).await.unwrap();
 
     // Load settings
     let result = load_settings().await;
     assert!(result.is_ok());
 
     let loaded = result.unwrap();
-    assert_eq!(loaded.anthropic_api_key, Some("saved-key".to_string()));
+    assert_eq!(loaded.anthropic_api_key, Some("sk-ant-sav...1234".to_string()));
     assert_eq!(loaded.theme, "light");
     assert_eq!(loaded.auto_save, false);
     assert_eq!(loaded.default_project_path, "/saved/path");
 
+    // Saving the masked key back keeps the real one
+    let settings = SettingsUpdate {
+        anthropic_api_key: loaded.anthropic_api_key,
+        ..Default::default()
+    };
+    store_settings(&pool, settings).await.unwrap();
+
+    let api_key = test_utils::get_setting_value(&pool, "anthropic_api_key").await;
+    assert_eq!(api_key, Some("sk-ant-saved-key-1234".to_string()));
+
     test_utils::cleanup_test_db(pool).await;
     std::env::remove_var("TEST_DATABASE_PATH");
 }
@@ -405,7 +465,7 @@ async fn test_save_project_special_characters() {
         project_id: None,
         name: "Project with 'quotes' & <html> and émojis 🚀".to_string(),
         project_type: "web-app".to_string(),
-        active_agents: "[]".to_string(),
+        active_agents: None,
         messages: vec![],
         current_code: None,
     };
@@ -438,7 +498,7 @@ async fn test_save_project_transaction_integrity() {
         project_id: None,
         name: "Transaction Test".to_string(),
         project_type: "web-app".to_string(),
-        active_agents: "[]".to_string(),
+        active_agents: None,
         messages: vec![],
         current_code: None,
     };
@@ -465,12 +525,15 @@ async fn test_save_project_large_messages() {
         project_id: None,
         name: "Large Message Test".to_string(),
         project_type: "web-app".to_string(),
-        active_agents: "[]".to_string(),
+        active_agents: None,
         messages: vec![
             Message {
                 id: "msg-large".to_string(),
                 role: "user".to_string(),
                 content: large_content.clone(),
+                agent_id: None,
+                parent_id: None,
+                branch_status: None,
             },
         ],
         current_code: None,
@@ -29,7 +29,7 @@ pub fn mock_save_project_request(
         "project_id": project_id,
         "name": name,
         "project_type": "web-app",
-        "active_agents": "[]",
+        "active_agents": [],
         "messages": [
             {
                 "id": format!("msg-{}", uuid::Uuid::new_v4()),
@@ -94,8 +94,8 @@ pub async fn insert_test_project(
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
         r#"
-        INSERT INTO projects (id, name, project_type, active_agents, user_id, created_at, updated_at)
-        VALUES (?, ?, 'test-type', '[]', 'local-user', CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        INSERT INTO projects (id, name, project_type, user_id, created_at, updated_at)
+        VALUES (?, ?, 'test-type', 'local-user', CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
         "#,
     )
     .bind(id)
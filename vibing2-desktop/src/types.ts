@@ -3,6 +3,7 @@
  */
 
 export interface ClaudeCredentials {
+  /** Masked; call `reveal_api_key` for the full key */
   api_key: string;
   email?: string;
   subscription_tier?: string;